@@ -0,0 +1,186 @@
+//! Persistent cache for Web API responses, so lookups of items that rarely change don't need the
+//! network after a restart.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{debug, error};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// The default maximum size of the cache in megabytes.
+pub const DEFAULT_SIZE_MB: u32 = 50;
+
+/// File extension of the cached responses.
+const EXTENSION: &str = "json";
+
+/// A response as stored on disk.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse<T> {
+    /// Seconds since the Unix epoch.
+    stored: u64,
+    value: T,
+}
+
+/// Stores responses as files in a directory, keyed by endpoint and parameters. Every lookup
+/// specifies how old a response may be. When the cache grows over its maximum size, the least
+/// recently stored responses are removed.
+pub struct ApiCache {
+    directory: PathBuf,
+    max_size: u64,
+    /// The approximate size of all cached responses in bytes.
+    size: AtomicU64,
+}
+
+impl ApiCache {
+    pub fn new(directory: PathBuf, max_size: u64) -> Self {
+        if let Err(e) = fs::create_dir_all(&directory) {
+            error!("could not create API cache directory: {e}");
+        }
+        let cache = Self {
+            directory,
+            max_size,
+            size: AtomicU64::new(0),
+        };
+        cache.prune();
+        cache
+    }
+
+    /// Get the cached response of `endpoint` for `params` if it is younger than `ttl`.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: &str,
+        ttl: Duration,
+    ) -> Option<T> {
+        let contents = fs::read(self.path(endpoint, params)).ok()?;
+        let response: CachedResponse<T> = serde_json::from_slice(&contents)
+            .inspect_err(|e| debug!("ignoring unreadable cached {endpoint} response: {e}"))
+            .ok()?;
+        (now().saturating_sub(response.stored) < ttl.as_secs()).then(|| {
+            debug!("using cached {endpoint} response for {params}");
+            response.value
+        })
+    }
+
+    /// Store the response of `endpoint` for `params`.
+    pub fn put<T: Serialize>(&self, endpoint: &str, params: &str, value: &T) {
+        let response = CachedResponse {
+            stored: now(),
+            value,
+        };
+        let result = serde_json::to_vec(&response)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                // Without `write_atomically`, which would leave a lock file for every response.
+                let path = self.path(endpoint, params);
+                let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+                fs::write(&temporary, &contents)
+                    .and_then(|_| fs::rename(&temporary, &path))
+                    .map(|_| contents.len() as u64)
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(written) => {
+                if self.size.fetch_add(written, Ordering::Relaxed) + written > self.max_size {
+                    self.prune();
+                }
+            }
+            Err(e) => error!("could not cache {endpoint} response: {e}"),
+        }
+    }
+
+    /// Remove all cached responses. Returns the number of removed responses.
+    pub fn clear(&self) -> Result<usize, String> {
+        let entries = self.entries().map_err(|e| e.to_string())?;
+        for (path, _, _) in &entries {
+            fs::remove_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        self.size.store(0, Ordering::Relaxed);
+        Ok(entries.len())
+    }
+
+    /// Remove the oldest responses until the cache takes up at most 90% of its maximum size, so
+    /// pruning doesn't happen on every write.
+    fn prune(&self) {
+        let mut entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("could not list API cache: {e}");
+                return;
+            }
+        };
+        let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        if size > self.max_size {
+            let target = self.max_size / 10 * 9;
+            entries.sort_by_key(|(_, _, modified)| *modified);
+            for (path, len, _) in entries {
+                if size <= target {
+                    break;
+                }
+                if fs::remove_file(&path).is_ok() {
+                    size -= len;
+                }
+            }
+            debug!("pruned API cache to {size} bytes");
+        }
+        self.size.store(size, Ordering::Relaxed);
+    }
+
+    /// The cached responses with their size and modification time.
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        Ok(fs::read_dir(&self.directory)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == EXTENSION))
+            .filter_map(|path| {
+                let metadata = fs::metadata(&path).ok()?;
+                let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+                Some((path, metadata.len(), modified))
+            })
+            .collect())
+    }
+
+    fn path(&self, endpoint: &str, params: &str) -> PathBuf {
+        let params: String = params
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.directory
+            .join(format!("{endpoint}-{params}.{EXTENSION}"))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let directory =
+            std::env::temp_dir().join(format!("ncspot-api-cache-{}", std::process::id()));
+        let cache = ApiCache::new(directory.clone(), 1024 * 1024);
+        let hour = Duration::from_secs(3600);
+
+        assert_eq!(cache.get::<String>("album", "id", hour), None);
+        cache.put("album", "id", &"value".to_string());
+        assert_eq!(cache.get("album", "id", hour), Some("value".to_string()));
+        assert_eq!(cache.get::<String>("album", "id", Duration::ZERO), None);
+        assert_eq!(cache.get::<String>("artist", "id", hour), None);
+
+        assert_eq!(cache.clear(), Ok(1));
+        assert_eq!(cache.get::<String>("album", "id", hour), None);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -3,6 +3,7 @@ use std::path::Path;
 use std::rc::Rc;
 use std::sync::{Arc, OnceLock};
 
+use cursive::event::{EventResult, EventTrigger};
 use cursive::traits::Nameable;
 use cursive::{Cursive, CursiveRunner};
 use log::{error, info, trace};
@@ -13,20 +14,31 @@ use signal_hook::{consts::SIGHUP, consts::SIGTERM, iterator::Signals};
 use crate::client_config::ClientConfig;
 use crate::command::Command;
 use crate::commands::CommandManager;
-use crate::config::{Config, PlaybackState};
+use crate::config::{self, Config, ConfigWatcher, PlaybackState};
+use crate::discord::Discord;
+use crate::episode_progress::EpisodeProgressTracker;
 use crate::events::{Event, EventManager};
+use crate::hooks::{self, Hooks};
 use crate::library::Library;
+use crate::play_history::PlayHistory;
 use crate::queue::Queue;
+use crate::queue_autosave::QueueAutosave;
+use crate::queue_source::{QueueSource, StartupQueue};
+use crate::scrobbler::Scrobbler;
+use crate::session::{self, Session, StartupView};
+use crate::smart_playlist::SmartPlaylistSync;
 use crate::spotify::{PlayerEvent, Spotify};
-use crate::spotify_api::WebApi;
+use crate::spotify_url::SpotifyUrl;
+use crate::status::StatusPublisher;
+use crate::terminal_title::TerminalTitle;
+use crate::theme::ThemeSwitcher;
 use crate::ui::create_cursive;
-use crate::{authentication, ui, utils};
+use crate::{authentication, data_usage, http, instance, journal, ui, utils};
 use crate::{command, queue, spotify};
 
 #[cfg(feature = "mpris")]
 use crate::mpris::MprisManager;
 
-#[cfg(unix)]
 use crate::ipc::{self, IpcSocket};
 
 /// Set up the global logger to log to `filename`.
@@ -61,6 +73,18 @@ pub struct UserDataInner {
 /// The global Tokio runtime for running asynchronous tasks.
 pub static ASYNC_RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
 
+/// Create the global Tokio runtime. Must be called once before talking to Spotify.
+pub fn init_async_runtime() {
+    ASYNC_RUNTIME
+        .set(
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+}
+
 /// The representation of an ncspot application.
 pub struct Application {
     /// The music queue which controls playback order.
@@ -69,11 +93,34 @@ pub struct Application {
     spotify: Spotify,
     /// Internally shared
     event_manager: EventManager,
-    /// An IPC implementation using the D-Bus MPRIS protocol, used to control and inspect ncspot.
-    #[cfg(unix)]
-    ipc: Option<IpcSocket>,
+    /// The IPC socket, which is removed when the application is dropped.
+    _ipc: Option<IpcSocket>,
+    /// The playback status for the IPC socket and the HTTP server.
+    publisher: StatusPublisher,
     /// The object to render to the terminal.
     cursive: CursiveRunner<Cursive>,
+    /// Mirrors the currently playing item in the terminal title.
+    terminal_title: TerminalTitle,
+    /// Switches between the light and the dark theme.
+    theme_switcher: ThemeSwitcher,
+    /// Reloads the configuration when its file changes.
+    config_watcher: ConfigWatcher,
+    /// Runs the scripts configured for playback changes.
+    hooks: Hooks,
+    /// Submits the played tracks to Last.fm and ListenBrainz.
+    scrobbler: Scrobbler,
+    /// Records the played tracks locally.
+    play_history: PlayHistory,
+    /// Writes the smart playlists to Spotify when they are due.
+    smart_playlists: SmartPlaylistSync,
+    /// Saves the queue from time to time.
+    queue_autosave: QueueAutosave,
+    /// Remembers where podcast episodes were left.
+    episode_progress: EpisodeProgressTracker,
+    /// Shows the playing track in Discord.
+    discord: Discord,
+    /// Fills the queue from the configured source when the library is loaded.
+    startup_queue: StartupQueue,
 }
 
 impl Application {
@@ -82,27 +129,51 @@ impl Application {
     /// # Arguments
     ///
     /// * `configuration_file_path` - Relative path to the configuration file inside the base path
-    pub fn new(configuration_file_path: Option<String>) -> Result<Self, Box<dyn Error>> {
+    /// * `no_browser` - Log in without opening a browser or listening for the OAuth callback
+    /// * `url` - Spotify URL or URI of an item to open once the user interface is up
+    pub fn new(
+        configuration_file_path: Option<String>,
+        no_browser: bool,
+        url: Option<String>,
+    ) -> Result<Self, Box<dyn Error>> {
         // Things here may cause the process to abort; we must do them before creating curses
         // windows otherwise the error message will not be seen by a user
+        let url = url
+            .map(|url| {
+                SpotifyUrl::from_url(&url)
+                    .or_else(|| SpotifyUrl::from_uri(&url))
+                    .ok_or_else(|| format!("Not a Spotify URL or URI: {url}"))
+            })
+            .transpose()?;
 
-        ASYNC_RUNTIME
-            .set(
-                tokio::runtime::Builder::new_multi_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap(),
-            )
-            .unwrap();
+        init_async_runtime();
+        journal::init(config::cache_path(&instance::journal_file_name()));
 
         let configuration = Arc::new(Config::new(configuration_file_path));
+        data_usage::init(configuration.state().data_usage);
         let theme = configuration.build_theme();
 
         let mut client_config = ClientConfig::new();
+        if client_config.needs_setup()? {
+            ui::setup::run(&configuration)?;
+        }
         client_config.load_config()?;
+        if let Some(name) = &client_config.device_name {
+            instance::set_configured_name(name);
+        }
+
+        if let Some(instance) = instance::current()
+            && !instance.is_primary()
+        {
+            println!(
+                "Another ncspot instance is running, starting as \"{}\" with separate state",
+                instance.name()
+            );
+        }
 
         println!("Authenticating with Spotify...");
-        let auth_result = authentication::authenticate(&client_config, &configuration)?;
+        let auth_result = authentication::authenticate(&client_config, &configuration, no_browser)
+            .inspect_err(|e| hooks::auth_failure(&configuration.values(), e))?;
 
         println!("Connecting to Spotify..");
 
@@ -111,6 +182,12 @@ impl Application {
 
         cursive.set_theme(theme.clone());
 
+        // Mouse events are dropped before any view sees them while the mouse is disabled.
+        let mouse_cfg = configuration.clone();
+        cursive.set_on_pre_event_inner(EventTrigger::mouse(), move |_| {
+            (!mouse_cfg.values().mouse.unwrap_or(true)).then_some(EventResult::Consumed(None))
+        });
+
         #[cfg(all(unix, feature = "pancurses_backend"))]
         cursive.add_global_callback(cursive::event::Event::CtrlChar('z'), |_s| unsafe {
             libc::raise(libc::SIGTSTP);
@@ -124,7 +201,7 @@ impl Application {
             configuration.clone(),
         )?;
 
-        spotify.api = WebApi::with_authenticated_client(auth_result.web_api);
+        spotify.api.set_authenticated_client(auth_result.web_api);
 
         let library = Arc::new(Library::new(
             event_manager.clone(),
@@ -136,6 +213,7 @@ impl Application {
             spotify.clone(),
             configuration.clone(),
             library.clone(),
+            event_manager.clone(),
         ));
 
         #[cfg(feature = "mpris")]
@@ -152,8 +230,21 @@ impl Application {
         // Load the last played track into the player
         let playback_state = configuration.state().playback_state.clone();
         let queue_state = configuration.state().queuestate.clone();
+        let queue_source = configuration
+            .values()
+            .queue_source
+            .clone()
+            .unwrap_or_default();
+        let startup_queue = StartupQueue::new(
+            queue.clone(),
+            library.clone(),
+            queue_source.clone(),
+            playback_state == PlaybackState::Playing,
+        );
 
-        if let Some(playable) = queue.get_current() {
+        if queue_source == QueueSource::Resume
+            && let Some(playable) = queue.get_current()
+        {
             spotify.load(
                 &playable,
                 playback_state == PlaybackState::Playing,
@@ -170,13 +261,16 @@ impl Application {
             }
         }
 
+        let publisher = StatusPublisher::default();
+
         #[cfg(unix)]
         let ipc = if let Ok(runtime_directory) = utils::create_runtime_directory() {
             Some(
                 ipc::IpcSocket::new(
                     ASYNC_RUNTIME.get().unwrap().handle(),
-                    runtime_directory.join("ncspot.sock"),
+                    runtime_directory.join(ipc::SOCKET_NAME),
                     event_manager.clone(),
+                    publisher.subscribe(),
                 )
                 .map_err(|e| e.to_string())?,
             )
@@ -184,6 +278,25 @@ impl Application {
             error!("failed to create IPC socket: no suitable user runtime directory found");
             None
         };
+        #[cfg(windows)]
+        let ipc = Some(
+            ipc::IpcSocket::new(
+                ASYNC_RUNTIME.get().unwrap().handle(),
+                event_manager.clone(),
+                publisher.subscribe(),
+            )
+            .map_err(|e| e.to_string())?,
+        );
+
+        if let Err(e) = http::start(
+            ASYNC_RUNTIME.get().unwrap().handle(),
+            configuration.values().http.as_ref(),
+            event_manager.clone(),
+            queue.clone(),
+            publisher.subscribe(),
+        ) {
+            error!("failed to start the HTTP server: {e}");
+        }
 
         let mut cmd_manager = CommandManager::new(
             spotify.clone(),
@@ -201,7 +314,35 @@ impl Application {
         let search =
             ui::search::SearchView::new(event_manager.clone(), queue.clone(), library.clone());
 
-        let libraryview = ui::library::LibraryView::new(queue.clone(), library.clone());
+        // initial screen is library
+        let startup_view = configuration
+            .values()
+            .startup_view
+            .clone()
+            .or_else(|| configuration.values().initial_screen.clone())
+            .unwrap_or_else(|| "library".to_string())
+            .parse()
+            .unwrap_or_else(|e| {
+                error!("{e}");
+                StartupView::Screen("library".to_string())
+            });
+        let restore_session = configuration.values().restore_session.unwrap_or(false);
+        let session = if restore_session || startup_view == StartupView::Last {
+            Session::load()
+        } else {
+            Session::default()
+        };
+
+        let mut libraryview = ui::library::LibraryView::new(queue.clone(), library.clone());
+        let library_tab = match &startup_view {
+            StartupView::LibraryTab(tab) => Some(tab),
+            _ => session.library_tab.as_ref(),
+        };
+        if let Some(tab) = library_tab
+            && !libraryview.select_tab(tab)
+        {
+            error!("The library tab {tab:?} isn't shown");
+        }
 
         let queueview = ui::queue::QueueView::new(queue.clone(), library.clone());
 
@@ -219,31 +360,85 @@ impl Application {
         #[cfg(feature = "cover")]
         layout.add_screen("cover", coverview.with_name("cover"));
 
-        // initial screen is library
-        let initial_screen = configuration
-            .values()
-            .initial_screen
-            .clone()
-            .unwrap_or_else(|| "library".to_string());
-        if layout.has_screen(&initial_screen) {
-            layout.set_screen(initial_screen);
-        } else {
-            error!("Invalid screen name: {initial_screen}");
-            layout.set_screen("library");
+        match &startup_view {
+            StartupView::Screen(screen) if layout.has_screen(screen) => {
+                layout.set_screen(screen.clone())
+            }
+            StartupView::Screen(screen) => {
+                error!("Invalid screen name: {screen}");
+                layout.set_screen("library");
+            }
+            StartupView::Last => match &session.focus {
+                Some(screen) if layout.has_screen(screen) => layout.set_screen(screen.clone()),
+                _ => layout.set_screen("library"),
+            },
+            StartupView::LibraryTab(_) | StartupView::Item(_) => layout.set_screen("library"),
+        }
+        if let Some(screens) = configuration.values().split.clone()
+            && let Err(e) = layout.set_split(Some(screens))
+        {
+            error!("{e}");
+        }
+        if restore_session || startup_view == StartupView::Last {
+            layout.restore_session(&session, restore_session, |url| {
+                session::open(&SpotifyUrl::from_url(url)?, &queue, &library)
+            });
+        }
+        if let StartupView::Item(url) = &startup_view {
+            match session::open(url, &queue, &library) {
+                Some(view) => layout.push_view(view),
+                None => error!("Could not open {url}"),
+            }
         }
 
         cursive.add_fullscreen_layer(layout.with_name("main"));
 
+        if let Some(url) = url
+            && let Some(data) = cursive.user_data::<UserData>().cloned()
+        {
+            data.cmd.handle(&mut cursive, Command::OpenUrl(url));
+        }
+
+        ui::tour::show_once(&mut cursive, configuration.clone());
+
         Ok(Self {
+            queue_autosave: QueueAutosave::new(queue.clone(), configuration.clone()),
+            episode_progress: EpisodeProgressTracker::new(configuration.clone(), queue.clone()),
             queue,
             spotify,
             event_manager,
-            #[cfg(unix)]
-            ipc,
+            _ipc: ipc,
+            publisher,
             cursive,
+            theme_switcher: ThemeSwitcher::new(configuration.clone()),
+            config_watcher: ConfigWatcher::new(&configuration),
+            hooks: Hooks::new(configuration.clone(), library.clone()),
+            scrobbler: Scrobbler::new(configuration.clone()),
+            play_history: PlayHistory::new(configuration.clone()),
+            smart_playlists: SmartPlaylistSync::new(configuration.clone(), library.clone()),
+            discord: Discord::new(configuration.clone(), library.clone()),
+            startup_queue,
+            terminal_title: TerminalTitle::new(configuration, library),
         })
     }
 
+    /// Save the open screens and views to restore them on the next start.
+    fn save_session(&mut self) {
+        let Some(mut session) = self
+            .cursive
+            .call_on_name("main", |layout: &mut ui::layout::Layout| layout.session())
+        else {
+            return;
+        };
+        session.library_tab = self
+            .cursive
+            .call_on_name("library", |library: &mut ui::library::LibraryView| {
+                library.tab()
+            })
+            .flatten();
+        session.save();
+    }
+
     /// Start the application and run the event loop.
     pub fn run(&mut self) -> Result<(), String> {
         #[cfg(unix)]
@@ -253,6 +448,18 @@ impl Application {
         // cursive event loop
         while self.cursive.is_running() {
             self.cursive.step();
+            self.theme_switcher.update(&mut self.cursive);
+            self.scrobbler.tick();
+            self.smart_playlists.tick();
+            self.queue_autosave.tick();
+            self.episode_progress.tick();
+            self.queue.check_ab_loop();
+            if self.config_watcher.changed()
+                && let Some(data) = self.cursive.user_data::<UserData>().cloned()
+            {
+                info!("configuration file changed, reloading");
+                data.cmd.handle(&mut self.cursive, Command::ReloadConfig);
+            }
             #[cfg(unix)]
             for signal in signals.pending() {
                 if signal == SIGTERM || signal == SIGHUP {
@@ -266,20 +473,35 @@ impl Application {
                 match event {
                     Event::Player(state) => {
                         trace!("event received: {state:?}");
+                        journal::record(format_args!("player {state:?}"));
                         self.spotify.update_status(state.clone());
 
-                        #[cfg(unix)]
-                        if let Some(ref ipc) = self.ipc {
-                            ipc.publish(&state, self.queue.get_current());
+                        self.publisher.publish(&state, &self.queue);
+
+                        self.hooks.update(&state, self.queue.get_current().as_ref());
+                        self.scrobbler
+                            .update(&state, self.queue.get_current().as_ref());
+                        self.play_history
+                            .update(&state, self.queue.get_current().as_ref());
+                        self.episode_progress
+                            .update(&state, self.queue.get_current().as_ref());
+                        self.discord
+                            .update(&state, self.queue.get_current().as_ref());
+                        if state == PlayerEvent::FinishedTrack
+                            && !self.queue.next(false)
+                            && !self.queue.autoplay()
+                        {
+                            self.hooks.queue_empty();
                         }
 
-                        if state == PlayerEvent::FinishedTrack {
-                            self.queue.next(false);
-                        }
+                        self.terminal_title
+                            .update(&state, self.queue.get_current().as_ref());
                     }
                     Event::Queue(event) => {
                         self.queue.handle_event(event);
                     }
+                    Event::QueueChanged => self.publisher.publish_queue(&self.queue),
+                    Event::LibraryLoaded => self.startup_queue.library_loaded(),
                     Event::SessionDied => {
                         if self.spotify.start_worker(None).is_err() {
                             let data: UserData = self
@@ -304,6 +526,10 @@ impl Application {
                 }
             }
         }
+        self.save_session();
+        self.play_history.finish();
+        self.episode_progress.finish();
+        self.terminal_title.clear();
         Ok(())
     }
 }
@@ -0,0 +1,111 @@
+//! Listing the audio output devices of the audio backends, so one can be picked while ncspot runs.
+//! The backends don't expose their devices, so the tools of the sound systems are asked instead.
+
+use std::process::Command;
+
+/// An audio output device, as it is passed to the backend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// The output devices of the audio backend `backend`.
+pub fn list(backend: &str) -> Result<Vec<Device>, String> {
+    match backend {
+        "pulseaudio" => run("pactl", &["list", "short", "sinks"]).map(|out| parse_pactl(&out)),
+        "alsa" => run("aplay", &["-L"]).map(|out| parse_aplay(&out)),
+        backend => Err(format!(
+            "The devices of the {backend} backend can't be listed, use `device <name>` instead"
+        )),
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Could not run {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("{program} failed: {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse the output of `pactl list short sinks`, which has tab separated lines with the index,
+/// name, driver, sample format and state of every sink.
+fn parse_pactl(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .map(|name| Device {
+            name: name.to_string(),
+            description: None,
+        })
+        .collect()
+}
+
+/// Parse the output of `aplay -L`, which lists the device names with their description on the
+/// following, indented lines.
+fn parse_aplay(output: &str) -> Vec<Device> {
+    let mut devices: Vec<Device> = Vec::new();
+    for line in output.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some(device) = devices.last_mut() {
+                let description = device.description.get_or_insert_with(String::new);
+                if !description.is_empty() {
+                    description.push_str(", ");
+                }
+                description.push_str(line.trim());
+            }
+        } else if !line.is_empty() {
+            devices.push(Device {
+                name: line.to_string(),
+                description: None,
+            });
+        }
+    }
+    // The null device discards everything.
+    devices.retain(|device| device.name != "null");
+    devices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let pactl = "47\talsa_output.pci-0000_00_1f.3.analog-stereo\tPipeWire\ts32le 2ch 48000Hz\tRUNNING\n\
+                     52\tbluez_output.AC_80_0A.1\tPipeWire\ts16le 2ch 48000Hz\tSUSPENDED\n";
+        let names: Vec<String> = parse_pactl(pactl).into_iter().map(|d| d.name).collect();
+        assert_eq!(
+            names,
+            [
+                "alsa_output.pci-0000_00_1f.3.analog-stereo",
+                "bluez_output.AC_80_0A.1"
+            ]
+        );
+
+        let aplay = "null\n    Discard all samples (playback) or generate zero samples (capture)\n\
+                     default\n    Default ALSA Output (currently PipeWire Media Server)\n\
+                     hw:CARD=PCH,DEV=0\n    HDA Intel PCH, ALC257 Analog\n    Direct hardware device\n";
+        assert_eq!(
+            parse_aplay(aplay),
+            [
+                Device {
+                    name: "default".into(),
+                    description: Some(
+                        "Default ALSA Output (currently PipeWire Media Server)".into()
+                    ),
+                },
+                Device {
+                    name: "hw:CARD=PCH,DEV=0".into(),
+                    description: Some(
+                        "HDA Intel PCH, ALC257 Analog, Direct hardware device".into()
+                    ),
+                },
+            ]
+        );
+    }
+}
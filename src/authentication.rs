@@ -1,5 +1,8 @@
 use std::fs;
 use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use librespot_core::authentication::Credentials as LibrespotCredentials;
 use librespot_core::cache::Cache;
@@ -10,13 +13,28 @@ use rspotify::{AuthCodeSpotify, Config as RspotifyConfig, Credentials, OAuth, To
 
 use crate::client_config::ClientConfig;
 use crate::config::{self, Config};
-use crate::redirect_uri::redirect_uri_web_server;
+use crate::proxy;
+use crate::qr::QrCode;
+use crate::redirect_uri::{self, CallbackError, CallbackServer};
+use crate::serialization::{warn_if_not_private, write_private};
 use crate::spotify::Spotify;
+use crate::token_encryption;
 
 pub const SPOTIFY_CLIENT_ID: &str = "65b708073fc0480ea92a077233ca87bd";
 
 const TOKEN_CACHE_FILE: &str = ".spotify_token_cache.json";
 
+const NO_CACHED_LOGIN: &str = "No valid cached login found, start ncspot once to log in";
+
+/// Where users remove the access of ncspot to their account.
+pub const MANAGE_APPS_URL: &str = "https://www.spotify.com/account/apps/";
+
+/// Whether the user logged out during this run.
+static LOGGED_OUT: AtomicBool = AtomicBool::new(false);
+
+/// The location of the token cache, if it was moved in the client configuration.
+static TOKEN_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
 pub static OAUTH_SCOPES: &[&str] = &[
     "playlist-read-collaborative",
     "playlist-read-private",
@@ -36,13 +54,46 @@ pub static OAUTH_SCOPES: &[&str] = &[
     "streaming",
 ];
 
+/// How the user logs in when there is no valid cached login.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Login {
+    /// Fail instead of asking the user.
+    Never,
+    /// Open the login page in the browser and wait for the redirect on the callback server.
+    Browser,
+    /// Show the login page as a URL and a QR code, and ask for the URL it redirected to. Nothing
+    /// is opened and no port is bound, for machines without a browser.
+    Headless,
+}
+
 pub struct AuthResult {
     pub librespot_credentials: LibrespotCredentials,
     pub web_api: AuthCodeSpotify,
 }
 
-fn get_token_cache_path() -> std::path::PathBuf {
-    config::config_path(TOKEN_CACHE_FILE)
+fn get_token_cache_path() -> PathBuf {
+    TOKEN_FILE
+        .get()
+        .cloned()
+        .flatten()
+        .unwrap_or_else(|| config::config_path(TOKEN_CACHE_FILE))
+}
+
+fn get_librespot_credentials_path() -> PathBuf {
+    config::cache_path("librespot").join("credentials.json")
+}
+
+/// Apply the settings of `client_config` for the cached credentials. Has to be called before
+/// anything else in this module.
+pub fn init(client_config: &ClientConfig) {
+    TOKEN_FILE.set(client_config.get_token_file()).ok();
+    token_encryption::init(client_config.token_encryption);
+
+    if let Ok(paths) = client_config.get_or_build_paths() {
+        warn_if_not_private(&paths.config_file_path);
+    }
+    warn_if_not_private(&get_token_cache_path());
+    warn_if_not_private(&get_librespot_credentials_path());
 }
 
 fn save_token_to_file(spotify: &AuthCodeSpotify) -> Result<(), String> {
@@ -51,7 +102,11 @@ fn save_token_to_file(spotify: &AuthCodeSpotify) -> Result<(), String> {
         let token_json =
             serde_json::to_string_pretty(token).map_err(|e| format!("Serialize error: {}", e))?;
         let path = get_token_cache_path();
-        fs::write(&path, token_json).map_err(|e| format!("Write error: {}", e))?;
+        let contents = token_encryption::seal(token_json.as_bytes())?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|e| format!("Write error: {}", e))?;
+        }
+        write_private(&path, &contents).map_err(|e| format!("Write error: {}", e))?;
         debug!("Token saved to {:?}", path);
     }
     Ok(())
@@ -63,14 +118,20 @@ fn load_token_from_file(spotify: &AuthCodeSpotify) -> Result<bool, String> {
         return Ok(false);
     }
 
-    let token_json = fs::read_to_string(&path).map_err(|e| format!("Read error: {}", e))?;
+    let contents = fs::read(&path).map_err(|e| format!("Read error: {}", e))?;
+    let token_json = token_encryption::open(&contents)?;
     let token: Token =
-        serde_json::from_str(&token_json).map_err(|e| format!("Parse error: {}", e))?;
+        serde_json::from_slice(&token_json).map_err(|e| format!("Parse error: {}", e))?;
 
     let mut token_lock = spotify.token.lock().ok().ok_or("Failed to lock token")?;
     *token_lock = Some(token);
     drop(token_lock);
 
+    // Encrypt or decrypt the cache when the encryption was changed in the configuration.
+    if token_encryption::needs_update(&contents) {
+        save_token_to_file(spotify)?;
+    }
+
     info!("Loaded cached authentication token");
     Ok(true)
 }
@@ -92,7 +153,28 @@ fn create_rspotify_client(client_config: &ClientConfig) -> AuthCodeSpotify {
     AuthCodeSpotify::with_config(creds, oauth, config)
 }
 
-fn perform_oauth_flow(spotify: &mut AuthCodeSpotify, port: u16) -> Result<(), String> {
+fn perform_oauth_flow(
+    spotify: &mut AuthCodeSpotify,
+    client_config: &ClientConfig,
+) -> Result<(), String> {
+    let server = match CallbackServer::bind(client_config) {
+        Ok(server) => server,
+        Err(e) => {
+            println!("Web server failed: {}. Falling back to manual input.", e);
+            return manual_auth_flow(spotify);
+        }
+    };
+    if server.redirect_uri() != client_config.get_redirect_uri() {
+        println!(
+            "Port {} is in use, using {} instead. It has to be registered as a redirect URI in \
+             the Spotify Dashboard.",
+            client_config.get_port(),
+            server.redirect_uri()
+        );
+    }
+    // The redirect URI is part of the authorization URL and has to match the server.
+    spotify.oauth.redirect_uri = server.redirect_uri().to_string();
+
     let auth_url = spotify
         .get_authorize_url(false)
         .map_err(|e| format!("Failed to get auth URL: {}", e))?;
@@ -106,31 +188,68 @@ fn perform_oauth_flow(spotify: &mut AuthCodeSpotify, port: u16) -> Result<(), St
     }
 
     println!(
-        "Waiting for authorization callback on http://127.0.0.1:{}...\n",
-        port
+        "Waiting for authorization callback on {}...\n",
+        server.redirect_uri()
     );
 
-    match redirect_uri_web_server(port) {
-        Ok(callback_url) => {
-            if let Some(code) = spotify.parse_response_code(&callback_url) {
-                spotify
-                    .request_token(&code)
-                    .map_err(|e| format!("Token request failed: {}", e))?;
-
-                save_token_to_file(spotify)?;
-                println!("Successfully authenticated with Spotify!");
-                Ok(())
-            } else {
-                Err("Failed to parse authorization code from callback URL".to_string())
+    let timeout = client_config.get_callback_timeout();
+    loop {
+        match server.wait(timeout) {
+            Ok(callback_url) => {
+                return if let Some(code) = spotify.parse_response_code(&callback_url) {
+                    spotify
+                        .request_token(&code)
+                        .map_err(|e| format!("Token request failed: {}", e))?;
+
+                    save_token_to_file(spotify)?;
+                    println!("Successfully authenticated with Spotify!");
+                    Ok(())
+                } else {
+                    Err("Failed to parse authorization code from callback URL".to_string())
+                };
+            }
+            Err(CallbackError::Timeout) => {
+                print!(
+                    "No authorization callback received within {} seconds. Keep waiting, enter \
+                     the URL manually or quit? [W/m/q] ",
+                    timeout.as_secs()
+                );
+                io::stdout().flush().ok();
+                let mut input = String::new();
+                io::stdin()
+                    .read_line(&mut input)
+                    .map_err(|e| format!("Failed to read input: {}", e))?;
+                match input.trim().to_lowercase().as_str() {
+                    "q" => return Err(CallbackError::Timeout.to_string()),
+                    "m" => return manual_auth_flow(spotify),
+                    _ => continue,
+                }
+            }
+            Err(CallbackError::Cancelled) => return Err(CallbackError::Cancelled.to_string()),
+            Err(CallbackError::Failed(e)) => {
+                println!("Web server failed: {}. Falling back to manual input.", e);
+                return manual_auth_flow(spotify);
             }
         }
-        Err(e) => {
-            println!("Web server failed: {}. Falling back to manual input.", e);
-            manual_auth_flow(spotify)
-        }
     }
 }
 
+fn headless_auth_flow(spotify: &mut AuthCodeSpotify) -> Result<(), String> {
+    let auth_url = spotify
+        .get_authorize_url(false)
+        .map_err(|e| format!("Failed to get auth URL: {}", e))?;
+
+    if let Some(qr) = QrCode::encode(auth_url.as_bytes()) {
+        println!("\nScan this code to log in on another device:\n");
+        println!("{}", qr.render());
+    }
+    println!(
+        "The page you are redirected to after logging in won't load, copy its URL from the \
+         address bar."
+    );
+    manual_auth_flow(spotify)
+}
+
 fn manual_auth_flow(spotify: &mut AuthCodeSpotify) -> Result<(), String> {
     let auth_url = spotify
         .get_authorize_url(false)
@@ -158,10 +277,58 @@ fn manual_auth_flow(spotify: &mut AuthCodeSpotify) -> Result<(), String> {
     }
 }
 
+/// Remove the cached Web API token and librespot credentials, so the next start asks to log in.
+///
+/// Spotify has no endpoint to revoke tokens, so the refresh token stays valid until the user
+/// removes ncspot at [`MANAGE_APPS_URL`]. Deleting it here at least makes it unusable from this
+/// machine.
+pub fn logout() -> Result<(), String> {
+    for path in [get_token_cache_path(), get_librespot_credentials_path()] {
+        match fs::remove_file(&path) {
+            Ok(()) => info!("Removed {:?}", path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to remove {}: {}", path.display(), e)),
+        }
+    }
+    LOGGED_OUT.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Whether [`logout`] was called during this run.
+pub fn logged_out() -> bool {
+    LOGGED_OUT.load(Ordering::Relaxed)
+}
+
+/// Authenticate with Spotify, running the OAuth flow in the browser if no valid cached login
+/// exists. With `no_browser` the login URL is printed instead, and the user pastes the URL they
+/// were redirected to.
 pub fn authenticate(
     client_config: &ClientConfig,
     app_config: &Config,
+    no_browser: bool,
 ) -> Result<AuthResult, String> {
+    let login = if no_browser {
+        Login::Headless
+    } else {
+        Login::Browser
+    };
+    authenticate_with(client_config, app_config, login)
+}
+
+/// Authenticate with Spotify using only the cached login, without ever prompting the user.
+pub fn authenticate_cached(
+    client_config: &ClientConfig,
+    app_config: &Config,
+) -> Result<AuthResult, String> {
+    authenticate_with(client_config, app_config, Login::Never)
+}
+
+fn authenticate_with(
+    client_config: &ClientConfig,
+    app_config: &Config,
+    login: Login,
+) -> Result<AuthResult, String> {
+    init(client_config);
     let mut spotify = create_rspotify_client(client_config);
 
     let needs_auth = match load_token_from_file(&spotify) {
@@ -201,10 +368,15 @@ pub fn authenticate(
     };
 
     if needs_auth {
-        perform_oauth_flow(&mut spotify, client_config.get_port())?;
+        match login {
+            Login::Never => return Err(NO_CACHED_LOGIN.to_string()),
+            Login::Browser => perform_oauth_flow(&mut spotify, client_config)?,
+            Login::Headless => headless_auth_flow(&mut spotify)?,
+        }
     }
 
-    let librespot_credentials = get_librespot_credentials(client_config, app_config)?;
+    let librespot_credentials =
+        get_librespot_credentials(client_config, app_config, login, &spotify)?;
 
     Ok(AuthResult {
         librespot_credentials,
@@ -215,7 +387,13 @@ pub fn authenticate(
 fn get_librespot_credentials(
     client_config: &ClientConfig,
     configuration: &Config,
+    login: Login,
+    web_api: &AuthCodeSpotify,
 ) -> Result<LibrespotCredentials, String> {
+    // Without a proxy librespot can use, the cached credentials would look invalid and the login
+    // would start over.
+    proxy::session_proxy()?;
+
     let cache = Cache::new(Some(config::cache_path("librespot")), None, None, None)
         .expect("Could not create librespot cache");
 
@@ -227,14 +405,36 @@ fn get_librespot_credentials(
         info!("Cached librespot credentials invalid, getting new ones");
     }
 
-    info!("Getting librespot credentials via OAuth");
-    create_librespot_credentials(client_config)
+    match login {
+        Login::Never => Err(NO_CACHED_LOGIN.to_string()),
+        Login::Browser => {
+            info!("Getting librespot credentials via OAuth");
+            create_librespot_credentials(client_config)
+        }
+        Login::Headless => {
+            // The Web API token has the streaming scope, so librespot can log in with it without a
+            // second OAuth flow, which would need the callback server or another pasted URL.
+            info!("Getting librespot credentials from the Web API token");
+            let token = web_api.token.lock().ok().ok_or("Failed to lock token")?;
+            token
+                .as_ref()
+                .map(|token| LibrespotCredentials::with_access_token(&token.access_token))
+                .ok_or_else(|| NO_CACHED_LOGIN.to_string())
+        }
+    }
 }
 
 fn create_librespot_credentials(
     client_config: &ClientConfig,
 ) -> Result<LibrespotCredentials, String> {
-    let redirect_uri = client_config.get_redirect_uri();
+    let redirect_uri = redirect_uri::free_redirect_uri(client_config);
+    if redirect_uri != client_config.get_redirect_uri() {
+        println!(
+            "Port {} is in use, using {} instead for the playback login.",
+            client_config.get_port(),
+            redirect_uri
+        );
+    }
 
     let client_builder = OAuthClientBuilder::new(
         &client_config.client_id,
@@ -0,0 +1,455 @@
+//! Snapshots of the library of the user in a JSON file, and restoring them to the same or another
+//! account, in case items are removed by accident.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rspotify::model::Page;
+use rspotify::prelude::Id;
+use serde::de::DeserializeOwned;
+
+use crate::serialization::write_atomically;
+use crate::spotify_api::SpotifyApi;
+use crate::traits::ListItem;
+use crate::ui::pagination::ApiResult;
+
+/// The version of the backup format, increased when it changes incompatibly.
+const VERSION: u32 = 1;
+const FILE_PREFIX: &str = "ncspot-backup-";
+
+/// The most items the Web API accepts in one request, by endpoint.
+const TRACKS_PER_REQUEST: usize = 50;
+const ALBUMS_PER_REQUEST: usize = 20;
+const ARTISTS_PER_REQUEST: usize = 50;
+const SHOWS_PER_REQUEST: usize = 50;
+const PLAYLIST_ITEMS_PER_REQUEST: usize = 100;
+
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    version: u32,
+    created_at: DateTime<Utc>,
+    user: Option<String>,
+    /// The saved tracks, the most recently saved first.
+    tracks: Vec<Item>,
+    albums: Vec<Item>,
+    artists: Vec<Item>,
+    shows: Vec<Item>,
+    playlists: Vec<PlaylistBackup>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Item {
+    uri: String,
+    /// Only for reading the backup, it isn't used when restoring.
+    name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlaylistBackup {
+    uri: String,
+    name: String,
+    /// Playlists of the user are created again, those of others are followed.
+    owned: bool,
+    tracks: Vec<Item>,
+}
+
+/// Save the library of the logged in user to a new file in `directory` and return its path.
+pub fn backup(api: &dyn SpotifyApi, directory: &Path) -> Result<PathBuf, String> {
+    let failed = |what: &str| format!("Could not fetch the {what}");
+
+    println!("Fetching saved tracks");
+    let tracks = all_pages(
+        |offset| api.current_user_saved_tracks(offset),
+        |saved| {
+            let track = &saved.track;
+            let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+            Some(Item {
+                uri: track.id.as_ref()?.uri(),
+                name: format!("{} - {}", artists.join(", "), track.name),
+            })
+        },
+    )
+    .map_err(|_| failed("saved tracks"))?;
+
+    println!("Fetching saved albums");
+    let albums = all_pages(
+        |offset| api.current_user_saved_albums(offset),
+        |saved| {
+            Some(Item {
+                uri: saved.album.id.uri(),
+                name: saved.album.name.clone(),
+            })
+        },
+    )
+    .map_err(|_| failed("saved albums"))?;
+
+    println!("Fetching followed artists");
+    let artists = followed_artists(api)
+        .map_err(|_| failed("followed artists"))?
+        .into_iter()
+        .map(|(uri, name)| Item { uri, name })
+        .collect();
+
+    println!("Fetching saved shows");
+    let shows = all_pages(
+        |offset| api.get_saved_shows(offset),
+        |saved| {
+            Some(Item {
+                uri: saved.show.id.uri(),
+                name: saved.show.name.clone(),
+            })
+        },
+    )
+    .map_err(|_| failed("saved shows"))?;
+
+    let user = api.current_user().ok().map(|user| user.id.id().to_string());
+    let mut playlists = Vec::new();
+    for playlist in load_all(api.current_user_playlist()) {
+        println!("Fetching playlist {}", playlist.name);
+        let tracks = load_all(api.user_playlist_tracks(&playlist.id))
+            .iter()
+            .map(|playable| Item {
+                uri: playable.uri(),
+                name: playable.to_string(),
+            })
+            .collect();
+        playlists.push(PlaylistBackup {
+            uri: format!("spotify:playlist:{}", playlist.id),
+            owned: Some(&playlist.owner_id) == user.as_ref(),
+            name: playlist.name,
+            tracks,
+        });
+    }
+
+    let backup = Backup {
+        version: VERSION,
+        created_at: Utc::now(),
+        user,
+        tracks,
+        albums,
+        artists,
+        shows,
+        playlists,
+    };
+    fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    let path = directory.join(format!(
+        "{FILE_PREFIX}{}.json",
+        backup.created_at.format("%Y%m%d-%H%M%S")
+    ));
+    let contents = serde_json::to_vec_pretty(&backup).map_err(|e| e.to_string())?;
+    write_atomically(&path, &contents).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Add the items of the backup at `path`, or of the newest backup in the directory at `path`, that
+/// are missing from the library of the logged in user. Nothing is removed from the library.
+pub fn restore(api: &dyn SpotifyApi, path: &Path) -> Result<(), String> {
+    let path = if path.is_dir() {
+        newest_backup(path)?
+    } else {
+        path.to_path_buf()
+    };
+    println!("Restoring {}", path.display());
+    let contents = fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let backup: Backup =
+        serde_json::from_slice(&contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    if backup.version > VERSION {
+        return Err(format!(
+            "The backup has version {}, this version of ncspot only reads version {VERSION}",
+            backup.version
+        ));
+    }
+
+    let failed = |what: &str| format!("Could not fetch the current {what}");
+
+    let saved = all_pages(
+        |offset| api.current_user_saved_tracks(offset),
+        |saved| Some(saved.track.id.as_ref()?.uri()),
+    )
+    .map_err(|_| failed("saved tracks"))?;
+    add_missing(
+        "saved tracks",
+        &backup.tracks,
+        &saved,
+        TRACKS_PER_REQUEST,
+        |ids| api.current_user_saved_tracks_add(ids),
+    )?;
+
+    let saved = all_pages(
+        |offset| api.current_user_saved_albums(offset),
+        |saved| Some(saved.album.id.uri()),
+    )
+    .map_err(|_| failed("saved albums"))?;
+    add_missing(
+        "saved albums",
+        &backup.albums,
+        &saved,
+        ALBUMS_PER_REQUEST,
+        |ids| api.current_user_saved_albums_add(ids),
+    )?;
+
+    let followed: Vec<String> = followed_artists(api)
+        .map_err(|_| failed("followed artists"))?
+        .into_iter()
+        .map(|(uri, _)| uri)
+        .collect();
+    add_missing(
+        "followed artists",
+        &backup.artists,
+        &followed,
+        ARTISTS_PER_REQUEST,
+        |ids| api.user_follow_artists(ids),
+    )?;
+
+    let saved = all_pages(
+        |offset| api.get_saved_shows(offset),
+        |saved| Some(saved.show.id.uri()),
+    )
+    .map_err(|_| failed("saved shows"))?;
+    add_missing(
+        "saved shows",
+        &backup.shows,
+        &saved,
+        SHOWS_PER_REQUEST,
+        |ids| api.save_shows(&ids),
+    )?;
+
+    let user = api.current_user().ok().map(|user| user.id.id().to_string());
+    let mut existing = HashSet::new();
+    let mut owned_names = HashSet::new();
+    for playlist in load_all(api.current_user_playlist()) {
+        if Some(&playlist.owner_id) == user.as_ref() {
+            owned_names.insert(playlist.name);
+        }
+        existing.insert(format!("spotify:playlist:{}", playlist.id));
+    }
+    for playlist in backup.playlists {
+        if is_restored(&playlist, &existing, &owned_names) {
+            continue;
+        }
+        if playlist.owned {
+            println!("Creating playlist {}", playlist.name);
+            let id = api
+                .create_playlist(&playlist.name, None, None)
+                .map_err(|_| format!("Could not create the playlist {}", playlist.name))?;
+            for chunk in playlist.tracks.chunks(PLAYLIST_ITEMS_PER_REQUEST) {
+                let uris: Vec<&str> = chunk.iter().map(|item| item.uri.as_str()).collect();
+                api.playlist_add_uris(&id, &uris)
+                    .map_err(|_| format!("Could not add tracks to {}", playlist.name))?;
+            }
+        } else {
+            println!("Following playlist {}", playlist.name);
+            api.user_playlist_follow_playlist(id(&playlist.uri))
+                .map_err(|_| format!("Could not follow the playlist {}", playlist.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `playlist` is in the library already. Playlists of the user get a new URI when they are
+/// created again, so they are also recognized by their name.
+fn is_restored(
+    playlist: &PlaylistBackup,
+    existing: &HashSet<String>,
+    owned_names: &HashSet<String>,
+) -> bool {
+    existing.contains(&playlist.uri) || (playlist.owned && owned_names.contains(&playlist.name))
+}
+
+/// The backup in `directory` with the newest timestamp in its name.
+fn newest_backup(directory: &Path) -> Result<PathBuf, String> {
+    fs::read_dir(directory)
+        .map_err(|e| format!("{}: {e}", directory.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(FILE_PREFIX))
+        })
+        .max()
+        .ok_or_else(|| format!("There is no backup in {}", directory.display()))
+}
+
+/// Add the `items` that aren't in `existing` with `add`, which takes up to `chunk_size` ids.
+/// The oldest items are added first, so the newest ones end up at the top of the library again.
+fn add_missing(
+    what: &str,
+    items: &[Item],
+    existing: &[String],
+    chunk_size: usize,
+    add: impl Fn(Vec<&str>) -> Result<(), ()>,
+) -> Result<(), String> {
+    let existing: HashSet<&String> = existing.iter().collect();
+    let missing: Vec<&str> = items
+        .iter()
+        .rev()
+        .filter(|item| !existing.contains(&item.uri))
+        .map(|item| id(&item.uri))
+        .collect();
+    for chunk in missing.chunks(chunk_size) {
+        add(chunk.to_vec()).map_err(|_| format!("Could not restore the {what}"))?;
+    }
+    println!("Restored {} {what}", missing.len());
+    Ok(())
+}
+
+/// The id at the end of the Spotify `uri`.
+fn id(uri: &str) -> &str {
+    uri.rsplit(':').next().unwrap_or(uri)
+}
+
+/// Map the items of all pages returned by `fetch`, which takes the offset of the page.
+fn all_pages<T: DeserializeOwned, U>(
+    fetch: impl Fn(u32) -> Result<Page<T>, ()>,
+    map: impl Fn(&T) -> Option<U>,
+) -> Result<Vec<U>, ()> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = fetch(offset)?;
+        // Items that are left out by `map` still count for the offset of the next page.
+        let count = page.items.len() as u32;
+        items.extend(page.items.iter().filter_map(&map));
+        if page.next.is_none() || count == 0 {
+            return Ok(items);
+        }
+        offset = page.offset + count;
+    }
+}
+
+/// The URIs and names of the followed artists.
+fn followed_artists(api: &dyn SpotifyApi) -> Result<Vec<(String, String)>, ()> {
+    let mut artists = Vec::new();
+    let mut last: Option<String> = None;
+    loop {
+        let page = api.current_user_followed_artists(last.as_deref())?;
+        artists.extend(
+            page.items
+                .iter()
+                .map(|artist| (artist.id.uri(), artist.name.clone())),
+        );
+        last = page.cursors.and_then(|cursors| cursors.after);
+        if last.is_none() || page.items.is_empty() {
+            return Ok(artists);
+        }
+    }
+}
+
+fn load_all<T: ListItem + Clone>(result: ApiResult<T>) -> Vec<T> {
+    while !result.at_end() {
+        result.next();
+    }
+    result.items.read().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(items: &[&str], offset: u32, total: u32) -> Page<String> {
+        let end = offset + items.len() as u32;
+        Page {
+            href: String::new(),
+            items: items.iter().map(|item| item.to_string()).collect(),
+            limit: items.len() as u32,
+            next: (end < total).then(String::new),
+            offset,
+            previous: None,
+            total,
+        }
+    }
+
+    #[test]
+    fn test_all_pages_skips_filtered_items() {
+        let pages = [
+            page(&["a", "local", "b"], 0, 6),
+            page(&["local", "local", "c"], 3, 6),
+        ];
+        let items = all_pages(
+            |offset| Ok(pages[offset as usize / 3].clone()),
+            |item| (item != "local").then(|| item.clone()),
+        );
+        assert_eq!(items, Ok(vec!["a".into(), "b".into(), "c".into()]));
+
+        let pages = [page(&["local", "local"], 0, 3), page(&["a"], 2, 3)];
+        let items = all_pages(
+            |offset| Ok(pages[offset as usize / 2].clone()),
+            |item| (item != "local").then(|| item.clone()),
+        );
+        assert_eq!(items, Ok(vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn test_is_restored() {
+        let playlist = |uri: &str, name: &str, owned| PlaylistBackup {
+            uri: uri.into(),
+            name: name.into(),
+            owned,
+            tracks: Vec::new(),
+        };
+        let existing = HashSet::from(["spotify:playlist:followed".to_string()]);
+        let owned_names = HashSet::from(["Mine".to_string()]);
+
+        assert!(is_restored(
+            &playlist("spotify:playlist:followed", "Theirs", false),
+            &existing,
+            &owned_names
+        ));
+        assert!(is_restored(
+            &playlist("spotify:playlist:deleted", "Mine", true),
+            &existing,
+            &owned_names
+        ));
+        assert!(!is_restored(
+            &playlist("spotify:playlist:other", "Mine", false),
+            &existing,
+            &owned_names
+        ));
+        assert!(!is_restored(
+            &playlist("spotify:playlist:deleted", "Gone", true),
+            &existing,
+            &owned_names
+        ));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_backup_and_restore() {
+        use crate::mock_api::MockApi;
+
+        let directory =
+            std::env::temp_dir().join(format!("ncspot-backup-test-{}", std::process::id()));
+        let path = backup(&MockApi::with_library(), &directory).unwrap();
+
+        // Restore to an empty library without the playlist.
+        let mut api = MockApi::with_fixtures();
+        api.playlists.clear();
+        restore(&api, &path).unwrap();
+        fs::remove_dir_all(directory).unwrap();
+
+        let lookups = [
+            "current_user",
+            "current_user_playlist",
+            "current_user_saved_tracks",
+            "current_user_saved_albums",
+            "current_user_followed_artists",
+            "get_saved_shows",
+        ];
+        let restored: Vec<String> = api
+            .requests()
+            .into_iter()
+            .filter(|request| !lookups.contains(&request.split(' ').next().unwrap()))
+            .collect();
+        assert_eq!(
+            restored,
+            vec![
+                // The oldest saved track is restored first.
+                "current_user_saved_tracks_add 6rqhFgbbKwnb9MLmUQDhG6,2takcwOaAZWiXQijPHIx7B,4uLU6hMCjMI75M1A2tKUQC",
+                "user_follow_artists 4M5nCE77Qaxayuhp3fVn4V,0OdUWJ0sBjDrqHygGUXeCF",
+                "create_playlist Fixtures",
+                "playlist_add_uris created spotify:track:4uLU6hMCjMI75M1A2tKUQC,spotify:track:2takcwOaAZWiXQijPHIx7B,spotify:track:6rqhFgbbKwnb9MLmUQDhG6",
+            ]
+        );
+    }
+}
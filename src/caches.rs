@@ -0,0 +1,146 @@
+//! The caches ncspot keeps on disk, how large they are and how they are limited.
+//!
+//! librespot limits the audio cache itself and the API cache prunes itself as it grows. Cover art
+//! is pruned when ncspot starts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use log::debug;
+use strum_macros::Display;
+
+use crate::config::{self, Config};
+use crate::utils::dir_size;
+
+/// The default maximum size of the audio cache in megabytes.
+pub const DEFAULT_AUDIO_SIZE_MB: u32 = 10240;
+
+/// The default maximum size of the cover art in megabytes.
+pub const DEFAULT_IMAGES_SIZE_MB: u32 = 100;
+
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    /// Audio files, kept by librespot.
+    Audio,
+    /// Cover art.
+    Images,
+    /// Web API responses.
+    Api,
+}
+
+impl CacheKind {
+    pub const ALL: [Self; 3] = [Self::Audio, Self::Images, Self::Api];
+
+    /// The directory the cache is kept in.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Self::Audio => config::cache_path("librespot").join("files"),
+            Self::Images => config::cache_path("covers"),
+            Self::Api => config::cache_path("api"),
+        }
+    }
+
+    /// The size of all cached files in bytes.
+    pub fn size(&self) -> u64 {
+        dir_size(&self.path())
+    }
+
+    /// The maximum size of the cache in bytes, if it is limited.
+    pub fn max_size(&self, cfg: &Config) -> Option<u64> {
+        let values = cfg.values();
+        let megabytes = match self {
+            Self::Audio => values.audio_cache_size.unwrap_or(DEFAULT_AUDIO_SIZE_MB),
+            Self::Images => values.cover_cache_size.unwrap_or(DEFAULT_IMAGES_SIZE_MB),
+            Self::Api => values
+                .api_cache_size
+                .unwrap_or(crate::api_cache::DEFAULT_SIZE_MB),
+        };
+        // A size of 0 means no limit.
+        (megabytes > 0).then_some(megabytes as u64 * 1048576)
+    }
+}
+
+/// All files in `directory` and its subdirectories, with their size and the time they were last
+/// used.
+fn files(directory: &Path) -> Vec<(PathBuf, u64, std::time::SystemTime)> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            files.extend(self::files(&entry.path()));
+        } else {
+            // Not every file system records access times.
+            let used = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(UNIX_EPOCH);
+            files.push((entry.path(), metadata.len(), used));
+        }
+    }
+    files
+}
+
+/// Remove the least recently used files in `directory` until it takes up at most `max_size`
+/// bytes. Returns how many bytes were removed.
+pub fn prune(directory: &Path, max_size: u64) -> u64 {
+    let mut files = files(directory);
+    let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+    let mut removed = 0;
+    files.sort_by_key(|(_, _, used)| *used);
+    for (path, len, _) in files {
+        if size <= max_size {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            size -= len;
+            removed += len;
+        }
+    }
+    debug!("pruned {} to {size} bytes", directory.display());
+    removed
+}
+
+/// Remove all files of the cache of `kind`. Returns how many bytes were removed.
+pub fn clear(kind: CacheKind) -> u64 {
+    prune(&kind.path(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn test_prune() {
+        let directory = std::env::temp_dir().join(format!("ncspot-caches-{}", std::process::id()));
+        fs::create_dir_all(directory.join("ab")).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old", 30), ("ab/middle", 20), ("new", 10)] {
+            let path = directory.join(name);
+            fs::write(&path, [0; 100]).unwrap();
+            let time = now - Duration::from_secs(age);
+            let times = fs::FileTimes::new().set_accessed(time).set_modified(time);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_times(times)
+                .unwrap();
+        }
+
+        assert_eq!(dir_size(&directory), 300);
+        assert_eq!(prune(&directory, 150), 200);
+        assert!(directory.join("new").exists());
+        assert_eq!(prune(&directory, 0), 100);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
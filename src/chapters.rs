@@ -0,0 +1,182 @@
+//! Chapters of long episodes and bookmarks, which are shown as markers on the progress bar and can
+//! be jumped between. Spotify doesn't expose chapters of podcast episodes, but many list them in
+//! their description as lines like `12:34 Topic`. Bookmarks are set by the user for any playable
+//! and stored locally.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use regex::Regex;
+
+use crate::model::playable::Playable;
+use crate::storage::{JsonStore, Storage};
+
+const KEY: &str = "bookmarks.json";
+
+/// Jumping to the previous chapter this far into a chapter goes back to its start instead.
+const RESTART_MARGIN: u32 = 3000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Chapter {
+    /// The start of the chapter in milliseconds.
+    pub position: u32,
+    pub title: String,
+}
+
+/// Timestamps like `1:02:03` or `12:34`, followed by the title of the chapter.
+static TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[\s(\[])(?:(\d{1,2}):)?(\d{1,2}):(\d{2})\b").unwrap());
+
+/// The chapters listed in the `description` of an item of `duration` milliseconds. A single
+/// timestamp is rather a mention than a list of chapters, so at least two are needed.
+pub fn parse_description(description: &str, duration: u32) -> Vec<Chapter> {
+    let matches: Vec<_> = TIMESTAMP.captures_iter(description).collect();
+    let mut chapters = Vec::new();
+    for (index, captures) in matches.iter().enumerate() {
+        let number = |group| {
+            captures
+                .get(group)
+                .and_then(|m| m.as_str().parse::<u32>().ok())
+        };
+        let seconds =
+            number(1).unwrap_or(0) * 3600 + number(2).unwrap_or(0) * 60 + number(3).unwrap_or(0);
+        // The title runs up to the end of the line or the next timestamp.
+        let end = matches
+            .get(index + 1)
+            .map_or(description.len(), |next| next.get(0).unwrap().start());
+        let rest = &description[captures.get(0).unwrap().end()..end];
+        let title = rest
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches(|c: char| c.is_whitespace() || "-–—:|)]".contains(c))
+            .trim_end();
+        chapters.push(Chapter {
+            position: seconds * 1000,
+            title: title.chars().take(60).collect(),
+        });
+    }
+    let ascending = chapters.windows(2).all(|w| w[0].position < w[1].position);
+    if chapters.len() < 2 || !ascending || chapters.iter().any(|c| c.position >= duration) {
+        return Vec::new();
+    }
+    chapters
+}
+
+/// The chapters parsed from the description of the last episode asked for, by uri, so the
+/// description isn't parsed on every redraw.
+static PARSED: Mutex<Option<(String, Vec<Chapter>)>> = Mutex::new(None);
+
+fn description_chapters(playable: &Playable) -> Vec<Chapter> {
+    let Playable::Episode(episode) = playable else {
+        return Vec::new();
+    };
+    let mut parsed = PARSED.lock().unwrap();
+    match &*parsed {
+        Some((uri, chapters)) if *uri == episode.uri => chapters.clone(),
+        _ => {
+            let chapters = parse_description(&episode.description, episode.duration);
+            *parsed = Some((episode.uri.clone(), chapters.clone()));
+            chapters
+        }
+    }
+}
+
+/// The bookmarks by uri.
+static BOOKMARKS: JsonStore<HashMap<String, Vec<Chapter>>> = JsonStore::new(KEY, "bookmarks");
+
+/// Add a bookmark at `position` in `playable`. Returns the title of the bookmark.
+pub fn add_bookmark(
+    storage: &dyn Storage,
+    playable: &Playable,
+    position: u32,
+    title: Option<String>,
+) -> String {
+    let mut added = String::new();
+    BOOKMARKS.update(storage, |bookmarks| {
+        let list = bookmarks.entry(playable.uri()).or_default();
+        added = title.unwrap_or_else(|| format!("Bookmark {}", list.len() + 1));
+        list.push(Chapter {
+            position,
+            title: added.clone(),
+        });
+        list.sort_by_key(|bookmark| bookmark.position);
+    });
+    added
+}
+
+/// Remove all bookmarks of `playable`. Returns how many there were.
+pub fn clear_bookmarks(storage: &dyn Storage, playable: &Playable) -> usize {
+    let mut removed = 0;
+    BOOKMARKS.update(storage, |bookmarks| {
+        removed = bookmarks
+            .remove(&playable.uri())
+            .map_or(0, |list| list.len());
+    });
+    removed
+}
+
+/// The chapters and bookmarks of `playable`, in the order they appear.
+pub fn markers(storage: &dyn Storage, playable: &Playable) -> Vec<Chapter> {
+    let mut markers = description_chapters(playable);
+    BOOKMARKS.read(storage, |bookmarks| {
+        markers.extend(
+            bookmarks
+                .get(&playable.uri())
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+    });
+    markers.sort_by_key(|marker| marker.position);
+    markers
+}
+
+/// The marker playback at `position` is in, if it is past the first one.
+pub fn current(markers: &[Chapter], position: u32) -> Option<&Chapter> {
+    markers
+        .iter()
+        .rev()
+        .find(|marker| marker.position <= position)
+}
+
+/// The marker after `position`.
+pub fn next(markers: &[Chapter], position: u32) -> Option<&Chapter> {
+    markers.iter().find(|marker| marker.position > position)
+}
+
+/// The start of the marker playback is in, or the one before when it only just started.
+pub fn previous(markers: &[Chapter], position: u32) -> Option<&Chapter> {
+    markers
+        .iter()
+        .rev()
+        .find(|marker| marker.position + RESTART_MARGIN <= position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        let description = "In this episode:\n00:00 Intro\n(04:10) The news – part 1\n\
+                           1:02:03 Listener questions";
+        let chapters = parse_description(description, 4_000_000);
+        let titles: Vec<&str> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Intro", "The news – part 1", "Listener questions"]);
+        assert_eq!(chapters[2].position, 3_723_000);
+
+        // Chapters on a single line, as descriptions often lose their line breaks.
+        let chapters = parse_description("0:00 Intro 5:30 Interview 20:00 Outro", 1_500_000);
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[1].title, "Interview");
+
+        assert!(parse_description("We meet at 10:30 every week", 4_000_000).is_empty());
+        assert!(parse_description("0:00 Intro 90:00 Outro", 1_500_000).is_empty());
+
+        assert_eq!(previous(&chapters, 331_000).unwrap().title, "Intro");
+        assert_eq!(previous(&chapters, 340_000).unwrap().title, "Interview");
+        assert_eq!(next(&chapters, 330_000).unwrap().title, "Outro");
+        assert_eq!(current(&chapters, 340_000).unwrap().title, "Interview");
+    }
+}
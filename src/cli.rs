@@ -1,4 +1,22 @@
-use crate::config::{user_cache_directory, user_configuration_directory};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use crate::application::init_async_runtime;
+use crate::authentication;
+use crate::backup;
+use crate::client_config::ClientConfig;
+use crate::config::{Config, user_cache_directory, user_configuration_directory};
+use crate::events::{Event, EventManager};
+use crate::model::album::Album;
+use crate::model::episode::Episode;
+use crate::model::playable::Playable;
+use crate::model::show::Show;
+use crate::model::track::Track;
+use crate::queue::QueueEvent;
+use crate::spotify::{PlayerEvent, Spotify, UriType, backend_names};
+use crate::spotify_url::SpotifyUrl;
+use crate::traits::ListItem;
 
 /// Print platform info like which platform directories will be used.
 pub fn info() -> Result<(), String> {
@@ -31,5 +49,307 @@ pub fn info() -> Result<(), String> {
         );
     }
 
+    println!("AUDIO_BACKENDS {}", backend_names().join(", "));
+
+    Ok(())
+}
+
+/// Remove the cached login, so the next start asks to log in again.
+pub fn logout() -> Result<(), String> {
+    // Only read the configuration for a moved token cache, don't start the setup without one.
+    let mut client_config = ClientConfig::new();
+    if client_config
+        .get_or_build_paths()?
+        .config_file_path
+        .exists()
+    {
+        client_config.load_config()?;
+    }
+    authentication::init(&client_config);
+    authentication::logout()?;
+    print_logout_hint();
     Ok(())
 }
+
+/// Tell the user how to revoke the access of ncspot, which can't be done from here.
+pub fn print_logout_hint() {
+    println!("Logged out. To revoke the access of ncspot to your Spotify account as well, remove");
+    println!("it at {}", authentication::MANAGE_APPS_URL);
+}
+
+/// Send `command` to the IPC socket of the running instance and wait until it was accepted.
+pub fn send(command: &str) -> Result<(), String> {
+    use std::io::{BufRead, BufReader, Write};
+
+    use crate::ipc::Response;
+
+    let mut stream = connect_ipc()?;
+    let request = serde_json::json!({ "command": command });
+    writeln!(stream, "{request}").map_err(|e| e.to_string())?;
+
+    // Status updates can arrive before the response, skip them.
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if let Ok(response) = serde_json::from_str::<Response>(&line)
+            && response.event == "response"
+        {
+            return response.error.map_or(Ok(()), Err);
+        }
+    }
+    Err("ncspot closed the connection without a response".into())
+}
+
+/// Print the status of the running instance with the placeholders of `format` filled in, or as a
+/// Waybar custom module if `json` is set. With `follow`, a line is printed for every change, and
+/// every second while playing.
+pub fn status(format: &str, follow: bool, json: bool) -> Result<(), String> {
+    use std::io::{BufRead, BufReader};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::time::{Duration, SystemTime};
+
+    use crate::spotify::PlayerEvent;
+    use crate::status::Status;
+
+    // Reads from named pipes can't time out, so the lines are read on their own thread.
+    let stream = connect_ipc()?;
+    let (tx, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            if tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut status: Option<Status> = None;
+    let mut printed = None;
+    loop {
+        match lines.recv_timeout(Duration::from_secs(1)) {
+            Ok(Ok(line)) => {
+                // Lines that aren't a status, like responses to other clients, are skipped.
+                let update = serde_json::from_str::<Status>(&line).ok();
+                if update.is_none() {
+                    continue;
+                }
+                status = update;
+            }
+            Ok(Err(e)) => return Err(e.to_string()),
+            Err(RecvTimeoutError::Timeout) => {
+                // Only the position changes while playing.
+                if !status
+                    .as_ref()
+                    .is_some_and(|s| matches!(s.mode, PlayerEvent::Playing(_)))
+                {
+                    continue;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err("ncspot closed the connection".into());
+            }
+        }
+
+        let Some(status) = &status else {
+            continue;
+        };
+        let now = SystemTime::now();
+        let text = if status.playable.is_some() {
+            status.format(format, now)
+        } else {
+            String::new()
+        };
+        let output = if json {
+            serde_json::json!({
+                "text": text,
+                "alt": status.state(),
+                "class": status.state(),
+                "tooltip": status.format("{title}\n{artist}\n{album}", now),
+            })
+            .to_string()
+        } else {
+            text
+        };
+        if printed.as_ref() != Some(&output) {
+            println!("{output}");
+            printed = Some(output);
+        }
+        if !follow {
+            return Ok(());
+        }
+    }
+}
+
+/// A connection to the IPC socket of a running instance, a named pipe on Windows.
+#[cfg(unix)]
+type IpcStream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type IpcStream = std::fs::File;
+
+/// Connect to the IPC socket of the first running instance.
+#[cfg(unix)]
+fn connect_ipc() -> Result<IpcStream, String> {
+    use crate::ipc;
+    use crate::utils::user_runtime_directory;
+
+    let path = user_runtime_directory()
+        .ok_or("no runtime directory found")?
+        .join(ipc::SOCKET_NAME);
+    std::os::unix::net::UnixStream::connect(&path)
+        .map_err(|e| format!("Could not connect to {}: {e}", path.display()))
+}
+
+/// Connect to the IPC named pipe of the first running instance.
+#[cfg(windows)]
+fn connect_ipc() -> Result<IpcStream, String> {
+    use crate::ipc;
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(ipc::PIPE_NAME)
+        .map_err(|e| format!("Could not connect to {}: {e}", ipc::PIPE_NAME))
+}
+
+/// Log in with the cached credentials, without the user interface.
+fn connect_spotify(
+    configuration_file_path: Option<String>,
+) -> Result<(Spotify, EventManager), String> {
+    init_async_runtime();
+
+    let configuration = Arc::new(Config::new(configuration_file_path));
+    let mut client_config = ClientConfig::new();
+    client_config.load_config().map_err(|e| e.to_string())?;
+    let auth_result = authentication::authenticate_cached(&client_config, &configuration)?;
+
+    let events = EventManager::headless();
+    let mut spotify = Spotify::new(
+        events.clone(),
+        auth_result.librespot_credentials,
+        configuration,
+    )
+    .map_err(|e| e.to_string())?;
+    spotify.api.set_authenticated_client(auth_result.web_api);
+    Ok((spotify, events))
+}
+
+/// Save the library to a new backup file in `directory`.
+pub fn backup(configuration_file_path: Option<String>, directory: &Path) -> Result<(), String> {
+    let (spotify, _events) = connect_spotify(configuration_file_path)?;
+    let path = backup::backup(&spotify.api, directory)?;
+    println!("Saved the library to {}", path.display());
+    Ok(())
+}
+
+/// Add the items missing from the library from the backup at `path`, a file or a directory.
+pub fn restore(configuration_file_path: Option<String>, path: &Path) -> Result<(), String> {
+    let (spotify, _events) = connect_spotify(configuration_file_path)?;
+    backup::restore(&spotify.api, path)
+}
+
+/// Play the Spotify item at `uri` without the user interface. Playback starts over when the end is
+/// reached, unless `exit_after` is set.
+pub fn play(
+    configuration_file_path: Option<String>,
+    uri: &str,
+    exit_after: bool,
+) -> Result<(), String> {
+    let url = SpotifyUrl::from_uri(uri)
+        .or_else(|| SpotifyUrl::from_url(uri))
+        .ok_or_else(|| format!("Not a valid Spotify URI or URL: {uri}"))?;
+
+    let (spotify, events) = connect_spotify(configuration_file_path)?;
+
+    let mut playables = resolve_playables(&spotify, &url);
+    playables.retain(|p| p.is_playable());
+    if playables.is_empty() {
+        return Err(format!("Nothing playable found for {uri}"));
+    }
+
+    let mut index = 0;
+    play_index(&spotify, &playables, index);
+
+    while let Some(event) = events.recv() {
+        match event {
+            Event::Player(state) => {
+                spotify.update_status(state.clone());
+                if state == PlayerEvent::FinishedTrack {
+                    index += 1;
+                    if index == playables.len() {
+                        if exit_after {
+                            spotify.stop();
+                            break;
+                        }
+                        index = 0;
+                    }
+                    play_index(&spotify, &playables, index);
+                }
+            }
+            Event::Queue(QueueEvent::PreloadTrackRequest) => {
+                if let Some(next) = playables.get(index + 1) {
+                    spotify.preload(next);
+                }
+            }
+            Event::SessionDied => spotify.start_worker(None).map_err(|e| e.to_string())?,
+            Event::Queue(_) | Event::IpcInput(_) | Event::QueueChanged | Event::LibraryLoaded => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn play_index(spotify: &Spotify, playables: &[Playable], index: usize) {
+    let playable = &playables[index];
+    println!("Playing {playable}");
+    spotify.load(playable, true, 0);
+    spotify.update_track();
+}
+
+/// Fetch all the items that should be played for `url`, in playback order.
+pub fn resolve_playables(spotify: &Spotify, url: &SpotifyUrl) -> Vec<Playable> {
+    let api = &spotify.api;
+    match url.uri_type {
+        UriType::Track => api
+            .track(&url.id)
+            .map(|t| vec![Playable::Track(Track::from(&t))])
+            .unwrap_or_default(),
+        UriType::Episode => api
+            .episode(&url.id)
+            .map(|e| vec![Playable::Episode(Episode::from(&e))])
+            .unwrap_or_default(),
+        UriType::Album => {
+            let Ok(album) = api.album(&url.id) else {
+                return Vec::new();
+            };
+            let mut album = Album::from(&album);
+            album.load_all_tracks(spotify.clone());
+            album
+                .tracks
+                .unwrap_or_default()
+                .into_iter()
+                .map(Playable::Track)
+                .collect()
+        }
+        UriType::Artist => api
+            .artist_top_tracks(&url.id)
+            .map(|tracks| tracks.into_iter().map(Playable::Track).collect())
+            .unwrap_or_default(),
+        UriType::Playlist => {
+            let tracks = api.user_playlist_tracks(&url.id);
+            while !tracks.at_end() {
+                tracks.next();
+            }
+            tracks.items.read().unwrap().clone()
+        }
+        UriType::Show => {
+            let Ok(show) = api.show(&url.id) else {
+                return Vec::new();
+            };
+            let mut show = Show::from(&show);
+            show.load_all_episodes(spotify.clone());
+            show.episodes
+                .unwrap_or_default()
+                .into_iter()
+                .map(Playable::Episode)
+                .collect()
+        }
+    }
+}
@@ -1,21 +1,80 @@
+use std::env;
 use std::fs;
-use std::io::{Write, stdin};
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::config;
+use crate::proxy;
+use crate::serialization::write_private;
+use crate::token_encryption::TokenEncryption;
 
 const CLIENT_CONFIG_FILE: &str = "client.yml";
-const DEFAULT_PORT: u16 = 8888;
+/// The environment variable that overrides the location of `client.yml`.
+const CLIENT_CONFIG_VARIABLE: &str = "NCSPOT_CLIENT_CONFIG";
+/// The environment variable that overrides the location of the Web API token cache.
+const TOKEN_FILE_VARIABLE: &str = "NCSPOT_TOKEN_FILE";
+/// The environment variables that override the values of `client.yml`.
+const CLIENT_ID_VARIABLE: &str = "NCSPOT_CLIENT_ID";
+const CLIENT_SECRET_VARIABLE: &str = "NCSPOT_CLIENT_SECRET";
+const PORT_VARIABLE: &str = "NCSPOT_PORT";
+pub const DEFAULT_PORT: u16 = 8888;
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_CALLBACK_TIMEOUT: u64 = 300;
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
 
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ClientConfig {
     pub client_id: String,
     pub client_secret: String,
     pub device_id: Option<String>,
+    /// The name shown in audio mixers and MPRIS clients, unless one is passed with `--name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
     pub port: Option<u16>,
+    /// Ports the OAuth callback server tries in turn when `port` is in use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_ports: Option<Vec<u16>>,
+    /// The address the OAuth callback server listens on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
+    /// The host name in the redirect URI, for logging in with a browser on another machine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_host: Option<String>,
+    /// PEM file with the certificate the OAuth callback server uses for HTTPS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_certificate: Option<String>,
+    /// PEM file with the private key of `tls_certificate`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+    /// Seconds to wait for the OAuth callback before asking whether to keep waiting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_timeout: Option<u64>,
+    /// Encrypt the cached Web API token with a passphrase or a machine key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_encryption: Option<TokenEncryption>,
+    /// Where the Web API token is cached, instead of the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<String>,
+}
+
+/// Values of the client configuration passed on the command line, which take precedence over the
+/// environment and `client.yml`.
+#[derive(Default, Debug)]
+pub struct Overrides {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub port: Option<String>,
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Set the values passed on the command line. Has to be called before any configuration is loaded.
+pub fn set_overrides(overrides: Overrides) {
+    OVERRIDES.set(overrides).ok();
 }
 
 pub struct ClientConfigPaths {
@@ -28,19 +87,83 @@ impl ClientConfig {
             client_id: String::new(),
             client_secret: String::new(),
             device_id: None,
+            device_name: None,
             port: None,
+            fallback_ports: None,
+            bind_address: None,
+            redirect_host: None,
+            tls_certificate: None,
+            tls_key: None,
+            callback_timeout: None,
+            token_encryption: None,
+            token_file: None,
         }
     }
 
     pub fn get_redirect_uri(&self) -> String {
-        format!("http://127.0.0.1:{}/callback", self.get_port())
+        self.get_redirect_uri_for(self.get_port())
+    }
+
+    /// Get the redirect URI for the callback server listening on `port`.
+    pub fn get_redirect_uri_for(&self, port: u16) -> String {
+        let scheme = if self.get_tls().is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let host = self.redirect_host.as_deref().unwrap_or(DEFAULT_HOST);
+        // IPv6 addresses have to be enclosed in brackets in URLs.
+        let host = if host.contains(':') && !host.starts_with('[') {
+            format!("[{host}]")
+        } else {
+            host.to_string()
+        };
+        format!("{scheme}://{host}:{port}/callback")
+    }
+
+    pub fn get_bind_address(&self) -> &str {
+        self.bind_address.as_deref().unwrap_or(DEFAULT_HOST)
+    }
+
+    pub fn get_callback_timeout(&self) -> Duration {
+        Duration::from_secs(self.callback_timeout.unwrap_or(DEFAULT_CALLBACK_TIMEOUT))
+    }
+
+    /// Get the paths of the certificate and the private key for HTTPS, if both are configured.
+    pub fn get_tls(&self) -> Option<(&str, &str)> {
+        self.tls_certificate.as_deref().zip(self.tls_key.as_deref())
     }
 
     pub fn get_port(&self) -> u16 {
         self.port.unwrap_or(DEFAULT_PORT)
     }
 
+    /// Get the ports for the callback server in the order they are tried.
+    pub fn get_ports(&self) -> Vec<u16> {
+        let mut ports = vec![self.get_port()];
+        for &port in self.fallback_ports.iter().flatten() {
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+        ports
+    }
+
+    /// Get the path of the Web API token cache if it was moved, by `NCSPOT_TOKEN_FILE` or the
+    /// configuration.
+    pub fn get_token_file(&self) -> Option<PathBuf> {
+        env::var_os(TOKEN_FILE_VARIABLE)
+            .map(PathBuf::from)
+            .or_else(|| self.token_file.as_ref().map(PathBuf::from))
+    }
+
     pub fn get_or_build_paths(&self) -> Result<ClientConfigPaths, String> {
+        if let Some(path) = env::var_os(CLIENT_CONFIG_VARIABLE) {
+            return Ok(ClientConfigPaths {
+                config_file_path: PathBuf::from(path),
+            });
+        }
+
         let config_dir = config::user_configuration_directory()
             .ok_or_else(|| "Could not determine configuration directory".to_string())?;
 
@@ -56,113 +179,125 @@ impl ClientConfig {
 
     pub fn load_config(&mut self) -> Result<(), String> {
         let paths = self.get_or_build_paths()?;
+        let exists = paths.config_file_path.exists();
 
-        if paths.config_file_path.exists() {
+        if exists {
             debug!("Loading client config from {:?}", paths.config_file_path);
             let config_string = fs::read_to_string(&paths.config_file_path)
                 .map_err(|e| format!("Failed to read config: {}", e))?;
-            let config_yml: Self = serde_yaml::from_str(&config_string)
+            *self = serde_yaml::from_str(&config_string)
                 .map_err(|e| format!("Failed to parse config: {}", e))?;
+        }
+        self.apply_overrides()?;
 
-            self.client_id = config_yml.client_id;
-            self.client_secret = config_yml.client_secret;
-            self.device_id = config_yml.device_id;
-            self.port = config_yml.port;
-
-            if self.client_id.is_empty() || self.client_secret.is_empty() {
-                return Err("client_id or client_secret is empty in config file".to_string());
-            }
+        if !exists && !self.has_credentials() {
+            return Err(format!(
+                "No client configuration found at {}, start ncspot once to set it up",
+                paths.config_file_path.display()
+            ));
+        }
+        if !self.has_credentials() {
+            return Err("client_id or client_secret is empty in config file".to_string());
+        }
+        if self.tls_certificate.is_some() != self.tls_key.is_some() {
+            return Err("tls_certificate and tls_key have to be set together".to_string());
+        }
 
-            info!("Loaded client configuration");
-            Ok(())
-        } else {
-            self.run_setup_wizard(&paths)
-        }
-    }
-
-    fn run_setup_wizard(&mut self, paths: &ClientConfigPaths) -> Result<(), String> {
-        println!("\n=== ncspot OAuth Setup ===\n");
-        println!(
-            "Config will be saved to: {}\n",
-            paths.config_file_path.display()
-        );
-
-        println!("To use ncspot, you need to create a Spotify Developer application:\n");
-        println!("  1. Go to https://developer.spotify.com/dashboard/applications");
-        println!("  2. Click 'Create app' and fill in a name and description");
-        println!(
-            "  3. Add `http://127.0.0.1:{}/callback` to Redirect URIs",
-            DEFAULT_PORT
-        );
-        println!("  4. Save your app and copy the Client ID and Client Secret\n");
-
-        let client_id = Self::get_client_key_from_input("Client ID")?;
-        let client_secret = Self::get_client_key_from_input("Client Secret")?;
-
-        println!("\nEnter port for redirect URI (default {}): ", DEFAULT_PORT);
-        let mut port_input = String::new();
-        stdin()
-            .read_line(&mut port_input)
-            .map_err(|e| format!("Failed to read input: {}", e))?;
-        let port = port_input.trim().parse::<u16>().unwrap_or(DEFAULT_PORT);
-
-        let config_yml = Self {
-            client_id: client_id.clone(),
-            client_secret: client_secret.clone(),
-            device_id: None,
-            port: Some(port),
-        };
+        info!("Loaded client configuration");
+        Ok(())
+    }
 
-        let content_yml = serde_yaml::to_string(&config_yml)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    /// Whether the setup has to run first, because there is neither a configuration file nor are
+    /// the credentials given on the command line or in the environment.
+    pub fn needs_setup(&self) -> Result<bool, String> {
+        let mut overrides = Self::new();
+        overrides.apply_overrides()?;
+        Ok(!self.get_or_build_paths()?.config_file_path.exists() && !overrides.has_credentials())
+    }
 
-        let mut new_config = fs::File::create(&paths.config_file_path)
-            .map_err(|e| format!("Failed to create config file: {}", e))?;
-        write!(new_config, "{}", content_yml)
-            .map_err(|e| format!("Failed to write config: {}", e))?;
+    fn has_credentials(&self) -> bool {
+        !self.client_id.is_empty() && !self.client_secret.is_empty()
+    }
 
-        self.client_id = client_id;
-        self.client_secret = client_secret;
-        self.device_id = None;
-        self.port = Some(port);
+    /// Take the credentials and the port from the environment and the command line, in that order,
+    /// over the ones in the configuration file.
+    fn apply_overrides(&mut self) -> Result<(), String> {
+        let overrides = OVERRIDES.get();
+        let value = |variable: &str, flag: fn(&Overrides) -> &Option<String>| {
+            overrides
+                .and_then(|overrides| flag(overrides).clone())
+                .or_else(|| env::var(variable).ok())
+                .filter(|value| !value.is_empty())
+        };
 
-        println!("\nConfiguration saved successfully!");
-        println!(
-            "Make sure your Redirect URI in Spotify Dashboard matches: http://127.0.0.1:{}/callback\n",
-            port
-        );
+        if let Some(client_id) = value(CLIENT_ID_VARIABLE, |o| &o.client_id) {
+            self.client_id = client_id;
+        }
+        if let Some(client_secret) = value(CLIENT_SECRET_VARIABLE, |o| &o.client_secret) {
+            self.client_secret = client_secret;
+        }
+        if let Some(port) = value(PORT_VARIABLE, |o| &o.port) {
+            let port = port
+                .parse()
+                .map_err(|_| format!("Invalid port {port}, expected a number up to 65535"))?;
+            self.port = Some(port);
+        }
+        Ok(())
+    }
 
+    /// Write the configuration to `client.yml`, readable only by the current user.
+    pub fn save(&self) -> Result<(), String> {
+        let paths = self.get_or_build_paths()?;
+        let content_yml = serde_yaml::to_string(self)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        write_private(&paths.config_file_path, content_yml.as_bytes())
+            .map_err(|e| format!("Failed to write config: {}", e))?;
+        info!("Saved client configuration to {:?}", paths.config_file_path);
         Ok(())
     }
 
-    fn get_client_key_from_input(type_label: &str) -> Result<String, String> {
-        const MAX_RETRIES: u8 = 5;
-        let mut num_retries = 0;
-
-        loop {
-            print!("Enter your {}: ", type_label);
-            std::io::stdout().flush().ok();
-
-            let mut client_key = String::new();
-            stdin()
-                .read_line(&mut client_key)
-                .map_err(|e| format!("Failed to read input: {}", e))?;
-            let client_key = client_key.trim().to_string();
-
-            match Self::validate_client_key(&client_key) {
-                Ok(()) => return Ok(client_key),
-                Err(error_string) => {
-                    println!("  Error: {}", error_string);
-                    num_retries += 1;
-                    if num_retries >= MAX_RETRIES {
-                        return Err(format!("Maximum retries ({}) exceeded", MAX_RETRIES));
-                    }
-                }
+    /// Check the credentials with a client credentials request, which Spotify rejects for an
+    /// unknown ID or a wrong secret. Returns whether they could be checked, they are accepted if
+    /// Spotify can't be reached as the login will tell.
+    pub fn verify_credentials(client_id: &str, client_secret: &str) -> Result<bool, String> {
+        let client = proxy::client_builder().build().map_err(|e| e.to_string())?;
+        let response = client
+            .post(TOKEN_URL)
+            .basic_auth(client_id, Some(client_secret))
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body("grant_type=client_credentials")
+            .send();
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Could not check the client credentials: {}", e);
+                return Ok(false);
+            }
+        };
+        if response.status().is_success() {
+            return Ok(true);
+        }
+
+        let status = response.status();
+        let body: serde_json::Value = response.json().unwrap_or_default();
+        match body["error"].as_str() {
+            Some("invalid_client") => Err(format!(
+                "Spotify rejected the credentials: {}",
+                body["error_description"]
+                    .as_str()
+                    .unwrap_or("invalid client")
+            )),
+            _ => {
+                warn!("Unexpected response when checking the client credentials: {status}");
+                Ok(false)
             }
         }
     }
 
-    fn validate_client_key(key: &str) -> Result<(), String> {
+    pub fn validate_client_key(key: &str) -> Result<(), String> {
         const EXPECTED_LEN: usize = 32;
 
         if key.is_empty() {
@@ -214,5 +349,25 @@ mod tests {
             ..Default::default()
         };
         assert_eq!(config.get_redirect_uri(), "http://127.0.0.1:8888/callback");
+
+        let config = ClientConfig {
+            port: Some(8443),
+            redirect_host: Some("::1".into()),
+            tls_certificate: Some("cert.pem".into()),
+            tls_key: Some("key.pem".into()),
+            ..Default::default()
+        };
+        assert_eq!(config.get_redirect_uri(), "https://[::1]:8443/callback");
+    }
+
+    #[test]
+    fn test_ports() {
+        let config = ClientConfig {
+            port: Some(8888),
+            fallback_ports: Some(vec![8889, 8888, 8890]),
+            ..Default::default()
+        };
+        assert_eq!(config.get_ports(), vec![8888, 8889, 8890]);
+        assert_eq!(ClientConfig::new().get_ports(), vec![DEFAULT_PORT]);
     }
 }
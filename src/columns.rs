@@ -0,0 +1,153 @@
+//! Configurable columns of track lists. The columns configured for a kind of view replace the
+//! left, center and right parts of the `track_format` in its track lists. Every column has a
+//! format with the same placeholders, an alignment and limits for its width. The space left after
+//! the minimum widths is shared evenly between the columns that can still grow.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::{Config, ConfigColumn};
+use crate::library::Library;
+use crate::model::playable::Playable;
+
+/// The space between two columns.
+const GAP: usize = 1;
+
+/// Columns are at least this wide unless they have a minimum width.
+const DEFAULT_MIN_WIDTH: usize = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// The kinds of views with their own columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnSet {
+    Playlist,
+    Queue,
+    Search,
+    Album,
+}
+
+impl ColumnSet {
+    /// The columns configured for this kind of view, if any.
+    pub fn columns(self, cfg: &Config) -> Option<Vec<ConfigColumn>> {
+        let values = cfg.values();
+        let columns = values.columns.as_ref()?;
+        match self {
+            Self::Playlist => columns.playlist.clone(),
+            Self::Queue => columns.queue.clone(),
+            Self::Search => columns.search.clone(),
+            Self::Album => columns.album.clone(),
+        }
+        .filter(|columns| !columns.is_empty())
+    }
+}
+
+/// The widths of `columns` in a row of `total` cells.
+pub fn widths(columns: &[ConfigColumn], total: usize) -> Vec<usize> {
+    let max_width = |column: &ConfigColumn| column.max_width.unwrap_or(usize::MAX);
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .map(|column| column.min_width.unwrap_or(DEFAULT_MIN_WIDTH))
+        .collect();
+    let used = widths.iter().sum::<usize>() + GAP * columns.len().saturating_sub(1);
+    let mut free = total.saturating_sub(used);
+    while free > 0 {
+        let growing: Vec<usize> = (0..columns.len())
+            .filter(|i| widths[*i] < max_width(&columns[*i]))
+            .collect();
+        if growing.is_empty() {
+            break;
+        }
+        let share = (free / growing.len()).max(1);
+        for i in growing {
+            let added = share.min(max_width(&columns[i]) - widths[i]).min(free);
+            widths[i] += added;
+            free -= added;
+        }
+    }
+    widths
+}
+
+/// `text` aligned in a cell of `width`, cut off with `..` if it is too long.
+pub fn cell(text: &str, width: usize, align: ColumnAlign) -> String {
+    let text = if text.width() > width {
+        let mut cut = String::new();
+        let mut cut_width = 0;
+        for c in text.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if cut_width + char_width + 2 > width {
+                break;
+            }
+            cut.push(c);
+            cut_width += char_width;
+        }
+        cut.push_str(&".."[..width.min(2)]);
+        cut
+    } else {
+        text.to_string()
+    };
+    let padding = width.saturating_sub(text.width());
+    let (before, after) = match align {
+        ColumnAlign::Left => (0, padding),
+        ColumnAlign::Center => (padding / 2, padding - padding / 2),
+        ColumnAlign::Right => (padding, 0),
+    };
+    format!("{}{text}{}", " ".repeat(before), " ".repeat(after))
+}
+
+/// The row of `playable` with `columns` in `total` cells.
+pub fn row(
+    playable: &Playable,
+    columns: &[ConfigColumn],
+    total: usize,
+    library: &Library,
+) -> String {
+    columns
+        .iter()
+        .zip(widths(columns, total))
+        .map(|(column, width)| {
+            let text = Playable::format(playable, &column.format, library);
+            cell(&text, width, column.align.unwrap_or_default())
+        })
+        .collect::<Vec<String>>()
+        .join(&" ".repeat(GAP))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(min_width: Option<usize>, max_width: Option<usize>) -> ConfigColumn {
+        ConfigColumn {
+            format: String::new(),
+            align: None,
+            min_width,
+            max_width,
+        }
+    }
+
+    #[test]
+    fn test_widths() {
+        let columns = [
+            column(Some(10), None),
+            column(None, None),
+            column(Some(5), Some(5)),
+        ];
+        assert_eq!(widths(&columns, 40), [21, 12, 5]);
+        // Without enough space, the minimum widths are kept.
+        assert_eq!(widths(&columns, 10), [10, 1, 5]);
+        // Space that no column can take is left over.
+        assert_eq!(widths(&[column(None, Some(8))], 40), [8]);
+
+        assert_eq!(cell("Hello world", 8, ColumnAlign::Left), "Hello ..");
+        assert_eq!(cell("abc", 5, ColumnAlign::Right), "  abc");
+        assert_eq!(cell("abc", 6, ColumnAlign::Center), " abc  ");
+        assert_eq!(cell("abc", 1, ColumnAlign::Left), ".");
+    }
+}
@@ -1,8 +1,12 @@
+use crate::caches::CacheKind;
+use crate::playlist_merge::MergeMode;
 use crate::queue::RepeatSetting;
+use crate::shuffle::ShuffleMode;
 use crate::spotify_url::SpotifyUrl;
-use std::collections::HashMap;
+use crate::visualizer::VisualizerStyle;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 
 use strum_macros::Display;
 
@@ -46,6 +50,8 @@ pub enum SortKey {
     Artist,
     Album,
     Added,
+    Popularity,
+    Year,
 }
 
 #[derive(Display, Clone, Serialize, Deserialize, Debug)]
@@ -55,6 +61,32 @@ pub enum SortDirection {
     Descending,
 }
 
+/// What the tracks of a playlist can be grouped by.
+#[derive(Display, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum GroupBy {
+    Album,
+    Artist,
+}
+
+/// Which marker of the A-B loop the `abloop` command places, or `Off` to remove the loop.
+#[derive(Display, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum LoopMode {
+    A,
+    B,
+    Off,
+}
+
+/// How the `mark` command changes the marked items of a list.
+#[derive(Display, Clone, Serialize, Deserialize, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum MarkMode {
+    Toggle,
+    Range,
+    Clear,
+}
+
 #[derive(Display, Clone, Serialize, Deserialize, Debug)]
 #[strum(serialize_all = "lowercase")]
 pub enum JumpMode {
@@ -63,6 +95,20 @@ pub enum JumpMode {
     Query(String),
 }
 
+#[derive(Display, Clone, Copy, Serialize, Deserialize, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum PaneMode {
+    Left,
+    Right,
+}
+
+#[derive(Display, Clone, Copy, Serialize, Deserialize, Debug)]
+#[strum(serialize_all = "lowercase")]
+pub enum JumpListMode {
+    Back,
+    Forward,
+}
+
 #[derive(Display, Clone, Serialize, Deserialize, Debug)]
 #[strum(serialize_all = "lowercase")]
 pub enum ShiftMode {
@@ -81,6 +127,12 @@ pub enum GotoMode {
 pub enum SeekDirection {
     Relative(i32),
     Absolute(u32),
+    /// A position in percent of the duration.
+    Percent(f64),
+    /// A number of the configured seek steps, backwards if negative.
+    Step(i32),
+    /// A number of the configured large seek steps, backwards if negative.
+    LargeStep(i32),
 }
 
 impl fmt::Display for SeekDirection {
@@ -90,11 +142,79 @@ impl fmt::Display for SeekDirection {
             Self::Relative(delta) => {
                 format!("{}{}", if delta > &0 { "+" } else { "" }, delta)
             }
+            Self::Percent(percent) => format!("{percent}%"),
+            Self::Step(steps) => format!("{}step", Self::steps(*steps)),
+            Self::LargeStep(steps) => format!("{}largestep", Self::steps(*steps)),
         };
         write!(f, "{repr}")
     }
 }
 
+impl SeekDirection {
+    /// The sign and count of steps, leaving out a count of one.
+    fn steps(steps: i32) -> String {
+        let sign = if steps < 0 { "-" } else { "+" };
+        match steps.unsigned_abs() {
+            1 => sign.to_string(),
+            count => format!("{sign}{count}"),
+        }
+    }
+
+    /// Parse the special positions `seek` accepts besides durations: a percentage like `50%`, a
+    /// time like `2:30` or `1:02:30`, and steps like `+step`, `-2step` or `+largestep`.
+    fn parse_special(arg: &str) -> Option<Self> {
+        if let Some(percent) = arg.strip_suffix('%') {
+            return percent
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|percent| Self::Percent(percent.clamp(0.0, 100.0)));
+        }
+        if arg.contains(':') {
+            let mut seconds: u32 = 0;
+            for part in arg.split(':') {
+                seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+            }
+            return seconds.checked_mul(1000).map(Self::Absolute);
+        }
+        let (large, rest) = match arg.strip_suffix("largestep") {
+            Some(rest) => (true, rest),
+            None => (false, arg.strip_suffix("step")?),
+        };
+        let (sign, count) = match rest.split_at_checked(1)? {
+            ("+", count) => (1, count),
+            ("-", count) => (-1, count),
+            _ => return None,
+        };
+        let count: i32 = if count.is_empty() {
+            1
+        } else {
+            count.parse().ok()?
+        };
+        Some(if large {
+            Self::LargeStep(sign * count)
+        } else {
+            Self::Step(sign * count)
+        })
+    }
+}
+
+/// A change of a gain in dB.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum GainChange {
+    Relative(f64),
+    Absolute(f64),
+}
+
+impl fmt::Display for GainChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Absolute(db) => write!(f, "{db}"),
+            Self::Relative(delta) => write!(f, "{delta:+}"),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum InsertSource {
     #[cfg(feature = "share_clipboard")]
@@ -132,31 +252,83 @@ pub enum Command {
     AddCurrent,
     Delete,
     Focus(String),
+    Split(Option<[String; 2]>),
+    Pane(Option<PaneMode>),
     Seek(SeekDirection),
     VolumeUp(u16),
     VolumeDown(u16),
     Repeat(Option<RepeatSetting>),
     Shuffle(Option<bool>),
+    ShuffleMode(ShuffleMode),
+    Autoplay(Option<bool>),
+    Volnorm(Option<bool>),
+    Preamp(Option<GainChange>),
+    TrackGain(Option<GainChange>),
+    Device(Option<String>),
+    Backend(Option<String>),
+    Theme(Option<String>),
+    Loop(Option<LoopMode>),
     #[cfg(feature = "share_clipboard")]
     Share(TargetMode),
     Back,
     Open(TargetMode),
+    OpenUrl(SpotifyUrl),
     Goto(GotoMode),
     Move(MoveMode, MoveAmount),
     Shift(ShiftMode, Option<i32>),
     Search(String),
     Jump(JumpMode),
+    JumpList(JumpListMode),
+    SetMark(Option<char>),
+    GotoMark(Option<char>),
     Help,
+    HelpKeys,
     ReloadConfig,
     Noop,
     Insert(InsertSource),
+    #[cfg(feature = "share_clipboard")]
+    Paste,
     NewPlaylist(String),
     Sort(SortKey, SortDirection),
     Logout,
     ShowRecommendations(TargetMode),
+    Radio(TargetMode),
     Redraw,
     Execute(String),
     Reconnect,
+    ShowMixer,
+    ShowCache,
+    ClearCache(CacheKind),
+    ShowCredits,
+    ShowLyrics,
+    ShowVisualizer(Option<VisualizerStyle>),
+    ShowStats,
+    Download,
+    ShowDownloads,
+    CancelDownloads,
+    Bookmark(Option<String>),
+    ClearBookmarks,
+    NextChapter,
+    PreviousChapter,
+    SmartPlaylist(String),
+    DumpJournal(Option<String>),
+    ExportHistory(Option<String>),
+    Offline(Option<bool>),
+    Merge(Option<MergeMode>),
+    Duplicates,
+    Unavailable,
+    Discography,
+    NewFolder(String),
+    MoveToFolder(Option<String>),
+    Contributor(Option<String>),
+    Group(Option<GroupBy>),
+    Filter(Option<String>),
+    Mark(MarkMode),
+    Undo,
+    Redo,
+    /// A command added by a script, with its arguments.
+    #[cfg(feature = "scripting")]
+    Script(String, Vec<String>),
 }
 
 impl fmt::Display for Command {
@@ -164,6 +336,8 @@ impl fmt::Display for Command {
         let mut repr_tokens = vec![self.basename().to_owned()];
         let mut extras_args = match self {
             Self::Focus(tab) => vec![tab.to_owned()],
+            Self::Split(screens) => screens.iter().flatten().cloned().collect(),
+            Self::Pane(mode) => mode.iter().map(PaneMode::to_string).collect(),
             Self::Seek(direction) => vec![direction.to_string()],
             Self::VolumeUp(amount) => vec![amount.to_string()],
             Self::VolumeDown(amount) => vec![amount.to_string()],
@@ -171,13 +345,26 @@ impl fmt::Display for Command {
                 Some(mode) => vec![mode.to_string()],
                 None => vec![],
             },
-            Self::Shuffle(on) => match on {
+            Self::Shuffle(on) | Self::Autoplay(on) | Self::Volnorm(on) => match on {
                 Some(b) => vec![(if *b { "on" } else { "off" }).into()],
                 None => vec![],
             },
+            Self::ShuffleMode(mode) => vec!["mode".into(), mode.to_string()],
+            Self::ClearCache(kind) => vec![kind.to_string()],
+            Self::ShowVisualizer(style) => style.iter().map(VisualizerStyle::to_string).collect(),
+            Self::Preamp(change) | Self::TrackGain(change) => {
+                change.iter().map(GainChange::to_string).collect()
+            }
+            Self::Device(name) | Self::Backend(name) | Self::Theme(name) | Self::Bookmark(name) => {
+                name.iter().cloned().collect()
+            }
+            Self::Loop(mode) => mode.iter().map(LoopMode::to_string).collect(),
             #[cfg(feature = "share_clipboard")]
             Self::Share(mode) => vec![mode.to_string()],
             Self::Open(mode) => vec![mode.to_string()],
+            Self::OpenUrl(url) => vec![url.to_string()],
+            #[cfg(feature = "scripting")]
+            Self::Script(_, args) => args.clone(),
             Self::Goto(mode) => vec![mode.to_string()],
             Self::Move(mode, amount) => match (mode, amount) {
                 (MoveMode::Playing, _) => vec!["playing".to_string()],
@@ -194,11 +381,33 @@ impl fmt::Display for Command {
                 JumpMode::Previous | JumpMode::Next => vec![],
                 JumpMode::Query(term) => vec![term.to_owned()],
             },
+            Self::JumpList(mode) => vec![mode.to_string()],
+            Self::SetMark(name) | Self::GotoMark(name) => {
+                name.iter().map(char::to_string).collect()
+            }
             Self::Insert(source) => vec![source.to_string()],
             Self::NewPlaylist(name) => vec![name.to_owned()],
             Self::Sort(key, direction) => vec![key.to_string(), direction.to_string()],
             Self::ShowRecommendations(mode) => vec![mode.to_string()],
+            Self::Radio(mode) => vec![mode.to_string()],
             Self::Execute(cmd) => vec![cmd.to_owned()],
+            Self::SmartPlaylist(name) => vec![name.to_owned()],
+            Self::DumpJournal(path) => path.iter().cloned().collect(),
+            Self::ExportHistory(path) => path.iter().cloned().collect(),
+            Self::Merge(mode) => mode.iter().map(MergeMode::to_string).collect(),
+            Self::NewFolder(name) => vec![name.to_owned()],
+            Self::MoveToFolder(name) => name.iter().cloned().collect(),
+            Self::Contributor(name) => name.iter().cloned().collect(),
+            Self::Group(by) => by.iter().map(GroupBy::to_string).collect(),
+            Self::Filter(query) => query.iter().cloned().collect(),
+            Self::Mark(mode) => match mode {
+                MarkMode::Toggle => vec![],
+                mode => vec![mode.to_string()],
+            },
+            Self::Offline(on) => match on {
+                Some(b) => vec![(if *b { "on" } else { "off" }).into()],
+                None => vec![],
+            },
             Self::Quit
             | Self::TogglePlay
             | Self::Stop
@@ -217,11 +426,30 @@ impl fmt::Display for Command {
             | Self::Delete
             | Self::Back
             | Self::Help
+            | Self::HelpKeys
             | Self::ReloadConfig
             | Self::Noop
             | Self::Logout
             | Self::Reconnect
+            | Self::ShowMixer
+            | Self::ShowCache
+            | Self::ShowCredits
+            | Self::ShowLyrics
+            | Self::ShowStats
+            | Self::Download
+            | Self::ShowDownloads
+            | Self::CancelDownloads
+            | Self::ClearBookmarks
+            | Self::NextChapter
+            | Self::PreviousChapter
+            | Self::Duplicates
+            | Self::Unavailable
+            | Self::Discography
+            | Self::Undo
+            | Self::Redo
             | Self::Redraw => vec![],
+            #[cfg(feature = "share_clipboard")]
+            Self::Paste => vec![],
         };
         repr_tokens.append(&mut extras_args);
         write!(f, "{}", repr_tokens.join(" "))
@@ -248,15 +476,25 @@ impl Command {
             Self::AddCurrent => "add current",
             Self::Delete => "delete",
             Self::Focus(_) => "focus",
+            Self::Split(_) => "split",
+            Self::Pane(_) => "pane",
             Self::Seek(_) => "seek",
             Self::VolumeUp(_) => "volup",
             Self::VolumeDown(_) => "voldown",
             Self::Repeat(_) => "repeat",
-            Self::Shuffle(_) => "shuffle",
+            Self::Shuffle(_) | Self::ShuffleMode(_) => "shuffle",
+            Self::Autoplay(_) => "autoplay",
+            Self::Volnorm(_) => "volnorm",
+            Self::Preamp(_) => "preamp",
+            Self::TrackGain(_) => "trackgain",
+            Self::Device(_) => "device",
+            Self::Backend(_) => "backend",
+            Self::Theme(_) => "theme",
+            Self::Loop(_) => "abloop",
             #[cfg(feature = "share_clipboard")]
             Self::Share(_) => "share",
             Self::Back => "back",
-            Self::Open(_) => "open",
+            Self::Open(_) | Self::OpenUrl(_) => "open",
             Self::Goto(_) => "goto",
             Self::Move(_, _) => "move",
             Self::Shift(_, _) => "shift",
@@ -264,17 +502,56 @@ impl Command {
             Self::Jump(JumpMode::Previous) => "jumpprevious",
             Self::Jump(JumpMode::Next) => "jumpnext",
             Self::Jump(JumpMode::Query(_)) => "jump",
+            Self::JumpList(_) => "jumplist",
+            Self::SetMark(_) => "setmark",
+            Self::GotoMark(_) => "gotomark",
             Self::Help => "help",
+            Self::HelpKeys => "help keys",
             Self::ReloadConfig => "reload",
             Self::Noop => "noop",
             Self::Insert(_) => "insert",
+            #[cfg(feature = "share_clipboard")]
+            Self::Paste => "paste",
             Self::NewPlaylist(_) => "newplaylist",
             Self::Sort(_, _) => "sort",
             Self::Logout => "logout",
             Self::ShowRecommendations(_) => "similar",
+            Self::Radio(_) => "radio",
             Self::Redraw => "redraw",
             Self::Execute(_) => "exec",
             Self::Reconnect => "reconnect",
+            Self::ShowMixer => "mixer",
+            Self::ShowCache => "cache",
+            Self::ClearCache(_) => "cache clear",
+            Self::ShowCredits => "credits",
+            Self::ShowLyrics => "lyrics",
+            Self::ShowVisualizer(_) => "visualizer",
+            Self::ShowStats => "stats",
+            Self::Download => "download",
+            Self::ShowDownloads => "downloads",
+            Self::CancelDownloads => "downloads cancel",
+            Self::Bookmark(_) => "bookmark",
+            Self::ClearBookmarks => "bookmark clear",
+            Self::NextChapter => "nextchapter",
+            Self::PreviousChapter => "previouschapter",
+            Self::SmartPlaylist(_) => "smartplaylist",
+            Self::DumpJournal(_) => "debug dump",
+            Self::ExportHistory(_) => "history export",
+            Self::Offline(_) => "offline",
+            Self::Merge(_) => "merge",
+            Self::Duplicates => "duplicates",
+            Self::Unavailable => "unavailable",
+            Self::Discography => "discography",
+            Self::NewFolder(_) => "folder new",
+            Self::MoveToFolder(_) => "folder move",
+            Self::Contributor(_) => "contributor",
+            Self::Group(_) => "group",
+            Self::Filter(_) => "filter",
+            Self::Mark(_) => "mark",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            #[cfg(feature = "scripting")]
+            Self::Script(name, _) => name,
         }
     }
 }
@@ -310,6 +587,50 @@ fn handle_aliases(input: &str) -> &str {
     }
 }
 
+/// The aliases from the configuration, which stand for a sequence of commands.
+static USER_ALIASES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// Replace the user defined aliases with `aliases`, which map a name to the commands it runs.
+/// Returns the errors of the aliases that can't be used.
+pub fn set_user_aliases(aliases: HashMap<String, String>) -> Vec<String> {
+    USER_ALIASES.write().unwrap().clear();
+
+    let mut errors = Vec::new();
+    let mut valid = BTreeMap::new();
+    for (name, commands) in aliases {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ';') {
+            errors.push(format!("Invalid alias name \"{name}\""));
+        } else if !matches!(parse(&name), Err(CommandParseError::NoSuchCommand { .. })) {
+            errors.push(format!(
+                "Alias \"{name}\" is the name of a command and is ignored"
+            ));
+        } else {
+            valid.insert(name, commands);
+        }
+    }
+    *USER_ALIASES.write().unwrap() = valid;
+
+    // Check the commands once all aliases are known, as they can use each other.
+    let names: Vec<String> = USER_ALIASES.read().unwrap().keys().cloned().collect();
+    for name in names {
+        if let Err(err) = parse(&name) {
+            errors.push(format!("Invalid alias \"{name}\": {err}"));
+        }
+    }
+    errors
+}
+
+/// The commands added by scripts, see [crate::scripting].
+#[cfg(feature = "scripting")]
+static SCRIPT_COMMANDS: RwLock<std::collections::BTreeSet<String>> =
+    RwLock::new(std::collections::BTreeSet::new());
+
+/// Replace the commands added by scripts with `names`.
+#[cfg(feature = "scripting")]
+pub fn set_script_commands(names: Vec<String>) {
+    *SCRIPT_COMMANDS.write().unwrap() = names.into_iter().collect();
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum CommandParseError {
     NoSuchCommand {
@@ -328,6 +649,9 @@ pub enum CommandParseError {
         arg: String,
         err: String,
     },
+    RecursiveAlias {
+        alias: String,
+    },
 }
 
 impl fmt::Display for CommandParseError {
@@ -354,12 +678,21 @@ impl fmt::Display for CommandParseError {
                 }
             }
             Self::ArgParseError { arg, err } => format!("Error with argument \"{arg}\": {err}"),
+            Self::RecursiveAlias { alias } => format!("Alias \"{alias}\" runs itself"),
         };
         write!(f, "{formatted}")
     }
 }
 
 pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
+    parse_expanding(input, &mut Vec::new())
+}
+
+/// Parse `input`, where `expanding` are the user defined aliases whose commands are being parsed.
+fn parse_expanding(
+    input: &str,
+    expanding: &mut Vec<String>,
+) -> Result<Vec<Command>, CommandParseError> {
     let mut command_inputs = vec!["".to_string()];
     let mut command_idx = 0;
     enum ParseState {
@@ -431,6 +764,28 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     // TODO: this really should be strongly typed
                     Command::Focus(target.into())
                 }
+                "split" => match args[..] {
+                    [] => Command::Split(None),
+                    [left, right] => Command::Split(Some([left.into(), right.into()])),
+                    _ => {
+                        return Err(E::InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("the screens of the left and the right pane".into()),
+                        });
+                    }
+                },
+                "pane" => match args.first().copied() {
+                    None => Command::Pane(None),
+                    Some("left") => Command::Pane(Some(PaneMode::Left)),
+                    Some("right") => Command::Pane(Some(PaneMode::Right)),
+                    Some(arg) => {
+                        return Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["left".into(), "right".into()],
+                            optional: true,
+                        });
+                    }
+                },
                 "seek" => {
                     if args.is_empty() {
                         return Err(E::InsufficientArgs {
@@ -439,41 +794,48 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         });
                     }
                     let arg = args.join(" ");
-                    let first_char = arg.chars().next();
-                    let duration_raw = match first_char {
-                        Some('+' | '-') => {
-                            arg.chars().skip(1).collect::<String>().trim().into()
-                            // `trim` is necessary here, otherwise `+1000` -> 1 second, but `+ 1000` -> 1000 seconds
-                            // this behaviour is inconsistent and could cause confusion
+                    let seek_direction = match SeekDirection::parse_special(&arg) {
+                        Some(direction) => direction,
+                        None => {
+                            let first_char = arg.chars().next();
+                            let duration_raw = match first_char {
+                                Some('+' | '-') => {
+                                    arg.chars().skip(1).collect::<String>().trim().into()
+                                    // `trim` is necessary here, otherwise `+1000` -> 1 second, but `+ 1000` -> 1000 seconds
+                                    // this behaviour is inconsistent and could cause confusion
+                                }
+                                _ => arg,
+                            };
+                            let unsigned_millis = match duration_raw.parse() {
+                                // accept raw milliseconds
+                                Ok(millis) => millis,
+                                Err(_) => parse_duration::parse(&duration_raw) // accept fancy duration
+                                    .map_err(|err| E::ArgParseError {
+                                        arg: duration_raw.clone(),
+                                        err: err.to_string(),
+                                    })
+                                    .and_then(|dur| {
+                                        dur.as_millis().try_into().map_err(|_| E::ArgParseError {
+                                            arg: duration_raw.clone(),
+                                            err: "Duration value too large".into(),
+                                        })
+                                    })?,
+                            };
+                            match first_char {
+                                // handle i32::MAX < unsigned_millis < u32::MAX gracefully
+                                Some('+') => {
+                                    i32::try_from(unsigned_millis).map(SeekDirection::Relative)
+                                }
+                                Some('-') => i32::try_from(unsigned_millis)
+                                    .map(|millis| SeekDirection::Relative(-millis)),
+                                _ => Ok(SeekDirection::Absolute(unsigned_millis)),
+                            }
+                            .map_err(|_| E::ArgParseError {
+                                arg: duration_raw,
+                                err: "Duration value too large".into(),
+                            })?
                         }
-                        _ => arg,
                     };
-                    let unsigned_millis = match duration_raw.parse() {
-                        // accept raw milliseconds
-                        Ok(millis) => millis,
-                        Err(_) => parse_duration::parse(&duration_raw) // accept fancy duration
-                            .map_err(|err| E::ArgParseError {
-                                arg: duration_raw.clone(),
-                                err: err.to_string(),
-                            })
-                            .and_then(|dur| {
-                                dur.as_millis().try_into().map_err(|_| E::ArgParseError {
-                                    arg: duration_raw.clone(),
-                                    err: "Duration value too large".into(),
-                                })
-                            })?,
-                    };
-                    let seek_direction = match first_char {
-                        // handle i32::MAX < unsigned_millis < u32::MAX gracefully
-                        Some('+') => i32::try_from(unsigned_millis).map(SeekDirection::Relative),
-                        Some('-') => i32::try_from(unsigned_millis)
-                            .map(|millis| SeekDirection::Relative(-millis)),
-                        _ => Ok(SeekDirection::Absolute(unsigned_millis)),
-                    }
-                    .map_err(|_| E::ArgParseError {
-                        arg: duration_raw,
-                        err: "Duration value too large".into(),
-                    })?;
                     Command::Seek(seek_direction)
                 }
                 "volup" => {
@@ -525,6 +887,27 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::Repeat(mode)
                 }
+                "shuffle" if args.first() == Some(&"mode") => {
+                    let accept = || -> Vec<String> {
+                        ShuffleMode::ALL
+                            .iter()
+                            .map(ShuffleMode::to_string)
+                            .collect()
+                    };
+                    let &name = args.get(1).ok_or_else(|| E::InsufficientArgs {
+                        cmd: "shuffle mode".into(),
+                        hint: Some(accept().join("|")),
+                    })?;
+                    let mode = ShuffleMode::ALL
+                        .into_iter()
+                        .find(|mode| mode.to_string() == name)
+                        .ok_or_else(|| E::BadEnumArg {
+                            arg: name.into(),
+                            accept: accept(),
+                            optional: false,
+                        })?;
+                    Command::ShuffleMode(mode)
+                }
                 "shuffle" => {
                     let switch = match args.first().cloned() {
                         Some("on") => Ok(Some(true)),
@@ -538,6 +921,72 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::Shuffle(switch)
                 }
+                "autoplay" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["on".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Autoplay(switch)
+                }
+                "volnorm" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["on".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Volnorm(switch)
+                }
+                "preamp" | "trackgain" => {
+                    let change = match args.first() {
+                        Some(&"reset") => Some(GainChange::Absolute(0.0)),
+                        Some(&db_raw) => {
+                            let db = db_raw.parse::<f64>().map_err(|err| E::ArgParseError {
+                                arg: db_raw.into(),
+                                err: err.to_string(),
+                            })?;
+                            // Like with seeking, a sign makes the change relative.
+                            Some(if db_raw.starts_with(['+', '-']) {
+                                GainChange::Relative(db)
+                            } else {
+                                GainChange::Absolute(db)
+                            })
+                        }
+                        None => None,
+                    };
+                    if command == "preamp" {
+                        Command::Preamp(change)
+                    } else {
+                        Command::TrackGain(change)
+                    }
+                }
+                "device" => Command::Device((!args.is_empty()).then(|| args.join(" "))),
+                "backend" => Command::Backend(args.first().map(|name| name.to_string())),
+                "theme" => Command::Theme(args.first().map(|name| name.to_string())),
+                "abloop" => {
+                    let mode = match args.first().cloned() {
+                        Some("a") => Ok(Some(LoopMode::A)),
+                        Some("b") => Ok(Some(LoopMode::B)),
+                        Some("off") => Ok(Some(LoopMode::Off)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["a".into(), "b".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Loop(mode)
+                }
                 #[cfg(feature = "share_clipboard")]
                 "share" => {
                     let &target_mode_raw = args.first().ok_or(E::InsufficientArgs {
@@ -559,18 +1008,24 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "open" => {
                     let &target_mode_raw = args.first().ok_or(E::InsufficientArgs {
                         cmd: command.into(),
-                        hint: Some("selected|current".into()),
+                        hint: Some("selected|current|<URL>".into()),
                     })?;
-                    let target_mode = match target_mode_raw {
-                        "selected" => Ok(TargetMode::Selected),
-                        "current" => Ok(TargetMode::Current),
+                    let url = SpotifyUrl::from_url(target_mode_raw)
+                        .or_else(|| SpotifyUrl::from_uri(target_mode_raw));
+                    match (target_mode_raw, url) {
+                        (_, Some(url)) => Command::OpenUrl(url),
+                        ("selected", None) => Command::Open(TargetMode::Selected),
+                        ("current", None) => Command::Open(TargetMode::Current),
                         _ => Err(E::BadEnumArg {
                             arg: target_mode_raw.into(),
-                            accept: vec!["selected".into(), "current".into()],
+                            accept: vec![
+                                "selected".into(),
+                                "current".into(),
+                                "a Spotify URL or URI".into(),
+                            ],
                             optional: false,
-                        }),
-                    }?;
-                    Command::Open(target_mode)
+                        })?,
+                    }
                 }
                 "goto" => {
                     let &goto_mode_raw = args.first().ok_or(E::InsufficientArgs {
@@ -686,8 +1141,86 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                 "jump" => Command::Jump(JumpMode::Query(args.join(" "))),
                 "jumpnext" => Command::Jump(JumpMode::Next),
                 "jumpprevious" => Command::Jump(JumpMode::Previous),
-                "help" => Command::Help,
-                "reload" => Command::ReloadConfig,
+                "jumplist" => match args.first().copied() {
+                    Some("back") => Command::JumpList(JumpListMode::Back),
+                    Some("forward") => Command::JumpList(JumpListMode::Forward),
+                    Some(arg) => {
+                        return Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["back".into(), "forward".into()],
+                            optional: false,
+                        });
+                    }
+                    None => {
+                        return Err(E::InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("back|forward".into()),
+                        });
+                    }
+                },
+                "setmark" | "gotomark" => {
+                    let name = match args.first() {
+                        Some(arg) => {
+                            let mut chars = arg.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(name), None) => Some(name),
+                                _ => {
+                                    return Err(E::ArgParseError {
+                                        arg: arg.to_string(),
+                                        err: "A mark is named by a single character".into(),
+                                    });
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    if command == "setmark" {
+                        Command::SetMark(name)
+                    } else {
+                        Command::GotoMark(name)
+                    }
+                }
+                "help" => match args.first().copied() {
+                    None => Command::Help,
+                    Some("keys") => Command::HelpKeys,
+                    Some(arg) => {
+                        return Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["keys".into()],
+                            optional: true,
+                        });
+                    }
+                },
+                "stats" => Command::ShowStats,
+                "lyrics" => Command::ShowLyrics,
+                "download" => Command::Download,
+                "bookmark" => match args.first().copied() {
+                    Some("clear") if args.len() == 1 => Command::ClearBookmarks,
+                    _ => Command::Bookmark((!args.is_empty()).then(|| args.join(" "))),
+                },
+                "nextchapter" => Command::NextChapter,
+                "previouschapter" => Command::PreviousChapter,
+                "downloads" => match args.first().copied() {
+                    None => Command::ShowDownloads,
+                    Some("cancel") => Command::CancelDownloads,
+                    Some(arg) => {
+                        return Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["cancel".into()],
+                            optional: true,
+                        });
+                    }
+                },
+                "smartplaylist" => {
+                    if args.is_empty() {
+                        return Err(E::InsufficientArgs {
+                            cmd: command.into(),
+                            hint: Some("the name of a smart playlist".into()),
+                        });
+                    }
+                    Command::SmartPlaylist(args.join(" "))
+                }
+                "reload" | "reload-config" => Command::ReloadConfig,
                 "noop" => Command::Noop,
                 "insert" => {
                     let insert_source = match args.first().cloned() {
@@ -708,6 +1241,8 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::Insert(insert_source)
                 }
+                #[cfg(feature = "share_clipboard")]
+                "paste" => Command::Paste,
                 "newplaylist" => {
                     if !args.is_empty() {
                         Ok(Command::NewPlaylist(args.join(" ")))
@@ -729,6 +1264,8 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                         "album" => Ok(SortKey::Album),
                         "added" => Ok(SortKey::Added),
                         "artist" => Ok(SortKey::Artist),
+                        "popularity" => Ok(SortKey::Popularity),
+                        "year" => Ok(SortKey::Year),
                         _ => Err(E::BadEnumArg {
                             arg: key_raw.into(),
                             accept: vec![
@@ -737,6 +1274,8 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                                 "album".into(),
                                 "added".into(),
                                 "artist".into(),
+                                "popularity".into(),
+                                "year".into(),
                             ],
                             optional: false,
                         }),
@@ -777,13 +1316,209 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
                     }?;
                     Command::ShowRecommendations(target_mode)
                 }
+                "radio" => {
+                    let &target_mode_raw = args.first().ok_or(E::InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("selected|current".into()),
+                    })?;
+                    let target_mode = match target_mode_raw {
+                        "selected" => Ok(TargetMode::Selected),
+                        "current" => Ok(TargetMode::Current),
+                        _ => Err(E::BadEnumArg {
+                            arg: target_mode_raw.into(),
+                            accept: vec!["selected".into(), "current".into()],
+                            optional: false,
+                        }),
+                    }?;
+                    Command::Radio(target_mode)
+                }
                 "redraw" => Command::Redraw,
                 "exec" => Command::Execute(args.join(" ")),
                 "reconnect" => Command::Reconnect,
-                _ => {
-                    return Err(E::NoSuchCommand {
+                "mixer" => Command::ShowMixer,
+                "credits" => Command::ShowCredits,
+                "duplicates" => Command::Duplicates,
+                "undo" => Command::Undo,
+                "redo" => Command::Redo,
+                "unavailable" => Command::Unavailable,
+                "discography" => Command::Discography,
+                "group" => match args.first().cloned() {
+                    Some("album") => Command::Group(Some(GroupBy::Album)),
+                    Some("artist") => Command::Group(Some(GroupBy::Artist)),
+                    Some("none") | None => Command::Group(None),
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["album".into(), "artist".into(), "none".into()],
+                        optional: true,
+                    })?,
+                },
+                "filter" => {
+                    let query = args.join(" ");
+                    Command::Filter((!query.is_empty()).then_some(query))
+                }
+                "mark" => match args.first().cloned() {
+                    Some("range") => Command::Mark(MarkMode::Range),
+                    Some("clear") => Command::Mark(MarkMode::Clear),
+                    None => Command::Mark(MarkMode::Toggle),
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["range".into(), "clear".into()],
+                        optional: true,
+                    })?,
+                },
+                "contributor" => {
+                    let name = args.join(" ");
+                    Command::Contributor((!name.is_empty()).then_some(name))
+                }
+                "merge" => {
+                    let mode = match args.first().cloned() {
+                        Some(arg) => arg.parse().map(Some).map_err(|_| E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec![
+                                "keep_first".into(),
+                                "keep_all".into(),
+                                "interleave".into(),
+                            ],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Merge(mode)
+                }
+                "offline" => {
+                    let switch = match args.first().cloned() {
+                        Some("on") => Ok(Some(true)),
+                        Some("off") => Ok(Some(false)),
+                        Some(arg) => Err(E::BadEnumArg {
+                            arg: arg.into(),
+                            accept: vec!["on".into(), "off".into()],
+                            optional: true,
+                        }),
+                        None => Ok(None),
+                    }?;
+                    Command::Offline(switch)
+                }
+                "debug" => match args.first().cloned() {
+                    Some("dump") => {
+                        let path = args[1..].join(" ");
+                        Ok(Command::DumpJournal((!path.is_empty()).then_some(path)))
+                    }
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["dump".into()],
+                        optional: false,
+                    }),
+                    None => Err(E::InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("dump".into()),
+                    }),
+                }?,
+                "history" => match args.first().cloned() {
+                    Some("export") => {
+                        let path = args[1..].join(" ");
+                        Ok(Command::ExportHistory((!path.is_empty()).then_some(path)))
+                    }
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["export".into()],
+                        optional: false,
+                    }),
+                    None => Err(E::InsufficientArgs {
+                        cmd: command.into(),
+                        hint: Some("export".into()),
+                    }),
+                }?,
+                "folder" => match args.first().cloned() {
+                    Some("new") if args.len() > 1 => Ok(Command::NewFolder(args[1..].join(" "))),
+                    Some("new") => Err(E::InsufficientArgs {
+                        cmd: "folder new".into(),
+                        hint: Some("the name of the folder".into()),
+                    }),
+                    Some("move") => {
+                        let name = args[1..].join(" ");
+                        Ok(Command::MoveToFolder((!name.is_empty()).then_some(name)))
+                    }
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["new".into(), "move".into()],
+                        optional: false,
+                    }),
+                    None => Err(E::InsufficientArgs {
                         cmd: command.into(),
-                    });
+                        hint: Some("new|move".into()),
+                    }),
+                }?,
+                "visualizer" => {
+                    let style = match args.first() {
+                        Some(arg) => Some(
+                            VisualizerStyle::ALL
+                                .into_iter()
+                                .find(|style| style.to_string() == *arg)
+                                .ok_or_else(|| E::BadEnumArg {
+                                    arg: arg.to_string(),
+                                    accept: VisualizerStyle::ALL
+                                        .iter()
+                                        .map(|s| s.to_string())
+                                        .collect(),
+                                    optional: true,
+                                })?,
+                        ),
+                        None => None,
+                    };
+                    Command::ShowVisualizer(style)
+                }
+                "cache" => match args.first().cloned() {
+                    Some("clear") => {
+                        // Without a kind, only the API responses are cleared, as before there
+                        // were kinds.
+                        let kind = match args.get(1) {
+                            Some(arg) => CacheKind::ALL
+                                .into_iter()
+                                .find(|kind| kind.to_string() == *arg)
+                                .ok_or_else(|| E::BadEnumArg {
+                                    arg: arg.to_string(),
+                                    accept: CacheKind::ALL.iter().map(|k| k.to_string()).collect(),
+                                    optional: true,
+                                })?,
+                            None => CacheKind::Api,
+                        };
+                        Ok(Command::ClearCache(kind))
+                    }
+                    Some(arg) => Err(E::BadEnumArg {
+                        arg: arg.into(),
+                        accept: vec!["clear".into()],
+                        optional: true,
+                    }),
+                    None => Ok(Command::ShowCache),
+                }?,
+                #[cfg(feature = "scripting")]
+                _ if SCRIPT_COMMANDS.read().unwrap().contains(command) => Command::Script(
+                    command.to_string(),
+                    args.iter().map(|arg| arg.to_string()).collect(),
+                ),
+                _ => {
+                    let alias = USER_ALIASES.read().unwrap().get(command).cloned();
+                    let Some(alias) = alias else {
+                        return Err(E::NoSuchCommand {
+                            cmd: command.into(),
+                        });
+                    };
+                    if let Some(arg) = args.first() {
+                        return Err(E::ArgParseError {
+                            arg: arg.to_string(),
+                            err: format!("the alias {command} takes no arguments"),
+                        });
+                    }
+                    if expanding.iter().any(|name| name == command) {
+                        return Err(E::RecursiveAlias {
+                            alias: command.into(),
+                        });
+                    }
+                    expanding.push(command.to_string());
+                    let alias_commands = parse_expanding(&alias, expanding);
+                    expanding.pop();
+                    commands.extend(alias_commands?);
+                    continue;
                 }
             };
             commands.push(command);
@@ -791,3 +1526,64 @@ pub fn parse(input: &str) -> Result<Vec<Command>, CommandParseError> {
     }
     Ok(commands)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_aliases() {
+        let aliases = [
+            ("fav", "save; add"),
+            ("twice", "fav; next"),
+            ("loop1", "loop2"),
+            ("loop2", "loop1"),
+            ("next", "previous"),
+        ];
+        let errors = set_user_aliases(
+            aliases
+                .into_iter()
+                .map(|(name, commands)| (name.to_string(), commands.to_string()))
+                .collect(),
+        );
+        assert_eq!(errors.len(), 3);
+
+        let parsed = parse("twice; stop").unwrap();
+        let names: Vec<String> = parsed.iter().map(|c| c.to_string()).collect();
+        assert_eq!(names, vec!["save", "add", "next", "stop"]);
+        assert!(matches!(
+            parse("loop1"),
+            Err(CommandParseError::RecursiveAlias { .. })
+        ));
+        assert!(parse("fav now").is_err());
+    }
+
+    #[test]
+    fn test_open_url() {
+        let open = |arg: &str| parse(&format!("open {arg}")).map(|c| c[0].to_string());
+        assert_eq!(open("selected").unwrap(), "open selected");
+        assert_eq!(
+            open("spotify:album:4aawyAB9vmqN3uQ7FjRGTy").unwrap(),
+            "open https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy"
+        );
+        assert_eq!(
+            open("https://open.spotify.com/intl-de/track/4uLU6hMCjMI75M1A2tKUQC?si=1").unwrap(),
+            "open https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC"
+        );
+        assert!(open("https://example.com/track/4uLU6hMCjMI75M1A2tKUQC").is_err());
+    }
+
+    #[test]
+    fn test_seek() {
+        let seek = |arg: &str| parse(&format!("seek {arg}")).map(|c| c[0].to_string());
+        assert_eq!(seek("2:30").unwrap(), "seek 150000");
+        assert_eq!(seek("1:02:30").unwrap(), "seek 3750000");
+        assert_eq!(seek("50%").unwrap(), "seek 50%");
+        assert_eq!(seek("+step").unwrap(), "seek +step");
+        assert_eq!(seek("-3largestep").unwrap(), "seek -3largestep");
+        assert_eq!(seek("+10s").unwrap(), "seek +10000");
+        assert_eq!(seek("150%").unwrap(), "seek 100%");
+        assert!(seek("x%").is_err());
+        assert!(seek("2:x").is_err());
+    }
+}
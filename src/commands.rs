@@ -1,25 +1,57 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use crate::application::UserData;
+use crate::caches::{self, CacheKind};
+use crate::chapters;
 use crate::command::{
-    Command, GotoMode, JumpMode, MoveAmount, MoveMode, SeekDirection, ShiftMode, TargetMode, parse,
+    self, Command, GainChange, GotoMode, JumpListMode, JumpMode, LoopMode, MarkMode, MoveAmount,
+    MoveMode, PaneMode, SeekDirection, ShiftMode, TargetMode, parse,
 };
-use crate::config::{Config, user_configuration_directory};
+use crate::config::{self, Config, ConfigValues, user_configuration_directory};
+use crate::downloads;
 use crate::events::EventManager;
 use crate::ext_traits::CursiveExt;
+use crate::gain;
+use crate::journal;
 use crate::library::Library;
-use crate::queue::{Queue, RepeatSetting};
-use crate::spotify::{Spotify, VOLUME_PERCENT};
+use crate::model::{
+    album::Album, artist::Artist, episode::Episode, playlist::Playlist, show::Show, track::Track,
+};
+use crate::play_history;
+use crate::queue::{AbLoop, Queue, RepeatSetting};
+#[cfg(feature = "share_clipboard")]
+use crate::sharing::read_share;
+use crate::smart_playlist::SmartPlaylist;
+use crate::spotify::UriType;
+use crate::spotify::{Spotify, VOLUME_PERCENT, backend_names};
+use crate::spotify_url::SpotifyUrl;
+use crate::themes;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::ui::caches::CacheView;
+#[cfg(feature = "share_clipboard")]
+use crate::ui::contextmenu::ShareMenu;
 use crate::ui::contextmenu::{
-    AddToPlaylistMenu, ContextMenu, SelectArtistActionMenu, SelectArtistMenu,
+    AddToPlaylistMenu, ContextMenu, RestorePlaylistMenu, SelectArtistActionMenu, SelectArtistMenu,
+    TrackInfoMenu,
 };
-use crate::ui::help::HelpView;
+use crate::ui::devices;
+use crate::ui::downloads::DownloadsView;
+use crate::ui::help::{self, HelpView};
 use crate::ui::layout::Layout;
+use crate::ui::listview::ListView;
+use crate::ui::lyrics::LyricsView;
+use crate::ui::mixer::MixerView;
 use crate::ui::modal::Modal;
 use crate::ui::search_results::SearchResultsView;
+use crate::ui::stats::StatsView;
+use crate::ui::tune_recommendations::TuneRecommendationsDialog;
+use crate::ui::visualizer::VisualizerView;
+use crate::ui::whichkey::{KeySequence, WhichKeyView};
+use crate::undo;
+use crate::utils::{format_size, ms_to_hms};
 use cursive::Cursive;
 use cursive::event::{Event, Key};
 use cursive::traits::View;
@@ -35,9 +67,23 @@ pub enum CommandResult {
     Ignored,
 }
 
+/// Pass `cmd` on to the share dialog, if it is open.
+#[cfg(feature = "share_clipboard")]
+fn share_menu_command(s: &mut Cursive, cmd: &Command) -> Option<Result<CommandResult, String>> {
+    let mut share = s.find_name::<ShareMenu>("sharemenu")?;
+    Some(share.on_command(s, cmd))
+}
+
+#[cfg(not(feature = "share_clipboard"))]
+fn share_menu_command(_s: &mut Cursive, _cmd: &Command) -> Option<Result<CommandResult, String>> {
+    None
+}
+
 pub struct CommandManager {
     aliases: HashMap<String, String>,
     bindings: RefCell<HashMap<String, Vec<Command>>>,
+    /// The events the keybindings were registered for, to remove them again.
+    registered: RefCell<Vec<Event>>,
     spotify: Spotify,
     queue: Arc<Queue>,
     library: Arc<Library>,
@@ -53,10 +99,14 @@ impl CommandManager {
         config: Arc<Config>,
         events: EventManager,
     ) -> Self {
-        let bindings = RefCell::new(Self::get_bindings(&config));
+        Self::load_aliases(&config);
+        #[cfg(feature = "scripting")]
+        crate::scripting::start(&config, queue.clone(), library.clone(), events.clone());
+        let (bindings, _) = Self::get_bindings(&config);
         Self {
             aliases: HashMap::new(),
-            bindings,
+            bindings: RefCell::new(bindings),
+            registered: RefCell::new(Vec::new()),
             spotify,
             queue,
             library,
@@ -65,7 +115,20 @@ impl CommandManager {
         }
     }
 
-    pub fn get_bindings(config: &Config) -> HashMap<String, Vec<Command>> {
+    /// Make the aliases of the configuration available as commands. Returns the invalid ones as
+    /// errors.
+    fn load_aliases(config: &Config) -> Vec<String> {
+        let aliases = config.values().aliases.clone().unwrap_or_default();
+        let errors = command::set_user_aliases(aliases);
+        for error in &errors {
+            error!("{error}");
+        }
+        errors
+    }
+
+    /// Build the keybindings from the defaults and the configuration. Bindings that conflict with
+    /// others are left out and returned as errors.
+    pub fn get_bindings(config: &Config) -> (HashMap<String, Vec<Command>>, Vec<String>) {
         let config = config.values();
         let mut kb = if config.default_keybindings.unwrap_or(true) {
             Self::default_keybindings()
@@ -73,11 +136,19 @@ impl CommandManager {
             HashMap::new()
         };
         let custom_bindings: Option<HashMap<String, String>> = config.keybindings.clone();
+        #[cfg_attr(not(feature = "scripting"), allow(unused_mut))]
+        let mut custom_bindings = custom_bindings.unwrap_or_default();
+        #[cfg(feature = "scripting")]
+        for (key, commands) in crate::scripting::keybindings() {
+            custom_bindings.entry(key).or_insert(commands);
+        }
+        let mut custom = HashSet::new();
 
-        for (key, commands) in custom_bindings.unwrap_or_default() {
+        for (key, commands) in custom_bindings {
             match parse(&commands) {
                 Ok(cmds) => {
                     info!("Custom keybinding: {key} -> {cmds:?}");
+                    custom.insert(key.clone());
                     kb.insert(key, cmds);
                 }
                 Err(err) => {
@@ -86,7 +157,56 @@ impl CommandManager {
             }
         }
 
-        kb
+        let leader = Self::leader_key(&config);
+        let conflicts = Self::remove_conflicts(&mut kb, &custom, &leader);
+        for conflict in &conflicts {
+            error!("{conflict}");
+        }
+        (kb, conflicts)
+    }
+
+    /// Remove the keybindings that are the same as or a prefix of another one, as the longer one
+    /// could never be typed. Custom bindings replace default ones, between the others the shorter
+    /// binding wins. Returns the custom bindings that were removed.
+    fn remove_conflicts(
+        kb: &mut HashMap<String, Vec<Command>>,
+        custom: &HashSet<String>,
+        leader: &str,
+    ) -> Vec<String> {
+        let mut keys: Vec<(String, Vec<Event>)> = kb
+            .keys()
+            .filter_map(|key| Some((key.clone(), Self::parse_sequence(key, leader)?)))
+            .collect();
+        keys.sort_by_key(|(key, events)| (!custom.contains(key), events.len(), key.clone()));
+
+        let mut kept: Vec<(String, Vec<Event>)> = Vec::new();
+        let mut conflicts = Vec::new();
+        for (key, events) in keys {
+            let conflict = kept.iter().find(|(_, other)| {
+                let len = events.len().min(other.len());
+                events[..len] == other[..len]
+            });
+            let Some((other, _)) = conflict else {
+                kept.push((key, events));
+                continue;
+            };
+            if custom.contains(&key) {
+                conflicts.push(format!(
+                    "Keybinding \"{key}\" conflicts with \"{other}\" and is ignored"
+                ));
+            } else {
+                debug!("Default keybinding \"{key}\" replaced by \"{other}\"");
+            }
+            kb.remove(&key);
+        }
+        conflicts
+    }
+
+    fn leader_key(config: &ConfigValues) -> String {
+        config
+            .leader_key
+            .clone()
+            .unwrap_or_else(|| config::DEFAULT_LEADER_KEY.to_string())
     }
 
     pub fn register_aliases<S: Into<String>>(&mut self, name: S, aliases: Vec<S>) {
@@ -110,19 +230,7 @@ impl CommandManager {
         match cmd {
             Command::Noop => Ok(None),
             Command::Quit => {
-                let queue = self.queue.queue.read().unwrap();
-                self.config.with_state_mut(move |s| {
-                    debug!(
-                        "saving state, {} items, current track: {:?}",
-                        queue.len(),
-                        self.queue.get_current_index()
-                    );
-                    s.queuestate.queue.clone_from(&queue);
-                    s.queuestate.random_order = self.queue.get_random_order();
-                    s.queuestate.current_track = self.queue.get_current_index();
-                    s.queuestate.track_progress = self.spotify.get_current_progress();
-                });
-                self.config.save_state();
+                self.queue.save_state();
                 s.quit();
                 Ok(None)
             }
@@ -152,7 +260,7 @@ impl CommandManager {
                 let confirmation = Dialog::text("Clear queue?")
                     .button("Yes", move |s| {
                         s.pop_layer();
-                        queue.clear()
+                        undo::clear_queue(&queue)
                     })
                     .dismiss_button("No");
                 s.add_layer(Modal::new(confirmation));
@@ -171,6 +279,133 @@ impl CommandManager {
                 self.queue.set_shuffle(mode);
                 Ok(None)
             }
+            Command::ShuffleMode(mode) => {
+                self.queue.set_shuffle_mode(*mode);
+                Ok(Some(format!("Shuffle mode: {mode}")))
+            }
+            Command::Loop(mode) => {
+                let mode = mode.unwrap_or_else(|| match self.queue.get_ab_loop() {
+                    None => LoopMode::A,
+                    Some(AbLoop { end: None, .. }) => LoopMode::B,
+                    Some(_) => LoopMode::Off,
+                });
+                let position = |d: Duration| ms_to_hms(d.as_millis() as u32);
+                match mode {
+                    LoopMode::A => {
+                        let start = self.queue.set_loop_start()?;
+                        Ok(Some(format!("Loop starts at {}", position(start))))
+                    }
+                    LoopMode::B => {
+                        let end = self.queue.set_loop_end()?;
+                        let start = self
+                            .queue
+                            .get_ab_loop()
+                            .map_or(Duration::ZERO, |ab| ab.start);
+                        Ok(Some(format!(
+                            "Looping from {} to {}",
+                            position(start),
+                            position(end)
+                        )))
+                    }
+                    LoopMode::Off => {
+                        self.queue.clear_ab_loop();
+                        Ok(Some("Loop off".into()))
+                    }
+                }
+            }
+            Command::Device(name) => {
+                let Some(name) = name else {
+                    let picker = devices::picker(self.queue.clone(), self.config.clone())?;
+                    s.add_layer(picker);
+                    return Ok(None);
+                };
+                let device = (name != "default").then(|| name.clone());
+                self.spotify
+                    .set_output_device(device, self.queue.get_current());
+                Ok(Some(format!("Output device: {name}")))
+            }
+            Command::Theme(name) => {
+                let Some(name) = name else {
+                    s.add_layer(crate::ui::themes::picker(self.config.clone()));
+                    return Ok(None);
+                };
+                let picked = if name == themes::DEFAULT {
+                    None
+                } else {
+                    // Check the theme file before switching to it.
+                    themes::load(name)?;
+                    Some(name.clone())
+                };
+                self.config
+                    .with_state_mut(|state| state.theme.clone_from(&picked));
+                s.set_theme(self.config.build_theme());
+                Ok(Some(format!("Theme: {name}")))
+            }
+            Command::Backend(name) => {
+                let Some(name) = name else {
+                    let active = self.spotify.backend_name();
+                    let backends: Vec<String> = backend_names()
+                        .into_iter()
+                        .map(|backend| {
+                            if Some(backend) == active {
+                                format!("{backend} (in use)")
+                            } else {
+                                backend.to_string()
+                            }
+                        })
+                        .collect();
+                    return Ok(Some(format!("Audio backends: {}", backends.join(", "))));
+                };
+                let backend = (name != "default").then(|| name.clone());
+                self.spotify
+                    .set_backend(backend, self.queue.get_current())?;
+                Ok(Some(format!("Audio backend: {name}")))
+            }
+            Command::Volnorm(mode) => {
+                let volnorm = mode.unwrap_or_else(|| !self.config.gain_settings().volnorm);
+                self.config
+                    .with_state_mut(|state| state.mixer.volnorm = Some(volnorm));
+                self.spotify.restart_player(self.queue.get_current());
+                Ok(Some(
+                    if volnorm {
+                        "Volume normalisation on"
+                    } else {
+                        "Volume normalisation off"
+                    }
+                    .into(),
+                ))
+            }
+            Command::Preamp(change) => {
+                let mut db = self.config.gain_settings().preamp;
+                if let Some(change) = change {
+                    db = gain::adjust(db, change);
+                    self.config
+                        .with_state_mut(|state| state.mixer.preamp = Some(db));
+                    self.spotify.update_gain(self.queue.get_current().as_ref());
+                }
+                Ok(Some(format!("Preamp: {}", gain::format_db(db))))
+            }
+            Command::TrackGain(change) => {
+                let current = self.queue.get_current().ok_or("Nothing is playing")?;
+                let storage = self.config.storage();
+                let mut db = gain::track_offset(storage.as_ref(), &current.uri());
+                if let Some(change) = change {
+                    db = gain::adjust(db, change);
+                    gain::set_track_offset(storage.as_ref(), &current.uri(), db);
+                    self.spotify.update_gain(Some(&current));
+                }
+                Ok(Some(format!(
+                    "Volume of {current}: {}",
+                    gain::format_db(db)
+                )))
+            }
+            Command::Autoplay(mode) => {
+                let mode = mode.unwrap_or_else(|| !self.queue.get_autoplay());
+                self.queue.set_autoplay(mode);
+                Ok(Some(
+                    if mode { "Autoplay on" } else { "Autoplay off" }.into(),
+                ))
+            }
             Command::Repeat(mode) => {
                 let mode = mode.unwrap_or_else(|| match self.queue.get_repeat() {
                     RepeatSetting::None => RepeatSetting::RepeatPlaylist,
@@ -182,9 +417,8 @@ impl CommandManager {
                 Ok(None)
             }
             Command::Seek(direction) => {
-                match *direction {
-                    SeekDirection::Relative(rel) => self.spotify.seek_relative(rel),
-                    SeekDirection::Absolute(abs) => self.spotify.seek(abs),
+                if let Some(playable) = self.queue.get_current() {
+                    self.spotify.seek_to(direction, playable.duration());
                 }
                 Ok(None)
             }
@@ -210,6 +444,93 @@ impl CommandManager {
                 s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
                 Ok(None)
             }
+            Command::HelpKeys => {
+                // Asking again while the keys are shown opens the full help.
+                if help::close_keys(s) {
+                    return self.handle_default_commands(s, &Command::Help);
+                }
+                let contexts = s
+                    .call_on_name("main", |v: &mut Layout| v.help_contexts())
+                    .unwrap_or_default();
+                HelpView::show_keys(s, &self.bindings.borrow(), contexts);
+                Ok(None)
+            }
+            Command::ShowDownloads => {
+                let view = Box::new(DownloadsView::new(self.config.clone()));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
+            Command::CancelDownloads => {
+                let cancelled = downloads::cancel();
+                Ok(Some(format!("Cancelled {cancelled} downloads")))
+            }
+            Command::Bookmark(title) => {
+                let current = self.queue.get_current().ok_or("Nothing is playing")?;
+                let position = self.spotify.get_current_progress().as_millis() as u32;
+                let storage = self.config.storage();
+                let title =
+                    chapters::add_bookmark(storage.as_ref(), &current, position, title.clone());
+                Ok(Some(format!("Added {title} at {}", ms_to_hms(position))))
+            }
+            Command::ClearBookmarks => {
+                let current = self.queue.get_current().ok_or("Nothing is playing")?;
+                let removed = chapters::clear_bookmarks(self.config.storage().as_ref(), &current);
+                Ok(Some(format!("Removed {removed} bookmarks")))
+            }
+            Command::NextChapter | Command::PreviousChapter => {
+                let current = self.queue.get_current().ok_or("Nothing is playing")?;
+                let markers = chapters::markers(self.config.storage().as_ref(), &current);
+                let position = self.spotify.get_current_progress().as_millis() as u32;
+                let marker = if let Command::NextChapter = cmd {
+                    chapters::next(&markers, position)
+                } else {
+                    chapters::previous(&markers, position)
+                };
+                match marker {
+                    Some(marker) => {
+                        self.spotify.seek(marker.position);
+                        Ok(Some(marker.title.clone()))
+                    }
+                    None if markers.is_empty() => Err("No chapters or bookmarks".into()),
+                    None => Ok(None),
+                }
+            }
+            Command::ShowLyrics => {
+                let view =
+                    LyricsView::new(self.queue.clone(), self.config.clone(), s.cb_sink().clone());
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+                Ok(None)
+            }
+            Command::ShowVisualizer(style) => {
+                let values = self.config.values();
+                if !values.visualizer.unwrap_or(false) {
+                    return Err(
+                        "The visualizer is disabled, enable it with `visualizer = true`".into(),
+                    );
+                }
+                let style = style.or(values.visualizer_style).unwrap_or_default();
+                let tap = self.spotify.audio_tap().clone();
+                let view = VisualizerView::new(tap, style, s.cb_sink().clone());
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+                Ok(None)
+            }
+            Command::ShowStats => {
+                let view = Box::new(StatsView::new(self.config.clone()));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
+            Command::SmartPlaylist(name) => {
+                let playlist = SmartPlaylist::find(&self.config, name)?;
+                let tracks = playlist.tracks(&self.library)?;
+                let view = ListView::new(
+                    Arc::new(RwLock::new(tracks)),
+                    self.queue.clone(),
+                    self.library.clone(),
+                )
+                .with_title(&playlist.name);
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+                Ok(None)
+            }
             Command::ReloadConfig => {
                 self.config.reload().map_err(|_| {
                     format!(
@@ -228,10 +549,17 @@ impl CommandManager {
                 s.set_theme(theme);
 
                 // update bindings
+                let mut errors = Self::load_aliases(&self.config);
                 self.unregister_keybindings(s);
-                self.bindings.replace(Self::get_bindings(&self.config));
+                let (bindings, conflicts) = Self::get_bindings(&self.config);
+                self.bindings.replace(bindings);
                 self.register_keybindings(s);
-                Ok(None)
+                errors.extend(conflicts);
+                if errors.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(errors.join(", "))
+                }
             }
             Command::NewPlaylist(name) => {
                 match self.spotify.api.create_playlist(name, None, None) {
@@ -261,17 +589,15 @@ impl CommandManager {
             }
             Command::Logout => {
                 self.spotify.shutdown();
-
-                let mut credentials_path = crate::config::cache_path("librespot");
-                credentials_path.push("credentials.json");
-                std::fs::remove_file(credentials_path).unwrap();
-
+                crate::authentication::logout()?;
+                // ncspot starts over with the login once the user interface is closed.
                 s.quit();
                 Ok(None)
             }
             Command::Execute(cmd) => {
                 log::info!("Executing command: {cmd}");
-                let cmd = std::ffi::CString::new(cmd.clone()).unwrap();
+                let cmd = std::ffi::CString::new(cmd.clone())
+                    .map_err(|_| "The command can't contain a NUL byte".to_string())?;
                 let result = unsafe { libc::system(cmd.as_ptr()) };
                 log::info!("Exit code: {result}");
                 Ok(None)
@@ -284,10 +610,10 @@ impl CommandManager {
                 if let Some(track) = self.queue.get_current()
                     && let Some(track) = track.track()
                 {
-                    let dialog = ContextMenu::add_track_dialog(
+                    let dialog = ContextMenu::add_tracks_dialog(
                         self.library.clone(),
                         self.queue.get_spotify(),
-                        track,
+                        vec![track],
                     );
                     s.add_layer(dialog);
                 }
@@ -299,6 +625,121 @@ impl CommandManager {
                 }
                 Ok(None)
             }
+            Command::ShowMixer => {
+                let mixer = MixerView::new(self.queue.clone(), self.config.clone());
+                s.add_layer(mixer);
+                Ok(None)
+            }
+            Command::ShowCredits => {
+                let track = self
+                    .queue
+                    .get_current()
+                    .and_then(|playable| playable.track())
+                    .ok_or("No track is playing")?;
+                ContextMenu::show_credits(s, self.spotify.clone(), &track);
+                Ok(None)
+            }
+            #[cfg(feature = "scripting")]
+            Command::Script(name, args) => {
+                crate::scripting::run_command(name, args);
+                Ok(None)
+            }
+            Command::Undo => undo::undo(&self.library, &self.queue).map(Some),
+            Command::Redo => undo::redo(&self.library, &self.queue).map(Some),
+            Command::ShowCache => {
+                let view = Box::new(CacheView::new(self.config.clone()));
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                Ok(None)
+            }
+            Command::ClearCache(CacheKind::Api) => {
+                let removed = self
+                    .spotify
+                    .api
+                    .clear_cache()
+                    .map_err(|e| format!("Could not clear the cache: {e}"))?;
+                Ok(Some(format!("Removed {removed} cached responses")))
+            }
+            Command::ClearCache(kind) => {
+                let removed = caches::clear(*kind);
+                if *kind == CacheKind::Audio {
+                    downloads::forget_all(self.config.storage().as_ref());
+                }
+                Ok(Some(format!("Removed {} of {kind}", format_size(removed))))
+            }
+            Command::Offline(mode) => {
+                let offline = mode.unwrap_or_else(|| !self.spotify.api.is_offline());
+                self.spotify.api.set_offline(offline);
+                if !offline {
+                    self.library.update_library();
+                }
+                Ok(Some(
+                    if offline {
+                        "Offline, only cached data is shown"
+                    } else {
+                        "Online"
+                    }
+                    .into(),
+                ))
+            }
+            Command::ExportHistory(path) => {
+                let path = path.as_ref().map(PathBuf::from).unwrap_or_else(|| {
+                    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                    config::cache_path(&format!("play-history-{timestamp}.csv"))
+                });
+                let plays =
+                    play_history::export(self.config.storage().as_ref(), &path.to_string_lossy())?;
+                Ok(Some(format!("{plays} plays written to {}", path.display())))
+            }
+            Command::DumpJournal(path) => {
+                let path = path.as_ref().map(PathBuf::from).unwrap_or_else(|| {
+                    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                    config::cache_path(&format!("journal-dump-{timestamp}.log"))
+                });
+                journal::dump(&path).map_err(|e| format!("Could not dump the journal: {e}"))?;
+                Ok(Some(format!("Journal written to {}", path.display())))
+            }
+            #[cfg(feature = "share_clipboard")]
+            Command::Paste => {
+                let text =
+                    read_share().map_err(|e| format!("Could not read the clipboard: {e}"))?;
+                let urls = SpotifyUrl::extract_all(text);
+                if urls.is_empty() {
+                    return Err("No Spotify links found in the clipboard".into());
+                }
+
+                let mut items = self.resolve_spotify_urls(&urls);
+                if items.is_empty() {
+                    return Err("Could not resolve any of the pasted Spotify links".into());
+                }
+
+                // A single collection is opened, everything else goes straight into the queue.
+                if items.len() == 1
+                    && let Some(view) = items[0].open(self.queue.clone(), self.library.clone())
+                {
+                    s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                    return Ok(None);
+                }
+
+                for item in items.iter_mut() {
+                    item.queue(&self.queue);
+                }
+                Ok(Some(format!("Queued {} pasted items", items.len())))
+            }
+            Command::OpenUrl(url) => {
+                let mut items = self.resolve_spotify_urls(std::slice::from_ref(url));
+                let Some(item) = items.first_mut() else {
+                    return Err(format!("Could not open {url}"));
+                };
+
+                // Collections get their own view, tracks and episodes are queued.
+                if let Some(view) = item.open(self.queue.clone(), self.library.clone()) {
+                    s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                    return Ok(None);
+                }
+
+                item.queue(&self.queue);
+                Ok(Some(format!("Queued {}", item.display_left(&self.library))))
+            }
 
             Command::Queue
             | Command::PlayNext
@@ -308,14 +749,31 @@ impl CommandManager {
             | Command::Add
             | Command::Delete
             | Command::Focus(_)
+            | Command::Split(_)
+            | Command::Pane(_)
             | Command::Back
             | Command::Open(_)
             | Command::Goto(_)
             | Command::Move(_, _)
             | Command::Shift(_, _)
             | Command::Jump(_)
+            | Command::JumpList(_)
+            | Command::SetMark(_)
+            | Command::GotoMark(_)
             | Command::Insert(_)
             | Command::ShowRecommendations(_)
+            | Command::Radio(_)
+            | Command::Download
+            | Command::Merge(_)
+            | Command::Duplicates
+            | Command::Unavailable
+            | Command::Discography
+            | Command::NewFolder(_)
+            | Command::MoveToFolder(_)
+            | Command::Contributor(_)
+            | Command::Group(_)
+            | Command::Filter(_)
+            | Command::Mark(_)
             | Command::Sort(_, _) => Err(format!(
                 "The command \"{}\" is unsupported in this view",
                 cmd.basename()
@@ -328,6 +786,60 @@ impl CommandManager {
         }
     }
 
+    /// Resolve `urls` into list items, preserving their order. Tracks and episodes are fetched in
+    /// batches, other items one by one. Links that can't be resolved are skipped.
+    fn resolve_spotify_urls(&self, urls: &[SpotifyUrl]) -> Vec<Box<dyn ListItem>> {
+        let ids_of = |uri_type: UriType| -> Vec<&str> {
+            urls.iter()
+                .filter(|url| url.uri_type == uri_type)
+                .map(|url| url.id.as_str())
+                .collect()
+        };
+
+        let track_ids = ids_of(UriType::Track);
+        let mut tracks: HashMap<String, Track> = HashMap::new();
+        if !track_ids.is_empty()
+            && let Ok(fetched) = self.spotify.api.tracks(&track_ids)
+        {
+            for track in fetched.iter().map(Track::from) {
+                if let Some(id) = track.id.clone() {
+                    tracks.insert(id, track);
+                }
+            }
+        }
+
+        let episode_ids = ids_of(UriType::Episode);
+        let mut episodes: HashMap<String, Episode> = HashMap::new();
+        if !episode_ids.is_empty()
+            && let Ok(fetched) = self.spotify.api.episodes(&episode_ids)
+        {
+            for episode in fetched.iter().map(Episode::from) {
+                episodes.insert(episode.id.clone(), episode);
+            }
+        }
+
+        let api = &self.spotify.api;
+        urls.iter()
+            .filter_map(|url| match url.uri_type {
+                UriType::Track => tracks.get(&url.id).map(|t| t.as_listitem()),
+                UriType::Episode => episodes.get(&url.id).map(|e| e.as_listitem()),
+                UriType::Album => api
+                    .album(&url.id)
+                    .ok()
+                    .map(|a| Album::from(&a).as_listitem()),
+                UriType::Artist => api
+                    .artist(&url.id)
+                    .ok()
+                    .map(|a| Artist::from(&a).as_listitem()),
+                UriType::Playlist => api
+                    .playlist(&url.id)
+                    .ok()
+                    .map(|p| Playlist::from(&p).as_listitem()),
+                UriType::Show => api.show(&url.id).ok().map(|s| Show::from(&s).as_listitem()),
+            })
+            .collect()
+    }
+
     fn handle_callbacks(&self, s: &mut Cursive, cmd: &Command) -> Result<Option<String>, String> {
         let local = if let Some(mut contextmenu) = s.find_name::<ContextMenu>("contextmenu") {
             contextmenu.on_command(s, cmd)?
@@ -339,6 +851,20 @@ impl CommandManager {
             s.find_name::<SelectArtistActionMenu>("selectartistaction")
         {
             select_artist_action.on_command(s, cmd)?
+        } else if let Some(mut restore_playlist) =
+            s.find_name::<RestorePlaylistMenu>("restoreplaylist")
+        {
+            restore_playlist.on_command(s, cmd)?
+        } else if let Some(mut track_info) = s.find_name::<TrackInfoMenu>("trackinfo") {
+            track_info.on_command(s, cmd)?
+        } else if let Some(result) = share_menu_command(s, cmd) {
+            result?
+        } else if let Some(mut mixer) = s.find_name::<MixerView>("mixer") {
+            mixer.on_command(s, cmd)?
+        } else if let Some(mut tune_recommendations) =
+            s.find_name::<TuneRecommendationsDialog>("tunerecommendations")
+        {
+            tune_recommendations.on_command(s, cmd)?
         } else {
             s.on_layout(|siv, mut l| l.on_command(siv, cmd))?
         };
@@ -385,25 +911,51 @@ impl CommandManager {
     }
 
     pub fn unregister_keybindings(&self, cursive: &mut Cursive) {
-        let kb = self.bindings.borrow();
-
-        for (k, _v) in kb.iter() {
-            if let Some(binding) = Self::parse_keybinding(k) {
-                cursive.clear_global_callbacks(binding);
-            }
+        for binding in self.registered.take() {
+            cursive.clear_global_callbacks(binding);
         }
     }
 
     pub fn register_keybindings(&self, cursive: &mut Cursive) {
         let kb = self.bindings.borrow();
+        let (leader, timeout) = {
+            let config = self.config.values();
+            let timeout = config
+                .keybinding_timeout
+                .unwrap_or(config::DEFAULT_KEYBINDING_TIMEOUT);
+            (
+                Self::leader_key(&config),
+                (timeout > 0).then(|| Duration::from_millis(timeout)),
+            )
+        };
+        let mut registered = self.registered.borrow_mut();
 
+        // Keybindings of several keys open the popup with their completions on the first key.
+        let mut sequences: HashMap<Event, Vec<KeySequence>> = HashMap::new();
         for (k, v) in kb.iter() {
-            if let Some(binding) = Self::parse_keybinding(k) {
-                self.register_keybinding(cursive, binding, v.clone());
-            } else {
-                error!("Could not parse keybinding: \"{k}\"");
+            match Self::parse_sequence(k, &leader) {
+                Some(mut keys) if keys.len() == 1 => {
+                    let binding = keys.remove(0);
+                    registered.push(binding.clone());
+                    self.register_keybinding(cursive, binding, v.clone());
+                }
+                Some(keys) => sequences
+                    .entry(keys[0].clone())
+                    .or_default()
+                    .push(KeySequence {
+                        keys,
+                        names: k.split_whitespace().map(str::to_string).collect(),
+                        commands: v.clone(),
+                    }),
+                None => error!("Could not parse keybinding: \"{k}\""),
             }
         }
+        for (first, sequences) in sequences {
+            registered.push(first.clone());
+            cursive.add_global_callback(first, move |s| {
+                WhichKeyView::start(s, sequences.clone(), timeout)
+            });
+        }
     }
 
     fn default_keybindings() -> HashMap<String, Vec<Command>> {
@@ -417,6 +969,8 @@ impl CommandManager {
         kb.insert("<".into(), vec![Command::Previous]);
         kb.insert(">".into(), vec![Command::Next]);
         kb.insert("c".into(), vec![Command::Clear]);
+        kb.insert("u".into(), vec![Command::Undo]);
+        kb.insert("Ctrl+r".into(), vec![Command::Redo]);
         kb.insert(
             "Space".into(),
             vec![
@@ -431,34 +985,58 @@ impl CommandManager {
                 Command::Move(MoveMode::Down, Default::default()),
             ],
         );
+        kb.insert(
+            "v".into(),
+            vec![
+                Command::Mark(MarkMode::Toggle),
+                Command::Move(MoveMode::Down, Default::default()),
+            ],
+        );
+        kb.insert("Shift+v".into(), vec![Command::Mark(MarkMode::Range)]);
         kb.insert("Enter".into(), vec![Command::Play]);
         kb.insert("n".into(), vec![Command::Jump(JumpMode::Next)]);
         kb.insert("Shift+n".into(), vec![Command::Jump(JumpMode::Previous)]);
         kb.insert("s".into(), vec![Command::Save]);
         kb.insert("Ctrl+s".into(), vec![Command::SaveQueue]);
         kb.insert("d".into(), vec![Command::Delete]);
-        kb.insert(
-            "f".into(),
-            vec![Command::Seek(SeekDirection::Relative(1000))],
-        );
-        kb.insert(
-            "b".into(),
-            vec![Command::Seek(SeekDirection::Relative(-1000))],
-        );
+        kb.insert("f".into(), vec![Command::Seek(SeekDirection::Step(1))]);
+        kb.insert("b".into(), vec![Command::Seek(SeekDirection::Step(-1))]);
         kb.insert(
             "Shift+f".into(),
-            vec![Command::Seek(SeekDirection::Relative(10000))],
+            vec![Command::Seek(SeekDirection::LargeStep(1))],
         );
         kb.insert(
             "Shift+b".into(),
-            vec![Command::Seek(SeekDirection::Relative(-10000))],
+            vec![Command::Seek(SeekDirection::LargeStep(-1))],
         );
         kb.insert("+".into(), vec![Command::VolumeUp(1)]);
         kb.insert("]".into(), vec![Command::VolumeUp(5)]);
         kb.insert("-".into(), vec![Command::VolumeDown(1)]);
         kb.insert("[".into(), vec![Command::VolumeDown(5)]);
 
+        kb.insert("Alt+m".into(), vec![Command::ShowMixer]);
+        kb.insert("Shift+l".into(), vec![Command::ShowLyrics]);
+        kb.insert("Alt+f".into(), vec![Command::NextChapter]);
+        kb.insert("Alt+b".into(), vec![Command::PreviousChapter]);
+        kb.insert(
+            "}".into(),
+            vec![Command::TrackGain(Some(GainChange::Relative(1.0)))],
+        );
+        kb.insert(
+            "{".into(),
+            vec![Command::TrackGain(Some(GainChange::Relative(-1.0)))],
+        );
+        kb.insert(
+            "Alt+]".into(),
+            vec![Command::Preamp(Some(GainChange::Relative(1.0)))],
+        );
+        kb.insert(
+            "Alt+[".into(),
+            vec![Command::Preamp(Some(GainChange::Relative(-1.0)))],
+        );
+
         kb.insert("r".into(), vec![Command::Repeat(None)]);
+        kb.insert("Shift+r".into(), vec![Command::Loop(None)]);
         kb.insert("z".into(), vec![Command::Shuffle(None)]);
 
         #[cfg(feature = "share_clipboard")]
@@ -472,8 +1050,21 @@ impl CommandManager {
         kb.insert("F3".into(), vec![Command::Focus("library".into())]);
         #[cfg(feature = "cover")]
         kb.insert("F8".into(), vec![Command::Focus("cover".into())]);
-        kb.insert("?".into(), vec![Command::Help]);
+        kb.insert("?".into(), vec![Command::HelpKeys]);
         kb.insert("Backspace".into(), vec![Command::Back]);
+        kb.insert("`".into(), vec![Command::SetMark(None)]);
+        kb.insert("'".into(), vec![Command::GotoMark(None)]);
+        kb.insert("Ctrl+w".into(), vec![Command::Pane(None)]);
+        kb.insert("Alt+Left".into(), vec![Command::Pane(Some(PaneMode::Left))]);
+        kb.insert(
+            "Alt+Right".into(),
+            vec![Command::Pane(Some(PaneMode::Right))],
+        );
+        kb.insert("Ctrl+o".into(), vec![Command::JumpList(JumpListMode::Back)]);
+        kb.insert(
+            "Alt+o".into(),
+            vec![Command::JumpList(JumpListMode::Forward)],
+        );
 
         kb.insert("o".into(), vec![Command::Open(TargetMode::Selected)]);
         kb.insert("Shift+o".into(), vec![Command::Open(TargetMode::Current)]);
@@ -575,10 +1166,13 @@ impl CommandManager {
         );
 
         #[cfg(feature = "share_clipboard")]
-        kb.insert(
-            "Ctrl+v".into(),
-            vec![Command::Insert(crate::command::InsertSource::Clipboard)],
-        );
+        {
+            kb.insert(
+                "Ctrl+v".into(),
+                vec![Command::Insert(crate::command::InsertSource::Clipboard)],
+            );
+            kb.insert("Alt+v".into(), vec![Command::Paste]);
+        }
 
         kb
     }
@@ -619,6 +1213,21 @@ impl CommandManager {
         }
     }
 
+    /// Parse a keybinding of one or more keys separated by spaces, where `<leader>` stands for the
+    /// key `leader`.
+    fn parse_sequence(kb: &str, leader: &str) -> Option<Vec<Event>> {
+        let keys: Vec<&str> = kb.split_whitespace().collect();
+        if keys.len() < 2 && kb != "<leader>" {
+            return Self::parse_keybinding(kb).map(|binding| vec![binding]);
+        }
+        keys.into_iter()
+            .map(|key| match key {
+                "<leader>" => Self::parse_keybinding(leader),
+                key => Self::parse_keybinding(key),
+            })
+            .collect()
+    }
+
     fn parse_keybinding(kb: &str) -> Option<cursive::event::Event> {
         let mut split = kb.split('+');
         if kb != "+" && split.clone().count() == 2 {
@@ -647,3 +1256,35 @@ impl CommandManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sequence() {
+        let parse = |kb| CommandManager::parse_sequence(kb, "Space");
+        assert_eq!(parse("g"), Some(vec![Event::Char('g')]));
+        assert_eq!(
+            parse("<leader> Shift+p"),
+            Some(vec![Event::Char(' '), Event::Char('P')])
+        );
+        assert_eq!(parse("Foo+a b"), None);
+    }
+
+    #[test]
+    fn test_remove_conflicts() {
+        let mut kb: HashMap<String, Vec<Command>> = ["s", "g", "g g", "s a", "P", "Shift+p"]
+            .into_iter()
+            .map(|key| (key.to_string(), vec![Command::Noop]))
+            .collect();
+        let custom = ["g", "g g", "s a", "P"].map(String::from).into();
+
+        let conflicts = CommandManager::remove_conflicts(&mut kb, &custom, "\\");
+        let mut keys: Vec<&str> = kb.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["P", "g", "s a"]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].contains("\"g g\""));
+    }
+}
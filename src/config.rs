@@ -1,21 +1,41 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
-use std::sync::{RwLock, RwLockReadGuard};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant, SystemTime};
 use std::{fs, process};
 
 use cursive::theme::Theme;
+use librespot_playback::audio_backend;
 use log::{debug, error};
 use ncspot::{CONFIGURATION_FILE_NAME, USER_STATE_FILE_NAME};
 use platform_dirs::AppDirs;
 
-use crate::command::{SortDirection, SortKey};
+use crate::columns::ColumnAlign;
+use crate::command::{GroupBy, SortDirection, SortKey};
+use crate::data_usage::{self, DataUsage};
+use crate::instance;
 use crate::model::playable::Playable;
+use crate::playlist_folders::PlaylistFolder;
+use crate::plugins;
 use crate::queue;
-use crate::serialization::{CBOR, Serializer, TOML};
+use crate::queue_source::QueueSource;
+use crate::serialization::{CBOR, Serializer, TOML, write_atomically};
+use crate::shuffle::ShuffleMode;
+use crate::skip_silence::SkipSilence;
+use crate::state_sync::StateSync;
+use crate::storage::{self, Namespace, Storage};
+use crate::theme::{self, Appearance};
+use crate::themes;
+use crate::visualizer::VisualizerStyle;
 
 pub const CACHE_VERSION: u16 = 1;
 pub const DEFAULT_COMMAND_KEY: char = ':';
+pub const DEFAULT_LEADER_KEY: &str = "\\";
+/// How long to wait for the next key of a keybinding of several keys, in milliseconds.
+pub const DEFAULT_KEYBINDING_TIMEOUT: u64 = 1000;
+/// The screens shown side by side when the layout is split without configured screens.
+pub const DEFAULT_SPLIT: [&str; 2] = ["library", "queue"];
 
 /// The playback state when ncspot is started.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -27,8 +47,19 @@ pub enum PlaybackState {
 }
 
 /// The focussed library tab when ncspot is started.
-#[derive(Clone, Serialize, Deserialize, Debug, Hash, strum_macros::EnumIter)]
+#[derive(
+    Clone,
+    Serialize,
+    Deserialize,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    strum_macros::EnumIter,
+    strum_macros::EnumString,
+)]
 #[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
 pub enum LibraryTab {
     Tracks,
     Albums,
@@ -36,6 +67,8 @@ pub enum LibraryTab {
     Playlists,
     Podcasts,
     Browse,
+    /// Files from the local directories, only shown when some are configured.
+    Local,
 }
 
 /// The format used to represent tracks in a list.
@@ -56,6 +89,31 @@ impl TrackFormat {
     }
 }
 
+/// The layout of the status bar, see [crate::ui::statusbar].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigStatusbar {
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// A column of track lists, see [crate::columns].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigColumn {
+    pub format: String,
+    pub align: Option<ColumnAlign>,
+    pub min_width: Option<usize>,
+    pub max_width: Option<usize>,
+}
+
+/// The columns of the track lists in each kind of view, see [crate::columns].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigColumns {
+    pub playlist: Option<Vec<ConfigColumn>>,
+    pub queue: Option<Vec<ConfigColumn>>,
+    pub search: Option<Vec<ConfigColumn>>,
+    pub album: Option<Vec<ConfigColumn>>,
+}
+
 /// The format used when sending desktop notifications about playback status.
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NotificationFormat {
@@ -72,35 +130,146 @@ impl NotificationFormat {
     }
 }
 
+/// Scripts that are run when something happens, see [crate::hooks].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigHooks {
+    pub on_track_change: Option<String>,
+    pub on_play: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_queue_empty: Option<String>,
+    pub on_auth_failure: Option<String>,
+}
+
+/// The built-in HTTP server, see [crate::http].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigHttp {
+    pub address: Option<String>,
+    pub token: Option<String>,
+}
+
+/// The services the played tracks are submitted to, see [crate::scrobbler].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigScrobbling {
+    pub lastfm: Option<bool>,
+    pub listenbrainz: Option<bool>,
+}
+
+/// The Discord Rich Presence, see [crate::discord].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigDiscord {
+    pub client_id: Option<String>,
+    pub privacy: Option<bool>,
+}
+
+/// The confirmations of dangerous operations, see [crate::ui::confirm].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigConfirm {
+    pub delete_playlist: Option<bool>,
+    pub overwrite_playlist: Option<bool>,
+    /// Deleting playlists with at least this many tracks asks for their name, `0` never does.
+    pub type_name_above: Option<usize>,
+}
+
+/// A playlist of the saved tracks that match rules, see [crate::smart_playlist].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigSmartPlaylist {
+    pub name: String,
+    pub rules: Vec<String>,
+    /// Match the tracks that match any rule instead of all of them.
+    pub any: Option<bool>,
+    /// The name of a playlist to take the tracks from instead of the saved tracks.
+    pub source: Option<String>,
+    pub limit: Option<usize>,
+    /// Write the tracks to a Spotify playlist of the same name every this many hours.
+    pub sync_hours: Option<u64>,
+}
+
 /// The configuration of ncspot.
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct ConfigValues {
     pub command_key: Option<char>,
     pub initial_screen: Option<String>,
+    /// What to show after startup: a screen, a library tab, an item or the last focused view.
+    /// Takes precedence over `initial_screen`.
+    pub startup_view: Option<String>,
+    /// Whether to open the screens and views of the last session again.
+    pub restore_session: Option<bool>,
+    /// The screens of the left and the right pane, to start with a split layout.
+    pub split: Option<[String; 2]>,
     pub default_keybindings: Option<bool>,
     pub keybindings: Option<HashMap<String, String>>,
+    pub mouse: Option<bool>,
+    pub leader_key: Option<String>,
+    pub aliases: Option<HashMap<String, String>>,
+    pub hooks: Option<ConfigHooks>,
+    /// The scripts that may run commands, by the name of their file without `.rhai`.
+    pub writable_scripts: Option<Vec<String>>,
+    /// The hooks of the plugins, see [crate::plugins].
+    #[serde(skip)]
+    pub plugin_hooks: Vec<ConfigHooks>,
+    pub keybinding_timeout: Option<u64>,
+    pub seek_step: Option<u32>,
+    pub seek_step_large: Option<u32>,
+    pub seek_preview: Option<u64>,
+    pub http: Option<ConfigHttp>,
+    pub scrobbling: Option<ConfigScrobbling>,
+    pub discord: Option<ConfigDiscord>,
+    pub smart_playlists: Option<Vec<ConfigSmartPlaylist>>,
     pub theme: Option<ConfigTheme>,
+    pub light_theme: Option<ConfigTheme>,
+    pub theme_schedule: Option<ThemeSchedule>,
     pub use_nerdfont: Option<bool>,
     pub flip_status_indicators: Option<bool>,
     pub audio_cache: Option<bool>,
     pub audio_cache_size: Option<u32>,
+    pub local_directories: Option<Vec<String>>,
+    pub lyrics_provider: Option<String>,
+    pub lyrics_directory: Option<String>,
+    pub visualizer: Option<bool>,
+    pub visualizer_style: Option<VisualizerStyle>,
     pub backend: Option<String>,
     pub backend_device: Option<String>,
+    pub backend_devices: Option<HashMap<String, String>>,
+    pub backend_format: Option<String>,
     pub volnorm: Option<bool>,
     pub volnorm_pregain: Option<f64>,
+    pub volnorm_limiter: Option<bool>,
+    pub preamp: Option<f64>,
     pub notify: Option<bool>,
     pub bitrate: Option<u32>,
     pub gapless: Option<bool>,
+    pub preload_max_size: Option<u32>,
+    pub download_quota: Option<u32>,
     pub shuffle: Option<bool>,
     pub repeat: Option<queue::RepeatSetting>,
     pub cover_max_scale: Option<f32>,
     pub playback_state: Option<PlaybackState>,
+    pub queue_source: Option<QueueSource>,
     pub track_format: Option<TrackFormat>,
+    pub columns: Option<ConfigColumns>,
     pub notification_format: Option<NotificationFormat>,
     pub statusbar_format: Option<String>,
+    pub statusbar: Option<ConfigStatusbar>,
+    pub terminal_title: Option<String>,
     pub library_tabs: Option<Vec<LibraryTab>>,
     pub hide_display_names: Option<bool>,
     pub ap_port: Option<u16>,
+    pub proxy: Option<String>,
+    pub pagination_read_ahead: Option<u32>,
+    pub sync_directory: Option<String>,
+    pub context_volume: Option<bool>,
+    pub api_cache: Option<bool>,
+    pub api_cache_size: Option<u32>,
+    pub cover_cache_size: Option<u32>,
+    pub api_rate_limit: Option<f64>,
+    pub playlist_history: Option<usize>,
+    pub storage_backend: Option<String>,
+    pub confirm: Option<ConfigConfirm>,
+    pub queue_autosave: Option<u64>,
+    pub play_history: Option<bool>,
+    pub autoplay: Option<bool>,
+    pub shuffle_mode: Option<ShuffleMode>,
+    pub skip_silence: Option<SkipSilence>,
 }
 
 /// The ncspot theme.
@@ -116,6 +285,8 @@ pub struct ConfigTheme {
     pub highlight: Option<String>,
     pub highlight_bg: Option<String>,
     pub highlight_inactive_bg: Option<String>,
+    pub highlight_unfocused: Option<String>,
+    pub highlight_unfocused_bg: Option<String>,
     pub error: Option<String>,
     pub error_bg: Option<String>,
     pub statusbar_progress: Option<String>,
@@ -124,10 +295,28 @@ pub struct ConfigTheme {
     pub statusbar_bg: Option<String>,
     pub cmdline: Option<String>,
     pub cmdline_bg: Option<String>,
+    pub tab: Option<String>,
+    pub tab_bg: Option<String>,
+    pub tab_active: Option<String>,
+    pub tab_active_bg: Option<String>,
     pub search_match: Option<String>,
+    pub search_match_bg: Option<String>,
+    /// Colors of single screens like `queue` or `library`, replacing the ones above.
+    pub views: Option<HashMap<String, Self>>,
+}
+
+/// When to use the light theme instead of the regular one.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ThemeSchedule {
+    /// Follow the color scheme preference of the desktop, if it has one.
+    pub follow_desktop: Option<bool>,
+    /// The time the light theme starts, as `HH:MM`.
+    pub light: Option<String>,
+    /// The time the dark theme starts, as `HH:MM`.
+    pub dark: Option<String>,
 }
 
-/// The ordering that is used when representing a playlist.
+/// The ordering that is used when representing a playlist or another list.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SortingOrder {
     pub key: SortKey,
@@ -143,16 +332,102 @@ pub struct QueueState {
     pub queue: Vec<Playable>,
 }
 
+/// Gain settings changed through the mixer. They take precedence over the configuration file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct MixerState {
+    pub volnorm: Option<bool>,
+    pub volnorm_pregain: Option<f64>,
+    pub volnorm_limiter: Option<bool>,
+    pub preamp: Option<f64>,
+}
+
+/// Kinds of content that remember their own volume, as spoken word usually needs a different level
+/// than music.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeContext {
+    Music,
+    Podcast,
+}
+
+/// The effective gain staging of the audio pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GainSettings {
+    /// Whether volume normalisation is enabled.
+    pub volnorm: bool,
+    /// Normalisation pregain in dB.
+    pub volnorm_pregain: f64,
+    /// Whether the dynamic limiter is used during normalisation.
+    pub volnorm_limiter: bool,
+    /// Preamp in dB, applied to the audio whether it is normalised or not.
+    pub preamp: f64,
+}
+
+impl GainSettings {
+    /// Whether the settings differ from the `applied` ones in a way that takes a restart of the
+    /// player, as they are part of its configuration. The preamp is applied right away.
+    pub fn needs_restart(&self, applied: &Self) -> bool {
+        let settings = Self {
+            preamp: applied.preamp,
+            ..self.clone()
+        };
+        settings != *applied
+    }
+}
+
 /// Runtime state that should be persisted accross sessions.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserState {
     pub volume: u16,
     pub shuffle: bool,
+    /// How the queue is shuffled.
+    #[serde(default)]
+    pub shuffle_mode: ShuffleMode,
     pub repeat: queue::RepeatSetting,
+    /// Whether the queue is continued with recommendations when it runs out.
+    #[serde(default)]
+    pub autoplay: bool,
     pub queuestate: QueueState,
     pub playlist_orders: HashMap<String, SortingOrder>,
+    /// The orders of the other list views that keep their order, by view.
+    #[serde(default)]
+    pub view_orders: HashMap<String, SortingOrder>,
+    /// How the tracks of playlists are grouped, by playlist id.
+    #[serde(default)]
+    pub playlist_groups: HashMap<String, GroupBy>,
     pub cache_version: u16,
     pub playback_state: PlaybackState,
+    #[serde(default)]
+    pub mixer: MixerState,
+    /// The audio output device picked with the `device` command, which takes precedence over the
+    /// configuration file.
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// The audio backend picked with the `backend` command, which takes precedence over the
+    /// configuration file.
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// The last volume used for each kind of content.
+    #[serde(default)]
+    pub context_volumes: HashMap<VolumeContext, u16>,
+    /// Whether the onboarding tour was finished or skipped. Users that already have a saved state
+    /// don't need the tour.
+    #[serde(default = "tour_completed_default")]
+    pub tour_completed: bool,
+    /// The data transferred in all sessions.
+    #[serde(default)]
+    pub data_usage: DataUsage,
+    /// The local folders of the playlists tab.
+    #[serde(default)]
+    pub playlist_folders: Vec<PlaylistFolder>,
+    /// The theme file picked with the `theme` command, which takes precedence over the themes of
+    /// the configuration file.
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+fn tour_completed_default() -> bool {
+    true
 }
 
 impl Default for UserState {
@@ -160,11 +435,23 @@ impl Default for UserState {
         Self {
             volume: u16::MAX,
             shuffle: false,
+            shuffle_mode: ShuffleMode::default(),
             repeat: queue::RepeatSetting::None,
+            autoplay: false,
             queuestate: QueueState::default(),
             playlist_orders: HashMap::new(),
+            view_orders: HashMap::new(),
+            playlist_groups: HashMap::new(),
             cache_version: 0,
             playback_state: PlaybackState::Default,
+            mixer: MixerState::default(),
+            output_device: None,
+            backend: None,
+            context_volumes: HashMap::new(),
+            tour_completed: false,
+            data_usage: DataUsage::default(),
+            playlist_folders: Vec::new(),
+            theme: None,
         }
     }
 }
@@ -180,6 +467,10 @@ pub struct Config {
     values: RwLock<ConfigValues>,
     /// Runtime state which can't be edited by the user, read/write.
     state: RwLock<UserState>,
+    /// Where the runtime state and the library caches are persisted.
+    storage: Arc<dyn Storage>,
+    /// Shares the runtime state with other machines, if a sync directory is configured.
+    sync: Option<Mutex<StateSync>>,
 }
 
 impl Config {
@@ -200,20 +491,39 @@ impl Config {
             process::exit(1);
         });
 
-        let mut userstate = {
-            let path = config_path(USER_STATE_FILE_NAME);
-            CBOR.load_or_generate_default(path, || Ok(UserState::default()), true)
-                .expect("could not load user state")
-        };
+        let storage = storage::open(values.storage_backend.as_deref()).unwrap_or_else(|e| {
+            eprintln!("Unable to open the storage backend: {e}");
+            process::exit(1);
+        });
+        let mut userstate = load_state(&*storage);
+
+        // Secondary instances share the machine identifier, only the first one takes part.
+        let sync = values
+            .sync_directory
+            .as_ref()
+            .filter(|_| instance::current().is_none_or(|instance| instance.is_primary()))
+            .map(|directory| {
+                let mut sync = StateSync::open(PathBuf::from(directory), &*storage);
+                sync.apply(&mut userstate);
+                Mutex::new(sync)
+            });
 
         if let Some(shuffle) = values.shuffle {
             userstate.shuffle = shuffle;
         }
 
+        if let Some(shuffle_mode) = values.shuffle_mode {
+            userstate.shuffle_mode = shuffle_mode;
+        }
+
         if let Some(repeat) = values.repeat {
             userstate.repeat = repeat;
         }
 
+        if let Some(autoplay) = values.autoplay {
+            userstate.autoplay = autoplay;
+        }
+
         if let Some(playback_state) = values.playback_state.clone() {
             userstate.playback_state = playback_state;
         }
@@ -222,6 +532,8 @@ impl Config {
             filename,
             values: RwLock::new(values),
             state: RwLock::new(userstate),
+            storage,
+            sync,
         }
     }
 
@@ -250,20 +562,117 @@ impl Config {
         self.with_state_mut(|state| state.cache_version = CACHE_VERSION);
     }
 
-    /// Save runtime state to the user configuration directory.
+    /// Get the backend that persists the runtime state and the library caches.
+    pub fn storage(&self) -> Arc<dyn Storage> {
+        self.storage.clone()
+    }
+
+    /// Save runtime state to the storage backend.
     pub fn save_state(&self) {
         self.update_state_cache_version();
-
-        let path = config_path(USER_STATE_FILE_NAME);
-        debug!("saving user state to {}", path.display());
-        if let Err(e) = CBOR.write(path, &*self.state()) {
+        self.with_state_mut(|state| state.data_usage = data_usage::total());
+
+        let key = instance::user_state_file_name();
+        debug!("saving user state to {key}");
+        let result = CBOR
+            .encode(&*self.state())
+            .and_then(|content| self.storage.write(Namespace::State, &key, &content));
+        if let Err(e) = result {
             error!("Could not save user state: {e}");
         }
+
+        if let Some(sync) = &self.sync {
+            sync.lock().unwrap().save(&self.state());
+        }
+    }
+
+    /// The audio backend picked with the `backend` command, or else the configured one. `None`
+    /// stands for the default backend of this build.
+    pub fn backend(&self) -> Option<String> {
+        // A backend picked with a previous build might not be available anymore.
+        let picked = self.state().backend.clone().filter(|name| {
+            audio_backend::BACKENDS
+                .iter()
+                .any(|backend| backend.0 == name)
+        });
+        picked.or_else(|| self.values().backend.clone())
     }
 
-    /// Create a [Theme] from the user supplied theme in the configuration file.
+    /// The audio output device picked with the `device` command, or else the one configured for
+    /// the audio backend in use.
+    pub fn output_device(&self) -> Option<String> {
+        let configured = || {
+            let backend = self
+                .backend()
+                .or_else(|| audio_backend::BACKENDS.first().map(|b| b.0.to_string()))?;
+            self.values()
+                .backend_devices
+                .as_ref()?
+                .get(&backend)
+                .cloned()
+        };
+        self.state()
+            .output_device
+            .clone()
+            .or_else(configured)
+            .or_else(|| self.values().backend_device.clone())
+    }
+
+    /// Get the gain settings of the audio pipeline, combining the configuration file with the
+    /// values changed in the mixer.
+    pub fn gain_settings(&self) -> GainSettings {
+        let values = self.values();
+        let mixer = &self.state().mixer;
+        GainSettings {
+            volnorm: mixer.volnorm.or(values.volnorm).unwrap_or(false),
+            volnorm_pregain: mixer
+                .volnorm_pregain
+                .or(values.volnorm_pregain)
+                .unwrap_or(0.0),
+            volnorm_limiter: mixer
+                .volnorm_limiter
+                .or(values.volnorm_limiter)
+                .unwrap_or(true),
+            preamp: mixer.preamp.or(values.preamp).unwrap_or(0.0),
+        }
+    }
+
+    /// Get whether the light or the dark theme should be used right now.
+    pub fn appearance(&self) -> Appearance {
+        let schedule = self.values().theme_schedule.clone();
+        schedule
+            .map(|schedule| theme::scheduled_appearance(&schedule))
+            .unwrap_or(Appearance::Dark)
+    }
+
+    /// Create a [Theme] from the theme file picked with the `theme` command, or else from the
+    /// configuration file.
     pub fn build_theme(&self) -> Theme {
-        crate::theme::load(&self.values().theme)
+        let picked = self.state().theme.clone();
+        match picked.map(|name| themes::load(&name)) {
+            Some(Ok(theme)) => theme::load(&Some(theme)),
+            Some(Err(e)) => {
+                error!("{e}");
+                self.configured_theme()
+            }
+            None => self.configured_theme(),
+        }
+    }
+
+    /// Create a [Theme] from the user supplied theme in the configuration file. The light theme is
+    /// used instead if one is configured and the theme schedule asks for it.
+    pub fn configured_theme(&self) -> Theme {
+        let appearance = self.appearance();
+        let values = self.values();
+        match (appearance, &values.light_theme) {
+            (Appearance::Light, Some(_)) => theme::load(&values.light_theme),
+            _ => theme::load(&values.theme),
+        }
+    }
+
+    /// The path of the configuration file.
+    pub fn path(&self) -> PathBuf {
+        config_path(&self.filename)
     }
 
     /// Attempt to reload the configuration from the configuration file.
@@ -275,12 +684,105 @@ impl Config {
         *self.values.write().unwrap() = cfg;
         Ok(())
     }
+
+    /// Set the audio backend in the configuration file, leaving the rest of the file untouched.
+    pub fn set_backend(&self, backend: &str) -> Result<(), Box<dyn Error>> {
+        let path = config_path(&self.filename);
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let line = format!("backend = {}", toml::Value::String(backend.to_string()));
+        // Top level keys have to come before the first table, so a new one goes to the top.
+        let existing = regex::Regex::new(r"(?m)^backend\s*=.*$")?;
+        let content = if existing.is_match(&content) {
+            existing.replace(&content, line.as_str()).into_owned()
+        } else {
+            format!("{line}\n{content}")
+        };
+        write_atomically(&path, content.as_bytes())?;
+        self.reload()
+    }
+}
+
+/// How often the configuration file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notices when the configuration file was changed on disk, so it can be reloaded without a
+/// restart. The modification time is polled, which also works on file systems without change
+/// notifications.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: &Config) -> Self {
+        let path = config.path();
+        let modified = modified(&path);
+        Self {
+            path,
+            modified,
+            checked: Instant::now(),
+        }
+    }
+
+    /// Whether the configuration file was changed since the last check.
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        self.checked = Instant::now();
+
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        // A removed file keeps the configuration that is loaded.
+        modified.is_some()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The `proxy` of the configuration file with name `filename`, which is needed before the rest of
+/// the configuration is loaded. Errors in the file are left to [Config::new] to report.
+pub fn proxy(filename: Option<&str>) -> Option<String> {
+    load(filename.unwrap_or(CONFIGURATION_FILE_NAME))
+        .ok()?
+        .proxy
 }
 
 /// Parse the configuration file with name `filename` at the configuration base path.
 fn load(filename: &str) -> Result<ConfigValues, String> {
     let path = config_path(filename);
-    TOML.load_or_generate_default(path, || Ok(ConfigValues::default()), false)
+    let mut values = TOML.load_or_generate_default(path, || Ok(ConfigValues::default()), false)?;
+    plugins::apply(&mut values);
+    Ok(values)
+}
+
+/// Load the runtime state of this instance. New instances start out with the state of the first
+/// one, and a missing or unreadable state is replaced by the default.
+fn load_state(storage: &dyn Storage) -> UserState {
+    let mut keys = vec![
+        instance::user_state_file_name(),
+        USER_STATE_FILE_NAME.to_string(),
+    ];
+    keys.dedup();
+    keys.iter()
+        .find_map(|key| match storage.read(Namespace::State, key) {
+            Ok(Some(content)) => CBOR
+                .decode(&content)
+                .inspect_err(|e| error!("Could not parse user state {key}: {e}"))
+                .ok(),
+            Ok(None) => None,
+            Err(e) => {
+                error!("Could not load user state: {e}");
+                None
+            }
+        })
+        .unwrap_or_default()
 }
 
 /// Returns the plaform app directories for ncspot if they could be determined,
@@ -0,0 +1,129 @@
+//! Writers, producers and other credits of a track.
+//!
+//! Spotify doesn't expose credits through its Web API, so they are looked up on MusicBrainz by the
+//! ISRC of the track.
+
+use std::thread;
+use std::time::Duration;
+
+use log::debug;
+use serde_json::Value;
+
+use crate::proxy;
+
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org/ws/2";
+
+/// MusicBrainz allows one request per second.
+const MUSICBRAINZ_DELAY: Duration = Duration::from_secs(1);
+
+/// The people credited with one role.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Credit {
+    pub role: String,
+    pub names: Vec<String>,
+}
+
+/// Look up the credits of the recording with the given `isrc` on MusicBrainz.
+pub fn musicbrainz(isrc: &str) -> Result<Vec<Credit>, String> {
+    let client = proxy::client_builder()
+        .user_agent(format!(
+            "ncspot/{} ( https://github.com/hrkfdn/ncspot )",
+            env!("VERSION")
+        ))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let get = |url: String| -> Result<Value, String> {
+        debug!("fetching {url}");
+        client
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| format!("MusicBrainz request failed: {e}"))
+    };
+
+    let recordings = get(format!("{MUSICBRAINZ_URL}/isrc/{isrc}?fmt=json"))?;
+    let Some(recording_id) = recordings["recordings"][0]["id"].as_str() else {
+        return Ok(Vec::new());
+    };
+    thread::sleep(MUSICBRAINZ_DELAY);
+    let recording = get(format!(
+        "{MUSICBRAINZ_URL}/recording/{recording_id}\
+         ?inc=artist-rels+work-rels+work-level-rels&fmt=json"
+    ))?;
+    Ok(parse_recording(&recording))
+}
+
+/// Collect the credits from the relationships of a recording and of the works it performs, like the
+/// producers of the recording and the composers of the song.
+fn parse_recording(recording: &Value) -> Vec<Credit> {
+    let mut credits: Vec<Credit> = Vec::new();
+    let mut add = |relation: &Value| {
+        let (Some(role), Some(name)) = (
+            relation["type"].as_str(),
+            relation["artist"]["name"].as_str(),
+        ) else {
+            return;
+        };
+        let role = capitalize(role);
+        match credits.iter_mut().find(|credit| credit.role == role) {
+            Some(credit) if credit.names.iter().any(|n| n == name) => {}
+            Some(credit) => credit.names.push(name.to_string()),
+            None => credits.push(Credit {
+                role,
+                names: vec![name.to_string()],
+            }),
+        }
+    };
+
+    let relations = |value: &Value| value["relations"].as_array().cloned().unwrap_or_default();
+    for relation in relations(recording) {
+        if relation["target-type"] == "work" {
+            relations(&relation["work"]).iter().for_each(&mut add);
+        } else {
+            add(&relation);
+        }
+    }
+    credits
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recording() {
+        let recording = serde_json::json!({
+            "relations": [
+                {"target-type": "artist", "type": "producer", "artist": {"name": "A"}},
+                {"target-type": "artist", "type": "producer", "artist": {"name": "B"}},
+                {"target-type": "artist", "type": "producer", "artist": {"name": "A"}},
+                {"target-type": "work", "type": "performance", "work": {"relations": [
+                    {"target-type": "artist", "type": "composer", "artist": {"name": "C"}},
+                    {"target-type": "url", "type": "lyrics"},
+                ]}},
+            ]
+        });
+        assert_eq!(
+            parse_recording(&recording),
+            vec![
+                Credit {
+                    role: "Producer".into(),
+                    names: vec!["A".into(), "B".into()],
+                },
+                Credit {
+                    role: "Composer".into(),
+                    names: vec!["C".into()],
+                },
+            ]
+        );
+    }
+}
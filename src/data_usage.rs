@@ -0,0 +1,151 @@
+//! Accounting of the data ncspot transfers, so users on metered connections can see what streaming
+//! costs them and tune the bitrate and the caches accordingly.
+//!
+//! Audio is counted by the estimated size of the streamed files, which are downloaded completely.
+//! Files that are played from the audio cache aren't counted. Web API calls are counted by the size
+//! of the response bodies the HTTP clients receive.
+
+use std::ops::Add;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rspotify::http::{HttpClient, Query};
+use rspotify::model::Token;
+use rspotify::prelude::*;
+use rspotify::{AuthCodeSpotify, ClientResult, Config, Credentials, OAuth};
+use serde_json::Value;
+
+/// Amounts of transferred data in bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataUsage {
+    /// Audio files streamed for playback.
+    pub streamed: u64,
+    /// Responses of the Spotify Web API.
+    pub api: u64,
+}
+
+impl Add for DataUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            streamed: self.streamed + other.streamed,
+            api: self.api + other.api,
+        }
+    }
+}
+
+static STREAMED: AtomicU64 = AtomicU64::new(0);
+static API: AtomicU64 = AtomicU64::new(0);
+
+/// The usage of all earlier sessions, as persisted in the user state.
+static PREVIOUS: Mutex<DataUsage> = Mutex::new(DataUsage {
+    streamed: 0,
+    api: 0,
+});
+
+/// Set the usage of the earlier sessions that the totals build on.
+pub fn init(previous: DataUsage) {
+    *PREVIOUS.lock().unwrap() = previous;
+}
+
+pub fn add_streamed(bytes: u64) {
+    STREAMED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Count a Web API response body of `bytes` bytes.
+pub fn add_api(bytes: u64) {
+    API.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// The usage since ncspot was started.
+pub fn session() -> DataUsage {
+    DataUsage {
+        streamed: STREAMED.load(Ordering::Relaxed),
+        api: API.load(Ordering::Relaxed),
+    }
+}
+
+/// The usage of all sessions, including the current one.
+pub fn total() -> DataUsage {
+    *PREVIOUS.lock().unwrap() + session()
+}
+
+/// The rspotify client, counting the bodies of the Web API responses it receives. All endpoints
+/// send their requests through the `api_*` methods, so overriding them covers every call.
+#[derive(Clone, Debug, Default)]
+pub struct MeteredClient(pub AuthCodeSpotify);
+
+impl MeteredClient {
+    pub fn with_config(creds: Credentials, oauth: OAuth, config: Config) -> Self {
+        Self(AuthCodeSpotify::with_config(creds, oauth, config))
+    }
+}
+
+fn count(response: ClientResult<String>) -> ClientResult<String> {
+    if let Ok(body) = &response {
+        add_api(body.len() as u64);
+    }
+    response
+}
+
+impl BaseClient for MeteredClient {
+    fn get_http(&self) -> &HttpClient {
+        self.0.get_http()
+    }
+
+    fn get_token(&self) -> std::sync::Arc<rspotify::sync::Mutex<Option<Token>>> {
+        self.0.get_token()
+    }
+
+    fn get_creds(&self) -> &Credentials {
+        self.0.get_creds()
+    }
+
+    fn get_config(&self) -> &Config {
+        self.0.get_config()
+    }
+
+    fn refetch_token(&self) -> ClientResult<Option<Token>> {
+        self.0.refetch_token()
+    }
+
+    fn api_get(&self, url: &str, payload: &Query<'_>) -> ClientResult<String> {
+        count(self.0.api_get(url, payload))
+    }
+
+    fn api_post(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        count(self.0.api_post(url, payload))
+    }
+
+    fn api_put(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        count(self.0.api_put(url, payload))
+    }
+
+    fn api_delete(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        count(self.0.api_delete(url, payload))
+    }
+}
+
+impl OAuthClient for MeteredClient {
+    fn get_oauth(&self) -> &OAuth {
+        self.0.get_oauth()
+    }
+
+    fn request_token(&self, code: &str) -> ClientResult<()> {
+        self.0.request_token(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        let before = session().api;
+        assert!(count(Ok(r#"{"id":"abc"}"#.to_string())).is_ok());
+        assert!(count(Err(rspotify::ClientError::InvalidToken)).is_err());
+        assert_eq!(session().api - before, r#"{"id":"abc"}"#.len() as u64);
+    }
+}
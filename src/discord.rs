@@ -0,0 +1,282 @@
+//! Shows the playing track as the Discord Rich Presence of the user, by talking to the Discord
+//! client over its local IPC socket.
+
+use std::io::{self, Read, Write};
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{Receiver, Sender};
+use log::{debug, info};
+use serde_json::{Value, json};
+
+use crate::config::Config;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+
+/// The IPC sockets are numbered, one for each running Discord client.
+const SOCKET_COUNT: usize = 10;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+/// The activity type that shows "Listening to".
+const ACTIVITY_LISTENING: u32 = 2;
+
+/// What the presence should show.
+#[derive(Clone, Debug, PartialEq)]
+struct Presence {
+    title: String,
+    artists: String,
+    album: String,
+    cover_url: Option<String>,
+    /// When playback started and will end, in milliseconds since the Unix epoch. Not set while
+    /// paused.
+    timestamps: Option<(u128, u128)>,
+}
+
+pub struct Discord {
+    cfg: Arc<Config>,
+    library: Arc<Library>,
+    /// The channel to the thread that talks to Discord, started with the first update.
+    worker: Option<Sender<Option<Presence>>>,
+    last: Option<Presence>,
+}
+
+impl Discord {
+    pub fn new(cfg: Arc<Config>, library: Arc<Library>) -> Self {
+        Self {
+            cfg,
+            library,
+            worker: None,
+            last: None,
+        }
+    }
+
+    /// Show `playable` with the given playback state, or clear the presence when stopped.
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let Some(client_id) = self
+            .cfg
+            .values()
+            .discord
+            .as_ref()
+            .and_then(|discord| discord.client_id.clone())
+        else {
+            return;
+        };
+        let presence = match (state, playable) {
+            (PlayerEvent::Playing(start), Some(playable)) => {
+                Some(self.presence(playable, Some(*start)))
+            }
+            (PlayerEvent::Paused(_), Some(playable)) => Some(self.presence(playable, None)),
+            _ => None,
+        };
+        if presence == self.last {
+            return;
+        }
+        self.last = presence.clone();
+
+        let worker = self.worker.get_or_insert_with(|| {
+            let (tx, rx) = crossbeam_channel::unbounded();
+            let cfg = self.cfg.clone();
+            thread::spawn(move || worker(cfg, &client_id, rx));
+            tx
+        });
+        worker.send(presence).ok();
+    }
+
+    fn presence(&self, playable: &Playable, start: Option<SystemTime>) -> Presence {
+        let format = |format| Playable::format(playable, format, &self.library);
+        let millis = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis()
+        };
+        Presence {
+            title: format("%title"),
+            artists: format("%artists"),
+            album: format("%album"),
+            cover_url: playable.cover_url(),
+            timestamps: start.map(|start| {
+                let start = millis(start);
+                (start, start + u128::from(playable.duration()))
+            }),
+        }
+    }
+}
+
+/// Send the presences received on `rx` to Discord, connecting again when it was restarted.
+fn worker(cfg: Arc<Config>, client_id: &str, rx: Receiver<Option<Presence>>) {
+    let mut connection: Option<Connection> = None;
+    for presence in rx.iter() {
+        // Only the latest presence matters after waiting for Discord.
+        let presence = rx.try_iter().last().unwrap_or(presence);
+        let privacy = cfg
+            .values()
+            .discord
+            .as_ref()
+            .and_then(|discord| discord.privacy)
+            .unwrap_or(false);
+        let activity = presence.map(|presence| activity(&presence, privacy));
+
+        for attempt in 0..2 {
+            if connection.is_none() {
+                connection = Connection::open(client_id)
+                    .inspect_err(|e| debug!("Discord is not available: {e}"))
+                    .ok();
+            }
+            let Some(open) = &mut connection else {
+                break;
+            };
+            match open.set_activity(activity.clone()) {
+                Ok(()) => break,
+                Err(e) => {
+                    debug!("Lost the connection to Discord (attempt {attempt}): {e}");
+                    connection = None;
+                }
+            }
+        }
+    }
+}
+
+/// The activity shown for `presence`. In privacy mode, only that music is playing is shown.
+fn activity(presence: &Presence, privacy: bool) -> Value {
+    let mut activity = if privacy {
+        json!({ "type": ACTIVITY_LISTENING, "details": "Listening to music" })
+    } else {
+        let mut activity = json!({
+            "type": ACTIVITY_LISTENING,
+            "details": presence.title,
+            "state": presence.artists,
+        });
+        if let Some(cover_url) = &presence.cover_url {
+            activity["assets"] = json!({ "large_image": cover_url, "large_text": presence.album });
+        }
+        activity
+    };
+    match presence.timestamps {
+        Some((start, end)) => activity["timestamps"] = json!({ "start": start, "end": end }),
+        None => activity["state"] = json!("Paused"),
+    }
+    activity
+}
+
+/// An open connection to the Discord client.
+struct Connection {
+    stream: Box<dyn Stream>,
+    nonce: u64,
+}
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+impl Connection {
+    fn open(client_id: &str) -> io::Result<Self> {
+        let stream = (0..SOCKET_COUNT)
+            .find_map(|i| connect(&format!("discord-ipc-{i}")).ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no Discord IPC socket"))?;
+        let mut connection = Self { stream, nonce: 0 };
+        connection.send(OP_HANDSHAKE, &json!({ "v": 1, "client_id": client_id }))?;
+        info!("connected to Discord");
+        Ok(connection)
+    }
+
+    fn set_activity(&mut self, activity: Option<Value>) -> io::Result<()> {
+        self.nonce += 1;
+        let command = json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": process::id(), "activity": activity },
+            "nonce": self.nonce.to_string(),
+        });
+        self.send(OP_FRAME, &command)
+    }
+
+    /// Send a frame and read the answer, so the answers don't pile up in the socket.
+    fn send(&mut self, opcode: u32, payload: &Value) -> io::Result<()> {
+        let payload = payload.to_string();
+        let mut frame = Vec::with_capacity(payload.len() + 8);
+        frame.extend(opcode.to_le_bytes());
+        frame.extend((payload.len() as u32).to_le_bytes());
+        frame.extend(payload.as_bytes());
+        self.stream.write_all(&frame)?;
+
+        let mut header = [0; 8];
+        self.stream.read_exact(&mut header)?;
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let mut answer = vec![0; length as usize];
+        self.stream.read_exact(&mut answer)?;
+        let answer: Value = serde_json::from_slice(&answer)?;
+        match answer["evt"].as_str() {
+            Some("ERROR") => Err(io::Error::other(answer["data"]["message"].to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn connect(name: &str) -> io::Result<Box<dyn Stream>> {
+    use std::env;
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+
+    let directory = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(env::var_os)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    // Discord installed with Flatpak or Snap uses a subdirectory.
+    ["", "app/com.discordapp.Discord", "snap.discord"]
+        .iter()
+        .find_map(|subdirectory| UnixStream::connect(directory.join(subdirectory).join(name)).ok())
+        .map(|stream| Box::new(stream) as Box<dyn Stream>)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+}
+
+#[cfg(windows)]
+fn connect(name: &str) -> io::Result<Box<dyn Stream>> {
+    let pipe = std::fs::File::options()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\pipe\{name}"))?;
+    Ok(Box::new(pipe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity() {
+        let presence = Presence {
+            title: "Pang".into(),
+            artists: "Caroline Polachek".into(),
+            album: "Pang".into(),
+            cover_url: Some("https://i.scdn.co/image/cover".into()),
+            timestamps: Some((1000, 2000)),
+        };
+        let activity = activity(&presence, false);
+        assert_eq!(activity["details"], "Pang");
+        assert_eq!(
+            activity["assets"]["large_image"],
+            "https://i.scdn.co/image/cover"
+        );
+        assert_eq!(activity["timestamps"]["end"], 2000);
+
+        let private = super::activity(&presence, true);
+        assert_eq!(private["details"], "Listening to music");
+        assert!(private.get("state").is_none());
+        assert!(private.get("assets").is_none());
+
+        let paused = super::activity(
+            &Presence {
+                timestamps: None,
+                ..presence
+            },
+            true,
+        );
+        assert_eq!(paused["state"], "Paused");
+        assert!(paused.get("timestamps").is_none());
+    }
+}
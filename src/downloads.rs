@@ -0,0 +1,228 @@
+//! Downloading playlists and albums into the audio cache ahead of time, so they play without
+//! streaming, e.g. on a flight. librespot plays files from the audio cache whenever they are in
+//! it, but still needs a connection to Spotify to decrypt them.
+//!
+//! Downloads run one after the other in a background thread and stop once the audio cache reaches
+//! the quota. The downloaded tracks are remembered, so the queue can skip the others while offline.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::{debug, error, info};
+
+use crate::caches::CacheKind;
+use crate::config::Config;
+use crate::model::playable::Playable;
+use crate::spotify::Spotify;
+use crate::storage::{JsonStore, Storage};
+use crate::traits::ListItem;
+use crate::utils::format_size;
+
+const KEY: &str = "downloads.json";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Queued,
+    Downloading,
+    Finished,
+    Cancelled,
+    /// The download was stopped, e.g. because the quota was reached.
+    Stopped(String),
+}
+
+/// A collection of tracks that is downloaded together.
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub title: String,
+    pub tracks: Vec<Playable>,
+    /// How many of the tracks are in the audio cache now.
+    pub done: usize,
+    /// How many of the tracks could not be downloaded.
+    pub failed: usize,
+    /// The size of the files downloaded, not counting those that were cached already.
+    pub downloaded: u64,
+    pub status: Status,
+}
+
+struct Downloads {
+    list: Vec<Download>,
+    /// Whether the background thread is running.
+    running: bool,
+}
+
+static DOWNLOADS: Mutex<Downloads> = Mutex::new(Downloads {
+    list: Vec::new(),
+    running: false,
+});
+
+/// The uris of the downloaded tracks.
+static DOWNLOADED: JsonStore<HashSet<String>> = JsonStore::new(KEY, "downloaded tracks");
+
+/// Whether the track with `uri` was downloaded. librespot might have removed it from the audio
+/// cache since, to keep the cache below its size limit.
+pub fn is_downloaded(storage: &dyn Storage, uri: &str) -> bool {
+    DOWNLOADED.read(storage, |downloaded| downloaded.contains(uri))
+}
+
+fn mark_downloaded(storage: &dyn Storage, uri: String) {
+    if !is_downloaded(storage, &uri) {
+        DOWNLOADED.update(storage, |downloaded| downloaded.insert(uri));
+    }
+}
+
+/// Forget which tracks were downloaded, as the audio cache was cleared.
+pub fn forget_all(storage: &dyn Storage) {
+    DOWNLOADED.clear(storage);
+}
+
+/// The size the audio cache may grow to by downloading, in bytes.
+pub fn quota(cfg: &Config) -> Option<u64> {
+    match cfg.values().download_quota {
+        Some(size) => Some(size as u64 * 1048576),
+        None => CacheKind::Audio.max_size(cfg),
+    }
+}
+
+/// All downloads of this session, the oldest first.
+pub fn list() -> Vec<Download> {
+    DOWNLOADS.lock().unwrap().list.clone()
+}
+
+fn check_enabled(cfg: &Config) -> Result<(), String> {
+    match cfg.values().audio_cache {
+        Some(false) => Err("Downloads need the audio cache, which is disabled".into()),
+        _ => Ok(()),
+    }
+}
+
+/// Download `item`, a track or a collection like an album, whose tracks are loaded in the
+/// background.
+pub fn add_item(
+    spotify: Spotify,
+    cfg: Arc<Config>,
+    title: String,
+    item: Box<dyn ListItem>,
+) -> Result<(), String> {
+    check_enabled(&cfg)?;
+    if let Some(track) = item.track() {
+        return add(spotify, cfg, title, vec![Playable::Track(track)]);
+    }
+    if !item.is_collection() {
+        return Err(format!("{title} can't be downloaded"));
+    }
+    thread::spawn(move || {
+        let tracks = item.collection_tracks(&spotify);
+        let tracks = tracks.into_iter().map(Playable::Track).collect();
+        if let Err(e) = add(spotify, cfg, title, tracks) {
+            error!("could not download: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Download `tracks` into the audio cache in the background.
+pub fn add(
+    spotify: Spotify,
+    cfg: Arc<Config>,
+    title: String,
+    tracks: Vec<Playable>,
+) -> Result<(), String> {
+    check_enabled(&cfg)?;
+    if tracks.is_empty() {
+        return Err(format!("{title} has no tracks to download"));
+    }
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    downloads.list.push(Download {
+        title,
+        tracks,
+        done: 0,
+        failed: 0,
+        downloaded: 0,
+        status: Status::Queued,
+    });
+    if !downloads.running {
+        downloads.running = true;
+        thread::spawn(move || run(spotify, cfg));
+    }
+    Ok(())
+}
+
+/// Cancel the running and queued downloads.
+pub fn cancel() -> usize {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    let mut cancelled = 0;
+    for download in downloads.list.iter_mut() {
+        if matches!(download.status, Status::Queued | Status::Downloading) {
+            download.status = Status::Cancelled;
+            cancelled += 1;
+        }
+    }
+    cancelled
+}
+
+/// Update the download at `index` with `f`, as long as it is still running.
+fn update(index: usize, f: impl FnOnce(&mut Download)) -> bool {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    match downloads.list.get_mut(index) {
+        Some(download) if download.status == Status::Downloading => {
+            f(download);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Work through the queued downloads until there are none left.
+fn run(spotify: Spotify, cfg: Arc<Config>) {
+    loop {
+        let next = {
+            let mut downloads = DOWNLOADS.lock().unwrap();
+            let next = downloads
+                .list
+                .iter_mut()
+                .enumerate()
+                .find(|(_, download)| download.status == Status::Queued);
+            match next {
+                Some((index, download)) => {
+                    download.status = Status::Downloading;
+                    (index, download.title.clone(), download.tracks.clone())
+                }
+                None => {
+                    downloads.running = false;
+                    return;
+                }
+            }
+        };
+        let (index, title, tracks) = next;
+        info!("downloading {title}");
+
+        for track in tracks {
+            if let Some(quota) = quota(&cfg)
+                && CacheKind::Audio.size() >= quota
+            {
+                let reason = format!("The quota of {} is reached", format_size(quota));
+                update(index, |download| download.status = Status::Stopped(reason));
+                break;
+            }
+            let result = spotify.download(&track);
+            if result.is_ok() {
+                mark_downloaded(cfg.storage().as_ref(), track.uri());
+            }
+            let running = update(index, |download| match result {
+                Ok(size) => {
+                    download.done += 1;
+                    download.downloaded += size;
+                }
+                Err(e) => {
+                    debug!("could not download {track}: {e}");
+                    download.failed += 1;
+                }
+            });
+            if !running {
+                break;
+            }
+        }
+        update(index, |download| download.status = Status::Finished);
+    }
+}
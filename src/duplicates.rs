@@ -0,0 +1,172 @@
+//! Finding tracks that occur more than once in a playlist or in the saved tracks, either as the
+//! very same track or as another release of the same song, like a remaster.
+
+use std::collections::HashMap;
+
+use crate::model::playable::Playable;
+
+/// Words in a version suffix of a title that mark another release of the same recording.
+const RELEASE_WORDS: [&str; 7] = [
+    "remaster",
+    "version",
+    "mono",
+    "stereo",
+    "deluxe",
+    "anniversary",
+    "edition",
+];
+
+/// How a duplicate matches the track it duplicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// The same Spotify track.
+    Identical,
+    /// The same title and artist, but another track, e.g. from a compilation or a remaster.
+    Similar,
+}
+
+/// A later occurrence of a track that is already in the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// The position of the duplicate.
+    pub index: usize,
+    /// The position of the first occurrence, which is kept.
+    pub original: usize,
+    pub kind: DuplicateKind,
+}
+
+/// What identifies a song when looking for duplicates.
+#[derive(Debug, PartialEq, Eq)]
+struct Song {
+    id: String,
+    title: String,
+    artist: String,
+}
+
+/// The duplicates in `playables`, in the order they occur. Local files can't be removed from
+/// playlists, so they are never considered duplicates.
+pub fn find(playables: &[Playable]) -> Vec<Duplicate> {
+    find_by(playables, |playable| match playable {
+        Playable::Track(track) if track.is_local => None,
+        Playable::Track(track) => Some(Song {
+            id: track.id.clone()?,
+            title: normalize_title(&track.title),
+            artist: track.artists.first()?.to_lowercase(),
+        }),
+        Playable::Episode(episode) => Some(Song {
+            id: episode.id.clone(),
+            title: String::new(),
+            artist: String::new(),
+        }),
+    })
+}
+
+fn find_by<T>(items: &[T], song: impl Fn(&T) -> Option<Song>) -> Vec<Duplicate> {
+    let mut by_id = HashMap::new();
+    let mut by_title = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let Some(song) = song(item) else {
+            continue;
+        };
+        let similar = !song.title.is_empty() && !song.artist.is_empty();
+        if let Some(&original) = by_id.get(&song.id) {
+            duplicates.push(Duplicate {
+                index,
+                original,
+                kind: DuplicateKind::Identical,
+            });
+            continue;
+        }
+        by_id.insert(song.id, index);
+        if !similar {
+            continue;
+        }
+        match by_title.get(&(song.title.clone(), song.artist.clone())) {
+            Some(&original) => duplicates.push(Duplicate {
+                index,
+                original,
+                kind: DuplicateKind::Similar,
+            }),
+            None => {
+                by_title.insert((song.title, song.artist), index);
+            }
+        }
+    }
+    duplicates
+}
+
+/// The title without the suffixes that only tell releases apart, like "- 2011 Remaster" or
+/// "(Mono Version)", in lowercase.
+pub fn normalize_title(title: &str) -> String {
+    let mut title = title.to_lowercase();
+    let is_release = |suffix: &str| RELEASE_WORDS.iter().any(|word| suffix.contains(word));
+
+    if let Some(start) = title.find(" - ")
+        && is_release(&title[start..])
+    {
+        title.truncate(start);
+    }
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        while let Some(start) = title.rfind(open) {
+            let end = title[start..]
+                .find(close)
+                .map_or(title.len(), |end| start + end + 1);
+            if !is_release(&title[start..end]) {
+                break;
+            }
+            title.replace_range(start..end, "");
+        }
+    }
+    title.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title("Heroes - 2017 Remaster"), "heroes");
+        assert_eq!(normalize_title("Help! (Remastered 2009)"), "help!");
+        assert_eq!(normalize_title("Wild Horses [Mono Version]"), "wild horses");
+        assert_eq!(normalize_title("Hurt - Live"), "hurt - live");
+        assert_eq!(
+            normalize_title("Talk (feat. Someone)"),
+            "talk (feat. someone)"
+        );
+    }
+
+    #[test]
+    fn test_find_by() {
+        let items = [
+            ("1", "Heroes", "David Bowie"),
+            ("2", "Help!", "The Beatles"),
+            ("1", "Heroes", "David Bowie"),
+            ("3", "Heroes - 2017 Remaster", "David Bowie"),
+            ("4", "Heroes", "Motörhead"),
+        ];
+        let duplicates = find_by(&items, |(id, title, artist)| {
+            Some(Song {
+                id: id.to_string(),
+                title: normalize_title(title),
+                artist: artist.to_lowercase(),
+            })
+        });
+        assert_eq!(
+            duplicates,
+            [
+                Duplicate {
+                    index: 2,
+                    original: 0,
+                    kind: DuplicateKind::Identical,
+                },
+                Duplicate {
+                    index: 3,
+                    original: 0,
+                    kind: DuplicateKind::Similar,
+                },
+            ]
+        );
+    }
+}
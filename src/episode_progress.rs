@@ -0,0 +1,160 @@
+//! Where partially played podcast episodes were left, so replaying them continues from there.
+//! Spotify's own resume points are used for episodes that weren't played in ncspot.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::model::episode::Episode;
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::spotify::PlayerEvent;
+use crate::storage::{JsonStore, Storage};
+
+const KEY: &str = "episode_progress.json";
+
+/// Episodes left this close to their end, in milliseconds, count as played.
+const END_MARGIN: u32 = 30_000;
+
+/// How often the position is saved while an episode plays.
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    /// The position in milliseconds.
+    pub position: u32,
+    pub fully_played: bool,
+}
+
+impl Progress {
+    /// Where to continue the episode, which is the start again once it was played.
+    pub fn resume_position(&self, duration: u32) -> u32 {
+        if self.fully_played || self.position.saturating_add(END_MARGIN) >= duration {
+            0
+        } else {
+            self.position
+        }
+    }
+
+    /// A short label for episode lists, if the episode was played at all.
+    pub fn label(&self, duration: u32) -> Option<String> {
+        if self.fully_played || self.position.saturating_add(END_MARGIN) >= duration {
+            Some("played".to_string())
+        } else if self.position > 0 && duration > 0 {
+            Some(format!("{}%", self.position as u64 * 100 / duration as u64))
+        } else {
+            None
+        }
+    }
+}
+
+/// The saved progress by episode id.
+static PROGRESS: JsonStore<HashMap<String, Progress>> = JsonStore::new(KEY, "episode progress");
+
+fn save(storage: &dyn Storage, id: &str, progress: Progress) {
+    if PROGRESS.read(storage, |saved| saved.get(id) == Some(&progress)) {
+        return;
+    }
+    PROGRESS.update(storage, |saved| saved.insert(id.to_string(), progress));
+}
+
+/// The progress of `episode`, as played in ncspot or else as reported by Spotify.
+pub fn of(storage: &dyn Storage, episode: &Episode) -> Option<Progress> {
+    PROGRESS
+        .read(storage, |saved| saved.get(&episode.id).copied())
+        .or(episode.resume_point)
+}
+
+/// Follows the playback to remember the position in episodes.
+pub struct EpisodeProgressTracker {
+    cfg: Arc<Config>,
+    queue: Arc<Queue>,
+    next_save: Instant,
+}
+
+impl EpisodeProgressTracker {
+    pub fn new(cfg: Arc<Config>, queue: Arc<Queue>) -> Self {
+        Self {
+            cfg,
+            queue,
+            next_save: Instant::now(),
+        }
+    }
+
+    fn save_position(&self, episode: &Episode, position: u32) {
+        let progress = Progress {
+            position,
+            fully_played: false,
+        };
+        save(self.cfg.storage().as_ref(), &episode.id, progress);
+    }
+
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let Some(Playable::Episode(episode)) = playable else {
+            return;
+        };
+        match state {
+            PlayerEvent::Paused(position) => {
+                self.save_position(episode, position.as_millis() as u32)
+            }
+            PlayerEvent::FinishedTrack => {
+                let progress = Progress {
+                    position: 0,
+                    fully_played: true,
+                };
+                save(self.cfg.storage().as_ref(), &episode.id, progress);
+            }
+            PlayerEvent::Playing(_) | PlayerEvent::Stopped => {}
+        }
+    }
+
+    /// Save the position in the playing episode every now and then.
+    pub fn tick(&mut self) {
+        if Instant::now() < self.next_save {
+            return;
+        }
+        self.next_save = Instant::now() + SAVE_INTERVAL;
+        self.finish();
+    }
+
+    /// Save the position in the playing episode, e.g. because ncspot quits.
+    pub fn finish(&self) {
+        let spotify = self.queue.get_spotify();
+        if let (
+            Some(Playable::Episode(episode)),
+            PlayerEvent::Playing(_) | PlayerEvent::Paused(_),
+        ) = (self.queue.get_current(), spotify.get_current_status())
+        {
+            // The progress is 0 until the player reports the position of a newly loaded item.
+            let position = spotify.get_current_progress().as_millis() as u32;
+            if position > 0 {
+                self.save_position(&episode, position);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_position() {
+        let progress = |position, fully_played| Progress {
+            position,
+            fully_played,
+        };
+        let hour = 3_600_000;
+
+        assert_eq!(progress(600_000, false).resume_position(hour), 600_000);
+        assert_eq!(progress(600_000, false).label(hour).unwrap(), "16%");
+        assert_eq!(progress(600_000, true).resume_position(hour), 0);
+        assert_eq!(progress(hour - 10_000, false).resume_position(hour), 0);
+        assert_eq!(
+            progress(hour - 10_000, false).label(hour).unwrap(),
+            "played"
+        );
+        assert_eq!(progress(0, false).label(hour), None);
+    }
+}
@@ -10,6 +10,10 @@ pub enum Event {
     Queue(QueueEvent),
     SessionDied,
     IpcInput(String),
+    /// The items of the queue changed.
+    QueueChanged,
+    /// The library finished loading from the Web API.
+    LibraryLoaded,
 }
 
 /// Manager that can be used to send and receive messages across threads.
@@ -17,7 +21,8 @@ pub enum Event {
 pub struct EventManager {
     tx: Sender<Event>,
     rx: Receiver<Event>,
-    cursive_sink: CbSink,
+    /// The sink of the Cursive event loop, `None` when running without the TUI.
+    cursive_sink: Option<CbSink>,
 }
 
 impl EventManager {
@@ -27,10 +32,27 @@ impl EventManager {
         Self {
             tx,
             rx,
-            cursive_sink,
+            cursive_sink: Some(cursive_sink),
         }
     }
 
+    /// Create a manager that isn't tied to a Cursive event loop. Events have to be processed with
+    /// [EventManager::recv].
+    pub fn headless() -> Self {
+        let (tx, rx) = unbounded();
+
+        Self {
+            tx,
+            rx,
+            cursive_sink: None,
+        }
+    }
+
+    /// Wait for the next event. Returns `None` when no more events can be received.
+    pub fn recv(&self) -> Option<Event> {
+        self.rx.recv().ok()
+    }
+
     /// Return a non-blocking iterator over the messages awaiting handling. Calling `next()` on the
     /// iterator never blocks.
     pub fn msg_iter(&self) -> TryIter<'_, Event> {
@@ -45,6 +67,8 @@ impl EventManager {
 
     /// Send a no-op to the Cursive event loop to trigger immediate processing of events.
     pub fn trigger(&self) {
-        self.cursive_sink.send(Box::new(Cursive::noop)).unwrap();
+        if let Some(cursive_sink) = &self.cursive_sink {
+            cursive_sink.send(Box::new(Cursive::noop)).unwrap();
+        }
     }
 }
@@ -0,0 +1,66 @@
+//! Fuzzy matching, used to narrow lists while typing.
+
+/// How well `query` matches the texts in `fields`, or `None` if it doesn't. Every word of the
+/// query has to match one of the fields, and the score adds up the best match of every word.
+pub fn score(query: &str, fields: &[String]) -> Option<i64> {
+    let fields: Vec<Vec<char>> = fields
+        .iter()
+        .map(|field| field.to_lowercase().chars().collect())
+        .collect();
+    query.split_whitespace().try_fold(0, |total, word| {
+        let word: Vec<char> = word.to_lowercase().chars().collect();
+        let best = fields
+            .iter()
+            .filter_map(|field| score_word(&word, field))
+            .max()?;
+        Some(total + best)
+    })
+}
+
+/// How well `word` matches `text`. All characters of the word have to appear in the text in the
+/// same order. Consecutive characters and characters at the start of words score higher, gaps
+/// between them lower.
+fn score_word(word: &[char], text: &[char]) -> Option<i64> {
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for &c in word {
+        let found = position + text[position..].iter().position(|&t| t == c)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if previous + 1 == found => score += 5,
+            Some(previous) => score -= (found - previous - 1).min(10) as i64,
+            None => {}
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(title: &str, artist: &str) -> Vec<String> {
+        vec![title.to_string(), artist.to_string()]
+    }
+
+    #[test]
+    fn test_score() {
+        let heroes = fields("Heroes", "David Bowie");
+        let hero = fields("Hero", "Family of the Year");
+        let hello = fields("Hello", "Adele");
+
+        assert!(score("hrs", &heroes).is_some());
+        assert!(score("sreh", &heroes).is_none());
+        assert!(score("bowie heroes", &heroes).is_some());
+        assert!(score("bowie hello", &heroes).is_none());
+        assert!(score("hero", &heroes) <= score("hero", &hero));
+        assert!(score("hel", &hello) > score("hel", &heroes));
+        assert!(score("db", &heroes) > score("db", &fields("Dumb", "")));
+    }
+}
@@ -0,0 +1,139 @@
+//! The pre-amp and the volume offsets of single tracks, for badly mastered tracks. Both are applied
+//! by a sink in front of the real one, so they take effect right away and leave the system volume
+//! alone. Unlike the normalisation pregain, they work without normalisation.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use librespot_playback::audio_backend::{Sink, SinkResult};
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::AudioPacket;
+
+use crate::command::GainChange;
+use crate::storage::{JsonStore, Storage};
+
+const KEY: &str = "track_gain.json";
+
+/// Gains are limited to this many dB in both directions.
+pub const MAX_DB: f64 = 12.0;
+
+/// The volume offsets in dB by track uri.
+static OFFSETS: JsonStore<HashMap<String, f64>> = JsonStore::new(KEY, "track gains");
+
+/// The volume offset of the track with `uri` in dB.
+pub fn track_offset(storage: &dyn Storage, uri: &str) -> f64 {
+    OFFSETS.read(storage, |offsets| offsets.get(uri).copied().unwrap_or(0.0))
+}
+
+/// Set the volume offset of the track with `uri`, where 0 removes it.
+pub fn set_track_offset(storage: &dyn Storage, uri: &str, db: f64) {
+    OFFSETS.update(storage, |offsets| {
+        if db == 0.0 {
+            offsets.remove(uri);
+        } else {
+            offsets.insert(uri.to_string(), db);
+        }
+    });
+}
+
+/// Apply `change` to the gain `db`, within the limits.
+pub fn adjust(db: f64, change: &GainChange) -> f64 {
+    let db = match change {
+        GainChange::Relative(delta) => db + delta,
+        GainChange::Absolute(db) => *db,
+    };
+    db.clamp(-MAX_DB, MAX_DB)
+}
+
+pub fn format_db(db: f64) -> String {
+    format!("{db:+.1} dB")
+}
+
+/// The gain applied to the audio, shared between the player and [crate::spotify::Spotify], which
+/// sets it for every track that is loaded.
+#[derive(Clone)]
+pub struct PlaybackGain {
+    /// The factor the samples are multiplied with, as the bits of an `f64`.
+    factor: Arc<AtomicU64>,
+}
+
+impl Default for PlaybackGain {
+    fn default() -> Self {
+        Self {
+            factor: Arc::new(AtomicU64::new(1f64.to_bits())),
+        }
+    }
+}
+
+impl PlaybackGain {
+    pub fn set_db(&self, db: f64) {
+        let factor = 10f64.powf(db / 20.0);
+        self.factor.store(factor.to_bits(), Ordering::Relaxed);
+    }
+
+    fn factor(&self) -> f64 {
+        f64::from_bits(self.factor.load(Ordering::Relaxed))
+    }
+
+    /// Wrap `sink` to apply the gain.
+    pub fn sink(&self, sink: Box<dyn Sink>) -> Box<dyn Sink> {
+        Box::new(GainSink {
+            sink,
+            gain: self.clone(),
+        })
+    }
+}
+
+/// Multiply `samples` with `factor`, clipping what ends up out of range.
+fn amplify(samples: &mut [f64], factor: f64) {
+    for sample in samples {
+        *sample = (*sample * factor).clamp(-1.0, 1.0);
+    }
+}
+
+struct GainSink {
+    sink: Box<dyn Sink>,
+    gain: PlaybackGain,
+}
+
+impl Sink for GainSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.sink.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.sink.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let factor = self.gain.factor();
+        match packet {
+            AudioPacket::Samples(mut samples) if factor != 1.0 => {
+                amplify(&mut samples, factor);
+                self.sink.write(AudioPacket::Samples(samples), converter)
+            }
+            packet => self.sink.write(packet, converter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gain() {
+        let gain = PlaybackGain::default();
+        gain.set_db(6.0);
+        let mut samples = vec![0.25, -0.25, 0.75];
+        amplify(&mut samples, gain.factor());
+        assert!((samples[0] - 0.4988).abs() < 0.001);
+        assert!((samples[1] + 0.4988).abs() < 0.001);
+        assert_eq!(samples[2], 1.0);
+
+        assert_eq!(adjust(11.0, &GainChange::Relative(2.0)), MAX_DB);
+        assert_eq!(adjust(1.5, &GainChange::Relative(-1.0)), 0.5);
+        assert_eq!(adjust(1.5, &GainChange::Absolute(0.0)), 0.0);
+    }
+}
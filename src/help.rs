@@ -0,0 +1,440 @@
+//! The commands shown in the help, with what they do and where they can be used. The keys bound to
+//! them are looked up in the keybindings in use, so they include the overrides of the user.
+
+use std::collections::{BTreeMap, HashMap};
+
+use strum_macros::Display;
+
+use crate::command::Command;
+
+/// Where a command can be used. Global commands, navigation and playback work everywhere, the
+/// others only in the views that handle them.
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HelpContext {
+    Global,
+    Navigation,
+    Playback,
+    #[strum(serialize = "Lists")]
+    List,
+    #[strum(serialize = "Playlist")]
+    Playlist,
+    #[strum(serialize = "Playlists")]
+    Playlists,
+    Artist,
+    Queue,
+}
+
+impl HelpContext {
+    /// Whether commands of this context work in every view.
+    pub fn is_global(self) -> bool {
+        matches!(self, Self::Global | Self::Navigation | Self::Playback)
+    }
+}
+
+pub struct CommandHelp {
+    /// The name of the command, like [Command::basename].
+    pub name: &'static str,
+    /// How the command is written, with its arguments.
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub context: HelpContext,
+}
+
+macro_rules! help {
+    ( $context: ident, $name: expr_2021, $usage: expr_2021, $description: expr_2021 ) => {
+        CommandHelp {
+            name: $name,
+            usage: $usage,
+            description: $description,
+            context: HelpContext::$context,
+        }
+    };
+}
+
+pub const COMMANDS: &[CommandHelp] = &[
+    help!(Global, "help", "help", "Show this help"),
+    help!(
+        Global,
+        "help keys",
+        "help keys",
+        "Show the keys of the current view"
+    ),
+    help!(Global, "quit", "quit", "Quit ncspot"),
+    help!(Global, "logout", "logout", "Log out and quit"),
+    help!(Global, "focus", "focus <SCREEN>", "Switch to a screen"),
+    help!(
+        Global,
+        "split",
+        "split [LEFT RIGHT]",
+        "Show two screens side by side"
+    ),
+    help!(
+        Global,
+        "pane",
+        "pane [left|right]",
+        "Switch to the other pane"
+    ),
+    help!(Global, "search", "search <QUERY>", "Search Spotify"),
+    help!(Global, "clear", "clear", "Clear the queue"),
+    help!(Global, "update", "update", "Update the library"),
+    help!(
+        Global,
+        "newplaylist",
+        "newplaylist <NAME>",
+        "Create a playlist from the queue"
+    ),
+    help!(
+        Global,
+        "smartplaylist",
+        "smartplaylist <NAME>",
+        "Create a smart playlist"
+    ),
+    help!(Global, "paste", "paste", "Queue the items in the clipboard"),
+    help!(Global, "undo", "undo", "Undo the last destructive action"),
+    help!(Global, "redo", "redo", "Redo the last undone action"),
+    help!(Global, "exec", "exec <CMD>", "Run a shell command"),
+    help!(Global, "noop", "noop", "Do nothing"),
+    help!(Global, "reload", "reload", "Reload the configuration"),
+    help!(Global, "redraw", "redraw", "Redraw the screen"),
+    help!(Global, "reconnect", "reconnect", "Reconnect to Spotify"),
+    help!(
+        Global,
+        "offline",
+        "offline [on|off]",
+        "Use cached data only"
+    ),
+    help!(Global, "theme", "theme [NAME]", "Switch to a theme file"),
+    help!(Global, "mixer", "mixer", "Open the mixer"),
+    help!(Global, "cache", "cache", "Show the caches"),
+    help!(Global, "cache clear", "cache clear [KIND]", "Clear a cache"),
+    help!(
+        Global,
+        "credits",
+        "credits",
+        "Show the credits of the current track"
+    ),
+    help!(Global, "stats", "stats", "Show listening statistics"),
+    help!(Global, "downloads", "downloads", "Show the downloads"),
+    help!(
+        Global,
+        "downloads cancel",
+        "downloads cancel",
+        "Cancel all downloads"
+    ),
+    help!(
+        Global,
+        "debug dump",
+        "debug dump [PATH]",
+        "Write the debug journal to a file"
+    ),
+    help!(
+        Global,
+        "history export",
+        "history export [PATH]",
+        "Export the play history"
+    ),
+    help!(
+        Navigation,
+        "move",
+        "move <DIRECTION> [STEP]",
+        "Move the selection or scroll"
+    ),
+    help!(Navigation, "back", "back", "Go back to the previous view"),
+    help!(Navigation, "jump", "jump <QUERY>", "Jump to the next match"),
+    help!(Navigation, "jumpnext", "jumpnext", "Jump to the next match"),
+    help!(
+        Navigation,
+        "jumpprevious",
+        "jumpprevious",
+        "Jump to the previous match"
+    ),
+    help!(
+        Navigation,
+        "jumplist",
+        "jumplist <back|forward>",
+        "Return to where you were before"
+    ),
+    help!(
+        Navigation,
+        "setmark",
+        "setmark [NAME]",
+        "Mark the current view and item"
+    ),
+    help!(
+        Navigation,
+        "gotomark",
+        "gotomark [NAME]",
+        "Go to a marked view and item"
+    ),
+    help!(Playback, "playpause", "playpause", "Play or pause"),
+    help!(Playback, "stop", "stop", "Stop playback"),
+    help!(Playback, "next", "next", "Play the next track"),
+    help!(Playback, "previous", "previous", "Play the previous track"),
+    help!(Playback, "seek", "seek [+|-]<TIME>", "Seek to or by a time"),
+    help!(Playback, "volup", "volup [AMOUNT]", "Raise the volume"),
+    help!(Playback, "voldown", "voldown [AMOUNT]", "Lower the volume"),
+    help!(
+        Playback,
+        "repeat",
+        "repeat [MODE]",
+        "Set or cycle the repeat mode"
+    ),
+    help!(
+        Playback,
+        "shuffle",
+        "shuffle [on|off]",
+        "Turn shuffle on or off"
+    ),
+    help!(
+        Playback,
+        "autoplay",
+        "autoplay [on|off]",
+        "Continue with recommendations"
+    ),
+    help!(
+        Playback,
+        "abloop",
+        "abloop [a|b|off]",
+        "Loop a part of the track"
+    ),
+    help!(
+        Playback,
+        "volnorm",
+        "volnorm [on|off]",
+        "Turn volume normalization on or off"
+    ),
+    help!(Playback, "preamp", "preamp [DB|reset]", "Set the preamp"),
+    help!(
+        Playback,
+        "trackgain",
+        "trackgain [DB|reset]",
+        "Set the gain of the current track"
+    ),
+    help!(
+        Playback,
+        "device",
+        "device [NAME]",
+        "Switch the audio output device"
+    ),
+    help!(
+        Playback,
+        "backend",
+        "backend [NAME]",
+        "Switch the audio backend"
+    ),
+    help!(
+        Playback,
+        "nextchapter",
+        "nextchapter",
+        "Skip to the next chapter"
+    ),
+    help!(
+        Playback,
+        "previouschapter",
+        "previouschapter",
+        "Go back to the previous chapter"
+    ),
+    help!(
+        Playback,
+        "bookmark",
+        "bookmark [NAME]",
+        "Bookmark the current position"
+    ),
+    help!(
+        Playback,
+        "bookmark clear",
+        "bookmark clear",
+        "Remove the bookmarks of the item"
+    ),
+    help!(
+        Playback,
+        "save current",
+        "save current",
+        "Save the current track"
+    ),
+    help!(
+        Playback,
+        "add current",
+        "add current",
+        "Add the current track to a playlist"
+    ),
+    help!(
+        Playback,
+        "lyrics",
+        "lyrics",
+        "Show the lyrics of the current track"
+    ),
+    help!(
+        Playback,
+        "visualizer",
+        "visualizer [STYLE]",
+        "Show the audio visualizer"
+    ),
+    help!(List, "play", "play", "Play the selected item"),
+    help!(List, "queue", "queue", "Queue the selected item"),
+    help!(List, "playnext", "playnext", "Play the selected item next"),
+    help!(
+        List,
+        "save",
+        "save",
+        "Save the selected item to the library"
+    ),
+    help!(List, "add", "add", "Add the selected item to a playlist"),
+    help!(List, "delete", "delete", "Delete the selected item"),
+    help!(
+        List,
+        "open",
+        "open <ITEM|URL>",
+        "Open the context menu of an item, or a Spotify URL or URI"
+    ),
+    help!(
+        List,
+        "goto",
+        "goto <album|artist>",
+        "Go to the album or artist"
+    ),
+    help!(List, "insert", "insert [URL]", "Insert a track from a URL"),
+    help!(List, "share", "share <ITEM>", "Copy the URL of an item"),
+    help!(List, "similar", "similar <ITEM>", "Show similar tracks"),
+    help!(List, "radio", "radio <ITEM>", "Start a radio from an item"),
+    help!(List, "sort", "sort <KEY> [DIRECTION]", "Sort the list"),
+    help!(
+        List,
+        "filter",
+        "filter [QUERY]",
+        "Show only the matching items"
+    ),
+    help!(
+        List,
+        "mark",
+        "mark [range|clear]",
+        "Mark items for bulk actions"
+    ),
+    help!(List, "download", "download", "Download the selected item"),
+    help!(
+        Playlist,
+        "group",
+        "group [album|artist|none]",
+        "Group the tracks"
+    ),
+    help!(
+        Playlist,
+        "contributor",
+        "contributor [NAME]",
+        "Show tracks added by someone"
+    ),
+    help!(
+        Playlist,
+        "duplicates",
+        "duplicates",
+        "Find duplicate tracks"
+    ),
+    help!(
+        Playlist,
+        "unavailable",
+        "unavailable",
+        "Find unavailable tracks"
+    ),
+    help!(
+        Artist,
+        "discography",
+        "discography",
+        "Play all releases, oldest first"
+    ),
+    help!(
+        Playlists,
+        "merge",
+        "merge [MODE]",
+        "Merge the marked playlists"
+    ),
+    help!(
+        Playlists,
+        "folder new",
+        "folder new <NAME>",
+        "Create a playlist folder"
+    ),
+    help!(
+        Playlists,
+        "folder move",
+        "folder move [NAME]",
+        "Move a playlist to a folder"
+    ),
+    help!(
+        Queue,
+        "shift",
+        "shift <up|down> [AMOUNT]",
+        "Move the selected track"
+    ),
+    help!(
+        Queue,
+        "save queue",
+        "save queue",
+        "Save the queue as a playlist"
+    ),
+];
+
+/// The keys bound to the command called `name` in `bindings`, by the commands they run. Commands
+/// with arguments are told apart, like `move up` and `move down`.
+pub fn keys_of(
+    bindings: &HashMap<String, Vec<Command>>,
+    name: &str,
+) -> BTreeMap<String, Vec<String>> {
+    let mut keys: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, commands) in bindings {
+        for command in commands.iter().filter(|c| c.basename() == name) {
+            keys.entry(command.to_string())
+                .or_default()
+                .push(key.clone());
+        }
+    }
+    for keys in keys.values_mut() {
+        keys.sort();
+    }
+    keys
+}
+
+/// Whether `query` appears in the usage, the description or the keys of `help`, ignoring case.
+pub fn matches(help: &CommandHelp, keys: &BTreeMap<String, Vec<String>>, query: &str) -> bool {
+    let query = query.to_lowercase();
+    help.usage.to_lowercase().contains(&query)
+        || help.description.to_lowercase().contains(&query)
+        || keys.iter().any(|(command, keys)| {
+            command.to_lowercase().contains(&query)
+                || keys.iter().any(|key| key.to_lowercase() == query)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_of() {
+        let bindings = HashMap::from([
+            (
+                "k".to_string(),
+                vec![Command::Move(
+                    crate::command::MoveMode::Up,
+                    Default::default(),
+                )],
+            ),
+            (
+                "Up".to_string(),
+                vec![Command::Move(
+                    crate::command::MoveMode::Up,
+                    Default::default(),
+                )],
+            ),
+            ("P".to_string(), vec![Command::TogglePlay]),
+        ]);
+        let keys = keys_of(&bindings, "move");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys.values().next().unwrap(), &["Up", "k"]);
+
+        let help = COMMANDS.iter().find(|c| c.name == "playpause").unwrap();
+        let keys = keys_of(&bindings, "playpause");
+        assert!(matches(help, &keys, "PAUSE"));
+        assert!(matches(help, &keys, "p"));
+        assert!(!matches(help, &keys, "volume"));
+    }
+}
@@ -0,0 +1,159 @@
+//! Shell commands run on playback events, configured in `[hooks]` or by plugins.
+//!
+//! Each hook runs in the background with the shell, without access to the terminal, and gets the
+//! event and the playing item in `NCSPOT_*` environment variables.
+
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+
+use log::{error, info};
+
+use crate::config::{Config, ConfigHooks, ConfigValues};
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+
+/// Runs the scripts configured in `[hooks]` when the playback changes, with the playing item in
+/// environment variables, and passes the events on to the handlers of Rhai scripts.
+pub struct Hooks {
+    cfg: Arc<Config>,
+    library: Arc<Library>,
+    /// The URI of the item the last `on_track_change` hook ran for.
+    current: Option<String>,
+    playing: bool,
+}
+
+impl Hooks {
+    pub fn new(cfg: Arc<Config>, library: Arc<Library>) -> Self {
+        Self {
+            cfg,
+            library,
+            current: None,
+            playing: false,
+        }
+    }
+
+    /// Run the hooks for the given playback state and item.
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let hooks = configured(&self.cfg.values());
+        match state {
+            PlayerEvent::Playing(_) => {
+                let uri = playable.map(Playable::uri);
+                if uri.is_some() && uri != self.current {
+                    self.current = uri;
+                    self.run("track_change", &hooks, |h| &h.on_track_change, playable);
+                }
+                if !self.playing {
+                    self.run("play", &hooks, |h| &h.on_play, playable);
+                }
+                self.playing = true;
+            }
+            PlayerEvent::Paused(_) => {
+                if self.playing {
+                    self.run("pause", &hooks, |h| &h.on_pause, playable);
+                }
+                self.playing = false;
+            }
+            PlayerEvent::Stopped => {
+                self.current = None;
+                self.playing = false;
+            }
+            PlayerEvent::FinishedTrack => {}
+        }
+    }
+
+    /// Run the `on_queue_empty` hook after the last item of the queue was played.
+    pub fn queue_empty(&self) {
+        let hooks = configured(&self.cfg.values());
+        self.run("queue_empty", &hooks, |h| &h.on_queue_empty, None);
+    }
+
+    /// Run the hook selected by `hook` of all `hooks` that define it.
+    fn run(
+        &self,
+        event: &str,
+        hooks: &[ConfigHooks],
+        hook: fn(&ConfigHooks) -> &Option<String>,
+        playable: Option<&Playable>,
+    ) {
+        #[cfg(feature = "scripting")]
+        crate::scripting::emit(event, playable);
+
+        let commands: Vec<&String> = hooks.iter().filter_map(|h| hook(h).as_ref()).collect();
+        if commands.is_empty() {
+            return;
+        }
+        let mut variables = vec![("NCSPOT_EVENT", event.to_string())];
+        if let Some(playable) = playable {
+            let format = |format| Playable::format(playable, format, &self.library);
+            variables.extend([
+                ("NCSPOT_TITLE", format("%title")),
+                ("NCSPOT_ARTISTS", format("%artists")),
+                ("NCSPOT_ALBUM", format("%album")),
+                ("NCSPOT_URI", playable.uri()),
+                ("NCSPOT_DURATION", (playable.duration() / 1000).to_string()),
+            ]);
+        }
+        for command in commands {
+            spawn(command, &variables);
+        }
+    }
+}
+
+/// The hooks of the configuration file and of the plugins.
+fn configured(values: &ConfigValues) -> Vec<ConfigHooks> {
+    values
+        .hooks
+        .iter()
+        .chain(&values.plugin_hooks)
+        .cloned()
+        .collect()
+}
+
+/// Run the `on_auth_failure` hook with the error that made logging in fail.
+pub fn auth_failure(values: &ConfigValues, error: &str) {
+    for hook in configured(values)
+        .iter()
+        .filter_map(|hooks| hooks.on_auth_failure.as_ref())
+    {
+        spawn(
+            hook,
+            &[
+                ("NCSPOT_EVENT", "auth_failure".to_string()),
+                ("NCSPOT_ERROR", error.to_string()),
+            ],
+        );
+    }
+}
+
+/// Run `hook` with the shell in the background, without access to the terminal of the TUI.
+fn spawn(hook: &str, variables: &[(&str, String)]) {
+    #[cfg(unix)]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    };
+    command
+        .arg(hook)
+        .envs(variables.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    match command.spawn() {
+        Ok(mut child) => {
+            info!("running hook {hook}");
+            // Wait in the background so the finished process doesn't linger.
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => error!("could not run hook {hook}: {e}"),
+    }
+}
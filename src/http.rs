@@ -0,0 +1,569 @@
+//! A small HTTP server to control ncspot from other devices, like a phone or a home dashboard.
+//!
+//! Every request has to carry the configured token as `Authorization: Bearer <token>`. Only
+//! `GET /events` also takes it as the `token` query parameter, as `EventSource` can't set headers.
+//! Commands that run programs or scripts, write files or end the session can't be run over HTTP.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info};
+use rspotify::model::SearchType;
+use rspotify::model::search::SearchResult;
+use serde_json::{Value, json};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Handle;
+use tokio::sync::{Semaphore, oneshot, watch::Receiver};
+use tokio::time::{Instant, timeout_at};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::WatchStream;
+
+use crate::cli;
+use crate::command::{self, Command};
+use crate::config::ConfigHttp;
+use crate::events::{Event, EventManager};
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::episode::Episode;
+use crate::model::playlist::Playlist;
+use crate::model::show::Show;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::spotify_url::SpotifyUrl;
+use crate::status::Status;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8182";
+
+/// The maximum size of the request line, headers and body together.
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+/// The largest body that is accepted, checked before it is read.
+const MAX_BODY: usize = 16 * 1024;
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Limits the threads that run blocking calls for requests at the same time.
+static BLOCKING: Semaphore = Semaphore::const_new(4);
+
+/// The playback commands that are available as `POST /playback/<command>`.
+const PLAYBACK_COMMANDS: [&str; 6] = ["play", "pause", "playpause", "stop", "next", "previous"];
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// The headers, with lowercase names.
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+struct Response {
+    code: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { code: 200, body }
+    }
+
+    fn error(code: u16, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Server {
+    token: String,
+    ev: EventManager,
+    queue: Arc<Queue>,
+    rx: Receiver<Status>,
+}
+
+/// Start the server configured in `[http]`, if there is one.
+pub fn start(
+    handle: &Handle,
+    cfg: Option<&ConfigHttp>,
+    ev: EventManager,
+    queue: Arc<Queue>,
+    rx: Receiver<Status>,
+) -> Result<(), String> {
+    let Some(cfg) = cfg else {
+        return Ok(());
+    };
+    let token = cfg
+        .token
+        .clone()
+        .filter(|token| !token.is_empty())
+        .ok_or("the HTTP server requires a token")?;
+    let address = cfg.address.as_deref().unwrap_or(DEFAULT_ADDRESS);
+
+    let listener = std::net::TcpListener::bind(address)
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            let _guard = handle.enter();
+            TcpListener::from_std(listener)
+        })
+        .map_err(|e| format!("could not listen on {address}: {e}"))?;
+    info!("HTTP server listening on {address}");
+
+    let server = Server {
+        token,
+        ev,
+        queue,
+        rx,
+    };
+    handle.spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, address)) => {
+                    debug!("HTTP connection from {address}");
+                    tokio::spawn(server.clone().connection(stream));
+                }
+                Err(e) => error!("Error accepting HTTP connection: {e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+impl Server {
+    async fn connection(self, mut stream: TcpStream) {
+        let (reader, mut writer) = stream.split();
+        let mut reader = BufReader::new(reader.take(MAX_REQUEST_SIZE));
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let timed_out = || Response::error(408, "the request took too long");
+        let response = match timeout_at(deadline, read_head(&mut reader)).await {
+            Ok(Ok(request)) if request.method == "OPTIONS" => {
+                write_preflight(&mut writer).await.ok();
+                return;
+            }
+            Ok(Ok(request)) if !authorized(&request, &self.token) => {
+                Response::error(401, "missing or wrong token")
+            }
+            Ok(Ok(request)) if request.method == "GET" && request.path == "/events" => {
+                return self.events(&mut writer).await;
+            }
+            Ok(Ok(mut request)) => {
+                match timeout_at(deadline, read_body(&mut reader, &mut request)).await {
+                    Ok(Ok(())) => self.handle(request).await,
+                    Ok(Err(response)) => response,
+                    Err(_) => timed_out(),
+                }
+            }
+            Ok(Err(e)) => Response::error(400, e),
+            Err(_) => timed_out(),
+        };
+        if let Err(e) = write_response(&mut writer, &response).await {
+            debug!("Could not send HTTP response: {e}");
+        }
+    }
+
+    async fn handle(&self, request: Request) -> Response {
+        let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["status"]) => Response::ok(json!(*self.rx.borrow())),
+            ("GET", ["queue"]) => Response::ok(json!({
+                "current": self.queue.get_current_index(),
+                "items": *self.queue.queue.read().unwrap(),
+            })),
+            ("POST", ["queue"]) => self.enqueue(&request.body).await,
+            ("DELETE", ["queue"]) => {
+                self.queue.clear();
+                Response::ok(json!({}))
+            }
+            ("POST", ["queue", index, "play"]) => self.queue_item(index, |queue, index| {
+                queue.play(index, false, false);
+            }),
+            ("DELETE", ["queue", index]) => self.queue_item(index, Queue::remove),
+            ("POST", ["playback", command]) if PLAYBACK_COMMANDS.contains(command) => {
+                self.run(command)
+            }
+            ("POST", ["command"]) => match serde_json::from_str::<Value>(&request.body) {
+                Ok(body) => match body["command"].as_str() {
+                    Some(command) => self.run(command),
+                    None => Response::error(400, "the body needs a \"command\""),
+                },
+                Err(e) => Response::error(400, format!("invalid JSON: {e}")),
+            },
+            ("GET", ["search"]) => self.search(&request.query).await,
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    /// Check `command` and pass it on to be run like a command typed after `:`.
+    fn run(&self, command: &str) -> Response {
+        match command::parse(command) {
+            Ok(commands) => match commands.iter().find(|command| !remote(command)) {
+                Some(command) => Response::error(
+                    403,
+                    format!("\"{}\" can't be run over HTTP", command.basename()),
+                ),
+                None => {
+                    self.ev.send(Event::IpcInput(command.to_string()));
+                    Response::ok(json!({}))
+                }
+            },
+            Err(e) => Response::error(400, e.to_string()),
+        }
+    }
+
+    /// Add the items of the Spotify URI or URL in the `uri` of `body` to the end of the queue, or
+    /// after the playing item if `next` is true.
+    async fn enqueue(&self, body: &str) -> Response {
+        let body: Value = match serde_json::from_str(body) {
+            Ok(body) => body,
+            Err(e) => return Response::error(400, format!("invalid JSON: {e}")),
+        };
+        let Some(url) = body["uri"]
+            .as_str()
+            .and_then(|uri| SpotifyUrl::from_uri(uri).or_else(|| SpotifyUrl::from_url(uri)))
+        else {
+            return Response::error(400, "the body needs a Spotify \"uri\"");
+        };
+        let next = body["next"].as_bool().unwrap_or(false);
+
+        let queue = self.queue.clone();
+        let added = blocking(move || {
+            let playables = cli::resolve_playables(&queue.get_spotify(), &url);
+            if next {
+                queue.append_next(&playables);
+            } else {
+                for playable in &playables {
+                    queue.append(playable.clone());
+                }
+            }
+            playables.len()
+        })
+        .await;
+        match added {
+            0 => Response::error(404, "nothing to add"),
+            added => Response::ok(json!({ "added": added })),
+        }
+    }
+
+    /// Run `action` with the queue index in the path segment `index`.
+    fn queue_item(&self, index: &str, action: impl FnOnce(&Queue, usize)) -> Response {
+        match index.parse() {
+            Ok(index) if index < self.queue.len() => {
+                action(&self.queue, index);
+                Response::ok(json!({}))
+            }
+            _ => Response::error(404, "no such queue item"),
+        }
+    }
+
+    /// Search Spotify for the `q` parameter, for the item type in `type` or tracks by default.
+    async fn search(&self, query: &HashMap<String, String>) -> Response {
+        let Some(text) = query.get("q").filter(|q| !q.is_empty()).cloned() else {
+            return Response::error(400, "the \"q\" parameter is missing");
+        };
+        let search_type = match query.get("type").map(String::as_str) {
+            None | Some("track") => SearchType::Track,
+            Some("album") => SearchType::Album,
+            Some("artist") => SearchType::Artist,
+            Some("playlist") => SearchType::Playlist,
+            Some("show") => SearchType::Show,
+            Some("episode") => SearchType::Episode,
+            Some(other) => return Response::error(400, format!("unknown type \"{other}\"")),
+        };
+
+        let spotify = self.queue.get_spotify();
+        let result = blocking(move || spotify.api.search(search_type, &text, 50, 0)).await;
+        let items = match result {
+            Ok(SearchResult::Tracks(page)) => {
+                json!(page.items.iter().map(Track::from).collect::<Vec<_>>())
+            }
+            Ok(SearchResult::Albums(page)) => {
+                json!(page.items.iter().map(Album::from).collect::<Vec<_>>())
+            }
+            Ok(SearchResult::Artists(page)) => {
+                json!(page.items.iter().map(Artist::from).collect::<Vec<_>>())
+            }
+            Ok(SearchResult::Playlists(page)) => {
+                json!(page.items.iter().map(Playlist::from).collect::<Vec<_>>())
+            }
+            Ok(SearchResult::Shows(page)) => {
+                json!(page.items.iter().map(Show::from).collect::<Vec<_>>())
+            }
+            Ok(SearchResult::Episodes(page)) => {
+                json!(page.items.iter().map(Episode::from).collect::<Vec<_>>())
+            }
+            Err(()) => return Response::error(502, "the search failed"),
+        };
+        Response::ok(json!({ "items": items }))
+    }
+
+    /// Send the status as server-sent events until the client disconnects.
+    async fn events(&self, writer: &mut (impl AsyncWrite + Unpin)) {
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\n";
+        if writer.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        let mut updates = WatchStream::new(self.rx.clone());
+        while let Some(status) = updates.next().await {
+            let event = format!("data: {}\n\n", json!(status));
+            if writer.write_all(event.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+                debug!("Closing HTTP event stream");
+                return;
+            }
+        }
+    }
+}
+
+/// Whether `command` may be run over HTTP. These are the commands to control playback, the queue
+/// and the views, leaving out those that run programs or scripts, write files, open a browser or
+/// end the session.
+fn remote(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::TogglePlay
+            | Command::Stop
+            | Command::Previous
+            | Command::Next
+            | Command::Play
+            | Command::PlayNext
+            | Command::Queue
+            | Command::Clear
+            | Command::Seek(_)
+            | Command::VolumeUp(_)
+            | Command::VolumeDown(_)
+            | Command::Repeat(_)
+            | Command::Shuffle(_)
+            | Command::ShuffleMode(_)
+            | Command::Autoplay(_)
+            | Command::Volnorm(_)
+            | Command::Preamp(_)
+            | Command::TrackGain(_)
+            | Command::Device(_)
+            | Command::Loop(_)
+            | Command::SaveCurrent
+            | Command::Radio(_)
+            | Command::NextChapter
+            | Command::PreviousChapter
+            | Command::Bookmark(_)
+            | Command::Focus(_)
+            | Command::Goto(_)
+            | Command::Move(_, _)
+            | Command::Search(_)
+            | Command::Back
+            | Command::Redraw
+            | Command::Noop
+    )
+}
+
+/// Run the blocking `f`, like a call of the Web API, on its own thread. At most as many run at
+/// once as [BLOCKING] has permits, the others wait for a permit.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let permit = BLOCKING
+        .acquire()
+        .await
+        .expect("the semaphore is never closed");
+    let (tx, rx) = oneshot::channel();
+    thread::spawn(move || {
+        let _permit = permit;
+        tx.send(f())
+    });
+    rx.await.expect("the blocking call panicked")
+}
+
+/// Read the request line and the headers. The body is left to [read_body], so it is only read
+/// for authorized requests.
+async fn read_head(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Request, String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("invalid request line".into());
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        headers: HashMap::new(),
+        body: String::new(),
+    };
+
+    loop {
+        line.clear();
+        if reader
+            .read_line(&mut line)
+            .await
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            return Err("incomplete request".into());
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            break;
+        };
+        request
+            .headers
+            .insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+    Ok(request)
+}
+
+/// Read the body announced by the `Content-Length` of `request` into it.
+async fn read_body(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    request: &mut Request,
+) -> Result<(), Response> {
+    let length: usize = match request.headers.get("content-length") {
+        Some(length) => length
+            .parse()
+            .map_err(|_| Response::error(400, "invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(Response::error(413, "the body is too large"));
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .await
+        .map_err(|e| Response::error(400, e.to_string()))?;
+    request.body = String::from_utf8(body).map_err(|e| Response::error(400, e.to_string()))?;
+    Ok(())
+}
+
+/// Whether `request` carries `token` in its `Authorization` header, or in the query of
+/// `GET /events`.
+fn authorized(request: &Request, token: &str) -> bool {
+    let query = (request.method == "GET" && request.path == "/events")
+        .then(|| request.query.get("token").map(String::as_str))
+        .flatten();
+    let given = request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query);
+    given.is_some_and(|given| {
+        // Compare every byte so the time taken doesn't tell how much of the token is right.
+        given.len() == token.len()
+            && given
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    })
+}
+
+async fn write_response(
+    writer: &mut (impl AsyncWrite + Unpin),
+    response: &Response,
+) -> std::io::Result<()> {
+    let reason = match response.code {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Content Too Large",
+        _ => "Bad Gateway",
+    };
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        response.code,
+        body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body.as_bytes()).await
+}
+
+/// Allow browsers to send requests with a token from other origins, like a dashboard page.
+async fn write_preflight(writer: &mut (impl AsyncWrite + Unpin)) -> std::io::Result<()> {
+    let head = "HTTP/1.1 204 No Content\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, DELETE\r\nAccess-Control-Allow-Headers: Authorization, Content-Type\r\nConnection: close\r\n\r\n";
+    writer.write_all(head.as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read `request`, returning the status code of the error response if it is rejected.
+    fn parse(request: &str) -> Result<Request, u16> {
+        futures::executor::block_on(async {
+            let mut reader = request.as_bytes();
+            let mut request = read_head(&mut reader).await.map_err(|_| 400u16)?;
+            read_body(&mut reader, &mut request)
+                .await
+                .map_err(|response| response.code)?;
+            Ok(request)
+        })
+    }
+
+    #[test]
+    fn test_read_request() {
+        let request = parse(
+            "POST /queue?token=a%20b HTTP/1.1\r\nHost: x\r\nContent-Length: 4\r\n\r\n{}\r\nignored",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/queue");
+        assert_eq!(request.query["token"], "a b");
+        assert_eq!(request.headers["host"], "x");
+        assert_eq!(request.body, "{}\r\n");
+
+        assert!(parse("GET /status HTTP/1.1\r\n").is_err());
+        assert!(parse("\r\n").is_err());
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        assert_eq!(
+            parse("POST /queue HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n").err(),
+            Some(413)
+        );
+        assert_eq!(
+            parse("POST /queue HTTP/1.1\r\nContent-Length: x\r\n\r\n").err(),
+            Some(400)
+        );
+    }
+
+    #[test]
+    fn test_authorized() {
+        let request =
+            parse("GET /status HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n").unwrap();
+        assert!(authorized(&request, "secret"));
+        assert!(!authorized(&request, "secreT"));
+        assert!(!authorized(&request, "secret2"));
+
+        let request = parse("GET /events?token=secret HTTP/1.1\r\n\r\n").unwrap();
+        assert!(authorized(&request, "secret"));
+        let request = parse("GET /events HTTP/1.1\r\n\r\n").unwrap();
+        assert!(!authorized(&request, "secret"));
+        let request = parse("GET /status?token=secret HTTP/1.1\r\n\r\n").unwrap();
+        assert!(!authorized(&request, "secret"));
+        let request = parse("POST /events?token=secret HTTP/1.1\r\n\r\n").unwrap();
+        assert!(!authorized(&request, "secret"));
+    }
+
+    #[test]
+    fn test_remote_commands() {
+        let allowed = |input: &str| command::parse(input).unwrap().iter().all(remote);
+        assert!(allowed("playpause"));
+        assert!(allowed("next; volup 5"));
+        assert!(!allowed("exec touch /tmp/x"));
+        assert!(!allowed("next; exec touch /tmp/x"));
+        assert!(!allowed("quit"));
+        assert!(!allowed("history export /tmp/x"));
+    }
+}
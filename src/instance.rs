@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::sync::OnceLock;
+
+use log::{error, info};
+use ncspot::{BIN_NAME, USER_STATE_FILE_NAME};
+
+use crate::config::config_path;
+
+/// The maximum amount of instances that can run with the same configuration directory.
+const MAX_INSTANCES: usize = 32;
+
+static INSTANCE: OnceLock<Instance> = OnceLock::new();
+
+/// A running ncspot process. Instances sharing a configuration directory are told apart by their
+/// number, which is reserved with a lock file for as long as the process runs.
+pub struct Instance {
+    number: usize,
+    /// The name passed on the command line or set in the configuration.
+    name: OnceLock<String>,
+    default_name: String,
+    /// Held for the lifetime of the process, the lock is released by the OS when it exits.
+    _lock: Option<File>,
+}
+
+impl Instance {
+    /// The number of this instance, starting at 1 for the first one.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Whether this is the first instance, which uses the regular state files.
+    pub fn is_primary(&self) -> bool {
+        self.number == 1
+    }
+
+    /// The name of this instance as shown in the audio mixer and MPRIS clients.
+    pub fn name(&self) -> &str {
+        self.name.get().unwrap_or(&self.default_name)
+    }
+}
+
+/// Reserve the lowest free instance number for this process. `name` overrides the default name,
+/// which is "ncspot" for the first instance and "ncspot-<number>" for all others.
+pub fn register(name: Option<String>) -> &'static Instance {
+    INSTANCE.get_or_init(|| {
+        let (number, lock) = reserve_number();
+        let default_name = match number {
+            1 => BIN_NAME.to_string(),
+            _ => format!("{BIN_NAME}-{number}"),
+        };
+        info!("running as instance {number}");
+        Instance {
+            number,
+            name: name.map(OnceLock::from).unwrap_or_default(),
+            default_name,
+            _lock: lock,
+        }
+    })
+}
+
+/// Name the first instance `name` from the configuration, unless a name was passed on the command
+/// line. Other instances keep their numbered names to tell them apart.
+pub fn set_configured_name(name: &str) {
+    if let Some(instance) = current()
+        && instance.is_primary()
+    {
+        instance.name.set(name.to_string()).ok();
+    }
+}
+
+/// The instance registered for this process, if any.
+pub fn current() -> Option<&'static Instance> {
+    INSTANCE.get()
+}
+
+/// The name of this instance, or the default name if none was registered.
+pub fn name() -> &'static str {
+    current().map(Instance::name).unwrap_or(BIN_NAME)
+}
+
+/// The file name of the user state for this instance. Secondary instances keep their own state so
+/// they don't overwrite the queue of the first one.
+pub fn user_state_file_name() -> String {
+    match current() {
+        Some(instance) if !instance.is_primary() => {
+            format!("userstate.{}.cbor", instance.number())
+        }
+        _ => USER_STATE_FILE_NAME.to_string(),
+    }
+}
+
+/// The file name of the queue journal for this instance.
+pub fn journal_file_name() -> String {
+    match current() {
+        Some(instance) if !instance.is_primary() => format!("journal.{}.log", instance.number()),
+        _ => "journal.log".to_string(),
+    }
+}
+
+fn reserve_number() -> (usize, Option<File>) {
+    for number in 1..=MAX_INSTANCES {
+        let path = config_path(&format!("instance.{number}.lock"));
+        let file = match File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!("could not open instance lock {}: {e}", path.display());
+                break;
+            }
+        };
+        match file.try_lock() {
+            Ok(()) => return (number, Some(file)),
+            Err(std::fs::TryLockError::WouldBlock) => continue,
+            Err(e) => {
+                error!("could not lock {}: {e}", path.display());
+                break;
+            }
+        }
+    }
+
+    // Without working locks every process has to assume it's the only one.
+    (1, None)
+}
@@ -1,29 +1,53 @@
-use std::{io, path::PathBuf};
+use std::io;
+#[cfg(unix)]
+use std::path::PathBuf;
 
 use futures::SinkExt;
 use log::{debug, error, info};
-use tokio::net::{UnixListener, UnixStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
 use tokio::runtime::Handle;
-use tokio::sync::watch::{Receiver, Sender};
+use tokio::sync::watch::Receiver;
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::WatchStream;
 use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
 
+use crate::command;
 use crate::events::{Event, EventManager};
-use crate::model::playable::Playable;
-use crate::spotify::PlayerEvent;
+use crate::status::Status;
+
+/// The file name of the socket of the first instance.
+#[cfg(unix)]
+pub const SOCKET_NAME: &str = "ncspot.sock";
+
+/// The named pipe of the first instance on Windows, which stands in for the socket.
+#[cfg(windows)]
+pub const PIPE_NAME: &str = r"\\.\pipe\ncspot";
 
 pub struct IpcSocket {
-    tx: Sender<Status>,
+    /// The socket file, which is removed again on exit. Named pipes go away by themselves.
+    #[cfg(unix)]
     path: PathBuf,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct Status {
-    mode: PlayerEvent,
-    playable: Option<Playable>,
+/// A command in JSON, the alternative to sending the command as a plain line.
+#[derive(Deserialize)]
+struct Request {
+    command: String,
 }
 
+/// The answer to a [Request], which tells whether the command could be parsed.
+#[derive(Serialize, Deserialize)]
+pub struct Response {
+    /// Always `"response"`, to tell it apart from status updates.
+    pub event: String,
+    pub error: Option<String>,
+}
+
+#[cfg(unix)]
 impl Drop for IpcSocket {
     fn drop(&mut self) {
         self.try_remove_socket();
@@ -31,7 +55,13 @@ impl Drop for IpcSocket {
 }
 
 impl IpcSocket {
-    pub fn new(handle: &Handle, path: PathBuf, ev: EventManager) -> io::Result<Self> {
+    #[cfg(unix)]
+    pub fn new(
+        handle: &Handle,
+        path: PathBuf,
+        ev: EventManager,
+        rx: Receiver<Status>,
+    ) -> io::Result<Self> {
         let path = if path.exists() && Self::is_open_socket(&path) {
             let mut new_path = path;
             new_path.set_file_name(format!("ncspot.{}.sock", std::process::id()));
@@ -45,34 +75,46 @@ impl IpcSocket {
 
         info!("Creating IPC domain socket at {path:?}");
 
-        let status = Status {
-            mode: PlayerEvent::Stopped,
-            playable: None,
-        };
-
-        let (tx, rx) = tokio::sync::watch::channel(status);
         let listener_path = path.clone();
         handle.spawn(async move {
             let listener =
                 UnixListener::bind(listener_path).expect("Could not create IPC domain socket");
-            Self::worker(listener, ev, rx.clone()).await;
+            Self::worker(listener, ev, rx).await;
         });
 
-        Ok(Self { tx, path })
+        Ok(Self { path })
     }
 
-    fn is_open_socket(path: &PathBuf) -> bool {
-        std::os::unix::net::UnixStream::connect(path).is_ok()
+    /// Listen on the named pipe [PIPE_NAME], or one with the process id appended if another
+    /// instance already has it.
+    #[cfg(windows)]
+    pub fn new(handle: &Handle, ev: EventManager, rx: Receiver<Status>) -> io::Result<Self> {
+        let _guard = handle.enter();
+        let (name, server) = match ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(PIPE_NAME)
+        {
+            Ok(server) => (PIPE_NAME.to_string(), server),
+            Err(_) => {
+                let name = format!("{PIPE_NAME}.{}", std::process::id());
+                let server = ServerOptions::new()
+                    .first_pipe_instance(true)
+                    .create(&name)?;
+                (name, server)
+            }
+        };
+
+        info!("Creating IPC named pipe at {name}");
+        handle.spawn(Self::pipe_worker(name, server, ev, rx));
+        Ok(Self {})
     }
 
-    pub fn publish(&self, event: &PlayerEvent, playable: Option<Playable>) {
-        let status = Status {
-            mode: event.clone(),
-            playable,
-        };
-        self.tx.send(status).unwrap();
+    #[cfg(unix)]
+    fn is_open_socket(path: &PathBuf) -> bool {
+        std::os::unix::net::UnixStream::connect(path).is_ok()
     }
 
+    #[cfg(unix)]
     async fn worker(listener: UnixListener, ev: EventManager, tx: Receiver<Status>) {
         loop {
             match listener.accept().await {
@@ -89,12 +131,45 @@ impl IpcSocket {
         }
     }
 
+    /// Serve the clients of the named pipe `name`. Every client takes over the instance of the pipe
+    /// it connected to, so a new one is created for the next client.
+    #[cfg(windows)]
+    async fn pipe_worker(
+        name: String,
+        mut server: NamedPipeServer,
+        ev: EventManager,
+        tx: Receiver<Status>,
+    ) {
+        loop {
+            let connected = server.connect().await;
+            let next = match ServerOptions::new().create(&name) {
+                Ok(next) => next,
+                Err(e) => {
+                    error!("Could not create IPC named pipe: {e}");
+                    return;
+                }
+            };
+            let stream = std::mem::replace(&mut server, next);
+            match connected {
+                Ok(()) => {
+                    debug!("Connection on {name}");
+                    tokio::spawn(Self::stream_handler(
+                        stream,
+                        ev.clone(),
+                        WatchStream::new(tx.clone()),
+                    ));
+                }
+                Err(e) => error!("Error accepting connection: {e}"),
+            }
+        }
+    }
+
     async fn stream_handler(
-        mut stream: UnixStream,
+        stream: impl AsyncRead + AsyncWrite,
         ev: EventManager,
         mut rx: WatchStream<Status>,
     ) -> Result<(), String> {
-        let (reader, writer) = stream.split();
+        let (reader, writer) = tokio::io::split(stream);
         let mut framed_reader = FramedRead::new(reader, LinesCodec::new());
         let mut framed_writer = FramedWrite::new(writer, LinesCodec::new());
 
@@ -102,6 +177,12 @@ impl IpcSocket {
             tokio::select! {
                 line = framed_reader.next() => {
                     match line {
+                        Some(Ok(line)) if line.trim_start().starts_with('{') => {
+                            debug!("Received request: \"{line}\"");
+                            let response = Self::handle_request(&line, &ev);
+                            let response = serde_json::to_string(&response).map_err(|e| e.to_string())?;
+                            framed_writer.send(response).await.map_err(|e| e.to_string())?;
+                        }
                         Some(Ok(line)) => {
                             debug!("Received line: \"{line}\"");
                             ev.send(Event::IpcInput(line));
@@ -126,8 +207,24 @@ impl IpcSocket {
         }
     }
 
+    /// Check the command of the JSON request `line` and pass it on to be run.
+    fn handle_request(line: &str, ev: &EventManager) -> Response {
+        let result = serde_json::from_str::<Request>(line)
+            .map_err(|e| format!("Invalid request: {e}"))
+            .and_then(|request| {
+                command::parse(&request.command).map_err(|e| e.to_string())?;
+                ev.send(Event::IpcInput(request.command));
+                Ok(())
+            });
+        Response {
+            event: "response".to_string(),
+            error: result.err(),
+        }
+    }
+
     /// Try to remove the IPC socket if there is one for this instance of `ncspot`. Don't do
     /// anything if the socket has already been removed for some reason.
+    #[cfg(unix)]
     fn try_remove_socket(&mut self) {
         if std::fs::remove_file(&self.path).is_ok() {
             info!("removed socket at {:?}", self.path);
@@ -0,0 +1,77 @@
+//! An append-only journal of the changes to the queue and the playback state. It makes reports
+//! like "my queue was suddenly cleared" diagnosable: the journal can be written to a file with
+//! `debug dump` and attached to the report.
+
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use log::error;
+
+/// The size at which the journal is rotated. At most two files of this size are kept.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+static JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// The previous journal file, which is kept after a rotation.
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("log.old")
+}
+
+/// Start recording entries to the journal at `path`, appending to the entries of earlier sessions.
+pub fn init(path: PathBuf) {
+    match open(&path) {
+        Ok(file) => *JOURNAL.lock().unwrap() = Some(Journal { path, file }),
+        Err(e) => error!("could not open journal {}: {e}", path.display()),
+    }
+    record("session started");
+}
+
+/// Add `event` to the journal. Does nothing if the journal wasn't initialized.
+pub fn record(event: impl Display) {
+    let mut journal = JOURNAL.lock().unwrap();
+    let Some(journal) = journal.as_mut() else {
+        return;
+    };
+
+    let line = format!(
+        "{} [{}] {event}\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        std::thread::current().name().unwrap_or("unnamed")
+    );
+    if let Err(e) = journal.append(&line) {
+        error!("could not write to journal: {e}");
+    }
+}
+
+impl Journal {
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        if self.file.metadata()?.len() + line.len() as u64 > MAX_FILE_SIZE {
+            fs::rename(&self.path, rotated_path(&self.path))?;
+            self.file = open(&self.path)?;
+        }
+        self.file.write_all(line.as_bytes())
+    }
+}
+
+/// Write all recorded entries, oldest first, to the file at `target`.
+pub fn dump(target: &Path) -> Result<(), String> {
+    let journal = JOURNAL.lock().unwrap();
+    let journal = journal.as_ref().ok_or("The journal is not available")?;
+
+    let mut contents = fs::read(rotated_path(&journal.path)).unwrap_or_default();
+    contents.extend(fs::read(&journal.path).map_err(|e| e.to_string())?);
+    fs::write(target, contents).map_err(|e| e.to_string())
+}
@@ -44,5 +44,110 @@ pub fn program_arguments() -> clap::Command {
                 .help("Filename of config file in basepath")
                 .default_value(CONFIGURATION_FILE_NAME),
         )
-        .subcommands([clap::Command::new("info").about("Print platform information like paths")])
+        .arg(
+            clap::Arg::new("name")
+                .short('n')
+                .long("name")
+                .value_name("NAME")
+                .help("Name of this instance, shown in audio mixers and MPRIS clients"),
+        )
+        .arg(
+            clap::Arg::new("client-id")
+                .long("client-id")
+                .value_name("ID")
+                .help("Client ID of the Spotify application, instead of the one in client.yml"),
+        )
+        .arg(
+            clap::Arg::new("client-secret")
+                .long("client-secret")
+                .value_name("SECRET")
+                .help("Client Secret of the Spotify application, prefer NCSPOT_CLIENT_SECRET"),
+        )
+        .arg(
+            clap::Arg::new("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port of the OAuth redirect URI, instead of the one in client.yml"),
+        )
+        .arg(
+            clap::Arg::new("no-browser")
+                .long("no-browser")
+                .action(clap::ArgAction::SetTrue)
+                .help("Log in by pasting the redirect URL, without opening a browser"),
+        )
+        .arg(clap::Arg::new("url").value_name("URL").help(
+            "Spotify URL or URI of a track, album, artist, playlist, show or episode to open",
+        ))
+        .subcommands([
+            clap::Command::new("backup")
+                .about("Save the library, playlists included, to a JSON file")
+                .arg(
+                    clap::Arg::new("directory")
+                        .value_name("DIRECTORY")
+                        .required(true)
+                        .value_parser(PathBufValueParser::new())
+                        .help("Directory to write the backup to"),
+                ),
+            clap::Command::new("info").about("Print platform information like paths"),
+            clap::Command::new("logout").about("Remove the cached login and credentials"),
+            clap::Command::new("play")
+                .about("Play a Spotify URI or URL without the user interface")
+                .arg(
+                    clap::Arg::new("uri")
+                        .short('u')
+                        .long("uri")
+                        .value_name("URI")
+                        .required(true)
+                        .help("Track, album, artist, playlist, show or episode to play"),
+                )
+                .arg(
+                    clap::Arg::new("exit-after")
+                        .long("exit-after")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Exit when playback finishes instead of starting over"),
+                ),
+            clap::Command::new("restore")
+                .about("Add the items missing from the library from a backup")
+                .arg(
+                    clap::Arg::new("path")
+                        .value_name("PATH")
+                        .required(true)
+                        .value_parser(PathBufValueParser::new())
+                        .help("Backup file, or a directory to restore the newest backup from"),
+                ),
+            clap::Command::new("send")
+                .about("Send a command to the running instance")
+                .arg(
+                    clap::Arg::new("command")
+                        .value_name("COMMAND")
+                        .required(true)
+                        .num_args(1..)
+                        .help("The command, as it would be typed after ':'"),
+                ),
+            clap::Command::new("status")
+                .about("Print the playing track of the running instance, for status bars")
+                .arg(
+                    clap::Arg::new("follow")
+                        .short('f')
+                        .long("follow")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Keep running and print a line for every change"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .default_value("{artist} - {title}")
+                        .help(
+                            "Placeholders: {title}, {artist}, {album}, {state}, {position}, \
+                             {duration}, {progress}",
+                        ),
+                )
+                .arg(
+                    clap::Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print JSON for a Waybar custom module"),
+                ),
+        ])
 }
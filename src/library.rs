@@ -1,38 +1,49 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
 use std::iter::Iterator;
-use std::path::Path;
 use std::sync::{Arc, RwLock};
 use std::thread;
+use std::time::Duration;
 
 use log::{debug, error, info};
 use rspotify::model::Id;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+use crate::application::ASYNC_RUNTIME;
+use crate::config::CACHE_VERSION;
 use crate::config::Config;
-use crate::config::{self, CACHE_VERSION};
-use crate::events::EventManager;
+use crate::events::{Event, EventManager};
+use crate::library_index::{self, IndexEntry, IndexMatches, Indexed, LibraryIndex};
+use crate::library_sync::{self, SavedPage};
+use crate::local_files;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::audio_features::AudioFeatures;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
+use crate::playlist_history::{self, PlaylistSnapshot};
 use crate::spotify::Spotify;
+use crate::spotify_api::SpotifyApi;
+use crate::storage::Namespace;
+use crate::undo::{self, Change};
 
-/// Cached tracks database filename.
+/// Storage key of the cached tracks.
 const CACHE_TRACKS: &str = "tracks.db";
 
-/// Cached albums database filename.
+/// Storage key of the cached albums.
 const CACHE_ALBUMS: &str = "albums.db";
 
-/// Cached artists database filename.
+/// Storage key of the cached artists.
 const CACHE_ARTISTS: &str = "artists.db";
 
-/// Cached playlists database filename.
+/// Storage key of the cached playlists.
 const CACHE_PLAYLISTS: &str = "playlists.db";
 
+/// The maximum amount of tracks that can be saved or removed in one request.
+const SAVED_TRACKS_BATCH: usize = 50;
+
 /// The user library with all their saved tracks, albums, playlists... High level interface to the
 /// Spotify API used to manage items in the user library.
 #[derive(Clone)]
@@ -42,9 +53,27 @@ pub struct Library {
     pub artists: Arc<RwLock<Vec<Artist>>>,
     pub playlists: Arc<RwLock<Vec<Playlist>>>,
     pub shows: Arc<RwLock<Vec<Show>>>,
+    /// The files from the local directories.
+    pub local_tracks: Arc<RwLock<Vec<Track>>>,
+    /// Audio features by track id. `None` marks tracks without features.
+    audio_features: Arc<RwLock<HashMap<String, Option<AudioFeatures>>>>,
+    /// Track ids whose audio features still have to be fetched.
+    pending_audio_features: Arc<RwLock<HashSet<String>>>,
+    /// Genres by artist id, for smart playlists.
+    artist_genres: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    /// Artist ids whose genres still have to be fetched.
+    pending_artist_genres: Arc<RwLock<HashSet<String>>>,
+    /// Whether the user follows an artist or playlist, by its id, as last reported by the Web API.
+    follow_state: Arc<RwLock<HashMap<String, bool>>>,
+    /// Artist ids whose follow state still has to be checked.
+    pending_follow_checks: Arc<RwLock<HashSet<String>>>,
+    /// Display names by user id, for the contributors of collaborative playlists.
+    user_names: Arc<RwLock<HashMap<String, String>>>,
     pub is_done: Arc<RwLock<bool>>,
     pub user_id: Option<String>,
     pub display_name: Option<String>,
+    /// The full-text index of the saved items, if the storage backend has one.
+    index: Option<Arc<dyn LibraryIndex>>,
     ev: EventManager,
     spotify: Spotify,
     pub cfg: Arc<Config>,
@@ -55,6 +84,12 @@ impl Library {
         let current_user = spotify.api.current_user().ok();
         let user_id = current_user.as_ref().map(|u| u.id.id().to_string());
         let display_name = current_user.as_ref().and_then(|u| u.display_name.clone());
+        let index = library_index::open(cfg.values().storage_backend.as_deref()).unwrap_or_else(
+            |message| {
+                error!("{message}");
+                None
+            },
+        );
 
         let library = Self {
             tracks: Arc::new(RwLock::new(Vec::new())),
@@ -62,9 +97,18 @@ impl Library {
             artists: Arc::new(RwLock::new(Vec::new())),
             playlists: Arc::new(RwLock::new(Vec::new())),
             shows: Arc::new(RwLock::new(Vec::new())),
+            local_tracks: Arc::new(RwLock::new(Vec::new())),
+            audio_features: Arc::new(RwLock::new(HashMap::new())),
+            pending_audio_features: Arc::new(RwLock::new(HashSet::new())),
+            artist_genres: Arc::new(RwLock::new(HashMap::new())),
+            pending_artist_genres: Arc::new(RwLock::new(HashSet::new())),
+            follow_state: Arc::new(RwLock::new(HashMap::new())),
+            pending_follow_checks: Arc::new(RwLock::new(HashSet::new())),
+            user_names: Arc::new(RwLock::new(HashMap::new())),
             is_done: Arc::new(RwLock::new(false)),
             user_id,
             display_name,
+            index,
             ev,
             spotify,
             cfg,
@@ -74,58 +118,75 @@ impl Library {
         library
     }
 
-    /// Load cached items from the file at `cache_path` into the given `store`.
-    fn load_cache<T: DeserializeOwned>(&self, cache_path: &Path, store: &mut Vec<T>) {
+    /// Load the cached items stored under `key` into the given `store`.
+    fn load_cache<T: DeserializeOwned>(&self, key: &str, store: &mut Vec<T>) {
         let saved_cache_version = self.cfg.state().cache_version;
         if saved_cache_version < CACHE_VERSION {
             debug!(
-                "Cache version for {cache_path:?} has changed from {saved_cache_version} to {CACHE_VERSION}, ignoring cache"
+                "Cache version for {key} has changed from {saved_cache_version} to {CACHE_VERSION}, ignoring cache"
             );
             return;
         }
 
-        if let Ok(contents) = std::fs::read_to_string(cache_path) {
-            debug!("loading cache from {}", cache_path.display());
-            // Parse from in-memory string instead of directly from the file because it's faster.
-            let parsed = serde_json::from_str::<Vec<_>>(&contents);
-            match parsed {
-                Ok(cache) => {
-                    debug!(
-                        "cache from {} loaded ({} items)",
-                        cache_path.display(),
-                        cache.len()
-                    );
-                    store.clear();
-                    store.extend(cache);
-
-                    // force refresh of UI (if visible)
-                    self.trigger_redraw();
-                }
-                Err(e) => {
-                    error!("can't parse cache: {e}");
+        match self.cfg.storage().read(Namespace::Cache, key) {
+            Ok(Some(contents)) => {
+                debug!("loading cache from {key}");
+                // Parse from memory instead of directly from the storage because it's faster.
+                let parsed = serde_json::from_slice::<Vec<_>>(&contents);
+                match parsed {
+                    Ok(cache) => {
+                        debug!("cache from {key} loaded ({} items)", cache.len());
+                        store.clear();
+                        store.extend(cache);
+
+                        // force refresh of UI (if visible)
+                        self.trigger_redraw();
+                    }
+                    Err(e) => {
+                        error!("can't parse cache: {e}");
+                    }
                 }
             }
+            Ok(None) => {}
+            Err(e) => error!("can't read cache: {e}"),
         }
     }
 
-    /// Save the items from `store` in the file at `cache_path`.
-    fn save_cache<T: Serialize>(&self, cache_path: &Path, store: &[T]) {
-        let cache_file = File::create(cache_path).unwrap();
-        let serialize_result = serde_json::to_writer(cache_file, store);
+    /// Save the items from `store` under `key` and update the search index with them.
+    fn save_cache<T: Serialize + Indexed>(&self, key: &str, store: &[T]) {
+        let serialize_result = serde_json::to_vec(store)
+            .map_err(|e| e.to_string())
+            .and_then(|content| self.cfg.storage().write(Namespace::Cache, key, &content));
         if let Err(message) = serialize_result {
             error!("could not write cache: {message:?}");
         }
+
+        if let Some(index) = &self.index {
+            let entries: Vec<IndexEntry> = store.iter().filter_map(Indexed::index_entry).collect();
+            if let Err(message) = index.sync(T::KIND, &entries) {
+                error!("could not update the library index: {message}");
+            }
+        }
+    }
+
+    /// Search the saved items offline. Returns `None` if there is no search index.
+    pub fn search_index(&self, query: &str) -> Option<IndexMatches> {
+        let index = self.index.as_ref()?;
+        Some(index.search(query).unwrap_or_else(|message| {
+            error!("{message}");
+            IndexMatches::default()
+        }))
     }
 
     /// Check whether the `remote` [Playlist] is newer than its locally saved version. Returns
-    /// `true` if it is or if a local version isn't found.
+    /// `true` if it is or if a local version with tracks isn't found.
     fn needs_download(&self, remote: &Playlist) -> bool {
         self.playlists
             .read()
             .unwrap()
             .iter()
             .find(|local| local.id == remote.id)
-            .map(|local| local.snapshot_id != remote.snapshot_id)
+            .map(|local| local.snapshot_id != remote.snapshot_id || local.tracks.is_none())
             .unwrap_or(true)
     }
 
@@ -159,11 +220,13 @@ impl Library {
         if let Some(position) = position
             && self.spotify.api.delete_playlist(id).is_ok()
         {
-            self.playlists.write().unwrap().remove(position);
-            self.save_cache(
-                &config::cache_path(CACHE_PLAYLISTS),
-                &self.playlists.read().unwrap(),
-            );
+            let playlist = self.playlists.write().unwrap().remove(position);
+            self.follow_state
+                .write()
+                .unwrap()
+                .insert(playlist.id.clone(), false);
+            undo::record(Change::DeletePlaylist(Box::new(playlist)));
+            self.save_cache(CACHE_PLAYLISTS, &self.playlists.read().unwrap());
         }
     }
 
@@ -174,10 +237,33 @@ impl Library {
         self.spotify.api.overwrite_playlist(id, tracks);
 
         self.fetch_playlists();
-        self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
-            &self.playlists.read().unwrap(),
+        self.save_cache(CACHE_PLAYLISTS, &self.playlists.read().unwrap());
+    }
+
+    /// Record the tracks of `playlist` in the local playlist history if the user owns it.
+    fn record_snapshot(&self, playlist: &Playlist) {
+        let limit = self
+            .cfg
+            .values()
+            .playlist_history
+            .unwrap_or(playlist_history::DEFAULT_SNAPSHOTS);
+        if limit > 0 && self.user_id.as_ref() == Some(&playlist.owner_id) {
+            playlist_history::record(self.cfg.storage().as_ref(), playlist, limit);
+        }
+    }
+
+    /// Get the recorded versions of `playlist`, newest first.
+    pub fn playlist_snapshots(&self, playlist: &Playlist) -> Vec<PlaylistSnapshot> {
+        playlist_history::snapshots(self.cfg.storage().as_ref(), &playlist.id)
+    }
+
+    /// Replace the tracks of the playlist with `id` with the ones recorded in `snapshot`.
+    pub fn restore_playlist(&self, id: &str, snapshot: &PlaylistSnapshot) {
+        info!(
+            "restoring playlist {id} to its version from {}",
+            snapshot.date()
         );
+        self.overwrite_playlist(id, &snapshot.tracks);
     }
 
     /// Create a playlist with the given `name` and add `tracks` to it.
@@ -193,97 +279,101 @@ impl Library {
     pub fn update_library(&self) {
         *self.is_done.write().unwrap() = false;
 
+        // The categories are fetched on the blocking thread pool of the async runtime, so the
+        // threads are reused and rate limit backoffs don't hold up anything else. The task that
+        // waits for them doesn't occupy a thread meanwhile.
+        let runtime = ASYNC_RUNTIME.get().unwrap();
         let library = self.clone();
-        thread::spawn(move || {
+        runtime.spawn(async move {
             let t_tracks = {
                 let library = library.clone();
-                thread::spawn(move || {
-                    library.load_cache(
-                        &config::cache_path(CACHE_TRACKS),
-                        library.tracks.write().unwrap().as_mut(),
-                    );
+                runtime.spawn_blocking(move || {
+                    library.load_cache(CACHE_TRACKS, library.tracks.write().unwrap().as_mut());
                     library.fetch_tracks();
-                    library.save_cache(
-                        &config::cache_path(CACHE_TRACKS),
-                        &library.tracks.read().unwrap(),
-                    );
+                    library.save_cache(CACHE_TRACKS, &library.tracks.read().unwrap());
                 })
             };
 
             let t_albums = {
                 let library = library.clone();
-                thread::spawn(move || {
-                    library.load_cache(
-                        &config::cache_path(CACHE_ALBUMS),
-                        library.albums.write().unwrap().as_mut(),
-                    );
+                runtime.spawn_blocking(move || {
+                    library.load_cache(CACHE_ALBUMS, library.albums.write().unwrap().as_mut());
                     library.fetch_albums();
-                    library.save_cache(
-                        &config::cache_path(CACHE_ALBUMS),
-                        &library.albums.read().unwrap(),
-                    );
+                    library.save_cache(CACHE_ALBUMS, &library.albums.read().unwrap());
                 })
             };
 
             let t_artists = {
                 let library = library.clone();
-                thread::spawn(move || {
-                    library.load_cache(
-                        &config::cache_path(CACHE_ARTISTS),
-                        library.artists.write().unwrap().as_mut(),
-                    );
+                runtime.spawn_blocking(move || {
+                    library.load_cache(CACHE_ARTISTS, library.artists.write().unwrap().as_mut());
                     library.fetch_artists();
                 })
             };
 
             let t_playlists = {
                 let library = library.clone();
-                thread::spawn(move || {
-                    library.load_cache(
-                        &config::cache_path(CACHE_PLAYLISTS),
-                        library.playlists.write().unwrap().as_mut(),
-                    );
+                runtime.spawn_blocking(move || {
+                    library
+                        .load_cache(CACHE_PLAYLISTS, library.playlists.write().unwrap().as_mut());
                     library.fetch_playlists();
-                    library.save_cache(
-                        &config::cache_path(CACHE_PLAYLISTS),
-                        &library.playlists.read().unwrap(),
-                    );
+                    library.save_cache(CACHE_PLAYLISTS, &library.playlists.read().unwrap());
                 })
             };
 
             let t_shows = {
                 let library = library.clone();
-                thread::spawn(move || {
+                runtime.spawn_blocking(move || {
                     library.fetch_shows();
                 })
             };
 
-            t_tracks.join().unwrap();
-            t_artists.join().unwrap();
+            let t_local = {
+                let library = library.clone();
+                runtime.spawn_blocking(move || {
+                    let directories = local_files::directories(&library.cfg);
+                    if !directories.is_empty() {
+                        *library.local_tracks.write().unwrap() = local_files::index(&directories);
+                        library.trigger_redraw();
+                    }
+                })
+            };
+
+            t_tracks.await.unwrap();
+            t_artists.await.unwrap();
 
-            library.populate_artists();
-            library.save_cache(
-                &config::cache_path(CACHE_ARTISTS),
-                &library.artists.read().unwrap(),
-            );
+            {
+                let library = library.clone();
+                runtime
+                    .spawn_blocking(move || {
+                        library.populate_artists();
+                        library.save_cache(CACHE_ARTISTS, &library.artists.read().unwrap());
+                    })
+                    .await
+                    .unwrap();
+            }
 
-            t_albums.join().unwrap();
-            t_playlists.join().unwrap();
-            t_shows.join().unwrap();
+            t_albums.await.unwrap();
+            t_playlists.await.unwrap();
+            t_shows.await.unwrap();
+            t_local.await.unwrap();
 
-            let mut is_done = library.is_done.write().unwrap();
-            *is_done = true;
+            *library.is_done.write().unwrap() = true;
 
-            library.ev.trigger();
+            library.ev.send(Event::LibraryLoaded);
         });
     }
 
     /// Fetch the shows from the web API and save them to the local library.
     fn fetch_shows(&self) {
         debug!("loading shows");
+        *self.shows.write().unwrap() = Self::saved_shows(&self.spotify.api);
+    }
 
+    /// Fetch all saved shows. Pages that fail to load are left out.
+    fn saved_shows(api: &dyn SpotifyApi) -> Vec<Show> {
         let mut saved_shows: Vec<Show> = Vec::new();
-        let mut shows_result = self.spotify.api.get_saved_shows(0).ok();
+        let mut shows_result = api.get_saved_shows(0).ok();
 
         while let Some(shows) = shows_result {
             saved_shows.extend(shows.items.iter().map(|show| (&show.show).into()));
@@ -292,16 +382,14 @@ impl Library {
             shows_result = match shows.next {
                 Some(_) => {
                     debug!("requesting shows again..");
-                    self.spotify
-                        .api
-                        .get_saved_shows(shows.offset + shows.items.len() as u32)
+                    api.get_saved_shows(shows.offset + shows.items.len() as u32)
                         .ok()
                 }
                 None => None,
             }
         }
 
-        *self.shows.write().unwrap() = saved_shows;
+        saved_shows
     }
 
     /// Fetch the playlists from the web API and save them to the local library. This synchronizes
@@ -327,6 +415,7 @@ impl Library {
                     let mut playlist: Playlist = remote.clone();
                     playlist.tracks = None;
                     playlist.load_tracks(&self.spotify);
+                    self.record_snapshot(&playlist);
                     self.append_or_update(playlist);
                     // trigger redraw
                     self.trigger_redraw();
@@ -335,6 +424,12 @@ impl Library {
             lists_batch = lists_page.next();
         }
 
+        // The playlists that failed to load aren't stale.
+        if self.spotify.api.is_offline() {
+            debug!("offline, keeping the cached playlists");
+            return;
+        }
+
         // remove stale playlists
         for stale in stale_lists {
             let index = self
@@ -362,41 +457,44 @@ impl Library {
 
     /// Fetch the artists from the web API and save them to the local library.
     fn fetch_artists(&self) {
+        let Ok(artists) = Self::followed_artists(&self.spotify.api) else {
+            error!("Failed to fetch artists.");
+            return;
+        };
+
+        let mut store = self.artists.write().unwrap();
+
+        for mut artist in artists {
+            let pos = store.iter().position(|a| a.id == artist.id);
+            if let Some(i) = pos {
+                store[i].is_followed = true;
+                continue;
+            }
+
+            artist.is_followed = true;
+
+            store.push(artist);
+        }
+    }
+
+    /// Fetch all followed artists.
+    fn followed_artists(api: &dyn SpotifyApi) -> Result<Vec<Artist>, ()> {
         let mut artists: Vec<Artist> = Vec::new();
         let mut last: Option<&str> = None;
         let mut i = 0u32;
 
         loop {
-            let page = self.spotify.api.current_user_followed_artists(last);
+            let page = api.current_user_followed_artists(last)?;
             debug!("artists page: {i}");
             i += 1;
-            if page.is_err() {
-                error!("Failed to fetch artists.");
-                return;
-            }
-            let page = page.unwrap();
 
             artists.extend(page.items.iter().map(|fa| fa.into()));
 
             if page.next.is_some() {
                 last = artists.last().unwrap().id.as_deref();
             } else {
-                break;
-            }
-        }
-
-        let mut store = self.artists.write().unwrap();
-
-        for mut artist in artists {
-            let pos = store.iter().position(|a| a.id == artist.id);
-            if let Some(i) = pos {
-                store[i].is_followed = true;
-                continue;
+                return Ok(artists);
             }
-
-            artist.is_followed = true;
-
-            store.push(artist);
         }
     }
 
@@ -415,32 +513,14 @@ impl Library {
         }
     }
 
-    /// Fetch the albums from the web API and store them in the local library.
+    /// Fetch the albums saved since the last sync from the web API and store them in the local
+    /// library.
     fn fetch_albums(&self) {
-        let mut albums: Vec<Album> = Vec::new();
-        let mut i = 0u32;
-
-        loop {
-            let page = self
-                .spotify
-                .api
-                .current_user_saved_albums(albums.len() as u32);
-            debug!("albums page: {i}");
-
-            i += 1;
-
-            if page.is_err() {
-                error!("Failed to fetch albums.");
-                return;
-            }
-
-            let page = page.unwrap();
-            albums.extend(page.items.iter().map(|a| a.into()));
-
-            if page.next.is_none() {
-                break;
-            }
-        }
+        let cached = self.albums.read().unwrap().clone();
+        let Ok(mut albums) = Self::saved_albums(&self.spotify.api, &cached) else {
+            error!("Failed to fetch albums.");
+            return;
+        };
 
         albums.sort_unstable_by_key(|album| {
             let album_artist = album.artists[0]
@@ -458,51 +538,36 @@ impl Library {
         *self.albums.write().unwrap() = albums;
     }
 
-    /// Fetch the tracks from the web API and save them in the local library.
+    /// Fetch the tracks saved since the last sync from the web API and save them in the local
+    /// library.
     fn fetch_tracks(&self) {
-        let mut tracks = Vec::new();
-        let mut i = 0u32;
-
-        loop {
-            let page = self
-                .spotify
-                .api
-                .current_user_saved_tracks(tracks.len() as u32);
-
-            debug!("tracks page: {i}");
-            i += 1;
-
-            if page.is_err() {
-                error!("Failed to fetch tracks.");
-                return;
-            }
-            let page = page.unwrap();
-
-            if page.offset == 0 {
-                // If first page matches the first items in store and total is
-                // identical, assume list is unchanged.
-
-                let store = self.tracks.read().unwrap();
-
-                if page.total as usize == store.len()
-                    && !page
-                        .items
-                        .iter()
-                        .enumerate()
-                        .any(|(i, t)| t.track.id.as_ref().map(|id| id.to_string()) != store[i].id)
-                {
-                    return;
-                }
-            }
-
-            tracks.extend(page.items.iter().map(|t| t.into()));
-
-            if page.next.is_none() {
-                break;
-            }
+        let cached = self.tracks.read().unwrap().clone();
+        match Self::saved_tracks(&self.spotify.api, &cached) {
+            Ok(tracks) => *self.tracks.write().unwrap() = tracks,
+            Err(()) => error!("Failed to fetch tracks."),
         }
+    }
+
+    /// The saved albums, fetching only those saved since `cached` was synced.
+    fn saved_albums(api: &dyn SpotifyApi, cached: &[Album]) -> Result<Vec<Album>, ()> {
+        library_sync::fetch_saved(cached, |offset| {
+            api.current_user_saved_albums(offset).map(|page| SavedPage {
+                items: page.items.iter().map(Album::from).collect(),
+                total: page.total,
+                has_next: page.next.is_some(),
+            })
+        })
+    }
 
-        *self.tracks.write().unwrap() = tracks;
+    /// The saved tracks, fetching only those saved since `cached` was synced.
+    fn saved_tracks(api: &dyn SpotifyApi, cached: &[Track]) -> Result<Vec<Track>, ()> {
+        library_sync::fetch_saved(cached, |offset| {
+            api.current_user_saved_tracks(offset).map(|page| SavedPage {
+                items: page.items.iter().map(Track::from).collect(),
+                total: page.total,
+                has_next: page.next.is_some(),
+            })
+        })
     }
 
     fn populate_artists(&self) {
@@ -577,6 +642,7 @@ impl Library {
 
     /// If there is a local version of the playlist, update it and rewrite the cache.
     pub fn playlist_update(&self, updated: &Playlist) {
+        self.record_snapshot(updated);
         {
             let mut playlists = self.playlists.write().unwrap();
             if let Some(playlist) = playlists.iter_mut().find(|p| p.id == updated.id) {
@@ -584,10 +650,7 @@ impl Library {
             }
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
-            &self.playlists.read().unwrap(),
-        );
+        self.save_cache(CACHE_PLAYLISTS, &self.playlists.read().unwrap());
     }
 
     /// Check whether `track` is saved in the user's library.
@@ -600,10 +663,10 @@ impl Library {
         tracks.iter().any(|t| t.id == track.id())
     }
 
-    /// Save `tracks` to the user's library.
-    pub fn save_tracks(&self, tracks: &[&Track]) {
+    /// Save `tracks` to the user's library. Returns whether it succeeded.
+    pub fn save_tracks(&self, tracks: &[&Track]) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
         let save_tracks_result = self
@@ -612,7 +675,7 @@ impl Library {
             .current_user_saved_tracks_add(tracks.iter().filter_map(|t| t.id.as_deref()).collect());
 
         if save_tracks_result.is_err() {
-            return;
+            return false;
         }
 
         {
@@ -630,20 +693,15 @@ impl Library {
 
         self.populate_artists();
 
-        self.save_cache(
-            &config::cache_path(CACHE_TRACKS),
-            &self.tracks.read().unwrap(),
-        );
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
-            &self.artists.read().unwrap(),
-        );
+        self.save_cache(CACHE_TRACKS, &self.tracks.read().unwrap());
+        self.save_cache(CACHE_ARTISTS, &self.artists.read().unwrap());
+        true
     }
 
-    /// Remove `tracks` from the user's library.
-    pub fn unsave_tracks(&self, tracks: &[&Track]) {
+    /// Remove `tracks` from the user's library. Returns whether it succeeded.
+    pub fn unsave_tracks(&self, tracks: &[&Track]) -> bool {
         if !*self.is_done.read().unwrap() {
-            return;
+            return false;
         }
 
         if self
@@ -654,7 +712,7 @@ impl Library {
             )
             .is_err()
         {
-            return;
+            return false;
         }
 
         {
@@ -668,14 +726,50 @@ impl Library {
 
         self.populate_artists();
 
-        self.save_cache(
-            &config::cache_path(CACHE_TRACKS),
-            &self.tracks.read().unwrap(),
-        );
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
-            &self.artists.read().unwrap(),
-        );
+        self.save_cache(CACHE_TRACKS, &self.tracks.read().unwrap());
+        self.save_cache(CACHE_ARTISTS, &self.artists.read().unwrap());
+        undo::record(Change::UnsaveTracks(
+            tracks.iter().map(|&track| track.clone()).collect(),
+        ));
+        true
+    }
+
+    /// Save `tracks` to the user's library, or remove them if `saved` is `false`. Tracks are sent
+    /// in batches and `progress` is called with the amount of handled tracks after every batch.
+    /// Stops early if `progress` returns `false`. Returns the amount of changed tracks.
+    pub fn set_tracks_saved(
+        &self,
+        tracks: &[Track],
+        saved: bool,
+        progress: impl Fn(usize, usize) -> bool,
+    ) -> Result<usize, String> {
+        let pending: Vec<&Track> = tracks
+            .iter()
+            .filter(|track| {
+                track.id.is_some()
+                    && self.is_saved_track(&Playable::Track((*track).clone())) != saved
+            })
+            .collect();
+
+        let mut changed = 0;
+        for batch in pending.chunks(SAVED_TRACKS_BATCH) {
+            let success = if saved {
+                self.save_tracks(batch)
+            } else {
+                self.unsave_tracks(batch)
+            };
+            if !success {
+                return Err(format!(
+                    "Could not update {} tracks",
+                    pending.len() - changed
+                ));
+            }
+            changed += batch.len();
+            if !progress(changed, pending.len()) {
+                break;
+            }
+        }
+        Ok(changed)
     }
 
     /// Check whether `album` is saved to the user's library.
@@ -714,10 +808,7 @@ impl Library {
             }
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_ALBUMS),
-            &self.albums.read().unwrap(),
-        );
+        self.save_cache(CACHE_ALBUMS, &self.albums.read().unwrap());
     }
 
     /// Remove `album` from the user's library.
@@ -741,10 +832,8 @@ impl Library {
             *store = store.iter().filter(|a| a.id != album.id).cloned().collect();
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_ALBUMS),
-            &self.albums.read().unwrap(),
-        );
+        self.save_cache(CACHE_ALBUMS, &self.albums.read().unwrap());
+        undo::record(Change::UnsaveAlbum(Box::new(album.clone())));
     }
 
     /// Check whether the user follows `artist`.
@@ -757,6 +846,59 @@ impl Library {
         artists.iter().any(|a| a.id == artist.id && a.is_followed)
     }
 
+    /// Ask the Web API whether the user follows `artist`. Falls back to the cached library state
+    /// if the request fails.
+    pub fn check_followed_artist(&self, artist: &Artist) -> bool {
+        artist
+            .id
+            .as_ref()
+            .and_then(|id| {
+                self.spotify
+                    .api
+                    .current_user_follows_artists(&[id.as_str()])
+                    .ok()
+            })
+            .and_then(|follows| follows.first().copied())
+            .unwrap_or_else(|| self.is_followed_artist(artist))
+    }
+
+    /// Whether the user follows `artist`, without waiting for the Web API. If it isn't known yet,
+    /// it is checked in the background together with other pending artists, like
+    /// [Self::audio_features]. Until then, the library state is used.
+    pub fn is_following_artist(&self, artist: &Artist) -> bool {
+        let Some(id) = artist.id.as_ref() else {
+            return self.is_followed_artist(artist);
+        };
+        if let Some(followed) = self.follow_state.read().unwrap().get(id) {
+            return *followed;
+        }
+
+        let start_fetching = {
+            let mut pending = self.pending_follow_checks.write().unwrap();
+            let was_empty = pending.is_empty();
+            pending.insert(id.clone());
+            was_empty
+        };
+
+        if start_fetching {
+            let library = self.clone();
+            thread::spawn(move || library.fetch_pending_follow_checks());
+        }
+
+        self.is_followed_artist(artist)
+    }
+
+    /// Whether the user follows `playlist`, without asking the Web API. The user's own and
+    /// followed playlists are all part of the library, so it answers for every playlist.
+    pub fn is_following_playlist(&self, playlist: &Playlist) -> bool {
+        self.follow_state
+            .read()
+            .unwrap()
+            .get(&playlist.id)
+            .copied()
+            .unwrap_or_else(|| self.is_saved_playlist(playlist))
+    }
+
     /// Follow `artist` as the logged in user.
     pub fn follow_artist(&self, artist: &Artist) {
         if !*self.is_done.read().unwrap() {
@@ -783,13 +925,16 @@ impl Library {
                 store.push(artist);
             }
         }
+        if let Some(ref artist_id) = artist.id {
+            self.follow_state
+                .write()
+                .unwrap()
+                .insert(artist_id.clone(), true);
+        }
 
         self.populate_artists();
 
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
-            &self.artists.read().unwrap(),
-        );
+        self.save_cache(CACHE_ARTISTS, &self.artists.read().unwrap());
     }
 
     /// Unfollow `artist` as the logged in user.
@@ -814,13 +959,16 @@ impl Library {
                 store[i].is_followed = false;
             }
         }
+        if let Some(ref artist_id) = artist.id {
+            self.follow_state
+                .write()
+                .unwrap()
+                .insert(artist_id.clone(), false);
+        }
 
         self.populate_artists();
 
-        self.save_cache(
-            &config::cache_path(CACHE_ARTISTS),
-            &self.artists.read().unwrap(),
-        );
+        self.save_cache(CACHE_ARTISTS, &self.artists.read().unwrap());
     }
 
     /// Check whether `playlist` is saved in the user's library.
@@ -841,6 +989,15 @@ impl Library {
             .unwrap_or(false)
     }
 
+    /// Ask the Web API whether the user follows `playlist`. Falls back to the cached library
+    /// state if the request fails.
+    pub fn check_followed_playlist(&self, playlist: &Playlist) -> bool {
+        self.spotify
+            .api
+            .playlist_check_follow(&playlist.id)
+            .unwrap_or_else(|_| self.is_saved_playlist(playlist))
+    }
+
     /// Add `playlist` to the user's library by following it as the logged in user.
     pub fn follow_playlist(&self, mut playlist: Playlist) {
         if !*self.is_done.read().unwrap() {
@@ -853,6 +1010,10 @@ impl Library {
             return;
         }
 
+        self.follow_state
+            .write()
+            .unwrap()
+            .insert(playlist.id.clone(), true);
         playlist.load_tracks(&self.spotify);
 
         {
@@ -862,10 +1023,7 @@ impl Library {
             }
         }
 
-        self.save_cache(
-            &config::cache_path(CACHE_PLAYLISTS),
-            &self.playlists.read().unwrap(),
-        );
+        self.save_cache(CACHE_PLAYLISTS, &self.playlists.read().unwrap());
     }
 
     /// Check whether `show` is already in the user's library.
@@ -910,4 +1068,242 @@ impl Library {
     pub fn trigger_redraw(&self) {
         self.ev.trigger();
     }
+
+    /// Get the audio features of the track with `track_id`. If they aren't known yet, they are
+    /// fetched in the background together with other pending tracks and the UI is redrawn once
+    /// they arrive.
+    pub fn audio_features(&self, track_id: &str) -> Option<AudioFeatures> {
+        if let Some(features) = self.audio_features.read().unwrap().get(track_id) {
+            return features.clone();
+        }
+
+        let start_fetching = {
+            let mut pending = self.pending_audio_features.write().unwrap();
+            let was_empty = pending.is_empty();
+            pending.insert(track_id.to_string());
+            was_empty
+        };
+
+        if start_fetching {
+            let library = self.clone();
+            thread::spawn(move || library.fetch_pending_audio_features());
+        }
+
+        None
+    }
+
+    /// Get the genres of the artist with `artist_id`. If they aren't known yet, they are fetched in
+    /// the background together with other pending artists, like [Self::audio_features].
+    pub fn artist_genres(&self, artist_id: &str) -> Option<Vec<String>> {
+        if let Some(genres) = self.artist_genres.read().unwrap().get(artist_id) {
+            return Some(genres.clone());
+        }
+
+        let start_fetching = {
+            let mut pending = self.pending_artist_genres.write().unwrap();
+            let was_empty = pending.is_empty();
+            pending.insert(artist_id.to_string());
+            was_empty
+        };
+
+        if start_fetching {
+            let library = self.clone();
+            thread::spawn(move || library.fetch_pending_artist_genres());
+        }
+
+        None
+    }
+
+    /// Get the display name of the user with `user_id`. If it isn't known yet, it is fetched in the
+    /// background and the UI is redrawn once it arrives. Until then, the id is returned.
+    pub fn user_name(&self, user_id: &str) -> String {
+        if let Some(name) = self.user_names.read().unwrap().get(user_id) {
+            return name.clone();
+        }
+
+        // Users without a display name keep their id, so they aren't looked up again.
+        self.user_names
+            .write()
+            .unwrap()
+            .insert(user_id.to_string(), user_id.to_string());
+        let library = self.clone();
+        let id = user_id.to_string();
+        thread::spawn(move || {
+            let name = library
+                .spotify
+                .api
+                .user(&id)
+                .ok()
+                .and_then(|user| user.display_name);
+            if let Some(name) = name {
+                library.user_names.write().unwrap().insert(id, name);
+                library.trigger_redraw();
+            }
+        });
+        user_id.to_string()
+    }
+
+    /// Fetch the audio features of all pending tracks in as few requests as possible. Only tracks
+    /// the API answered for without features are cached as `None`, after a failed request the
+    /// tracks are looked up again.
+    fn fetch_pending_audio_features(&self) {
+        // Give list views a moment to request all visible rows so they end up in one batch.
+        thread::sleep(Duration::from_millis(100));
+
+        let ids: Vec<String> = self
+            .pending_audio_features
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let fetched = self.spotify.api.audio_features(&id_refs);
+        let succeeded = fetched.is_ok();
+        if let Ok(fetched) = fetched {
+            let mut store = self.audio_features.write().unwrap();
+            for id in ids.iter() {
+                store.insert(id.clone(), None);
+            }
+            for features in fetched {
+                store.insert(features.id.clone(), Some(features));
+            }
+        }
+
+        let mut pending = self.pending_audio_features.write().unwrap();
+        for id in ids.iter() {
+            pending.remove(id);
+        }
+        let more_pending = !pending.is_empty();
+        drop(pending);
+
+        if more_pending {
+            self.fetch_pending_audio_features();
+        }
+
+        // Redrawing after a failure would request the same tracks right away again.
+        if succeeded {
+            self.trigger_redraw();
+        }
+    }
+
+    /// Check whether the user follows the pending artists in as few requests as possible. When the
+    /// request fails, they are checked again the next time they are needed.
+    fn fetch_pending_follow_checks(&self) {
+        thread::sleep(Duration::from_millis(100));
+
+        let ids: Vec<String> = self
+            .pending_follow_checks
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let fetched = self.spotify.api.current_user_follows_artists(&id_refs);
+        let succeeded = fetched.is_ok();
+        if let Ok(follows) = fetched {
+            let mut store = self.follow_state.write().unwrap();
+            for (id, followed) in ids.iter().zip(follows) {
+                store.insert(id.clone(), followed);
+            }
+        }
+
+        let mut pending = self.pending_follow_checks.write().unwrap();
+        for id in ids.iter() {
+            pending.remove(id);
+        }
+        let more_pending = !pending.is_empty();
+        drop(pending);
+
+        if more_pending {
+            self.fetch_pending_follow_checks();
+        }
+
+        if succeeded {
+            self.trigger_redraw();
+        }
+    }
+
+    /// Fetch the genres of all pending artists in as few requests as possible. When the request
+    /// fails, they are asked for again the next time they are needed.
+    fn fetch_pending_artist_genres(&self) {
+        thread::sleep(Duration::from_millis(100));
+
+        let ids: Vec<String> = self
+            .pending_artist_genres
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        let id_refs: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let fetched = self.spotify.api.artists(&id_refs);
+        let succeeded = fetched.is_ok();
+        if let Ok(artists) = fetched {
+            let mut store = self.artist_genres.write().unwrap();
+            for id in ids.iter() {
+                store.insert(id.clone(), Vec::new());
+            }
+            for artist in artists {
+                store.insert(artist.id.id().to_string(), artist.genres);
+            }
+        }
+
+        let mut pending = self.pending_artist_genres.write().unwrap();
+        for id in ids.iter() {
+            pending.remove(id);
+        }
+        let more_pending = !pending.is_empty();
+        drop(pending);
+
+        if more_pending {
+            self.fetch_pending_artist_genres();
+        }
+
+        if succeeded {
+            self.trigger_redraw();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock_api::MockApi;
+
+    #[test]
+    fn test_followed_artists() {
+        let mut api = MockApi::with_library();
+        api.page_size = 1;
+
+        let artists = Library::followed_artists(&api).unwrap();
+        let names: Vec<&str> = artists.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["Band of Horses", "Iron & Wine"]);
+        assert_eq!(
+            api.requests(),
+            vec![
+                "current_user_followed_artists ",
+                "current_user_followed_artists 0OdUWJ0sBjDrqHygGUXeCF"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_saved_tracks() {
+        let api = MockApi::with_library();
+
+        let tracks = Library::saved_tracks(&api, &[]).unwrap();
+        let ids: Vec<&str> = tracks.iter().filter_map(|t| t.id.as_deref()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "4uLU6hMCjMI75M1A2tKUQC",
+                "2takcwOaAZWiXQijPHIx7B",
+                "6rqhFgbbKwnb9MLmUQDhG6"
+            ]
+        );
+        assert!(Library::saved_albums(&api, &[]).unwrap().is_empty());
+        assert!(Library::saved_shows(&api).is_empty());
+    }
 }
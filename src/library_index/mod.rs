@@ -0,0 +1,154 @@
+//! A full-text search index of the library, so it can be searched offline and without walking all
+//! saved items.
+//!
+//! The index is derived from the library caches and kept up to date whenever they are saved. Like
+//! the [storage](crate::storage), the backend is hidden behind the [LibraryIndex] trait.
+
+use std::sync::Arc;
+
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteIndex;
+
+/// The kinds of items in the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+}
+
+/// The searchable text of an item.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The id of the item, or the URI for playlist tracks, which aren't guaranteed to have an id.
+    pub id: String,
+    pub title: String,
+    pub artists: String,
+    pub album: String,
+    /// For playlists, the snapshot the tracks belong to.
+    pub version: Option<String>,
+    /// For playlists, the entries of their tracks if they were loaded.
+    pub tracks: Option<Vec<Self>>,
+}
+
+/// Items that can be added to the index.
+pub trait Indexed {
+    const KIND: ItemKind;
+
+    /// The entry of this item, or `None` if it can't be indexed.
+    fn index_entry(&self) -> Option<IndexEntry>;
+}
+
+impl Indexed for Track {
+    const KIND: ItemKind = ItemKind::Track;
+
+    fn index_entry(&self) -> Option<IndexEntry> {
+        Some(IndexEntry {
+            id: self.id.clone()?,
+            ..track_entry(self)
+        })
+    }
+}
+
+impl Indexed for Album {
+    const KIND: ItemKind = ItemKind::Album;
+
+    fn index_entry(&self) -> Option<IndexEntry> {
+        Some(IndexEntry {
+            id: self.id.clone()?,
+            title: self.title.clone(),
+            artists: self.artists.join(", "),
+            album: self.title.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+impl Indexed for Artist {
+    const KIND: ItemKind = ItemKind::Artist;
+
+    fn index_entry(&self) -> Option<IndexEntry> {
+        Some(IndexEntry {
+            id: self.id.clone()?,
+            title: self.name.clone(),
+            artists: self.name.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+impl Indexed for Playlist {
+    const KIND: ItemKind = ItemKind::Playlist;
+
+    fn index_entry(&self) -> Option<IndexEntry> {
+        Some(IndexEntry {
+            id: self.id.clone(),
+            title: self.name.clone(),
+            artists: self.owner_name.clone().unwrap_or_default(),
+            album: String::new(),
+            version: Some(self.snapshot_id.clone()),
+            tracks: self.tracks.as_ref().map(|tracks| {
+                tracks
+                    .iter()
+                    .map(|playable| match playable {
+                        Playable::Track(track) => track_entry(track),
+                        Playable::Episode(episode) => IndexEntry {
+                            id: episode.uri.clone(),
+                            title: episode.name.clone(),
+                            ..Default::default()
+                        },
+                    })
+                    .collect()
+            }),
+        })
+    }
+}
+
+/// The entry of `track`, identified by its URI.
+fn track_entry(track: &Track) -> IndexEntry {
+    IndexEntry {
+        id: track.uri.clone(),
+        title: track.title.clone(),
+        artists: track.artists.join(", "),
+        album: track.album.clone().unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// The ids of the items matching a search, the best matches first.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexMatches {
+    pub tracks: Vec<String>,
+    pub albums: Vec<String>,
+    pub artists: Vec<String>,
+    /// Playlists whose name or any of whose tracks match.
+    pub playlists: Vec<String>,
+}
+
+/// A searchable index of the library.
+pub trait LibraryIndex: Send + Sync {
+    /// Replace the indexed items of `kind` with `entries`, only touching the ones that changed.
+    fn sync(&self, kind: ItemKind, entries: &[IndexEntry]) -> Result<(), String>;
+
+    /// Find the items that contain every word of `query`, also as prefix of a longer word.
+    fn search(&self, query: &str) -> Result<IndexMatches, String>;
+}
+
+/// Open the index used with the given storage backend. Only the SQLite backend has one.
+pub fn open(storage_backend: Option<&str>) -> Result<Option<Arc<dyn LibraryIndex>>, String> {
+    match storage_backend {
+        #[cfg(feature = "sqlite")]
+        Some("sqlite") => Ok(Some(Arc::new(SqliteIndex::open()?))),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::debug;
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+
+use crate::config::cache_path;
+use crate::library_index::{IndexEntry, IndexMatches, ItemKind, LibraryIndex};
+
+/// The database file in the cache directory. It only holds derived data, so it can be removed.
+const DATABASE_FILE_NAME: &str = "library.sqlite3";
+
+/// The kind of the tracks of playlists, which don't have to be saved themselves.
+const PLAYLIST_TRACK: &str = "playlist_track";
+
+/// How long to wait for other instances to release the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The items live in `library_items`, `library_search` indexes their text and is kept in sync by
+/// the triggers. `playlist_tracks` relates playlists to the URIs of their tracks.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS library_items (
+        rowid INTEGER PRIMARY KEY,
+        kind TEXT NOT NULL,
+        id TEXT NOT NULL,
+        title TEXT NOT NULL,
+        artists TEXT NOT NULL,
+        album TEXT NOT NULL,
+        UNIQUE (kind, id)
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS library_search USING fts5(
+        title, artists, album,
+        content = 'library_items', content_rowid = 'rowid',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER IF NOT EXISTS library_items_insert AFTER INSERT ON library_items BEGIN
+        INSERT INTO library_search (rowid, title, artists, album)
+        VALUES (new.rowid, new.title, new.artists, new.album);
+    END;
+    CREATE TRIGGER IF NOT EXISTS library_items_delete AFTER DELETE ON library_items BEGIN
+        INSERT INTO library_search (library_search, rowid, title, artists, album)
+        VALUES ('delete', old.rowid, old.title, old.artists, old.album);
+    END;
+    CREATE TRIGGER IF NOT EXISTS library_items_update AFTER UPDATE ON library_items BEGIN
+        INSERT INTO library_search (library_search, rowid, title, artists, album)
+        VALUES ('delete', old.rowid, old.title, old.artists, old.album);
+        INSERT INTO library_search (rowid, title, artists, album)
+        VALUES (new.rowid, new.title, new.artists, new.album);
+    END;
+    CREATE TABLE IF NOT EXISTS playlist_tracks (
+        playlist_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        uri TEXT NOT NULL,
+        PRIMARY KEY (playlist_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS playlist_tracks_uri ON playlist_tracks (uri);
+    CREATE TABLE IF NOT EXISTS playlist_snapshots (
+        playlist_id TEXT PRIMARY KEY,
+        snapshot_id TEXT NOT NULL
+    );
+";
+
+/// Indexes the library with the full-text search of SQLite.
+pub struct SqliteIndex {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteIndex {
+    pub fn open() -> Result<Self, String> {
+        let path = cache_path(DATABASE_FILE_NAME);
+        debug!("opening library index at {}", path.display());
+        let connection = Connection::open(&path)
+            .map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, String> {
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .map_err(|e| format!("Unable to initialize the library index: {e}"))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl LibraryIndex for SqliteIndex {
+    fn sync(&self, kind: ItemKind, entries: &[IndexEntry]) -> Result<(), String> {
+        let mut connection = self.connection.lock().unwrap();
+        connection
+            .transaction()
+            .and_then(|transaction| {
+                sync_items(&transaction, kind, entries)?;
+                transaction.commit()
+            })
+            .map_err(|e| format!("Unable to update the {} index: {e}", name(kind)))
+    }
+
+    fn search(&self, query: &str) -> Result<IndexMatches, String> {
+        let Some(query) = match_query(query) else {
+            return Ok(IndexMatches::default());
+        };
+        find(&self.connection.lock().unwrap(), &query)
+            .map_err(|e| format!("Unable to search the library index: {e}"))
+    }
+}
+
+/// The full-text query for what the user typed: every word has to match, at least as a prefix.
+/// Returns `None` if there is nothing to search for.
+fn match_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// The items matching the full-text `query`, and the playlists with matching tracks.
+fn find(connection: &Connection, query: &str) -> rusqlite::Result<IndexMatches> {
+    let mut matches = IndexMatches::default();
+    let mut statement = connection.prepare(
+        "SELECT i.kind, i.id FROM library_search
+         JOIN library_items i ON i.rowid = library_search.rowid
+         WHERE library_search MATCH ?1 AND i.kind != 'playlist_track'
+         ORDER BY rank",
+    )?;
+    let rows = statement.query_map(params![query], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (kind, id) = row?;
+        match kind.as_str() {
+            "track" => matches.tracks.push(id),
+            "album" => matches.albums.push(id),
+            "artist" => matches.artists.push(id),
+            "playlist" => matches.playlists.push(id),
+            _ => {}
+        }
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT DISTINCT t.playlist_id FROM library_search
+         JOIN library_items i ON i.rowid = library_search.rowid
+         JOIN playlist_tracks t ON t.uri = i.id
+         WHERE library_search MATCH ?1 AND i.kind = 'playlist_track'",
+    )?;
+    for id in statement.query_map(params![query], |row| row.get::<_, String>(0))? {
+        let id = id?;
+        if !matches.playlists.contains(&id) {
+            matches.playlists.push(id);
+        }
+    }
+    Ok(matches)
+}
+
+/// Bring the items of `kind` in line with `entries`. Unchanged rows aren't written, so the search
+/// index is only updated for the items that actually changed.
+fn sync_items(
+    transaction: &Transaction,
+    kind: ItemKind,
+    entries: &[IndexEntry],
+) -> rusqlite::Result<()> {
+    let existing = ids(transaction, kind)?;
+    for entry in entries {
+        upsert(transaction, name(kind), entry)?;
+        if let Some(tracks) = &entry.tracks {
+            sync_playlist_tracks(transaction, entry, tracks)?;
+        }
+    }
+
+    let current: HashSet<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+    for id in existing.iter().filter(|id| !current.contains(id.as_str())) {
+        transaction.execute(
+            "DELETE FROM library_items WHERE kind = ?1 AND id = ?2",
+            params![name(kind), id],
+        )?;
+        if kind == ItemKind::Playlist {
+            transaction.execute(
+                "DELETE FROM playlist_tracks WHERE playlist_id = ?1",
+                params![id],
+            )?;
+            transaction.execute(
+                "DELETE FROM playlist_snapshots WHERE playlist_id = ?1",
+                params![id],
+            )?;
+        }
+    }
+
+    if kind == ItemKind::Playlist {
+        // Tracks are shared between playlists, only drop those no playlist contains anymore.
+        transaction.execute(
+            "DELETE FROM library_items WHERE kind = 'playlist_track'
+             AND id NOT IN (SELECT uri FROM playlist_tracks)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Replace the tracks of the playlist `entry` unless they are from the same snapshot.
+fn sync_playlist_tracks(
+    transaction: &Transaction,
+    entry: &IndexEntry,
+    tracks: &[IndexEntry],
+) -> rusqlite::Result<()> {
+    let indexed: Option<String> = transaction
+        .query_row(
+            "SELECT snapshot_id FROM playlist_snapshots WHERE playlist_id = ?1",
+            params![entry.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if indexed.is_some() && indexed == entry.version {
+        return Ok(());
+    }
+
+    transaction.execute(
+        "DELETE FROM playlist_tracks WHERE playlist_id = ?1",
+        params![entry.id],
+    )?;
+    let mut insert = transaction.prepare_cached(
+        "INSERT INTO playlist_tracks (playlist_id, position, uri) VALUES (?1, ?2, ?3)",
+    )?;
+    for (position, track) in tracks.iter().enumerate() {
+        insert.execute(params![entry.id, position, track.id])?;
+        upsert(transaction, PLAYLIST_TRACK, track)?;
+    }
+    transaction.execute(
+        "INSERT OR REPLACE INTO playlist_snapshots (playlist_id, snapshot_id) VALUES (?1, ?2)",
+        params![entry.id, entry.version.clone().unwrap_or_default()],
+    )?;
+    Ok(())
+}
+
+/// Insert `entry`, or update it if its text changed.
+fn upsert(transaction: &Transaction, kind: &str, entry: &IndexEntry) -> rusqlite::Result<()> {
+    transaction
+        .prepare_cached(
+            "INSERT INTO library_items (kind, id, title, artists, album) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (kind, id) DO UPDATE
+             SET title = excluded.title, artists = excluded.artists, album = excluded.album
+             WHERE title != excluded.title OR artists != excluded.artists
+             OR album != excluded.album",
+        )?
+        .execute(params![
+            kind,
+            entry.id,
+            entry.title,
+            entry.artists,
+            entry.album
+        ])?;
+    Ok(())
+}
+
+/// The name of `kind` in the `kind` column.
+fn name(kind: ItemKind) -> &'static str {
+    match kind {
+        ItemKind::Track => "track",
+        ItemKind::Album => "album",
+        ItemKind::Artist => "artist",
+        ItemKind::Playlist => "playlist",
+    }
+}
+
+fn ids(transaction: &Transaction, kind: ItemKind) -> rusqlite::Result<HashSet<String>> {
+    let mut statement = transaction.prepare("SELECT id FROM library_items WHERE kind = ?1")?;
+    statement
+        .query_map(params![name(kind)], |row| row.get(0))?
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, title: &str, artists: &str) -> IndexEntry {
+        IndexEntry {
+            id: id.into(),
+            title: title.into(),
+            artists: artists.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_match_query() {
+        assert_eq!(match_query("  "), None);
+        assert_eq!(match_query("daft punk"), Some("\"daft\"* \"punk\"*".into()));
+        assert_eq!(
+            match_query("say \"hi\""),
+            Some("\"say\"* \"\"\"hi\"\"\"*".into())
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let index = SqliteIndex::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        index
+            .sync(
+                ItemKind::Track,
+                &[
+                    entry("1", "Around the World", "Daft Punk"),
+                    entry("2", "Café del Mar", "Energy 52"),
+                ],
+            )
+            .unwrap();
+        let playlist = IndexEntry {
+            version: Some("a".into()),
+            tracks: Some(vec![entry("spotify:track:3", "Windowlicker", "Aphex Twin")]),
+            ..entry("p", "Electronic", "")
+        };
+        index
+            .sync(ItemKind::Playlist, std::slice::from_ref(&playlist))
+            .unwrap();
+
+        assert_eq!(index.search("daft aro").unwrap().tracks, ["1"]);
+        assert_eq!(index.search("cafe").unwrap().tracks, ["2"]);
+        let matches = index.search("aphex").unwrap();
+        assert!(matches.tracks.is_empty());
+        assert_eq!(matches.playlists, ["p"]);
+
+        index
+            .sync(ItemKind::Track, &[entry("2", "Café del Mar", "Energy 52")])
+            .unwrap();
+        assert!(index.search("daft").unwrap().tracks.is_empty());
+        index.sync(ItemKind::Playlist, &[]).unwrap();
+        assert!(index.search("aphex").unwrap().playlists.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+//! Incremental syncing of the saved tracks and albums.
+//!
+//! The Web API lists saved items with the most recently saved first, but it can't tell what was
+//! added or removed since a point in time. Instead of fetching everything again, the newest pages
+//! are fetched until an item that is already cached shows up. When the merged list has as many
+//! items as the API reports, nothing older was removed and the sync is complete. Otherwise all
+//! items are fetched again, as there is no way to find out which ones were removed.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+
+use crate::model::album::Album;
+use crate::model::track::Track;
+
+/// An item of a saved collection.
+pub trait Saved {
+    fn saved_id(&self) -> Option<&str>;
+
+    /// When the item was saved. An item saved again after removing it counts as a new item.
+    fn added_at(&self) -> Option<DateTime<Utc>>;
+}
+
+impl Saved for Track {
+    fn saved_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn added_at(&self) -> Option<DateTime<Utc>> {
+        self.added_at
+    }
+}
+
+impl Saved for Album {
+    fn saved_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn added_at(&self) -> Option<DateTime<Utc>> {
+        self.added_at
+    }
+}
+
+/// A page of saved items, the most recently saved first.
+pub struct SavedPage<T> {
+    pub items: Vec<T>,
+    /// The number of saved items in total.
+    pub total: u32,
+    pub has_next: bool,
+}
+
+/// Bring the `cached` items up to date. `fetch_page` fetches the page at the given offset.
+pub fn fetch_saved<T: Saved + Clone>(
+    cached: &[T],
+    fetch_page: impl Fn(u32) -> Result<SavedPage<T>, ()>,
+) -> Result<Vec<T>, ()> {
+    let (newest, total) = fetch_newest(cached, &fetch_page)?;
+    let added = newest.len();
+    let new_ids: HashSet<Option<&str>> = newest.iter().map(Saved::saved_id).collect();
+    let merged: Vec<T> = newest
+        .iter()
+        .cloned()
+        .chain(
+            cached
+                .iter()
+                .filter(|item| !new_ids.contains(&item.saved_id()))
+                .cloned(),
+        )
+        .collect();
+    if merged.len() == total as usize {
+        debug!("{added} saved items were added");
+        return Ok(merged);
+    }
+
+    debug!("saved items were removed, fetching all of them");
+    fetch_newest(&[], &fetch_page).map(|(items, _)| items)
+}
+
+/// Fetch the items that were saved after the newest `cached` one, and the total number of items.
+fn fetch_newest<T: Saved>(
+    cached: &[T],
+    fetch_page: &impl Fn(u32) -> Result<SavedPage<T>, ()>,
+) -> Result<(Vec<T>, u32), ()> {
+    let known: HashSet<(Option<&str>, Option<DateTime<Utc>>)> = cached
+        .iter()
+        .map(|item| (item.saved_id(), item.added_at()))
+        .collect();
+    let mut newest = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = fetch_page(offset)?;
+        offset += page.items.len() as u32;
+        let fetched = page.items.len();
+        for item in page.items {
+            if known.contains(&(item.saved_id(), item.added_at())) {
+                return Ok((newest, page.total));
+            }
+            newest.push(item);
+        }
+        if !page.has_next || fetched == 0 {
+            return Ok((newest, page.total));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Item(&'static str, i64);
+
+    impl Saved for Item {
+        fn saved_id(&self) -> Option<&str> {
+            Some(self.0)
+        }
+
+        fn added_at(&self) -> Option<DateTime<Utc>> {
+            Utc.timestamp_opt(self.1, 0).single()
+        }
+    }
+
+    /// Sync `cached` with `remote` in pages of two items, returning the result and the number of
+    /// fetched pages.
+    fn sync(cached: &[Item], remote: &[Item]) -> (Vec<Item>, u32) {
+        let fetches = Cell::new(0);
+        let synced = fetch_saved(cached, |offset| {
+            fetches.set(fetches.get() + 1);
+            let start = (offset as usize).min(remote.len());
+            let end = (start + 2).min(remote.len());
+            Ok(SavedPage {
+                items: remote[start..end].to_vec(),
+                total: remote.len() as u32,
+                has_next: end < remote.len(),
+            })
+        })
+        .unwrap();
+        (synced, fetches.get())
+    }
+
+    #[test]
+    fn test_fetch_saved() {
+        let cached = [Item("c", 3), Item("b", 2), Item("a", 1)];
+
+        // Unchanged: a single page.
+        assert_eq!(sync(&cached, &cached), (cached.to_vec(), 1));
+
+        // Added and saved again: only the new pages.
+        let remote = [Item("a", 5), Item("d", 4), Item("c", 3), Item("b", 2)];
+        assert_eq!(sync(&cached, &remote), (remote.to_vec(), 2));
+
+        // Removed: everything again.
+        let remote = [Item("d", 4), Item("c", 3), Item("a", 1)];
+        assert_eq!(sync(&cached, &remote), (remote.to_vec(), 3));
+
+        // Nothing cached.
+        assert_eq!(sync(&[], &cached), (cached.to_vec(), 2));
+    }
+}
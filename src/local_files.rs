@@ -0,0 +1,222 @@
+//! Playing music files from local directories alongside Spotify content. librespot decodes the
+//! files itself and finds them by a `spotify:local:` uri made from their tags, the same kind of
+//! uri the Spotify clients use for local files in playlists. So the files are indexed the same way
+//! here, which makes local files in playlists playable as well when they are found on disk.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use log::{debug, warn};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, Tag};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::config::Config;
+use crate::model::track::Track;
+
+/// The file types both librespot looks for and can decode.
+const EXTENSIONS: [&str; 2] = ["mp3", "flac"];
+
+/// The uris of the indexed files.
+static INDEXED: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// The configured directories with local files.
+pub fn directories(cfg: &Config) -> Vec<PathBuf> {
+    cfg.values()
+        .local_directories
+        .iter()
+        .flatten()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Whether a local file with `uri` was found in the local directories.
+pub fn is_available(uri: &str) -> bool {
+    INDEXED.read().unwrap().contains(uri)
+}
+
+/// Read the tags of all supported files in `directories` and their subdirectories.
+pub fn index(directories: &[PathBuf]) -> Vec<Track> {
+    let mut tracks = Vec::new();
+    for directory in directories {
+        if let Err(e) = visit(directory, &mut tracks) {
+            warn!("could not index {}: {e}", directory.display());
+        }
+    }
+    tracks.sort_by(|a, b| {
+        (&a.album_artists, &a.album, a.disc_number, a.track_number).cmp(&(
+            &b.album_artists,
+            &b.album,
+            b.disc_number,
+            b.track_number,
+        ))
+    });
+    for (index, track) in tracks.iter_mut().enumerate() {
+        track.list_index = index;
+    }
+    *INDEXED.write().unwrap() = tracks.iter().map(|track| track.uri.clone()).collect();
+    debug!("indexed {} local files", tracks.len());
+    tracks
+}
+
+fn visit(directory: &Path, tracks: &mut Vec<Track>) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            visit(&path, tracks)?;
+            continue;
+        }
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            continue;
+        };
+        if EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            match read(&path, extension) {
+                Ok(track) => tracks.push(track),
+                Err(e) => warn!("could not read {}: {e}", path.display()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The tags of the latest metadata revision, from the container or else from the probe.
+fn tags(probed: &mut ProbeResult) -> Vec<Tag> {
+    let mut metadata = probed.format.metadata();
+    if metadata.current().is_none()
+        && let Some(probe_metadata) = probed.metadata.get()
+    {
+        metadata = probe_metadata;
+    }
+    metadata.skip_to_latest();
+    metadata
+        .current()
+        .map(|revision| revision.tags().to_vec())
+        .unwrap_or_default()
+}
+
+/// The leading number of a tag like `3` or `3/12`.
+fn number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+fn read(path: &Path, extension: &str) -> Result<Track, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let (mut artist, mut album, mut title, mut album_artist) = (None, None, None, None);
+    let (mut track_number, mut disc_number, mut release_year) = (None, None, None);
+    // Like librespot, the last value of a tag wins.
+    for tag in tags(&mut probed) {
+        let value = tag.value.to_string();
+        match tag.std_key {
+            Some(StandardTagKey::Artist) => artist = Some(value),
+            Some(StandardTagKey::Album) => album = Some(value),
+            Some(StandardTagKey::TrackTitle) => title = Some(value),
+            Some(StandardTagKey::AlbumArtist) => album_artist = Some(value),
+            Some(StandardTagKey::TrackNumber) => track_number = number(&value),
+            Some(StandardTagKey::DiscNumber) => disc_number = number(&value),
+            Some(StandardTagKey::Date) => {
+                release_year = value.get(..4).and_then(|y| y.parse().ok())
+            }
+            _ => {}
+        }
+    }
+
+    let params = &probed
+        .format
+        .default_track()
+        .ok_or("no audio track")?
+        .codec_params;
+    let time = match (params.time_base, params.n_frames) {
+        (Some(time_base), Some(frames)) => time_base.calc_time(frames),
+        _ => return Err("unknown duration".into()),
+    };
+
+    let artists: Vec<String> = artist.iter().cloned().collect();
+    Ok(Track {
+        id: None,
+        uri: uri(
+            artist.as_deref(),
+            album.as_deref(),
+            title.as_deref(),
+            time.seconds,
+        ),
+        title: title.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        }),
+        track_number: track_number.unwrap_or(0),
+        disc_number: disc_number.unwrap_or(1) as i32,
+        duration: (time.seconds * 1000 + (time.frac * 1000.0) as u64) as u32,
+        album_artists: album_artist
+            .map(|a| vec![a])
+            .unwrap_or_else(|| artists.clone()),
+        artists,
+        artist_ids: Vec::new(),
+        album,
+        album_id: None,
+        cover_url: None,
+        url: String::new(),
+        added_at: None,
+        added_by: None,
+        popularity: None,
+        release_year,
+        list_index: 0,
+        is_local: true,
+        is_playable: Some(true),
+    })
+}
+
+/// The uri librespot looks up a local file by, with the url encoded tags and the duration in whole
+/// seconds. Missing tags are left empty.
+pub fn uri(artist: Option<&str>, album: Option<&str>, title: Option<&str>, seconds: u64) -> String {
+    let part = |value: Option<&str>| {
+        value
+            .map(|value| url::form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>())
+            .unwrap_or_default()
+    };
+    format!(
+        "spotify:local:{}:{}:{}:{seconds}",
+        part(artist),
+        part(album),
+        part(title)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri() {
+        // The example from librespot's tests.
+        assert_eq!(
+            uri(
+                Some("David Wise"),
+                Some("Donkey Kong Country: Tropical Freeze"),
+                Some("Snomads Island"),
+                127
+            ),
+            "spotify:local:David+Wise:Donkey+Kong+Country%3A+Tropical+Freeze:Snomads+Island:127"
+        );
+        assert_eq!(
+            uri(None, None, Some("Snomads Island"), 127),
+            "spotify:local:::Snomads+Island:127"
+        );
+        assert_eq!(number("3/12"), Some(3));
+    }
+}
@@ -0,0 +1,202 @@
+//! Lyrics of tracks, read from a local directory or fetched from an LRCLIB compatible provider.
+//!
+//! Synced lyrics come in the LRC format, where every line starts with the time it is sung at, like
+//! `[01:23.45] Text`. Lyrics without times are shown as they are.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use log::debug;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::model::track::Track;
+use crate::proxy;
+use crate::spotify_api::WebApi;
+
+/// The provider lyrics are fetched from unless another one is configured.
+pub const DEFAULT_PROVIDER: &str = "https://lrclib.net";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Line {
+    /// The time the line is sung at in milliseconds, if the lyrics are synced.
+    pub time: Option<u32>,
+    pub text: String,
+}
+
+/// The lyrics of a track. Tracks without known lyrics have no lines.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Lyrics {
+    pub lines: Vec<Line>,
+    pub instrumental: bool,
+}
+
+impl Lyrics {
+    pub fn is_synced(&self) -> bool {
+        self.lines.first().is_some_and(|line| line.time.is_some())
+    }
+
+    /// The index of the line sung at `position`, for synced lyrics.
+    pub fn current_line(&self, position: u32) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| line.time.is_some_and(|time| time <= position))
+    }
+}
+
+/// A time tag like `[01:23.45]`, of which a line can have several when it is repeated.
+static TIME_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[(\d+):(\d{2})(?:[.:](\d{1,3}))?\]").unwrap());
+
+/// An ID tag like `[ar: Artist]`, which holds information about the song rather than lyrics.
+static ID_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[[a-z#]+:.*\]$").unwrap());
+
+/// Parse lyrics in the LRC format, or plain lyrics when no line has a time.
+pub fn parse(text: &str) -> Lyrics {
+    let mut timed = Vec::new();
+    let mut plain = Vec::new();
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(captures) = TIME_TAG.captures(rest) {
+            let number = |group| {
+                captures
+                    .get(group)
+                    .map_or(0, |m| m.as_str().parse::<u32>().unwrap_or(0))
+            };
+            // The fraction is in hundredths usually, but some files use tenths or milliseconds.
+            let fraction = captures
+                .get(3)
+                .map_or(0, |m| number(3) * 10u32.pow(3 - m.as_str().len() as u32));
+            times.push((number(1) * 60 + number(2)) * 1000 + fraction);
+            rest = rest[captures.get(0).unwrap().end()..].trim_start();
+        }
+        if times.is_empty() {
+            if !ID_TAG.is_match(rest) {
+                plain.push(Line {
+                    time: None,
+                    text: rest.to_string(),
+                });
+            }
+            continue;
+        }
+        timed.extend(times.into_iter().map(|time| Line {
+            time: Some(time),
+            text: rest.to_string(),
+        }));
+    }
+
+    let lines = if timed.is_empty() {
+        // Leading and trailing empty lines are left over from the formatting of the source.
+        let start = plain.iter().position(|l| !l.text.is_empty());
+        let end = plain.iter().rposition(|l| !l.text.is_empty());
+        match (start, end) {
+            (Some(start), Some(end)) => plain.drain(start..=end).collect(),
+            _ => Vec::new(),
+        }
+    } else {
+        timed.sort_by_key(|line| line.time);
+        timed
+    };
+    Lyrics {
+        lines,
+        instrumental: false,
+    }
+}
+
+/// Look up the lyrics of `track` on the LRCLIB compatible `provider`. Returns `None` when the
+/// provider doesn't know the track.
+pub fn lrclib(provider: &str, track: &Track) -> Result<Option<Lyrics>, String> {
+    let client = proxy::client_builder()
+        .user_agent(format!(
+            "ncspot/{} ( https://github.com/hrkfdn/ncspot )",
+            env!("VERSION")
+        ))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut query = vec![
+        ("artist_name", track.artists.join(", ")),
+        ("track_name", track.title.clone()),
+        ("duration", (track.duration / 1000).to_string()),
+    ];
+    if let Some(album) = &track.album {
+        query.push(("album_name", album.clone()));
+    }
+    let url = format!("{}/api/get", provider.trim_end_matches('/'));
+    let url = url::Url::parse_with_params(&url, &query).map_err(|e| e.to_string())?;
+    debug!("fetching {url}");
+    let response = client
+        .get(url)
+        .send()
+        .map_err(|e| format!("Lyrics request failed: {e}"))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let value: Value = response
+        .error_for_status()
+        .and_then(|response| response.json())
+        .map_err(|e| format!("Lyrics request failed: {e}"))?;
+
+    if value["instrumental"].as_bool().unwrap_or(false) {
+        return Ok(Some(Lyrics {
+            lines: Vec::new(),
+            instrumental: true,
+        }));
+    }
+    Ok(["syncedLyrics", "plainLyrics"]
+        .iter()
+        .filter_map(|key| value[key].as_str())
+        .map(parse)
+        .find(|lyrics| !lyrics.lines.is_empty()))
+}
+
+/// The lyrics file of `track` in the configured lyrics directory, named like
+/// `Artist - Title.lrc` or `Artist - Title.txt`.
+fn local_file(cfg: &Config, track: &Track) -> Option<PathBuf> {
+    let directory = PathBuf::from(cfg.values().lyrics_directory.clone()?);
+    let artist = track.artists.first().map_or("", String::as_str);
+    // Slashes can't be part of file names.
+    let name = format!("{artist} - {}", track.title).replace('/', "_");
+    ["lrc", "txt"]
+        .iter()
+        .map(|extension| directory.join(format!("{name}.{extension}")))
+        .find(|path| path.is_file())
+}
+
+/// The lyrics of `track`, preferably from the lyrics directory, else from the configured provider.
+pub fn find(cfg: &Config, api: &WebApi, track: &Track) -> Result<Lyrics, String> {
+    if let Some(path) = local_file(cfg, track) {
+        debug!("reading lyrics from {}", path.display());
+        return fs::read_to_string(&path)
+            .map(|text| parse(&text))
+            .map_err(|e| format!("Could not read {}: {e}", path.display()));
+    }
+    match cfg.values().lyrics_provider.as_deref() {
+        Some("none") => Ok(Lyrics::default()),
+        provider => api.lyrics(provider.unwrap_or(DEFAULT_PROVIDER), track),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let lyrics =
+            parse("[ar: Someone]\n[00:12.34]First line\n[00:20.5][01:10.500]Chorus\n[00:15]");
+        assert!(lyrics.is_synced());
+        let times: Vec<_> = lyrics.lines.iter().map(|l| l.time.unwrap()).collect();
+        assert_eq!(times, [12_340, 15_000, 20_500, 70_500]);
+        assert_eq!(lyrics.lines[2].text, "Chorus");
+        assert_eq!(lyrics.current_line(16_000), Some(1));
+        assert_eq!(lyrics.current_line(1_000), None);
+
+        let lyrics = parse("\nFirst line\n\nSecond verse\n\n");
+        assert!(!lyrics.is_synced());
+        assert_eq!(lyrics.lines.len(), 3);
+        assert_eq!(lyrics.current_line(16_000), None);
+    }
+}
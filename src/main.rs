@@ -10,37 +10,114 @@ use config::set_configuration_base_path;
 use log::error;
 use ncspot::program_arguments;
 
+mod api_cache;
 mod application;
+mod audio_devices;
 mod authentication;
+mod backup;
+mod caches;
+mod chapters;
 mod cli;
 mod client_config;
+mod columns;
 mod command;
 mod commands;
 mod config;
+mod credits;
+mod data_usage;
+mod discord;
+mod downloads;
+mod duplicates;
+mod episode_progress;
 mod events;
 mod ext_traits;
+mod fuzzy;
+mod gain;
+mod help;
+mod hooks;
+mod http;
+mod instance;
+mod journal;
 mod library;
+mod library_index;
+mod library_sync;
+mod local_files;
+mod lyrics;
+mod marks;
+#[cfg(all(test, feature = "mock"))]
+mod mock_api;
 mod model;
 mod panic;
+mod play_history;
+mod playlist_folders;
+mod playlist_history;
+mod playlist_merge;
+mod plugins;
+mod proxy;
+mod qr;
 mod queue;
+mod queue_autosave;
+mod queue_source;
+mod rate_limiter;
 mod redirect_uri;
+mod relink;
+mod scripting;
+mod scrobbler;
+mod search_filter;
 mod serialization;
+mod session;
 mod sharing;
+mod shuffle;
+mod skip_silence;
+mod smart_playlist;
+mod sorting;
 mod spotify;
 mod spotify_api;
 mod spotify_url;
 mod spotify_worker;
+mod state_sync;
+mod status;
+mod storage;
+mod terminal_title;
 mod theme;
+mod themes;
+mod token_encryption;
 mod traits;
 mod ui;
+mod undo;
 mod utils;
+mod visualizer;
 
-#[cfg(unix)]
 mod ipc;
 
 #[cfg(feature = "mpris")]
 mod mpris;
 
+/// Replace this process with a new ncspot instance with the same arguments.
+fn restart() -> ! {
+    let Ok(program) = std::env::current_exe() else {
+        exit(0);
+    };
+    let mut command = std::process::Command::new(program);
+    command.args(std::env::args_os().skip(1));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = command.exec();
+        eprintln!("Could not restart ncspot: {error}");
+        exit(-1);
+    }
+    #[cfg(not(unix))]
+    match command.status() {
+        Ok(status) => exit(status.code().unwrap_or(0)),
+        Err(error) => {
+            eprintln!("Could not restart ncspot: {error}");
+            exit(-1);
+        }
+    }
+}
+
 fn main() -> Result<(), String> {
     // Set a custom backtrace hook that writes the backtrace to a file instead of stdout, since
     // stdout is most likely in use by Cursive.
@@ -58,23 +135,86 @@ fn main() -> Result<(), String> {
     // path.
     set_configuration_base_path(matches.get_one::<PathBuf>("basepath").cloned());
 
+    // The proxy has to be set up before any other thread is started.
+    let proxy = config::proxy(matches.get_one::<String>("config").map(String::as_str));
+    if let Err(error) = proxy::init(proxy.as_deref()) {
+        eprintln!("{error}");
+        exit(-1);
+    }
+
+    // Credentials on the command line take precedence over the environment and client.yml.
+    client_config::set_overrides(client_config::Overrides {
+        client_id: matches.get_one::<String>("client-id").cloned(),
+        client_secret: matches.get_one::<String>("client-secret").cloned(),
+        port: matches.get_one::<String>("port").cloned(),
+    });
+
+    // Reserve an instance number, so multiple instances can share the configuration directory.
+    instance::register(matches.get_one::<String>("name").cloned());
+
     match matches.subcommand() {
+        Some(("backup", subcommand_matches)) => cli::backup(
+            matches.get_one::<String>("config").cloned(),
+            subcommand_matches
+                .get_one::<PathBuf>("directory")
+                .expect("directory is required"),
+        ),
         Some(("info", _subcommand_matches)) => cli::info(),
+        Some(("logout", _subcommand_matches)) => cli::logout(),
+        Some(("play", subcommand_matches)) => cli::play(
+            matches.get_one::<String>("config").cloned(),
+            subcommand_matches
+                .get_one::<String>("uri")
+                .expect("uri is required"),
+            subcommand_matches.get_flag("exit-after"),
+        ),
+        Some(("restore", subcommand_matches)) => cli::restore(
+            matches.get_one::<String>("config").cloned(),
+            subcommand_matches
+                .get_one::<PathBuf>("path")
+                .expect("path is required"),
+        ),
+        Some(("send", subcommand_matches)) => cli::send(
+            &subcommand_matches
+                .get_many::<String>("command")
+                .expect("command is required")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        Some(("status", subcommand_matches)) => cli::status(
+            subcommand_matches
+                .get_one::<String>("format")
+                .expect("format has a default"),
+            subcommand_matches.get_flag("follow"),
+            subcommand_matches.get_flag("json"),
+        ),
         Some((_, _)) => unreachable!(),
         None => {
             // Create the application.
-            let mut application =
-                match Application::new(matches.get_one::<String>("config").cloned()) {
-                    Ok(application) => application,
-                    Err(error) => {
-                        eprintln!("{error}");
-                        error!("{error}");
-                        exit(-1);
-                    }
-                };
+            let mut application = match Application::new(
+                matches.get_one::<String>("config").cloned(),
+                matches.get_flag("no-browser"),
+                matches.get_one::<String>("url").cloned(),
+            ) {
+                Ok(application) => application,
+                Err(error) => {
+                    eprintln!("{error}");
+                    error!("{error}");
+                    exit(-1);
+                }
+            };
 
             // Start the application event loop.
-            application.run()
+            let result = application.run();
+
+            // Start over with the login after the user logged out, once the terminal is restored.
+            drop(application);
+            if authentication::logged_out() {
+                cli::print_logout_hint();
+                restart();
+            }
+            result
         }
     }?;
 
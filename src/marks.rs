@@ -0,0 +1,98 @@
+//! Places in the views that can be returned to: marks set by name, and the jump list that records
+//! where the user was before switching screens, opening a view or jumping to a mark.
+
+/// The view on top of a screen and the item selected in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub screen: String,
+    /// How many views were opened on top of the screen, 0 for the screen itself.
+    pub depth: usize,
+    /// The title of the view, to tell if it is still the same view.
+    pub title: String,
+    pub position: Option<usize>,
+}
+
+/// The most locations kept in the jump list.
+const MAX_JUMPS: usize = 100;
+
+/// The locations jumped away from, oldest first. Going back and forward moves through them like
+/// the history of a browser: jumping somewhere new drops the locations after the current one.
+#[derive(Default)]
+pub struct JumpList {
+    locations: Vec<Location>,
+    /// The location that was gone back to, or the length of `locations` if none was.
+    index: usize,
+}
+
+impl JumpList {
+    /// Remember `location` before jumping away from it.
+    pub fn record(&mut self, location: Location) {
+        self.locations.truncate(self.index);
+        if self.locations.last() != Some(&location) {
+            self.locations.push(location);
+        }
+        if self.locations.len() > MAX_JUMPS {
+            self.locations.remove(0);
+        }
+        self.index = self.locations.len();
+    }
+
+    /// The location before the current one, which is `current` unless it was gone back to.
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        if self.index == self.locations.len() {
+            // The current location is kept to go forward to it again.
+            if self.locations.last() != Some(&current) {
+                self.locations.push(current);
+            }
+            self.index = self.locations.len() - 1;
+        }
+        self.index = self.index.checked_sub(1)?;
+        Some(self.locations[self.index].clone())
+    }
+
+    /// The location after the current one, if it was gone back from.
+    pub fn forward(&mut self) -> Option<Location> {
+        if self.index + 1 >= self.locations.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.locations[self.index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(screen: &str, depth: usize) -> Location {
+        Location {
+            screen: screen.to_string(),
+            depth,
+            title: format!("{screen} {depth}"),
+            position: None,
+        }
+    }
+
+    #[test]
+    fn test_jump_list() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(location("queue", 0)), None);
+
+        jumps.record(location("queue", 0));
+        jumps.record(location("library", 2));
+        let current = location("search", 1);
+        assert_eq!(jumps.back(current.clone()), Some(location("library", 2)));
+        assert_eq!(jumps.back(current.clone()), Some(location("queue", 0)));
+        assert_eq!(jumps.back(current.clone()), None);
+        assert_eq!(jumps.forward(), Some(location("library", 2)));
+        assert_eq!(jumps.forward(), Some(current));
+        assert_eq!(jumps.forward(), None);
+
+        // Jumping somewhere new after going back drops the locations that came after.
+        jumps.back(location("search", 1));
+        jumps.back(location("search", 1));
+        jumps.record(location("queue", 0));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(location("cover", 0)), Some(location("queue", 0)));
+    }
+}
@@ -0,0 +1,335 @@
+//! A [SpotifyApi] with canned responses, so the queue, library and backup logic can be tested
+//! without network access or credentials.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{TimeZone, Utc};
+use rspotify::model::{
+    Cursor, CursorBasedPage, Followers, FullArtist, FullPlaylist, FullTrack, Page, PlayableItem,
+    PlaylistId, PlaylistItem, PrivateUser, PublicUser, Recommendations, RecommendationsAttribute,
+    SavedAlbum, SavedTrack, Show, SimplifiedArtist, SimplifiedTrack, UserId,
+};
+use rspotify::prelude::Id;
+use serde::de::DeserializeOwned;
+use tokio_util::sync::CancellationToken;
+
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::spotify_api::SpotifyApi;
+use crate::traits::ListItem;
+use crate::ui::pagination::{ApiPage, ApiResult};
+
+/// The id of the user the mock is logged in as.
+pub const MOCK_USER_ID: &str = "mock";
+
+/// The id of the playlist that contains all fixture tracks.
+pub const FIXTURE_PLAYLIST_ID: &str = "37i9dQZF1DXcBWIGoYBM5M";
+
+pub struct MockApi {
+    pub tracks: Vec<FullTrack>,
+    pub playlists: HashMap<String, FullPlaylist>,
+    /// The saved tracks, the most recently saved first.
+    pub saved_tracks: Vec<FullTrack>,
+    pub followed_artists: Vec<FullArtist>,
+    /// The size of the pages of saved items and followed artists.
+    pub page_size: usize,
+    /// The requests that were made, like `recommendations` or `playlist 37i9dQZF1DXcBWIGoYBM5M`.
+    requests: Mutex<Vec<String>>,
+}
+
+impl MockApi {
+    /// Create a mock that knows the tracks in `tests/fixtures/tracks.json` and a playlist with
+    /// [FIXTURE_PLAYLIST_ID] that contains them. Recommendations return all of them. The library
+    /// is empty.
+    pub fn with_fixtures() -> Self {
+        let tracks: Vec<FullTrack> =
+            serde_json::from_str(include_str!("../tests/fixtures/tracks.json")).unwrap();
+        let playlist = playlist(FIXTURE_PLAYLIST_ID, "Fixtures", &tracks);
+        Self {
+            tracks,
+            playlists: HashMap::from([(FIXTURE_PLAYLIST_ID.to_string(), playlist)]),
+            saved_tracks: Vec::new(),
+            followed_artists: Vec::new(),
+            page_size: 50,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Like [MockApi::with_fixtures], with all fixture tracks saved and their artists followed.
+    pub fn with_library() -> Self {
+        let mut api = Self::with_fixtures();
+        api.saved_tracks = api.tracks.clone();
+        for track in &api.tracks {
+            for artist in &track.artists {
+                if !api.followed_artists.iter().any(|a| a.name == artist.name) {
+                    api.followed_artists.push(full_artist(artist));
+                }
+            }
+        }
+        api
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn request(&self, request: String) {
+        self.requests.lock().unwrap().push(request);
+    }
+}
+
+/// Build a playlist owned by the user [MOCK_USER_ID] with `tracks`.
+pub fn playlist(id: &str, name: &str, tracks: &[FullTrack]) -> FullPlaylist {
+    let owner = PublicUser {
+        display_name: Some("Mock".into()),
+        external_urls: HashMap::new(),
+        followers: None,
+        href: String::new(),
+        id: UserId::from_id(MOCK_USER_ID).unwrap().into_static(),
+        images: Vec::new(),
+    };
+    FullPlaylist {
+        collaborative: false,
+        description: None,
+        external_urls: HashMap::new(),
+        followers: Default::default(),
+        href: String::new(),
+        id: PlaylistId::from_id(id).unwrap().into_static(),
+        images: Vec::new(),
+        name: name.into(),
+        owner: owner.clone(),
+        public: Some(true),
+        snapshot_id: "snapshot".into(),
+        tracks: Page {
+            href: String::new(),
+            items: tracks
+                .iter()
+                .map(|track| PlaylistItem {
+                    added_at: None,
+                    added_by: Some(owner.clone()),
+                    is_local: false,
+                    track: Some(PlayableItem::Track(track.clone())),
+                })
+                .collect(),
+            limit: 100,
+            next: None,
+            offset: 0,
+            previous: None,
+            total: tracks.len() as u32,
+        },
+    }
+}
+
+fn full_artist(artist: &SimplifiedArtist) -> FullArtist {
+    FullArtist {
+        external_urls: artist.external_urls.clone(),
+        followers: Followers { total: 0 },
+        genres: Vec::new(),
+        href: artist.href.clone().unwrap_or_default(),
+        id: artist.id.clone().unwrap(),
+        images: Vec::new(),
+        name: artist.name.clone(),
+        popularity: 0,
+    }
+}
+
+/// The page of `items` at `offset`.
+fn page<T: Clone + DeserializeOwned>(items: &[T], offset: u32, size: usize) -> Page<T> {
+    let offset = (offset as usize).min(items.len());
+    let end = (offset + size).min(items.len());
+    Page {
+        href: String::new(),
+        items: items[offset..end].to_vec(),
+        limit: size as u32,
+        next: (end < items.len()).then(String::new),
+        offset: offset as u32,
+        previous: None,
+        total: items.len() as u32,
+    }
+}
+
+/// A result that has all `items` on its first page.
+fn api_result<I: ListItem + Clone>(items: Vec<I>) -> ApiResult<I> {
+    let total = items.len() as u32;
+    ApiResult::new(
+        total.max(1),
+        0,
+        Arc::new(move |offset| {
+            Some(ApiPage {
+                offset,
+                total,
+                items: items.clone(),
+            })
+        }),
+        CancellationToken::new(),
+    )
+}
+
+impl SpotifyApi for MockApi {
+    fn current_user(&self) -> Result<PrivateUser, ()> {
+        self.request("current_user".into());
+        Ok(PrivateUser {
+            country: None,
+            display_name: Some("Mock".into()),
+            email: None,
+            external_urls: HashMap::new(),
+            explicit_content: None,
+            followers: None,
+            href: String::new(),
+            id: UserId::from_id(MOCK_USER_ID).unwrap().into_static(),
+            images: None,
+            product: None,
+        })
+    }
+
+    fn playlist(&self, playlist_id: &str) -> Result<FullPlaylist, ()> {
+        self.request(format!("playlist {playlist_id}"));
+        self.playlists.get(playlist_id).cloned().ok_or(())
+    }
+
+    fn current_user_playlist(&self) -> ApiResult<Playlist> {
+        self.request("current_user_playlist".into());
+        let mut playlists: Vec<Playlist> = self.playlists.values().map(Playlist::from).collect();
+        playlists.sort_by(|a, b| a.id.cmp(&b.id));
+        api_result(playlists)
+    }
+
+    fn user_playlist_tracks(&self, playlist_id: &str) -> ApiResult<Playable> {
+        self.request(format!("user_playlist_tracks {playlist_id}"));
+        let tracks = self
+            .playlists
+            .get(playlist_id)
+            .map(|playlist| {
+                playlist
+                    .tracks
+                    .items
+                    .iter()
+                    .filter_map(|item| item.track.as_ref().map(Playable::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        api_result(tracks)
+    }
+
+    fn create_playlist(
+        &self,
+        name: &str,
+        _public: Option<bool>,
+        _description: Option<&str>,
+    ) -> Result<String, ()> {
+        self.request(format!("create_playlist {name}"));
+        Ok("created".into())
+    }
+
+    fn playlist_add_uris(&self, playlist_id: &str, uris: &[&str]) -> Result<(), ()> {
+        self.request(format!(
+            "playlist_add_uris {playlist_id} {}",
+            uris.join(",")
+        ));
+        Ok(())
+    }
+
+    fn user_playlist_follow_playlist(&self, id: &str) -> Result<(), ()> {
+        self.request(format!("user_playlist_follow_playlist {id}"));
+        Ok(())
+    }
+
+    fn current_user_saved_tracks(&self, offset: u32) -> Result<Page<SavedTrack>, ()> {
+        self.request(format!("current_user_saved_tracks {offset}"));
+        let saved: Vec<SavedTrack> = self
+            .saved_tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| SavedTrack {
+                // The most recently saved tracks come first.
+                added_at: Utc.timestamp_opt(1_700_000_000 - index as i64, 0).unwrap(),
+                track: track.clone(),
+            })
+            .collect();
+        Ok(page(&saved, offset, self.page_size))
+    }
+
+    fn current_user_saved_tracks_add(&self, ids: Vec<&str>) -> Result<(), ()> {
+        self.request(format!("current_user_saved_tracks_add {}", ids.join(",")));
+        Ok(())
+    }
+
+    fn current_user_saved_albums(&self, offset: u32) -> Result<Page<SavedAlbum>, ()> {
+        self.request(format!("current_user_saved_albums {offset}"));
+        Ok(page(&[], offset, self.page_size))
+    }
+
+    fn current_user_saved_albums_add(&self, ids: Vec<&str>) -> Result<(), ()> {
+        self.request(format!("current_user_saved_albums_add {}", ids.join(",")));
+        Ok(())
+    }
+
+    fn current_user_followed_artists(
+        &self,
+        last: Option<&str>,
+    ) -> Result<CursorBasedPage<FullArtist>, ()> {
+        self.request(format!(
+            "current_user_followed_artists {}",
+            last.unwrap_or_default()
+        ));
+        let start = match last {
+            Some(last) => {
+                self.followed_artists
+                    .iter()
+                    .position(|artist| artist.id.id() == last)
+                    .ok_or(())?
+                    + 1
+            }
+            None => 0,
+        };
+        let end = (start + self.page_size).min(self.followed_artists.len());
+        let items = self.followed_artists[start..end].to_vec();
+        let next = (end < self.followed_artists.len()).then(String::new);
+        Ok(CursorBasedPage {
+            href: String::new(),
+            cursors: next.as_ref().map(|_| Cursor {
+                after: items.last().map(|artist| artist.id.id().to_string()),
+            }),
+            items,
+            limit: self.page_size as u32,
+            next,
+            total: Some(self.followed_artists.len() as u32),
+        })
+    }
+
+    fn user_follow_artists(&self, ids: Vec<&str>) -> Result<(), ()> {
+        self.request(format!("user_follow_artists {}", ids.join(",")));
+        Ok(())
+    }
+
+    fn get_saved_shows(&self, offset: u32) -> Result<Page<Show>, ()> {
+        self.request(format!("get_saved_shows {offset}"));
+        Ok(page(&[], offset, self.page_size))
+    }
+
+    fn save_shows(&self, ids: &[&str]) -> Result<(), ()> {
+        self.request(format!("save_shows {}", ids.join(",")));
+        Ok(())
+    }
+
+    fn recommendations(
+        &self,
+        _seed_artists: Option<Vec<&str>>,
+        _seed_genres: Option<Vec<&str>>,
+        _seed_tracks: Option<Vec<&str>>,
+        _attributes: &[RecommendationsAttribute],
+    ) -> Result<Recommendations, ()> {
+        self.request("recommendations".into());
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|track| serde_json::to_value(track).and_then(serde_json::from_value))
+            .collect::<Result<Vec<SimplifiedTrack>, _>>()
+            .map_err(|_| ())?;
+        Ok(Recommendations {
+            seeds: Vec::new(),
+            tracks,
+        })
+    }
+}
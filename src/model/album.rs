@@ -11,7 +11,8 @@ use crate::library::Library;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::model::track::Track;
-use crate::queue::Queue;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{album::AlbumView, listview::ListView};
@@ -27,6 +28,14 @@ pub struct Album {
     pub url: Option<String>,
     pub tracks: Option<Vec<Track>>,
     pub added_at: Option<DateTime<Utc>>,
+    /// The release date, as precise as Spotify knows it: `2020`, `2020-03` or `2020-03-01`.
+    #[serde(default)]
+    pub release_date: Option<String>,
+    /// The record label, only known once the full album was fetched.
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub copyrights: Vec<String>,
     total_tracks: Option<usize>,
 }
 
@@ -39,6 +48,13 @@ impl Album {
         if let Some(ref album_id) = self.id {
             let mut collected_tracks = Vec::new();
             if let Ok(full_album) = spotify.api.album(album_id) {
+                self.release_date = Some(full_album.release_date.clone());
+                self.label.clone_from(&full_album.label);
+                self.copyrights = full_album
+                    .copyrights
+                    .iter()
+                    .map(|c| c.text.clone())
+                    .collect();
                 let mut tracks_result = Some(full_album.tracks.clone());
                 while let Some(ref tracks) = tracks_result {
                     for t in &tracks.items {
@@ -94,6 +110,9 @@ impl From<&SimplifiedAlbum> for Album {
             url: sa.id.as_ref().map(|id| id.url()),
             tracks: None,
             added_at: None,
+            release_date: sa.release_date.clone(),
+            label: None,
+            copyrights: Vec::new(),
             total_tracks: None,
         }
     }
@@ -123,6 +142,9 @@ impl From<&FullAlbum> for Album {
             url: Some(fa.id.uri()),
             tracks,
             added_at: None,
+            release_date: Some(fa.release_date.clone()),
+            label: fa.label.clone(),
+            copyrights: fa.copyrights.iter().map(|c| c.text.clone()).collect(),
             total_tracks: Some(fa.tracks.total as usize),
         }
     }
@@ -275,6 +297,7 @@ impl ListItem for Album {
                 artist_id.as_ref().map(|aid| vec![aid.as_str()]),
                 None,
                 Some(track_ids),
+                &[],
             )
             .ok()
             .map(|r| r.tracks)
@@ -296,6 +319,26 @@ impl ListItem for Album {
             .map(|id| format!("https://open.spotify.com/album/{id}"))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(format!("{} – {}", self.artists.join(", "), self.title))
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        // Leave room for one artist next to the track seeds.
+        let track_ids = self
+            .tracks
+            .iter()
+            .flatten()
+            .filter_map(|t| t.id.clone())
+            .take(4)
+            .collect();
+        Some(RadioSeed {
+            artist_ids: self.artist_ids.iter().take(1).cloned().collect(),
+            track_ids,
+            ..Default::default()
+        })
+    }
+
     fn artists(&self) -> Option<Vec<Artist>> {
         Some(
             self.artist_ids
@@ -312,10 +355,31 @@ impl ListItem for Album {
     }
 
     #[inline]
+    fn sort_fields(&self) -> Option<SortFields> {
+        Some(SortFields {
+            title: self.title.clone(),
+            artists: self.artists.clone(),
+            album: Some(self.title.clone()),
+            added_at: self.added_at,
+            year: self.year.parse().ok(),
+            ..Default::default()
+        })
+    }
+
     fn is_playable(&self) -> bool {
         true
     }
 
+    fn is_collection(&self) -> bool {
+        true
+    }
+
+    fn collection_tracks(&self, spotify: &Spotify) -> Vec<Track> {
+        let mut album = self.clone();
+        album.load_all_tracks(spotify.clone());
+        album.tracks.unwrap_or_default()
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         Box::new(self.clone())
     }
@@ -1,13 +1,15 @@
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
-use rspotify::model::Id;
 use rspotify::model::artist::{FullArtist, SimplifiedArtist};
+use rspotify::model::{AlbumType, Id};
 
 use crate::library::Library;
+use crate::model::album::Album;
 use crate::model::playable::Playable;
 use crate::model::track::Track;
-use crate::queue::Queue;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{artist::ArtistView, listview::ListView};
@@ -32,6 +34,25 @@ impl Artist {
         }
     }
 
+    /// The tracks of the albums, singles and compilations of the artist, oldest release first.
+    pub fn discography(&self, spotify: &Spotify) -> Vec<Track> {
+        let Some(artist_id) = &self.id else {
+            return Vec::new();
+        };
+        let mut albums: Vec<Album> = Vec::new();
+        for album_type in [AlbumType::Album, AlbumType::Single, AlbumType::Compilation] {
+            let result = spotify.api.artist_albums(artist_id, Some(album_type));
+            while !result.at_end() && result.next().is_some() {}
+            // The albums come newest first.
+            albums.extend(result.items.read().unwrap().iter().rev().cloned());
+        }
+        albums.sort_by(|a, b| a.year.cmp(&b.year));
+        albums
+            .iter()
+            .flat_map(|album| album.collection_tracks(spotify))
+            .collect()
+    }
+
     fn load_top_tracks(&mut self, spotify: Spotify) {
         if let Some(artist_id) = &self.id
             && self.tracks.is_none()
@@ -152,7 +173,7 @@ impl ListItem for Artist {
     }
 
     fn toggle_saved(&mut self, library: &Library) {
-        if library.is_followed_artist(self) {
+        if library.check_followed_artist(self) {
             library.unfollow_artist(self);
         } else {
             library.follow_artist(self);
@@ -181,7 +202,7 @@ impl ListItem for Artist {
         let spotify = queue.get_spotify();
         let recommendations: Option<Vec<Track>> = spotify
             .api
-            .recommendations(Some(vec![&id]), None, None)
+            .recommendations(Some(vec![&id]), None, None, &[])
             .ok()
             .map(|r| r.tracks)
             .map(|tracks| tracks.iter().map(Track::from).collect());
@@ -203,12 +224,31 @@ impl ListItem for Artist {
             .map(|id| format!("https://open.spotify.com/artist/{id}"))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        Some(RadioSeed {
+            artist_ids: vec![self.id.clone()?],
+            ..Default::default()
+        })
+    }
+
     #[inline]
     fn is_saved(&self, library: &Library) -> Option<bool> {
-        Some(library.is_followed_artist(self))
+        Some(library.is_following_artist(self))
     }
 
     #[inline]
+    fn sort_fields(&self) -> Option<SortFields> {
+        Some(SortFields {
+            title: self.name.clone(),
+            artists: vec![self.name.clone()],
+            ..Default::default()
+        })
+    }
+
     fn is_playable(&self) -> bool {
         true
     }
@@ -0,0 +1,60 @@
+use rspotify::model::{AudioFeatures as SpotifyAudioFeatures, Id, Modality};
+
+/// Pitch class names as used by Spotify, where 0 = C, 1 = C♯/D♭ and so on.
+const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// A subset of the audio features Spotify computes for a track.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AudioFeatures {
+    pub id: String,
+    /// Estimated tempo in beats per minute.
+    pub tempo: f32,
+    /// Pitch class of the key, -1 if no key was detected.
+    pub key: i32,
+    pub major: bool,
+    /// Perceptual intensity from 0.0 to 1.0.
+    pub energy: f32,
+    /// Suitability for dancing from 0.0 to 1.0.
+    pub danceability: f32,
+}
+
+impl AudioFeatures {
+    /// The rounded tempo, i.e. "128".
+    pub fn bpm(&self) -> String {
+        format!("{:.0}", self.tempo)
+    }
+
+    /// The key in short notation, i.e. "F#m" for F sharp minor, or an empty string if unknown.
+    pub fn key_name(&self) -> String {
+        usize::try_from(self.key)
+            .ok()
+            .and_then(|key| PITCH_CLASSES.get(key))
+            .map(|pitch| format!("{}{}", pitch, if self.major { "" } else { "m" }))
+            .unwrap_or_default()
+    }
+
+    /// The energy as a percentage, i.e. "73%".
+    pub fn energy_percent(&self) -> String {
+        format!("{:.0}%", self.energy * 100.0)
+    }
+
+    /// The danceability as a percentage, i.e. "61%".
+    pub fn danceability_percent(&self) -> String {
+        format!("{:.0}%", self.danceability * 100.0)
+    }
+}
+
+impl From<&SpotifyAudioFeatures> for AudioFeatures {
+    fn from(features: &SpotifyAudioFeatures) -> Self {
+        Self {
+            id: features.id.id().to_string(),
+            tempo: features.tempo,
+            key: features.key,
+            major: features.mode == Modality::Major,
+            energy: features.energy,
+            danceability: features.danceability,
+        }
+    }
+}
@@ -1,11 +1,13 @@
+use crate::episode_progress::{self, Progress};
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
+use crate::sorting::SortFields;
 use crate::traits::{ListItem, ViewExt};
 use crate::utils::ms_to_hms;
 use chrono::{DateTime, Utc};
 use rspotify::model::Id;
-use rspotify::model::show::{FullEpisode, SimplifiedEpisode};
+use rspotify::model::show::{FullEpisode, ResumePoint, SimplifiedEpisode};
 use std::fmt;
 use std::sync::Arc;
 
@@ -19,6 +21,12 @@ pub struct Episode {
     pub release_date: String,
     pub cover_url: Option<String>,
     pub added_at: Option<DateTime<Utc>>,
+    /// The id of the user who added the item to the playlist it was loaded from.
+    #[serde(default)]
+    pub added_by: Option<String>,
+    /// Where Spotify says the episode was left.
+    #[serde(default)]
+    pub resume_point: Option<Progress>,
     pub list_index: usize,
 }
 
@@ -28,6 +36,15 @@ impl Episode {
     }
 }
 
+impl From<&ResumePoint> for Progress {
+    fn from(resume_point: &ResumePoint) -> Self {
+        Self {
+            position: resume_point.resume_position.num_milliseconds() as u32,
+            fully_played: resume_point.fully_played,
+        }
+    }
+}
+
 impl From<&SimplifiedEpisode> for Episode {
     fn from(episode: &SimplifiedEpisode) -> Self {
         Self {
@@ -39,6 +56,8 @@ impl From<&SimplifiedEpisode> for Episode {
             release_date: episode.release_date.clone(),
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
+            added_by: None,
+            resume_point: episode.resume_point.as_ref().map(Progress::from),
             list_index: 0,
         }
     }
@@ -55,6 +74,8 @@ impl From<&FullEpisode> for Episode {
             release_date: episode.release_date.clone(),
             cover_url: episode.images.first().map(|img| img.url.clone()),
             added_at: None,
+            added_by: None,
+            resume_point: episode.resume_point.as_ref().map(Progress::from),
             list_index: 0,
         }
     }
@@ -78,8 +99,13 @@ impl ListItem for Episode {
         self.name.clone()
     }
 
-    fn display_right(&self, _library: &Library) -> String {
-        format!("{} [{}]", self.duration_str(), self.release_date)
+    fn display_right(&self, library: &Library) -> String {
+        let progress = episode_progress::of(library.cfg.storage().as_ref(), self)
+            .and_then(|progress| progress.label(self.duration));
+        match progress {
+            Some(progress) => format!("{progress} {} [{}]", self.duration_str(), self.release_date),
+            None => format!("{} [{}]", self.duration_str(), self.release_date),
+        }
     }
 
     fn play(&mut self, queue: &Queue) {
@@ -109,7 +135,21 @@ impl ListItem for Episode {
         Some(format!("https://open.spotify.com/episode/{}", self.id))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
     #[inline]
+    fn sort_fields(&self) -> Option<SortFields> {
+        Some(SortFields {
+            title: self.name.clone(),
+            added_at: self.added_at,
+            duration: self.duration,
+            year: self.release_date.split('-').next()?.parse().ok(),
+            ..Default::default()
+        })
+    }
+
     fn is_playable(&self) -> bool {
         true
     }
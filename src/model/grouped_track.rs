@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
+use crate::traits::{ListItem, ViewExt};
+use crate::utils::ms_to_hms;
+
+/// A row of a playlist whose tracks are grouped: the header of a group, or one of its tracks.
+/// Tracks behave exactly like in any other list.
+#[derive(Clone)]
+pub enum GroupedTrack {
+    Header {
+        name: String,
+        tracks: usize,
+        duration: u32,
+        collapsed: bool,
+    },
+    Track {
+        playable: Box<Playable>,
+        in_group: bool,
+    },
+}
+
+impl GroupedTrack {
+    pub fn playable(&self) -> Option<&Playable> {
+        match self {
+            Self::Header { .. } => None,
+            Self::Track { playable, .. } => Some(playable.as_ref()),
+        }
+    }
+}
+
+impl ListItem for GroupedTrack {
+    fn is_playing(&self, queue: &Queue) -> bool {
+        self.playable()
+            .is_some_and(|playable| playable.is_playing(queue))
+    }
+
+    fn display_left(&self, library: &Library) -> String {
+        match self {
+            Self::Header {
+                name, collapsed, ..
+            } => format!("{} {name}", if *collapsed { "▸" } else { "▾" }),
+            Self::Track {
+                playable,
+                in_group: true,
+            } => format!("  {}", playable.display_left(library)),
+            Self::Track { playable, .. } => playable.display_left(library),
+        }
+    }
+
+    fn display_center(&self, library: &Library) -> String {
+        match self {
+            Self::Header { .. } => String::new(),
+            Self::Track { playable, .. } => playable.display_center(library),
+        }
+    }
+
+    fn display_right(&self, library: &Library) -> String {
+        match self {
+            Self::Header {
+                tracks, duration, ..
+            } => format!("{tracks} tracks, {}", ms_to_hms(*duration)),
+            Self::Track { playable, .. } => playable.display_right(library),
+        }
+    }
+
+    fn play(&mut self, queue: &Queue) {
+        if let Self::Track { playable, .. } = self {
+            playable.play(queue);
+        }
+    }
+
+    fn play_next(&mut self, queue: &Queue) {
+        if let Self::Track { playable, .. } = self {
+            playable.play_next(queue);
+        }
+    }
+
+    fn queue(&mut self, queue: &Queue) {
+        if let Self::Track { playable, .. } = self {
+            playable.queue(queue);
+        }
+    }
+
+    fn toggle_saved(&mut self, library: &Library) {
+        if let Self::Track { playable, .. } = self {
+            playable.toggle_saved(library);
+        }
+    }
+
+    fn save(&mut self, library: &Library) {
+        if let Self::Track { playable, .. } = self {
+            playable.save(library);
+        }
+    }
+
+    fn unsave(&mut self, library: &Library) {
+        if let Self::Track { playable, .. } = self {
+            playable.unsave(library);
+        }
+    }
+
+    fn open(&self, queue: Arc<Queue>, library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
+        self.playable()?.open(queue, library)
+    }
+
+    fn share_url(&self) -> Option<String> {
+        self.playable()?.share_url()
+    }
+
+    fn share_name(&self) -> Option<String> {
+        self.playable()?.share_name()
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        self.playable()?.radio_seed()
+    }
+
+    fn album(&self, queue: &Queue) -> Option<Album> {
+        self.playable()?.album(queue)
+    }
+
+    fn artists(&self) -> Option<Vec<Artist>> {
+        self.playable()?.artists()
+    }
+
+    fn track(&self) -> Option<Track> {
+        ListItem::track(self.playable()?)
+    }
+
+    fn sort_fields(&self) -> Option<SortFields> {
+        self.playable()?.sort_fields()
+    }
+
+    fn is_saved(&self, library: &Library) -> Option<bool> {
+        self.playable()?.is_saved(library)
+    }
+
+    fn is_playable(&self) -> bool {
+        self.playable().is_some()
+    }
+
+    fn as_listitem(&self) -> Box<dyn ListItem> {
+        match self {
+            Self::Header { .. } => Box::new(self.clone()),
+            Self::Track { playable, .. } => playable.as_listitem(),
+        }
+    }
+}
@@ -1,8 +1,11 @@
 pub mod album;
 pub mod artist;
+pub mod audio_features;
 pub mod category;
 pub mod episode;
+pub mod grouped_track;
 pub mod playable;
 pub mod playlist;
+pub mod playlist_folder;
 pub mod show;
 pub mod track;
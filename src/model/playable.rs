@@ -1,14 +1,17 @@
 use chrono::{DateTime, Utc};
 use rspotify::model::PlayableItem;
 
+use crate::config::VolumeContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::audio_features::AudioFeatures;
 use crate::model::episode::Episode;
 use crate::model::track::Track;
-use crate::queue::Queue;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
 use crate::traits::{ListItem, ViewExt};
-use crate::utils::ms_to_hms;
+use crate::utils::{ms_to_hms, popularity_bar};
 use std::fmt;
 use std::sync::Arc;
 
@@ -21,6 +24,17 @@ pub enum Playable {
 
 impl Playable {
     pub fn format(playable: &Self, formatting: &str, library: &Library) -> String {
+        // Only look up audio features when they are actually shown, as they need an API call.
+        let uses_audio_features = ["%bpm", "%key", "%energy", "%danceability"]
+            .iter()
+            .any(|placeholder| formatting.contains(placeholder));
+        let audio_features = match playable {
+            Self::Track(Track { id: Some(id), .. }) if uses_audio_features => {
+                library.audio_features(id)
+            }
+            _ => None,
+        };
+
         formatting
             .replace(
                 "%artists",
@@ -78,6 +92,66 @@ impl Playable {
                 },
             )
             .replace("%duration", playable.duration_str().as_str())
+            .replace(
+                "%added_by",
+                &playable
+                    .added_by()
+                    .map(|user_id| library.user_name(user_id))
+                    .unwrap_or_default(),
+            )
+            // After `%added_by`, which starts the same.
+            .replace(
+                "%added",
+                &playable
+                    .added_at()
+                    .map(|added_at| added_at.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+            )
+            // Before `%popularity`, which starts the same.
+            .replace(
+                "%popularity_bar",
+                &match playable {
+                    Self::Track(track) => track.popularity.map(popularity_bar),
+                    Self::Episode(_) => None,
+                }
+                .unwrap_or_default(),
+            )
+            .replace(
+                "%popularity",
+                &match playable {
+                    Self::Track(track) => track.popularity.map(|p| p.to_string()),
+                    Self::Episode(_) => None,
+                }
+                .unwrap_or_default(),
+            )
+            .replace(
+                "%bpm",
+                &audio_features
+                    .as_ref()
+                    .map(AudioFeatures::bpm)
+                    .unwrap_or_default(),
+            )
+            .replace(
+                "%key",
+                &audio_features
+                    .as_ref()
+                    .map(AudioFeatures::key_name)
+                    .unwrap_or_default(),
+            )
+            .replace(
+                "%energy",
+                &audio_features
+                    .as_ref()
+                    .map(AudioFeatures::energy_percent)
+                    .unwrap_or_default(),
+            )
+            .replace(
+                "%danceability",
+                &audio_features
+                    .as_ref()
+                    .map(AudioFeatures::danceability_percent)
+                    .unwrap_or_default(),
+            )
     }
 
     pub fn id(&self) -> Option<String> {
@@ -94,6 +168,14 @@ impl Playable {
         }
     }
 
+    /// The kind of content, which remembers its own volume.
+    pub fn volume_context(&self) -> VolumeContext {
+        match self {
+            Self::Track(_) => VolumeContext::Music,
+            Self::Episode(_) => VolumeContext::Podcast,
+        }
+    }
+
     pub fn cover_url(&self) -> Option<String> {
         match self {
             Self::Track(track) => track.cover_url.clone(),
@@ -122,6 +204,13 @@ impl Playable {
         }
     }
 
+    pub fn added_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Track(track) => track.added_at,
+            Self::Episode(episode) => episode.added_at,
+        }
+    }
+
     pub fn set_added_at(&mut self, added_at: Option<DateTime<Utc>>) {
         match self {
             Self::Track(track) => track.added_at = added_at,
@@ -129,6 +218,20 @@ impl Playable {
         }
     }
 
+    pub fn added_by(&self) -> Option<&str> {
+        match self {
+            Self::Track(track) => track.added_by.as_deref(),
+            Self::Episode(episode) => episode.added_by.as_deref(),
+        }
+    }
+
+    pub fn set_added_by(&mut self, added_by: Option<String>) {
+        match self {
+            Self::Track(track) => track.added_by = added_by,
+            Self::Episode(episode) => episode.added_by = added_by,
+        }
+    }
+
     pub fn duration_str(&self) -> String {
         ms_to_hms(self.duration())
     }
@@ -228,6 +331,14 @@ impl ListItem for Playable {
         self.as_listitem().share_url()
     }
 
+    fn share_name(&self) -> Option<String> {
+        self.as_listitem().share_name()
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        self.as_listitem().radio_seed()
+    }
+
     fn album(&self, queue: &Queue) -> Option<Album> {
         self.as_listitem().album(queue)
     }
@@ -240,6 +351,10 @@ impl ListItem for Playable {
         self.as_listitem().track()
     }
 
+    fn sort_fields(&self) -> Option<SortFields> {
+        self.as_listitem().sort_fields()
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         self.as_listitem()
     }
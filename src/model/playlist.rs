@@ -1,6 +1,6 @@
 use std::collections::HashSet;
+use std::iter::Iterator;
 use std::sync::{Arc, RwLock};
-use std::{cmp::Ordering, iter::Iterator};
 
 use rand::{rng, seq::IteratorRandom};
 
@@ -11,9 +11,12 @@ use rspotify::model::playlist::{FullPlaylist, SimplifiedPlaylist};
 use crate::model::playable::Playable;
 use crate::model::track::Track;
 use crate::queue::Queue;
+use crate::sorting::{self, SortFields};
 use crate::spotify::Spotify;
+use crate::spotify_api::WebApi;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::{listview::ListView, playlist::PlaylistView};
+use crate::undo::{self, Change};
 use crate::{command::SortDirection, command::SortKey, library::Library};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -38,13 +41,9 @@ impl Playlist {
     }
 
     fn get_all_tracks(&self, spotify: &Spotify) -> Vec<Playable> {
-        let tracks_result = spotify.api.user_playlist_tracks(&self.id);
-        while !tracks_result.at_end() {
-            tracks_result.next();
-        }
-
-        let tracks = tracks_result.items.read().unwrap();
-        tracks.clone()
+        spotify
+            .api
+            .playlist_snapshot_tracks(&self.id, &self.snapshot_id)
     }
 
     pub fn has_track(&self, track_id: &str) -> bool {
@@ -64,32 +63,140 @@ impl Playlist {
             return false;
         }
 
-        match spotify
-            .api
-            .delete_tracks(&self.id, &self.snapshot_id, &[playable])
-            .is_ok()
-        {
-            false => false,
-            true => {
+        match spotify.api.delete_tracks(
+            &self.id,
+            &self.snapshot_id,
+            std::slice::from_ref(&playable),
+        ) {
+            Err(_) => false,
+            Ok(result) => {
+                // The local copy matches the new snapshot, so it won't be downloaded again.
+                self.snapshot_id = result.snapshot_id;
                 if let Some(tracks) = &mut self.tracks {
                     tracks.remove(index);
                     library.playlist_update(self);
                 }
+                undo::record(Change::DeleteTracks {
+                    playlist_id: self.id.clone(),
+                    playlist_name: self.name.clone(),
+                    tracks: vec![(playable.list_index(), playable)],
+                });
 
                 true
             }
         }
     }
 
+    /// Delete `playables`, identified by their position in the playlist. Returns whether all of
+    /// them were deleted.
+    pub fn delete_tracks(
+        &mut self,
+        playables: &[Playable],
+        spotify: &Spotify,
+        library: &Library,
+    ) -> bool {
+        let mut playables = playables.to_vec();
+        // Delete from the end, so the positions of the remaining tracks stay valid.
+        playables.sort_by_key(|playable| std::cmp::Reverse(playable.list_index()));
+
+        let mut deleted = HashSet::new();
+        for chunk in playables.chunks(100) {
+            match spotify
+                .api
+                .delete_tracks(&self.id, &self.snapshot_id, chunk)
+            {
+                Ok(result) => {
+                    self.snapshot_id = result.snapshot_id;
+                    deleted.extend(chunk.iter().map(Playable::list_index));
+                }
+                Err(_) => break,
+            }
+        }
+
+        if let Some(tracks) = &mut self.tracks {
+            tracks.retain(|playable| !deleted.contains(&playable.list_index()));
+            for playable in tracks.iter_mut() {
+                let index = playable.list_index();
+                let shift = deleted.iter().filter(|&&d| d < index).count();
+                playable.set_list_index(index - shift);
+            }
+            self.num_tracks = tracks.len();
+            library.playlist_update(self);
+        }
+
+        if !deleted.is_empty() {
+            undo::record(Change::DeleteTracks {
+                playlist_id: self.id.clone(),
+                playlist_name: self.name.clone(),
+                tracks: playables
+                    .iter()
+                    .filter(|playable| deleted.contains(&playable.list_index()))
+                    .map(|playable| (playable.list_index(), playable.clone()))
+                    .collect(),
+            });
+        }
+
+        deleted.len() == playables.len()
+    }
+
+    /// Replace `playable` with `replacement` at the same position. The replacement is inserted
+    /// first, so `playable` is only deleted once it is in the playlist. Returns whether both
+    /// succeeded.
+    pub fn replace_track(
+        &mut self,
+        playable: &Playable,
+        replacement: Playable,
+        api: &WebApi,
+        library: &Library,
+    ) -> bool {
+        let position = playable.list_index();
+        let Ok(result) = api.append_tracks(
+            &self.id,
+            std::slice::from_ref(&replacement),
+            Some(position as u32),
+        ) else {
+            return false;
+        };
+        self.snapshot_id = result.snapshot_id;
+
+        // The replacement pushed the original one position down.
+        let mut original = playable.clone();
+        original.set_list_index(position + 1);
+        let deleted =
+            api.delete_tracks(&self.id, &self.snapshot_id, std::slice::from_ref(&original));
+        if let Ok(result) = &deleted {
+            self.snapshot_id = result.snapshot_id.clone();
+        }
+
+        if let Some(tracks) = &mut self.tracks {
+            let mut replacement = replacement;
+            replacement.set_list_index(position);
+            let index = tracks.iter().position(|t| t.list_index() == position);
+            if deleted.is_ok() {
+                if let Some(index) = index {
+                    tracks[index] = replacement;
+                }
+            } else {
+                // Both are in the playlist now, the original after its replacement.
+                for playable in tracks.iter_mut().filter(|t| t.list_index() >= position) {
+                    playable.set_list_index(playable.list_index() + 1);
+                }
+                tracks.insert(index.unwrap_or(tracks.len()), replacement);
+            }
+            self.num_tracks = tracks.len();
+        }
+        library.playlist_update(self);
+
+        deleted.is_ok()
+    }
+
     pub fn append_tracks(&mut self, new_tracks: &[Playable], spotify: &Spotify, library: &Library) {
         let mut has_modified = false;
 
-        if spotify
-            .api
-            .append_tracks(&self.id, new_tracks, None)
-            .is_ok()
+        if let Ok(result) = spotify.api.append_tracks(&self.id, new_tracks, None)
             && let Some(tracks) = &mut self.tracks
         {
+            self.snapshot_id = result.snapshot_id;
             tracks.append(&mut new_tracks.to_vec());
             has_modified = true;
         }
@@ -100,51 +207,8 @@ impl Playlist {
     }
 
     pub fn sort(&mut self, key: &SortKey, direction: &SortDirection) {
-        fn compare_artists(a: &[String], b: &[String]) -> Ordering {
-            let sanitize_artists_name = |x: &[String]| -> Vec<String> {
-                x.iter()
-                    .map(|x| {
-                        x.to_lowercase()
-                            .split(' ')
-                            .skip_while(|x| x == &"the")
-                            .collect()
-                    })
-                    .collect()
-            };
-
-            let a = sanitize_artists_name(a);
-            let b = sanitize_artists_name(b);
-
-            a.cmp(&b)
-        }
-
-        fn compare_album(a: &Track, b: &Track) -> Ordering {
-            a.album
-                .as_ref()
-                .map(|x| x.to_lowercase())
-                .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
-                .then_with(|| a.disc_number.cmp(&b.disc_number))
-                .then_with(|| a.track_number.cmp(&b.track_number))
-        }
-
-        if let Some(c) = self.tracks.as_mut() {
-            c.sort_by(|a, b| match (a.track(), b.track()) {
-                (Some(a), Some(b)) => {
-                    let (a, b) = match *direction {
-                        SortDirection::Ascending => (a, b),
-                        SortDirection::Descending => (b, a),
-                    };
-                    match *key {
-                        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
-                        SortKey::Duration => a.duration.cmp(&b.duration),
-                        SortKey::Album => compare_album(&a, &b),
-                        SortKey::Added => a.added_at.cmp(&b.added_at),
-                        SortKey::Artist => compare_artists(&a.artists, &b.artists)
-                            .then_with(|| compare_album(&a, &b)),
-                    }
-                }
-                _ => std::cmp::Ordering::Equal,
-            })
+        if let Some(tracks) = self.tracks.as_mut() {
+            sorting::sort(tracks, key, direction);
         }
     }
 }
@@ -259,7 +323,7 @@ impl ListItem for Playlist {
             return;
         }
 
-        if library.is_saved_playlist(self) {
+        if library.check_followed_playlist(self) {
             library.delete_playlist(&self.id);
         } else {
             library.follow_playlist(self.clone());
@@ -307,6 +371,7 @@ impl ListItem for Playlist {
                 None,
                 None,
                 Some(track_ids.iter().map(|t| t.as_ref()).collect()),
+                &[],
             )
             .ok()
             .map(|r| r.tracks)
@@ -330,20 +395,57 @@ impl ListItem for Playlist {
         ))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(match &self.owner_name {
+            Some(owner) => format!("{owner} – {}", self.name),
+            None => self.name.clone(),
+        })
+    }
+
     fn is_saved(&self, library: &Library) -> Option<bool> {
         // save status of personal playlists can't be toggled for safety
         if !library.is_followed_playlist(self) {
             return None;
         }
 
-        Some(library.is_saved_playlist(self))
+        Some(library.is_following_playlist(self))
     }
 
     #[inline]
+    fn sort_fields(&self) -> Option<SortFields> {
+        Some(SortFields {
+            title: self.name.clone(),
+            artists: self.owner_name.iter().cloned().collect(),
+            ..Default::default()
+        })
+    }
+
     fn is_playable(&self) -> bool {
         true
     }
 
+    fn playlist(&self) -> Option<Playlist> {
+        Some(self.clone())
+    }
+
+    fn is_collection(&self) -> bool {
+        true
+    }
+
+    fn collection_tracks(&self, spotify: &Spotify) -> Vec<Track> {
+        let mut playlist = self.clone();
+        playlist.load_tracks(spotify);
+        playlist
+            .tracks
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|playable| match playable {
+                Playable::Track(track) => Some(track),
+                Playable::Episode(_) => None,
+            })
+            .collect()
+    }
+
     fn as_listitem(&self) -> Box<dyn ListItem> {
         Box::new(self.clone())
     }
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use crate::library::Library;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
+use crate::spotify::Spotify;
+use crate::traits::{ListItem, ViewExt};
+
+/// A row of the playlists tab: a local folder, or a playlist that may be inside one. Playlists
+/// behave exactly like in any other list.
+#[derive(Clone)]
+pub enum PlaylistEntry {
+    Folder {
+        name: String,
+        playlists: usize,
+        collapsed: bool,
+    },
+    Playlist {
+        playlist: Box<Playlist>,
+        in_folder: bool,
+    },
+}
+
+impl ListItem for PlaylistEntry {
+    fn is_playing(&self, queue: &Queue) -> bool {
+        match self {
+            Self::Folder { .. } => false,
+            Self::Playlist { playlist, .. } => playlist.is_playing(queue),
+        }
+    }
+
+    fn display_left(&self, library: &Library) -> String {
+        match self {
+            Self::Folder {
+                name, collapsed, ..
+            } => format!("{} {name}", if *collapsed { "▸" } else { "▾" }),
+            Self::Playlist {
+                playlist,
+                in_folder: true,
+            } => format!("  {}", playlist.display_left(library)),
+            Self::Playlist { playlist, .. } => playlist.display_left(library),
+        }
+    }
+
+    fn display_right(&self, library: &Library) -> String {
+        match self {
+            Self::Folder { playlists, .. } => format!("{playlists} playlists"),
+            Self::Playlist { playlist, .. } => playlist.display_right(library),
+        }
+    }
+
+    fn play(&mut self, queue: &Queue) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.play(queue);
+        }
+    }
+
+    fn play_next(&mut self, queue: &Queue) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.play_next(queue);
+        }
+    }
+
+    fn queue(&mut self, queue: &Queue) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.queue(queue);
+        }
+    }
+
+    fn toggle_saved(&mut self, library: &Library) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.toggle_saved(library);
+        }
+    }
+
+    fn save(&mut self, library: &Library) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.save(library);
+        }
+    }
+
+    fn unsave(&mut self, library: &Library) {
+        if let Self::Playlist { playlist, .. } = self {
+            playlist.unsave(library);
+        }
+    }
+
+    fn open(&self, queue: Arc<Queue>, library: Arc<Library>) -> Option<Box<dyn ViewExt>> {
+        match self {
+            Self::Folder { .. } => None,
+            Self::Playlist { playlist, .. } => playlist.open(queue, library),
+        }
+    }
+
+    fn open_recommendations(
+        &mut self,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+    ) -> Option<Box<dyn ViewExt>> {
+        match self {
+            Self::Folder { .. } => None,
+            Self::Playlist { playlist, .. } => playlist.open_recommendations(queue, library),
+        }
+    }
+
+    fn share_url(&self) -> Option<String> {
+        self.playlist()?.share_url()
+    }
+
+    fn share_name(&self) -> Option<String> {
+        self.playlist()?.share_name()
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        self.playlist()?.radio_seed()
+    }
+
+    fn playlist(&self) -> Option<Playlist> {
+        match self {
+            Self::Folder { .. } => None,
+            Self::Playlist { playlist, .. } => Some(*playlist.clone()),
+        }
+    }
+
+    fn is_collection(&self) -> bool {
+        matches!(self, Self::Playlist { .. })
+    }
+
+    fn collection_tracks(&self, spotify: &Spotify) -> Vec<Track> {
+        match self {
+            Self::Folder { .. } => Vec::new(),
+            Self::Playlist { playlist, .. } => playlist.collection_tracks(spotify),
+        }
+    }
+
+    fn sort_fields(&self) -> Option<SortFields> {
+        self.playlist()?.sort_fields()
+    }
+
+    fn is_saved(&self, library: &Library) -> Option<bool> {
+        self.playlist()?.is_saved(library)
+    }
+
+    fn is_playable(&self) -> bool {
+        matches!(self, Self::Playlist { .. })
+    }
+
+    fn as_listitem(&self) -> Box<dyn ListItem> {
+        match self {
+            Self::Folder { .. } => Box::new(self.clone()),
+            Self::Playlist { playlist, .. } => playlist.as_listitem(),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::library::Library;
 use crate::model::episode::Episode;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
+use crate::sorting::SortFields;
 use crate::spotify::Spotify;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::show::ShowView;
@@ -150,12 +151,24 @@ impl ListItem for Show {
         Some(format!("https://open.spotify.com/show/{}", self.id))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(format!("{} – {}", self.publisher, self.name))
+    }
+
     #[inline]
     fn is_saved(&self, library: &Library) -> Option<bool> {
         Some(library.is_saved_show(self))
     }
 
     #[inline]
+    fn sort_fields(&self) -> Option<SortFields> {
+        Some(SortFields {
+            title: self.name.clone(),
+            artists: vec![self.publisher.clone()],
+            ..Default::default()
+        })
+    }
+
     fn is_playable(&self) -> bool {
         true
     }
@@ -9,10 +9,11 @@ use rspotify::model::album::FullAlbum;
 use rspotify::model::track::{FullTrack, SavedTrack, SimplifiedTrack};
 
 use crate::library::Library;
+use crate::local_files;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
-use crate::queue::Queue;
+use crate::queue::{Queue, RadioSeed};
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::listview::ListView;
 
@@ -32,6 +33,15 @@ pub struct Track {
     pub cover_url: Option<String>,
     pub url: String,
     pub added_at: Option<DateTime<Utc>>,
+    /// The id of the user who added the item to the playlist it was loaded from.
+    #[serde(default)]
+    pub added_by: Option<String>,
+    /// The popularity of the track on Spotify, from 0 to 100.
+    #[serde(default)]
+    pub popularity: Option<u32>,
+    /// The year the album of the track was released.
+    #[serde(default)]
+    pub release_year: Option<u32>,
     pub list_index: usize,
     pub is_local: bool,
     pub is_playable: Option<bool>,
@@ -70,6 +80,13 @@ impl Track {
             cover_url: album.images.first().map(|img| img.url.clone()),
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            popularity: None,
+            release_year: album
+                .release_date
+                .split('-')
+                .next()
+                .and_then(|y| y.parse().ok()),
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
@@ -79,6 +96,33 @@ impl Track {
     pub fn duration_str(&self) -> String {
         ms_to_hms(self.duration)
     }
+
+    /// A track for tests, with its id and URI made from the title.
+    #[cfg(test)]
+    pub fn test(title: &str, artist: &str, album: &str) -> Self {
+        Self {
+            id: Some(title.into()),
+            uri: format!("spotify:track:{title}"),
+            title: title.into(),
+            track_number: 1,
+            disc_number: 1,
+            duration: 0,
+            artists: vec![artist.into()],
+            artist_ids: Vec::new(),
+            album: Some(album.into()),
+            album_id: None,
+            album_artists: Vec::new(),
+            cover_url: None,
+            url: String::new(),
+            added_at: None,
+            added_by: None,
+            popularity: None,
+            release_year: None,
+            list_index: 0,
+            is_local: false,
+            is_playable: None,
+        }
+    }
 }
 
 impl From<&SimplifiedTrack> for Track {
@@ -109,6 +153,9 @@ impl From<&SimplifiedTrack> for Track {
             cover_url: None,
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            popularity: None,
+            release_year: None,
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
@@ -150,6 +197,13 @@ impl From<&FullTrack> for Track {
             cover_url: track.album.images.first().map(|img| img.url.clone()),
             url: track.id.as_ref().map(|id| id.url()).unwrap_or_default(),
             added_at: None,
+            added_by: None,
+            popularity: Some(track.popularity),
+            release_year: track
+                .album
+                .release_date
+                .as_deref()
+                .and_then(|date| date.split('-').next()?.parse().ok()),
             list_index: 0,
             is_local: track.is_local,
             is_playable: track.is_playable,
@@ -230,7 +284,13 @@ impl ListItem for Track {
             } else {
                 ""
             };
-            format!("{} {}", saved, self.duration_str())
+            // Greyed out alone is easy to miss, so unavailable tracks get a mark as well.
+            let unavailable = if !self.is_local && self.is_playable == Some(false) {
+                "⊘ "
+            } else {
+                ""
+            };
+            format!("{}{} {}", unavailable, saved, self.duration_str())
         }
     }
 
@@ -277,7 +337,7 @@ impl ListItem for Track {
         let recommendations: Option<Vec<Self>> = if let Some(id) = &self.id {
             spotify
                 .api
-                .recommendations(None, None, Some(vec![id]))
+                .recommendations(None, None, Some(vec![id]), &[])
                 .ok()
                 .map(|r| r.tracks)
                 .map(|tracks| tracks.iter().map(Self::from).collect())
@@ -306,6 +366,18 @@ impl ListItem for Track {
             .map(|id| format!("https://open.spotify.com/track/{id}"))
     }
 
+    fn share_name(&self) -> Option<String> {
+        Some(format!("{} – {}", self.artists.join(", "), self.title))
+    }
+
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        Some(RadioSeed {
+            artist_ids: self.artist_ids.clone(),
+            track_ids: vec![self.id.clone()?],
+            ..Default::default()
+        })
+    }
+
     fn album(&self, queue: &Queue) -> Option<Album> {
         let spotify = queue.get_spotify();
 
@@ -336,7 +408,7 @@ impl ListItem for Track {
 
     #[inline]
     fn is_playable(&self) -> bool {
-        self.is_playable == Some(true)
+        self.is_playable == Some(true) || (self.is_local && local_files::is_available(&self.uri))
     }
 
     fn as_listitem(&self) -> Box<dyn ListItem> {
@@ -3,16 +3,18 @@
 use log::info;
 use std::collections::HashMap;
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use zbus::object_server::SignalEmitter;
-use zbus::zvariant::{ObjectPath, Value};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
 use zbus::{connection, interface};
 
 use crate::application::ASYNC_RUNTIME;
+use crate::instance;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::episode::Episode;
@@ -30,6 +32,9 @@ use crate::{
     spotify::{PlayerEvent, Spotify, VOLUME_PERCENT},
 };
 
+/// The track id that stands for no track.
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
 struct MprisRoot {}
 
 #[interface(name = "org.mpris.MediaPlayer2")]
@@ -51,6 +56,11 @@ impl MprisRoot {
 
     #[zbus(property)]
     fn identity(&self) -> &str {
+        instance::name()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> &str {
         "ncspot"
     }
 
@@ -124,8 +134,6 @@ impl MprisPlayer {
 
     #[zbus(property)]
     fn metadata(&self) -> HashMap<String, Value<'_>> {
-        let mut hm = HashMap::new();
-
         let playable = self.queue.get_current();
 
         // Fetch full track details in case this playable is based on a SimplifiedTrack
@@ -146,115 +154,12 @@ impl MprisPlayer {
             }
             Playable::Episode(episode) => Some(Playable::Episode(episode)),
         });
-        let playable = playable_full.as_ref();
-
-        hm.insert(
-            "mpris:trackid".to_string(),
-            Value::ObjectPath(ObjectPath::from_string_unchecked(format!(
-                "/org/ncspot/{}",
-                playable
-                    .filter(|t| t.id().is_some())
-                    .map(|t| t.uri().replace(':', "/"))
-                    .unwrap_or_else(|| String::from("0"))
-            ))),
-        );
-        hm.insert(
-            "mpris:length".to_string(),
-            Value::I64(playable.map(|t| t.duration() as i64 * 1_000).unwrap_or(0)),
-        );
-        hm.insert(
-            "mpris:artUrl".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| t.cover_url().unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-
-        hm.insert(
-            "xesam:album".to_string(),
-            Value::Str(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.album.unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:albumArtist".to_string(),
-            Value::Array(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.album_artists)
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:artist".to_string(),
-            Value::Array(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.artists)
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:discNumber".to_string(),
-            Value::I32(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.disc_number)
-                    .unwrap_or(0),
-            ),
-        );
-        hm.insert(
-            "xesam:title".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| match t {
-                        Playable::Track(t) => t.title.clone(),
-                        Playable::Episode(ep) => ep.name.clone(),
-                    })
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:trackNumber".to_string(),
-            Value::I32(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| t.track_number)
-                    .unwrap_or(0) as i32,
-            ),
-        );
-        hm.insert(
-            "xesam:url".to_string(),
-            Value::Str(
-                playable
-                    .map(|t| t.share_url().unwrap_or_default())
-                    .unwrap_or_default()
-                    .into(),
-            ),
-        );
-        hm.insert(
-            "xesam:userRating".to_string(),
-            Value::F64(
-                playable
-                    .and_then(|p| p.track())
-                    .map(|t| match self.library.is_saved_track(&Playable::Track(t)) {
-                        true => 1.0,
-                        false => 0.0,
-                    })
-                    .unwrap_or(0.0),
-            ),
-        );
 
-        hm
+        metadata(
+            playable_full.as_ref(),
+            current_track_id(&self.queue),
+            &self.library,
+        )
     }
 
     #[zbus(property)]
@@ -321,7 +226,7 @@ impl MprisPlayer {
     async fn seeked(context: &SignalEmitter<'_>, position: &i64) -> zbus::Result<()>;
 
     fn next(&self) {
-        self.queue.next(true)
+        self.queue.next(true);
     }
 
     fn previous(&self) {
@@ -377,95 +282,408 @@ impl MprisPlayer {
     }
 
     fn open_uri(&self, uri: &str) {
-        let spotify_url = if uri.contains("open.spotify.com") {
-            SpotifyUrl::from_url(uri)
-        } else if let Ok(uri_type) = uri.parse() {
-            let id = &uri[uri.rfind(':').unwrap_or(0) + 1..uri.len()];
-            Some(SpotifyUrl::new(id, uri_type))
+        play_tracks(&self.queue, &resolve_uri(&self.spotify, uri));
+    }
+}
+
+/// The items of the queue, as the optional TrackList interface of MPRIS.
+struct MprisTrackList {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    spotify: Spotify,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.TrackList")]
+impl MprisTrackList {
+    #[zbus(property(emits_changed_signal = "invalidates"))]
+    fn tracks(&self) -> Vec<OwnedObjectPath> {
+        track_ids(&self.queue.queue.read().unwrap())
+    }
+
+    #[zbus(property)]
+    fn can_edit_tracks(&self) -> bool {
+        true
+    }
+
+    fn get_tracks_metadata(
+        &self,
+        track_ids_requested: Vec<OwnedObjectPath>,
+    ) -> Vec<HashMap<String, Value<'static>>> {
+        let queue = self.queue.queue.read().unwrap();
+        let ids = track_ids(&queue);
+        track_ids_requested
+            .iter()
+            .filter_map(|requested| {
+                let index = ids.iter().position(|id| id == requested)?;
+                Some(metadata(
+                    Some(&queue[index]),
+                    ids[index].clone(),
+                    &self.library,
+                ))
+            })
+            .collect()
+    }
+
+    fn add_track(&self, uri: &str, after_track: ObjectPath<'_>, set_as_current: bool) {
+        let index = if after_track.as_str() == NO_TRACK {
+            0
         } else {
-            None
+            match queue_index(&self.queue, &after_track) {
+                Some(index) => index + 1,
+                None => return,
+            }
         };
+        let tracks = resolve_uri(&self.spotify, uri);
+        if tracks.is_empty() {
+            return;
+        }
+        self.queue.insert(index, &tracks);
+        if set_as_current {
+            self.queue.play(index, false, false);
+        }
+    }
 
-        let id = spotify_url
-            .as_ref()
-            .map(|s| s.id.clone())
-            .unwrap_or("".to_string());
-        let uri_type = spotify_url.map(|s| s.uri_type);
-        match uri_type {
-            Some(UriType::Album) => {
-                if let Ok(a) = self.spotify.api.album(&id)
-                    && let Some(t) = &Album::from(&a).tracks
-                {
-                    let should_shuffle = self.queue.get_shuffle();
-                    self.queue.clear();
-                    let index = self.queue.append_next(
-                        &t.iter()
-                            .map(|track| Playable::Track(track.clone()))
-                            .collect(),
-                    );
-                    self.queue.play(index, should_shuffle, should_shuffle)
-                }
-            }
-            Some(UriType::Track) => {
-                if let Ok(t) = self.spotify.api.track(&id) {
-                    self.queue.clear();
-                    self.queue.append(Playable::Track(Track::from(&t)));
-                    self.queue.play(0, false, false)
-                }
-            }
-            Some(UriType::Playlist) => {
-                if let Ok(p) = self.spotify.api.playlist(&id) {
-                    let mut playlist = Playlist::from(&p);
-                    playlist.load_tracks(&self.spotify);
-                    if let Some(tracks) = &playlist.tracks {
-                        let should_shuffle = self.queue.get_shuffle();
-                        self.queue.clear();
-                        let index = self.queue.append_next(tracks);
-                        self.queue.play(index, should_shuffle, should_shuffle)
-                    }
-                }
-            }
-            Some(UriType::Show) => {
-                if let Ok(s) = self.spotify.api.show(&id) {
-                    let mut show: Show = (&s).into();
-                    let spotify = self.spotify.clone();
-                    show.load_all_episodes(spotify);
-                    if let Some(e) = &show.episodes {
-                        let should_shuffle = self.queue.get_shuffle();
-                        self.queue.clear();
-                        let mut ep = e.clone();
-                        ep.reverse();
-                        let index = self.queue.append_next(
-                            &ep.iter()
-                                .map(|episode| Playable::Episode(episode.clone()))
-                                .collect(),
-                        );
-                        self.queue.play(index, should_shuffle, should_shuffle)
-                    }
-                }
-            }
-            Some(UriType::Episode) => {
-                if let Ok(e) = self.spotify.api.episode(&id) {
-                    self.queue.clear();
-                    self.queue.append(Playable::Episode(Episode::from(&e)));
-                    self.queue.play(0, false, false)
-                }
-            }
-            Some(UriType::Artist) => {
-                if let Ok(a) = self.spotify.api.artist_top_tracks(&id) {
-                    let should_shuffle = self.queue.get_shuffle();
-                    self.queue.clear();
-                    let index = self.queue.append_next(
-                        &a.iter()
-                            .map(|track| Playable::Track(track.clone()))
-                            .collect(),
-                    );
-                    self.queue.play(index, should_shuffle, should_shuffle)
-                }
-            }
-            None => {}
+    fn remove_track(&self, track_id: ObjectPath<'_>) {
+        if let Some(index) = queue_index(&self.queue, &track_id) {
+            self.queue.remove(index);
         }
     }
+
+    fn go_to(&self, track_id: ObjectPath<'_>) {
+        if let Some(index) = queue_index(&self.queue, &track_id) {
+            self.queue.play(index, false, false);
+        }
+    }
+
+    #[zbus(signal)]
+    async fn track_list_replaced(
+        context: &SignalEmitter<'_>,
+        tracks: Vec<OwnedObjectPath>,
+        current_track: OwnedObjectPath,
+    ) -> zbus::Result<()>;
+}
+
+/// An entry of the Playlists interface: its id, name and icon.
+type MprisPlaylist = (OwnedObjectPath, String, String);
+
+/// The playlists of the library, as the optional Playlists interface of MPRIS.
+struct MprisPlaylists {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    spotify: Spotify,
+    /// The playlist that was activated last.
+    active: Mutex<Option<MprisPlaylist>>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Playlists")]
+impl MprisPlaylists {
+    #[zbus(property)]
+    fn playlist_count(&self) -> u32 {
+        self.library.playlists.read().unwrap().len() as u32
+    }
+
+    #[zbus(property)]
+    fn orderings(&self) -> Vec<String> {
+        vec!["Alphabetical".to_string(), "UserDefined".to_string()]
+    }
+
+    #[zbus(property)]
+    fn active_playlist(&self) -> (bool, MprisPlaylist) {
+        match self.active.lock().unwrap().clone() {
+            Some(playlist) => (true, playlist),
+            None => (
+                false,
+                (
+                    ObjectPath::from_static_str_unchecked("/").into(),
+                    String::new(),
+                    String::new(),
+                ),
+            ),
+        }
+    }
+
+    async fn activate_playlist(
+        &self,
+        playlist_id: ObjectPath<'_>,
+        #[zbus(signal_emitter)] context: SignalEmitter<'_>,
+    ) {
+        let playlist = self
+            .library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .find(|playlist| playlist_entry(playlist).0.as_str() == playlist_id.as_str())
+            .cloned();
+        let Some(mut playlist) = playlist else {
+            return;
+        };
+        playlist.load_tracks(&self.spotify);
+        if let Some(tracks) = &playlist.tracks {
+            play_tracks(&self.queue, tracks);
+        }
+        *self.active.lock().unwrap() = Some(playlist_entry(&playlist));
+        self.active_playlist_changed(&context).await.ok();
+    }
+
+    fn get_playlists(
+        &self,
+        index: u32,
+        max_count: u32,
+        order: &str,
+        reverse_order: bool,
+    ) -> Vec<MprisPlaylist> {
+        let mut playlists = self.library.playlists.read().unwrap().clone();
+        if order == "Alphabetical" {
+            playlists.sort_by_key(|playlist| playlist.name.to_lowercase());
+        }
+        if reverse_order {
+            playlists.reverse();
+        }
+        playlists
+            .iter()
+            .skip(index as usize)
+            .take(max_count as usize)
+            .map(playlist_entry)
+            .collect()
+    }
+}
+
+fn playlist_entry(playlist: &Playlist) -> MprisPlaylist {
+    (
+        ObjectPath::from_string_unchecked(format!("/org/ncspot/spotify/playlist/{}", playlist.id))
+            .into(),
+        playlist.name.clone(),
+        String::new(),
+    )
+}
+
+/// The track ids of the items in `queue`. The same item can be queued several times, so repeated
+/// ones are numbered.
+fn track_ids(queue: &[Playable]) -> Vec<OwnedObjectPath> {
+    let paths = queue.iter().map(|playable| match playable.id() {
+        Some(_) => format!("/org/ncspot/{}", playable.uri().replace(':', "/")),
+        None => "/org/ncspot/local".to_string(),
+    });
+    unique_paths(paths)
+        .into_iter()
+        .map(|path| ObjectPath::from_string_unchecked(path).into())
+        .collect()
+}
+
+fn unique_paths(paths: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = HashMap::<String, usize>::new();
+    paths
+        .into_iter()
+        .map(|path| {
+            let count = seen.entry(path.clone()).or_insert(0);
+            *count += 1;
+            match *count {
+                1 => path,
+                count => format!("{path}/{count}"),
+            }
+        })
+        .collect()
+}
+
+/// The index in `queue` of the item with `track_id`.
+fn queue_index(queue: &Queue, track_id: &ObjectPath<'_>) -> Option<usize> {
+    track_ids(&queue.queue.read().unwrap())
+        .iter()
+        .position(|id| id.as_str() == track_id.as_str())
+}
+
+/// The track id of the playing item, or the special id for no track.
+fn current_track_id(queue: &Queue) -> OwnedObjectPath {
+    queue
+        .get_current_index()
+        .and_then(|index| track_ids(&queue.queue.read().unwrap()).get(index).cloned())
+        .unwrap_or_else(|| ObjectPath::from_static_str_unchecked(NO_TRACK).into())
+}
+
+/// The MPRIS metadata of `playable`.
+fn metadata(
+    playable: Option<&Playable>,
+    track_id: OwnedObjectPath,
+    library: &Library,
+) -> HashMap<String, Value<'static>> {
+    let mut hm = HashMap::new();
+
+    hm.insert(
+        "mpris:trackid".to_string(),
+        Value::ObjectPath(track_id.into_inner()),
+    );
+    hm.insert(
+        "mpris:length".to_string(),
+        Value::I64(playable.map(|t| t.duration() as i64 * 1_000).unwrap_or(0)),
+    );
+    hm.insert(
+        "mpris:artUrl".to_string(),
+        Value::Str(
+            playable
+                .map(|t| t.cover_url().unwrap_or_default())
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+
+    hm.insert(
+        "xesam:album".to_string(),
+        Value::Str(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.album.unwrap_or_default())
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:albumArtist".to_string(),
+        Value::Array(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.album_artists)
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:artist".to_string(),
+        Value::Array(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.artists)
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:discNumber".to_string(),
+        Value::I32(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.disc_number)
+                .unwrap_or(0),
+        ),
+    );
+    hm.insert(
+        "xesam:title".to_string(),
+        Value::Str(
+            playable
+                .map(|t| match t {
+                    Playable::Track(t) => t.title.clone(),
+                    Playable::Episode(ep) => ep.name.clone(),
+                })
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:trackNumber".to_string(),
+        Value::I32(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| t.track_number)
+                .unwrap_or(0) as i32,
+        ),
+    );
+    hm.insert(
+        "xesam:url".to_string(),
+        Value::Str(
+            playable
+                .map(|t| t.share_url().unwrap_or_default())
+                .unwrap_or_default()
+                .into(),
+        ),
+    );
+    hm.insert(
+        "xesam:userRating".to_string(),
+        Value::F64(
+            playable
+                .and_then(|p| p.track())
+                .map(|t| match library.is_saved_track(&Playable::Track(t)) {
+                    true => 1.0,
+                    false => 0.0,
+                })
+                .unwrap_or(0.0),
+        ),
+    );
+
+    hm
+}
+
+/// Get the items of the Spotify URI or URL `uri`.
+fn resolve_uri(spotify: &Spotify, uri: &str) -> Vec<Playable> {
+    let spotify_url = if uri.contains("open.spotify.com") {
+        SpotifyUrl::from_url(uri)
+    } else if let Ok(uri_type) = uri.parse() {
+        let id = &uri[uri.rfind(':').unwrap_or(0) + 1..uri.len()];
+        Some(SpotifyUrl::new(id, uri_type))
+    } else {
+        None
+    };
+
+    let id = spotify_url
+        .as_ref()
+        .map(|s| s.id.clone())
+        .unwrap_or("".to_string());
+    let uri_type = spotify_url.map(|s| s.uri_type);
+    match uri_type {
+        Some(UriType::Album) => spotify
+            .api
+            .album(&id)
+            .ok()
+            .and_then(|a| Album::from(&a).tracks)
+            .map(|tracks| tracks.into_iter().map(Playable::Track).collect())
+            .unwrap_or_default(),
+        Some(UriType::Track) => spotify
+            .api
+            .track(&id)
+            .map(|t| vec![Playable::Track(Track::from(&t))])
+            .unwrap_or_default(),
+        Some(UriType::Playlist) => spotify
+            .api
+            .playlist(&id)
+            .map(|p| {
+                let mut playlist = Playlist::from(&p);
+                playlist.load_tracks(spotify);
+                playlist.tracks.unwrap_or_default()
+            })
+            .unwrap_or_default(),
+        Some(UriType::Show) => spotify
+            .api
+            .show(&id)
+            .map(|s| {
+                let mut show: Show = (&s).into();
+                show.load_all_episodes(spotify.clone());
+                let mut episodes = show.episodes.unwrap_or_default();
+                episodes.reverse();
+                episodes.into_iter().map(Playable::Episode).collect()
+            })
+            .unwrap_or_default(),
+        Some(UriType::Episode) => spotify
+            .api
+            .episode(&id)
+            .map(|e| vec![Playable::Episode(Episode::from(&e))])
+            .unwrap_or_default(),
+        Some(UriType::Artist) => spotify
+            .api
+            .artist_top_tracks(&id)
+            .map(|tracks| tracks.into_iter().map(Playable::Track).collect())
+            .unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+/// Replace the queue with `tracks` and play them.
+fn play_tracks(queue: &Queue, tracks: &Vec<Playable>) {
+    if tracks.is_empty() {
+        return;
+    }
+    let should_shuffle = queue.get_shuffle();
+    queue.clear();
+    let index = queue.append_next(tracks);
+    queue.play(index, should_shuffle, should_shuffle)
 }
 
 /// Commands to control the [MprisManager] worker thread.
@@ -480,6 +698,8 @@ pub enum MprisCommand {
     EmitMetadataStatus,
     /// Emit seeked position
     EmitSeekedStatus(i64),
+    /// Emit the changed items, shuffle and loop status of the queue
+    EmitQueueStatus,
 }
 
 /// An MPRIS server that internally manager a thread which can be sent commands. This is internally
@@ -487,6 +707,8 @@ pub enum MprisCommand {
 #[derive(Clone)]
 pub struct MprisManager {
     tx: mpsc::UnboundedSender<MprisCommand>,
+    /// Whether a queue update is waiting to be sent, so a burst of changes only sends one.
+    queue_pending: Arc<AtomicBool>,
 }
 
 impl MprisManager {
@@ -497,6 +719,17 @@ impl MprisManager {
         spotify: Spotify,
     ) -> Self {
         let root = MprisRoot {};
+        let tracklist = MprisTrackList {
+            queue: queue.clone(),
+            library: library.clone(),
+            spotify: spotify.clone(),
+        };
+        let playlists = MprisPlaylists {
+            queue: queue.clone(),
+            library: library.clone(),
+            spotify: spotify.clone(),
+            active: Mutex::new(None),
+        };
         let player = MprisPlayer {
             event,
             queue,
@@ -505,34 +738,58 @@ impl MprisManager {
         };
 
         let (tx, rx) = mpsc::unbounded_channel::<MprisCommand>();
+        let queue_pending = Arc::new(AtomicBool::new(false));
 
+        let pending = queue_pending.clone();
         ASYNC_RUNTIME.get().unwrap().spawn(async {
-            let result = Self::serve(UnboundedReceiverStream::new(rx), root, player).await;
+            let result = Self::serve(
+                UnboundedReceiverStream::new(rx),
+                pending,
+                root,
+                player,
+                tracklist,
+                playlists,
+            )
+            .await;
             if let Err(e) = result {
                 log::error!("MPRIS error: {e}");
             }
         });
 
-        Self { tx }
+        Self { tx, queue_pending }
     }
 
     async fn serve(
         mut rx: UnboundedReceiverStream<MprisCommand>,
+        queue_pending: Arc<AtomicBool>,
         root: MprisRoot,
         player: MprisPlayer,
+        tracklist: MprisTrackList,
+        playlists: MprisPlaylists,
     ) -> Result<(), Box<dyn Error + Sync + Send>> {
         let conn = connection::Builder::session()?
-            .name(instance_bus_name())?
             .serve_at("/org/mpris/MediaPlayer2", root)?
             .serve_at("/org/mpris/MediaPlayer2", player)?
+            .serve_at("/org/mpris/MediaPlayer2", tracklist)?
+            .serve_at("/org/mpris/MediaPlayer2", playlists)?
             .build()
             .await?;
 
+        let name = instance_bus_name();
+        if let Err(e) = conn.request_name(name.as_str()).await {
+            let fallback = pid_bus_name();
+            info!("could not claim the bus name {name} ({e}), using {fallback}");
+            conn.request_name(fallback).await?;
+        }
+
         let object_server = conn.object_server();
         let player_iface_ref = object_server
             .interface::<_, MprisPlayer>("/org/mpris/MediaPlayer2")
             .await?;
         let player_iface = player_iface_ref.get().await;
+        let tracklist_iface_ref = object_server
+            .interface::<_, MprisTrackList>("/org/mpris/MediaPlayer2")
+            .await?;
 
         loop {
             let ctx = player_iface_ref.signal_emitter();
@@ -551,6 +808,20 @@ impl MprisManager {
                     info!("sending MPRIS seeked signal");
                     MprisPlayer::seeked(ctx, &pos).await?;
                 }
+                Some(MprisCommand::EmitQueueStatus) => {
+                    queue_pending.store(false, Ordering::SeqCst);
+                    player_iface.shuffle_changed(ctx).await?;
+                    player_iface.loop_status_changed(ctx).await?;
+                    let tracklist = tracklist_iface_ref.get().await;
+                    let tracks = track_ids(&tracklist.queue.queue.read().unwrap());
+                    let current = current_track_id(&tracklist.queue);
+                    MprisTrackList::track_list_replaced(
+                        tracklist_iface_ref.signal_emitter(),
+                        tracks,
+                        current,
+                    )
+                    .await?;
+                }
                 None => break,
             }
         }
@@ -558,18 +829,44 @@ impl MprisManager {
     }
 
     pub fn send(&self, command: MprisCommand) {
+        if matches!(command, MprisCommand::EmitQueueStatus)
+            && self.queue_pending.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
         if let Err(e) = self.tx.send(command) {
             log::warn!("Could not update MPRIS state: {e}");
         }
     }
 }
 
-/// Get the D-Bus bus name for this instance according to the MPRIS specification.
+/// Get the D-Bus bus name for this instance according to the MPRIS specification. The primary
+/// instance uses the plain name, further ones are told apart by their process id.
 ///
 /// <https://specifications.freedesktop.org/mpris-spec/2.2/#Bus-Name-Policy>
 pub fn instance_bus_name() -> String {
+    match instance::current() {
+        Some(instance) if !instance.is_primary() => pid_bus_name(),
+        _ => "org.mpris.MediaPlayer2.ncspot".to_string(),
+    }
+}
+
+/// The bus name told apart by the process id. A primary instance falls back to it when another
+/// one already has the plain name, i.e. because it uses a separate configuration directory.
+fn pid_bus_name() -> String {
     format!(
         "org.mpris.MediaPlayer2.ncspot.instance{}",
         std::process::id()
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_paths() {
+        let paths = ["/a", "/b", "/a", "/a"].map(String::from);
+        assert_eq!(unique_paths(paths), vec!["/a", "/b", "/a/2", "/a/3"]);
+    }
+}
@@ -0,0 +1,330 @@
+//! A local history of the played tracks, independent of the short history Spotify keeps. It is
+//! stored by month and summarized in the stats view.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use log::{debug, error};
+
+use crate::config::Config;
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+use crate::storage::{Namespace, Storage};
+
+/// Listens shorter than this aren't recorded.
+const MIN_PLAYED: Duration = Duration::from_secs(5);
+/// A listen counts as a play once half of the track or this time was played.
+const MAX_REQUIRED: Duration = Duration::from_secs(4 * 60);
+
+/// One listen of a track.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Play {
+    /// When playback started, in seconds since the Unix epoch.
+    pub started: i64,
+    pub uri: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    /// The duration of the track in milliseconds.
+    pub duration: u32,
+    /// How long the track was played in milliseconds.
+    pub played: u32,
+}
+
+impl Play {
+    /// How much of the track was played, in percent.
+    pub fn completion(&self) -> u32 {
+        match self.duration {
+            0 => 0,
+            duration => (self.played as u64 * 100 / duration as u64).min(100) as u32,
+        }
+    }
+
+    /// Whether the track was played long enough to count as played, like a scrobble.
+    pub fn counts(&self) -> bool {
+        let required = Duration::from_millis(self.duration as u64 / 2).min(MAX_REQUIRED);
+        Duration::from_millis(self.played.into()) >= required
+    }
+
+    fn date(&self) -> Option<DateTime<Local>> {
+        DateTime::from_timestamp(self.started, 0).map(|date| date.with_timezone(&Local))
+    }
+}
+
+const KEY_PREFIX: &str = "play_history_";
+
+/// The storage key of the plays of the month `timestamp` is in.
+fn key(timestamp: i64) -> String {
+    let month = DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&Local)
+        .format("%Y-%m");
+    format!("{KEY_PREFIX}{month}.json")
+}
+
+fn read(storage: &dyn Storage, key: &str) -> Vec<Play> {
+    match storage.read(Namespace::State, key) {
+        Ok(Some(contents)) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+            error!("can't parse play history {key}: {e}");
+            Vec::new()
+        }),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            error!("can't read play history {key}: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Add `play` to the history.
+pub fn record(storage: &dyn Storage, play: Play) {
+    let key = key(play.started);
+    debug!("recording play of {}", play.uri);
+    let mut plays = read(storage, &key);
+    plays.push(play);
+    let result = serde_json::to_vec(&plays)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| storage.write(Namespace::State, &key, &contents));
+    if let Err(e) = result {
+        error!("could not record play: {e}");
+    }
+}
+
+/// The plays that started at `since` or later, oldest first.
+pub fn load(storage: &dyn Storage, since: i64) -> Vec<Play> {
+    let first = key(since);
+    let mut keys: Vec<String> = storage
+        .keys(Namespace::State)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|key| key.starts_with(KEY_PREFIX) && *key >= first)
+        .collect();
+    keys.sort();
+    keys.iter()
+        .flat_map(|key| read(storage, key))
+        .filter(|play| play.started >= since)
+        .collect()
+}
+
+/// Count the plays by `name`, most played first.
+fn top<'a>(
+    plays: &'a [Play],
+    n: usize,
+    name: impl Fn(&'a Play) -> Vec<String>,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for play in plays.iter().filter(|play| play.counts()) {
+        for name in name(play) {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(n);
+    counts
+}
+
+/// How often each track was played, by its URI.
+pub fn play_counts(plays: &[Play]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for play in plays.iter().filter(|play| play.counts()) {
+        *counts.entry(play.uri.clone()).or_default() += 1;
+    }
+    counts
+}
+
+/// The `n` most played artists with their amount of plays.
+pub fn top_artists(plays: &[Play], n: usize) -> Vec<(String, usize)> {
+    top(plays, n, |play| play.artists.clone())
+}
+
+/// The `n` most played tracks, as "title - artists", with their amount of plays.
+pub fn top_tracks(plays: &[Play], n: usize) -> Vec<(String, usize)> {
+    top(plays, n, |play| {
+        vec![format!("{} - {}", play.title, play.artists.join(", "))]
+    })
+}
+
+/// How long was listened on each of the `days` days up to `today`, oldest first.
+pub fn time_per_day(plays: &[Play], days: u32, today: NaiveDate) -> Vec<(NaiveDate, Duration)> {
+    let mut per_day: HashMap<NaiveDate, u64> = HashMap::new();
+    for play in plays {
+        if let Some(date) = play.date() {
+            *per_day.entry(date.date_naive()).or_default() += play.played as u64;
+        }
+    }
+    (0..days)
+        .rev()
+        .filter_map(|ago| today.checked_sub_days(chrono::Days::new(ago.into())))
+        .map(|day| {
+            let played = per_day.get(&day).copied().unwrap_or_default();
+            (day, Duration::from_millis(played))
+        })
+        .collect()
+}
+
+/// Quote `field` for CSV if it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The plays as CSV, with a header row.
+pub fn to_csv(plays: &[Play]) -> String {
+    let mut csv =
+        String::from("started,title,artists,album,duration_ms,played_ms,completion,uri\n");
+    for play in plays {
+        let started = DateTime::<Utc>::from_timestamp(play.started, 0)
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_default();
+        let fields = [
+            started,
+            csv_field(&play.title),
+            csv_field(&play.artists.join(", ")),
+            csv_field(play.album.as_deref().unwrap_or_default()),
+            play.duration.to_string(),
+            play.played.to_string(),
+            play.completion().to_string(),
+            csv_field(&play.uri),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Write the whole history to `path` as CSV. Returns the amount of plays.
+pub fn export(storage: &dyn Storage, path: &str) -> Result<usize, String> {
+    let plays = load(storage, 0);
+    fs::write(path, to_csv(&plays)).map_err(|e| format!("Could not write {path}: {e}"))?;
+    Ok(plays.len())
+}
+
+/// The playing track and how long it has been played.
+struct Listen {
+    play: Play,
+    played: Duration,
+    /// When playback was started or resumed, if it is playing.
+    since: Option<Instant>,
+}
+
+/// Follows the playback to record every listen when it ends.
+pub struct PlayHistory {
+    cfg: Arc<Config>,
+    current: Option<Listen>,
+}
+
+impl PlayHistory {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        Self { cfg, current: None }
+    }
+
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let track = match playable {
+            Some(Playable::Track(track)) => Some(track),
+            _ => None,
+        };
+        if self.current.as_ref().map(|listen| &listen.play.uri) != track.map(|t| &t.uri) {
+            self.finish();
+            self.current = track.map(|track| Listen {
+                play: Play {
+                    started: Utc::now().timestamp(),
+                    uri: track.uri.clone(),
+                    title: track.title.clone(),
+                    artists: track.artists.clone(),
+                    album: track.album.clone(),
+                    duration: track.duration,
+                    played: 0,
+                },
+                played: Duration::ZERO,
+                since: None,
+            });
+        }
+
+        let Some(listen) = &mut self.current else {
+            return;
+        };
+        match state {
+            PlayerEvent::Playing(_) => {
+                listen.since.get_or_insert_with(Instant::now);
+            }
+            PlayerEvent::Paused(_) => {
+                if let Some(since) = listen.since.take() {
+                    listen.played += since.elapsed();
+                }
+            }
+            // Playing the same track again counts as another listen.
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => self.finish(),
+        }
+    }
+
+    /// Record the playing track as far as it was played, e.g. because ncspot quits.
+    pub fn finish(&mut self) {
+        let Some(mut listen) = self.current.take() else {
+            return;
+        };
+        let played = listen.played + listen.since.map_or(Duration::ZERO, |t| t.elapsed());
+        if played < MIN_PLAYED || !self.cfg.values().play_history.unwrap_or(true) {
+            return;
+        }
+        listen.play.played = played.as_millis().min(u32::MAX as u128) as u32;
+        record(self.cfg.storage().as_ref(), listen.play);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(title: &str, artist: &str, started: i64, played: u32) -> Play {
+        Play {
+            started,
+            uri: format!("spotify:track:{title}"),
+            title: title.into(),
+            artists: vec![artist.into()],
+            album: None,
+            duration: 200_000,
+            played,
+        }
+    }
+
+    #[test]
+    fn test_top() {
+        let plays = [
+            play("Heroes", "David Bowie", 0, 200_000),
+            play("Heroes", "David Bowie", 0, 150_000),
+            play("Airbag", "Radiohead", 0, 100_000),
+            // Skipped, so it doesn't count.
+            play("Karma Police", "Radiohead", 0, 10_000),
+        ];
+
+        assert_eq!(
+            top_artists(&plays, 10),
+            [("David Bowie".to_string(), 2), ("Radiohead".to_string(), 1)]
+        );
+        assert_eq!(
+            top_tracks(&plays, 1),
+            [("Heroes - David Bowie".to_string(), 2)]
+        );
+        assert_eq!(plays[2].completion(), 50);
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut heroes = play("Heroes, \"live\"", "David Bowie", 0, 100_000);
+        heroes.album = Some("Stage".into());
+        heroes.uri = "spotify:track:1".into();
+        let csv = to_csv(&[heroes]);
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "1970-01-01T00:00:00+00:00,\"Heroes, \"\"live\"\"\",David Bowie,Stage,200000,100000,50,spotify:track:1"
+        );
+    }
+}
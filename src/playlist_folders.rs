@@ -0,0 +1,185 @@
+//! Folders for the playlists tab. The Web API doesn't expose the folders of the Spotify clients,
+//! so they only exist locally and are kept in the user state.
+
+use std::collections::HashSet;
+
+/// A folder of playlists, in the order they are shown.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistFolder {
+    pub name: String,
+    /// The ids of the playlists in the folder.
+    pub playlists: Vec<String>,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// A row of the playlists tab.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Row {
+    /// The folder at the given index.
+    Folder(usize),
+    /// The playlist at `index` in the library, inside the folder at the given index if any.
+    Playlist { index: usize, folder: Option<usize> },
+}
+
+/// The rows of the playlists with the given ids: the folders with their playlists first, unless
+/// they are collapsed, then the playlists that aren't in any folder in the order of the library.
+pub fn rows(playlist_ids: &[&str], folders: &[PlaylistFolder]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut in_folder = HashSet::new();
+    for (folder_index, folder) in folders.iter().enumerate() {
+        rows.push(Row::Folder(folder_index));
+        for id in &folder.playlists {
+            in_folder.insert(id.as_str());
+            let Some(index) = playlist_ids.iter().position(|p| p == id) else {
+                continue;
+            };
+            if !folder.collapsed {
+                rows.push(Row::Playlist {
+                    index,
+                    folder: Some(folder_index),
+                });
+            }
+        }
+    }
+    for (index, id) in playlist_ids.iter().enumerate() {
+        if !in_folder.contains(id) {
+            rows.push(Row::Playlist {
+                index,
+                folder: None,
+            });
+        }
+    }
+    rows
+}
+
+/// Add an empty folder called `name` after the existing ones.
+pub fn create(folders: &mut Vec<PlaylistFolder>, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The folder needs a name".to_string());
+    }
+    if folders.iter().any(|folder| folder.name == name) {
+        return Err(format!("There already is a folder named {name}"));
+    }
+    folders.push(PlaylistFolder {
+        name: name.to_string(),
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// Move the playlist with `id` to the end of the folder called `name`, or out of its folder if
+/// `name` is `None`.
+pub fn move_playlist(
+    folders: &mut [PlaylistFolder],
+    id: &str,
+    name: Option<&str>,
+) -> Result<(), String> {
+    let target = match name {
+        Some(name) => Some(
+            folders
+                .iter()
+                .position(|folder| folder.name == name.trim())
+                .ok_or_else(|| format!("There is no folder named {name}"))?,
+        ),
+        None => None,
+    };
+    for folder in folders.iter_mut() {
+        folder.playlists.retain(|playlist| playlist != id);
+    }
+    if let Some(target) = target {
+        folders[target].playlists.push(id.to_string());
+    }
+    Ok(())
+}
+
+/// Move the folder at `row`, or the playlist with `id` inside its folder, one place up or down.
+/// Playlists outside of folders keep the order of the library. Returns whether anything moved.
+pub fn shift(folders: &mut [PlaylistFolder], row: Row, id: Option<&str>, up: bool) -> bool {
+    let swap = |len: usize, index: usize| match up {
+        true => index.checked_sub(1),
+        false => Some(index + 1).filter(|&other| other < len),
+    };
+    match (row, id) {
+        (Row::Folder(index), _) => swap(folders.len(), index)
+            .map(|other| folders.swap(index, other))
+            .is_some(),
+        (
+            Row::Playlist {
+                folder: Some(folder),
+                ..
+            },
+            Some(id),
+        ) => {
+            let playlists = &mut folders[folder].playlists;
+            playlists
+                .iter()
+                .position(|playlist| playlist == id)
+                .and_then(|index| Some((index, swap(playlists.len(), index)?)))
+                .map(|(index, other)| playlists.swap(index, other))
+                .is_some()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(name: &str, playlists: &[&str], collapsed: bool) -> PlaylistFolder {
+        PlaylistFolder {
+            name: name.into(),
+            playlists: playlists.iter().map(|id| id.to_string()).collect(),
+            collapsed,
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        let folders = [
+            folder("2023", &["c", "gone", "a"], false),
+            folder("2024", &["d"], true),
+        ];
+        let playlist = |index, folder| Row::Playlist { index, folder };
+        assert_eq!(
+            rows(&["a", "b", "c", "d", "e"], &folders),
+            [
+                Row::Folder(0),
+                playlist(2, Some(0)),
+                playlist(0, Some(0)),
+                Row::Folder(1),
+                playlist(1, None),
+                playlist(4, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_and_shift() {
+        let mut folders = vec![folder("2023", &["a"], false)];
+        create(&mut folders, "2024").unwrap();
+        assert!(create(&mut folders, "2024").is_err());
+
+        move_playlist(&mut folders, "b", Some("2023")).unwrap();
+        move_playlist(&mut folders, "a", Some("2024")).unwrap();
+        assert!(move_playlist(&mut folders, "a", Some("2025")).is_err());
+        assert_eq!(folders[0].playlists, ["b"]);
+        assert_eq!(folders[1].playlists, ["a"]);
+
+        move_playlist(&mut folders, "a", Some("2023")).unwrap();
+        let row = Row::Playlist {
+            index: 0,
+            folder: Some(0),
+        };
+        assert!(shift(&mut folders, row, Some("a"), true));
+        assert!(!shift(&mut folders, row, Some("a"), true));
+        assert_eq!(folders[0].playlists, ["a", "b"]);
+
+        assert!(shift(&mut folders, Row::Folder(1), None, true));
+        assert_eq!(folders[0].name, "2024");
+        move_playlist(&mut folders, "a", None).unwrap();
+        assert_eq!(folders[1].playlists, ["b"]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Local history of the contents of the user's own playlists, so a playlist can be restored after
+//! an accidental bulk deletion or a bad import.
+
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error};
+
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::storage::{Namespace, Storage};
+
+/// The default amount of snapshots kept for every playlist.
+pub const DEFAULT_SNAPSHOTS: usize = 10;
+
+/// The contents of a playlist at one point in time.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlaylistSnapshot {
+    pub snapshot_id: String,
+    /// Seconds since the Unix epoch.
+    pub taken: i64,
+    pub tracks: Vec<Playable>,
+}
+
+impl PlaylistSnapshot {
+    /// The time the snapshot was taken, in the local time zone.
+    pub fn date(&self) -> String {
+        DateTime::from_timestamp(self.taken, 0)
+            .map(|date| {
+                date.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn key(playlist_id: &str) -> String {
+    format!("playlist_history_{playlist_id}.json")
+}
+
+/// Get the recorded snapshots of the playlist with `playlist_id`, newest first.
+pub fn snapshots(storage: &dyn Storage, playlist_id: &str) -> Vec<PlaylistSnapshot> {
+    match storage.read(Namespace::State, &key(playlist_id)) {
+        Ok(Some(contents)) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+            error!("can't parse history of playlist {playlist_id}: {e}");
+            Vec::new()
+        }),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            error!("can't read history of playlist {playlist_id}: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Record the tracks of `playlist` unless its current snapshot is already recorded. Only the
+/// `limit` newest snapshots are kept.
+pub fn record(storage: &dyn Storage, playlist: &Playlist, limit: usize) {
+    let Some(tracks) = &playlist.tracks else {
+        return;
+    };
+    let mut history = snapshots(storage, &playlist.id);
+    let snapshot = PlaylistSnapshot {
+        snapshot_id: playlist.snapshot_id.clone(),
+        taken: Utc::now().timestamp(),
+        tracks: tracks.clone(),
+    };
+    if !push(&mut history, snapshot, limit) {
+        return;
+    }
+
+    debug!("recording snapshot of playlist {}", playlist.name);
+    let result = serde_json::to_vec(&history)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| storage.write(Namespace::State, &key(&playlist.id), &contents));
+    if let Err(e) = result {
+        error!(
+            "could not record snapshot of playlist {}: {e}",
+            playlist.name
+        );
+    }
+}
+
+/// Add `snapshot` to the front of `history` and drop the oldest snapshots over `limit`. Returns
+/// whether `history` changed.
+fn push(history: &mut Vec<PlaylistSnapshot>, snapshot: PlaylistSnapshot, limit: usize) -> bool {
+    if history
+        .first()
+        .is_some_and(|newest| newest.snapshot_id == snapshot.snapshot_id)
+    {
+        return false;
+    }
+    history.insert(0, snapshot);
+    history.truncate(limit);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str) -> PlaylistSnapshot {
+        PlaylistSnapshot {
+            snapshot_id: id.to_string(),
+            taken: 0,
+            tracks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        let mut history = Vec::new();
+        assert!(push(&mut history, snapshot("a"), 2));
+        assert!(!push(&mut history, snapshot("a"), 2));
+        assert!(push(&mut history, snapshot("b"), 2));
+        assert!(push(&mut history, snapshot("c"), 2));
+
+        let ids: Vec<&str> = history.iter().map(|s| s.snapshot_id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+    }
+}
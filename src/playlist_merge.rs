@@ -0,0 +1,165 @@
+//! Merging the tracks of several playlists into one, or comparing two of them, curation chores that
+//! otherwise need external tools.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::model::playable::Playable;
+
+/// How the tracks of the merged playlists are combined.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeMode {
+    /// All tracks, one playlist after another, with only the first copy of every duplicate.
+    #[default]
+    KeepFirst,
+    /// All tracks, one playlist after another, including duplicates.
+    KeepAll,
+    /// One track of every playlist in turn, with only the first copy of every duplicate.
+    Interleave,
+}
+
+impl MergeMode {
+    /// The mode that follows this one when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Self::KeepFirst => Self::KeepAll,
+            Self::KeepAll => Self::Interleave,
+            Self::Interleave => Self::KeepFirst,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::KeepFirst => "keep first",
+            Self::KeepAll => "keep all",
+            Self::Interleave => "interleave",
+        }
+    }
+}
+
+impl fmt::Display for MergeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Self::KeepFirst => "keep_first",
+            Self::KeepAll => "keep_all",
+            Self::Interleave => "interleave",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+impl FromStr for MergeMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep_first" => Ok(Self::KeepFirst),
+            "keep_all" => Ok(Self::KeepAll),
+            "interleave" => Ok(Self::Interleave),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Combine the tracks of `playlists` according to `mode`.
+pub fn merge(playlists: &[Vec<Playable>], mode: MergeMode) -> Vec<Playable> {
+    merge_by(playlists, mode, Playable::id)
+}
+
+/// Combine `lists` according to `mode`, using `id` to find duplicates. Items without an id, like
+/// local files, are never considered duplicates.
+fn merge_by<T: Clone>(
+    lists: &[Vec<T>],
+    mode: MergeMode,
+    id: impl Fn(&T) -> Option<String>,
+) -> Vec<T> {
+    let ordered: Vec<&T> = match mode {
+        MergeMode::KeepFirst | MergeMode::KeepAll => lists.iter().flatten().collect(),
+        MergeMode::Interleave => {
+            let longest = lists.iter().map(Vec::len).max().unwrap_or(0);
+            (0..longest)
+                .flat_map(|i| lists.iter().filter_map(move |list| list.get(i)))
+                .collect()
+        }
+    };
+
+    let mut seen = HashSet::new();
+    ordered
+        .into_iter()
+        .filter(|item| mode == MergeMode::KeepAll || id(item).is_none_or(|id| seen.insert(id)))
+        .cloned()
+        .collect()
+}
+
+/// The tracks of two playlists, split by which of them contain them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlaylistDiff<T> {
+    pub only_a: Vec<T>,
+    pub only_b: Vec<T>,
+    pub both: Vec<T>,
+}
+
+/// Compare the tracks of the playlists `a` and `b`.
+pub fn diff(a: &[Playable], b: &[Playable]) -> PlaylistDiff<Playable> {
+    diff_by(a, b, Playable::id)
+}
+
+/// Compare `a` and `b`, using `id` to find the same items. Items without an id, like local files,
+/// are only in the list they come from. Tracks in both lists are listed once, in the order of `a`.
+fn diff_by<T: Clone>(a: &[T], b: &[T], id: impl Fn(&T) -> Option<String>) -> PlaylistDiff<T> {
+    let ids_a: HashSet<String> = a.iter().filter_map(&id).collect();
+    let ids_b: HashSet<String> = b.iter().filter_map(&id).collect();
+    let in_other =
+        |item: &T, other: &HashSet<String>| id(item).is_some_and(|id| other.contains(&id));
+
+    let mut seen = HashSet::new();
+    PlaylistDiff {
+        only_a: a
+            .iter()
+            .filter(|item| !in_other(item, &ids_b))
+            .cloned()
+            .collect(),
+        only_b: b
+            .iter()
+            .filter(|item| !in_other(item, &ids_a))
+            .cloned()
+            .collect(),
+        both: a
+            .iter()
+            .filter(|item| in_other(item, &ids_b) && id(item).is_some_and(|id| seen.insert(id)))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_by() {
+        let lists = vec![vec!["a", "b", "c"], vec!["d", "a"], vec!["e"]];
+        let merge = |mode| merge_by(&lists, mode, |item: &&str| Some(item.to_string()));
+
+        assert_eq!(merge(MergeMode::KeepFirst), vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(
+            merge(MergeMode::KeepAll),
+            vec!["a", "b", "c", "d", "a", "e"]
+        );
+        assert_eq!(merge(MergeMode::Interleave), vec!["a", "d", "e", "b", "c"]);
+    }
+
+    #[test]
+    fn test_diff_by() {
+        let id = |item: &&str| Some(item.to_string());
+        assert_eq!(
+            diff_by(&["a", "b", "c", "b"], &["d", "b", "a"], id),
+            PlaylistDiff {
+                only_a: vec!["c"],
+                only_b: vec!["d"],
+                both: vec!["a", "b"],
+            }
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Plugins are files in the `plugins` directory of the configuration that bundle commands,
+//! keybindings and hooks, so a feature can be shared as one file and turned off by removing it.
+//!
+//! They use the same sections as `config.toml`. Their hooks are external scripts that only get the
+//! playback details, code that runs inside ncspot goes in `.rhai` scripts next to them, see
+//! `scripting.rs`.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs;
+
+use log::{error, info, warn};
+
+use crate::config::{self, ConfigHooks, ConfigValues};
+use crate::serialization::{Serializer, TOML};
+
+const PLUGIN_DIRECTORY: &str = "plugins";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Plugin {
+    pub aliases: Option<HashMap<String, String>>,
+    pub keybindings: Option<HashMap<String, String>>,
+    pub hooks: Option<ConfigHooks>,
+}
+
+/// Load the `.toml` files in the plugin directory, ordered by their name.
+fn load() -> Vec<(String, Plugin)> {
+    let directory = config::config_path(PLUGIN_DIRECTORY);
+    let Ok(entries) = fs::read_dir(&directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            match TOML.load(&path) {
+                Ok(plugin) => {
+                    info!("loaded plugin {name}");
+                    Some((name, plugin))
+                }
+                Err(e) => {
+                    error!("Could not load plugin {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Add the aliases, keybindings and hooks of the plugins to `values`. Aliases and keybindings of
+/// the configuration file take precedence, between plugins the first one wins.
+pub fn apply(values: &mut ConfigValues) {
+    merge(values, load());
+}
+
+fn merge(values: &mut ConfigValues, plugins: Vec<(String, Plugin)>) {
+    for (name, plugin) in plugins {
+        let sections = [
+            (plugin.aliases, &mut values.aliases),
+            (plugin.keybindings, &mut values.keybindings),
+        ];
+        for (entries, existing) in sections {
+            let existing = existing.get_or_insert_default();
+            for (key, value) in entries.unwrap_or_default() {
+                match existing.entry(key) {
+                    Entry::Occupied(entry) => {
+                        warn!(
+                            "plugin {name}: \"{}\" is already defined, ignoring it",
+                            entry.key()
+                        );
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(value);
+                    }
+                }
+            }
+        }
+        if let Some(hooks) = plugin.hooks {
+            values.plugin_hooks.push(hooks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut values = ConfigValues {
+            keybindings: Some(HashMap::from([("g".to_string(), "stop".to_string())])),
+            ..Default::default()
+        };
+        let plugin = |key: &str, command: &str| Plugin {
+            keybindings: Some(HashMap::from([(key.to_string(), command.to_string())])),
+            hooks: Some(ConfigHooks::default()),
+            ..Default::default()
+        };
+        merge(
+            &mut values,
+            vec![
+                ("a".to_string(), plugin("g", "next")),
+                ("b".to_string(), plugin("h", "next")),
+                ("c".to_string(), plugin("h", "previous")),
+            ],
+        );
+
+        let keybindings = values.keybindings.unwrap();
+        assert_eq!(keybindings["g"], "stop");
+        assert_eq!(keybindings["h"], "next");
+        assert_eq!(values.plugin_hooks.len(), 3);
+        assert!(values.aliases.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Routing the connections of ncspot through a HTTP or SOCKS5 proxy, for networks that require one
+//! and for Tor.
+
+use std::env;
+use std::sync::OnceLock;
+
+use log::{info, warn};
+use url::Url;
+
+/// The URL schemes of the supported proxies.
+const SCHEMES: [&str; 3] = ["http", "socks5", "socks5h"];
+
+static PROXY: OnceLock<Url> = OnceLock::new();
+
+/// Parse and check the proxy URL `proxy` from the configuration.
+fn parse(proxy: &str) -> Result<Url, String> {
+    let url = Url::parse(proxy).map_err(|e| format!("invalid proxy URL {proxy}: {e}"))?;
+    if !SCHEMES.contains(&url.scheme()) {
+        return Err(format!(
+            "unsupported proxy scheme {}, use one of {}",
+            url.scheme(),
+            SCHEMES.join(", ")
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("proxy URL {proxy} has no host"));
+    }
+    Ok(url)
+}
+
+/// The environment variables that route the Web API and OAuth clients through `url`.
+///
+/// rspotify's ureq client reads `ALL_PROXY` first and only knows the `socks5` scheme, but it
+/// resolves host names through the proxy like `socks5h` does. reqwest, which librespot-oauth uses,
+/// prefers `HTTPS_PROXY` and `HTTP_PROXY` over `ALL_PROXY`, so it gets the URL as configured.
+fn environment(url: &Url) -> [(&'static str, String); 3] {
+    let ureq_url = url.as_str().replacen("socks5h://", "socks5://", 1);
+    [
+        ("ALL_PROXY", ureq_url),
+        ("HTTPS_PROXY", url.to_string()),
+        ("HTTP_PROXY", url.to_string()),
+    ]
+}
+
+/// Route all connections of ncspot through the proxy at `proxy`.
+///
+/// The Web API and OAuth clients can only take their proxy from the environment, so this has to
+/// be called before any other thread is started.
+pub fn init(proxy: Option<&str>) -> Result<(), String> {
+    let Some(proxy) = proxy else {
+        return Ok(());
+    };
+    let url = parse(proxy)?;
+    info!("using proxy {url}");
+    for (variable, value) in environment(&url) {
+        // SAFETY: `main` calls this before any other thread is started.
+        unsafe { env::set_var(variable, value) };
+    }
+    PROXY.set(url).ok();
+    Ok(())
+}
+
+/// A builder for the HTTP clients of ncspot, that connects through the configured proxy. Without
+/// one, reqwest uses the proxy from the environment.
+pub fn client_builder() -> reqwest::blocking::ClientBuilder {
+    let builder = reqwest::blocking::Client::builder();
+    match PROXY.get().map(|url| reqwest::Proxy::all(url.as_str())) {
+        Some(Ok(proxy)) => builder.proxy(proxy),
+        Some(Err(e)) => {
+            warn!("could not use the proxy: {e}");
+            builder
+        }
+        None => builder,
+    }
+}
+
+/// The proxy that librespot should connect through, or the `http_proxy` environment variable if
+/// none is configured. librespot only supports HTTP proxies, so a configured SOCKS proxy is an
+/// error rather than a reason to connect directly.
+pub fn session_proxy() -> Result<Option<Url>, String> {
+    match PROXY.get() {
+        Some(url) if url.scheme() == "http" => Ok(Some(url.clone())),
+        Some(url) => Err(format!(
+            "librespot can't connect through {} proxies, so ncspot can't log in or play without \
+             connecting directly. Use a HTTP proxy instead.",
+            url.scheme()
+        )),
+        None => Ok(env::var("http_proxy")
+            .ok()
+            .and_then(|proxy| Url::parse(&proxy).ok())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("http://proxy.example.com:3128").is_ok());
+        assert!(parse("socks5h://127.0.0.1:9050").is_ok());
+        assert!(parse("ftp://proxy.example.com").is_err());
+        assert!(parse("https://proxy.example.com").is_err());
+        assert!(parse("proxy.example.com:3128").is_err());
+    }
+
+    #[test]
+    fn test_environment() {
+        let variables = environment(&parse("socks5h://127.0.0.1:9050").unwrap());
+        assert_eq!(
+            variables[0],
+            ("ALL_PROXY", "socks5://127.0.0.1:9050".into())
+        );
+        assert_eq!(
+            variables[1],
+            ("HTTPS_PROXY", "socks5h://127.0.0.1:9050".into())
+        );
+
+        let variables = environment(&parse("http://proxy.example.com:3128").unwrap());
+        assert_eq!(
+            variables[0],
+            ("ALL_PROXY", "http://proxy.example.com:3128/".into())
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! A minimal QR code encoder, used to show the login URL on machines without a browser so it can be
+//! opened on a phone instead.
+//!
+//! Only what that needs is supported: binary data in byte mode, with the lowest error correction
+//! level to keep the long login URLs at a size that fits into a terminal.
+
+/// Error correction codewords per block for the error correction level L, by version.
+const ECC_CODEWORDS_PER_BLOCK: [usize; 41] = [
+    0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30,
+    30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+];
+
+/// Error correction blocks for the error correction level L, by version.
+const ERROR_CORRECTION_BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14,
+    15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+];
+
+/// The format bits of the error correction level L.
+const ECC_LEVEL_BITS: u32 = 1;
+
+/// Light modules drawn around the code, so scanners can find its edges.
+const QUIET_ZONE: usize = 2;
+
+/// A QR code as a square grid of modules, where `true` is dark.
+pub struct QrCode {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    is_function: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    /// Encode `data` in the smallest version that fits it, or `None` if it is too long for any.
+    pub fn encode(data: &[u8]) -> Option<Self> {
+        let version = (1..=40).find(|&version| {
+            let count_bits = if version < 10 { 8 } else { 16 };
+            4 + count_bits + data.len() * 8 <= data_codewords(version) * 8
+        })?;
+        let mut qr = Self::new(version);
+        let codewords = add_error_correction(&data_codewords_for(data, version), version);
+        qr.draw_codewords(&codewords);
+
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                qr.apply_mask(mask);
+                qr.draw_format_bits(mask);
+                let penalty = qr.penalty();
+                qr.apply_mask(mask);
+                penalty
+            })
+            .unwrap_or(0);
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        Some(qr)
+    }
+
+    /// Render the code with Unicode half blocks, two rows of modules per line. Light modules are
+    /// drawn as blocks, which suits the usual light text on a dark terminal background.
+    pub fn render(&self) -> String {
+        let light = |x: usize, y: usize| {
+            x < QUIET_ZONE
+                || y < QUIET_ZONE
+                || x >= self.size + QUIET_ZONE
+                || y >= self.size + QUIET_ZONE
+                || !self.modules[y - QUIET_ZONE][x - QUIET_ZONE]
+        };
+        let width = self.size + 2 * QUIET_ZONE;
+        let mut output = String::new();
+        for y in (0..width).step_by(2) {
+            for x in 0..width {
+                let bottom = y + 1 < width && light(x, y + 1);
+                output.push(match (light(x, y), bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// An empty code of `version` with all function patterns drawn.
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        let mut qr = Self {
+            size,
+            modules: vec![vec![false; size]; size],
+            is_function: vec![vec![false; size]; size],
+        };
+
+        for i in 0..size {
+            qr.set_function(6, i, i % 2 == 0);
+            qr.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            qr.draw_pattern(x, y, 4, |distance| distance != 2 && distance != 4);
+        }
+        let positions = alignment_pattern_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                let overlaps_finder = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+                if !overlaps_finder {
+                    qr.draw_pattern(x, y, 2, |distance| distance != 1);
+                }
+            }
+        }
+        // Reserve the format areas, they are drawn once the mask is known.
+        qr.draw_format_bits(0);
+        qr.draw_version(version);
+        qr
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.is_function[y][x] = true;
+    }
+
+    /// Draw a square pattern of `radius` around `(x, y)`, with the modules at a distance from the
+    /// center for which `dark` is true being dark.
+    fn draw_pattern(&mut self, x: usize, y: usize, radius: isize, dark: impl Fn(isize) -> bool) {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (Some(xx), Some(yy)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if xx < self.size && yy < self.size {
+                    self.set_function(xx, yy, dark(dx.abs().max(dy.abs())));
+                }
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let bits = version_bits(version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place `codewords` in the zigzag order of the standard, skipping the function patterns.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..self.size {
+                for x in [right, right - 1] {
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        self.size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.is_function[y][x] && i < codewords.len() * 8 {
+                        self.modules[y][x] = (codewords[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 3 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// Invert the data modules selected by `mask`. Applying the same mask twice undoes it.
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                self.modules[y][x] ^= invert && !self.is_function[y][x];
+            }
+        }
+    }
+
+    /// The penalty score of the standard, used to pick the mask that is easiest to scan.
+    fn penalty(&self) -> usize {
+        let rows = (0..self.size).map(|y| self.modules[y].clone());
+        let columns = (0..self.size).map(|x| (0..self.size).map(|y| self.modules[y][x]).collect());
+        let lines: Vec<Vec<bool>> = rows.chain(columns).collect();
+
+        let mut penalty = 0;
+        for line in &lines {
+            for run in line.chunk_by(|a, b| a == b) {
+                if run.len() >= 5 {
+                    penalty += run.len() - 2;
+                }
+            }
+            let mut padded = vec![false; 4];
+            padded.extend(line);
+            padded.extend([false; 4]);
+            let finder = [true, false, true, true, true, false, true];
+            for window in padded.windows(11) {
+                let light = |range: std::ops::Range<usize>| window[range].iter().all(|m| !m);
+                if (window[..7] == finder && light(7..11)) || (light(0..4) && window[4..] == finder)
+                {
+                    penalty += 40;
+                }
+            }
+        }
+
+        for y in 0..self.size - 1 {
+            for x in 0..self.size - 1 {
+                let color = self.modules[y][x];
+                if color == self.modules[y][x + 1]
+                    && color == self.modules[y + 1][x]
+                    && color == self.modules[y + 1][x + 1]
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let total = self.size * self.size;
+        let dark = self.modules.iter().flatten().filter(|&&m| m).count();
+        let deviation = (dark * 20).abs_diff(total * 10);
+        penalty + deviation.div_ceil(total).saturating_sub(1) * 10
+    }
+}
+
+/// The codewords of a code of `version`, data and error correction.
+fn raw_codewords(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_codewords(version) - ECC_CODEWORDS_PER_BLOCK[version] * ERROR_CORRECTION_BLOCKS[version]
+}
+
+fn alignment_pattern_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// The 15 format bits for `mask`, with their BCH error correction.
+fn format_bits(mask: u32) -> u32 {
+    let data = (ECC_LEVEL_BITS << 3) | mask;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+/// The 18 version bits for `version`, with their BCH error correction.
+fn version_bits(version: usize) -> usize {
+    let mut remainder = version;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+    }
+    (version << 12) | remainder
+}
+
+/// Encode `data` as a single byte mode segment, padded to the capacity of `version`.
+fn data_codewords_for(data: &[u8], version: usize) -> Vec<u8> {
+    let mut bits = BitBuffer::default();
+    bits.append(0b0100, 4);
+    bits.append(data.len(), if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        bits.append(byte.into(), 8);
+    }
+
+    let capacity = data_codewords(version) * 8;
+    bits.append(0, (capacity - bits.0.len()).min(4));
+    bits.append(0, bits.0.len().wrapping_neg() % 8);
+
+    let mut codewords: Vec<u8> = bits
+        .0
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| (acc << 1) | u8::from(bit)))
+        .collect();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if codewords.len() * 8 >= capacity {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// Split `data` into the blocks of `version`, add the error correction to each and interleave them.
+fn add_error_correction(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = ERROR_CORRECTION_BLOCKS[version];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw = raw_codewords(version);
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut offset = 0;
+    let mut split = Vec::with_capacity(blocks);
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[offset..offset + len].to_vec();
+        offset += len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend(ecc);
+        split.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw);
+    for i in 0..=short_len {
+        for (j, block) in split.iter().enumerate() {
+            // Short blocks have a placeholder where the long blocks have their last data codeword.
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (remainder, &coefficient) in result.iter_mut().zip(divisor) {
+            *remainder ^= gf_multiply(coefficient, factor);
+        }
+    }
+    result
+}
+
+/// Multiply in the Galois field GF(2^8) with the polynomial of the standard.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1d);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+#[derive(Default)]
+struct BitBuffer(Vec<bool>);
+
+impl BitBuffer {
+    fn append(&mut self, value: usize, len: usize) {
+        self.0.extend((0..len).rev().map(|i| (value >> i) & 1 != 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(format_bits(0), 0b111011111000100);
+        assert_eq!(version_bits(7), 0x07c94);
+        assert_eq!(
+            alignment_pattern_positions(32),
+            vec![6, 34, 60, 86, 112, 138]
+        );
+
+        // Mode, length and data of "hello", followed by the terminator.
+        let data = data_codewords_for(b"hello", 1);
+        assert_eq!(&data[..7], &[0x40, 0x56, 0x86, 0x56, 0xc6, 0xc6, 0xf0]);
+        assert_eq!(data.len(), 19);
+
+        assert_eq!(QrCode::encode(&[b'a'; 17]).unwrap().size, 21);
+        assert_eq!(QrCode::encode(&[b'a'; 18]).unwrap().size, 25);
+        assert!(QrCode::encode(&[b'a'; 3000]).is_none());
+    }
+
+    #[test]
+    fn test_reed_solomon() {
+        // The data codewords of "HELLO WORLD" in version 1 with error correction level M, and
+        // their error correction codewords as worked out in the QR code tutorial of thonky.com.
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn test_golden_matrix() {
+        // Encoded with the reference implementation of Project Nayuki at error correction level
+        // L, without boosting the level. It picks version 2 and mask 4.
+        let expected = [
+            "#######.#.##..###.#######",
+            "#.....#.#.......#.#.....#",
+            "#.###.#.###...#.#.#.###.#",
+            "#.###.#.#..######.#.###.#",
+            "#.###.#..#.#..###.#.###.#",
+            "#.....#.###..#....#.....#",
+            "#######.#.#.#.#.#.#######",
+            ".........#....#..........",
+            "##..###..##.#.##...#.####",
+            "..###..#..##..##.#..##.#.",
+            "#.#.####.#.#.###.###.##..",
+            "#.#..#..###.#.##.#.#..##.",
+            ".#..####...##.#..###.####",
+            "##.###.....#...##...#..#.",
+            "....#.#....#######.####..",
+            "..#..#....###.##...##.##.",
+            "###.####.###...########..",
+            "........#.#####.#...#....",
+            "#######...####..#.#.#....",
+            "#.....#.##.##.#.#...####.",
+            "#.###.#.##..#...#######.#",
+            "#.###.#...##..#..###..#..",
+            "#.###.#..###.######..#.#.",
+            "#.....#.#####.#...######.",
+            "#######.#...#..#.##...###",
+        ];
+        let qr = QrCode::encode(b"https://example.com/?code=ncspot").unwrap();
+        let modules: Vec<String> = qr
+            .modules
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&dark| if dark { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(modules, expected);
+    }
+}
@@ -1,18 +1,33 @@
 use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::Utc;
 use log::{debug, info};
 #[cfg(feature = "notify")]
 use notify_rust::Notification;
 
 use rand::prelude::*;
+use rspotify::model::RecommendationsAttribute;
 use strum_macros::Display;
 
 use crate::config::Config;
+use crate::downloads;
+use crate::episode_progress;
+use crate::events::{Event, EventManager};
+use crate::journal;
 use crate::library::Library;
+use crate::local_files;
 use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::play_history;
+use crate::shuffle::{self, ShuffleMode};
 use crate::spotify::PlayerEvent;
 use crate::spotify::Spotify;
+use crate::spotify_api::SpotifyApi;
 use crate::traits::ListItem;
 
 /// Repeat behavior for the [Queue].
@@ -26,12 +41,80 @@ pub enum RepeatSetting {
     RepeatTrack,
 }
 
+/// A section of the playing item that is played over and over, from `start` to `end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbLoop {
+    pub uri: String,
+    pub start: Duration,
+    /// Not set until the second marker was placed.
+    pub end: Option<Duration>,
+}
+
+/// The shortest section that can be looped.
+const MIN_LOOP_LENGTH: Duration = Duration::from_secs(1);
+
+/// How long to wait after jumping back to the start of the loop before checking it again, while
+/// the player catches up with the new position.
+const LOOP_SEEK_GRACE: Duration = Duration::from_millis(500);
+
+/// Spotify accepts at most 5 seeds (artists and tracks combined) for recommendations.
+const MAX_RADIO_SEEDS: usize = 5;
+
+/// The amount of recommendations added to the queue when a radio is started or topped up.
+const RADIO_BATCH_SIZE: usize = 50;
+
+/// Top up the radio when fewer than this many items are left to play.
+const RADIO_LOW_WATERMARK: usize = 10;
+
+/// The plays of this many days are considered by the shuffle modes that use the play history.
+const PLAYS_CONSIDERED_DAYS: i64 = 365;
+
+/// The seeds used to request recommendations for a radio.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RadioSeed {
+    pub artist_ids: Vec<String>,
+    pub track_ids: Vec<String>,
+    /// Tunes the recommendations, i.e. to stay within a tempo range.
+    pub attributes: Vec<RecommendationsAttribute>,
+}
+
+impl RadioSeed {
+    /// Limit the seeds to what Spotify accepts, preferring tracks over artists.
+    fn limited(&self) -> Self {
+        let track_ids: Vec<String> = self
+            .track_ids
+            .iter()
+            .take(MAX_RADIO_SEEDS)
+            .cloned()
+            .collect();
+        let artist_ids = self
+            .artist_ids
+            .iter()
+            .take(MAX_RADIO_SEEDS - track_ids.len())
+            .cloned()
+            .collect();
+        Self {
+            artist_ids,
+            track_ids,
+            attributes: self.attributes.clone(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.artist_ids.is_empty() && self.track_ids.is_empty()
+    }
+}
+
 /// Events that are specific to the [Queue].
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub enum QueueEvent {
     /// Request the player to 'preload' a track, basically making sure that
     /// transitions between tracks can be uninterrupted.
     PreloadTrackRequest,
+    /// More recommendations were fetched for the running radio.
+    RadioTracks(Vec<Playable>),
+    /// Recommendations to continue with after the queue ran out, and the seed they are based on.
+    AutoplayTracks(RadioSeed, Vec<Playable>),
 }
 
 /// The queue determines the playback order of [Playable] items, and is also used to control
@@ -46,11 +129,31 @@ pub struct Queue {
     spotify: Spotify,
     cfg: Arc<Config>,
     library: Arc<Library>,
+    events: EventManager,
+    /// The seed of the running radio, if any. The queue is topped up with
+    /// recommendations while this is set.
+    radio: RwLock<Option<RadioSeed>>,
+    /// Whether recommendations for the radio are currently being fetched.
+    radio_pending: Arc<AtomicBool>,
+    /// The A-B loop in the playing item, if any.
+    ab_loop: RwLock<Option<AbLoop>>,
+    /// When playback last jumped back to the start of the A-B loop.
+    loop_seeked: RwLock<Option<Instant>>,
 }
 
 impl Queue {
-    pub fn new(spotify: Spotify, cfg: Arc<Config>, library: Arc<Library>) -> Self {
+    pub fn new(
+        spotify: Spotify,
+        cfg: Arc<Config>,
+        library: Arc<Library>,
+        events: EventManager,
+    ) -> Self {
         let queue_state = cfg.state().queuestate.clone();
+        journal::record(format_args!(
+            "queue restored with {} items, current {:?}",
+            queue_state.queue.len(),
+            queue_state.current_track
+        ));
 
         Self {
             queue: Arc::new(RwLock::new(queue_state.queue)),
@@ -59,6 +162,11 @@ impl Queue {
             random_order: RwLock::new(queue_state.random_order),
             cfg,
             library,
+            events,
+            radio: RwLock::new(None),
+            radio_pending: Arc::new(AtomicBool::new(false)),
+            ab_loop: RwLock::new(None),
+            loop_seeked: RwLock::new(None),
         }
     }
 
@@ -87,6 +195,24 @@ impl Queue {
         }
     }
 
+    /// The index of the item in `self.queue` that plays when the current one ends, taking the
+    /// repeat setting into account. None if playback stops then.
+    fn following_index(&self) -> Option<usize> {
+        match self.get_repeat() {
+            RepeatSetting::RepeatTrack => self.get_current_index(),
+            RepeatSetting::RepeatPlaylist => self.next_index().or_else(|| {
+                if self.len() == 0 {
+                    return None;
+                }
+                match self.random_order.read().unwrap().as_ref() {
+                    Some(order) => order.first().copied(),
+                    None => Some(0),
+                }
+            }),
+            RepeatSetting::None => self.next_index(),
+        }
+    }
+
     /// The index of the previous item in `self.queue` that should be played.
     /// None if at the start of the queue.
     pub fn previous_index(&self) -> Option<usize> {
@@ -127,6 +253,7 @@ impl Queue {
     /// playing item, taking into account shuffle status.
     pub fn insert_after_current(&self, track: Playable) {
         if let Some(index) = self.get_current_index() {
+            journal::record(format_args!("insert {track} at {}", index + 1));
             let mut random_order = self.random_order.write().unwrap();
             if let Some(order) = random_order.as_mut() {
                 let next_i = order.iter().position(|&i| i == index).unwrap();
@@ -141,6 +268,7 @@ impl Queue {
             }
             let mut q = self.queue.write().unwrap();
             q.insert(index + 1, track);
+            self.changed();
         } else {
             self.append(track);
         }
@@ -148,6 +276,7 @@ impl Queue {
 
     /// Add `track` to the end of the queue.
     pub fn append(&self, track: Playable) {
+        journal::record(format_args!("append {track}"));
         let mut random_order = self.random_order.write().unwrap();
         if let Some(order) = random_order.as_mut() {
             let index = order.len().saturating_sub(1);
@@ -156,12 +285,18 @@ impl Queue {
 
         let mut q = self.queue.write().unwrap();
         q.push(track);
+        self.changed();
     }
 
     /// Append `tracks` after the currently playing item, taking into account
     /// shuffle status. Returns the first index(in `self.queue`) of added items.
     pub fn append_next(&self, tracks: &Vec<Playable>) -> usize {
         let mut q = self.queue.write().unwrap();
+        journal::record(format_args!(
+            "append {} items after current {:?}",
+            tracks.len(),
+            self.get_current_index()
+        ));
 
         {
             let mut random_order = self.random_order.write().unwrap();
@@ -180,13 +315,40 @@ impl Queue {
             q.insert(i, track.clone());
             i += 1;
         }
+        self.changed();
 
         first
     }
 
+    /// Insert `tracks` at `index` in `self.queue`.
+    pub fn insert(&self, index: usize, tracks: &[Playable]) {
+        journal::record(format_args!("insert {} items at {index}", tracks.len()));
+        let mut q = self.queue.write().unwrap();
+        let index = index.min(q.len());
+        q.splice(index..index, tracks.iter().cloned());
+
+        let mut current = self.current_track.write().unwrap();
+        if let Some(current) = current.as_mut()
+            && *current >= index
+        {
+            *current += tracks.len();
+        }
+        let mut random_order = self.random_order.write().unwrap();
+        if let Some(order) = random_order.as_mut() {
+            for item in order.iter_mut() {
+                if *item >= index {
+                    *item += tracks.len();
+                }
+            }
+            order.extend(index..index + tracks.len());
+        }
+        self.changed();
+    }
+
     /// Remove the item at `index`. This doesn't take into account shuffle
     /// status, and will literally remove the item at `index` in `self.queue`.
     pub fn remove(&self, index: usize) {
+        journal::record(format_args!("remove {index}"));
         {
             let mut q = self.queue.write().unwrap();
             if q.is_empty() {
@@ -195,6 +357,7 @@ impl Queue {
             }
             q.remove(index);
         }
+        self.changed();
 
         // if the queue is empty stop playback
         let len = self.queue.read().unwrap().len();
@@ -235,9 +398,12 @@ impl Queue {
         }
     }
 
-    /// Clear all the items from the queue and stop playback.
+    /// Clear all the items from the queue and stop playback. This also stops
+    /// a running radio.
     pub fn clear(&self) {
+        journal::record(format_args!("clear {} items", self.len()));
         self.stop();
+        self.radio.write().unwrap().take();
 
         let mut q = self.queue.write().unwrap();
         q.clear();
@@ -246,6 +412,27 @@ impl Queue {
         if let Some(o) = random_order.as_mut() {
             o.clear()
         }
+        self.changed();
+    }
+
+    /// Replace the items in the queue with `tracks` and load the first one. Playback is started if
+    /// `play` is `true`.
+    pub fn replace(&self, tracks: Vec<Playable>, play: bool) {
+        self.clear();
+        journal::record(format_args!("replace with {} items", tracks.len()));
+        *self.queue.write().unwrap() = tracks;
+        self.changed();
+
+        if play {
+            self.play(0, true, false);
+        } else if let Some(track) = self.queue.read().unwrap().first() {
+            self.spotify.load(track, false, 0);
+            self.current_track.write().unwrap().replace(0);
+            self.spotify.update_track();
+            if self.get_shuffle() {
+                self.generate_random_order();
+            }
+        }
     }
 
     /// The amount of items in `self.queue`.
@@ -255,6 +442,7 @@ impl Queue {
 
     /// Shift the item at `from` in `self.queue` to `to`.
     pub fn shift(&self, from: usize, to: usize) {
+        journal::record(format_args!("shift {from} to {to}"));
         let mut queue = self.queue.write().unwrap();
         let item = queue.remove(from);
         queue.insert(to, item);
@@ -271,6 +459,12 @@ impl Queue {
                 current.replace(to - 1);
             }
         }
+        self.changed();
+    }
+
+    /// Tell the IPC and MPRIS clients about changes to the items or options of the queue.
+    fn changed(&self) {
+        self.spotify.notify_queue_changed();
     }
 
     /// Play the item at `index` in `self.queue`.
@@ -287,7 +481,17 @@ impl Queue {
         }
 
         if let Some(track) = &self.queue.read().unwrap().get(index) {
-            self.spotify.load(track, true, 0);
+            journal::record(format_args!("play {index}: {track}"));
+            let uri = track.uri();
+            self.ab_loop.write().unwrap().take_if(|ab| ab.uri != uri);
+            let position = match track {
+                Playable::Episode(episode) => {
+                    episode_progress::of(self.cfg.storage().as_ref(), episode)
+                        .map_or(0, |progress| progress.resume_position(episode.duration))
+                }
+                Playable::Track(_) => 0,
+            };
+            self.spotify.load(track, true, position);
             let mut current = self.current_track.write().unwrap();
             current.replace(index);
             self.spotify.update_track();
@@ -323,6 +527,274 @@ impl Queue {
         if reshuffle && self.get_shuffle() {
             self.generate_random_order()
         }
+
+        self.top_up_radio();
+    }
+
+    /// Replace the queue with recommendations based on `seed` and start
+    /// playing them. The queue is topped up with more recommendations as it
+    /// drains, until it is cleared. Returns the amount of queued items.
+    pub fn start_radio(&self, seed: RadioSeed) -> Result<usize, String> {
+        let seed = seed.limited();
+        if seed.is_empty() {
+            return Err("Nothing to start a radio from".to_string());
+        }
+
+        let tracks = Self::fetch_radio_tracks(&self.spotify.api, &seed, &HashSet::new())
+            .ok_or("Could not fetch recommendations")?;
+        if tracks.is_empty() {
+            return Err("No recommendations found".to_string());
+        }
+
+        self.clear();
+        let count = tracks.len();
+        for track in tracks {
+            self.append(track);
+        }
+        journal::record("radio started");
+        *self.radio.write().unwrap() = Some(seed);
+        self.play(0, true, false);
+        Ok(count)
+    }
+
+    /// Fetch recommendations for `seed` without touching the queue.
+    pub fn fetch_recommendations(&self, seed: &RadioSeed) -> Option<Vec<Playable>> {
+        let seed = seed.limited();
+        if seed.is_empty() {
+            return None;
+        }
+        Self::fetch_radio_tracks(&self.spotify.api, &seed, &HashSet::new())
+    }
+
+    /// Whether a radio is running.
+    pub fn is_radio(&self) -> bool {
+        self.radio.read().unwrap().is_some()
+    }
+
+    /// The A-B loop in the playing item, if any.
+    pub fn get_ab_loop(&self) -> Option<AbLoop> {
+        let current = self.get_current()?.uri();
+        self.ab_loop
+            .read()
+            .unwrap()
+            .clone()
+            .filter(|ab| ab.uri == current)
+    }
+
+    /// Place the start of the A-B loop at the current position, removing its end.
+    pub fn set_loop_start(&self) -> Result<Duration, String> {
+        let current = self.get_current().ok_or("Nothing is playing")?;
+        let start = self.spotify.get_current_progress();
+        *self.ab_loop.write().unwrap() = Some(AbLoop {
+            uri: current.uri(),
+            start,
+            end: None,
+        });
+        self.changed();
+        Ok(start)
+    }
+
+    /// Place the end of the A-B loop at the current position. Playback then jumps back to its
+    /// start whenever it gets there.
+    pub fn set_loop_end(&self) -> Result<Duration, String> {
+        let mut ab_loop = self
+            .get_ab_loop()
+            .ok_or("Set the start of the loop first")?;
+        let end = self.spotify.get_current_progress();
+        if end < ab_loop.start + MIN_LOOP_LENGTH {
+            return Err("The end of the loop has to be after its start".to_string());
+        }
+        ab_loop.end = Some(end);
+        *self.ab_loop.write().unwrap() = Some(ab_loop.clone());
+        self.changed();
+        self.spotify.seek(ab_loop.start.as_millis() as u32);
+        *self.loop_seeked.write().unwrap() = Some(Instant::now());
+        Ok(end)
+    }
+
+    pub fn clear_ab_loop(&self) {
+        if self.ab_loop.write().unwrap().take().is_some() {
+            self.changed();
+        }
+    }
+
+    /// Jump back to the start of the A-B loop once playback reached its end. Called regularly
+    /// from the main loop.
+    pub fn check_ab_loop(&self) {
+        let Some(AbLoop {
+            start,
+            end: Some(end),
+            ..
+        }) = self.get_ab_loop()
+        else {
+            return;
+        };
+        let seeked = *self.loop_seeked.read().unwrap();
+        if seeked.is_some_and(|seeked| seeked.elapsed() < LOOP_SEEK_GRACE)
+            || !matches!(self.spotify.get_current_status(), PlayerEvent::Playing(_))
+            || self.spotify.get_current_progress() < end
+        {
+            return;
+        }
+        debug!("Looping back to {start:?}");
+        self.spotify.seek(start.as_millis() as u32);
+        *self.loop_seeked.write().unwrap() = Some(Instant::now());
+    }
+
+    /// Whether the queue is continued with recommendations when it runs out.
+    pub fn get_autoplay(&self) -> bool {
+        self.cfg.state().autoplay
+    }
+
+    pub fn set_autoplay(&self, new: bool) {
+        journal::record(format_args!("autoplay {new}"));
+        self.cfg.with_state_mut(|s| s.autoplay = new);
+        self.changed();
+    }
+
+    /// Continue playback with recommendations based on the last played tracks once the queue ran
+    /// out, if autoplay is enabled. Returns whether recommendations are being fetched. From then
+    /// on the queue is topped up like a radio.
+    pub fn autoplay(&self) -> bool {
+        if !self.get_autoplay()
+            || self.is_radio()
+            || self.radio_pending.swap(true, AtomicOrdering::SeqCst)
+        {
+            return false;
+        }
+
+        let (track_ids, known_ids) = {
+            let queue = self.queue.read().unwrap();
+            let known_ids: HashSet<String> = queue.iter().filter_map(|p| p.id()).collect();
+            let order: Vec<usize> = self
+                .get_random_order()
+                .unwrap_or_else(|| (0..queue.len()).collect());
+            let played = order
+                .iter()
+                .position(|&i| Some(i) == self.get_current_index())
+                .map_or(order.len(), |position| position + 1);
+            let track_ids: Vec<String> = order[..played]
+                .iter()
+                .rev()
+                .filter_map(|&i| match &queue[i] {
+                    Playable::Track(track) => track.id.clone(),
+                    Playable::Episode(_) => None,
+                })
+                .take(MAX_RADIO_SEEDS)
+                .collect();
+            (track_ids, known_ids)
+        };
+        if track_ids.is_empty() {
+            self.radio_pending.store(false, AtomicOrdering::SeqCst);
+            return false;
+        }
+        let seed = RadioSeed {
+            track_ids,
+            ..Default::default()
+        };
+
+        let spotify = self.spotify.clone();
+        let events = self.events.clone();
+        let pending = self.radio_pending.clone();
+        thread::spawn(
+            move || match Self::fetch_radio_tracks(&spotify.api, &seed, &known_ids) {
+                Some(tracks) if !tracks.is_empty() => {
+                    events.send(Event::Queue(QueueEvent::AutoplayTracks(seed, tracks)))
+                }
+                _ => pending.store(false, AtomicOrdering::SeqCst),
+            },
+        );
+        true
+    }
+
+    /// Fetch more recommendations in the background if a radio is running and
+    /// the queue is about to run out of items.
+    fn top_up_radio(&self) {
+        if !self.is_radio() {
+            return;
+        }
+
+        let remaining = match *self.current_track.read().unwrap() {
+            Some(current) => {
+                let position = match self.random_order.read().unwrap().as_ref() {
+                    Some(order) => order.iter().position(|&i| i == current).unwrap_or(0),
+                    None => current,
+                };
+                self.len().saturating_sub(position + 1)
+            }
+            None => self.len(),
+        };
+        if remaining >= RADIO_LOW_WATERMARK || self.radio_pending.swap(true, AtomicOrdering::SeqCst)
+        {
+            return;
+        }
+
+        // Seed with the most recently queued tracks so the radio keeps drifting
+        // along with what is being played.
+        let (track_ids, known_ids) = {
+            let queue = self.queue.read().unwrap();
+            let known_ids: HashSet<String> = queue.iter().filter_map(|p| p.id()).collect();
+            let track_ids: Vec<String> = queue
+                .iter()
+                .rev()
+                .filter_map(|p| match p {
+                    Playable::Track(track) => track.id.clone(),
+                    Playable::Episode(_) => None,
+                })
+                .take(MAX_RADIO_SEEDS)
+                .collect();
+            (track_ids, known_ids)
+        };
+        let seed = {
+            let radio = self.radio.read().unwrap().clone().unwrap_or_default();
+            if track_ids.is_empty() {
+                radio
+            } else {
+                RadioSeed {
+                    artist_ids: Vec::new(),
+                    track_ids,
+                    attributes: radio.attributes,
+                }
+            }
+        };
+
+        let spotify = self.spotify.clone();
+        let events = self.events.clone();
+        let pending = self.radio_pending.clone();
+        thread::spawn(
+            move || match Self::fetch_radio_tracks(&spotify.api, &seed, &known_ids) {
+                Some(tracks) => events.send(Event::Queue(QueueEvent::RadioTracks(tracks))),
+                None => pending.store(false, AtomicOrdering::SeqCst),
+            },
+        );
+    }
+
+    /// Request recommendations for `seed`, skipping items whose id is in `known_ids`.
+    fn fetch_radio_tracks(
+        api: &dyn SpotifyApi,
+        seed: &RadioSeed,
+        known_ids: &HashSet<String>,
+    ) -> Option<Vec<Playable>> {
+        let artist_ids: Vec<&str> = seed.artist_ids.iter().map(String::as_str).collect();
+        let track_ids: Vec<&str> = seed.track_ids.iter().map(String::as_str).collect();
+        let recommendations = api
+            .recommendations(
+                (!artist_ids.is_empty()).then_some(artist_ids),
+                None,
+                (!track_ids.is_empty()).then_some(track_ids),
+                &seed.attributes,
+            )
+            .ok()?;
+
+        Some(
+            recommendations
+                .tracks
+                .iter()
+                .map(|t| Playable::Track(Track::from(t)))
+                .filter(|p| p.id().is_none_or(|id| !known_ids.contains(&id)))
+                .take(RADIO_BATCH_SIZE)
+                .collect(),
+        )
     }
 
     /// Toggle the playback. If playback is currently stopped, this will either
@@ -333,7 +805,9 @@ impl Queue {
                 self.spotify.toggleplayback();
             }
             PlayerEvent::Stopped => match self.next_index() {
-                Some(_) => self.next(false),
+                Some(_) => {
+                    self.next(false);
+                }
                 None => self.play(0, false, false),
             },
             _ => (),
@@ -342,6 +816,7 @@ impl Queue {
 
     /// Stop playback.
     pub fn stop(&self) {
+        journal::record("stop");
         let mut current = self.current_track.write().unwrap();
         *current = None;
         self.spotify.stop();
@@ -353,7 +828,9 @@ impl Queue {
     /// `manual`: If this is true, normal queue logic like repeat will not be
     /// used, and the next track will actually be played. This should be used
     /// when going to the next entry in the queue is the wanted behavior.
-    pub fn next(&self, manual: bool) {
+    ///
+    /// Returns `false` if playback stopped because the queue ran out.
+    pub fn next(&self, manual: bool) -> bool {
         let q = self.queue.read().unwrap();
         let current = *self.current_track.read().unwrap();
         let repeat = self.cfg.state().repeat;
@@ -365,6 +842,7 @@ impl Queue {
                 self.play(index, false, false);
             }
         } else if let Some(index) = self.next_index() {
+            let index = self.skip_to_downloaded(&q, index);
             self.play(index, false, false);
             if repeat == RepeatSetting::RepeatTrack && manual {
                 self.set_repeat(RepeatSetting::RepeatPlaylist);
@@ -373,15 +851,47 @@ impl Queue {
             && !q.is_empty()
             && q.iter().any(|track| track.is_playable())
         {
-            let random_order = self.random_order.read().unwrap();
-            self.play(
-                random_order.as_ref().map(|o| o[0]).unwrap_or(0),
-                false,
-                false,
-            );
+            drop(q);
+            if self.get_shuffle() && self.get_shuffle_mode() == ShuffleMode::Bag {
+                // Start over with a new order, which doesn't repeat the last played item first.
+                let mut order = self.shuffled_order(None);
+                if order.len() > 1 && order.first() == current.as_ref() {
+                    let last = order.len() - 1;
+                    order.swap(0, last);
+                }
+                *self.random_order.write().unwrap() = Some(order);
+            }
+            let first = self.random_order.read().unwrap().as_ref().map(|o| o[0]);
+            self.play(first.unwrap_or(0), false, false);
         } else {
             self.spotify.stop();
+            return false;
         }
+        true
+    }
+
+    /// While offline, the first item from `index` on in play order that was downloaded, as others
+    /// can't play. `index` otherwise, or if none was downloaded.
+    fn skip_to_downloaded(&self, q: &[Playable], index: usize) -> usize {
+        if !self.spotify.api.is_offline() {
+            return index;
+        }
+        let order = self
+            .random_order
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| (0..q.len()).collect());
+        let start = order.iter().position(|&i| i == index).unwrap_or(0);
+        let storage = self.cfg.storage();
+        order[start..]
+            .iter()
+            .copied()
+            .find(|&i| {
+                let uri = q[i].uri();
+                local_files::is_available(&uri) || downloads::is_downloaded(storage.as_ref(), &uri)
+            })
+            .unwrap_or(index)
     }
 
     /// Play the previous item in the queue.
@@ -415,7 +925,9 @@ impl Queue {
 
     /// Set the current repeat behavior and save it to the configuration.
     pub fn set_repeat(&self, new: RepeatSetting) {
+        journal::record(format_args!("repeat {new:?}"));
         self.cfg.with_state_mut(|s| s.repeat = new);
+        self.changed();
     }
 
     /// Get the current shuffle behavior.
@@ -423,6 +935,26 @@ impl Queue {
         self.cfg.state().shuffle
     }
 
+    /// Write the queue, the playing item and its progress to the user state on disk, so they are
+    /// restored in the next session.
+    pub fn save_state(&self) {
+        let queue = self.queue.read().unwrap();
+        let progress = self.spotify.get_current_progress();
+        self.cfg.with_state_mut(|s| {
+            debug!(
+                "saving state, {} items, current track: {:?}",
+                queue.len(),
+                self.get_current_index()
+            );
+            s.queuestate.queue.clone_from(&queue);
+            s.queuestate.random_order = self.get_random_order();
+            s.queuestate.current_track = self.get_current_index();
+            s.queuestate.track_progress = progress;
+        });
+        drop(queue);
+        self.cfg.save_state();
+    }
+
     /// Get the current order that is used to shuffle.
     pub fn get_random_order(&self) -> Option<Vec<usize>> {
         self.random_order.read().unwrap().clone()
@@ -430,25 +962,57 @@ impl Queue {
 
     /// (Re)generate the random shuffle order.
     fn generate_random_order(&self) {
+        let current = *self.current_track.read().unwrap();
+        let order = self.shuffled_order(current);
+        let mut random_order = self.random_order.write().unwrap();
+        *random_order = Some(order);
+    }
+
+    /// A shuffled order of the queue according to the shuffle mode, starting with `first`.
+    fn shuffled_order(&self, first: Option<usize>) -> Vec<usize> {
+        let mode = self.get_shuffle_mode();
+        let plays = if mode.uses_history() {
+            let since = Utc::now() - chrono::Duration::days(PLAYS_CONSIDERED_DAYS);
+            play_history::load(self.cfg.storage().as_ref(), since.timestamp())
+        } else {
+            Vec::new()
+        };
+
         let q = self.queue.read().unwrap();
         let mut order: Vec<usize> = Vec::with_capacity(q.len());
         let mut random: Vec<usize> = (0..q.len()).collect();
 
-        if let Some(current) = *self.current_track.read().unwrap() {
-            order.push(current);
-            random.remove(current);
+        if let Some(first) = first {
+            order.push(first);
+            random.remove(first);
         }
 
         let mut rng = rand::rng();
-        random.shuffle(&mut rng);
-        order.extend(random);
+        let now = Utc::now().timestamp();
+        order.extend(shuffle::shuffle(mode, random, &q, &plays, now, &mut rng));
+        if mode == ShuffleMode::Spread {
+            order = shuffle::spread(order, &q);
+        }
+        order
+    }
 
-        let mut random_order = self.random_order.write().unwrap();
-        *random_order = Some(order);
+    pub fn get_shuffle_mode(&self) -> ShuffleMode {
+        self.cfg.state().shuffle_mode
+    }
+
+    /// Set how the queue is shuffled, and shuffle it again if shuffle is enabled.
+    pub fn set_shuffle_mode(&self, mode: ShuffleMode) {
+        journal::record(format_args!("shuffle mode {mode}"));
+        self.cfg.with_state_mut(|s| s.shuffle_mode = mode);
+        if self.get_shuffle() {
+            self.generate_random_order();
+        }
+        self.changed();
     }
 
     /// Set the current shuffle behavior.
     pub fn set_shuffle(&self, new: bool) {
+        journal::record(format_args!("shuffle {new}"));
         self.cfg.with_state_mut(|s| s.shuffle = new);
         if new {
             self.generate_random_order();
@@ -456,18 +1020,72 @@ impl Queue {
             let mut random_order = self.random_order.write().unwrap();
             *random_order = None;
         }
+        self.changed();
     }
 
     /// Handle events that are specific to the queue.
     pub fn handle_event(&self, event: QueueEvent) {
         match event {
             QueueEvent::PreloadTrackRequest => {
-                if let Some(next_index) = self.next_index() {
+                if !self.cfg.values().gapless.unwrap_or(true) {
+                    return;
+                }
+                if let Some(next_index) = self.following_index() {
                     let track = self.queue.read().unwrap()[next_index].clone();
                     debug!("Preloading track {track} as requested by librespot");
                     self.spotify.preload(&track);
                 }
             }
+            QueueEvent::RadioTracks(tracks) => {
+                self.radio_pending.store(false, AtomicOrdering::SeqCst);
+                // The radio might have been stopped while the tracks were fetched.
+                if !self.is_radio() {
+                    return;
+                }
+                let known_ids: HashSet<String> = self
+                    .queue
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|p| p.id())
+                    .collect();
+                let tracks: Vec<Playable> = tracks
+                    .into_iter()
+                    .filter(|p| p.id().is_none_or(|id| !known_ids.contains(&id)))
+                    .collect();
+                debug!("Adding {} tracks to the radio", tracks.len());
+                for track in tracks {
+                    self.append(track);
+                }
+                if self.get_shuffle() {
+                    self.generate_random_order();
+                }
+            }
+            QueueEvent::AutoplayTracks(seed, tracks) => {
+                self.radio_pending.store(false, AtomicOrdering::SeqCst);
+                // Autoplay might have been disabled, or something else started playing, while the
+                // tracks were fetched.
+                if !self.get_autoplay()
+                    || self.is_radio()
+                    || !matches!(
+                        self.spotify.get_current_status(),
+                        PlayerEvent::Stopped | PlayerEvent::FinishedTrack
+                    )
+                {
+                    return;
+                }
+                debug!("Continuing with {} recommended tracks", tracks.len());
+                journal::record("autoplay started");
+                let first = self.len();
+                for track in tracks {
+                    self.append(track);
+                }
+                *self.radio.write().unwrap() = Some(seed);
+                if self.get_shuffle() {
+                    self.generate_random_order();
+                }
+                self.play(first, false, false);
+            }
         }
     }
 
@@ -515,3 +1133,27 @@ pub fn send_notification(summary_txt: &str, body_txt: &str, cover_url: Option<St
         Err(e) => log::error!("Failed to send notification cover: {e}"),
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock_api::MockApi;
+
+    #[test]
+    fn test_fetch_radio_tracks() {
+        let api = MockApi::with_fixtures();
+        let seed = RadioSeed {
+            track_ids: vec!["4uLU6hMCjMI75M1A2tKUQC".into()],
+            ..Default::default()
+        };
+        let known_ids = HashSet::from(["4uLU6hMCjMI75M1A2tKUQC".to_string()]);
+
+        let tracks = Queue::fetch_radio_tracks(&api, &seed, &known_ids).unwrap();
+        let ids: Vec<String> = tracks.iter().filter_map(Playable::id).collect();
+        assert_eq!(
+            ids,
+            vec!["2takcwOaAZWiXQijPHIx7B", "6rqhFgbbKwnb9MLmUQDhG6"]
+        );
+        assert_eq!(api.requests(), vec!["recommendations"]);
+    }
+}
@@ -0,0 +1,72 @@
+//! Saving the queue while ncspot runs, and not only when it quits, so a crash or a closed terminal
+//! doesn't lose it.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::queue::Queue;
+
+/// The default interval between saves, in seconds.
+const DEFAULT_INTERVAL: u64 = 60;
+
+pub struct QueueAutosave {
+    queue: Arc<Queue>,
+    cfg: Arc<Config>,
+    next_save: Instant,
+    /// A hash of the queue when it was last saved.
+    saved: Option<u64>,
+}
+
+impl QueueAutosave {
+    pub fn new(queue: Arc<Queue>, cfg: Arc<Config>) -> Self {
+        Self {
+            queue,
+            cfg,
+            next_save: Instant::now() + Duration::from_secs(DEFAULT_INTERVAL),
+            saved: None,
+        }
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        match self.cfg.values().queue_autosave.unwrap_or(DEFAULT_INTERVAL) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    /// A hash of everything about the queue that is saved, the progress in whole seconds.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for playable in self.queue.queue.read().unwrap().iter() {
+            playable.uri().hash(&mut hasher);
+        }
+        self.queue.get_current_index().hash(&mut hasher);
+        self.queue.get_random_order().hash(&mut hasher);
+        self.queue
+            .get_spotify()
+            .get_current_progress()
+            .as_secs()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Save the queue if the interval passed and it changed since it was last saved.
+    pub fn tick(&mut self) {
+        let Some(interval) = self.interval() else {
+            return;
+        };
+        if Instant::now() < self.next_save {
+            return;
+        }
+        self.next_save = Instant::now() + interval;
+
+        let fingerprint = self.fingerprint();
+        if self.saved != Some(fingerprint) {
+            self.queue.save_state();
+            self.saved = Some(fingerprint);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Filling the queue from a configurable source when ncspot starts, so playback can begin with a
+//! single keypress.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use log::{info, warn};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::queue::Queue;
+use crate::spotify::UriType;
+use crate::spotify_api::SpotifyApi;
+use crate::spotify_url::SpotifyUrl;
+
+/// What the queue is filled with on startup.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum QueueSource {
+    /// The queue of the last session.
+    #[default]
+    Resume,
+    /// Nothing.
+    Empty,
+    /// The saved tracks of the user in random order.
+    LikedSongs,
+    /// A playlist, given by its name in the library, its URI or its URL.
+    Playlist(String),
+    /// The Daily Mix with the given number. It has to be saved in the library.
+    DailyMix(u32),
+}
+
+impl FromStr for QueueSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, argument) = match s.split_once(':') {
+            Some((kind, argument)) => (kind, Some(argument.trim())),
+            None => (s, None),
+        };
+        match (kind.trim(), argument) {
+            ("resume", None) => Ok(Self::Resume),
+            ("empty", None) => Ok(Self::Empty),
+            ("liked", None) => Ok(Self::LikedSongs),
+            ("playlist", Some(playlist)) if !playlist.is_empty() => {
+                Ok(Self::Playlist(playlist.to_string()))
+            }
+            ("daily_mix", None) => Ok(Self::DailyMix(1)),
+            ("daily_mix", Some(number)) => number
+                .parse()
+                .map(Self::DailyMix)
+                .map_err(|_| format!("invalid Daily Mix number: {number}")),
+            _ => Err(format!("unknown queue source: {s}")),
+        }
+    }
+}
+
+impl TryFrom<String> for QueueSource {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for QueueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Resume => write!(f, "resume"),
+            Self::Empty => write!(f, "empty"),
+            Self::LikedSongs => write!(f, "liked"),
+            Self::Playlist(playlist) => write!(f, "playlist:{playlist}"),
+            Self::DailyMix(number) => write!(f, "daily_mix:{number}"),
+        }
+    }
+}
+
+impl From<QueueSource> for String {
+    fn from(source: QueueSource) -> Self {
+        source.to_string()
+    }
+}
+
+/// Fills the queue from the configured source once the library has been loaded for the first
+/// time.
+pub struct StartupQueue {
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    /// The source that still has to be loaded, `None` once the queue was filled.
+    source: Option<QueueSource>,
+    play: bool,
+}
+
+impl StartupQueue {
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, source: QueueSource, play: bool) -> Self {
+        let source = match source {
+            QueueSource::Resume => None,
+            QueueSource::Empty => {
+                queue.clear();
+                None
+            }
+            source => Some(source),
+        };
+        Self {
+            queue,
+            library,
+            source,
+            play,
+        }
+    }
+
+    /// Handle the library being loaded. Only the first call fills the queue, so refreshing the
+    /// library later doesn't replace it.
+    pub fn library_loaded(&mut self) {
+        if let Some(source) = self.source.take() {
+            populate(self.queue.clone(), self.library.clone(), source, self.play);
+        }
+    }
+}
+
+/// Replace the queue with the tracks of `source`, and start playing them if `play` is `true`.
+/// Playlists and saved tracks are looked up in the library, so it has to be loaded.
+fn populate(queue: Arc<Queue>, library: Arc<Library>, source: QueueSource, play: bool) {
+    queue.get_spotify().api.spawn(move |_| {
+        let mut tracks = tracks(&queue, &library, &source);
+        if tracks.is_empty() {
+            warn!("queue source {source:?} has no tracks");
+            return;
+        }
+        if source == QueueSource::LikedSongs {
+            tracks.shuffle(&mut rand::rng());
+        }
+
+        info!("filling queue with {} tracks of {source:?}", tracks.len());
+        queue.replace(tracks, play);
+        library.trigger_redraw();
+    });
+}
+
+fn tracks(queue: &Queue, library: &Library, source: &QueueSource) -> Vec<Playable> {
+    let playlist = match source {
+        QueueSource::LikedSongs => {
+            return library
+                .tracks
+                .read()
+                .unwrap()
+                .iter()
+                .map(|track| Playable::Track(track.clone()))
+                .collect();
+        }
+        QueueSource::Playlist(playlist) => {
+            find_playlist(&queue.get_spotify().api, library, playlist)
+        }
+        QueueSource::DailyMix(number) => {
+            let name = format!("Daily Mix {number}");
+            library
+                .playlists
+                .read()
+                .unwrap()
+                .iter()
+                .find(|playlist| playlist.name == name)
+                .cloned()
+        }
+        QueueSource::Resume | QueueSource::Empty => None,
+    };
+
+    playlist
+        .map(|mut playlist| {
+            playlist.load_tracks(&queue.get_spotify());
+            playlist.tracks.unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Find the playlist with the name, URI or URL `playlist` in the library, or fetch it if it isn't
+/// saved there.
+fn find_playlist(api: &dyn SpotifyApi, library: &Library, playlist: &str) -> Option<Playlist> {
+    let id = SpotifyUrl::from_url(playlist)
+        .or_else(|| SpotifyUrl::from_uri(playlist))
+        .filter(|url| url.uri_type == UriType::Playlist)
+        .map(|url| url.id);
+
+    let saved = library
+        .playlists
+        .read()
+        .unwrap()
+        .iter()
+        .find(|saved| match &id {
+            Some(id) => &saved.id == id,
+            None => saved.name.eq_ignore_ascii_case(playlist),
+        })
+        .cloned();
+    saved.or_else(|| {
+        let playlist = api.playlist(&id?).ok()?;
+        Some((&playlist).into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("resume".parse(), Ok(QueueSource::Resume));
+        assert_eq!("liked".parse(), Ok(QueueSource::LikedSongs));
+        assert_eq!("daily_mix".parse(), Ok(QueueSource::DailyMix(1)));
+        assert_eq!("daily_mix:3".parse(), Ok(QueueSource::DailyMix(3)));
+        assert_eq!(
+            "playlist:spotify:playlist:37i9dQZF1DXcBWIGoYBM5M".parse(),
+            Ok(QueueSource::Playlist(
+                "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M".into()
+            ))
+        );
+        assert!("playlist".parse::<QueueSource>().is_err());
+        assert!("daily_mix:x".parse::<QueueSource>().is_err());
+        assert!("everything".parse::<QueueSource>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for source in [
+            QueueSource::Resume,
+            QueueSource::Empty,
+            QueueSource::LikedSongs,
+            QueueSource::Playlist("Road trip".into()),
+            QueueSource::DailyMix(2),
+        ] {
+            assert_eq!(source.to_string().parse(), Ok(source));
+        }
+    }
+}
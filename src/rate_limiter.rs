@@ -0,0 +1,137 @@
+//! Proactive limiting of the Web API request rate, so bulk operations like a library update don't
+//! run into rate limit responses in the first place.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+/// The default amount of requests per second.
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 10.0;
+
+/// How often waiting requests check whether they were cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sleep for `duration`, or until `cancelled` returns `true`. Returns whether the whole duration
+/// passed.
+pub fn wait(duration: Duration, cancelled: &dyn Fn() -> bool) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if cancelled() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep((deadline - now).min(CANCEL_POLL_INTERVAL));
+    }
+}
+
+/// A token bucket that allows `rate` requests per second on average, with bursts of up to one
+/// second worth of requests.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// The available tokens. Negative if requests are already waiting for tokens.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        let burst = requests_per_second.max(1.0);
+        Self {
+            rate: requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Block until another request may be sent. Returns `false` if `cancelled` returned `true`
+    /// while waiting, the request then doesn't count against the limit.
+    pub fn acquire(&self, cancelled: &dyn Fn() -> bool) -> bool {
+        let duration = self.reserve(Instant::now());
+        if duration.is_zero() {
+            return true;
+        }
+        debug!("rate limiting API request for {duration:?}");
+        let acquired = wait(duration, cancelled);
+        if !acquired {
+            self.release();
+        }
+        acquired
+    }
+
+    /// Return a token that was reserved for a request that wasn't sent.
+    fn release(&self) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.tokens = (bucket.tokens + 1.0).min(self.burst);
+    }
+
+    /// Take a token at `now`. Returns how long the caller has to wait until the token is available.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        if now > bucket.updated {
+            let elapsed = (now - bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+            bucket.updated = now;
+        }
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(2.0);
+        let start = limiter.bucket.lock().unwrap().updated;
+
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start), Duration::from_secs(1));
+
+        // The waiting requests used up the tokens of the next second.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(limiter.reserve(later), Duration::from_millis(500));
+        // The bucket never holds more than a burst.
+        let much_later = start + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(much_later), Duration::ZERO);
+        assert_eq!(limiter.reserve(much_later), Duration::ZERO);
+        assert_eq!(limiter.reserve(much_later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_release() {
+        let limiter = RateLimiter::new(1.0);
+        let start = limiter.bucket.lock().unwrap().updated;
+
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::from_secs(1));
+        limiter.release();
+        assert_eq!(limiter.reserve(start), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_wait() {
+        assert!(wait(Duration::ZERO, &|| false));
+        assert!(!wait(Duration::from_secs(60), &|| true));
+    }
+}
@@ -1,54 +1,237 @@
-use std::io::prelude::*;
-use std::net::{TcpListener, TcpStream};
-
-pub fn redirect_uri_web_server(port: u16) -> Result<String, String> {
-    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
-        .map_err(|e| format!("Failed to bind to port {}: {}", port, e))?;
-
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                if let Some(url) = handle_connection(stream) {
-                    return Ok(url);
-                }
+use std::fmt;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use native_tls::{Identity, TlsAcceptor};
+use url::Url;
+
+use crate::client_config::ClientConfig;
+
+/// How often the listener checks for connections, the timeout and Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The time a connected browser has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why no callback URL was received.
+#[derive(Debug)]
+pub enum CallbackError {
+    /// The callback wasn't called within the timeout.
+    Timeout,
+    /// The user pressed Ctrl-C.
+    Cancelled,
+    /// The server couldn't be started or failed.
+    Failed(String),
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "Timed out waiting for the authorization callback"),
+            Self::Cancelled => write!(f, "Authorization cancelled"),
+            Self::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Catches Ctrl-C while it is alive, so the user can stop waiting without killing ncspot.
+struct CtrlCGuard {
+    interrupted: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
+}
+
+impl CtrlCGuard {
+    fn new() -> Self {
+        // Signal handlers can't be removed again, so they are installed once. While idle, Ctrl-C
+        // terminates as usual.
+        static FLAGS: OnceLock<(Arc<AtomicBool>, Arc<AtomicBool>)> = OnceLock::new();
+        let (interrupted, idle) = FLAGS.get_or_init(|| {
+            let interrupted = Arc::new(AtomicBool::new(false));
+            let idle = Arc::new(AtomicBool::new(true));
+            #[cfg(unix)]
+            {
+                use signal_hook::{consts::SIGINT, flag};
+                flag::register_conditional_default(SIGINT, idle.clone()).ok();
+                flag::register(SIGINT, interrupted.clone()).ok();
             }
-            Err(e) => {
-                return Err(format!("Connection error: {}", e));
+            (interrupted, idle)
+        });
+        interrupted.store(false, Ordering::Relaxed);
+        idle.store(false, Ordering::Relaxed);
+        Self {
+            interrupted: interrupted.clone(),
+            idle: idle.clone(),
+        }
+    }
+
+    fn pressed(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for CtrlCGuard {
+    fn drop(&mut self) {
+        self.idle.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The server the browser is redirected to after the login. The port is released when it is
+/// dropped.
+pub struct CallbackServer {
+    listener: TcpListener,
+    acceptor: Option<TlsAcceptor>,
+    redirect_uri: String,
+}
+
+impl CallbackServer {
+    /// Listen on the first free port of `config`, so a busy port doesn't stop the login.
+    pub fn bind(config: &ClientConfig) -> Result<Self, CallbackError> {
+        let acceptor = config
+            .get_tls()
+            .map(|(certificate, key)| tls_acceptor(certificate, key))
+            .transpose()
+            .map_err(CallbackError::Failed)?;
+        let address = config.get_bind_address();
+        let ports = config.get_ports();
+        let (listener, port) = ports
+            .iter()
+            .find_map(|&port| bind(address, port).map(|listener| (listener, port)))
+            .ok_or_else(|| CallbackError::Failed(all_ports_busy(config)))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| CallbackError::Failed(format!("Failed to set up listener: {e}")))?;
+
+        Ok(Self {
+            listener,
+            acceptor,
+            redirect_uri: config.get_redirect_uri_for(port),
+        })
+    }
+
+    /// The redirect URI for the port the server listens on.
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// Wait up to `timeout` for the OAuth callback and return the full URL it was called with.
+    /// Ctrl-C stops waiting.
+    pub fn wait(&self, timeout: Duration) -> Result<String, CallbackError> {
+        let ctrl_c = CtrlCGuard::new();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if ctrl_c.pressed() {
+                return Err(CallbackError::Cancelled);
+            }
+            if Instant::now() >= deadline {
+                return Err(CallbackError::Timeout);
+            }
+            if let Some(url) = self.accept()? {
+                return Ok(url);
+            }
+        }
+    }
+
+    /// Handle the next connection, if there is one, and return the callback URL it requested.
+    fn accept(&self) -> Result<Option<String>, CallbackError> {
+        let stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                return Ok(None);
             }
+            Err(e) => return Err(CallbackError::Failed(format!("Connection error: {e}"))),
+        };
+        // Accepted connections inherit non-blocking mode on some platforms.
+        if stream.set_nonblocking(false).is_err()
+            || stream.set_read_timeout(Some(REQUEST_TIMEOUT)).is_err()
+        {
+            return Ok(None);
         }
+        Ok(match &self.acceptor {
+            Some(acceptor) => match acceptor.accept(stream) {
+                Ok(stream) => handle_connection(stream, &self.redirect_uri),
+                Err(e) => {
+                    // Browsers abort the handshake when they don't trust the certificate yet.
+                    warn!("TLS handshake failed: {}", e);
+                    None
+                }
+            },
+            None => handle_connection(stream, &self.redirect_uri),
+        })
     }
+}
 
-    Err("No valid callback received".to_string())
+/// The redirect URI for the first free port of `config`, for OAuth clients that start their own
+/// callback server. Falls back to the configured port if all of them are busy.
+pub fn free_redirect_uri(config: &ClientConfig) -> String {
+    let port = config
+        .get_ports()
+        .into_iter()
+        .find(|&port| bind(config.get_bind_address(), port).is_some())
+        .unwrap_or(config.get_port());
+    config.get_redirect_uri_for(port)
+}
+
+fn bind(address: &str, port: u16) -> Option<TcpListener> {
+    match TcpListener::bind((address, port)) {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            info!("Failed to bind to {address}:{port}: {e}");
+            None
+        }
+    }
 }
 
-fn handle_connection(mut stream: TcpStream) -> Option<String> {
+/// The error when none of the ports of `config` is free.
+fn all_ports_busy(config: &ClientConfig) -> String {
+    let ports: Vec<String> = config.get_ports().iter().map(u16::to_string).collect();
+    format!(
+        "Ports {} on {} are all in use. Free one of them, or add more to fallback_ports in \
+         client.yml and register their redirect URIs in the Spotify Dashboard.",
+        ports.join(", "),
+        config.get_bind_address()
+    )
+}
+
+fn tls_acceptor(certificate: &str, key: &str) -> Result<TlsAcceptor, String> {
+    let read = |path: &str| fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e));
+    let identity = Identity::from_pkcs8(&read(certificate)?, &read(key)?)
+        .map_err(|e| format!("Invalid TLS certificate or key: {}", e))?;
+    TlsAcceptor::new(identity).map_err(|e| format!("Failed to set up TLS: {}", e))
+}
+
+fn handle_connection(mut stream: impl Read + Write, redirect_uri: &str) -> Option<String> {
     let mut buffer = [0; 2048];
     let _ = stream.read(&mut buffer).ok()?;
 
     let request = String::from_utf8_lossy(&buffer);
     let split: Vec<&str> = request.split_whitespace().collect();
 
-    if split.len() > 1 {
-        let path = split[1];
-
-        let host = request
-            .lines()
-            .find(|line| line.to_lowercase().starts_with("host:"))
-            .and_then(|line| line.split(':').nth(1))
-            .map(|h| h.trim())
-            .unwrap_or("127.0.0.1:8888");
-
-        let full_url = format!("http://{}{}", host, path);
-
+    if split.len() > 1
+        && let Some(url) = callback_url(redirect_uri, split[1])
+    {
         respond_with_success(stream);
-        return Some(full_url);
+        return Some(url);
     }
 
     respond_with_error("Malformed request".to_string(), stream);
     None
 }
 
-fn respond_with_success(mut stream: TcpStream) {
+/// Reconstruct the URL the callback was requested with from the `path` of the request. The
+/// scheme, host and port are the ones of `redirect_uri`, as the request was sent there.
+fn callback_url(redirect_uri: &str, path: &str) -> Option<String> {
+    let url = Url::parse(redirect_uri).ok()?.join(path).ok()?;
+    Some(url.to_string())
+}
+
+fn respond_with_success(mut stream: impl Write) {
     let contents = include_str!("redirect_uri.html");
 
     let response = format!(
@@ -62,7 +245,7 @@ fn respond_with_success(mut stream: TcpStream) {
     std::thread::sleep(std::time::Duration::from_millis(100));
 }
 
-fn respond_with_error(error_message: String, mut stream: TcpStream) {
+fn respond_with_error(error_message: String, mut stream: impl Write) {
     let body = format!("400 - Bad Request - {}", error_message);
     let response = format!(
         "HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
@@ -74,3 +257,16 @@ fn respond_with_error(error_message: String, mut stream: TcpStream) {
     let _ = stream.flush();
     std::thread::sleep(std::time::Duration::from_millis(100));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_url() {
+        assert_eq!(
+            callback_url("https://example.com:8443/callback", "/callback?code=abc").as_deref(),
+            Some("https://example.com:8443/callback?code=abc")
+        );
+    }
+}
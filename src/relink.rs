@@ -0,0 +1,99 @@
+//! Finding playable versions of tracks that became unavailable in the user's market, e.g. because
+//! the release they are on was taken down while another release of the same recording remains.
+
+use log::debug;
+use rspotify::model::{SearchResult, SearchType};
+
+use crate::duplicates::normalize_title;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::spotify::Spotify;
+
+/// How many search results are considered as replacement.
+const CANDIDATES: u32 = 20;
+
+/// Whether `playable` is a Spotify track that can't be played in the user's market.
+pub fn is_unavailable(playable: &Playable) -> bool {
+    match playable {
+        Playable::Track(track) => !track.is_local && track.is_playable == Some(false),
+        Playable::Episode(_) => false,
+    }
+}
+
+/// Look for a playable version of `track`: the same recording by its ISRC first, then the same
+/// title by the same artist.
+pub fn find_replacement(spotify: &Spotify, track: &Track) -> Option<Track> {
+    let isrc = track
+        .id
+        .as_deref()
+        .and_then(|id| spotify.api.track(id).ok())
+        .and_then(|full| full.external_ids.get("isrc").cloned());
+    if let Some(isrc) = isrc
+        && let Some(replacement) = pick(track, &search(spotify, &format!("isrc:{isrc}")), true)
+    {
+        return Some(replacement);
+    }
+
+    let artist = track.artists.first()?;
+    let query = format!(
+        "track:\"{}\" artist:\"{}\"",
+        normalize_title(&track.title).replace('"', ""),
+        artist.replace('"', "")
+    );
+    pick(track, &search(spotify, &query), false)
+}
+
+fn search(spotify: &Spotify, query: &str) -> Vec<Track> {
+    debug!("searching a replacement with {query}");
+    match spotify.api.search(SearchType::Track, query, CANDIDATES, 0) {
+        Ok(SearchResult::Tracks(page)) => page.items.iter().map(Track::from).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The first playable one of `candidates` that can replace `track`. Unless they are known to be
+/// the same recording, the title and the first artist have to match.
+fn pick(track: &Track, candidates: &[Track], same_recording: bool) -> Option<Track> {
+    let title = normalize_title(&track.title);
+    let artist = track.artists.first().map(|artist| artist.to_lowercase());
+    candidates
+        .iter()
+        .filter(|candidate| candidate.is_playable != Some(false) && candidate.id != track.id)
+        .find(|candidate| {
+            same_recording
+                || (normalize_title(&candidate.title) == title
+                    && candidate.artists.first().map(|a| a.to_lowercase()) == artist)
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, title: &str, artist: &str, is_playable: bool) -> Track {
+        Track {
+            id: Some(id.into()),
+            uri: format!("spotify:track:{id}"),
+            is_playable: Some(is_playable),
+            ..Track::test(title, artist, "Heroes")
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let original = track("1", "Heroes", "David Bowie", false);
+        let candidates = [
+            track("1", "Heroes", "David Bowie", false),
+            track("2", "Heroes", "David Bowie", false),
+            track("3", "Heroes (Live)", "David Bowie", true),
+            track("4", "Heroes - 2017 Remaster", "David Bowie", true),
+        ];
+
+        let picked = pick(&original, &candidates, false).and_then(|t| t.id);
+        assert_eq!(picked.as_deref(), Some("4"));
+        let picked = pick(&original, &candidates, true).and_then(|t| t.id);
+        assert_eq!(picked.as_deref(), Some("3"));
+        assert!(pick(&original, &candidates[..3], false).is_none());
+    }
+}
@@ -0,0 +1,347 @@
+#![cfg(feature = "scripting")]
+//! Rhai scripts in the `plugins` directory of the configuration, that can add commands,
+//! keybindings and handlers for the events of the hooks.
+//!
+//! Scripts run on their own thread, so a slow script doesn't block the interface. They can read the
+//! queue, the library and search results, but only the scripts listed in `writable_scripts` may
+//! change anything, by running commands.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use log::{error, info, warn};
+use rhai::{AST, Array, Dynamic, Engine, EvalAltResult, FnPtr};
+use rspotify::model::{SearchResult, SearchType};
+
+use crate::command::{self, CommandParseError};
+use crate::config::{self, Config};
+use crate::events::{Event, EventManager};
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::queue::Queue;
+
+const PLUGIN_DIRECTORY: &str = "plugins";
+
+/// Scripts get this many operations for a call before they are stopped.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// The events scripts can register handlers for with `on`, the same as those of the hooks.
+const EVENTS: [&str; 4] = ["track_change", "play", "pause", "queue_empty"];
+
+/// The jobs for the script thread, once the scripts are loaded.
+static JOBS: OnceLock<Sender<Job>> = OnceLock::new();
+
+/// The keybindings added by scripts, once the scripts are loaded.
+static KEYBINDINGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+enum Job {
+    Command(String, Vec<String>),
+    Event(String, Option<Box<Playable>>),
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+    writable: bool,
+}
+
+/// The script that is running.
+#[derive(Clone, Copy, Default)]
+struct Current {
+    index: usize,
+    writable: bool,
+}
+
+/// What the scripts registered while they were loaded, with the index of the script.
+#[derive(Default)]
+struct Registry {
+    commands: HashMap<String, (usize, FnPtr)>,
+    handlers: Vec<(String, usize, FnPtr)>,
+    keybindings: HashMap<String, (usize, String)>,
+}
+
+impl Registry {
+    /// Drop what the script at `index` registered, when it failed to load.
+    fn forget(&mut self, index: usize) {
+        self.commands.retain(|_, (script, _)| *script != index);
+        self.handlers.retain(|(_, script, _)| *script != index);
+        self.keybindings.retain(|_, (script, _)| *script != index);
+    }
+}
+
+/// Load the scripts and start the thread that runs them. The commands of the scripts can be used
+/// afterwards, and their keybindings are returned by [keybindings].
+pub fn start(cfg: &Config, queue: Arc<Queue>, library: Arc<Library>, events: EventManager) {
+    let writable = cfg.values().writable_scripts.clone().unwrap_or_default();
+    let (loaded_tx, loaded_rx) = channel();
+    let (jobs_tx, jobs_rx) = channel();
+    thread::spawn(move || {
+        let registry = Rc::new(RefCell::new(Registry::default()));
+        let current = Rc::new(Cell::new(Current::default()));
+        let mut scripts: Vec<Script> = Vec::new();
+        let engine = engine(&registry, &current, queue, library, events);
+        let directory = config::config_path(PLUGIN_DIRECTORY);
+        for path in script_paths(&directory) {
+            let Some(name) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            current.set(Current {
+                index: scripts.len(),
+                writable: writable.contains(&name),
+            });
+            match engine
+                .compile_file(path.clone())
+                .and_then(|ast| engine.run_ast(&ast).map(|_| ast))
+            {
+                Ok(ast) => {
+                    info!("loaded script {name}");
+                    scripts.push(Script {
+                        writable: writable.contains(&name),
+                        name,
+                        ast,
+                    });
+                }
+                Err(e) => {
+                    error!("Could not load script {}: {e}", path.display());
+                    registry.borrow_mut().forget(scripts.len());
+                }
+            }
+        }
+
+        let commands: Vec<String> = registry.borrow().commands.keys().cloned().collect();
+        let keybindings: HashMap<String, String> = registry
+            .borrow()
+            .keybindings
+            .iter()
+            .map(|(key, (_, commands))| (key.clone(), commands.clone()))
+            .collect();
+        loaded_tx.send((commands, keybindings)).ok();
+
+        for job in jobs_rx {
+            let calls: Vec<(usize, FnPtr, Vec<Dynamic>)> = match job {
+                Job::Command(name, args) => {
+                    let args: Array = args.into_iter().map(Dynamic::from).collect();
+                    registry
+                        .borrow()
+                        .commands
+                        .get(&name)
+                        .map(|(script, f)| (*script, f.clone(), vec![Dynamic::from(args)]))
+                        .into_iter()
+                        .collect()
+                }
+                Job::Event(event, playable) => {
+                    let args = match playable.as_ref().map(rhai::serde::to_dynamic) {
+                        Some(Ok(playable)) => vec![playable],
+                        _ => vec![Dynamic::UNIT],
+                    };
+                    registry
+                        .borrow()
+                        .handlers
+                        .iter()
+                        .filter(|(name, _, _)| *name == event)
+                        .map(|(_, script, f)| (*script, f.clone(), args.clone()))
+                        .collect()
+                }
+            };
+            for (index, f, args) in calls {
+                let script = &scripts[index];
+                current.set(Current {
+                    index,
+                    writable: script.writable,
+                });
+                if let Err(e) = f.call::<Dynamic>(&engine, &script.ast, args) {
+                    error!("script {}: {e}", script.name);
+                }
+            }
+        }
+    });
+
+    let Ok((commands, keybindings)) = loaded_rx.recv() else {
+        error!("Could not load the scripts");
+        return;
+    };
+    command::set_script_commands(commands);
+    KEYBINDINGS.set(keybindings).ok();
+    JOBS.set(jobs_tx).ok();
+}
+
+/// The keybindings added by the scripts, which those of the configuration override.
+pub fn keybindings() -> HashMap<String, String> {
+    KEYBINDINGS.get().cloned().unwrap_or_default()
+}
+
+/// Run the script command `name` with `args`.
+pub fn run_command(name: &str, args: &[String]) {
+    send(Job::Command(name.to_string(), args.to_vec()));
+}
+
+/// Call the handlers the scripts registered for `event` with the item it is about.
+pub fn emit(event: &str, playable: Option<&Playable>) {
+    send(Job::Event(
+        event.to_string(),
+        playable.cloned().map(Box::new),
+    ));
+}
+
+fn send(job: Job) {
+    if let Some(jobs) = JOBS.get() {
+        jobs.send(job).ok();
+    }
+}
+
+/// The `.rhai` files in `directory`, ordered by their name.
+fn script_paths(directory: &Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "rhai")
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The engine with the functions available to scripts. `current` is the index of the script that
+/// is running.
+fn engine(
+    registry: &Rc<RefCell<Registry>>,
+    current: &Rc<Cell<Current>>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+    events: EventManager,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| info!("script: {text}"));
+    engine.on_debug(|text, _, _| info!("script: {text}"));
+
+    let commands = registry.clone();
+    let script = current.clone();
+    engine.register_fn(
+        "command",
+        move |name: &str, f: FnPtr| -> Result<(), Box<EvalAltResult>> {
+            if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ';') {
+                return Err(format!("invalid command name \"{name}\"").into());
+            }
+            if !matches!(
+                command::parse(name),
+                Err(CommandParseError::NoSuchCommand { .. })
+            ) {
+                return Err(format!("\"{name}\" is already a command").into());
+            }
+            let mut commands = commands.borrow_mut();
+            if commands.commands.contains_key(name) {
+                warn!("script command \"{name}\" is already defined, ignoring it");
+            } else {
+                commands
+                    .commands
+                    .insert(name.to_string(), (script.get().index, f));
+            }
+            Ok(())
+        },
+    );
+
+    let handlers = registry.clone();
+    let script = current.clone();
+    engine.register_fn(
+        "on",
+        move |event: &str, f: FnPtr| -> Result<(), Box<EvalAltResult>> {
+            if !EVENTS.contains(&event) {
+                return Err(format!("unknown event \"{event}\", use one of {EVENTS:?}").into());
+            }
+            handlers
+                .borrow_mut()
+                .handlers
+                .push((event.to_string(), script.get().index, f));
+            Ok(())
+        },
+    );
+
+    let keybindings = registry.clone();
+    let script = current.clone();
+    engine.register_fn("bind", move |key: &str, commands: &str| {
+        keybindings
+            .borrow_mut()
+            .keybindings
+            .entry(key.to_string())
+            .or_insert_with(|| (script.get().index, commands.to_string()));
+    });
+
+    let q = queue.clone();
+    engine.register_fn("current", move || -> Dynamic {
+        q.get_current()
+            .and_then(|playable| rhai::serde::to_dynamic(playable).ok())
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let q = queue.clone();
+    engine.register_fn("queue", move || -> Array {
+        to_array(q.queue.read().unwrap().iter())
+    });
+
+    let l = library.clone();
+    engine.register_fn("saved_tracks", move || -> Array {
+        to_array(l.tracks.read().unwrap().iter())
+    });
+
+    let l = library.clone();
+    engine.register_fn("playlists", move || -> Array {
+        l.playlists
+            .read()
+            .unwrap()
+            .iter()
+            .map(|playlist| {
+                let mut map = rhai::Map::new();
+                map.insert("id".into(), playlist.id.clone().into());
+                map.insert("name".into(), playlist.name.clone().into());
+                map.insert("owner_id".into(), playlist.owner_id.clone().into());
+                map.insert("num_tracks".into(), (playlist.num_tracks as i64).into());
+                Dynamic::from_map(map)
+            })
+            .collect()
+    });
+
+    let spotify = queue.get_spotify();
+    engine.register_fn("search_tracks", move |query: &str| -> Array {
+        match spotify.api.search(SearchType::Track, query, 50, 0) {
+            Ok(SearchResult::Tracks(page)) => to_array(page.items.iter().map(Track::from)),
+            _ => Array::new(),
+        }
+    });
+
+    let script = current.clone();
+    engine.register_fn(
+        "run",
+        move |commands: &str| -> Result<(), Box<EvalAltResult>> {
+            if !script.get().writable {
+                return Err("the script isn't in writable_scripts and can't run commands".into());
+            }
+            command::parse(commands).map_err(|e| e.to_string())?;
+            events.send(Event::IpcInput(commands.to_string()));
+            Ok(())
+        },
+    );
+
+    engine
+}
+
+/// Convert `items` to an array of object maps with the fields of their JSON representation.
+fn to_array<T: serde::Serialize>(items: impl Iterator<Item = T>) -> Array {
+    items
+        .filter_map(|item| rhai::serde::to_dynamic(item).ok())
+        .collect()
+}
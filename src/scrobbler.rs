@@ -0,0 +1,609 @@
+//! Submits the played tracks to Last.fm and ListenBrainz.
+//!
+//! A track is scrobbled once half of it or four minutes were played, whichever comes first.
+//! Scrobbles that can't be submitted are kept in the cache and sent on the next start or along
+//! with the next one, for up to two weeks.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{Receiver, Sender};
+use log::{debug, error, info, warn};
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde_json::{Value, json};
+
+use crate::config::{self, Config, ConfigScrobbling};
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::proxy;
+use crate::serialization::{CBOR, Serializer, TOML};
+use crate::spotify::PlayerEvent;
+
+const LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+const LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+const SECRETS_FILE: &str = "scrobbling.toml";
+const PENDING_FILE: &str = "scrobbles.cbor";
+
+/// Shorter tracks aren't scrobbled, as required by Last.fm.
+const MIN_DURATION: Duration = Duration::from_secs(30);
+/// Longer tracks are scrobbled after this time instead of after half of their duration.
+const MAX_PLAYED: Duration = Duration::from_secs(4 * 60);
+/// The most scrobbles that are submitted in one request, the limit of Last.fm.
+const BATCH_SIZE: usize = 50;
+/// Older pending scrobbles are dropped, as Last.fm ignores them.
+const MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+/// The Last.fm error codes of requests that won't succeed when sent again.
+/// <https://www.last.fm/api/errorcodes>
+const LASTFM_REJECTED: [u64; 5] = [2, 3, 5, 6, 7];
+/// The Last.fm error code of an invalid session key.
+const LASTFM_INVALID_SESSION: u64 = 9;
+
+/// The credentials of the services, kept apart from the configuration so it can be shared.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Secrets {
+    lastfm: Option<LastFmSecrets>,
+    listenbrainz: Option<ListenBrainzSecrets>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LastFmSecrets {
+    api_key: String,
+    api_secret: String,
+    /// Used instead of logging in with `username` and `password` if set.
+    session_key: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ListenBrainzSecrets {
+    token: String,
+}
+
+/// A played track, as submitted to the services.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Scrobble {
+    artists: Vec<String>,
+    title: String,
+    album: Option<String>,
+    /// The duration in seconds.
+    duration: u32,
+    url: String,
+    /// When playback started, in seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+impl From<&Track> for Scrobble {
+    fn from(track: &Track) -> Self {
+        Self {
+            artists: track.artists.clone(),
+            title: track.title.clone(),
+            album: track.album.clone(),
+            duration: track.duration / 1000,
+            url: track.url.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+enum Submission {
+    NowPlaying(Scrobble),
+    Scrobble(Scrobble),
+}
+
+/// Why a submission failed.
+enum SubmitError {
+    /// Worth trying again later, like a network error, an unavailable service or credentials that
+    /// have to be fixed.
+    Temporary(String),
+    /// The service won't accept the submission, so pending scrobbles are dropped.
+    Rejected(String),
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Temporary(e) | Self::Rejected(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// The playing track and how long it has been played.
+struct Listen {
+    uri: String,
+    scrobble: Scrobble,
+    /// How long the track needs to be played to be scrobbled.
+    required: Duration,
+    played: Duration,
+    /// When playback was started or resumed, if it is playing.
+    since: Option<Instant>,
+    announced: bool,
+    scrobbled: bool,
+}
+
+pub struct Scrobbler {
+    cfg: Arc<Config>,
+    /// The channel to the thread that talks to the services, started with the first submission.
+    worker: Option<Sender<Submission>>,
+    current: Option<Listen>,
+}
+
+impl Scrobbler {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        let mut scrobbler = Self {
+            cfg,
+            worker: None,
+            current: None,
+        };
+        // The worker submits the scrobbles left over from earlier sessions right away.
+        if enabled(&scrobbler.cfg.values().scrobbling).contains(&true) {
+            scrobbler.spawn_worker();
+        }
+        scrobbler
+    }
+
+    /// Follow the playback of `playable` to tell when it was played long enough.
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let track = match playable {
+            Some(Playable::Track(track)) => Some(track),
+            _ => None,
+        };
+        let uri = track.map(|track| track.uri.clone());
+        if self.current.as_ref().map(|listen| &listen.uri) != uri.as_ref() {
+            self.current = track.and_then(|track| {
+                let duration = Duration::from_millis(track.duration.into());
+                (duration >= MIN_DURATION).then(|| Listen {
+                    uri: track.uri.clone(),
+                    scrobble: Scrobble::from(track),
+                    required: (duration / 2).min(MAX_PLAYED),
+                    played: Duration::ZERO,
+                    since: None,
+                    announced: false,
+                    scrobbled: false,
+                })
+            });
+        }
+
+        match state {
+            PlayerEvent::Playing(_) => {
+                let Some(listen) = &mut self.current else {
+                    return;
+                };
+                listen.since.get_or_insert_with(Instant::now);
+                if !listen.announced {
+                    listen.announced = true;
+                    let scrobble = listen.scrobble.clone();
+                    self.submit(Submission::NowPlaying(scrobble));
+                }
+            }
+            PlayerEvent::Paused(_) => self.pause(),
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => {
+                // Playing the same track again counts as another listen.
+                self.pause();
+                self.current = None;
+            }
+        }
+    }
+
+    /// Scrobble the playing track once it was played long enough.
+    pub fn tick(&mut self) {
+        let Some(listen) = &mut self.current else {
+            return;
+        };
+        let played = listen.played + listen.since.map_or(Duration::ZERO, |t| t.elapsed());
+        if !listen.scrobbled && played >= listen.required {
+            listen.scrobbled = true;
+            let scrobble = listen.scrobble.clone();
+            self.submit(Submission::Scrobble(scrobble));
+        }
+    }
+
+    fn pause(&mut self) {
+        self.tick();
+        if let Some(listen) = &mut self.current
+            && let Some(since) = listen.since.take()
+        {
+            listen.played += since.elapsed();
+        }
+    }
+
+    fn submit(&mut self, submission: Submission) {
+        if !enabled(&self.cfg.values().scrobbling).contains(&true) {
+            return;
+        }
+        self.spawn_worker().send(submission).ok();
+    }
+
+    /// The channel to the worker, which is started if it isn't running yet.
+    fn spawn_worker(&mut self) -> &Sender<Submission> {
+        self.worker.get_or_insert_with(|| {
+            let (tx, rx) = crossbeam_channel::unbounded();
+            let cfg = self.cfg.clone();
+            thread::spawn(move || worker(cfg, rx));
+            tx
+        })
+    }
+}
+
+/// Whether Last.fm and ListenBrainz are enabled, in this order.
+fn enabled(cfg: &Option<ConfigScrobbling>) -> [bool; 2] {
+    let cfg = cfg.clone().unwrap_or_default();
+    [cfg.lastfm, cfg.listenbrainz].map(|enabled| enabled.unwrap_or(false))
+}
+
+/// Submit what is received on `rx` to the enabled services, until the [Scrobbler] is dropped.
+fn worker(cfg: Arc<Config>, rx: Receiver<Submission>) {
+    let path = config::config_path(SECRETS_FILE);
+    let secrets: Secrets = if path.exists() {
+        TOML.load(&path).unwrap_or_else(|e| {
+            error!("Could not load the scrobbling credentials: {e}");
+            Secrets::default()
+        })
+    } else {
+        Secrets::default()
+    };
+    let mut services: [Option<Box<dyn Service>>; 2] = [
+        secrets
+            .lastfm
+            .map(|secrets| Box::new(LastFm { secrets }) as Box<dyn Service>),
+        secrets
+            .listenbrainz
+            .map(|secrets| Box::new(ListenBrainz { secrets }) as Box<dyn Service>),
+    ];
+    let client = match proxy::client_builder()
+        .user_agent(format!("ncspot/{}", env!("VERSION")))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Could not create the scrobbling client: {e}");
+            return;
+        }
+    };
+
+    let pending_path = config::cache_path(PENDING_FILE);
+    let mut pending: HashMap<String, Vec<Scrobble>> = CBOR.load(&pending_path).unwrap_or_default();
+    let save = |pending: &HashMap<String, Vec<Scrobble>>| {
+        if let Err(e) = CBOR.write(&pending_path, pending) {
+            error!("Could not save the pending scrobbles: {e}");
+        }
+    };
+
+    let mut changed = false;
+    for (service, enabled) in services.iter_mut().zip(enabled(&cfg.values().scrobbling)) {
+        if let (Some(service), true) = (service, enabled)
+            && let Some(queue) = pending.get_mut(service.name())
+        {
+            changed |= flush(service.as_mut(), &client, queue);
+        }
+    }
+    if changed {
+        save(&pending);
+    }
+
+    for submission in rx {
+        let enabled = enabled(&cfg.values().scrobbling);
+        let mut changed = false;
+        for (service, enabled) in services.iter_mut().zip(enabled) {
+            if !enabled {
+                continue;
+            }
+            let Some(service) = service else {
+                warn!("Scrobbling is enabled, but {SECRETS_FILE} has no credentials for it");
+                continue;
+            };
+            match &submission {
+                Submission::NowPlaying(scrobble) => {
+                    if let Err(e) = service.now_playing(&client, scrobble) {
+                        warn!("{}: could not update now playing: {e}", service.name());
+                    }
+                }
+                Submission::Scrobble(scrobble) => {
+                    let queue = pending.entry(service.name().to_string()).or_default();
+                    queue.push(scrobble.clone());
+                    flush(service.as_mut(), &client, queue);
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            save(&pending);
+        }
+    }
+    debug!("scrobbler stopped");
+}
+
+/// Submit the pending scrobbles in `queue` to `service`, until one of the requests fails
+/// temporarily. Scrobbles that are rejected or too old are dropped. Returns whether `queue`
+/// changed.
+fn flush(service: &mut dyn Service, client: &Client, queue: &mut Vec<Scrobble>) -> bool {
+    let length = queue.len();
+    let oldest = SystemTime::now()
+        .checked_sub(MAX_AGE)
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_secs();
+    queue.retain(|scrobble| scrobble.timestamp >= oldest);
+    if queue.len() < length {
+        warn!(
+            "{}: dropped {} scrobbles older than two weeks",
+            service.name(),
+            length - queue.len()
+        );
+    }
+
+    while !queue.is_empty() {
+        let batch = queue.len().min(BATCH_SIZE);
+        match service.scrobble(client, &queue[..batch]) {
+            Ok(()) => info!("{}: submitted {batch} scrobbles", service.name()),
+            Err(SubmitError::Rejected(e)) => {
+                error!(
+                    "{}: dropped {batch} rejected scrobbles: {e}",
+                    service.name()
+                );
+            }
+            Err(SubmitError::Temporary(e)) => {
+                warn!(
+                    "{}: could not submit scrobbles, {} are pending: {e}",
+                    service.name(),
+                    queue.len()
+                );
+                break;
+            }
+        }
+        queue.drain(..batch);
+    }
+    queue.len() != length
+}
+
+trait Service: Send {
+    fn name(&self) -> &'static str;
+
+    fn now_playing(&mut self, client: &Client, scrobble: &Scrobble) -> Result<(), SubmitError>;
+
+    fn scrobble(&mut self, client: &Client, scrobbles: &[Scrobble]) -> Result<(), SubmitError>;
+}
+
+struct LastFm {
+    secrets: LastFmSecrets,
+}
+
+impl LastFm {
+    /// Call the API `method` with `params`, signed with the API secret. When the session key is
+    /// invalid, it is forgotten so it is fetched again with the next call.
+    fn call(
+        &mut self,
+        client: &Client,
+        method: &str,
+        mut params: Vec<(String, String)>,
+    ) -> Result<Value, SubmitError> {
+        params.push(("method".into(), method.into()));
+        params.push(("api_key".into(), self.secrets.api_key.clone()));
+        params.sort();
+        let mut signature: String = params.iter().map(|(k, v)| format!("{k}{v}")).collect();
+        signature.push_str(&self.secrets.api_secret);
+        let signature = format!("{:x}", md5::compute(signature));
+
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&params)
+            .append_pair("api_sig", &signature)
+            .append_pair("format", "json")
+            .finish();
+        let response: Value = client
+            .post(LASTFM_URL)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .and_then(|response| response.json())
+            .map_err(|e| SubmitError::Temporary(e.to_string()))?;
+        let Some(code) = response["error"].as_u64() else {
+            return Ok(response);
+        };
+        let message = response["message"].as_str().unwrap_or_default().to_string();
+        if code == LASTFM_INVALID_SESSION {
+            self.secrets.session_key = None;
+        }
+        if LASTFM_REJECTED.contains(&code) {
+            Err(SubmitError::Rejected(message))
+        } else {
+            Err(SubmitError::Temporary(message))
+        }
+    }
+
+    /// The session key from the credentials, or from logging in with the username and password.
+    fn session_key(&mut self, client: &Client) -> Result<String, SubmitError> {
+        if let Some(key) = &self.secrets.session_key {
+            return Ok(key.clone());
+        }
+        let (Some(username), Some(password)) = (&self.secrets.username, &self.secrets.password)
+        else {
+            return Err(SubmitError::Temporary(
+                "set session_key, or username and password".into(),
+            ));
+        };
+        let params = vec![
+            ("username".into(), username.clone()),
+            ("password".into(), password.clone()),
+        ];
+        let response = self.call(client, "auth.getMobileSession", params)?;
+        let key = response["session"]["key"]
+            .as_str()
+            .ok_or_else(|| SubmitError::Temporary("no session key in the response".into()))?
+            .to_string();
+        self.secrets.session_key = Some(key.clone());
+        Ok(key)
+    }
+
+    /// The parameters of `scrobble`, with `suffix` appended to their names.
+    fn params(scrobble: &Scrobble, suffix: &str) -> Vec<(String, String)> {
+        let mut params = vec![
+            (
+                format!("artist{suffix}"),
+                scrobble.artists.first().cloned().unwrap_or_default(),
+            ),
+            (format!("track{suffix}"), scrobble.title.clone()),
+            (format!("duration{suffix}"), scrobble.duration.to_string()),
+        ];
+        if let Some(album) = &scrobble.album {
+            params.push((format!("album{suffix}"), album.clone()));
+        }
+        params
+    }
+}
+
+impl Service for LastFm {
+    fn name(&self) -> &'static str {
+        "Last.fm"
+    }
+
+    fn now_playing(&mut self, client: &Client, scrobble: &Scrobble) -> Result<(), SubmitError> {
+        let mut params = Self::params(scrobble, "");
+        params.push(("sk".into(), self.session_key(client)?));
+        self.call(client, "track.updateNowPlaying", params)
+            .map(|_| ())
+    }
+
+    fn scrobble(&mut self, client: &Client, scrobbles: &[Scrobble]) -> Result<(), SubmitError> {
+        let mut params = vec![("sk".into(), self.session_key(client)?)];
+        for (i, scrobble) in scrobbles.iter().enumerate() {
+            let suffix = format!("[{i}]");
+            params.extend(Self::params(scrobble, &suffix));
+            params.push((format!("timestamp{suffix}"), scrobble.timestamp.to_string()));
+        }
+        self.call(client, "track.scrobble", params).map(|_| ())
+    }
+}
+
+struct ListenBrainz {
+    secrets: ListenBrainzSecrets,
+}
+
+impl ListenBrainz {
+    fn submit(
+        &self,
+        client: &Client,
+        listen_type: &str,
+        payload: Vec<Value>,
+    ) -> Result<(), SubmitError> {
+        client
+            .post(LISTENBRAINZ_URL)
+            .header(AUTHORIZATION, format!("Token {}", self.secrets.token))
+            .json(&json!({ "listen_type": listen_type, "payload": payload }))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| {
+                // Invalid listens are answered with 400, the token is checked with 401.
+                if e.status() == Some(StatusCode::BAD_REQUEST) {
+                    SubmitError::Rejected(e.to_string())
+                } else {
+                    SubmitError::Temporary(e.to_string())
+                }
+            })
+    }
+
+    fn metadata(scrobble: &Scrobble) -> Value {
+        json!({
+            "artist_name": scrobble.artists.join(", "),
+            "track_name": scrobble.title,
+            "release_name": scrobble.album,
+            "additional_info": {
+                "artist_names": scrobble.artists,
+                "duration": scrobble.duration,
+                "spotify_id": scrobble.url,
+                "music_service": "spotify.com",
+                "submission_client": "ncspot",
+                "submission_client_version": env!("VERSION"),
+            },
+        })
+    }
+}
+
+impl Service for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "ListenBrainz"
+    }
+
+    fn now_playing(&mut self, client: &Client, scrobble: &Scrobble) -> Result<(), SubmitError> {
+        let payload = vec![json!({ "track_metadata": Self::metadata(scrobble) })];
+        self.submit(client, "playing_now", payload)
+    }
+
+    fn scrobble(&mut self, client: &Client, scrobbles: &[Scrobble]) -> Result<(), SubmitError> {
+        let payload = scrobbles
+            .iter()
+            .map(|scrobble| {
+                json!({
+                    "listened_at": scrobble.timestamp,
+                    "track_metadata": Self::metadata(scrobble),
+                })
+            })
+            .collect();
+        let listen_type = if scrobbles.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+        self.submit(client, listen_type, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers the scrobble requests with the given results, in order.
+    struct Answers(Vec<Result<(), SubmitError>>);
+
+    impl Service for Answers {
+        fn name(&self) -> &'static str {
+            "Test"
+        }
+
+        fn now_playing(&mut self, _: &Client, _: &Scrobble) -> Result<(), SubmitError> {
+            Ok(())
+        }
+
+        fn scrobble(&mut self, _: &Client, _: &[Scrobble]) -> Result<(), SubmitError> {
+            self.0.remove(0)
+        }
+    }
+
+    fn scrobble(timestamp: u64) -> Scrobble {
+        Scrobble {
+            artists: vec!["Caroline Polachek".into()],
+            title: "Hit Me Where It Hurts".into(),
+            album: None,
+            duration: 184,
+            url: "https://open.spotify.com/track/2wcrQZ7ZJolYEfIaPP9yL4".into(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_flush() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut queue = vec![scrobble(now - MAX_AGE.as_secs() - 60)];
+        queue.extend((0..120).map(|_| scrobble(now)));
+        let mut service = Answers(vec![
+            Ok(()),
+            Err(SubmitError::Rejected("Invalid parameters".into())),
+            Err(SubmitError::Temporary("Service offline".into())),
+        ]);
+
+        assert!(flush(&mut service, &Client::new(), &mut queue));
+        assert_eq!(queue.len(), 20);
+        assert!(service.0.is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+/// The filter keywords understood by the Spotify search, offered for completion in the search bar.
+pub const FILTER_KEYWORDS: [&str; 7] = [
+    "artist:",
+    "album:",
+    "track:",
+    "year:",
+    "genre:",
+    "tag:new",
+    "tag:hipster",
+];
+
+/// The filter keywords that complete the last word of `input`.
+pub fn complete_filter(input: &str) -> Vec<&'static str> {
+    let word = match input.rsplit(char::is_whitespace).next() {
+        Some(word) if !word.is_empty() && !input.ends_with(char::is_whitespace) => word,
+        _ => return Vec::new(),
+    };
+    let word = word.to_lowercase();
+
+    FILTER_KEYWORDS
+        .iter()
+        .copied()
+        .filter(|keyword| keyword.starts_with(&word) && *keyword != word)
+        .collect()
+}
+
+/// Replace the last word of `input` with the longest completion shared by all `candidates`.
+pub fn apply_completion(input: &str, candidates: &[&str]) -> String {
+    let Some(first) = candidates.first() else {
+        return input.to_string();
+    };
+    let common_len = candidates.iter().fold(first.len(), |len, candidate| {
+        first
+            .bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+
+    let word_start = input
+        .rfind(char::is_whitespace)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    format!("{}{}", &input[..word_start], &first[..common_len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_filter() {
+        assert_eq!(complete_filter("daft ar"), vec!["artist:"]);
+        assert_eq!(complete_filter("a"), vec!["artist:", "album:"]);
+        assert_eq!(complete_filter("tag:"), vec!["tag:new", "tag:hipster"]);
+        assert!(complete_filter("daft ").is_empty());
+        assert!(complete_filter("artist:").is_empty());
+        assert!(complete_filter("xyz").is_empty());
+
+        assert_eq!(apply_completion("daft ar", &["artist:"]), "daft artist:");
+        assert_eq!(apply_completion("a", &["artist:", "album:"]), "a");
+        assert_eq!(apply_completion("t", &["track:", "tag:new"]), "t");
+        assert_eq!(apply_completion("ta", &["tag:new", "tag:hipster"]), "tag:");
+    }
+}
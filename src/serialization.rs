@@ -1,4 +1,69 @@
-use std::{fs, path::Path};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Write `contents` to `path` without other ncspot instances ever seeing a partially written file.
+/// Writers are serialized with a lock file next to `path`, and the new content is moved into place
+/// in one step.
+pub fn write_atomically<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    write_atomically_with(path.as_ref(), contents, false)
+}
+
+/// Like [`write_atomically`], but the file can only be read and written by the current user, for
+/// credentials.
+pub fn write_private<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    write_atomically_with(path.as_ref(), contents, true)
+}
+
+/// Warn if the file at `path` can be accessed by other users, which credentials shouldn't be.
+pub fn warn_if_not_private(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path)
+            && metadata.permissions().mode() & 0o077 != 0
+        {
+            let warning = format!(
+                "{} can be accessed by other users, restrict it with `chmod 600 {}`",
+                path.display(),
+                path.display()
+            );
+            println!("Warning: {warning}");
+            log::warn!("{warning}");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn write_atomically_with(path: &Path, contents: &[u8], private: bool) -> io::Result<()> {
+    let sibling = |suffix: &str| {
+        let mut name = OsString::from(path.as_os_str());
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+
+    let lock = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling(".lock"))?;
+    lock.lock()?;
+
+    let temporary = sibling(&format!(".{}.tmp", std::process::id()));
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    options.open(&temporary)?.write_all(contents)?;
+    fs::rename(&temporary, path)
+}
 
 pub trait Serializer {
     /// Configuration and credential file helper
@@ -29,34 +94,19 @@ pub trait Serializer {
         result.map_err(|e| format!("Unable to parse {}: {}", path.to_string_lossy(), e))
     }
 
-    fn load<P: AsRef<Path>, T: serde::Serialize + serde::de::DeserializeOwned>(
-        &self,
-        path: P,
-    ) -> Result<T, String>;
-    fn write<P: AsRef<Path>, T: serde::Serialize>(&self, path: P, value: T) -> Result<T, String>;
-}
-
-pub struct TomlSerializer {}
-impl Serializer for TomlSerializer {
     fn load<P: AsRef<Path>, T: serde::Serialize + serde::de::DeserializeOwned>(
         &self,
         path: P,
     ) -> Result<T, String> {
-        let contents = std::fs::read_to_string(&path)
+        let contents = fs::read(&path)
             .map_err(|e| format!("Unable to read {}: {}", path.as_ref().to_string_lossy(), e))?;
-        toml::from_str(&contents).map_err(|e| {
-            format!(
-                "Unable to parse toml {}: {}",
-                path.as_ref().to_string_lossy(),
-                e
-            )
-        })
+        self.decode(&contents)
+            .map_err(|e| format!("{}: {}", path.as_ref().to_string_lossy(), e))
     }
 
     fn write<P: AsRef<Path>, T: serde::Serialize>(&self, path: P, value: T) -> Result<T, String> {
-        let content =
-            toml::to_string_pretty(&value).map_err(|e| format!("Failed serializing value: {e}"))?;
-        fs::write(path.as_ref(), content)
+        let content = self.encode(&value)?;
+        write_atomically(path.as_ref(), &content)
             .map(|_| value)
             .map_err(|e| {
                 format!(
@@ -66,42 +116,36 @@ impl Serializer for TomlSerializer {
                 )
             })
     }
+
+    /// Parse a value from its serialized representation.
+    fn decode<T: serde::de::DeserializeOwned>(&self, contents: &[u8]) -> Result<T, String>;
+
+    /// Serialize `value`.
+    fn encode<T: serde::Serialize>(&self, value: &T) -> Result<Vec<u8>, String>;
+}
+
+pub struct TomlSerializer {}
+impl Serializer for TomlSerializer {
+    fn decode<T: serde::de::DeserializeOwned>(&self, contents: &[u8]) -> Result<T, String> {
+        let contents = std::str::from_utf8(contents).map_err(|e| e.to_string())?;
+        toml::from_str(contents).map_err(|e| format!("Unable to parse toml: {e}"))
+    }
+
+    fn encode<T: serde::Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        toml::to_string_pretty(value)
+            .map(String::into_bytes)
+            .map_err(|e| format!("Failed serializing value: {e}"))
+    }
 }
 
 pub struct CborSerializer {}
 impl Serializer for CborSerializer {
-    fn load<P: AsRef<Path>, T: serde::Serialize + serde::de::DeserializeOwned>(
-        &self,
-        path: P,
-    ) -> Result<T, String> {
-        let contents = std::fs::read(&path)
-            .map_err(|e| format!("Unable to read {}: {}", path.as_ref().to_string_lossy(), e))?;
-        serde_cbor::from_slice(&contents).map_err(|e| {
-            format!(
-                "Unable to parse CBOR {}: {}",
-                path.as_ref().to_string_lossy(),
-                e
-            )
-        })
+    fn decode<T: serde::de::DeserializeOwned>(&self, contents: &[u8]) -> Result<T, String> {
+        serde_cbor::from_slice(contents).map_err(|e| format!("Unable to parse CBOR: {e}"))
     }
 
-    fn write<P: AsRef<Path>, T: serde::Serialize>(&self, path: P, value: T) -> Result<T, String> {
-        let file = std::fs::File::create(&path).map_err(|e| {
-            format!(
-                "Failed creating file {}: {}",
-                path.as_ref().to_string_lossy(),
-                e
-            )
-        })?;
-        serde_cbor::to_writer(file, &value)
-            .map(|_| value)
-            .map_err(|e| {
-                format!(
-                    "Failed writing content to {}: {}",
-                    path.as_ref().display(),
-                    e
-                )
-            })
+    fn encode<T: serde::Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        serde_cbor::to_vec(value).map_err(|e| format!("Failed serializing value: {e}"))
     }
 }
 
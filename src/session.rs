@@ -0,0 +1,167 @@
+//! The screens and views that were open when ncspot was quit, kept in the cache directory to open
+//! them again on the next start.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use log::{debug, warn};
+
+use crate::config::{self, LibraryTab};
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::model::artist::Artist;
+use crate::model::playlist::Playlist;
+use crate::model::show::Show;
+use crate::queue::Queue;
+use crate::serialization::{CBOR, Serializer};
+use crate::spotify::UriType;
+use crate::spotify_url::SpotifyUrl;
+use crate::traits::{ListItem, ViewExt};
+
+const SESSION_FILE: &str = "session.cbor";
+
+/// A view opened on top of a screen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ViewSession {
+    /// The open.spotify.com URL of the album, artist, playlist or show shown in the view.
+    pub url: String,
+    pub position: Option<usize>,
+}
+
+/// A screen and the views opened on top of it, bottom to top.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScreenSession {
+    pub name: String,
+    pub position: Option<usize>,
+    pub views: Vec<ViewSession>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    /// The focused screen.
+    pub focus: Option<String>,
+    pub split: Option<[String; 2]>,
+    pub library_tab: Option<LibraryTab>,
+    pub screens: Vec<ScreenSession>,
+}
+
+impl Session {
+    /// The session saved when ncspot was last quit, or an empty one.
+    pub fn load() -> Self {
+        let path = config::cache_path(SESSION_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+        CBOR.load(&path).unwrap_or_else(|e| {
+            warn!("Could not load the last session: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = CBOR.write(config::cache_path(SESSION_FILE), self) {
+            warn!("Could not save the session: {e}");
+        }
+    }
+}
+
+/// What to show after startup, set by `startup_view` in the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartupView {
+    Screen(String),
+    LibraryTab(LibraryTab),
+    /// An album, artist, playlist or show, opened on the library screen.
+    Item(SpotifyUrl),
+    /// The screen and the view that were focused when ncspot was quit.
+    Last,
+}
+
+impl FromStr for StartupView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "last" {
+            return Ok(Self::Last);
+        }
+        if let Some(tab) = s.strip_prefix("library:") {
+            return tab
+                .parse()
+                .map(Self::LibraryTab)
+                .map_err(|_| format!("Invalid library tab: {tab}"));
+        }
+        if let Some(url) = SpotifyUrl::from_url(s).or_else(|| SpotifyUrl::from_uri(s)) {
+            return match url.uri_type {
+                UriType::Album | UriType::Artist | UriType::Playlist | UriType::Show => {
+                    Ok(Self::Item(url))
+                }
+                UriType::Track | UriType::Episode => Err(format!(
+                    "Tracks and episodes can't be opened as a view: {s}"
+                )),
+            };
+        }
+        Ok(Self::Screen(s.to_string()))
+    }
+}
+
+/// Fetch the item `url` points to and open a view for it.
+pub fn open(
+    url: &SpotifyUrl,
+    queue: &Arc<Queue>,
+    library: &Arc<Library>,
+) -> Option<Box<dyn ViewExt>> {
+    let spotify = queue.get_spotify();
+    let api = &spotify.api;
+    let item = match url.uri_type {
+        UriType::Album => api
+            .album(&url.id)
+            .ok()
+            .map(|a| Album::from(&a).as_listitem()),
+        UriType::Artist => api
+            .artist(&url.id)
+            .ok()
+            .map(|a| Artist::from(&a).as_listitem()),
+        UriType::Playlist => api
+            .playlist(&url.id)
+            .ok()
+            .map(|p| Playlist::from(&p).as_listitem()),
+        UriType::Show => api.show(&url.id).ok().map(|s| Show::from(&s).as_listitem()),
+        UriType::Track | UriType::Episode => None,
+    };
+    if item.is_none() {
+        debug!("Could not fetch {url} to open it");
+    }
+    item?.open(queue.clone(), library.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_startup_view() {
+        assert_eq!("last".parse(), Ok(StartupView::Last));
+        assert_eq!(
+            "queue".parse(),
+            Ok(StartupView::Screen("queue".to_string()))
+        );
+        assert_eq!(
+            "library:playlists".parse(),
+            Ok(StartupView::LibraryTab(LibraryTab::Playlists))
+        );
+        assert!("library:nothing".parse::<StartupView>().is_err());
+        assert!(matches!(
+            "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M".parse(),
+            Ok(StartupView::Item(url))
+                if url.id == "37i9dQZF1DXcBWIGoYBM5M" && url.uri_type == UriType::Playlist
+        ));
+        assert!(matches!(
+            "https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy".parse(),
+            Ok(StartupView::Item(url)) if url.uri_type == UriType::Album
+        ));
+        assert!(
+            "spotify:track:4uLU6hMCjMI75M1A2tKUQC"
+                .parse::<StartupView>()
+                .is_err()
+        );
+    }
+}
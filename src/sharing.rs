@@ -1,6 +1,11 @@
 #![cfg(feature = "share_clipboard")]
 use arboard::Clipboard;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use log::debug;
+use std::env;
 use std::error::Error;
+use std::io::{self, Write};
 
 #[cfg(feature = "share_selection")]
 use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
@@ -15,7 +20,20 @@ pub fn read_share() -> Result<String, Box<dyn Error>> {
     return Ok(ctx.get_text()?);
 }
 
+/// Copy `url` to the system clipboard. Over SSH, or when there is no clipboard to talk to, the
+/// terminal is asked to copy it with an OSC 52 escape sequence instead.
 pub fn write_share(url: String) -> Result<(), Box<dyn Error>> {
+    if is_remote_session() {
+        return write_osc52(&url);
+    }
+
+    write_clipboard(url.clone()).or_else(|e| {
+        debug!("could not write to the clipboard, falling back to OSC 52: {e}");
+        write_osc52(&url)
+    })
+}
+
+fn write_clipboard(url: String) -> Result<(), Box<dyn Error>> {
     let mut ctx = Clipboard::new()?;
 
     #[cfg(feature = "share_selection")]
@@ -24,3 +42,53 @@ pub fn write_share(url: String) -> Result<(), Box<dyn Error>> {
     #[cfg(not(feature = "share_selection"))]
     return Ok(ctx.set_text(url)?);
 }
+
+/// Whether ncspot runs on another machine than the terminal it is shown in.
+fn is_remote_session() -> bool {
+    env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some()
+}
+
+fn write_osc52(text: &str) -> Result<(), Box<dyn Error>> {
+    let sequence = osc52_sequence(text, env::var_os("TMUX").is_some());
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The OSC 52 sequence that copies `text`, wrapped for tmux to pass it on to the terminal.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let selection = if cfg!(feature = "share_selection") {
+        "p"
+    } else {
+        "c"
+    };
+    let sequence = format!("\x1b]52;{selection};{}\x07", BASE64.encode(text));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::osc52_sequence;
+
+    #[test]
+    fn test_osc52_sequence() {
+        let selection = if cfg!(feature = "share_selection") {
+            "p"
+        } else {
+            "c"
+        };
+        assert_eq!(
+            osc52_sequence("ncspot", false),
+            format!("\x1b]52;{selection};bmNzcG90\x07")
+        );
+        assert_eq!(
+            osc52_sequence("ncspot", true),
+            format!("\x1bPtmux;\x1b\x1b]52;{selection};bmNzcG90\x07\x1b\\")
+        );
+    }
+}
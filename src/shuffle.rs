@@ -0,0 +1,184 @@
+//! The shuffle modes of the queue, which decide the order in which its items are played while
+//! shuffle is enabled.
+
+use std::collections::HashMap;
+
+use rand::prelude::*;
+use strum_macros::Display;
+
+use crate::model::playable::Playable;
+use crate::play_history::Play;
+
+/// How many of the following items are looked at to find one by another artist.
+const SPREAD_WINDOW: usize = 50;
+
+/// Items that weren't played in this many days are all equally fresh.
+const FRESH_DAYS: i64 = 30;
+
+#[derive(Display, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ShuffleMode {
+    /// Every order is equally likely.
+    #[default]
+    Random,
+    /// Like random, but avoids playing the same artist or album back-to-back.
+    Spread,
+    /// Often played items tend to come first.
+    Played,
+    /// Items that weren't played for a long time tend to come first.
+    Fresh,
+    /// Like random, but a new order is made every time a repeating queue starts over, so nothing
+    /// is played twice before everything was played.
+    Bag,
+}
+
+impl ShuffleMode {
+    pub const ALL: [Self; 5] = [
+        Self::Random,
+        Self::Spread,
+        Self::Played,
+        Self::Fresh,
+        Self::Bag,
+    ];
+
+    /// Whether the mode uses the play history.
+    pub fn uses_history(&self) -> bool {
+        matches!(self, Self::Played | Self::Fresh)
+    }
+}
+
+/// Shuffle `indexes` into `queue` according to `mode`. `plays` is the play history used by the
+/// weighted modes and `now` the current time in seconds since the Unix epoch.
+pub fn shuffle(
+    mode: ShuffleMode,
+    mut indexes: Vec<usize>,
+    queue: &[Playable],
+    plays: &[Play],
+    now: i64,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
+    match mode {
+        ShuffleMode::Random | ShuffleMode::Bag | ShuffleMode::Spread => {
+            indexes.shuffle(rng);
+            indexes
+        }
+        ShuffleMode::Played | ShuffleMode::Fresh => {
+            let mut stats: HashMap<&str, (u32, i64)> = HashMap::new();
+            for play in plays.iter().filter(|play| play.counts()) {
+                let (count, last) = stats.entry(play.uri.as_str()).or_default();
+                *count += 1;
+                *last = (*last).max(play.started);
+            }
+            let weight = |index: usize| -> f64 {
+                let stat = stats.get(queue[index].uri().as_str());
+                match (mode, stat) {
+                    (ShuffleMode::Played, Some((count, _))) => 1.0 + *count as f64,
+                    (ShuffleMode::Fresh, Some((_, last))) => {
+                        1.0 + ((now - last) / 86400).clamp(0, FRESH_DAYS) as f64
+                    }
+                    (ShuffleMode::Fresh, None) => 1.0 + FRESH_DAYS as f64,
+                    _ => 1.0,
+                }
+            };
+            // Weighted random sampling without replacement: sorting by u^(1/w), with u uniform,
+            // draws the items with a probability proportional to their weight.
+            let mut keyed: Vec<(f64, usize)> = indexes
+                .into_iter()
+                .map(|index| (rng.random::<f64>().ln() / weight(index), index))
+                .collect();
+            keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+            keyed.into_iter().map(|(_, index)| index).collect()
+        }
+    }
+}
+
+/// The artists and the album of `playable`. Episodes have neither.
+fn origin(playable: &Playable) -> (Vec<&str>, Option<&str>) {
+    match playable {
+        Playable::Track(track) => (
+            track.artists.iter().map(String::as_str).collect(),
+            track.album.as_deref(),
+        ),
+        Playable::Episode(_) => (Vec::new(), None),
+    }
+}
+
+/// Reorder `order` so that, where possible, no item follows one by the same artist or from the
+/// same album. The first item stays in place.
+pub fn spread(order: Vec<usize>, queue: &[Playable]) -> Vec<usize> {
+    let mut remaining = order;
+    if remaining.is_empty() {
+        return remaining;
+    }
+    let mut spread = vec![remaining.remove(0)];
+    while !remaining.is_empty() {
+        let (artists, album) = origin(&queue[*spread.last().unwrap()]);
+        let next = remaining
+            .iter()
+            .take(SPREAD_WINDOW)
+            .position(|&index| {
+                let (other_artists, other_album) = origin(&queue[index]);
+                (album.is_none() || other_album != album)
+                    && !other_artists.iter().any(|artist| artists.contains(artist))
+            })
+            .unwrap_or(0);
+        spread.push(remaining.remove(next));
+    }
+    spread
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::model::track::Track;
+
+    fn track(artist: &str, album: &str) -> Playable {
+        Playable::Track(Track::test(&format!("{artist}-{album}"), artist, album))
+    }
+
+    #[test]
+    fn test_spread() {
+        let queue = [
+            track("Bowie", "Heroes"),
+            track("Bowie", "Low"),
+            track("Bowie", "Lodger"),
+            track("Eno", "Apollo"),
+            track("Eno", "Another Green World"),
+            track("Can", "Tago Mago"),
+        ];
+        let order = spread((0..queue.len()).collect(), &queue);
+
+        assert_eq!(order[0], 0);
+        assert_eq!(order.len(), queue.len());
+        for pair in order.windows(2) {
+            assert_ne!(origin(&queue[pair[0]]).0, origin(&queue[pair[1]]).0);
+        }
+    }
+
+    #[test]
+    fn test_played() {
+        let queue = [track("Bowie", "Heroes"), track("Eno", "Apollo")];
+        let plays: Vec<Play> = (0..50)
+            .map(|started| Play {
+                started,
+                uri: queue[1].uri(),
+                title: String::new(),
+                artists: Vec::new(),
+                album: None,
+                duration: 1000,
+                played: 1000,
+            })
+            .collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        let first_played = (0..100)
+            .filter(|_| {
+                shuffle(ShuffleMode::Played, vec![0, 1], &queue, &plays, 0, &mut rng)[0] == 1
+            })
+            .count();
+
+        assert!(first_played > 80);
+    }
+}
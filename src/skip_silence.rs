@@ -0,0 +1,149 @@
+//! Skipping long pauses in podcast episodes. The audio passes through a sink that forwards
+//! everything to the real one, except for silence that lasts longer than a short pause.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use librespot_playback::SAMPLES_PER_SECOND;
+use librespot_playback::audio_backend::{Sink, SinkResult};
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::AudioPacket;
+use strum_macros::Display;
+
+/// How eagerly pauses are skipped.
+#[derive(Display, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SkipSilence {
+    #[default]
+    Off,
+    /// Only skips long, almost inaudible pauses.
+    Gentle,
+    Normal,
+    /// Also skips short and not quite silent pauses.
+    Aggressive,
+}
+
+impl SkipSilence {
+    /// The peak level below which audio counts as silent, and how much of a pause is kept.
+    fn settings(self) -> Option<(f64, Duration)> {
+        match self {
+            Self::Off => None,
+            Self::Gentle => Some((db_to_level(-50.0), Duration::from_millis(1500))),
+            Self::Normal => Some((db_to_level(-45.0), Duration::from_millis(750))),
+            Self::Aggressive => Some((db_to_level(-40.0), Duration::from_millis(300))),
+        }
+    }
+}
+
+fn db_to_level(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Shared between the player, which applies it, and [crate::spotify::Spotify], which turns it
+/// on for episodes and accounts for the skipped time in the playback progress.
+#[derive(Clone, Default)]
+pub struct SilenceSkipping {
+    mode: Arc<RwLock<SkipSilence>>,
+    /// The time skipped since the player last reported its position, in microseconds.
+    skipped_us: Arc<AtomicU64>,
+}
+
+impl SilenceSkipping {
+    pub fn set_mode(&self, mode: SkipSilence) {
+        *self.mode.write().unwrap() = mode;
+    }
+
+    pub fn skipped(&self) -> Duration {
+        Duration::from_micros(self.skipped_us.load(Ordering::Relaxed))
+    }
+
+    /// Forget the skipped time, once the player reported where playback is.
+    pub fn reset_skipped(&self) {
+        self.skipped_us.store(0, Ordering::Relaxed);
+    }
+
+    /// Wrap `sink` to skip silence as configured.
+    pub fn sink(&self, sink: Box<dyn Sink>) -> Box<dyn Sink> {
+        Box::new(SkipSilenceSink {
+            sink,
+            skipping: self.clone(),
+            gate: Gate::default(),
+        })
+    }
+}
+
+/// Decides which packets are passed on, by how long the audio has been silent.
+#[derive(Default)]
+struct Gate {
+    silent: Duration,
+}
+
+impl Gate {
+    /// Whether the packet of `samples` is played, or skipped as part of a long pause.
+    fn pass(&mut self, samples: &[f64], level: f64, keep: Duration) -> bool {
+        let peak = samples.iter().fold(0.0, |peak: f64, s| peak.max(s.abs()));
+        if peak >= level {
+            self.silent = Duration::ZERO;
+            return true;
+        }
+        self.silent += packet_duration(samples);
+        self.silent <= keep
+    }
+}
+
+fn packet_duration(samples: &[f64]) -> Duration {
+    Duration::from_secs_f64(samples.len() as f64 / SAMPLES_PER_SECOND as f64)
+}
+
+struct SkipSilenceSink {
+    sink: Box<dyn Sink>,
+    skipping: SilenceSkipping,
+    gate: Gate,
+}
+
+impl Sink for SkipSilenceSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.gate = Gate::default();
+        self.sink.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.sink.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        let mode = *self.skipping.mode.read().unwrap();
+        if let (Some((level, keep)), AudioPacket::Samples(samples)) = (mode.settings(), &packet)
+            && !self.gate.pass(samples, level, keep)
+        {
+            let skipped = packet_duration(samples).as_micros() as u64;
+            self.skipping
+                .skipped_us
+                .fetch_add(skipped, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.sink.write(packet, converter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate() {
+        let (level, keep) = SkipSilence::Normal.settings().unwrap();
+        // 100 ms of audio per packet.
+        let silence = vec![0.0; SAMPLES_PER_SECOND as usize / 10];
+        let speech = vec![0.5; SAMPLES_PER_SECOND as usize / 10];
+        let mut gate = Gate::default();
+
+        assert!(gate.pass(&speech, level, keep));
+        let passed = (0..20).filter(|_| gate.pass(&silence, level, keep)).count();
+        assert_eq!(passed, 7);
+        assert!(gate.pass(&speech, level, keep));
+        assert!(gate.pass(&silence, level, keep));
+    }
+}
@@ -0,0 +1,464 @@
+//! Playlists made of the saved tracks that match rules from the configuration, i.e. "the saved
+//! tracks by Metallica added in the last 90 days". They are evaluated locally, and can be written
+//! to a Spotify playlist of the same name regularly to have them on other devices too.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{debug, error, info};
+
+use crate::config::{Config, ConfigSmartPlaylist};
+use crate::library::Library;
+use crate::model::audio_features::AudioFeatures;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
+use crate::play_history;
+use crate::storage::Namespace;
+
+/// Storage key of when the smart playlists were last written to Spotify.
+const SYNC_STATE_KEY: &str = "smart_playlists.json";
+
+/// How often to check whether a smart playlist is due to be written to Spotify.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// A property of a track that rules can test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    /// The genres of the artists of the track.
+    Genre,
+    /// Days since the track was saved.
+    Added,
+    /// In seconds.
+    Duration,
+    Bpm,
+    /// In percent.
+    Energy,
+    /// In percent.
+    Danceability,
+    /// How often the track was played, from the play history.
+    Plays,
+}
+
+impl Field {
+    fn is_text(self) -> bool {
+        matches!(self, Self::Title | Self::Artist | Self::Album | Self::Genre)
+    }
+
+    fn is_audio_feature(self) -> bool {
+        matches!(self, Self::Bpm | Self::Energy | Self::Danceability)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Contains(String),
+    Is(String),
+    Less(f64),
+    Greater(f64),
+}
+
+/// A single condition on a field, i.e. `artist contains metallica` or `added < 90`.
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    field: Field,
+    condition: Condition,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("Invalid rule \"{rule}\": {reason}");
+        let mut words = rule.split_whitespace();
+        let (Some(field), Some(operator)) = (words.next(), words.next()) else {
+            return Err(invalid("expected <field> <operator> <value>"));
+        };
+        let value = words.collect::<Vec<_>>().join(" ");
+        let value = value.trim_matches('"').to_lowercase();
+        if value.is_empty() {
+            return Err(invalid("missing value"));
+        }
+
+        let field = match field {
+            "title" => Field::Title,
+            "artist" => Field::Artist,
+            "album" => Field::Album,
+            "genre" => Field::Genre,
+            "added" => Field::Added,
+            "duration" => Field::Duration,
+            "bpm" => Field::Bpm,
+            "energy" => Field::Energy,
+            "danceability" => Field::Danceability,
+            "plays" => Field::Plays,
+            _ => return Err(invalid("unknown field")),
+        };
+        let condition = match (operator, field.is_text()) {
+            ("contains", true) => Condition::Contains(value),
+            ("is", true) => Condition::Is(value),
+            ("<" | ">", false) => {
+                let number = parse_number(field, &value).ok_or_else(|| invalid("not a number"))?;
+                if operator == "<" {
+                    Condition::Less(number)
+                } else {
+                    Condition::Greater(number)
+                }
+            }
+            ("contains" | "is", false) => return Err(invalid("use < or > for numbers")),
+            ("<" | ">", true) => return Err(invalid("use contains or is for text")),
+            _ => return Err(invalid("unknown operator")),
+        };
+        Ok(Self { field, condition })
+    }
+}
+
+/// Parse a number, durations can also be given as minutes and seconds like `4:30`.
+fn parse_number(field: Field, value: &str) -> Option<f64> {
+    match value.split_once(':') {
+        Some((minutes, seconds)) if field == Field::Duration => {
+            Some(minutes.parse::<f64>().ok()? * 60.0 + seconds.parse::<f64>().ok()?)
+        }
+        _ => value.parse().ok(),
+    }
+}
+
+/// The details of a track that aren't part of it, looked up when a rule needs them.
+struct Details<'a> {
+    features: &'a dyn Fn() -> Option<AudioFeatures>,
+    genres: &'a dyn Fn() -> Option<Vec<String>>,
+    plays: &'a dyn Fn() -> usize,
+}
+
+impl Rule {
+    /// Whether `track` matches. Tracks without a value for the field, like tracks whose audio
+    /// features or genres aren't known yet, don't match. Genres match if any of them does.
+    fn matches(&self, track: &Track, details: &Details, now: DateTime<Utc>) -> bool {
+        let texts = match self.field {
+            Field::Title => Some(vec![track.title.to_lowercase()]),
+            Field::Artist => Some(vec![track.artists.join(", ").to_lowercase()]),
+            Field::Album => Some(vec![track.album.clone().unwrap_or_default().to_lowercase()]),
+            Field::Genre => (details.genres)()
+                .map(|genres| genres.iter().map(|genre| genre.to_lowercase()).collect()),
+            _ => None,
+        };
+        let features = details.features;
+        let number = || match self.field {
+            Field::Added => track
+                .added_at
+                .map(|added_at| (now - added_at).num_seconds() as f64 / 86400.0),
+            Field::Duration => Some(f64::from(track.duration) / 1000.0),
+            Field::Bpm => features().map(|features| f64::from(features.tempo)),
+            Field::Energy => features().map(|features| f64::from(features.energy) * 100.0),
+            Field::Danceability => {
+                features().map(|features| f64::from(features.danceability) * 100.0)
+            }
+            Field::Plays => Some((details.plays)() as f64),
+            _ => None,
+        };
+        match &self.condition {
+            Condition::Contains(value) => {
+                texts.is_some_and(|texts| texts.iter().any(|text| text.contains(value.as_str())))
+            }
+            Condition::Is(value) => texts.is_some_and(|texts| texts.contains(value)),
+            Condition::Less(value) => number().is_some_and(|number| number < *value),
+            Condition::Greater(value) => number().is_some_and(|number| number > *value),
+        }
+    }
+}
+
+/// A smart playlist from the configuration with its rules parsed.
+pub struct SmartPlaylist {
+    pub name: String,
+    rules: Vec<Rule>,
+    any: bool,
+    source: Option<String>,
+    limit: Option<usize>,
+}
+
+impl SmartPlaylist {
+    pub fn new(config: &ConfigSmartPlaylist) -> Result<Self, String> {
+        Ok(Self {
+            name: config.name.clone(),
+            rules: config
+                .rules
+                .iter()
+                .map(|rule| rule.parse())
+                .collect::<Result<_, _>>()?,
+            any: config.any.unwrap_or(false),
+            source: config.source.clone(),
+            limit: config.limit,
+        })
+    }
+
+    /// The smart playlist called `name` in the configuration.
+    pub fn find(cfg: &Config, name: &str) -> Result<Self, String> {
+        let playlists = cfg.values().smart_playlists.clone().unwrap_or_default();
+        match playlists.iter().find(|playlist| playlist.name == name) {
+            Some(playlist) => Self::new(playlist),
+            None => {
+                let names: Vec<&str> = playlists.iter().map(|p| p.name.as_str()).collect();
+                Err(format!(
+                    "No smart playlist \"{name}\", configured are: {}",
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// The matching tracks from the saved tracks, or from the `source` playlist, in their order.
+    pub fn tracks(&self, library: &Library) -> Result<Vec<Track>, String> {
+        let candidates: Vec<Track> = match &self.source {
+            Some(source) => library
+                .playlists
+                .read()
+                .unwrap()
+                .iter()
+                .find(|playlist| &playlist.name == source)
+                .ok_or_else(|| format!("No playlist \"{source}\" in the library"))?
+                .tracks
+                .iter()
+                .flatten()
+                .filter_map(|playable| match playable {
+                    Playable::Track(track) => Some(track.clone()),
+                    Playable::Episode(_) => None,
+                })
+                .collect(),
+            None => library.tracks.read().unwrap().clone(),
+        };
+
+        let needs_features = self.rules.iter().any(|rule| rule.field.is_audio_feature());
+        let play_counts = if self.rules.iter().any(|rule| rule.field == Field::Plays) {
+            play_history::play_counts(&play_history::load(library.cfg.storage().as_ref(), 0))
+        } else {
+            HashMap::new()
+        };
+        let now = Utc::now();
+        let matching = candidates.into_iter().filter(|track| {
+            let features = || match &track.id {
+                Some(id) if needs_features => library.audio_features(id),
+                _ => None,
+            };
+            // Ask for the genres of all artists at once, so they are fetched in one batch.
+            let genres = || {
+                let genres: Vec<Option<Vec<String>>> = track
+                    .artist_ids
+                    .iter()
+                    .map(|id| library.artist_genres(id))
+                    .collect();
+                genres
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .map(|genres| genres.concat())
+            };
+            let plays = || play_counts.get(&track.uri).copied().unwrap_or(0);
+            let details = Details {
+                features: &features,
+                genres: &genres,
+                plays: &plays,
+            };
+            let mut results = self
+                .rules
+                .iter()
+                .map(|rule| rule.matches(track, &details, now));
+            if self.any {
+                results.any(|matches| matches)
+            } else {
+                results.all(|matches| matches)
+            }
+        });
+        Ok(match self.limit {
+            Some(limit) => matching.take(limit).collect(),
+            None => matching.collect(),
+        })
+    }
+}
+
+/// Writes the smart playlists with `sync_hours` to Spotify when they are due.
+pub struct SmartPlaylistSync {
+    cfg: Arc<Config>,
+    library: Arc<Library>,
+    next_check: Instant,
+}
+
+impl SmartPlaylistSync {
+    pub fn new(cfg: Arc<Config>, library: Arc<Library>) -> Self {
+        Self {
+            cfg,
+            library,
+            next_check: Instant::now(),
+        }
+    }
+
+    /// Check from time to time whether a smart playlist is due, once the library is loaded.
+    pub fn tick(&mut self) {
+        if Instant::now() < self.next_check || !*self.library.is_done.read().unwrap() {
+            return;
+        }
+        self.next_check = Instant::now() + CHECK_INTERVAL;
+
+        let now = Utc::now();
+        let mut synced = load_sync_state(&self.cfg);
+        let due: Vec<ConfigSmartPlaylist> = self
+            .cfg
+            .values()
+            .smart_playlists
+            .iter()
+            .flatten()
+            .filter(|playlist| {
+                playlist.sync_hours.is_some_and(|hours| {
+                    synced
+                        .get(&playlist.name)
+                        .is_none_or(|last| now - *last >= chrono::Duration::hours(hours as i64))
+                })
+            })
+            .cloned()
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+
+        for playlist in &due {
+            synced.insert(playlist.name.clone(), now);
+        }
+        save_sync_state(&self.cfg, &synced);
+        let library = self.library.clone();
+        thread::spawn(move || {
+            for playlist in due {
+                if let Err(e) = SmartPlaylist::new(&playlist).and_then(|p| write(&p, &library)) {
+                    error!("could not sync smart playlist {}: {e}", playlist.name);
+                }
+            }
+        });
+    }
+}
+
+/// Write the tracks of `playlist` to the Spotify playlist of the user with the same name, creating
+/// it if needed. It is only changed if the tracks are different.
+fn write(playlist: &SmartPlaylist, library: &Library) -> Result<(), String> {
+    let tracks: Vec<Playable> = playlist
+        .tracks(library)?
+        .into_iter()
+        .map(Playable::Track)
+        .collect();
+    let existing = library
+        .playlists
+        .read()
+        .unwrap()
+        .iter()
+        .find(|remote| {
+            remote.name == playlist.name && Some(&remote.owner_id) == library.user_id.as_ref()
+        })
+        .cloned();
+    match existing {
+        Some(remote) => {
+            let uris = |tracks: &[Playable]| tracks.iter().map(Playable::uri).collect::<Vec<_>>();
+            if remote.tracks.as_deref().map(uris) == Some(uris(&tracks)) {
+                debug!("smart playlist {} is unchanged", playlist.name);
+                return Ok(());
+            }
+            info!("updating smart playlist {}", playlist.name);
+            library.overwrite_playlist(&remote.id, &tracks);
+        }
+        None => {
+            info!("creating smart playlist {}", playlist.name);
+            library.save_playlist(&playlist.name, &tracks);
+        }
+    }
+    Ok(())
+}
+
+fn load_sync_state(cfg: &Config) -> HashMap<String, DateTime<Utc>> {
+    cfg.storage()
+        .read(Namespace::State, SYNC_STATE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_sync_state(cfg: &Config, synced: &HashMap<String, DateTime<Utc>>) {
+    let result = serde_json::to_vec(synced)
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            cfg.storage()
+                .write(Namespace::State, SYNC_STATE_KEY, &content)
+        });
+    if let Err(e) = result {
+        error!("could not save the smart playlist state: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn track(title: &str, artist: &str, duration: u32, added_days_ago: i64) -> Track {
+        Track {
+            duration,
+            added_at: Some(now() - chrono::Duration::days(added_days_ago)),
+            ..Track::test(title, artist, "Master of Puppets")
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn matches(rule: &str, track: &Track) -> bool {
+        let details = Details {
+            features: &|| None,
+            genres: &|| Some(vec!["Thrash Metal".into(), "Rock".into()]),
+            plays: &|| 3,
+        };
+        rule.parse::<Rule>()
+            .unwrap()
+            .matches(track, &details, now())
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            "artist contains \"Iron Maiden\"".parse::<Rule>(),
+            Ok(Rule {
+                field: Field::Artist,
+                condition: Condition::Contains("iron maiden".into())
+            })
+        );
+        assert_eq!(
+            "duration > 4:30".parse::<Rule>().map(|rule| rule.condition),
+            Ok(Condition::Greater(270.0))
+        );
+        assert!("artist < 3".parse::<Rule>().is_err());
+        assert!("added contains 3".parse::<Rule>().is_err());
+        assert!("genre < 3".parse::<Rule>().is_err());
+        assert!("plays contains 3".parse::<Rule>().is_err());
+        assert!("title contains".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let battery = track("Battery", "Metallica", 312_000, 10);
+        assert!(matches("artist contains metal", &battery));
+        assert!(matches("title is battery", &battery));
+        assert!(!matches("title is bat", &battery));
+        assert!(matches("added < 90", &battery));
+        assert!(!matches("added > 90", &battery));
+        assert!(matches("duration > 5:00", &battery));
+        // Unknown audio features never match.
+        assert!(!matches("bpm > 0", &battery));
+        assert!(!matches("bpm < 1000", &battery));
+        assert!(matches("genre contains metal", &battery));
+        assert!(matches("genre is rock", &battery));
+        assert!(!matches("genre is metal", &battery));
+        assert!(matches("plays > 2", &battery));
+        assert!(!matches("plays > 3", &battery));
+    }
+}
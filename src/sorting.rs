@@ -0,0 +1,176 @@
+//! Sorting the items of list views and grouping the tracks of playlists.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::command::{GroupBy, SortDirection, SortKey};
+use crate::model::track::Track;
+use crate::traits::ListItem;
+
+/// The values a list item is sorted and grouped by. Items only fill in the ones they have.
+#[derive(Clone, Debug, Default)]
+pub struct SortFields {
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub disc_number: i32,
+    pub track_number: u32,
+    pub added_at: Option<DateTime<Utc>>,
+    pub duration: u32,
+    pub popularity: Option<u32>,
+    pub year: Option<u32>,
+}
+
+impl From<&Track> for SortFields {
+    fn from(track: &Track) -> Self {
+        Self {
+            title: track.title.clone(),
+            artists: track.artists.clone(),
+            album: track.album.clone(),
+            disc_number: track.disc_number,
+            track_number: track.track_number,
+            added_at: track.added_at,
+            duration: track.duration,
+            popularity: track.popularity,
+            year: track.release_year,
+        }
+    }
+}
+
+/// Sort `items` by `key`. Items that can't be sorted keep their order after the others.
+pub fn sort<I: ListItem>(items: &mut Vec<I>, key: &SortKey, direction: &SortDirection) {
+    let mut keyed: Vec<(Option<SortFields>, I)> = items
+        .drain(..)
+        .map(|item| (item.sort_fields(), item))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => match direction {
+            SortDirection::Ascending => compare(a, b, key),
+            SortDirection::Descending => compare(b, a, key),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+}
+
+fn compare(a: &SortFields, b: &SortFields, key: &SortKey) -> Ordering {
+    match key {
+        SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        SortKey::Duration => a.duration.cmp(&b.duration),
+        SortKey::Album => compare_album(a, b),
+        SortKey::Added => a.added_at.cmp(&b.added_at),
+        SortKey::Artist => {
+            compare_artists(&a.artists, &b.artists).then_with(|| compare_album(a, b))
+        }
+        SortKey::Popularity => a.popularity.cmp(&b.popularity),
+        SortKey::Year => a.year.cmp(&b.year).then_with(|| compare_album(a, b)),
+    }
+}
+
+/// Compare artists by name, ignoring case and a leading "The".
+fn compare_artists(a: &[String], b: &[String]) -> Ordering {
+    let sanitize_artists_name = |x: &[String]| -> Vec<String> {
+        x.iter()
+            .map(|x| {
+                x.to_lowercase()
+                    .split(' ')
+                    .skip_while(|x| x == &"the")
+                    .collect()
+            })
+            .collect()
+    };
+
+    sanitize_artists_name(a).cmp(&sanitize_artists_name(b))
+}
+
+fn compare_album(a: &SortFields, b: &SortFields) -> Ordering {
+    a.album
+        .as_ref()
+        .map(|x| x.to_lowercase())
+        .cmp(&b.album.as_ref().map(|x| x.to_lowercase()))
+        .then_with(|| a.disc_number.cmp(&b.disc_number))
+        .then_with(|| a.track_number.cmp(&b.track_number))
+}
+
+/// The name of the group an item belongs to when grouping by `by`.
+fn group_name(fields: Option<SortFields>, by: GroupBy) -> String {
+    let fields = fields.unwrap_or_default();
+    match by {
+        GroupBy::Album => fields.album.unwrap_or_else(|| "No album".to_string()),
+        GroupBy::Artist => fields
+            .artists
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "No artist".to_string()),
+    }
+}
+
+/// Split `items` into named groups, in the order of their first items. The items keep their order
+/// inside of their group.
+pub fn group<I: ListItem + Clone>(items: &[I], by: GroupBy) -> Vec<(String, Vec<I>)> {
+    let mut groups: Vec<(String, Vec<I>)> = Vec::new();
+    let mut positions = HashMap::new();
+    for item in items {
+        let name = group_name(item.sort_fields(), by);
+        let position = *positions.entry(name.clone()).or_insert_with(|| {
+            groups.push((name, Vec::new()));
+            groups.len() - 1
+        });
+        groups[position].1.push(item.clone());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, artist: &str, album: &str, popularity: u32) -> Track {
+        Track {
+            popularity: Some(popularity),
+            ..Track::test(title, artist, album)
+        }
+    }
+
+    fn titles(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut tracks = vec![
+            track("Heroes", "David Bowie", "Heroes", 70),
+            track("Airbag", "Radiohead", "OK Computer", 60),
+            track("Breathe", "The Prodigy", "The Fat of the Land", 50),
+        ];
+
+        sort(&mut tracks, &SortKey::Title, &SortDirection::Ascending);
+        assert_eq!(titles(&tracks), ["Airbag", "Breathe", "Heroes"]);
+        sort(&mut tracks, &SortKey::Artist, &SortDirection::Ascending);
+        assert_eq!(titles(&tracks), ["Heroes", "Breathe", "Airbag"]);
+        sort(
+            &mut tracks,
+            &SortKey::Popularity,
+            &SortDirection::Descending,
+        );
+        assert_eq!(titles(&tracks), ["Heroes", "Airbag", "Breathe"]);
+    }
+
+    #[test]
+    fn test_group() {
+        let tracks = [
+            track("Airbag", "Radiohead", "OK Computer", 0),
+            track("Heroes", "David Bowie", "Heroes", 0),
+            track("Karma Police", "Radiohead", "OK Computer", 0),
+        ];
+
+        let groups = group(&tracks, GroupBy::Artist);
+        let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Radiohead", "David Bowie"]);
+        assert_eq!(titles(&groups[0].1), ["Airbag", "Karma Police"]);
+    }
+}
@@ -1,7 +1,8 @@
 use std::error::Error;
 use std::str::FromStr;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use std::{env, fmt};
 
 use futures::channel::oneshot;
@@ -11,32 +12,58 @@ use librespot_core::config::SessionConfig;
 use librespot_core::session::Session;
 use librespot_playback::audio_backend;
 use librespot_playback::audio_backend::SinkBuilder;
-use librespot_playback::config::Bitrate;
-use librespot_playback::config::PlayerConfig;
+use librespot_playback::config::{AudioFormat, Bitrate};
+use librespot_playback::config::{NormalisationMethod, PlayerConfig};
 use librespot_playback::mixer::MixerConfig;
 use librespot_playback::mixer::softmixer::SoftMixer;
 use librespot_playback::player::Player;
 use log::{debug, error, info, warn};
 use tokio::sync::mpsc;
-use url::Url;
 
+use crate::api_cache::ApiCache;
 use crate::application::ASYNC_RUNTIME;
 use crate::authentication::SPOTIFY_CLIENT_ID;
-use crate::config;
+use crate::caches::{self, CacheKind};
+use crate::command::SeekDirection;
+use crate::config::{self, VolumeContext};
 use crate::events::{Event, EventManager};
+use crate::gain::{self, PlaybackGain};
+use crate::instance;
+use crate::local_files;
 use crate::model::playable::Playable;
 #[cfg(feature = "mpris")]
 use crate::mpris::{MprisCommand, MprisManager};
+use crate::proxy;
+use crate::rate_limiter;
+use crate::skip_silence::{SilenceSkipping, SkipSilence};
 use crate::spotify_api::WebApi;
 use crate::spotify_worker::{Worker, WorkerCommand};
 use crate::traits::ListItem;
+use crate::ui::pagination::DEFAULT_READ_AHEAD;
+use crate::visualizer::AudioTap;
 
 /// One percent of the maximum supported [Player] volume, used when setting the volume to a certain
 /// percent.
 pub const VOLUME_PERCENT: u16 = ((u16::MAX as f64) * 1.0 / 100.0) as u16;
 
+/// Tracks larger than this, in bytes, aren't preloaded by default. That's a bit over 40 minutes at
+/// 320 kbps.
+const DEFAULT_PRELOAD_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// The default durations of the small and large seek steps in milliseconds.
+const DEFAULT_SEEK_STEP: u32 = 1000;
+const DEFAULT_SEEK_STEP_LARGE: u32 = 10000;
+
+/// How long a relative seek is previewed before seeking, in milliseconds.
+const DEFAULT_SEEK_PREVIEW: u64 = 500;
+
+/// The names of the audio backends this build supports, the default one first.
+pub fn backend_names() -> Vec<&'static str> {
+    audio_backend::BACKENDS.iter().map(|b| b.0).collect()
+}
+
 /// Events sent by the [Player].
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlayerEvent {
     Playing(SystemTime),
     Paused(Duration),
@@ -62,6 +89,18 @@ pub struct Spotify {
     since: Arc<RwLock<Option<SystemTime>>>,
     /// Channel to send commands to the worker thread.
     channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
+    /// The kind of content that was loaded last, whose volume is remembered.
+    volume_context: Arc<RwLock<Option<VolumeContext>>>,
+    /// The item to load when the worker is started, whether to play it and where.
+    resume: Arc<RwLock<Option<(Playable, bool, u32)>>>,
+    /// Skips the pauses in episodes.
+    silence: SilenceSkipping,
+    /// The pre-amp and the volume offset of the loaded track.
+    gain: PlaybackGain,
+    /// Keeps the latest samples for the visualizer.
+    tap: AudioTap,
+    /// The position a series of relative seeks leads to, and when the last one was made.
+    seek_preview: Arc<RwLock<Option<(u32, Instant)>>>,
 }
 
 impl Spotify {
@@ -81,6 +120,12 @@ impl Spotify {
             elapsed: Arc::new(RwLock::new(None)),
             since: Arc::new(RwLock::new(None)),
             channel: Arc::new(RwLock::new(None)),
+            volume_context: Arc::new(RwLock::new(None)),
+            resume: Arc::new(RwLock::new(None)),
+            silence: SilenceSkipping::default(),
+            gain: PlaybackGain::default(),
+            tap: AudioTap::default(),
+            seek_preview: Arc::new(RwLock::new(None)),
         };
 
         let (user_tx, user_rx) = oneshot::channel();
@@ -96,6 +141,27 @@ impl Spotify {
             .map(move |h| ASYNC_RUNTIME.get().unwrap().block_on(h).ok());
 
         spotify.api.set_user(user);
+        spotify.api.set_read_ahead(
+            cfg.values()
+                .pagination_read_ahead
+                .unwrap_or(DEFAULT_READ_AHEAD),
+        );
+        if cfg.values().api_cache.unwrap_or(true) {
+            let size = CacheKind::Api.max_size(&cfg).unwrap_or(u64::MAX);
+            let cache = ApiCache::new(CacheKind::Api.path(), size);
+            spotify.api.set_cache(Some(Arc::new(cache)));
+        }
+        if let Some(size) = CacheKind::Images.max_size(&cfg) {
+            caches::prune(&CacheKind::Images.path(), size);
+        }
+        // A limit of 0 disables the rate limiter.
+        let rate_limit = cfg
+            .values()
+            .api_rate_limit
+            .unwrap_or(rate_limiter::DEFAULT_REQUESTS_PER_SECOND);
+        spotify
+            .api
+            .set_rate_limit((rate_limit > 0.0).then_some(rate_limit));
 
         Ok(spotify)
     }
@@ -113,8 +179,7 @@ impl Spotify {
         let events = self.events.clone();
         let volume = self.volume();
         let credentials = self.credentials.clone();
-        let backend_name = cfg.values().backend.clone();
-        let backend = Self::init_backend(backend_name)?;
+        let backend = Self::init_backend(cfg.backend())?;
         ASYNC_RUNTIME.get().unwrap().spawn(Self::worker(
             worker_channel,
             events,
@@ -124,34 +189,97 @@ impl Spotify {
             user_tx,
             volume,
             backend,
+            self.silence.clone(),
+            self.gain.clone(),
+            self.tap.clone(),
         ));
+        if let Some((playable, playing, position_ms)) = self.resume.write().unwrap().take() {
+            self.load(&playable, playing, position_ms);
+        }
         Ok(())
     }
 
+    /// The name of the audio backend in use.
+    pub fn backend_name(&self) -> Option<&'static str> {
+        let desired = self.cfg.backend();
+        audio_backend::BACKENDS
+            .iter()
+            .find(|backend| desired.as_ref().is_none_or(|name| name == backend.0))
+            .map(|backend| backend.0)
+    }
+
+    /// Play through the audio output `device`, or the configured one if it is `None`. `current`
+    /// continues where it is.
+    pub fn set_output_device(&self, device: Option<String>, current: Option<Playable>) {
+        info!("switching the output device to {device:?}");
+        self.cfg
+            .with_state_mut(|s| s.output_device.clone_from(&device));
+        self.restart_player(current);
+    }
+
+    /// Play through the audio backend `backend`, or the configured one if it is `None`. The output
+    /// device picked for the previous backend is forgotten. `current` continues where it is.
+    pub fn set_backend(
+        &self,
+        backend: Option<String>,
+        current: Option<Playable>,
+    ) -> Result<(), String> {
+        if let Some(name) = &backend
+            && !backend_names().contains(&name.as_str())
+        {
+            return Err(format!(
+                r#"Audio backend "{name}" isn't available, this build has: {}"#,
+                backend_names().join(", ")
+            ));
+        }
+        info!("switching the audio backend to {backend:?}");
+        self.cfg.with_state_mut(|s| {
+            s.backend.clone_from(&backend);
+            s.output_device = None;
+        });
+        self.restart_player(current);
+        Ok(())
+    }
+
+    /// Restart the player to apply a changed player configuration. `current` continues where it
+    /// is once the player is back.
+    pub fn restart_player(&self, current: Option<Playable>) {
+        let playing = match self.get_current_status() {
+            PlayerEvent::Playing(_) => Some(true),
+            PlayerEvent::Paused(_) => Some(false),
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => None,
+        };
+        if let (Some(playable), Some(playing)) = (current, playing) {
+            let position_ms = self.get_current_progress().as_millis() as u32;
+            *self.resume.write().unwrap() = Some((playable, playing, position_ms));
+        }
+        self.shutdown();
+    }
+
     /// Generate the librespot [SessionConfig] used when creating a [Session].
-    pub fn session_config(cfg: &config::Config) -> SessionConfig {
+    pub fn session_config(cfg: &config::Config) -> Result<SessionConfig, librespot_core::Error> {
         let mut session_config = librespot_core::SessionConfig {
             client_id: SPOTIFY_CLIENT_ID.to_string(),
             ..Default::default()
         };
-        match env::var("http_proxy") {
-            Ok(proxy) => {
+        match proxy::session_proxy().map_err(librespot_core::Error::failed_precondition)? {
+            Some(proxy) => {
                 info!("Setting HTTP proxy {proxy}");
-                session_config.proxy = Url::parse(&proxy).ok();
+                session_config.proxy = Some(proxy);
             }
-            Err(_) => debug!("No HTTP proxy set"),
+            None => debug!("No HTTP proxy set"),
         }
         if let Some(ap_port) = cfg.values().ap_port {
             session_config.ap_port = Some(ap_port)
         }
-        session_config
+        Ok(session_config)
     }
 
     pub fn test_credentials(
         cfg: &config::Config,
         credentials: Credentials,
     ) -> Result<Session, librespot_core::Error> {
-        let config = Self::session_config(cfg);
+        let config = Self::session_config(cfg)?;
         let _guard = ASYNC_RUNTIME.get().unwrap().enter();
         let session = Session::new(config, None);
         ASYNC_RUNTIME
@@ -170,19 +298,17 @@ impl Spotify {
         let librespot_cache_path = config::cache_path("librespot");
         let audio_cache_path = match cfg.values().audio_cache {
             Some(false) => None,
-            _ => Some(librespot_cache_path.join("files")),
+            _ => Some(CacheKind::Audio.path()),
         };
         let cache = Cache::new(
             Some(librespot_cache_path.clone()),
             Some(librespot_cache_path.join("volume")),
             audio_cache_path,
-            cfg.values()
-                .audio_cache_size
-                .map(|size| size as u64 * 1048576),
+            CacheKind::Audio.max_size(cfg),
         )
         .expect("Could not create cache");
         debug!("opening spotify session");
-        let session_config = Self::session_config(cfg);
+        let session_config = Self::session_config(cfg)?;
         let session = Session::new(session_config, Some(cache));
         session.connect(credentials, true).await.map(|_| session)
     }
@@ -194,7 +320,8 @@ impl Spotify {
                 .iter()
                 .find(|backend| name == backend.0)
                 .ok_or(format!(
-                    r#"configured audio backend "{name}" can't be found"#
+                    r#"configured audio backend "{name}" can't be found, available: {}"#,
+                    backend_names().join(", ")
                 ))?
         } else {
             audio_backend::BACKENDS
@@ -207,7 +334,7 @@ impl Spotify {
         info!("Initializing audio backend {backend_name}");
         if backend_name == "pulseaudio" {
             // TODO: Audit that the environment access only happens in single-threaded code.
-            unsafe { env::set_var("PULSE_PROP_application.name", "ncspot") };
+            unsafe { env::set_var("PULSE_PROP_application.name", instance::name()) };
             // TODO: Audit that the environment access only happens in single-threaded code.
             unsafe { env::set_var("PULSE_PROP_stream.description", "ncurses Spotify client") };
             // TODO: Audit that the environment access only happens in single-threaded code.
@@ -228,6 +355,9 @@ impl Spotify {
         user_tx: Option<oneshot::Sender<String>>,
         volume: u16,
         backend: SinkBuilder,
+        silence: SilenceSkipping,
+        gain: PlaybackGain,
+        tap: AudioTap,
     ) {
         let bitrate_str = cfg.values().bitrate.unwrap_or(320).to_string();
         let bitrate = Bitrate::from_str(&bitrate_str);
@@ -235,11 +365,23 @@ impl Spotify {
             error!("invalid bitrate, will use 320 instead")
         }
 
+        let gain_settings = cfg.gain_settings();
+        let bitrate = bitrate.unwrap_or(Bitrate::Bitrate320);
+        let preload_max_size = cfg
+            .values()
+            .preload_max_size
+            .map_or(DEFAULT_PRELOAD_MAX_SIZE, |size| size as u64 * 1024 * 1024);
         let player_config = PlayerConfig {
             gapless: cfg.values().gapless.unwrap_or(true),
-            bitrate: bitrate.unwrap_or(Bitrate::Bitrate320),
-            normalisation: cfg.values().volnorm.unwrap_or(false),
-            normalisation_pregain_db: cfg.values().volnorm_pregain.unwrap_or(0.0),
+            bitrate,
+            normalisation: gain_settings.volnorm,
+            normalisation_pregain_db: gain_settings.volnorm_pregain,
+            normalisation_method: if gain_settings.volnorm_limiter {
+                NormalisationMethod::Dynamic
+            } else {
+                NormalisationMethod::Basic
+            },
+            local_file_directories: local_files::directories(&cfg),
             ..Default::default()
         };
 
@@ -254,12 +396,21 @@ impl Spotify {
 
         mixer.set_volume(volume);
 
-        let audio_format: librespot_playback::config::AudioFormat = Default::default();
+        let audio_format = match &cfg.values().backend_format {
+            Some(format) => AudioFormat::from_str(format).unwrap_or_else(|_| {
+                error!("invalid backend_format, will use the default instead");
+                AudioFormat::default()
+            }),
+            None => AudioFormat::default(),
+        };
         let player = Player::new(
             player_config,
             session.clone(),
             mixer.get_soft_volume(),
-            move || (backend)(cfg.values().backend_device.clone(), audio_format),
+            move || {
+                let sink = (backend)(cfg.output_device(), audio_format);
+                silence.sink(gain.sink(tap.sink(sink)))
+            },
         );
         let player_events = player.get_player_event_channel();
 
@@ -270,6 +421,8 @@ impl Spotify {
             session,
             player,
             mixer,
+            bitrate,
+            preload_max_size,
         );
         debug!("worker thread ready.");
         worker.run_loop().await;
@@ -292,6 +445,7 @@ impl Spotify {
                 .get_since()
                 .map(|t| t.elapsed().unwrap())
                 .unwrap_or_else(|| Duration::from_secs(0))
+            + self.silence.skipped()
     }
 
     fn set_elapsed(&self, new_elapsed: Option<Duration>) {
@@ -325,6 +479,13 @@ impl Spotify {
             return;
         }
 
+        self.switch_volume_context(track.volume_context());
+        self.update_gain(Some(track));
+        *self.seek_preview.write().unwrap() = None;
+        self.silence.set_mode(match track {
+            Playable::Episode(_) => self.cfg.values().skip_silence.unwrap_or_default(),
+            Playable::Track(_) => SkipSilence::Off,
+        });
         self.send_worker(WorkerCommand::Load(
             track.clone(),
             start_playing,
@@ -339,6 +500,8 @@ impl Spotify {
     /// doesn't have to be retrieved every time from the thread, which would be harder and more
     /// expensive.
     pub fn update_status(&self, new_status: PlayerEvent) {
+        // The reported position includes the skipped pauses.
+        self.silence.reset_skipped();
         match new_status {
             PlayerEvent::Paused(position) => {
                 self.set_elapsed(Some(position));
@@ -366,6 +529,7 @@ impl Spotify {
     pub fn update_track(&self) {
         self.set_elapsed(None);
         self.set_since(None);
+        self.silence.reset_skipped();
     }
 
     /// Start playback of the [Player].
@@ -437,11 +601,78 @@ impl Spotify {
         self.seek(std::cmp::max(0, new) as u32);
     }
 
+    /// The latest samples of the audio output, for the visualizer.
+    pub fn audio_tap(&self) -> &AudioTap {
+        &self.tap
+    }
+
+    /// The position that is previewed before seeking to it.
+    pub fn seek_preview(&self) -> Option<u32> {
+        self.seek_preview
+            .read()
+            .unwrap()
+            .map(|(position, _)| position)
+    }
+
+    /// Seek in `direction` in the loaded item of `duration` milliseconds. Relative seeks are
+    /// previewed for a moment first, so several of them add up to a single seek.
+    pub fn seek_to(&self, direction: &SeekDirection, duration: u32) {
+        let (step, large_step, delay) = {
+            let values = self.cfg.values();
+            (
+                values.seek_step.unwrap_or(DEFAULT_SEEK_STEP) as i64,
+                values.seek_step_large.unwrap_or(DEFAULT_SEEK_STEP_LARGE) as i64,
+                Duration::from_millis(values.seek_preview.unwrap_or(DEFAULT_SEEK_PREVIEW)),
+            )
+        };
+        let from = match self.seek_preview() {
+            Some(position) => position as i64,
+            None => self.get_current_progress().as_millis() as i64,
+        };
+        let (target, relative) = match *direction {
+            SeekDirection::Absolute(position) => (position as i64, false),
+            SeekDirection::Percent(percent) => ((duration as f64 * percent / 100.0) as i64, false),
+            SeekDirection::Relative(delta) => (from + delta as i64, true),
+            SeekDirection::Step(steps) => (from + steps as i64 * step, true),
+            SeekDirection::LargeStep(steps) => (from + steps as i64 * large_step, true),
+        };
+        let target = target.clamp(0, duration as i64) as u32;
+
+        if !relative || delay.is_zero() {
+            *self.seek_preview.write().unwrap() = None;
+            self.seek(target);
+            return;
+        }
+        *self.seek_preview.write().unwrap() = Some((target, Instant::now()));
+        let spotify = self.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            let mut preview = spotify.seek_preview.write().unwrap();
+            // Only the last of a series of seeks is still waiting after the delay.
+            if let Some((target, since)) = *preview
+                && since.elapsed() >= delay
+            {
+                *preview = None;
+                drop(preview);
+                spotify.seek(target);
+                spotify.events.trigger();
+            }
+        });
+    }
+
     /// Get the current volume of the [Player].
     pub fn volume(&self) -> u16 {
         self.cfg.state().volume
     }
 
+    /// Tell the main event loop and MPRIS clients that the items or the order of the queue
+    /// changed.
+    pub fn notify_queue_changed(&self) {
+        self.events.send(Event::QueueChanged);
+        #[cfg(feature = "mpris")]
+        self.send_mpris(MprisCommand::EmitQueueStatus);
+    }
+
     /// Send a Seeked signal on Mpris interface
     #[cfg(feature = "mpris")]
     pub fn notify_seeked(&self, position_ms: u32) {
@@ -459,7 +690,13 @@ impl Spotify {
     /// the update.
     pub fn set_volume(&self, volume: u16, notify: bool) {
         info!("setting volume to {volume}");
-        self.cfg.with_state_mut(|s| s.volume = volume);
+        let context = *self.volume_context.read().unwrap();
+        self.cfg.with_state_mut(|s| {
+            s.volume = volume;
+            if let Some(context) = context {
+                s.context_volumes.insert(context, volume);
+            }
+        });
         self.send_worker(WorkerCommand::SetVolume(volume));
         // HACK: This is a bit of a hack to prevent duplicate update signals when updating from the
         // MPRIS implementation.
@@ -469,12 +706,57 @@ impl Spotify {
         }
     }
 
+    /// Apply the pre-amp and the volume offset of `playable`, the loaded item.
+    pub fn update_gain(&self, playable: Option<&Playable>) {
+        let offset = playable.map_or(0.0, |playable| {
+            gain::track_offset(self.cfg.storage().as_ref(), &playable.uri())
+        });
+        self.gain.set_db(self.cfg.gain_settings().preamp + offset);
+    }
+
+    /// Restore the volume last used for `context` when switching between music and podcasts.
+    fn switch_volume_context(&self, context: VolumeContext) {
+        if !self.cfg.values().context_volume.unwrap_or(true) {
+            return;
+        }
+        let previous = self.volume_context.write().unwrap().replace(context);
+        if previous == Some(context) {
+            return;
+        }
+        let remembered = self.cfg.state().context_volumes.get(&context).copied();
+        match remembered {
+            Some(volume) if volume != self.volume() => {
+                debug!("restoring {context:?} volume");
+                self.set_volume(volume, true);
+            }
+            Some(_) => {}
+            // Start out with the current volume for content that wasn't played before.
+            None => self.cfg.with_state_mut(|s| {
+                s.context_volumes.insert(context, s.volume);
+            }),
+        }
+    }
+
     /// Preload the given [Playable] in the [Player]. This makes sure it can be played immediately
     /// after the current [Playable] is finished.
     pub fn preload(&self, track: &Playable) {
         self.send_worker(WorkerCommand::Preload(track.clone()));
     }
 
+    /// Download the audio file of `playable` into the audio cache without playing it. Blocks until
+    /// it is downloaded and returns its size, which is 0 if it was cached already.
+    pub fn download(&self, playable: &Playable) -> Result<u64, String> {
+        let (tx, rx) = oneshot::channel();
+        self.send_worker(WorkerCommand::Download(playable.clone(), tx));
+        let result = ASYNC_RUNTIME
+            .get()
+            .unwrap()
+            .block_on(rx)
+            .unwrap_or_else(|_| Err("The player isn't running".into()));
+        self.events.trigger();
+        result
+    }
+
     /// Shut down the worker thread.
     pub fn shutdown(&self) {
         self.send_worker(WorkerCommand::Shutdown);
@@ -1,48 +1,158 @@
-use std::collections::HashSet;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
-use std::sync::{Arc, RwLock};
-use std::thread;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
+use crate::api_cache::ApiCache;
 use crate::application::ASYNC_RUNTIME;
+use crate::credits::{self, Credit};
+use crate::data_usage::{self, MeteredClient};
+use crate::lyrics::{self, Lyrics};
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use log::{debug, error, info, warn};
 use rand::Rng;
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_NONE_MATCH, RETRY_AFTER};
 use rspotify::http::HttpError;
 use rspotify::model::{
     AlbumId, AlbumType, ArtistId, CursorBasedPage, EpisodeId, FullAlbum, FullArtist, FullEpisode,
     FullPlaylist, FullShow, FullTrack, ItemPositions, Market, Page, PlayableId, PlaylistId,
-    PlaylistResult, PrivateUser, Recommendations, SavedAlbum, SavedTrack, SearchResult, SearchType,
-    Show, ShowId, SimplifiedTrack, TrackId, UserId,
+    PlaylistResult, PrivateUser, PublicUser, Recommendations, RecommendationsAttribute, SavedAlbum,
+    SavedTrack, SearchResult, SearchType, Show, ShowId, SimplifiedPlaylist, SimplifiedTrack,
+    TrackId, UserId,
 };
 use rspotify::{AuthCodeSpotify, ClientError, ClientResult, Config, Token, prelude::*};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::audio_features::AudioFeatures;
 use crate::model::category::Category;
 use crate::model::episode::Episode;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::track::Track;
+use crate::proxy;
+use crate::rate_limiter::{self, RateLimiter};
 use crate::spotify_worker::WorkerCommand;
-use crate::ui::pagination::{ApiPage, ApiResult};
+use crate::ui::pagination::{ApiPage, ApiResult, DEFAULT_READ_AHEAD};
 
 const MAX_RETRIES: u32 = 3;
 const MAX_BACKOFF_SECS: u64 = 60;
 
+/// How often a request is let through to check whether the network is back after it failed.
+const OFFLINE_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long cached responses are used. Shows include their latest episodes, so they expire sooner.
+const ALBUM_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const ARTIST_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const SHOW_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const TRACK_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const EPISODE_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const CREDITS_CACHE_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Lyrics are added to the providers over time, so missing ones are looked up again after a week.
+const LYRICS_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const PLAYLIST_SNAPSHOT_CACHE_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const USER_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The cache endpoint of playlist snapshots. Renamed when the cached tracks gain fields, as older
+/// snapshots never change and would otherwise lack them until they expire.
+const PLAYLIST_TRACKS: &str = "playlist_tracks";
+
+/// A response cached together with the ETag the server sent for it.
+#[derive(Serialize, Deserialize)]
+struct ETagged<T> {
+    etag: String,
+    value: T,
+}
+
+/// Why the Web API is offline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OfflineMode {
+    /// Switched on by the user. No requests are sent until it is switched off again.
+    Manual,
+    /// Switched on after a network failure. Ends with the first request that succeeds.
+    Detected { last_probe: Instant },
+}
+
+/// Why a request failed, as far as retrying it is concerned.
+enum RequestError {
+    /// The server answered with an error status, and the seconds to wait from its `Retry-After`
+    /// header.
+    Status(u16, Option<u64>),
+    /// The server couldn't be reached.
+    Transport(String),
+    Other(String),
+}
+
+impl From<ClientError> for RequestError {
+    fn from(error: ClientError) -> Self {
+        match error {
+            ClientError::Http(error) => match *error {
+                HttpError::StatusCode(response) => Self::Status(
+                    response.status(),
+                    response.header("Retry-After").and_then(|v| v.parse().ok()),
+                ),
+                HttpError::Transport(transport) => Self::Transport(transport.to_string()),
+                _ => Self::Other("Unknown HTTP error".to_string()),
+            },
+            e => Self::Other(format!("API error: {e}")),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RequestError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_connect() || error.is_timeout() {
+            Self::Transport(error.to_string())
+        } else {
+            Self::Other(format!("HTTP error: {error}"))
+        }
+    }
+}
+
+/// The client for the requests rspotify can't send, shared so its connections are reused.
+fn http_client() -> &'static reqwest::blocking::Client {
+    static CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        proxy::client_builder().build().unwrap_or_else(|e| {
+            warn!("could not create the HTTP client: {e}");
+            reqwest::blocking::Client::new()
+        })
+    })
+}
+
+/// The shared result of a request that is in flight.
+type InFlightResult = Arc<OnceLock<Option<Arc<dyn Any + Send + Sync>>>>;
+
 /// Convenient wrapper around the rspotify web API functionality.
 #[derive(Clone)]
 pub struct WebApi {
     /// Rspotify web API.
-    api: AuthCodeSpotify,
+    api: MeteredClient,
     /// The username of the logged in user.
     user: Option<String>,
     /// Sender of the mpsc channel to the [Spotify](crate::spotify::Spotify) worker thread.
     worker_channel: Arc<RwLock<Option<mpsc::UnboundedSender<WorkerCommand>>>>,
     /// Time at which the token expires.
     token_expiration: Arc<RwLock<DateTime<Utc>>>,
+    /// The amount of pages paginated results fetch ahead.
+    read_ahead: u32,
+    /// Persistent cache for responses that rarely change.
+    cache: Option<Arc<ApiCache>>,
+    /// Limits the request rate of all clones of this client.
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Requests that are currently in flight, keyed by endpoint and id.
+    in_flight: Arc<Mutex<HashMap<String, InFlightResult>>>,
+    /// Once cancelled, requests of this client fail instead of being sent or retried.
+    cancellation: Option<CancellationToken>,
+    /// While offline, responses are only served from the cache.
+    offline: Arc<RwLock<Option<OfflineMode>>>,
 }
 
 impl Default for WebApi {
@@ -51,7 +161,7 @@ impl Default for WebApi {
             token_refreshing: false,
             ..Default::default()
         };
-        let api = AuthCodeSpotify::with_config(
+        let api = MeteredClient::with_config(
             rspotify::Credentials::default(),
             rspotify::OAuth::default(),
             config,
@@ -61,6 +171,12 @@ impl Default for WebApi {
             user: None,
             worker_channel: Arc::new(RwLock::new(None)),
             token_expiration: Arc::new(RwLock::new(Utc::now())),
+            read_ahead: DEFAULT_READ_AHEAD,
+            cache: None,
+            rate_limiter: None,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            cancellation: None,
+            offline: Arc::new(RwLock::new(None)),
         }
     }
 }
@@ -70,13 +186,12 @@ impl WebApi {
         Self::default()
     }
 
-    pub fn with_authenticated_client(api: AuthCodeSpotify) -> Self {
-        Self {
-            api,
-            user: None,
-            worker_channel: Arc::new(RwLock::new(None)),
-            token_expiration: Arc::new(RwLock::new(Utc::now() + ChronoDuration::hours(1))),
-        }
+    /// Replace the rspotify client with one that was authenticated by ncspot itself, keeping the
+    /// user and the settings. Its token is refreshed by rspotify, so it doesn't use the worker.
+    pub fn set_authenticated_client(&mut self, api: AuthCodeSpotify) {
+        self.api = MeteredClient(api);
+        self.worker_channel = Arc::new(RwLock::new(None));
+        self.token_expiration = Arc::new(RwLock::new(Utc::now() + ChronoDuration::hours(1)));
     }
 
     /// Set the username for use with the API.
@@ -84,6 +199,247 @@ impl WebApi {
         self.user = user;
     }
 
+    /// Set the amount of pages paginated results fetch in the background.
+    pub fn set_read_ahead(&mut self, pages: u32) {
+        self.read_ahead = pages;
+    }
+
+    /// Set the cache used for album, artist, show, track and episode lookups.
+    pub fn set_cache(&mut self, cache: Option<Arc<ApiCache>>) {
+        self.cache = cache;
+    }
+
+    /// Limit the requests of this client and all its clones to `requests_per_second`, or remove the
+    /// limit if it is `None`.
+    pub fn set_rate_limit(&mut self, requests_per_second: Option<f64>) {
+        self.rate_limiter = requests_per_second.map(|rate| Arc::new(RateLimiter::new(rate)));
+    }
+
+    /// Get a clone of this client whose requests are aborted once `token` is cancelled.
+    pub fn cancellable(&self, token: &CancellationToken) -> Self {
+        Self {
+            cancellation: Some(token.clone()),
+            ..self.clone()
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+    }
+
+    /// Whether requests are currently not sent, because the user switched to offline mode or the
+    /// network failed.
+    pub fn offline_mode(&self) -> Option<OfflineMode> {
+        *self.offline.read().unwrap()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline_mode().is_some()
+    }
+
+    /// Switch the offline mode on or off.
+    pub fn set_offline(&self, offline: bool) {
+        info!("offline mode {}", if offline { "on" } else { "off" });
+        *self.offline.write().unwrap() = offline.then_some(OfflineMode::Manual);
+    }
+
+    /// Whether a request may be sent. While the network is down, one request is let through every
+    /// [OFFLINE_PROBE_INTERVAL] to check whether it is back.
+    fn may_send(&self) -> bool {
+        let mut offline = self.offline.write().unwrap();
+        match *offline {
+            None => true,
+            Some(OfflineMode::Manual) => false,
+            Some(OfflineMode::Detected { last_probe }) => {
+                if last_probe.elapsed() < OFFLINE_PROBE_INTERVAL {
+                    return false;
+                }
+                debug!("checking whether the network is back");
+                *offline = Some(OfflineMode::Detected {
+                    last_probe: Instant::now(),
+                });
+                true
+            }
+        }
+    }
+
+    /// Update the detected offline mode after a request succeeded or failed to reach the server.
+    fn set_reachable(&self, reachable: bool) {
+        let mut offline = self.offline.write().unwrap();
+        match (*offline, reachable) {
+            (Some(OfflineMode::Detected { .. }), true) => {
+                info!("network is back, leaving offline mode");
+                *offline = None;
+            }
+            (None, false) => {
+                warn!("network failed, switching to offline mode");
+                *offline = Some(OfflineMode::Detected {
+                    last_probe: Instant::now(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Wait until the rate limit allows another request. Returns `false` if the client was
+    /// cancelled in the meantime or is offline.
+    fn throttle(&self) -> bool {
+        if self.is_cancelled() || !self.may_send() {
+            return false;
+        }
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.acquire(&|| self.is_cancelled()),
+            None => true,
+        }
+    }
+
+    /// Run `call` with a clone of this client on the blocking thread pool of the async runtime, so
+    /// waiting for the rate limit or a retry doesn't stall the calling thread. Nothing waits for
+    /// it, `call` reports its result itself, i.e. by triggering a redraw.
+    pub fn spawn<F>(&self, call: F)
+    where
+        F: FnOnce(&Self) + Send + 'static,
+    {
+        let api = self.clone();
+        ASYNC_RUNTIME
+            .get()
+            .unwrap()
+            .spawn_blocking(move || call(&api));
+    }
+
+    /// Run `call` in the background like [WebApi::spawn] and pass its result to `callback` once it
+    /// is available. UI code usually forwards the result to the Cursive callback sink.
+    pub fn spawn_with_callback<R, F, C>(&self, call: F, callback: C)
+    where
+        F: FnOnce(&Self) -> R + Send + 'static,
+        C: FnOnce(R) + Send + 'static,
+    {
+        self.spawn(move |api| callback(call(api)));
+    }
+
+    /// Remove all cached responses. Returns the number of removed responses.
+    pub fn clear_cache(&self) -> Result<usize, String> {
+        match &self.cache {
+            Some(cache) => cache.clear(),
+            None => Ok(0),
+        }
+    }
+
+    /// Look up the response of `endpoint` for `params` in the cache, or `fetch` and cache it.
+    fn cached<T, F>(&self, endpoint: &str, params: &str, ttl: Duration, fetch: F) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+        F: FnOnce() -> Option<T>,
+    {
+        // Outdated responses are better than none while offline.
+        let ttl = if self.is_offline() {
+            Duration::MAX
+        } else {
+            ttl
+        };
+        if let Some(value) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(endpoint, params, ttl))
+        {
+            return Some(value);
+        }
+        self.coalesced(endpoint, params, || {
+            let value = fetch()?;
+            if let Some(cache) = &self.cache {
+                cache.put(endpoint, params, &value);
+            }
+            Some(value)
+        })
+    }
+
+    /// Run `fetch` unless the same request for `endpoint` and `params` is already in flight, in
+    /// which case its result is shared instead of sending the request again.
+    fn coalesced<T, F>(&self, endpoint: &str, params: &str, fetch: F) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Option<T>,
+    {
+        let key = format!("{endpoint}/{params}");
+        let result = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        // Concurrent callers block here until the first one has fetched the result.
+        let value = result.get_or_init(|| {
+            let value = fetch().map(|value| Arc::new(value) as Arc<dyn Any + Send + Sync>);
+            self.in_flight.lock().unwrap().remove(&key);
+            value
+        });
+        value.as_ref()?.downcast_ref::<T>().cloned()
+    }
+
+    /// Fetch `url` with the `If-None-Match` header, so the server only sends the response if it
+    /// changed since it was cached under `key`. Returns `None` if the request fails, callers should
+    /// then fall back to a regular request.
+    fn conditional_get<T: Serialize + DeserializeOwned>(&self, key: &str, url: &str) -> Option<T> {
+        let cache = self.cache.as_ref()?;
+        let cached: Option<ETagged<T>> = cache.get("etag", key, Duration::MAX);
+
+        let response = self.with_retry(|| {
+            // Read the token on every attempt, it may have been refreshed after a 401.
+            let token = self
+                .api
+                .get_token()
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|token| token.access_token.clone())
+                .ok_or_else(|| RequestError::Other("no access token".to_string()))?;
+            let mut request = http_client().get(url).bearer_auth(token);
+            if let Some(cached) = &cached {
+                request = request.header(IF_NONE_MATCH, &cached.etag);
+            }
+            let response = request.send()?;
+            let status = response.status();
+            if status.is_success() || status == StatusCode::NOT_MODIFIED {
+                Ok(response)
+            } else {
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok());
+                Err(RequestError::Status(status.as_u16(), retry_after))
+            }
+        })?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            debug!("{key} not modified");
+            return cached.map(|cached| cached.value);
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(String::from);
+        let body = response.bytes().ok()?;
+        data_usage::add_api(body.len() as u64);
+        let value: T = serde_json::from_slice(&body).ok()?;
+        if let Some(etag) = etag {
+            cache.put(
+                "etag",
+                key,
+                &ETagged {
+                    etag,
+                    value: &value,
+                },
+            );
+        }
+        Some(value)
+    }
+
     /// Set the sending end of the channel to the worker thread, managed by
     /// [Spotify](crate::spotify::Spotify).
     pub(crate) fn set_worker_channel(
@@ -112,7 +468,7 @@ impl WebApi {
         let cmd = WorkerCommand::RequestToken(token_tx);
         if let Some(channel) = self.worker_channel.read().unwrap().as_ref() {
             channel.send(cmd).unwrap();
-            let api_token = self.api.token.clone();
+            let api_token = self.api.get_token();
             let api_token_expiration = self.token_expiration.clone();
             Some(
                 ASYNC_RUNTIME
@@ -142,80 +498,99 @@ impl WebApi {
 
     fn api_with_retry<F, R>(&self, api_call: F) -> Option<R>
     where
-        F: Fn(&AuthCodeSpotify) -> ClientResult<R>,
+        F: Fn(&MeteredClient) -> ClientResult<R>,
+    {
+        self.with_retry(|| api_call(&self.api).map_err(RequestError::from))
+    }
+
+    /// Send a request with `call`, waiting for the rate limit first. Requests that were rate
+    /// limited, failed on the server or were rejected because the token expired are retried.
+    fn with_retry<F, R>(&self, call: F) -> Option<R>
+    where
+        F: Fn() -> Result<R, RequestError>,
     {
         let mut attempt = 0;
         let mut last_error = None;
 
         while attempt < MAX_RETRIES {
-            let result = api_call(&self.api);
-            match result {
-                Ok(v) => return Some(v),
-                Err(ClientError::Http(ref error)) => {
-                    debug!("http error (attempt {}): {:?}", attempt + 1, error);
-                    match error.as_ref() {
-                        HttpError::StatusCode(response) => match response.status() {
-                            429 => {
-                                let retry_after = response
-                                    .header("Retry-After")
-                                    .and_then(|v| v.parse::<u64>().ok())
-                                    .unwrap_or(1);
-
-                                let jitter = rand::rng().random_range(0..3);
-                                let base_delay = retry_after + jitter;
-                                let backoff = (base_delay * (1 << attempt)).min(MAX_BACKOFF_SECS);
-
-                                warn!(
-                                    "Rate limited (429). Waiting {} seconds before retry {}/{}",
-                                    backoff,
-                                    attempt + 1,
-                                    MAX_RETRIES
-                                );
-                                thread::sleep(Duration::from_secs(backoff));
-                                attempt += 1;
-                                last_error = Some(format!("Rate limited: {}", response.status()));
-                                continue;
-                            }
-                            401 => {
-                                debug!("Token unauthorized (401). Attempting refresh...");
-                                if let Some(handle) = self.update_token() {
-                                    ASYNC_RUNTIME.get().unwrap().block_on(handle).ok();
-                                    attempt += 1;
-                                    continue;
-                                }
-                                last_error = Some("Token refresh failed".to_string());
-                                break;
+            if !self.throttle() {
+                debug!("API call cancelled or offline");
+                return None;
+            }
+            match call() {
+                Ok(v) => {
+                    self.set_reachable(true);
+                    return Some(v);
+                }
+                Err(RequestError::Status(status, retry_after)) => {
+                    debug!("http error (attempt {}): {status}", attempt + 1);
+                    match status {
+                        429 => {
+                            let jitter = rand::rng().random_range(0..3);
+                            let base_delay = retry_after.unwrap_or(1) + jitter;
+                            let backoff = (base_delay * (1 << attempt)).min(MAX_BACKOFF_SECS);
+
+                            warn!(
+                                "Rate limited (429). Waiting {} seconds before retry {}/{}",
+                                backoff,
+                                attempt + 1,
+                                MAX_RETRIES
+                            );
+                            if !rate_limiter::wait(Duration::from_secs(backoff), &|| {
+                                self.is_cancelled()
+                            }) {
+                                return None;
                             }
-                            502..=504 => {
-                                let backoff = (2u64.pow(attempt)).min(MAX_BACKOFF_SECS);
-                                warn!(
-                                    "Server error ({}). Waiting {} seconds before retry {}/{}",
-                                    response.status(),
-                                    backoff,
-                                    attempt + 1,
-                                    MAX_RETRIES
-                                );
-                                thread::sleep(Duration::from_secs(backoff));
+                            attempt += 1;
+                            last_error = Some(format!("Rate limited: {status}"));
+                            continue;
+                        }
+                        401 => {
+                            debug!("Token unauthorized (401). Attempting refresh...");
+                            if let Some(handle) = self.update_token() {
+                                // Not `Runtime::block_on`, pages are also fetched from
+                                // blocking tasks on the runtime.
+                                futures::executor::block_on(handle).ok();
                                 attempt += 1;
-                                last_error = Some(format!("Server error: {}", response.status()));
                                 continue;
                             }
-                            status => {
-                                error!("Unhandled HTTP status: {}", status);
-                                last_error = Some(format!("HTTP error: {}", status));
-                                break;
+                            last_error = Some("Token refresh failed".to_string());
+                            break;
+                        }
+                        502..=504 => {
+                            let backoff = (2u64.pow(attempt)).min(MAX_BACKOFF_SECS);
+                            warn!(
+                                "Server error ({}). Waiting {} seconds before retry {}/{}",
+                                status,
+                                backoff,
+                                attempt + 1,
+                                MAX_RETRIES
+                            );
+                            if !rate_limiter::wait(Duration::from_secs(backoff), &|| {
+                                self.is_cancelled()
+                            }) {
+                                return None;
                             }
-                        },
-                        _ => {
-                            error!("Unknown HTTP error");
-                            last_error = Some("Unknown HTTP error".to_string());
+                            attempt += 1;
+                            last_error = Some(format!("Server error: {status}"));
+                            continue;
+                        }
+                        status => {
+                            error!("Unhandled HTTP status: {}", status);
+                            last_error = Some(format!("HTTP error: {}", status));
                             break;
                         }
                     }
                 }
-                Err(e) => {
-                    error!("API error: {}", e);
-                    last_error = Some(format!("API error: {}", e));
+                Err(RequestError::Transport(transport)) => {
+                    error!("Could not reach the server: {transport}");
+                    self.set_reachable(false);
+                    last_error = Some(format!("Network error: {transport}"));
+                    break;
+                }
+                Err(RequestError::Other(e)) => {
+                    error!("{e}");
+                    last_error = Some(e);
                     break;
                 }
             }
@@ -248,6 +623,29 @@ impl WebApi {
         .ok_or(())
     }
 
+    /// Add the tracks and episodes with the given `uris` to the end of the playlist with
+    /// `playlist_id`. URIs of other items are skipped.
+    pub fn playlist_add_uris(&self, playlist_id: &str, uris: &[&str]) -> Result<(), ()> {
+        self.api_with_retry(|api| {
+            let ids: Vec<PlayableId> = uris
+                .iter()
+                .filter_map(|uri| {
+                    TrackId::from_uri(uri)
+                        .map(PlayableId::Track)
+                        .or_else(|_| EpisodeId::from_uri(uri).map(PlayableId::Episode))
+                        .ok()
+                })
+                .collect();
+            api.playlist_add_items(
+                PlaylistId::from_id(playlist_id).unwrap(),
+                ids.iter().map(|id| id.as_ref()),
+                None,
+            )
+        })
+        .map(|_| ())
+        .ok_or(())
+    }
+
     pub fn delete_tracks(
         &self,
         playlist_id: &str,
@@ -357,51 +755,179 @@ impl WebApi {
     pub fn album(&self, album_id: &str) -> Result<FullAlbum, ()> {
         debug!("fetching album {album_id}");
         let aid = AlbumId::from_id(album_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.album(aid.clone(), Some(Market::FromToken)))
-            .ok_or(())
+        self.cached("album", album_id, ALBUM_CACHE_TTL, || {
+            self.api_with_retry(|api| api.album(aid.clone(), Some(Market::FromToken)))
+        })
+        .ok_or(())
     }
 
     /// Fetch the artist with the given `artist_id`.
     pub fn artist(&self, artist_id: &str) -> Result<FullArtist, ()> {
         let aid = ArtistId::from_id(artist_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.artist(aid.clone())).ok_or(())
+        self.cached("artist", artist_id, ARTIST_CACHE_TTL, || {
+            self.api_with_retry(|api| api.artist(aid.clone()))
+        })
+        .ok_or(())
+    }
+
+    /// Fetch the artists with the given `artist_ids`, i.e. for their genres.
+    pub fn artists(&self, artist_ids: &[&str]) -> Result<Vec<FullArtist>, ()> {
+        const MAX_IDS: usize = 50;
+        let mut artists = Vec::with_capacity(artist_ids.len());
+        for chunk in artist_ids.chunks(MAX_IDS) {
+            let aids = chunk
+                .iter()
+                .map(|id| ArtistId::from_id(*id).map_err(|_| ()))
+                .collect::<Result<Vec<ArtistId>, ()>>()?;
+            let result = self
+                .api_with_retry(|api| api.artists(aids.clone()))
+                .ok_or(())?;
+            artists.extend(result);
+        }
+        Ok(artists)
     }
 
     /// Fetch the playlist with the given `playlist_id`.
     pub fn playlist(&self, playlist_id: &str) -> Result<FullPlaylist, ()> {
         let pid = PlaylistId::from_id(playlist_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.playlist(pid.clone(), None, Some(Market::FromToken)))
-            .ok_or(())
+        self.coalesced("playlist", playlist_id, || {
+            self.api_with_retry(|api| api.playlist(pid.clone(), None, Some(Market::FromToken)))
+        })
+        .ok_or(())
     }
 
     /// Fetch the track with the given `track_id`.
     pub fn track(&self, track_id: &str) -> Result<FullTrack, ()> {
         let tid = TrackId::from_id(track_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.track(tid.clone(), Some(Market::FromToken)))
-            .ok_or(())
+        self.cached("track", track_id, TRACK_CACHE_TTL, || {
+            self.api_with_retry(|api| api.track(tid.clone(), Some(Market::FromToken)))
+        })
+        .ok_or(())
+    }
+
+    /// Fetch the public profile of the user with the given `user_id`.
+    pub fn user(&self, user_id: &str) -> Result<PublicUser, ()> {
+        let uid = UserId::from_id(user_id).map_err(|_| ())?;
+        self.cached("user", user_id, USER_CACHE_TTL, || {
+            self.api_with_retry(|api| api.user(uid.clone()))
+        })
+        .ok_or(())
+    }
+
+    /// Get the writers, producers and other credits of the track with the given `track_id`.
+    pub fn track_credits(&self, track_id: &str) -> Result<Vec<Credit>, String> {
+        let track = self
+            .track(track_id)
+            .map_err(|_| "Could not fetch the track".to_string())?;
+        let isrc = track
+            .external_ids
+            .get("isrc")
+            .ok_or("No credits are available for this track")?;
+        self.cached("credits", track_id, CREDITS_CACHE_TTL, || {
+            credits::musicbrainz(isrc)
+                .inspect_err(|e| error!("could not fetch credits: {e}"))
+                .ok()
+        })
+        .ok_or_else(|| "Could not fetch the credits from MusicBrainz".to_string())
+    }
+
+    /// Get the lyrics of `track` from the LRCLIB compatible `provider`. Tracks the provider has no
+    /// lyrics for get empty ones.
+    pub fn lyrics(&self, provider: &str, track: &Track) -> Result<Lyrics, String> {
+        let params = format!("{} {provider}", track.uri);
+        self.cached("lyrics", &params, LYRICS_CACHE_TTL, || {
+            lyrics::lrclib(provider, track)
+                .inspect_err(|e| error!("could not fetch lyrics: {e}"))
+                .ok()
+                .map(Option::unwrap_or_default)
+        })
+        .ok_or_else(|| "Could not fetch the lyrics".to_string())
+    }
+
+    /// Fetch the tracks with the given `track_ids`. Requests are batched to keep the number of
+    /// API calls low.
+    pub fn tracks(&self, track_ids: &[&str]) -> Result<Vec<FullTrack>, ()> {
+        const MAX_IDS: usize = 50;
+        let mut tracks = Vec::with_capacity(track_ids.len());
+        for chunk in track_ids.chunks(MAX_IDS) {
+            let tids = chunk
+                .iter()
+                .map(|id| TrackId::from_id(*id).map_err(|_| ()))
+                .collect::<Result<Vec<TrackId>, ()>>()?;
+            let result = self
+                .api_with_retry(|api| api.tracks(tids.clone(), Some(Market::FromToken)))
+                .ok_or(())?;
+            tracks.extend(result);
+        }
+        Ok(tracks)
     }
 
     /// Fetch the show with the given `show_id`.
     pub fn show(&self, show_id: &str) -> Result<FullShow, ()> {
         let sid = ShowId::from_id(show_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.get_a_show(sid.clone(), Some(Market::FromToken)))
-            .ok_or(())
+        self.cached("show", show_id, SHOW_CACHE_TTL, || {
+            self.api_with_retry(|api| api.get_a_show(sid.clone(), Some(Market::FromToken)))
+        })
+        .ok_or(())
     }
 
     /// Fetch the episode with the given `episode_id`.
     pub fn episode(&self, episode_id: &str) -> Result<FullEpisode, ()> {
         let eid = EpisodeId::from_id(episode_id).map_err(|_| ())?;
-        self.api_with_retry(|api| api.get_an_episode(eid.clone(), Some(Market::FromToken)))
-            .ok_or(())
+        self.cached("episode", episode_id, EPISODE_CACHE_TTL, || {
+            self.api_with_retry(|api| api.get_an_episode(eid.clone(), Some(Market::FromToken)))
+        })
+        .ok_or(())
+    }
+
+    /// Fetch the episodes with the given `episode_ids`. Requests are batched to keep the number of
+    /// API calls low.
+    pub fn episodes(&self, episode_ids: &[&str]) -> Result<Vec<FullEpisode>, ()> {
+        const MAX_IDS: usize = 50;
+        let mut episodes = Vec::with_capacity(episode_ids.len());
+        for chunk in episode_ids.chunks(MAX_IDS) {
+            let eids = chunk
+                .iter()
+                .map(|id| EpisodeId::from_id(*id).map_err(|_| ()))
+                .collect::<Result<Vec<EpisodeId>, ()>>()?;
+            let result = self
+                .api_with_retry(|api| {
+                    api.get_several_episodes(eids.clone(), Some(Market::FromToken))
+                })
+                .ok_or(())?;
+            episodes.extend(result);
+        }
+        Ok(episodes)
+    }
+
+    /// Fetch the audio features (tempo, key, energy...) of the tracks with the given `track_ids`.
+    /// Tracks without audio features are left out of the result.
+    pub fn audio_features(&self, track_ids: &[&str]) -> Result<Vec<AudioFeatures>, ()> {
+        const MAX_IDS: usize = 100;
+        let mut features = Vec::with_capacity(track_ids.len());
+        for chunk in track_ids.chunks(MAX_IDS) {
+            let tids = chunk
+                .iter()
+                .map(|id| TrackId::from_id(*id).map_err(|_| ()))
+                .collect::<Result<Vec<TrackId>, ()>>()?;
+            #[allow(deprecated)]
+            let result = self
+                .api_with_retry(|api| api.tracks_features(tids.clone()))
+                .ok_or(())?;
+            features.extend(result.unwrap_or_default().iter().map(AudioFeatures::from));
+        }
+        Ok(features)
     }
 
     /// Get recommendations based on the seeds provided with `seed_artists`, `seed_genres` and
-    /// `seed_tracks`.
+    /// `seed_tracks`. `attributes` can be used to tune the results, i.e. to limit them to a tempo
+    /// range.
     pub fn recommendations(
         &self,
         seed_artists: Option<Vec<&str>>,
         seed_genres: Option<Vec<&str>>,
         seed_tracks: Option<Vec<&str>>,
+        attributes: &[RecommendationsAttribute],
     ) -> Result<Recommendations, ()> {
         self.api_with_retry(|api| {
             let seed_artistids = seed_artists.as_ref().map(|artistids| {
@@ -417,7 +943,7 @@ impl WebApi {
                     .collect::<Vec<TrackId>>()
             });
             api.recommendations(
-                std::iter::empty(),
+                attributes.iter().copied(),
                 seed_artistids,
                 seed_genres.clone(),
                 seed_trackids,
@@ -428,8 +954,9 @@ impl WebApi {
         .ok_or(())
     }
 
-    /// Search for items of `searchtype` using the provided `query`. Limit the results to `limit`
-    /// items with the given `offset` from the start.
+    /// Search for items of `searchtype` using the provided `query`, which may contain Spotify's
+    /// field filters like `artist:`. Limit the results to `limit` items with the given `offset`
+    /// from the start.
     pub fn search(
         &self,
         searchtype: SearchType,
@@ -453,27 +980,68 @@ impl WebApi {
     /// Fetch all the current user's playlists.
     pub fn current_user_playlist(&self) -> ApiResult<Playlist> {
         const MAX_LIMIT: u32 = 50;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let fetch_page = move |offset: u32| {
             debug!("fetching user playlists, offset: {offset}");
-            spotify.api_with_retry(|api| {
-                match api.current_user_playlists_manual(Some(MAX_LIMIT), Some(offset)) {
-                    Ok(page) => Ok(ApiPage {
-                        offset: page.offset,
-                        total: page.total,
-                        items: page.items.iter().map(|sp| sp.into()).collect(),
-                    }),
-                    Err(e) => Err(e),
-                }
-            })
+            let url = format!(
+                "{}me/playlists?limit={MAX_LIMIT}&offset={offset}",
+                spotify.api.get_config().api_base_url
+            );
+            spotify
+                .conditional_get::<Page<SimplifiedPlaylist>>(&format!("playlists-{offset}"), &url)
+                .or_else(|| {
+                    spotify.api_with_retry(|api| {
+                        api.current_user_playlists_manual(Some(MAX_LIMIT), Some(offset))
+                    })
+                })
+                .map(|page| ApiPage {
+                    offset: page.offset,
+                    total: page.total,
+                    items: page.items.iter().map(|sp| sp.into()).collect(),
+                })
         };
-        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+        ApiResult::new(MAX_LIMIT, self.read_ahead, Arc::new(fetch_page), cancel)
+    }
+
+    /// Get all tracks of the playlist given by `playlist_id` at the version `snapshot_id`. A
+    /// snapshot never changes, so the tracks can be cached for a long time.
+    pub fn playlist_snapshot_tracks(&self, playlist_id: &str, snapshot_id: &str) -> Vec<Playable> {
+        let key = format!("{playlist_id}-{snapshot_id}");
+        if !snapshot_id.is_empty()
+            && let Some(tracks) = self
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.get(PLAYLIST_TRACKS, &key, PLAYLIST_SNAPSHOT_CACHE_TTL))
+        {
+            return tracks;
+        }
+
+        let tracks_result = self.user_playlist_tracks(playlist_id);
+        let mut complete = true;
+        while !tracks_result.at_end() {
+            if tracks_result.next().is_none() {
+                complete = false;
+                break;
+            }
+        }
+        let tracks = tracks_result.items.read().unwrap().clone();
+
+        // Incomplete playlists are returned, but not cached.
+        if complete
+            && !snapshot_id.is_empty()
+            && let Some(cache) = &self.cache
+        {
+            cache.put(PLAYLIST_TRACKS, &key, &tracks);
+        }
+        tracks
     }
 
     /// Get the tracks in the playlist given by `playlist_id`.
     pub fn user_playlist_tracks(&self, playlist_id: &str) -> ApiResult<Playable> {
         const MAX_LIMIT: u32 = 100;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let playlist_id = playlist_id.to_string();
         let fetch_page = move |offset: u32| {
             debug!("fetching playlist {playlist_id} tracks, offset: {offset}");
@@ -507,6 +1075,9 @@ impl WebApi {
                                     let mut playable: Playable = t.into();
                                     // TODO: set these
                                     playable.set_added_at(pt.added_at);
+                                    playable.set_added_by(
+                                        pt.added_by.as_ref().map(|user| user.id.id().to_string()),
+                                    );
                                     playable.set_list_index(page.offset as usize + index);
                                     playable
                                 })
@@ -517,7 +1088,7 @@ impl WebApi {
                 }
             })
         };
-        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+        ApiResult::new(MAX_LIMIT, self.read_ahead, Arc::new(fetch_page), cancel)
     }
 
     /// Fetch all the tracks in the album with the given `album_id`. Limit the results to `limit`
@@ -548,7 +1119,8 @@ impl WebApi {
         album_type: Option<AlbumType>,
     ) -> ApiResult<Album> {
         const MAX_SIZE: u32 = 50;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let artist_id = artist_id.to_string();
         let fetch_page = move |offset: u32| {
             debug!("fetching artist {artist_id} albums, offset: {offset}");
@@ -575,13 +1147,14 @@ impl WebApi {
             })
         };
 
-        ApiResult::new(MAX_SIZE, Arc::new(fetch_page))
+        ApiResult::new(MAX_SIZE, self.read_ahead, Arc::new(fetch_page), cancel)
     }
 
     /// Get all the episodes of the show with the given `show_id`.
     pub fn show_episodes(&self, show_id: &str) -> ApiResult<Episode> {
         const MAX_SIZE: u32 = 50;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let show_id = show_id.to_string();
         let fetch_page = move |offset: u32| {
             debug!("fetching show {} episodes, offset: {}", &show_id, offset);
@@ -602,7 +1175,7 @@ impl WebApi {
             })
         };
 
-        ApiResult::new(MAX_SIZE, Arc::new(fetch_page))
+        ApiResult::new(MAX_SIZE, self.read_ahead, Arc::new(fetch_page), cancel)
     }
 
     /// Get the user's saved shows.
@@ -670,6 +1243,24 @@ impl WebApi {
         .ok_or(())
     }
 
+    /// Check whether the logged in user follows the artists with the given `ids`. The result
+    /// contains one entry per id, in the same order.
+    pub fn current_user_follows_artists(&self, ids: &[&str]) -> Result<Vec<bool>, ()> {
+        const MAX_IDS: usize = 50;
+        let mut follows = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(MAX_IDS) {
+            let aids = chunk
+                .iter()
+                .map(|id| ArtistId::from_id(*id).map_err(|_| ()))
+                .collect::<Result<Vec<ArtistId>, ()>>()?;
+            let result = self
+                .api_with_retry(|api| api.user_artist_check_follow(aids.clone()))
+                .ok_or(())?;
+            follows.extend(result);
+        }
+        Ok(follows)
+    }
+
     /// Get the user's saved albums, starting at the given `offset`. The result is paginated.
     pub fn current_user_saved_albums(&self, offset: u32) -> Result<Page<SavedAlbum>, ()> {
         self.api_with_retry(|api| {
@@ -740,10 +1331,28 @@ impl WebApi {
             .ok_or(())
     }
 
+    /// Check whether the logged in user follows the playlist with the given `playlist_id`.
+    pub fn playlist_check_follow(&self, playlist_id: &str) -> Result<bool, ()> {
+        let user_id = match self.user.clone() {
+            Some(user) => user,
+            None => self.current_user()?.id.id().to_string(),
+        };
+        self.api_with_retry(|api| {
+            api.playlist_check_follow(
+                PlaylistId::from_id(playlist_id).unwrap(),
+                &[UserId::from_id(&user_id).unwrap()],
+            )
+        })
+        .and_then(|follows| follows.first().copied())
+        .ok_or(())
+    }
+
     /// Get the top tracks of the artist with the given `id`.
     pub fn artist_top_tracks(&self, id: &str) -> Result<Vec<Track>, ()> {
-        self.api_with_retry(|api| {
-            api.artist_top_tracks(ArtistId::from_id(id).unwrap(), Some(Market::FromToken))
+        self.coalesced("artist_top_tracks", id, || {
+            self.api_with_retry(|api| {
+                api.artist_top_tracks(ArtistId::from_id(id).unwrap(), Some(Market::FromToken))
+            })
         })
         .map(|ft| ft.iter().map(|t| t.into()).collect())
         .ok_or(())
@@ -760,7 +1369,8 @@ impl WebApi {
     /// Get the available categories.
     pub fn categories(&self) -> ApiResult<Category> {
         const MAX_LIMIT: u32 = 50;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let fetch_page = move |offset: u32| {
             debug!("fetching categories, offset: {offset}");
             spotify.api_with_retry(|api| {
@@ -779,13 +1389,14 @@ impl WebApi {
                 }
             })
         };
-        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+        ApiResult::new(MAX_LIMIT, self.read_ahead, Arc::new(fetch_page), cancel)
     }
 
     /// Get the playlists in the category given by `category_id`.
     pub fn category_playlists(&self, category_id: &str) -> ApiResult<Playlist> {
         const MAX_LIMIT: u32 = 50;
-        let spotify = self.clone();
+        let cancel = CancellationToken::new();
+        let spotify = self.cancellable(&cancel);
         let category_id = category_id.to_string();
         let fetch_page = move |offset: u32| {
             debug!("fetching category playlists, offset: {offset}");
@@ -805,7 +1416,7 @@ impl WebApi {
                 }
             })
         };
-        ApiResult::new(MAX_LIMIT, Arc::new(fetch_page))
+        ApiResult::new(MAX_LIMIT, self.read_ahead, Arc::new(fetch_page), cancel)
     }
 
     /// Get details about the logged in user.
@@ -813,3 +1424,134 @@ impl WebApi {
         self.api_with_retry(|api| api.current_user()).ok_or(())
     }
 }
+
+/// The Web API calls the queue, library and backup logic depends on. Implemented by [WebApi] and,
+/// with the `mock` feature, by a mock with canned responses, so the logic can be tested without
+/// network access or credentials.
+pub trait SpotifyApi: Send + Sync {
+    fn current_user(&self) -> Result<PrivateUser, ()>;
+
+    fn playlist(&self, playlist_id: &str) -> Result<FullPlaylist, ()>;
+
+    fn current_user_playlist(&self) -> ApiResult<Playlist>;
+
+    fn user_playlist_tracks(&self, playlist_id: &str) -> ApiResult<Playable>;
+
+    fn create_playlist(
+        &self,
+        name: &str,
+        public: Option<bool>,
+        description: Option<&str>,
+    ) -> Result<String, ()>;
+
+    fn playlist_add_uris(&self, playlist_id: &str, uris: &[&str]) -> Result<(), ()>;
+
+    fn user_playlist_follow_playlist(&self, id: &str) -> Result<(), ()>;
+
+    fn current_user_saved_tracks(&self, offset: u32) -> Result<Page<SavedTrack>, ()>;
+
+    fn current_user_saved_tracks_add(&self, ids: Vec<&str>) -> Result<(), ()>;
+
+    fn current_user_saved_albums(&self, offset: u32) -> Result<Page<SavedAlbum>, ()>;
+
+    fn current_user_saved_albums_add(&self, ids: Vec<&str>) -> Result<(), ()>;
+
+    fn current_user_followed_artists(
+        &self,
+        last: Option<&str>,
+    ) -> Result<CursorBasedPage<FullArtist>, ()>;
+
+    fn user_follow_artists(&self, ids: Vec<&str>) -> Result<(), ()>;
+
+    fn get_saved_shows(&self, offset: u32) -> Result<Page<Show>, ()>;
+
+    fn save_shows(&self, ids: &[&str]) -> Result<(), ()>;
+
+    fn recommendations(
+        &self,
+        seed_artists: Option<Vec<&str>>,
+        seed_genres: Option<Vec<&str>>,
+        seed_tracks: Option<Vec<&str>>,
+        attributes: &[RecommendationsAttribute],
+    ) -> Result<Recommendations, ()>;
+}
+
+impl SpotifyApi for WebApi {
+    fn current_user(&self) -> Result<PrivateUser, ()> {
+        Self::current_user(self)
+    }
+
+    fn playlist(&self, playlist_id: &str) -> Result<FullPlaylist, ()> {
+        Self::playlist(self, playlist_id)
+    }
+
+    fn current_user_playlist(&self) -> ApiResult<Playlist> {
+        Self::current_user_playlist(self)
+    }
+
+    fn user_playlist_tracks(&self, playlist_id: &str) -> ApiResult<Playable> {
+        Self::user_playlist_tracks(self, playlist_id)
+    }
+
+    fn create_playlist(
+        &self,
+        name: &str,
+        public: Option<bool>,
+        description: Option<&str>,
+    ) -> Result<String, ()> {
+        Self::create_playlist(self, name, public, description)
+    }
+
+    fn playlist_add_uris(&self, playlist_id: &str, uris: &[&str]) -> Result<(), ()> {
+        Self::playlist_add_uris(self, playlist_id, uris)
+    }
+
+    fn user_playlist_follow_playlist(&self, id: &str) -> Result<(), ()> {
+        Self::user_playlist_follow_playlist(self, id)
+    }
+
+    fn current_user_saved_tracks(&self, offset: u32) -> Result<Page<SavedTrack>, ()> {
+        Self::current_user_saved_tracks(self, offset)
+    }
+
+    fn current_user_saved_tracks_add(&self, ids: Vec<&str>) -> Result<(), ()> {
+        Self::current_user_saved_tracks_add(self, ids)
+    }
+
+    fn current_user_saved_albums(&self, offset: u32) -> Result<Page<SavedAlbum>, ()> {
+        Self::current_user_saved_albums(self, offset)
+    }
+
+    fn current_user_saved_albums_add(&self, ids: Vec<&str>) -> Result<(), ()> {
+        Self::current_user_saved_albums_add(self, ids)
+    }
+
+    fn current_user_followed_artists(
+        &self,
+        last: Option<&str>,
+    ) -> Result<CursorBasedPage<FullArtist>, ()> {
+        Self::current_user_followed_artists(self, last)
+    }
+
+    fn user_follow_artists(&self, ids: Vec<&str>) -> Result<(), ()> {
+        Self::user_follow_artists(self, ids)
+    }
+
+    fn get_saved_shows(&self, offset: u32) -> Result<Page<Show>, ()> {
+        Self::get_saved_shows(self, offset)
+    }
+
+    fn save_shows(&self, ids: &[&str]) -> Result<(), ()> {
+        Self::save_shows(self, ids)
+    }
+
+    fn recommendations(
+        &self,
+        seed_artists: Option<Vec<&str>>,
+        seed_genres: Option<Vec<&str>>,
+        seed_tracks: Option<Vec<&str>>,
+        attributes: &[RecommendationsAttribute],
+    ) -> Result<Recommendations, ()> {
+        Self::recommendations(self, seed_artists, seed_genres, seed_tracks, attributes)
+    }
+}
@@ -4,7 +4,7 @@ use crate::spotify::UriType;
 
 use url::{Host, Url};
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct SpotifyUrl {
     pub id: String,
     pub uri_type: UriType,
@@ -12,15 +12,12 @@ pub struct SpotifyUrl {
 
 impl fmt::Display for SpotifyUrl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let type_seg = match self.uri_type {
-            UriType::Album => "album",
-            UriType::Artist => "artist",
-            UriType::Episode => "episode",
-            UriType::Playlist => "playlist",
-            UriType::Show => "show",
-            UriType::Track => "track",
-        };
-        write!(f, "https://open.spotify.com/{}/{}", type_seg, self.id)
+        write!(
+            f,
+            "https://open.spotify.com/{}/{}",
+            self.type_seg(),
+            self.id
+        )
     }
 }
 
@@ -32,6 +29,22 @@ impl SpotifyUrl {
         }
     }
 
+    fn type_seg(&self) -> &'static str {
+        match self.uri_type {
+            UriType::Album => "album",
+            UriType::Artist => "artist",
+            UriType::Episode => "episode",
+            UriType::Playlist => "playlist",
+            UriType::Show => "show",
+            UriType::Track => "track",
+        }
+    }
+
+    /// The `spotify:` URI of the item.
+    pub fn uri(&self) -> String {
+        format!("spotify:{}:{}", self.type_seg(), self.id)
+    }
+
     /// Get media id and type from open.spotify.com url
     ///
     /// ```
@@ -76,6 +89,39 @@ impl SpotifyUrl {
 
         Some(Self::new(id, uri_type))
     }
+
+    /// Get media id and type from a `spotify:<type>:<id>` URI
+    pub fn from_uri<S: AsRef<str>>(s: S) -> Option<Self> {
+        let uri = s.as_ref();
+        let uri_type = uri.parse::<UriType>().ok()?;
+        let id = uri.rsplit(':').next().filter(|id| !id.is_empty())?;
+
+        Some(Self::new(id, uri_type))
+    }
+
+    /// Extract all Spotify URLs and URIs contained in `text`, in order of appearance and without
+    /// duplicates.
+    pub fn extract_all<S: AsRef<str>>(text: S) -> Vec<Self> {
+        let mut urls: Vec<Self> = Vec::new();
+
+        let candidates = text
+            .as_ref()
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .map(|word| word.trim_matches(|c: char| "<>()[]{}\"'`".contains(c)));
+
+        for candidate in candidates {
+            let url = Self::from_url(candidate).or_else(|| Self::from_uri(candidate));
+            if let Some(url) = url
+                && !urls
+                    .iter()
+                    .any(|u| u.id == url.id && u.uri_type == url.uri_type)
+            {
+                urls.push(url);
+            }
+        }
+
+        urls
+    }
 }
 
 #[cfg(test)]
@@ -123,4 +169,25 @@ mod tests {
             assert_eq!(result.uri_type, case.1.uri_type);
         }
     }
+
+    #[test]
+    fn test_extract_all() {
+        let text = "check these out: https://open.spotify.com/track/6fRJg3R90w0juYoCJXxj2d?si=abc,\n\
+                    <spotify:album:29F5MF6Q9VYlryDsYEQz6a> and again \
+                    (https://open.spotify.com/track/6fRJg3R90w0juYoCJXxj2d) not-a-link";
+        let result = SpotifyUrl::extract_all(text);
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, "6fRJg3R90w0juYoCJXxj2d");
+        assert_eq!(result[0].uri_type, UriType::Track);
+        assert_eq!(result[1].id, "29F5MF6Q9VYlryDsYEQz6a");
+        assert_eq!(result[1].uri_type, UriType::Album);
+    }
+
+    #[test]
+    fn test_uri() {
+        let url = SpotifyUrl::new("0OgoSs65CLDPn6AF6tsZVg", UriType::Playlist);
+        assert_eq!(url.uri(), "spotify:playlist:0OgoSs65CLDPn6AF6tsZVg");
+        assert_eq!(SpotifyUrl::from_uri(url.uri()), Some(url));
+    }
 }
@@ -1,23 +1,32 @@
+use crate::data_usage;
 use crate::events::{Event, EventManager};
 use crate::model::playable::Playable;
 use crate::queue::QueueEvent;
 use crate::spotify::PlayerEvent;
 use futures::Future;
-use librespot_core::SpotifyUri;
+use futures::channel::oneshot;
+use librespot_audio::AudioFile;
 use librespot_core::session::Session;
 use librespot_core::token::Token;
+use librespot_core::{FileId, SpotifyUri};
+use librespot_metadata::audio::{AudioFileFormat, AudioItem};
+use librespot_playback::config::Bitrate;
 use librespot_playback::mixer::Mixer;
 use librespot_playback::player::{Player, PlayerEvent as LibrespotPlayerEvent};
 use log::{debug, error, info, warn};
 use std::sync::Arc;
 use std::sync::mpsc::Sender;
 use std::time::Duration;
+use std::{fs, io};
 use std::{pin::Pin, time::SystemTime};
 use tokio::sync::mpsc;
 use tokio::time;
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+/// The data rate librespot plans its requests with when downloading, that of a 320 kbps file.
+const DOWNLOAD_BYTES_PER_SECOND: usize = 40 * 1024;
+
 #[derive(Debug)]
 pub(crate) enum WorkerCommand {
     Load(Playable, bool, u32),
@@ -28,6 +37,8 @@ pub(crate) enum WorkerCommand {
     SetVolume(u16),
     RequestToken(Sender<Option<Token>>),
     Preload(Playable),
+    /// Download the audio file of a track into the audio cache without playing it.
+    Download(Playable, oneshot::Sender<Result<u64, String>>),
     Shutdown,
 }
 
@@ -46,9 +57,17 @@ pub struct Worker {
     token_task: Pin<Box<dyn Future<Output = ()> + Send>>,
     player_status: PlayerStatus,
     mixer: Arc<dyn Mixer>,
+    bitrate: Bitrate,
+    /// When the last track was requested to be loaded or preloaded.
+    requested: SystemTime,
+    /// Tracks larger than this many bytes aren't preloaded, as librespot holds them in full.
+    preload_max_size: u64,
+    /// The uri of the last preloaded track.
+    preloaded: Option<String>,
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         events: EventManager,
         player_events: mpsc::UnboundedReceiver<LibrespotPlayerEvent>,
@@ -56,6 +75,8 @@ impl Worker {
         session: Session,
         player: Arc<Player>,
         mixer: Arc<dyn Mixer>,
+        bitrate: Bitrate,
+        preload_max_size: u64,
     ) -> Self {
         Self {
             events,
@@ -66,9 +87,87 @@ impl Worker {
             token_task: Box::pin(futures::future::pending()),
             player_status: PlayerStatus::Stopped,
             mixer,
+            bitrate,
+            requested: SystemTime::now(),
+            preload_max_size,
+            preloaded: None,
         }
     }
 
+    /// The estimated size of the audio file of `duration_ms`, in bytes.
+    fn estimated_size(&self, duration_ms: u64) -> u64 {
+        let kbps = match self.bitrate {
+            Bitrate::Bitrate96 => 96,
+            Bitrate::Bitrate160 => 160,
+            Bitrate::Bitrate320 => 320,
+        };
+        duration_ms * kbps / 8
+    }
+
+    /// Whether one of the audio `files` of a track was in the audio cache before the track was
+    /// requested, so playing it doesn't stream anything.
+    fn is_cached<'a>(&self, files: impl IntoIterator<Item = &'a FileId>) -> bool {
+        let Some(cache) = self.session.cache() else {
+            return false;
+        };
+        files
+            .into_iter()
+            .filter_map(|file| cache.file_path(*file))
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .any(|modified| modified < self.requested)
+    }
+
+    /// Download the audio file of `playable` into the audio cache, in the format the player would
+    /// pick for `bitrate`. Returns the size of the file, which is 0 if it was cached already.
+    async fn download(
+        session: Session,
+        bitrate: Bitrate,
+        playable: Playable,
+    ) -> Result<u64, String> {
+        let uri = SpotifyUri::from_uri(&playable.uri()).map_err(|e| e.to_string())?;
+        let item = AudioItem::get_file(&session, uri)
+            .await
+            .map_err(|e| e.to_string())?;
+        let formats = match bitrate {
+            Bitrate::Bitrate96 => [AudioFileFormat::OGG_VORBIS_96, AudioFileFormat::MP3_96],
+            Bitrate::Bitrate160 => [AudioFileFormat::OGG_VORBIS_160, AudioFileFormat::MP3_160],
+            Bitrate::Bitrate320 => [AudioFileFormat::OGG_VORBIS_320, AudioFileFormat::MP3_320],
+        };
+        // Like the player, fall back to any other format, as most podcasts only have 96 kbps.
+        let file_id = formats
+            .iter()
+            .find_map(|format| item.files.get(format))
+            .or_else(|| item.files.values().next())
+            .copied()
+            .ok_or_else(|| format!("{playable} is not available in any format"))?;
+        let path = session
+            .cache()
+            .and_then(|cache| cache.file_path(file_id))
+            .ok_or("The audio cache is disabled")?;
+        if path.exists() {
+            return Ok(0);
+        }
+
+        let mut file = AudioFile::open(&session, file_id, DOWNLOAD_BYTES_PER_SECOND)
+            .await
+            .map_err(|e| e.to_string())?;
+        let task = tokio::task::spawn_blocking(move || {
+            if let Ok(controller) = file.get_stream_loader_controller() {
+                controller.set_stream_mode();
+            }
+            let size = io::copy(&mut file, &mut io::sink()).map_err(|e| e.to_string())?;
+            // librespot moves the complete file into the cache in the background.
+            for _ in 0..50 {
+                if path.exists() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Ok(size)
+        });
+        task.await.map_err(|e| e.to_string())?
+    }
+
     async fn get_token(session: Session, sender: Sender<Option<Token>>) {
         match session.login5().auth_token().await {
             Ok(token) => {
@@ -105,6 +204,8 @@ impl Worker {
                                     warn!("track is not playable");
                                     self.events.send(Event::Player(PlayerEvent::FinishedTrack));
                                 } else {
+                                    self.requested = SystemTime::now();
+                                    self.preloaded = None;
                                     self.player.load(uri, start_playing, position_ms);
                                 }
                             }
@@ -133,11 +234,28 @@ impl Worker {
                         self.token_task = Box::pin(Self::get_token(self.session.clone(), sender));
                     }
                     Some(WorkerCommand::Preload(playable)) => {
-                        if let Ok(uri) = SpotifyUri::from_uri(&playable.uri()) {
+                        let size = self.estimated_size(playable.duration().into());
+                        if self.preloaded.as_ref() == Some(&playable.uri()) {
+                            debug!("{playable} is already preloaded");
+                        } else if size > self.preload_max_size {
+                            debug!("Not preloading {playable}, it is about {size} bytes");
+                        } else if let Ok(uri) = SpotifyUri::from_uri(&playable.uri()) {
                             debug!("Preloading {uri:?}");
+                            self.requested = SystemTime::now();
+                            self.preloaded = Some(playable.uri());
                             self.player.preload(uri);
                         }
                     }
+                    Some(WorkerCommand::Download(playable, sender)) => {
+                        let session = self.session.clone();
+                        let bitrate = self.bitrate;
+                        tokio::spawn(async move {
+                            let result = Self::download(session, bitrate, playable).await;
+                            if sender.send(result).is_err() {
+                                debug!("download finished after it was abandoned");
+                            }
+                        });
+                    }
                     Some(WorkerCommand::Shutdown) => {
                         self.player.stop();
                         self.session.shutdown();
@@ -173,6 +291,14 @@ impl Worker {
                     Some(LibrespotPlayerEvent::EndOfTrack { .. }) => {
                         self.events.send(Event::Player(PlayerEvent::FinishedTrack));
                     }
+                    Some(LibrespotPlayerEvent::TrackChanged { audio_item }) => {
+                        // The whole file is downloaded, so its size is estimated from the length
+                        // of the track.
+                        if !self.is_cached(audio_item.files.values()) {
+                            let size = self.estimated_size(audio_item.duration_ms.into());
+                            data_usage::add_streamed(size);
+                        }
+                    }
                     Some(LibrespotPlayerEvent::TimeToPreloadNextTrack { .. }) => {
                         self.events
                             .send(Event::Queue(QueueEvent::PreloadTrackRequest));
@@ -0,0 +1,269 @@
+//! Sharing of the local state between machines through a directory that is kept in sync by an
+//! external tool like Syncthing or rsync.
+//!
+//! Every machine only ever writes its own snapshot to the directory, so the sync tool never has to
+//! resolve conflicts itself. Entries carry the time they were last changed and the snapshots of all
+//! machines are merged entry by entry, keeping the newest version.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, error, info};
+
+use crate::config::{QueueState, SortingOrder, UserState};
+use crate::journal;
+use crate::serialization::{CBOR, Serializer};
+use crate::storage::{FileStorage, Namespace, Storage};
+
+/// Storage key of the random identifier of this machine.
+const MACHINE_ID_KEY: &str = "sync_machine_id";
+
+/// File extension of the snapshots in the sync directory.
+const SNAPSHOT_EXTENSION: &str = ".cbor";
+
+/// A value with the time it was last changed, so the newest version wins when merging.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Stamped<T> {
+    /// Milliseconds since the Unix epoch.
+    pub modified: u64,
+    /// The machine that made the change.
+    pub machine: String,
+    pub value: T,
+}
+
+impl<T> Stamped<T> {
+    /// Stamp `value` as changed by `machine` just now.
+    fn now(machine: &str, value: T) -> Self {
+        let modified = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            modified,
+            machine: machine.to_string(),
+            value,
+        }
+    }
+
+    /// Whether this version should replace `other`. Equal timestamps are decided by the machine
+    /// identifier, so all machines agree on the result.
+    fn is_newer_than(&self, other: &Self) -> bool {
+        (self.modified, &self.machine) > (other.modified, &other.machine)
+    }
+
+    fn is_same_version(&self, other: &Self) -> bool {
+        self.modified == other.modified && self.machine == other.machine
+    }
+}
+
+/// The state that is shared between machines.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub queue: Option<Stamped<QueueState>>,
+    #[serde(default)]
+    pub playlist_orders: HashMap<String, Stamped<SortingOrder>>,
+}
+
+impl Snapshot {
+    /// Merge `other` into this snapshot, keeping the newest version of every entry.
+    fn merge(&mut self, other: Self) {
+        if let Some(queue) = other.queue
+            && self
+                .queue
+                .as_ref()
+                .is_none_or(|current| queue.is_newer_than(current))
+        {
+            self.queue = Some(queue);
+        }
+        for (id, order) in other.playlist_orders {
+            match self.playlist_orders.get(&id) {
+                Some(current) if !order.is_newer_than(current) => {}
+                _ => {
+                    self.playlist_orders.insert(id, order);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the snapshot of this machine in the sync directory up to date.
+pub struct StateSync {
+    directory: FileStorage,
+    machine: String,
+    /// The snapshot of this machine, including the entries taken over from other machines.
+    local: Snapshot,
+}
+
+impl StateSync {
+    /// Use `directory` to share the state. The machine identifier is kept in `storage`.
+    pub fn open(directory: PathBuf, storage: &dyn Storage) -> Self {
+        let machine = machine_id(storage);
+        let directory = FileStorage::new(directory.clone(), directory);
+        let local = read_snapshot(&directory, &snapshot_key(&machine)).unwrap_or_default();
+        debug!("syncing state as machine {machine}");
+        Self {
+            directory,
+            machine,
+            local,
+        }
+    }
+
+    /// Merge the snapshots of all machines and apply the entries that were changed elsewhere to
+    /// `state`.
+    pub fn apply(&mut self, state: &mut UserState) {
+        let own_key = snapshot_key(&self.machine);
+        let keys = self.directory.keys(Namespace::State).unwrap_or_else(|e| {
+            error!("Could not list the sync directory: {e}");
+            Vec::new()
+        });
+
+        let mut merged = self.local.clone();
+        for key in keys
+            .iter()
+            .filter(|key| key.ends_with(SNAPSHOT_EXTENSION) && **key != own_key)
+        {
+            if let Some(snapshot) = read_snapshot(&self.directory, key) {
+                merged.merge(snapshot);
+            }
+        }
+
+        if let Some(queue) = &merged.queue
+            && self
+                .local
+                .queue
+                .as_ref()
+                .is_none_or(|local| !queue.is_same_version(local))
+        {
+            info!("using the queue from machine {}", queue.machine);
+            journal::record(format_args!(
+                "queue replaced with {} items from machine {}",
+                queue.value.queue.len(),
+                queue.machine
+            ));
+            state.queuestate = queue.value.clone();
+        }
+        for (id, order) in &merged.playlist_orders {
+            if self
+                .local
+                .playlist_orders
+                .get(id)
+                .is_none_or(|local| !order.is_same_version(local))
+            {
+                state
+                    .playlist_orders
+                    .insert(id.clone(), order.value.clone());
+            }
+        }
+
+        self.local = merged;
+    }
+
+    /// Stamp the entries of `state` that changed since the last sync and write the snapshot of this
+    /// machine to the sync directory.
+    pub fn save(&mut self, state: &UserState) {
+        if is_changed(self.local.queue.as_ref(), &state.queuestate) {
+            self.local.queue = Some(Stamped::now(&self.machine, state.queuestate.clone()));
+        }
+        for (id, order) in &state.playlist_orders {
+            if is_changed(self.local.playlist_orders.get(id), order) {
+                let stamped = Stamped::now(&self.machine, order.clone());
+                self.local.playlist_orders.insert(id.clone(), stamped);
+            }
+        }
+
+        let key = snapshot_key(&self.machine);
+        let result = CBOR
+            .encode(&self.local)
+            .and_then(|content| self.directory.write(Namespace::State, &key, &content));
+        if let Err(e) = result {
+            error!("Could not write the sync snapshot: {e}");
+        }
+    }
+}
+
+/// Get the identifier of this machine, creating a random one the first time.
+fn machine_id(storage: &dyn Storage) -> String {
+    if let Ok(Some(id)) = storage.read(Namespace::State, MACHINE_ID_KEY)
+        && let Ok(id) = String::from_utf8(id)
+    {
+        return id;
+    }
+    let id = format!("{:016x}", rand::random::<u64>());
+    if let Err(e) = storage.write(Namespace::State, MACHINE_ID_KEY, id.as_bytes()) {
+        error!("Could not save the sync machine identifier: {e}");
+    }
+    id
+}
+
+fn snapshot_key(machine: &str) -> String {
+    format!("{machine}{SNAPSHOT_EXTENSION}")
+}
+
+fn read_snapshot(directory: &FileStorage, key: &str) -> Option<Snapshot> {
+    match directory.read(Namespace::State, key) {
+        Ok(Some(content)) => CBOR
+            .decode(&content)
+            .inspect_err(|e| error!("Could not parse the sync snapshot {key}: {e}"))
+            .ok(),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Could not read the sync snapshot: {e}");
+            None
+        }
+    }
+}
+
+/// Whether `value` differs from the synced version, compared by their serialized form.
+fn is_changed<T: serde::Serialize>(synced: Option<&Stamped<T>>, value: &T) -> bool {
+    synced.is_none_or(|synced| CBOR.encode(&synced.value).ok() != CBOR.encode(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamped(modified: u64, machine: &str, value: u32) -> Stamped<u32> {
+        Stamped {
+            modified,
+            machine: machine.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_newest_version_wins() {
+        assert!(stamped(2, "a", 1).is_newer_than(&stamped(1, "b", 2)));
+        assert!(!stamped(1, "b", 1).is_newer_than(&stamped(2, "a", 2)));
+        // Ties are decided the same way on every machine.
+        assert!(stamped(1, "b", 1).is_newer_than(&stamped(1, "a", 2)));
+        assert!(!stamped(1, "a", 1).is_newer_than(&stamped(1, "b", 2)));
+    }
+
+    #[test]
+    fn test_merge_playlist_orders() {
+        let order = |modified, machine: &str| Stamped {
+            modified,
+            machine: machine.to_string(),
+            value: SortingOrder {
+                key: crate::command::SortKey::Title,
+                direction: crate::command::SortDirection::Ascending,
+            },
+        };
+        let mut local = Snapshot::default();
+        local.playlist_orders.insert("first".into(), order(5, "a"));
+        local.playlist_orders.insert("second".into(), order(1, "a"));
+        let mut remote = Snapshot::default();
+        remote.playlist_orders.insert("first".into(), order(3, "b"));
+        remote
+            .playlist_orders
+            .insert("second".into(), order(4, "b"));
+        remote.playlist_orders.insert("third".into(), order(2, "b"));
+
+        local.merge(remote);
+        assert_eq!(local.playlist_orders["first"].machine, "a");
+        assert_eq!(local.playlist_orders["second"].machine, "b");
+        assert_eq!(local.playlist_orders["third"].machine, "b");
+    }
+}
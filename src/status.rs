@@ -0,0 +1,224 @@
+//! The playback status that is published to the clients of the IPC socket and the HTTP server.
+
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::watch::{self, Receiver, Sender};
+
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::spotify::PlayerEvent;
+use crate::utils::ms_to_hms;
+
+/// What caused a status update.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEvent {
+    /// The playback state changed.
+    Playback,
+    /// A different item is playing.
+    TrackChange,
+    /// Items were added to, removed from or moved in the queue.
+    Queue,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub event: StatusEvent,
+    pub mode: PlayerEvent,
+    pub playable: Option<Playable>,
+    pub queue: QueueStatus,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QueueStatus {
+    pub length: usize,
+    /// The index of the playing item.
+    pub current: Option<usize>,
+}
+
+impl From<&Queue> for QueueStatus {
+    fn from(queue: &Queue) -> Self {
+        Self {
+            length: queue.len(),
+            current: queue.get_current_index(),
+        }
+    }
+}
+
+impl Status {
+    /// `playing`, `paused` or `stopped`.
+    pub fn state(&self) -> &'static str {
+        match self.mode {
+            PlayerEvent::Playing(_) => "playing",
+            PlayerEvent::Paused(_) => "paused",
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => "stopped",
+        }
+    }
+
+    /// How far the playing item has been played at `now`.
+    pub fn position(&self, now: SystemTime) -> Duration {
+        match self.mode {
+            PlayerEvent::Playing(start) => now.duration_since(start).unwrap_or_default(),
+            PlayerEvent::Paused(position) => position,
+            PlayerEvent::Stopped | PlayerEvent::FinishedTrack => Duration::ZERO,
+        }
+    }
+
+    /// Replace the placeholders `{title}`, `{artist}`, `{album}`, `{state}`, `{position}`,
+    /// `{duration}` and `{progress}` in `format` with the values at `now`. Only the placeholders of
+    /// `format` itself are replaced, not those that happen to be in a value like the title.
+    pub fn format(&self, format: &str, now: SystemTime) -> String {
+        let (title, artist, album, duration) = match &self.playable {
+            Some(Playable::Track(track)) => (
+                track.title.clone(),
+                track.artists.join(", "),
+                track.album.clone().unwrap_or_default(),
+                track.duration,
+            ),
+            Some(Playable::Episode(episode)) => (
+                episode.name.clone(),
+                String::new(),
+                String::new(),
+                episode.duration,
+            ),
+            None => Default::default(),
+        };
+        let position = ms_to_hms(self.position(now).as_millis().min(duration.into()) as u32);
+        let duration = ms_to_hms(duration);
+        let progress = format!("{position}/{duration}");
+        let value = |placeholder: &str| match placeholder {
+            "title" => Some(title.as_str()),
+            "artist" => Some(artist.as_str()),
+            "album" => Some(album.as_str()),
+            "state" => Some(self.state()),
+            "position" => Some(position.as_str()),
+            "duration" => Some(duration.as_str()),
+            "progress" => Some(progress.as_str()),
+            _ => None,
+        };
+
+        let mut output = String::with_capacity(format.len());
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let replaced = rest
+                .find('}')
+                .and_then(|end| Some((value(&rest[1..end])?, end)));
+            match replaced {
+                Some((value, end)) => {
+                    output.push_str(value);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    output.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Keeps the latest [Status] for the clients that subscribed to it.
+pub struct StatusPublisher {
+    tx: Sender<Status>,
+}
+
+impl Default for StatusPublisher {
+    fn default() -> Self {
+        let status = Status {
+            event: StatusEvent::Playback,
+            mode: PlayerEvent::Stopped,
+            playable: None,
+            queue: QueueStatus::default(),
+        };
+        Self {
+            tx: watch::channel(status).0,
+        }
+    }
+}
+
+impl StatusPublisher {
+    pub fn subscribe(&self) -> Receiver<Status> {
+        self.tx.subscribe()
+    }
+
+    /// Publish the playback state `event` of the playing item of `queue`.
+    pub fn publish(&self, event: &PlayerEvent, queue: &Queue) {
+        let playable = queue.get_current();
+        let uri = |playable: &Option<Playable>| playable.as_ref().map(Playable::uri);
+        let changed = uri(&playable) != uri(&self.tx.borrow().playable);
+        let status = Status {
+            event: if changed {
+                StatusEvent::TrackChange
+            } else {
+                StatusEvent::Playback
+            },
+            mode: event.clone(),
+            playable,
+            queue: queue.into(),
+        };
+        self.tx.send_replace(status);
+    }
+
+    /// Publish that the items of `queue` changed.
+    pub fn publish_queue(&self, queue: &Queue) {
+        let mut status = self.tx.borrow().clone();
+        status.event = StatusEvent::Queue;
+        status.queue = queue.into();
+        self.tx.send_replace(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::track::Track;
+
+    #[test]
+    fn test_format() {
+        let json = r#"{"event":"playback","mode":{"Paused":{"secs":65,"nanos":0}},"playable":{"type":"Track","id":"2wcrQZ7ZJolYEfIaPP9yL4","uri":"spotify:track:2wcrQZ7ZJolYEfIaPP9yL4","title":"Hit Me Where It Hurts","track_number":4,"disc_number":1,"duration":184132,"artists":["Caroline Polachek"],"artist_ids":["4Ge8xMJNwt6EEXOzVXju9a"],"album":"Pang","album_id":"4ClyeVlAKJJViIyfVW0yQD","album_artists":["Caroline Polachek"],"cover_url":null,"url":"https://open.spotify.com/track/2wcrQZ7ZJolYEfIaPP9yL4","added_at":null,"list_index":0,"is_local":false,"is_playable":null},"queue":{"length":1,"current":0}}"#;
+        let status: Status = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            status.playable,
+            Some(Playable::Track(Track { .. }))
+        ));
+        assert_eq!(
+            status.format(
+                "{artist} - {title} ({album}) {state} {progress}",
+                SystemTime::now()
+            ),
+            "Caroline Polachek - Hit Me Where It Hurts (Pang) paused 1:05/3:04"
+        );
+
+        assert_eq!(
+            status.format("{{title}} {unknown} {title", SystemTime::now()),
+            "{Hit Me Where It Hurts} {unknown} {title"
+        );
+
+        let mut tricky = status.clone();
+        if let Some(Playable::Track(track)) = &mut tricky.playable {
+            track.title = "{artist} {progress}".to_string();
+        }
+        assert_eq!(
+            tricky.format("{title} - {artist}", SystemTime::now()),
+            "{artist} {progress} - Caroline Polachek"
+        );
+
+        let started = SystemTime::now() - Duration::from_secs(10);
+        let playing = Status {
+            mode: PlayerEvent::Playing(started),
+            ..status
+        };
+        assert_eq!(
+            playing.format("{position}", started + Duration::from_secs(20)),
+            "0:20"
+        );
+        assert_eq!(
+            playing.format("{position}", started + Duration::from_secs(999)),
+            "3:04"
+        );
+    }
+}
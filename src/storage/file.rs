@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::serialization::write_atomically;
+use crate::storage::{Namespace, Storage};
+
+/// Stores every value in its own file, state in the configuration directory and caches in the cache
+/// directory. This is the layout ncspot has always used.
+pub struct FileStorage {
+    state_dir: PathBuf,
+    cache_dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(state_dir: PathBuf, cache_dir: PathBuf) -> Self {
+        Self {
+            state_dir,
+            cache_dir,
+        }
+    }
+
+    fn directory(&self, namespace: Namespace) -> &PathBuf {
+        match namespace {
+            Namespace::State => &self.state_dir,
+            Namespace::Cache => &self.cache_dir,
+        }
+    }
+
+    fn path(&self, namespace: Namespace, key: &str) -> PathBuf {
+        self.directory(namespace).join(key)
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = self.path(namespace, key);
+        match fs::read(&path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Unable to read {}: {e}", path.display())),
+        }
+    }
+
+    fn write(&self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String> {
+        let path = self.path(namespace, key);
+        fs::create_dir_all(self.directory(namespace))
+            .and_then(|_| write_atomically(&path, value))
+            .map_err(|e| format!("Failed writing content to {}: {e}", path.display()))
+    }
+
+    fn remove(&self, namespace: Namespace, key: &str) -> Result<(), String> {
+        let path = self.path(namespace, key);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(format!("Unable to remove {}: {e}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn keys(&self, namespace: Namespace) -> Result<Vec<String>, String> {
+        let directory = self.directory(namespace);
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Unable to list {}: {e}", directory.display())),
+        };
+        Ok(entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            // Skip the lock and temporary files of `write_atomically`.
+            .filter(|name| !name.ends_with(".lock") && !name.ends_with(".tmp"))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let base = std::env::temp_dir().join(format!("ncspot-storage-{}", std::process::id()));
+        let storage = FileStorage::new(base.join("state"), base.join("cache"));
+
+        assert_eq!(storage.read(Namespace::State, "key"), Ok(None));
+        storage.write(Namespace::State, "key", b"state").unwrap();
+        storage.write(Namespace::Cache, "key", b"cache").unwrap();
+        assert_eq!(
+            storage.read(Namespace::State, "key"),
+            Ok(Some(b"state".to_vec()))
+        );
+        assert_eq!(
+            storage.read(Namespace::Cache, "key"),
+            Ok(Some(b"cache".to_vec()))
+        );
+
+        storage.remove(Namespace::State, "key").unwrap();
+        assert_eq!(storage.read(Namespace::State, "key"), Ok(None));
+        assert_eq!(storage.remove(Namespace::State, "key"), Ok(()));
+        assert_eq!(storage.keys(Namespace::State), Ok(Vec::new()));
+        assert_eq!(storage.keys(Namespace::Cache), Ok(vec!["key".to_string()]));
+
+        fs::remove_dir_all(base).unwrap();
+    }
+}
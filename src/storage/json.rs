@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+
+use log::error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::{Namespace, Storage};
+
+/// A value of the user state that is stored as JSON under `key`. It is read from the storage on
+/// first use and kept in memory afterwards, so it can be looked up on every redraw.
+pub struct JsonStore<T> {
+    key: &'static str,
+    /// What the value is, for the log messages.
+    name: &'static str,
+    value: Mutex<Option<T>>,
+}
+
+impl<T> JsonStore<T> {
+    pub const fn new(key: &'static str, name: &'static str) -> Self {
+        Self {
+            key,
+            name,
+            value: Mutex::new(None),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonStore<T> {
+    /// Look at the value with `f`.
+    pub fn read<R>(&self, storage: &dyn Storage, f: impl FnOnce(&T) -> R) -> R {
+        self.with(storage, |value| f(value))
+    }
+
+    /// Change the value with `f` and write it to the storage.
+    pub fn update<R>(&self, storage: &dyn Storage, f: impl FnOnce(&mut T) -> R) -> R {
+        self.with(storage, |value| {
+            let result = f(value);
+            let written = serde_json::to_vec(value)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| storage.write(Namespace::State, self.key, &contents));
+            if let Err(e) = written {
+                error!("could not save {}: {e}", self.name);
+            }
+            result
+        })
+    }
+
+    /// Reset the value and remove it from the storage.
+    pub fn clear(&self, storage: &dyn Storage) {
+        *self.value.lock().unwrap() = Some(T::default());
+        if let Err(e) = storage.remove(Namespace::State, self.key) {
+            error!("could not remove {}: {e}", self.name);
+        }
+    }
+
+    fn with<R>(&self, storage: &dyn Storage, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut value = self.value.lock().unwrap();
+        let value = value.get_or_insert_with(|| match storage.read(Namespace::State, self.key) {
+            Ok(Some(contents)) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+                error!("can't parse {}: {e}", self.name);
+                T::default()
+            }),
+            Ok(None) => T::default(),
+            Err(e) => {
+                error!("can't read {}: {e}", self.name);
+                T::default()
+            }
+        });
+        f(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use super::*;
+    use crate::storage::FileStorage;
+
+    #[test]
+    fn test_update_and_clear() {
+        let base = std::env::temp_dir().join(format!("ncspot-json-store-{}", std::process::id()));
+        let storage = FileStorage::new(base.join("state"), base.join("cache"));
+        let store: JsonStore<HashMap<String, u32>> = JsonStore::new("store.json", "test values");
+
+        assert_eq!(store.read(&storage, |values| values.len()), 0);
+        store.update(&storage, |values| values.insert("a".into(), 1));
+        assert_eq!(
+            storage.read(Namespace::State, "store.json"),
+            Ok(Some(br#"{"a":1}"#.to_vec()))
+        );
+
+        // A new store reads the value written by the first one.
+        let reopened: JsonStore<HashMap<String, u32>> = JsonStore::new("store.json", "test values");
+        assert_eq!(
+            reopened.read(&storage, |values| values.get("a").copied()),
+            Some(1)
+        );
+
+        reopened.clear(&storage);
+        assert_eq!(storage.read(Namespace::State, "store.json"), Ok(None));
+        assert_eq!(reopened.read(&storage, |values| values.len()), 0);
+
+        fs::remove_dir_all(base).unwrap();
+    }
+}
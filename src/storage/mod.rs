@@ -0,0 +1,80 @@
+//! Local persistence of the user state and the library caches.
+//!
+//! Everything ncspot remembers between sessions goes through a [Storage] backend, so the backend
+//! can be swapped without the rest of the application knowing where the data ends up.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::{user_cache_directory, user_configuration_directory};
+
+mod file;
+mod json;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use file::FileStorage;
+pub use json::JsonStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// The name of the default storage backend.
+pub const DEFAULT_BACKEND: &str = "files";
+
+/// Groups of stored values with different lifetimes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Namespace {
+    /// State that should be kept, like the queue and playback settings.
+    State,
+    /// Data that can be fetched again, like the library caches.
+    Cache,
+}
+
+impl Namespace {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::State => "state",
+            Self::Cache => "cache",
+        }
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A key-value store for serialized local data.
+pub trait Storage: Send + Sync {
+    /// Read the value stored under `key`, or `None` if there is none.
+    fn read(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String>;
+
+    /// Store `value` under `key`, replacing any previous value.
+    fn write(&self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String>;
+
+    /// Remove the value stored under `key`. Removing a missing key is not an error.
+    fn remove(&self, namespace: Namespace, key: &str) -> Result<(), String>;
+
+    /// List the keys that have a value in `namespace`.
+    fn keys(&self, namespace: Namespace) -> Result<Vec<String>, String>;
+}
+
+/// Open the storage backend with the given name, or the default one if `backend` is `None`.
+pub fn open(backend: Option<&str>) -> Result<Arc<dyn Storage>, String> {
+    match backend.unwrap_or(DEFAULT_BACKEND) {
+        DEFAULT_BACKEND => Ok(Arc::new(default_file_storage()?)),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(Arc::new(SqliteStorage::open(default_file_storage()?)?)),
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err("ncspot was built without the sqlite feature".into()),
+        other => Err(format!("Unknown storage backend: {other}")),
+    }
+}
+
+fn default_file_storage() -> Result<FileStorage, String> {
+    let state_dir =
+        user_configuration_directory().ok_or("configuration directory not found".to_string())?;
+    let cache_dir = user_cache_directory().ok_or("cache directory not found".to_string())?;
+    Ok(FileStorage::new(state_dir, cache_dir))
+}
@@ -0,0 +1,115 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::debug;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::config::config_path;
+use crate::storage::{FileStorage, Namespace, Storage};
+
+/// The database file in the configuration directory.
+const DATABASE_FILE_NAME: &str = "storage.sqlite3";
+
+/// How long to wait for other instances to release the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stores all values in a single SQLite database. Values that aren't in the database yet are read
+/// from the flat files, so switching to this backend keeps the existing state and caches.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    fallback: FileStorage,
+}
+
+impl SqliteStorage {
+    pub fn open(fallback: FileStorage) -> Result<Self, String> {
+        let path = config_path(DATABASE_FILE_NAME);
+        debug!("opening storage database at {}", path.display());
+        let connection = Connection::open(&path)
+            .map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+        Self::with_connection(connection, fallback)
+    }
+
+    fn with_connection(connection: Connection, fallback: FileStorage) -> Result<Self, String> {
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| {
+                connection.execute(
+                    "CREATE TABLE IF NOT EXISTS storage (
+                        namespace TEXT NOT NULL,
+                        key TEXT NOT NULL,
+                        value BLOB NOT NULL,
+                        PRIMARY KEY (namespace, key)
+                    )",
+                    [],
+                )
+            })
+            .map_err(|e| format!("Unable to initialize storage database: {e}"))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            fallback,
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self, namespace: Namespace, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let stored = self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM storage WHERE namespace = ?1 AND key = ?2",
+                params![namespace.as_str(), key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Unable to read {namespace}/{key}: {e}"))?;
+        match stored {
+            Some(value) => Ok(Some(value)),
+            None => self.fallback.read(namespace, key),
+        }
+    }
+
+    fn write(&self, namespace: Namespace, key: &str, value: &[u8]) -> Result<(), String> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO storage (namespace, key, value) VALUES (?1, ?2, ?3)",
+                params![namespace.as_str(), key, value],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Unable to write {namespace}/{key}: {e}"))
+    }
+
+    fn remove(&self, namespace: Namespace, key: &str) -> Result<(), String> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM storage WHERE namespace = ?1 AND key = ?2",
+                params![namespace.as_str(), key],
+            )
+            .map_err(|e| format!("Unable to remove {namespace}/{key}: {e}"))?;
+        self.fallback.remove(namespace, key)
+    }
+
+    fn keys(&self, namespace: Namespace) -> Result<Vec<String>, String> {
+        let mut keys = {
+            let connection = self.connection.lock().unwrap();
+            let mut statement = connection
+                .prepare("SELECT key FROM storage WHERE namespace = ?1")
+                .map_err(|e| e.to_string())?;
+            statement
+                .query_map(params![namespace.as_str()], |row| row.get(0))
+                .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+                .map_err(|e| format!("Unable to list {namespace}: {e}"))?
+        };
+        for key in self.fallback.keys(namespace)? {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+}
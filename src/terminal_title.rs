@@ -0,0 +1,78 @@
+use std::env;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use log::error;
+
+use crate::config::Config;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::spotify::PlayerEvent;
+
+/// Keeps the title of the terminal, and the window name when running inside tmux, in sync with
+/// the currently playing item. Only active when `terminal_title` is configured.
+pub struct TerminalTitle {
+    cfg: Arc<Config>,
+    library: Arc<Library>,
+    /// The title that was set last, to avoid writing the same escape sequence repeatedly.
+    current: Option<String>,
+}
+
+impl TerminalTitle {
+    pub fn new(cfg: Arc<Config>, library: Arc<Library>) -> Self {
+        Self {
+            cfg,
+            library,
+            current: None,
+        }
+    }
+
+    /// Update the title for the given playback state and item.
+    pub fn update(&mut self, state: &PlayerEvent, playable: Option<&Playable>) {
+        let Some(format) = self.cfg.values().terminal_title.clone() else {
+            // The option might have been removed by reloading the configuration.
+            self.clear();
+            return;
+        };
+
+        let title = match (state, playable) {
+            (PlayerEvent::Playing(_) | PlayerEvent::Paused(_), Some(playable)) => {
+                Playable::format(playable, &format, &self.library)
+            }
+            _ => String::new(),
+        };
+
+        if self.current.as_ref() != Some(&title) {
+            Self::write_title(&title);
+            self.current = Some(title);
+        }
+    }
+
+    /// Reset the title if it was set before.
+    pub fn clear(&mut self) {
+        if self.current.take().is_some() {
+            Self::write_title("");
+        }
+    }
+
+    fn write_title(title: &str) {
+        // Strip control characters so track titles can't end the escape sequence early.
+        let title: String = title.chars().filter(|c| !c.is_control()).collect();
+
+        // OSC 2 sets the window title in xterm compatible terminals.
+        let mut sequence = format!("\x1b]2;{title}\x07");
+        if env::var_os("TMUX").is_some() {
+            // tmux doesn't pass OSC 2 to the outer terminal but uses it for the pane title, the
+            // window name is set with its own escape sequence.
+            sequence.push_str(&format!("\x1bk{title}\x1b\\"));
+        }
+
+        let mut stdout = io::stdout();
+        if let Err(e) = stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+        {
+            error!("could not set terminal title: {e}");
+        }
+    }
+}
@@ -1,15 +1,22 @@
 #![allow(clippy::enum_glob_use)]
 
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use cursive::Cursive;
 use cursive::theme::BaseColor::*;
 use cursive::theme::Color::*;
-use cursive::theme::PaletteColor::*;
 use cursive::theme::*;
-use log::warn;
+use log::{info, warn};
+
+use crate::config::{Config, ConfigTheme, ThemeSchedule};
 
-use crate::config::ConfigTheme;
+/// How often the schedule and the desktop setting are checked.
+const APPEARANCE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Get the given color from the given [ConfigTheme]. The first argument is the [ConfigTheme] to get
-/// the color out of. The second argument is the name of the color to get and is an identifier. The
+/// the color out of, or several of them of which the first that sets the color is used. The second argument is the name of the color to get and is an identifier. The
 /// third argument is a [Color] that is used as the default when no color can be parsed from the
 /// provided [ConfigTheme].
 ///
@@ -22,8 +29,8 @@ use crate::config::ConfigTheme;
 macro_rules! load_color {
     ( $theme: expr_2021, $member: ident, $default: expr_2021 ) => {
         $theme
-            .as_ref()
-            .and_then(|t| t.$member.clone())
+            .iter()
+            .find_map(|t| t.$member.clone())
             .and_then(|c| Color::parse(c.as_ref()))
             .unwrap_or_else(|| {
                 warn!(
@@ -35,52 +42,108 @@ macro_rules! load_color {
     };
 }
 
-/// Create a [cursive::theme::Theme] from `theme_cfg`.
+/// The colors of the palette by their name in it, each from the first of `themes` that sets it.
+fn colors(themes: &[&ConfigTheme]) -> Vec<(&'static str, Color)> {
+    let background = load_color!(themes, background, TerminalDefault);
+    let primary = load_color!(themes, primary, TerminalDefault);
+    let highlight = load_color!(themes, highlight, Dark(White));
+    let highlight_bg = load_color!(themes, highlight_bg, Dark(Red));
+
+    let mut colors = vec![
+        ("background", background),
+        ("view", background),
+        ("primary", primary),
+        ("secondary", load_color!(themes, secondary, Dark(Blue))),
+        ("title_primary", load_color!(themes, title, Dark(Red))),
+        ("highlight_text", highlight),
+        ("highlight", highlight_bg),
+        (
+            "highlight_inactive",
+            load_color!(themes, highlight_inactive_bg, Dark(Blue)),
+        ),
+        (
+            "highlight_unfocused",
+            load_color!(themes, highlight_unfocused, highlight),
+        ),
+        (
+            "highlight_unfocused_bg",
+            load_color!(themes, highlight_unfocused_bg, highlight_bg),
+        ),
+        ("playing", load_color!(themes, playing, Dark(Blue))),
+        (
+            "playing_selected",
+            load_color!(themes, playing_selected, Light(Blue)),
+        ),
+        (
+            "playing_bg",
+            load_color!(themes, playing_bg, TerminalDefault),
+        ),
+        ("error", load_color!(themes, error, TerminalDefault)),
+        ("error_bg", load_color!(themes, error_bg, Dark(Red))),
+        (
+            "statusbar_progress",
+            load_color!(themes, statusbar_progress, Dark(Blue)),
+        ),
+        (
+            "statusbar_progress_bg",
+            load_color!(themes, statusbar_progress_bg, Light(Black)),
+        ),
+        ("statusbar", load_color!(themes, statusbar, Dark(Yellow))),
+        (
+            "statusbar_bg",
+            load_color!(themes, statusbar_bg, TerminalDefault),
+        ),
+        ("cmdline", load_color!(themes, cmdline, TerminalDefault)),
+        (
+            "cmdline_bg",
+            load_color!(themes, cmdline_bg, TerminalDefault),
+        ),
+        ("tab", load_color!(themes, tab, primary)),
+        ("tab_bg", load_color!(themes, tab_bg, background)),
+        ("tab_active", load_color!(themes, tab_active, highlight)),
+        (
+            "tab_active_bg",
+            load_color!(themes, tab_active_bg, highlight_bg),
+        ),
+        (
+            "search_match",
+            load_color!(themes, search_match, Light(Red)),
+        ),
+    ];
+    // Without a background of their own, search matches keep the background of their row.
+    if let Some(color) = themes
+        .iter()
+        .find_map(|t| t.search_match_bg.as_deref())
+        .and_then(Color::parse)
+    {
+        colors.push(("search_match_bg", color));
+    }
+    colors
+}
+
+/// The name of the palette namespace with the colors of `view`.
+fn view_namespace(view: &str) -> String {
+    format!("view.{view}")
+}
+
+/// Create a [cursive::theme::Theme] from `theme_cfg`. The colors of the views with their own
+/// colors are kept in a namespace of the palette each, see [for_view].
 pub fn load(theme_cfg: &Option<ConfigTheme>) -> Theme {
     let mut palette = Palette::default();
     let borders = BorderStyle::Simple;
 
-    palette[Background] = load_color!(theme_cfg, background, TerminalDefault);
-    palette[View] = load_color!(theme_cfg, background, TerminalDefault);
-    palette[Primary] = load_color!(theme_cfg, primary, TerminalDefault);
-    palette[Secondary] = load_color!(theme_cfg, secondary, Dark(Blue));
-    palette[TitlePrimary] = load_color!(theme_cfg, title, Dark(Red));
-    palette[HighlightText] = load_color!(theme_cfg, highlight, Dark(White));
-    palette[Highlight] = load_color!(theme_cfg, highlight_bg, Dark(Red));
-    palette[HighlightInactive] = load_color!(theme_cfg, highlight_inactive_bg, Dark(Blue));
-    palette.set_color("playing", load_color!(theme_cfg, playing, Dark(Blue)));
-    palette.set_color(
-        "playing_selected",
-        load_color!(theme_cfg, playing_selected, Light(Blue)),
-    );
-    palette.set_color(
-        "playing_bg",
-        load_color!(theme_cfg, playing_bg, TerminalDefault),
-    );
-    palette.set_color("error", load_color!(theme_cfg, error, TerminalDefault));
-    palette.set_color("error_bg", load_color!(theme_cfg, error_bg, Dark(Red)));
-    palette.set_color(
-        "statusbar_progress",
-        load_color!(theme_cfg, statusbar_progress, Dark(Blue)),
-    );
-    palette.set_color(
-        "statusbar_progress_bg",
-        load_color!(theme_cfg, statusbar_progress_bg, Light(Black)),
-    );
-    palette.set_color("statusbar", load_color!(theme_cfg, statusbar, Dark(Yellow)));
-    palette.set_color(
-        "statusbar_bg",
-        load_color!(theme_cfg, statusbar_bg, TerminalDefault),
-    );
-    palette.set_color("cmdline", load_color!(theme_cfg, cmdline, TerminalDefault));
-    palette.set_color(
-        "cmdline_bg",
-        load_color!(theme_cfg, cmdline_bg, TerminalDefault),
-    );
-    palette.set_color(
-        "search_match",
-        load_color!(theme_cfg, search_match, Light(Red)),
-    );
+    let base: Vec<&ConfigTheme> = theme_cfg.iter().collect();
+    for (name, color) in colors(&base) {
+        palette.set_color(name, color);
+    }
+    for (view, view_cfg) in theme_cfg.iter().flat_map(|t| t.views.iter().flatten()) {
+        let themes: Vec<&ConfigTheme> = std::iter::once(view_cfg).chain(base.clone()).collect();
+        let namespace = colors(&themes)
+            .into_iter()
+            .map(|(name, color)| (name.to_string(), PaletteNode::Color(color)))
+            .collect();
+        palette.add_namespace(&view_namespace(view), namespace);
+    }
 
     Theme {
         shadow: false,
@@ -88,3 +151,168 @@ pub fn load(theme_cfg: &Option<ConfigTheme>) -> Theme {
         borders,
     }
 }
+
+/// The theme of the screen `view` is on, with the colors configured for it replacing the others.
+pub fn for_view(theme: &Theme, view: &str) -> Theme {
+    Theme {
+        palette: theme.palette.merge(&view_namespace(view)),
+        ..theme.clone()
+    }
+}
+
+/// Whether the light or the regular, dark theme is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+/// Get the appearance that `schedule` asks for right now.
+pub fn scheduled_appearance(schedule: &ThemeSchedule) -> Appearance {
+    if schedule.follow_desktop.unwrap_or(false)
+        && let Some(appearance) = desktop_appearance()
+    {
+        return appearance;
+    }
+
+    let parse = |time: &Option<String>| {
+        time.as_ref().and_then(|time| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .inspect_err(|e| warn!("Invalid time \"{time}\" in theme schedule: {e}"))
+                .ok()
+        })
+    };
+    match (parse(&schedule.light), parse(&schedule.dark)) {
+        (Some(light), Some(dark)) => appearance_at(Local::now().time(), light, dark),
+        _ => Appearance::Dark,
+    }
+}
+
+/// Get the appearance at `now` if the light theme starts at `light` and the dark theme at `dark`.
+fn appearance_at(now: NaiveTime, light: NaiveTime, dark: NaiveTime) -> Appearance {
+    let is_light = if light <= dark {
+        light <= now && now < dark
+    } else {
+        now >= light || now < dark
+    };
+    if is_light {
+        Appearance::Light
+    } else {
+        Appearance::Dark
+    }
+}
+
+/// Read the color scheme preference of the desktop from the XDG desktop portal. Returns `None` if
+/// the portal isn't available or the user has no preference.
+#[cfg(feature = "dark_mode")]
+fn desktop_appearance() -> Option<Appearance> {
+    let runtime = crate::application::ASYNC_RUNTIME.get()?;
+    let scheme = runtime.block_on(async {
+        let connection = zbus::Connection::session().await.ok()?;
+        let reply = connection
+            .call_method(
+                Some("org.freedesktop.portal.Desktop"),
+                "/org/freedesktop/portal/desktop",
+                Some("org.freedesktop.portal.Settings"),
+                "ReadOne",
+                &("org.freedesktop.appearance", "color-scheme"),
+            )
+            .await
+            .inspect_err(|e| warn!("Could not read the desktop color scheme: {e}"))
+            .ok()?;
+        let value: zbus::zvariant::OwnedValue = reply.body().deserialize().ok()?;
+        u32::try_from(value).ok()
+    })?;
+    match scheme {
+        1 => Some(Appearance::Dark),
+        2 => Some(Appearance::Light),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "dark_mode"))]
+fn desktop_appearance() -> Option<Appearance> {
+    None
+}
+
+/// Switches between the light and the dark theme when the theme schedule or the desktop setting
+/// changes.
+pub struct ThemeSwitcher {
+    config: Arc<Config>,
+    appearance: Appearance,
+    checked: Instant,
+}
+
+impl ThemeSwitcher {
+    pub fn new(config: Arc<Config>) -> Self {
+        let appearance = config.appearance();
+        Self {
+            config,
+            appearance,
+            checked: Instant::now(),
+        }
+    }
+
+    /// Apply the theme for the current appearance if it changed since the last check.
+    pub fn update(&mut self, cursive: &mut Cursive) {
+        if self.checked.elapsed() < APPEARANCE_CHECK_INTERVAL
+            || self.config.values().theme_schedule.is_none()
+        {
+            return;
+        }
+        self.checked = Instant::now();
+
+        let appearance = self.config.appearance();
+        if appearance != self.appearance {
+            info!("switching to the {appearance:?} theme");
+            self.appearance = appearance;
+            cursive.set_theme(self.config.build_theme());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appearance_at() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+
+        assert_eq!(
+            appearance_at(time(12), time(7), time(19)),
+            Appearance::Light
+        );
+        assert_eq!(appearance_at(time(7), time(7), time(19)), Appearance::Light);
+        assert_eq!(appearance_at(time(19), time(7), time(19)), Appearance::Dark);
+        assert_eq!(appearance_at(time(3), time(7), time(19)), Appearance::Dark);
+
+        // A light theme at night.
+        assert_eq!(
+            appearance_at(time(23), time(22), time(6)),
+            Appearance::Light
+        );
+        assert_eq!(appearance_at(time(3), time(22), time(6)), Appearance::Light);
+        assert_eq!(appearance_at(time(12), time(22), time(6)), Appearance::Dark);
+    }
+
+    #[test]
+    fn test_for_view() {
+        let queue = ConfigTheme {
+            highlight_bg: Some("green".into()),
+            ..Default::default()
+        };
+        let theme = load(&Some(ConfigTheme {
+            highlight_bg: Some("blue".into()),
+            views: Some([("queue".to_string(), queue)].into()),
+            ..Default::default()
+        }));
+        let queue = for_view(&theme, "queue");
+        assert_eq!(queue.palette[PaletteColor::Highlight], Dark(Green));
+        // Colors that derive from others follow the colors of the view.
+        assert_eq!(queue.palette.custom("tab_active_bg"), Some(&Dark(Green)));
+        let library = for_view(&theme, "library");
+        assert_eq!(library.palette[PaletteColor::Highlight], Dark(Blue));
+        assert_eq!(library.palette.custom("search_match_bg"), None);
+    }
+}
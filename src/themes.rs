@@ -0,0 +1,70 @@
+//! Theme files in the `themes` directory next to the configuration file, which can be switched
+//! between while ncspot runs. A theme file has the same entries as the `[theme]` section of the
+//! configuration, and its name is the file name without the `.toml` extension.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{self, ConfigTheme};
+
+/// The name of the theme from the configuration file.
+pub const DEFAULT: &str = "default";
+
+/// The directory the theme files are read from.
+pub fn directory() -> PathBuf {
+    config::config_path("themes")
+}
+
+/// The names of the theme files, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Read the theme file called `name`.
+pub fn load(name: &str) -> Result<ConfigTheme, String> {
+    let path = directory().join(format!("{name}.toml"));
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read the theme \"{name}\": {e}"))?;
+    toml::from_str(&content).map_err(|e| format!("Invalid theme \"{name}\": {e}"))
+}
+
+/// The theme names out of `names` that complete the argument of the `theme` command in `input`,
+/// which doesn't include the command key.
+pub fn complete<'a>(input: &str, names: &'a [String]) -> Vec<&'a str> {
+    let Some(argument) = input.strip_prefix("theme ") else {
+        return Vec::new();
+    };
+    let argument = argument.trim_start();
+    names
+        .iter()
+        .map(String::as_str)
+        .filter(|name| name.starts_with(argument) && *name != argument)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        let names = ["default", "dracula", "solarized"].map(String::from);
+        assert_eq!(complete("theme d", &names), ["default", "dracula"]);
+        assert_eq!(complete("theme  so", &names), ["solarized"]);
+        assert_eq!(complete("theme ", &names).len(), 3);
+        assert!(complete("theme solarized", &names).is_empty());
+        assert!(complete("device d", &names).is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! Optional encryption of the cached Web API token, so it isn't stored in plain text in backups of
+//! the configuration directory.
+//!
+//! The token is sealed with ChaCha20-Poly1305. The key is derived from a passphrase with PBKDF2, or
+//! is a random machine key kept in the cache directory, which backups usually leave out.
+
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use log::info;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::config;
+
+/// The environment variable that provides the passphrase instead of a prompt.
+const PASSPHRASE_VARIABLE: &str = "NCSPOT_TOKEN_PASSPHRASE";
+
+const MACHINE_KEY_FILE: &str = "token.key";
+
+/// PBKDF2-HMAC-SHA256 iterations, as recommended by OWASP.
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+/// Where the key that encrypts the token cache comes from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenEncryption {
+    /// Derived from a passphrase that is asked for on startup.
+    Passphrase,
+    /// A random key stored in the cache directory.
+    Machine,
+}
+
+/// An encrypted token cache file.
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    encryption: TokenEncryption,
+    /// The salt of the passphrase, empty for the machine key.
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+static ENCRYPTION: OnceLock<Option<TokenEncryption>> = OnceLock::new();
+
+/// The key of this run and the salt it was derived with, so the passphrase is only asked once.
+static KEY: OnceLock<(TokenEncryption, Vec<u8>, [u8; KEY_LEN])> = OnceLock::new();
+
+/// Set how the token cache is encrypted from now on, `None` to store it in plain text.
+pub fn init(encryption: Option<TokenEncryption>) {
+    ENCRYPTION.set(encryption).ok();
+}
+
+fn configured() -> Option<TokenEncryption> {
+    ENCRYPTION.get().copied().flatten()
+}
+
+/// Whether `data` read from the token cache should be written again to match the configuration.
+pub fn needs_update(data: &[u8]) -> bool {
+    let sealed = serde_json::from_slice::<Sealed>(data).ok();
+    sealed.map(|sealed| sealed.encryption) != configured()
+}
+
+/// Encrypt the token cache contents `plaintext` as configured.
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(encryption) = configured() else {
+        return Ok(plaintext.to_vec());
+    };
+    let rng = SystemRandom::new();
+    let (salt, key) = match KEY.get() {
+        Some((used, salt, key)) if *used == encryption => (salt.clone(), *key),
+        _ => {
+            let salt = match encryption {
+                TokenEncryption::Passphrase => random::<SALT_LEN>(&rng)?.to_vec(),
+                TokenEncryption::Machine => Vec::new(),
+            };
+            let key = derive_key(encryption, &salt, true)?;
+            KEY.set((encryption, salt.clone(), key)).ok();
+            (salt, key)
+        }
+    };
+
+    let nonce = random::<NONCE_LEN>(&rng)?;
+    let mut ciphertext = plaintext.to_vec();
+    aead_key(&key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut ciphertext,
+        )
+        .map_err(|_| "Failed to encrypt token".to_string())?;
+
+    let sealed = Sealed {
+        encryption,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    serde_json::to_vec_pretty(&sealed).map_err(|e| format!("Serialize error: {}", e))
+}
+
+/// Decrypt the token cache contents `data`, which are returned as they are if they aren't
+/// encrypted.
+pub fn open(data: &[u8]) -> Result<Vec<u8>, String> {
+    let Ok(sealed) = serde_json::from_slice::<Sealed>(data) else {
+        return Ok(data.to_vec());
+    };
+    let decode = |value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|e| format!("Invalid token cache: {}", e))
+    };
+    let salt = decode(&sealed.salt)?;
+    let nonce = Nonce::try_assume_unique_for_key(&decode(&sealed.nonce)?)
+        .map_err(|_| "Invalid token cache nonce".to_string())?;
+    let mut ciphertext = decode(&sealed.ciphertext)?;
+
+    let key = derive_key(sealed.encryption, &salt, false)?;
+    let plaintext = aead_key(&key)
+        .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+        .map_err(|_| "Failed to decrypt token cache, wrong passphrase?".to_string())?;
+    let plaintext = plaintext.to_vec();
+    KEY.set((sealed.encryption, salt, key)).ok();
+    Ok(plaintext)
+}
+
+/// Get the key for `encryption`, creating a machine key or asking for a new passphrase if `create`
+/// is set.
+fn derive_key(
+    encryption: TokenEncryption,
+    salt: &[u8],
+    create: bool,
+) -> Result<[u8; KEY_LEN], String> {
+    match encryption {
+        TokenEncryption::Passphrase => {
+            let passphrase = passphrase(create)?;
+            let mut key = [0; KEY_LEN];
+            pbkdf2::derive(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations are not zero"),
+                salt,
+                passphrase.as_bytes(),
+                &mut key,
+            );
+            Ok(key)
+        }
+        TokenEncryption::Machine => machine_key(create),
+    }
+}
+
+fn machine_key(create: bool) -> Result<[u8; KEY_LEN], String> {
+    let path = config::cache_path(MACHINE_KEY_FILE);
+    match fs::read(&path) {
+        Ok(key) => key
+            .try_into()
+            .map_err(|_| format!("Invalid machine key in {}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound && create => {
+            let key = random::<KEY_LEN>(&SystemRandom::new())?;
+            let mut options = fs::File::options();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options
+                .open(&path)
+                .and_then(|mut file| file.write_all(&key))
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            info!("Created machine key {:?}", path);
+            Ok(key)
+        }
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Get the passphrase from the environment or ask for it, twice if it is a new one.
+fn passphrase(new: bool) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+    let passphrase = read_hidden("Token cache passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("The token cache passphrase can't be empty".to_string());
+    }
+    if new && read_hidden("Repeat the passphrase: ")? != passphrase {
+        return Err("The passphrases don't match".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// Read a line from the terminal without echoing it.
+fn read_hidden(prompt: &str) -> Result<String, String> {
+    print!("{prompt}");
+    io::stdout().flush().ok();
+
+    #[cfg(unix)]
+    let echo = unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        (libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0).then(|| {
+            let original = termios;
+            termios.c_lflag &= !libc::ECHO;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            original
+        })
+    };
+
+    let mut input = String::new();
+    let result = io::stdin().read_line(&mut input);
+
+    #[cfg(unix)]
+    if let Some(original) = echo {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original) };
+        println!();
+    }
+
+    result.map_err(|e| format!("Failed to read input: {}", e))?;
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn aead_key(key: &[u8; KEY_LEN]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).expect("key has the right length"))
+}
+
+fn random<const N: usize>(rng: &SystemRandom) -> Result<[u8; N], String> {
+    let mut bytes = [0; N];
+    rng.fill(&mut bytes)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        // SAFETY: No other test reads this variable.
+        unsafe { std::env::set_var(PASSPHRASE_VARIABLE, "correct horse") };
+        init(Some(TokenEncryption::Passphrase));
+
+        let sealed = seal(br#"{"access_token":"abc"}"#).unwrap();
+        assert!(!needs_update(&sealed));
+        assert!(!String::from_utf8_lossy(&sealed).contains("abc"));
+        assert_eq!(open(&sealed).unwrap(), br#"{"access_token":"abc"}"#);
+
+        assert!(needs_update(br#"{"access_token":"abc"}"#));
+        assert_eq!(open(b"{}").unwrap(), b"{}");
+    }
+}
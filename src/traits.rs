@@ -6,11 +6,15 @@ use cursive::views::NamedView;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::playlist::Playlist;
 use crate::model::track::Track;
-use crate::queue::Queue;
+use crate::queue::{Queue, RadioSeed};
+use crate::sorting::SortFields;
+use crate::spotify::Spotify;
 
 pub trait ListItem: Sync + Send + 'static {
     fn is_playing(&self, queue: &Queue) -> bool;
@@ -35,6 +39,16 @@ pub trait ListItem: Sync + Send + 'static {
     }
     fn share_url(&self) -> Option<String>;
 
+    /// The name this [ListItem] is shared with, like "Artist – Title".
+    fn share_name(&self) -> Option<String> {
+        None
+    }
+
+    /// The seeds to start a radio based on this [ListItem].
+    fn radio_seed(&self) -> Option<RadioSeed> {
+        None
+    }
+
     /// Get the album that contains this [ListItem].
     fn album(&self, _queue: &Queue) -> Option<Album> {
         None
@@ -48,6 +62,25 @@ pub trait ListItem: Sync + Send + 'static {
         None
     }
 
+    fn playlist(&self) -> Option<Playlist> {
+        None
+    }
+
+    /// The values this [ListItem] is sorted by, if it can be sorted.
+    fn sort_fields(&self) -> Option<SortFields> {
+        self.track().as_ref().map(SortFields::from)
+    }
+
+    /// Whether this [ListItem] is a collection of tracks, like an album or a playlist.
+    fn is_collection(&self) -> bool {
+        false
+    }
+
+    /// Load all tracks of a collection. Other items have no tracks.
+    fn collection_tracks(&self, _spotify: &Spotify) -> Vec<Track> {
+        Vec::new()
+    }
+
     #[allow(unused_variables)]
     #[inline]
     fn is_saved(&self, library: &Library) -> Option<bool> {
@@ -73,6 +106,23 @@ pub trait ViewExt: View {
 
     fn on_leave(&self) {}
 
+    /// The contexts of the commands that work in this view, besides the global ones.
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        Vec::new()
+    }
+
+    /// The selected item, to return to it with a mark or the jump list.
+    fn position(&self) -> Option<usize> {
+        None
+    }
+
+    fn set_position(&mut self, _position: usize) {}
+
+    /// The open.spotify.com URL of the item shown, to open the view again in the next session.
+    fn url(&self) -> Option<String> {
+        None
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, _cmd: &Command) -> Result<CommandResult, String> {
         Ok(CommandResult::Ignored)
     }
@@ -91,6 +141,22 @@ impl<V: ViewExt> ViewExt for NamedView<V> {
         self.with_view(|v| v.on_leave());
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.with_view(|v| v.help_contexts()).unwrap_or_default()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.with_view(|v| v.position()).flatten()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.with_view_mut(|v| v.set_position(position));
+    }
+
+    fn url(&self) -> Option<String> {
+        self.with_view(|v| v.url()).flatten()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.with_view_mut(move |v| v.on_command(s, cmd)).unwrap()
     }
@@ -177,6 +243,22 @@ impl ViewExt for BoxedViewExt {
         self.boxed_view.on_leave();
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.boxed_view.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.boxed_view.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.boxed_view.set_position(position);
+    }
+
+    fn url(&self) -> Option<String> {
+        self.boxed_view.url()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.boxed_view.on_command(s, cmd)
     }
@@ -1,20 +1,33 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, RwLock};
 
 use cursive::Cursive;
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
 use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+use rspotify::model::Id;
 
+use crate::columns::ColumnSet;
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::grouped_track::GroupedTrack;
+use crate::model::playable::Playable;
+use crate::model::track::Track;
 use crate::queue::Queue;
-use crate::traits::ViewExt;
+use crate::sorting;
+use crate::traits::{ListItem, ViewExt};
 use crate::ui::listview::ListView;
 use crate::ui::tabbedview::TabbedView;
 
 pub struct AlbumView {
     album: Album,
+    /// The rows of the tracks tab.
+    entries: Arc<RwLock<Vec<GroupedTrack>>>,
     tabs: TabbedView,
 }
 
@@ -29,6 +42,8 @@ impl AlbumView {
         } else {
             Vec::new()
         };
+        let entries = Arc::new(RwLock::new(Self::entries(&tracks)));
+        Self::load_popularity(&queue, &library, &tracks, entries.clone());
 
         let artists = album
             .artist_ids
@@ -40,21 +55,128 @@ impl AlbumView {
         let mut tabs = TabbedView::new();
         tabs.add_tab(
             "Tracks",
-            ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                queue.clone(),
-                library.clone(),
-            ),
+            ListView::new(entries.clone(), queue.clone(), library.clone())
+                .with_columns(ColumnSet::Album),
         );
         tabs.add_tab(
             "Artists",
             ListView::new(Arc::new(RwLock::new(artists)), queue, library),
         );
+        tabs.add_tab("Info", AlbumInfoView::new(&album));
 
-        Self { album, tabs }
+        Self {
+            album,
+            entries,
+            tabs,
+        }
+    }
+
+    /// The rows of `tracks`, under a header for each disc if there is more than one.
+    fn entries(tracks: &[Track]) -> Vec<GroupedTrack> {
+        let discs: BTreeSet<i32> = tracks.iter().map(|track| track.disc_number).collect();
+        let grouped = discs.len() > 1;
+        let track = |track: &Track| GroupedTrack::Track {
+            playable: Box::new(Playable::Track(track.clone())),
+            in_group: grouped,
+        };
+        if !grouped {
+            return tracks.iter().map(track).collect();
+        }
+
+        let mut entries = Vec::new();
+        for disc in discs {
+            let disc_tracks: Vec<&Track> = tracks
+                .iter()
+                .filter(|track| track.disc_number == disc)
+                .collect();
+            entries.push(GroupedTrack::Header {
+                name: format!("Disc {disc}"),
+                tracks: disc_tracks.len(),
+                duration: disc_tracks.iter().map(|track| track.duration).sum(),
+                collapsed: false,
+            });
+            entries.extend(disc_tracks.into_iter().map(track));
+        }
+        entries
+    }
+
+    /// Fetch the popularity of `tracks` in the background, as the tracks of an album don't have
+    /// one, and show it in `entries`.
+    fn load_popularity(
+        queue: &Queue,
+        library: &Arc<Library>,
+        tracks: &[Track],
+        entries: Arc<RwLock<Vec<GroupedTrack>>>,
+    ) {
+        let ids: Vec<String> = tracks.iter().filter_map(|track| track.id.clone()).collect();
+        if ids.is_empty() {
+            return;
+        }
+        let library = library.clone();
+        queue.get_spotify().api.spawn(move |api| {
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            let Ok(full_tracks) = api.tracks(&ids) else {
+                return;
+            };
+            let popularity: HashMap<String, u32> = full_tracks
+                .iter()
+                .filter_map(|track| Some((track.id.as_ref()?.id().to_string(), track.popularity)))
+                .collect();
+            for entry in entries.write().unwrap().iter_mut() {
+                if let GroupedTrack::Track { playable, .. } = entry
+                    && let Playable::Track(track) = playable.as_mut()
+                    && let Some(id) = &track.id
+                {
+                    track.popularity = popularity.get(id).copied();
+                }
+            }
+            library.trigger_redraw();
+        });
     }
 }
 
+/// The release date, label and copyrights of an album.
+struct AlbumInfoView {
+    view: ScrollView<TextView>,
+}
+
+impl AlbumInfoView {
+    fn new(album: &Album) -> Self {
+        let mut text = StyledString::new();
+        let mut row = |label: &str, value: &str| {
+            text.append_styled(format!("{label:<14}"), Effect::Bold);
+            text.append(format!("{value}\n"));
+        };
+        row("Artists", &album.artists.join(", "));
+        row(
+            "Released",
+            album.release_date.as_deref().unwrap_or(&album.year),
+        );
+        if let Some(label) = &album.label {
+            row("Label", label);
+        }
+        if let Some(tracks) = &album.tracks {
+            let discs: BTreeSet<i32> = tracks.iter().map(|track| track.disc_number).collect();
+            if discs.len() > 1 {
+                row("Discs", &discs.len().to_string());
+            }
+        }
+        for (i, copyright) in album.copyrights.iter().enumerate() {
+            row(if i == 0 { "Copyright" } else { "" }, copyright);
+        }
+
+        Self {
+            view: ScrollView::new(TextView::new(text)),
+        }
+    }
+}
+
+impl ViewWrapper for AlbumInfoView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+}
+
+impl ViewExt for AlbumInfoView {}
+
 impl ViewWrapper for AlbumView {
     wrap_impl!(self.tabs: TabbedView);
 }
@@ -75,7 +197,39 @@ impl ViewExt for AlbumView {
         }
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.tabs.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
+    fn url(&self) -> Option<String> {
+        self.album.share_url()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        // Sort the tracks within their discs, so they stay under the header of their disc.
+        if let Command::Sort(key, direction) = cmd
+            && self.tabs.selected() == 0
+        {
+            let mut tracks: Vec<Track> = self
+                .entries
+                .read()
+                .unwrap()
+                .iter()
+                .filter_map(|entry| entry.playable()?.track())
+                .collect();
+            sorting::sort(&mut tracks, key, direction);
+            *self.entries.write().unwrap() = Self::entries(&tracks);
+            return Ok(CommandResult::Consumed(None));
+        }
+
         self.tabs.on_command(s, cmd)
     }
 }
@@ -7,18 +7,22 @@ use rspotify::model::AlbumType;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
+use crate::model::playable::Playable;
 use crate::model::track::Track;
 use crate::queue::Queue;
-use crate::traits::ViewExt;
+use crate::traits::{ListItem, ViewExt};
 use crate::ui::listview::ListView;
 use crate::ui::tabbedview::TabbedView;
 
 pub struct ArtistView {
     artist: Artist,
     tabs: TabbedView,
+    followed: Arc<RwLock<Option<bool>>>,
+    queue: Arc<Queue>,
 }
 
 impl ArtistView {
@@ -29,16 +33,23 @@ impl ArtistView {
             Self::albums_view(artist, AlbumType::Album, queue.clone(), library.clone());
         let singles_view =
             Self::albums_view(artist, AlbumType::Single, queue.clone(), library.clone());
+        let compilations_view = Self::albums_view(
+            artist,
+            AlbumType::Compilation,
+            queue.clone(),
+            library.clone(),
+        );
+        let appears_on_view =
+            Self::albums_view(artist, AlbumType::AppearsOn, queue.clone(), library.clone());
 
         let top_tracks: Arc<RwLock<Vec<Track>>> = Arc::new(RwLock::new(Vec::new()));
         {
             let top_tracks = top_tracks.clone();
-            let spotify = spotify.clone();
             let id = artist.id.clone();
             let library = library.clone();
-            thread::spawn(move || {
+            spotify.api.spawn(move |api| {
                 if let Some(id) = id
-                    && let Ok(tracks) = spotify.api.artist_top_tracks(&id)
+                    && let Ok(tracks) = api.artist_top_tracks(&id)
                 {
                     top_tracks.write().unwrap().extend(tracks);
                     library.trigger_redraw();
@@ -46,14 +57,26 @@ impl ArtistView {
             });
         }
 
+        let followed = Arc::new(RwLock::new(None));
+        {
+            let followed = followed.clone();
+            let artist = artist.clone();
+            let library = library.clone();
+            spotify.api.spawn(move |_| {
+                let is_followed = library.check_followed_artist(&artist);
+                *followed.write().unwrap() = Some(is_followed);
+                library.trigger_redraw();
+            });
+        }
+
         let related: Arc<RwLock<Vec<Artist>>> = Arc::new(RwLock::new(Vec::new()));
         {
             let related = related.clone();
             let id = artist.id.clone();
             let library = library.clone();
-            thread::spawn(move || {
+            spotify.api.spawn(move |api| {
                 if let Some(id) = id
-                    && let Ok(artists) = spotify.api.artist_related_artists(&id)
+                    && let Ok(artists) = api.artist_related_artists(&id)
                 {
                     related.write().unwrap().extend(artists);
                     library.trigger_redraw();
@@ -75,17 +98,24 @@ impl ArtistView {
                 ),
             );
         }
+        tabs.add_tab("Albums", albums_view);
+        tabs.add_tab("Singles & EPs", singles_view);
+        tabs.add_tab("Compilations", compilations_view);
+        tabs.add_tab("Appears On", appears_on_view);
         tabs.add_tab(
-            "Top 10",
+            "Top Tracks",
             ListView::new(top_tracks, queue.clone(), library.clone()),
         );
-        tabs.add_tab("Albums", albums_view);
-        tabs.add_tab("Singles", singles_view);
-        tabs.add_tab("Related Artists", ListView::new(related, queue, library));
+        tabs.add_tab(
+            "Related Artists",
+            ListView::new(related, queue.clone(), library),
+        );
 
         Self {
             artist: artist.clone(),
             tabs,
+            followed,
+            queue,
         }
     }
 
@@ -117,7 +147,53 @@ impl ViewExt for ArtistView {
         self.artist.name.clone()
     }
 
+    fn title_sub(&self) -> String {
+        match *self.followed.read().unwrap() {
+            Some(true) => "Followed ✓".to_string(),
+            Some(false) => "Not followed".to_string(),
+            None => "".to_string(),
+        }
+    }
+
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        let mut contexts = self.tabs.help_contexts();
+        contexts.push(HelpContext::Artist);
+        contexts
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
+    fn url(&self) -> Option<String> {
+        self.artist.share_url()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Command::Discography = cmd {
+            let queue = self.queue.clone();
+            let artist = self.artist.clone();
+            thread::spawn(move || {
+                let tracks: Vec<Playable> = artist
+                    .discography(&queue.get_spotify())
+                    .into_iter()
+                    .map(Playable::Track)
+                    .collect();
+                if !tracks.is_empty() {
+                    let index = queue.append_next(&tracks);
+                    queue.play(index, true, true);
+                }
+            });
+            return Ok(CommandResult::Consumed(Some(format!(
+                "Loading the discography of {}",
+                self.artist.name
+            ))));
+        }
+
         self.tabs.on_command(s, cmd)
     }
 }
@@ -5,6 +5,7 @@ use cursive::view::ViewWrapper;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::category::Category;
 use crate::queue::Queue;
@@ -35,6 +36,18 @@ impl ViewExt for BrowseView {
         "Browse".to_string()
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.list.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.list.on_command(s, cmd)
     }
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+
+use crate::caches::CacheKind;
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::config::Config;
+use crate::traits::ViewExt;
+use crate::utils::format_size;
+
+/// The size of the caches on disk and their limits.
+pub struct CacheView {
+    view: ScrollView<TextView>,
+}
+
+impl CacheView {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        let mut text = StyledString::styled("Caches\n\n", Effect::Bold);
+        text.append_styled(
+            format!("{:<16}{:>14}{:>14}  Directory\n", "", "Size", "Limit"),
+            Effect::Italic,
+        );
+        let mut total = 0;
+        for kind in CacheKind::ALL {
+            let size = kind.size();
+            total += size;
+            let limit = kind.max_size(&cfg).map_or("none".to_string(), format_size);
+            text.append(format!(
+                "{:<16}{:>14}{limit:>14}  {}\n",
+                kind.to_string(),
+                format_size(size),
+                kind.path().display()
+            ));
+        }
+        text.append(format!("{:<16}{:>14}\n", "Total", format_size(total)));
+        text.append_styled(
+            "\nThe least recently used files are removed once a cache exceeds its limit. Cover \
+             art is pruned when ncspot starts. `cache clear audio`, `cache clear images` and \
+             `cache clear api` empty a cache.\n",
+            Effect::Italic,
+        );
+
+        Self {
+            view: ScrollView::new(TextView::new(text)),
+        }
+    }
+}
+
+impl ViewWrapper for CacheView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+}
+
+impl ViewExt for CacheView {
+    fn title(&self) -> String {
+        "Caches".to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::ShowCache => Ok(CommandResult::Consumed(None)),
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::Dialog;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::help::HelpContext;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::playlist_merge;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::contextmenu::ContextMenu;
+use crate::ui::listview::ListView;
+use crate::ui::modal::Modal;
+use crate::ui::tabbedview::TabbedView;
+
+/// The compared playlists and the tracks of the tabs: only in the first, only in the second and
+/// in both. Shared with the dialogs that change the playlists.
+#[derive(Clone)]
+struct Compared {
+    playlists: Arc<RwLock<[Playlist; 2]>>,
+    tabs: [Arc<RwLock<Vec<Playable>>>; 3],
+    /// Whether the tracks of the playlists are still being loaded.
+    loading: Arc<AtomicBool>,
+}
+
+impl Compared {
+    fn update(&self) {
+        let playlists = self.playlists.read().unwrap();
+        let [a, b] = &*playlists;
+        let diff = playlist_merge::diff(
+            a.tracks.as_deref().unwrap_or_default(),
+            b.tracks.as_deref().unwrap_or_default(),
+        );
+        *self.tabs[0].write().unwrap() = diff.only_a;
+        *self.tabs[1].write().unwrap() = diff.only_b;
+        *self.tabs[2].write().unwrap() = diff.both;
+    }
+
+    /// Add the tracks of the tab at `tab` to the playlist at `into`.
+    fn copy(&self, tab: usize, into: usize, queue: &Queue, library: &Library) {
+        let tracks = self.tabs[tab].read().unwrap().clone();
+        self.playlists.write().unwrap()[into].append_tracks(&tracks, &queue.get_spotify(), library);
+        self.update();
+    }
+}
+
+/// Two playlists compared with each other. The tracks of the selected tab can be copied to the
+/// other playlist or saved to a new one.
+pub struct CompareView {
+    compared: Compared,
+    tabs: TabbedView,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl CompareView {
+    /// Compare `a` and `b`, whose tracks are loaded in the background.
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, a: Playlist, b: Playlist) -> Self {
+        let lists = [(); 3].map(|_| Arc::new(RwLock::new(Vec::new())));
+        let mut tabs = TabbedView::new();
+        let titles = [
+            format!("Only in {}", a.name),
+            format!("Only in {}", b.name),
+            "In both".to_string(),
+        ];
+        for (title, list) in titles.into_iter().zip(&lists) {
+            tabs.add_tab(
+                title,
+                ListView::new(list.clone(), queue.clone(), library.clone()),
+            );
+        }
+
+        let compared = Compared {
+            playlists: Arc::new(RwLock::new([a.clone(), b.clone()])),
+            tabs: lists,
+            loading: Arc::new(AtomicBool::new(true)),
+        };
+
+        {
+            let compared = compared.clone();
+            let library = library.clone();
+            let spotify = queue.get_spotify();
+            queue.get_spotify().api.spawn(move |_| {
+                let mut loaded = [a, b];
+                for playlist in loaded.iter_mut() {
+                    playlist.load_tracks(&spotify);
+                }
+                *compared.playlists.write().unwrap() = loaded;
+                compared.update();
+                compared.loading.store(false, Ordering::Relaxed);
+                library.trigger_redraw();
+            });
+        }
+
+        Self {
+            compared,
+            tabs,
+            queue,
+            library,
+        }
+    }
+
+    /// Offer to copy the tracks of the selected tab to the other playlist, if it can be changed,
+    /// or to save them to a new one.
+    fn copy_dialog(&self) -> Modal<Dialog> {
+        let tab = self.tabs.selected();
+        let tracks = self.compared.tabs[tab].read().unwrap().clone();
+        let mut dialog = Dialog::text(format!("Copy {} tracks to:", tracks.len()))
+            .title("Copy tracks")
+            .padding(Margins::lrtb(1, 1, 1, 0));
+
+        // The tracks only in one playlist can be added to the other one.
+        let into = match tab {
+            0 => Some(1),
+            1 => Some(0),
+            _ => None,
+        };
+        let playlists = self.compared.playlists.read().unwrap();
+        if let Some(into) = into
+            && (self.library.user_id.as_ref() == Some(&playlists[into].owner_id)
+                || playlists[into].collaborative)
+        {
+            let compared = self.compared.clone();
+            let queue = self.queue.clone();
+            let library = self.library.clone();
+            dialog.add_button(playlists[into].name.clone(), move |s| {
+                compared.copy(tab, into, &queue, &library);
+                s.pop_layer();
+            });
+        }
+
+        let library = self.library.clone();
+        dialog.add_button("Other playlist", move |s| {
+            s.pop_layer();
+            s.add_layer(ContextMenu::save_tracks_dialog(
+                library.clone(),
+                tracks.clone(),
+            ));
+        });
+        Modal::new(dialog.dismiss_button("Cancel"))
+    }
+}
+
+impl ViewWrapper for CompareView {
+    wrap_impl!(self.tabs: TabbedView);
+}
+
+impl ViewExt for CompareView {
+    fn title(&self) -> String {
+        let playlists = self.compared.playlists.read().unwrap();
+        format!("Compare: {} and {}", playlists[0].name, playlists[1].name)
+    }
+
+    fn title_sub(&self) -> String {
+        if self.compared.loading.load(Ordering::Relaxed) {
+            return "Loading tracks...".to_string();
+        }
+        let [only_a, only_b, both] = &self.compared.tabs;
+        format!(
+            "{} only in the first, {} only in the second, {} in both",
+            only_a.read().unwrap().len(),
+            only_b.read().unwrap().len(),
+            both.read().unwrap().len()
+        )
+    }
+
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.tabs.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Command::SaveQueue = cmd {
+            if self.compared.loading.load(Ordering::Relaxed) {
+                return Err("The tracks are still loading.".to_string());
+            }
+            s.add_layer(self.copy_dialog());
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        self.tabs.on_command(s, cmd)
+    }
+}
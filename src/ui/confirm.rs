@@ -0,0 +1,146 @@
+//! Confirmations for operations that are hard to take back, configured in the `[confirm]` section
+//! of the configuration.
+
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::view::Margins;
+use cursive::views::{Dialog, EditView, LinearLayout, TextView};
+
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::ui::modal::Modal;
+
+/// Deleting playlists with at least this many tracks asks for their name by default.
+const DEFAULT_TYPE_NAME_ABOVE: usize = 100;
+
+/// Run `action` once the user answered `question` with yes, or right away if `enabled` is false.
+fn confirm(
+    s: &mut Cursive,
+    enabled: bool,
+    title: &str,
+    question: &str,
+    action: impl Fn(&mut Cursive) + Send + Sync + 'static,
+) {
+    if !enabled {
+        action(s);
+        return;
+    }
+    let dialog = Dialog::text(question)
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title(title)
+        .dismiss_button("No")
+        .button("Yes", move |s| {
+            s.pop_layer();
+            action(s);
+        });
+    s.add_layer(Modal::new(dialog));
+}
+
+/// Run `action` once the user typed `name`.
+fn confirm_typed(
+    s: &mut Cursive,
+    title: &str,
+    question: &str,
+    name: String,
+    action: impl Fn(&mut Cursive) + Send + Sync + 'static,
+) {
+    let action = Arc::new(action);
+    let check = move |s: &mut Cursive, typed: &str| {
+        if typed == name {
+            s.pop_layer();
+            action(s);
+        } else {
+            s.call_on_name("confirm_hint", |v: &mut TextView| {
+                v.set_content("The name doesn't match, try again.");
+            });
+        }
+    };
+    let check = Arc::new(check);
+
+    let submit = check.clone();
+    let edit = EditView::new()
+        .on_submit(move |s, typed| submit(s, typed))
+        .with_name("confirm_name")
+        .fixed_width(30);
+    let content = LinearLayout::vertical()
+        .child(TextView::new(question))
+        .child(TextView::new("Type its name to confirm:").with_name("confirm_hint"))
+        .child(edit);
+    let dialog = Dialog::around(content)
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title(title)
+        .dismiss_button("Cancel")
+        .button("Delete", move |s| {
+            let typed = s
+                .call_on_name("confirm_name", |v: &mut EditView| v.get_content())
+                .unwrap_or_default();
+            check(s, &typed);
+        });
+    s.add_layer(Modal::new(dialog));
+}
+
+/// Delete `playlist`, after asking the user as configured.
+pub fn delete_playlist(s: &mut Cursive, library: Arc<Library>, playlist: Playlist) {
+    let (enabled, type_name_above) = {
+        let confirm = library.cfg.values().confirm.clone().unwrap_or_default();
+        (
+            confirm.delete_playlist.unwrap_or(true),
+            confirm.type_name_above.unwrap_or(DEFAULT_TYPE_NAME_ABOVE),
+        )
+    };
+    let id = playlist.id.clone();
+    let action = move |_: &mut Cursive| library.delete_playlist(&id);
+
+    if enabled && type_name_above > 0 && playlist.num_tracks >= type_name_above {
+        let question = format!(
+            "\"{}\" has {} tracks. Are you sure you want to delete it?",
+            playlist.name, playlist.num_tracks
+        );
+        confirm_typed(s, "Delete playlist", &question, playlist.name, action);
+    } else {
+        confirm(
+            s,
+            enabled,
+            "Delete playlist",
+            "Are you sure you want to delete this playlist?",
+            action,
+        );
+    }
+}
+
+/// Replace the tracks of the playlist with `id` with `tracks`, after asking the user as
+/// configured.
+pub fn overwrite_playlist(
+    s: &mut Cursive,
+    library: Arc<Library>,
+    id: String,
+    tracks: Vec<Playable>,
+) {
+    let enabled = library
+        .cfg
+        .values()
+        .confirm
+        .as_ref()
+        .and_then(|confirm| confirm.overwrite_playlist)
+        .unwrap_or(true);
+    let playlist = library
+        .playlists
+        .read()
+        .unwrap()
+        .iter()
+        .find(|p| p.id == id)
+        .map(|p| (p.name.clone(), p.num_tracks));
+    let question = match playlist {
+        Some((name, num_tracks)) => format!(
+            "Replace the {num_tracks} tracks of \"{name}\" with {} tracks?",
+            tracks.len()
+        ),
+        None => "Replace the tracks of this playlist?".to_string(),
+    };
+    confirm(s, enabled, "Overwrite playlist", &question, move |_| {
+        library.overwrite_playlist(&id, &tracks)
+    });
+}
@@ -1,23 +1,37 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 use cursive::Cursive;
+use cursive::traits::Resizable;
 use cursive::view::{Margins, ViewWrapper};
-use cursive::views::{Dialog, NamedView, ScrollView, SelectView};
+use cursive::views::{
+    Dialog, DummyView, EditView, LinearLayout, NamedView, ScrollView, SelectView, TextView,
+};
 
 use crate::commands::CommandResult;
+use crate::downloads;
 use crate::ext_traits::SelectViewExt;
 use crate::library::Library;
+use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::track::Track;
-use crate::queue::Queue;
+use crate::playlist_history::PlaylistSnapshot;
+use crate::queue::{Queue, RadioSeed};
 #[cfg(feature = "share_clipboard")]
 use crate::sharing::write_share;
 use crate::spotify::PlayerEvent;
+#[cfg(feature = "share_clipboard")]
+use crate::spotify_url::SpotifyUrl;
 use crate::traits::{ListItem, ViewExt};
+use crate::ui::compare::CompareView;
+use crate::ui::confirm;
 use crate::ui::layout::Layout;
+use crate::ui::merge::MergeView;
 use crate::ui::modal::Modal;
+use crate::ui::tune_recommendations::TuneRecommendationsDialog;
 use crate::{command::Command, spotify::Spotify};
 use cursive::traits::{Finder, Nameable};
 
@@ -37,15 +51,54 @@ pub struct SelectArtistActionMenu {
     dialog: Modal<Dialog>,
 }
 
+pub struct RestorePlaylistMenu {
+    dialog: Modal<Dialog>,
+}
+
+pub struct MergePlaylistsMenu {
+    dialog: Modal<Dialog>,
+}
+
+pub struct ComparePlaylistsMenu {
+    dialog: Modal<Dialog>,
+}
+
+pub struct TrackInfoMenu {
+    dialog: Modal<Dialog>,
+}
+
+#[cfg(feature = "share_clipboard")]
+pub struct ShareMenu {
+    dialog: Modal<Dialog>,
+}
+
+/// What can be done from the track info dialog.
+enum TrackInfoAction {
+    /// Open the view of an artist or the album of the track.
+    Open(Box<dyn ListItem>),
+    Credits(Box<Track>),
+    #[cfg(feature = "share_clipboard")]
+    Copy(String),
+}
+
 enum ContextMenuAction {
     ShowItem(Box<dyn ListItem>),
     SelectArtist(Vec<Artist>),
     SelectArtistAction(Artist),
     #[cfg(feature = "share_clipboard")]
-    ShareUrl(String),
+    Share(Box<dyn ListItem>),
     AddToPlaylist(Box<Track>),
     ShowRecommendations(Box<Track>),
+    ShowTrackDetails(Box<Track>),
+    ShowCredits(Box<Track>),
+    RestorePlaylist(Box<Playlist>),
+    MergePlaylists(Box<Playlist>),
+    ComparePlaylists(Box<Playlist>),
+    StartRadio(RadioSeed),
+    TuneRecommendations(Box<dyn ListItem>, RadioSeed),
     ToggleSavedStatus(Box<dyn ListItem>),
+    SetAllTracksSaved(Box<dyn ListItem>, bool),
+    Download(Box<dyn ListItem>),
     Play(Box<dyn ListItem>),
     PlayNext(Box<dyn ListItem>),
     TogglePlayback,
@@ -53,11 +106,17 @@ enum ContextMenuAction {
 }
 
 impl ContextMenu {
-    pub fn add_track_dialog(
+    /// A dialog that adds `tracks` to one of the playlists the user can edit.
+    pub fn add_tracks_dialog(
         library: Arc<Library>,
         spotify: Spotify,
-        track: Track,
+        tracks: Vec<Track>,
     ) -> NamedView<AddToPlaylistMenu> {
+        let title = match tracks.len() {
+            1 => "Add track to playlist".to_string(),
+            n => format!("Add {n} tracks to playlist"),
+        };
+        let tracks: Vec<Playable> = tracks.into_iter().map(Playable::Track).collect();
         let mut list_select: SelectView<Playlist> = SelectView::new();
         let current_user_id = library.user_id.as_ref().unwrap();
 
@@ -68,18 +127,21 @@ impl ContextMenu {
         }
 
         list_select.set_on_submit(move |s, selected| {
-            let track = track.clone();
+            let tracks = tracks.clone();
             let mut playlist = selected.clone();
             let spotify = spotify.clone();
             let library = library.clone();
 
-            if playlist.has_track(track.id.as_ref().unwrap_or(&String::new())) {
-                let mut already_added_dialog = Self::track_already_added();
+            let already_added = tracks
+                .iter()
+                .any(|track| track.id().is_some_and(|id| playlist.has_track(&id)));
+            if already_added {
+                let mut already_added_dialog = Self::track_already_added(tracks.len());
 
                 already_added_dialog.add_button("Add anyway", move |c| {
                     let mut playlist = playlist.clone();
 
-                    playlist.append_tracks(&[Playable::Track(track.clone())], &spotify, &library);
+                    playlist.append_tracks(&tracks, &spotify, &library);
                     c.pop_layer();
 
                     // Close add_track_dialog too
@@ -89,13 +151,13 @@ impl ContextMenu {
                 let modal = Modal::new(already_added_dialog);
                 s.add_layer(modal);
             } else {
-                playlist.append_tracks(&[Playable::Track(track)], &spotify, &library);
+                playlist.append_tracks(&tracks, &spotify, &library);
                 s.pop_layer();
             }
         });
 
         let dialog = Dialog::new()
-            .title("Add track to playlist")
+            .title(title)
             .dismiss_button("Close")
             .padding(Margins::lrtb(1, 1, 1, 0))
             .content(ScrollView::new(list_select.with_name("addplaylist_select")));
@@ -106,6 +168,39 @@ impl ContextMenu {
         .with_name("addtrackmenu")
     }
 
+    /// Offer the URL, the URI and a "name (URL)" line of `item` to copy to the clipboard.
+    #[cfg(feature = "share_clipboard")]
+    pub fn share_dialog(item: &dyn ListItem) -> NamedView<ShareMenu> {
+        let url = item.share_url().unwrap_or_default();
+        let mut share_select = SelectView::<String>::new();
+        share_select.add_item("Copy URL", url.clone());
+        if let Some(spotify_url) = SpotifyUrl::from_url(&url) {
+            share_select.add_item("Copy URI", spotify_url.uri());
+        }
+        if let Some(name) = item.share_name() {
+            share_select.add_item("Copy name and URL", format!("{name} ({url})"));
+        }
+        share_select.set_on_submit(|s, text: &String| {
+            s.pop_layer();
+            if let Err(e) = write_share(text.clone()) {
+                s.call_on_name("main", |v: &mut Layout| {
+                    v.set_result(Err(format!("Could not copy to the clipboard: {e}")))
+                });
+            }
+        });
+
+        let dialog = Dialog::new()
+            .title("Share")
+            .dismiss_button("Close")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(share_select.with_name("share_select"));
+
+        ShareMenu {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("sharemenu")
+    }
+
     pub fn select_artist_dialog(
         library: Arc<Library>,
         queue: Arc<Queue>,
@@ -145,17 +240,11 @@ impl ContextMenu {
         artist: Artist,
     ) -> NamedView<SelectArtistActionMenu> {
         let moved_artist = artist.clone();
+        let is_followed = library.check_followed_artist(&artist);
         let mut artist_action_select = SelectView::<bool>::new();
         artist_action_select.add_item("View Artist", true);
         artist_action_select.add_item(
-            format!(
-                "{}ollow Artist",
-                if library.is_followed_artist(&artist) {
-                    "Unf"
-                } else {
-                    "F"
-                }
-            ),
+            format!("{}ollow Artist", if is_followed { "Unf" } else { "F" }),
             false,
         );
         artist_action_select.set_on_submit(move |s, selected_action| {
@@ -166,7 +255,7 @@ impl ContextMenu {
                     }
                 }
                 false => {
-                    if library.clone().is_followed_artist(&moved_artist) {
+                    if is_followed {
                         moved_artist.clone().unsave(&library);
                     } else {
                         moved_artist.clone().save(&library);
@@ -191,13 +280,432 @@ impl ContextMenu {
         .with_name("selectartistaction")
     }
 
-    fn track_already_added() -> Dialog {
-        Dialog::text("This track is already in your playlist")
+    /// Let the user pick one of the recorded versions of `playlist` to restore.
+    pub fn restore_playlist_dialog(
+        library: Arc<Library>,
+        playlist: Playlist,
+    ) -> NamedView<RestorePlaylistMenu> {
+        let mut snapshot_select = SelectView::<PlaylistSnapshot>::new();
+        for snapshot in library.playlist_snapshots(&playlist) {
+            let label = format!("{} ({} tracks)", snapshot.date(), snapshot.tracks.len());
+            snapshot_select.add_item(label, snapshot);
+        }
+
+        let title = format!("Restore playlist: {}", playlist.name);
+        snapshot_select.set_on_submit(move |s, snapshot: &PlaylistSnapshot| {
+            let library = library.clone();
+            let id = playlist.id.clone();
+            let snapshot = snapshot.clone();
+            let confirmation = Dialog::text(format!(
+                "Replace the {} tracks of {} with the {} tracks from {}?",
+                playlist.num_tracks,
+                playlist.name,
+                snapshot.tracks.len(),
+                snapshot.date()
+            ))
+            .button("Restore", move |s| {
+                library.restore_playlist(&id, &snapshot);
+                s.pop_layer();
+            })
+            .dismiss_button("Cancel");
+            s.pop_layer();
+            s.add_layer(Modal::new(confirmation));
+        });
+
+        let dialog = Dialog::new()
+            .title(title)
+            .dismiss_button("Close")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(
+                snapshot_select.with_name("restore_playlist_select"),
+            ));
+        RestorePlaylistMenu {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("restoreplaylist")
+    }
+
+    /// Let the user pick the playlists to merge with `playlist`, then show a preview of the merged
+    /// tracks.
+    pub fn merge_playlists_dialog(
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        playlist: &Playlist,
+    ) -> NamedView<MergePlaylistsMenu> {
+        let label = |checked: bool, playlist: &Playlist| {
+            format!("[{}] {}", if checked { "x" } else { " " }, playlist.name)
+        };
+        let mut playlist_select = SelectView::<(bool, Playlist)>::new();
+        for candidate in library.playlists.read().unwrap().iter() {
+            let checked = candidate.id == playlist.id;
+            playlist_select.add_item(label(checked, candidate), (checked, candidate.clone()));
+        }
+        playlist_select.set_on_submit(move |s, _: &(bool, Playlist)| {
+            s.call_on_name(
+                "merge_playlists_select",
+                |select: &mut SelectView<(bool, Playlist)>| {
+                    let Some(id) = select.selected_id() else {
+                        return;
+                    };
+                    if let Some((text, (checked, playlist))) = select.get_item_mut(id) {
+                        *checked = !*checked;
+                        *text = label(*checked, playlist).into();
+                    }
+                },
+            );
+        });
+
+        let dialog = Dialog::new()
+            .title("Merge playlists")
+            .dismiss_button("Cancel")
+            .button("Preview", move |s| {
+                let playlists: Vec<Playlist> = s
+                    .call_on_name(
+                        "merge_playlists_select",
+                        |select: &mut SelectView<(bool, Playlist)>| {
+                            select
+                                .iter()
+                                .filter(|(_, (checked, _))| *checked)
+                                .map(|(_, (_, playlist))| playlist.clone())
+                                .collect()
+                        },
+                    )
+                    .unwrap_or_default();
+                if playlists.len() < 2 {
+                    s.add_layer(Modal::new(
+                        Dialog::text("Select at least two playlists to merge.")
+                            .title("Merge playlists")
+                            .dismiss_button("Close"),
+                    ));
+                    return;
+                }
+                s.pop_layer();
+                let view = MergeView::new(queue.clone(), library.clone(), playlists);
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            })
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(
+                playlist_select.with_name("merge_playlists_select"),
+            ));
+        MergePlaylistsMenu {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("mergeplaylists")
+    }
+
+    /// Let the user pick the playlist to compare `playlist` with, then show the comparison.
+    pub fn compare_playlists_dialog(
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        playlist: &Playlist,
+    ) -> NamedView<ComparePlaylistsMenu> {
+        let mut playlist_select = SelectView::<Playlist>::new();
+        for candidate in library.playlists.read().unwrap().iter() {
+            if candidate.id != playlist.id {
+                playlist_select.add_item(candidate.name.clone(), candidate.clone());
+            }
+        }
+        let playlist = playlist.clone();
+        playlist_select.set_on_submit(move |s, other: &Playlist| {
+            s.pop_layer();
+            let view = CompareView::new(
+                queue.clone(),
+                library.clone(),
+                playlist.clone(),
+                other.clone(),
+            );
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+        });
+
+        let dialog = Dialog::new()
+            .title("Compare with playlist")
+            .dismiss_button("Cancel")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(
+                playlist_select.with_name("compare_playlists_select"),
+            ));
+        ComparePlaylistsMenu {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("compareplaylists")
+    }
+
+    /// Let the user write `tracks` to a new playlist or replace the tracks of an existing one.
+    pub fn save_tracks_dialog(library: Arc<Library>, tracks: Vec<Playable>) -> Modal<Dialog> {
+        let mut list_select: SelectView<Option<String>> = SelectView::new().autojump();
+        list_select.add_item("[Create new]", None);
+
+        for list in library.playlists.read().unwrap().iter() {
+            list_select.add_item(list.name.clone(), Some(list.id.clone()));
+        }
+
+        list_select.set_on_submit(move |s, selected: &Option<String>| {
+            let library = library.clone();
+            let tracks = tracks.clone();
+            s.pop_layer();
+            match selected {
+                Some(id) => confirm::overwrite_playlist(s, library, id.clone(), tracks),
+                None => {
+                    let edit = EditView::new()
+                        .on_submit(move |s: &mut Cursive, name| {
+                            library.save_playlist(name, &tracks);
+                            s.pop_layer();
+                        })
+                        .with_name("name")
+                        .fixed_width(20);
+                    let dialog = Dialog::new()
+                        .title("Enter name")
+                        .dismiss_button("Cancel")
+                        .padding(Margins::lrtb(1, 1, 1, 0))
+                        .content(edit);
+                    s.add_layer(Modal::new(dialog));
+                }
+            }
+        });
+
+        let dialog = Dialog::new()
+            .title("Create new or overwrite existing playlist?")
+            .dismiss_button("Cancel")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(ScrollView::new(list_select));
+        Modal::new(dialog)
+    }
+
+    /// Show the album, artists, release, popularity, ISRC and markets of `track` with the audio
+    /// features, and actions to open its artists and album or copy its links. The details from
+    /// the Web API are fetched in the background and filled in once they arrive.
+    fn show_track_info(s: &mut Cursive, queue: Arc<Queue>, library: Arc<Library>, track: &Track) {
+        let details = vec![
+            ("Title", track.title.clone()),
+            ("Artists", track.artists.join(", ")),
+            ("Album", track.album.clone().unwrap_or_default()),
+            ("Duration", track.duration_str()),
+        ];
+
+        let mut actions = SelectView::<TrackInfoAction>::new();
+        for artist in track.artists().unwrap_or_default() {
+            actions.add_item(
+                format!("Open artist: {}", artist.name),
+                TrackInfoAction::Open(artist.as_listitem()),
+            );
+        }
+        actions.add_item("Credits", TrackInfoAction::Credits(Box::new(track.clone())));
+        #[cfg(feature = "share_clipboard")]
+        {
+            if !track.uri.is_empty() {
+                actions.add_item("Copy URI", TrackInfoAction::Copy(track.uri.clone()));
+            }
+            if let Some(url) = track.share_url() {
+                actions.add_item("Copy URL", TrackInfoAction::Copy(url));
+            }
+        }
+        let spotify = queue.get_spotify();
+        actions.set_on_submit(move |s, action: &TrackInfoAction| {
+            s.pop_layer();
+            match action {
+                TrackInfoAction::Open(item) => {
+                    if let Some(view) = item.open(queue.clone(), library.clone()) {
+                        s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+                    }
+                }
+                TrackInfoAction::Credits(track) => {
+                    Self::show_credits(s, queue.get_spotify(), track);
+                }
+                #[cfg(feature = "share_clipboard")]
+                TrackInfoAction::Copy(text) => {
+                    if let Err(e) = write_share(text.clone()) {
+                        s.call_on_name("main", |v: &mut Layout| {
+                            v.set_result(Err(format!("Could not copy to the clipboard: {e}")))
+                        });
+                    }
+                }
+            }
+        });
+
+        let content = LinearLayout::vertical()
+            .child(TextView::new(format_details(&details)).with_name("track_info_text"))
+            .child(DummyView)
+            .child(ScrollView::new(actions.with_name("track_info_select")));
+        let dialog = Dialog::around(content)
+            .title("Track info")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .dismiss_button("Close");
+        s.add_layer(
+            TrackInfoMenu {
+                dialog: Modal::new_ext(dialog),
+            }
+            .with_name("trackinfo"),
+        );
+
+        let Some(id) = track.id.clone() else {
+            return;
+        };
+        let cb_sink = s.cb_sink().clone();
+        spotify.api.spawn_with_callback(
+            move |api| {
+                let full_track = api.track(&id).ok();
+                let features = api
+                    .audio_features(&[id.as_str()])
+                    .ok()
+                    .and_then(|features| features.into_iter().next());
+                (full_track, features)
+            },
+            move |(full_track, features)| {
+                let mut details = details;
+                if let Some(full_track) = &full_track {
+                    if let Some(date) = &full_track.album.release_date {
+                        details.push(("Released", date.clone()));
+                    }
+                    details.push(("Popularity", format!("{}/100", full_track.popularity)));
+                    if let Some(isrc) = full_track.external_ids.get("isrc") {
+                        details.push(("ISRC", isrc.clone()));
+                    }
+                    details.push((
+                        "Markets",
+                        match full_track.available_markets.len() {
+                            0 => "Not reported".to_string(),
+                            n => format!("{n}"),
+                        },
+                    ));
+                }
+                if let Some(features) = features {
+                    details.push(("BPM", features.bpm()));
+                    details.push(("Key", features.key_name()));
+                    details.push(("Energy", features.energy_percent()));
+                    details.push(("Danceability", features.danceability_percent()));
+                }
+                let album = full_track.map(|full_track| Album::from(&full_track.album));
+                cb_sink
+                    .send(Box::new(move |s| {
+                        s.call_on_name("track_info_text", |v: &mut TextView| {
+                            v.set_content(format_details(&details))
+                        });
+                        if let Some(album) = album {
+                            s.call_on_name(
+                                "track_info_select",
+                                |v: &mut SelectView<TrackInfoAction>| {
+                                    let position = v
+                                        .iter()
+                                        .position(|(_, action)| {
+                                            !matches!(action, TrackInfoAction::Open(_))
+                                        })
+                                        .unwrap_or(v.len());
+                                    v.insert_item(
+                                        position,
+                                        format!("Open album: {}", album.title),
+                                        TrackInfoAction::Open(album.as_listitem()),
+                                    );
+                                },
+                            );
+                        }
+                    }))
+                    .ok();
+            },
+        );
+    }
+
+    /// Show the credits of `track` in a dialog. They are fetched in the background, because they
+    /// come from MusicBrainz which only allows one request per second.
+    pub fn show_credits(s: &mut Cursive, spotify: Spotify, track: &Track) {
+        let dialog = Dialog::around(TextView::new("Loading credits...").with_name("credits_text"))
+            .title(format!("Credits: {}", track.title))
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .dismiss_button("Close");
+        s.add_layer(Modal::new(dialog));
+
+        let Some(id) = track.id.clone() else {
+            s.call_on_name("credits_text", |v: &mut TextView| {
+                v.set_content("No credits are available for local files")
+            });
+            return;
+        };
+        let cb_sink = s.cb_sink().clone();
+        spotify.api.spawn_with_callback(
+            move |api| match api.track_credits(&id) {
+                Ok(credits) if credits.is_empty() => "No credits found".to_string(),
+                Ok(credits) => {
+                    let width = credits.iter().map(|c| c.role.len()).max().unwrap_or(0) + 2;
+                    credits
+                        .iter()
+                        .map(|c| format!("{:<width$}{}", c.role, c.names.join(", ")))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                }
+                Err(e) => e,
+            },
+            move |text| {
+                cb_sink
+                    .send(Box::new(move |s| {
+                        s.call_on_name("credits_text", |v: &mut TextView| v.set_content(text));
+                    }))
+                    .ok();
+            },
+        );
+    }
+
+    /// Save all tracks of the collection `item` to the user's library, or remove them if `saved`
+    /// is `false`, showing the progress in a dialog.
+    fn set_all_tracks_saved(
+        s: &mut Cursive,
+        item: Box<dyn ListItem>,
+        library: Arc<Library>,
+        spotify: Spotify,
+        saved: bool,
+    ) {
+        let verb = if saved { "Liked" } else { "Unliked" };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let dialog = Dialog::around(TextView::new("Loading tracks...").with_name("batch_progress"))
+            .title(format!("{verb} tracks of {}", item.display_left(&library)))
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .button("Close", {
+                let cancelled = cancelled.clone();
+                move |s| {
+                    cancelled.store(true, Ordering::Relaxed);
+                    s.pop_layer();
+                }
+            });
+        s.add_layer(Modal::new(dialog));
+
+        let cb_sink = s.cb_sink().clone();
+        let set_text = move |text: String| {
+            cb_sink
+                .send(Box::new(move |s| {
+                    s.call_on_name("batch_progress", |v: &mut TextView| v.set_content(text));
+                }))
+                .ok();
+        };
+        thread::spawn(move || {
+            let tracks = item.collection_tracks(&spotify);
+            let result = library.set_tracks_saved(&tracks, saved, |done, total| {
+                set_text(format!("{verb} {done} of {total} tracks..."));
+                !cancelled.load(Ordering::Relaxed)
+            });
+            set_text(match result {
+                Ok(0) => format!("All tracks are already {}", verb.to_lowercase()),
+                Ok(changed) => format!("{verb} {changed} tracks"),
+                Err(e) => e,
+            });
+        });
+    }
+
+    fn track_already_added(tracks: usize) -> Dialog {
+        let text = match tracks {
+            1 => "This track is already in your playlist",
+            _ => "Some of these tracks are already in your playlist",
+        };
+        Dialog::text(text)
             .title("Track already exists")
             .padding(Margins::lrtb(1, 1, 1, 0))
             .dismiss_button("Close")
     }
 
+    fn radio_failed(reason: &str) -> Dialog {
+        Dialog::text(reason)
+            .title("Could not start radio")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .dismiss_button("Close")
+    }
+
     pub fn new(item: &dyn ListItem, queue: Arc<Queue>, library: Arc<Library>) -> NamedView<Self> {
         let mut content: SelectView<ContextMenuAction> = SelectView::new();
         let album = item.album(&queue);
@@ -248,11 +756,11 @@ impl ContextMenu {
 
         #[cfg(feature = "share_clipboard")]
         {
-            if let Some(url) = item.share_url() {
-                content.add_item("Share", ContextMenuAction::ShareUrl(url));
+            if item.share_url().is_some() {
+                content.add_item("Share", ContextMenuAction::Share(item.as_listitem()));
             }
-            if let Some(url) = album.as_ref().and_then(|a| a.share_url()) {
-                content.add_item("Share album", ContextMenuAction::ShareUrl(url));
+            if let Some(a) = album.as_ref().filter(|a| a.share_url().is_some()) {
+                content.add_item("Share album", ContextMenuAction::Share(a.as_listitem()));
             }
         }
 
@@ -263,8 +771,39 @@ impl ContextMenu {
             );
             content.add_item(
                 "Similar tracks",
-                ContextMenuAction::ShowRecommendations(Box::new(t)),
-            )
+                ContextMenuAction::ShowRecommendations(Box::new(t.clone())),
+            );
+            content.add_item(
+                "Track info",
+                ContextMenuAction::ShowTrackDetails(Box::new(t.clone())),
+            );
+            content.add_item("Credits", ContextMenuAction::ShowCredits(Box::new(t)));
+        }
+        if let Some(playlist) = item.playlist()
+            && library.user_id.as_ref() == Some(&playlist.owner_id)
+            && !library.playlist_snapshots(&playlist).is_empty()
+        {
+            content.add_item(
+                "Restore earlier version",
+                ContextMenuAction::RestorePlaylist(Box::new(playlist)),
+            );
+        }
+        if let Some(playlist) = item.playlist() {
+            content.add_item(
+                "Merge with other playlists",
+                ContextMenuAction::MergePlaylists(Box::new(playlist.clone())),
+            );
+            content.add_item(
+                "Compare with another playlist",
+                ContextMenuAction::ComparePlaylists(Box::new(playlist)),
+            );
+        }
+        if let Some(seed) = item.radio_seed() {
+            content.add_item("Start radio", ContextMenuAction::StartRadio(seed.clone()));
+            content.add_item(
+                "Tune recommendations",
+                ContextMenuAction::TuneRecommendations(item.as_listitem(), seed),
+            );
         }
         // If the item is saveable, its save state will be set
         if let Some(savestatus) = item.is_saved(&library) {
@@ -277,6 +816,21 @@ impl ContextMenu {
             );
         }
 
+        if item.is_collection() {
+            content.add_item(
+                "Like all tracks",
+                ContextMenuAction::SetAllTracksSaved(item.as_listitem(), true),
+            );
+            content.add_item(
+                "Unlike all tracks",
+                ContextMenuAction::SetAllTracksSaved(item.as_listitem(), false),
+            );
+        }
+
+        if item.is_collection() || item.track().is_some() {
+            content.add_item("Download", ContextMenuAction::Download(item.as_listitem()));
+        }
+
         if let Some(ref a) = album
             && let Some(savestatus) = a.is_saved(&library)
         {
@@ -304,12 +858,16 @@ impl ContextMenu {
                         }
                     }
                     #[cfg(feature = "share_clipboard")]
-                    ContextMenuAction::ShareUrl(url) => {
-                        write_share(url.to_string()).ok();
+                    ContextMenuAction::Share(item) => {
+                        let dialog = Self::share_dialog(item.as_ref());
+                        s.add_layer(dialog);
                     }
                     ContextMenuAction::AddToPlaylist(track) => {
-                        let dialog =
-                            Self::add_track_dialog(library, queue.get_spotify(), *track.clone());
+                        let dialog = Self::add_tracks_dialog(
+                            library,
+                            queue.get_spotify(),
+                            vec![*track.clone()],
+                        );
                         s.add_layer(dialog);
                     }
                     ContextMenuAction::ShowRecommendations(item) => {
@@ -317,6 +875,38 @@ impl ContextMenu {
                             s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
                         }
                     }
+                    ContextMenuAction::ShowTrackDetails(track) => {
+                        Self::show_track_info(s, queue, library, track);
+                    }
+                    ContextMenuAction::ShowCredits(track) => {
+                        Self::show_credits(s, queue.get_spotify(), track);
+                    }
+                    ContextMenuAction::RestorePlaylist(playlist) => {
+                        let dialog = Self::restore_playlist_dialog(library, *playlist.clone());
+                        s.add_layer(dialog);
+                    }
+                    ContextMenuAction::MergePlaylists(playlist) => {
+                        let dialog = Self::merge_playlists_dialog(queue, library, playlist);
+                        s.add_layer(dialog);
+                    }
+                    ContextMenuAction::ComparePlaylists(playlist) => {
+                        let dialog = Self::compare_playlists_dialog(queue, library, playlist);
+                        s.add_layer(dialog);
+                    }
+                    ContextMenuAction::StartRadio(seed) => {
+                        if let Err(e) = queue.start_radio(seed.clone()) {
+                            s.add_layer(Modal::new(Self::radio_failed(&e)));
+                        }
+                    }
+                    ContextMenuAction::TuneRecommendations(item, seed) => {
+                        let dialog = TuneRecommendationsDialog::new(
+                            item.as_ref(),
+                            seed.clone(),
+                            queue,
+                            library,
+                        );
+                        s.add_layer(dialog);
+                    }
                     ContextMenuAction::SelectArtist(artists) => {
                         let dialog = Self::select_artist_dialog(library, queue, artists.clone());
                         s.add_layer(dialog);
@@ -329,6 +919,28 @@ impl ContextMenu {
                     ContextMenuAction::ToggleSavedStatus(item) => {
                         item.as_listitem().toggle_saved(&library)
                     }
+                    ContextMenuAction::SetAllTracksSaved(item, saved) => {
+                        Self::set_all_tracks_saved(
+                            s,
+                            item.as_listitem(),
+                            library,
+                            queue.get_spotify(),
+                            *saved,
+                        );
+                    }
+                    ContextMenuAction::Download(item) => {
+                        let title = item.display_left(&library);
+                        let spotify = queue.get_spotify();
+                        let cfg = library.cfg.clone();
+                        if let Err(e) = downloads::add_item(spotify, cfg, title, item.as_listitem())
+                        {
+                            let dialog = Dialog::text(e)
+                                .title("Could not download")
+                                .padding(Margins::lrtb(1, 1, 1, 0))
+                                .dismiss_button("Close");
+                            s.add_layer(Modal::new(dialog));
+                        }
+                    }
                     ContextMenuAction::Play(item) => item.as_listitem().play(&queue),
                     ContextMenuAction::PlayNext(item) => item.as_listitem().play_next(&queue),
                     ContextMenuAction::TogglePlayback => queue.toggleplayback(),
@@ -375,6 +987,46 @@ impl ViewExt for SelectArtistActionMenu {
     }
 }
 
+impl ViewExt for RestorePlaylistMenu {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        handle_move_command::<PlaylistSnapshot>(&mut self.dialog, s, cmd, "restore_playlist_select")
+    }
+}
+
+impl ViewExt for MergePlaylistsMenu {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        handle_move_command::<(bool, Playlist)>(&mut self.dialog, s, cmd, "merge_playlists_select")
+    }
+}
+
+impl ViewExt for ComparePlaylistsMenu {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        handle_move_command::<Playlist>(&mut self.dialog, s, cmd, "compare_playlists_select")
+    }
+}
+
+/// The `details` of a track as label and value per line.
+fn format_details(details: &[(&str, String)]) -> String {
+    details
+        .iter()
+        .map(|(label, value)| format!("{label:<14}{value}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl ViewExt for TrackInfoMenu {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        handle_move_command::<TrackInfoAction>(&mut self.dialog, s, cmd, "track_info_select")
+    }
+}
+
+#[cfg(feature = "share_clipboard")]
+impl ViewExt for ShareMenu {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        handle_move_command::<String>(&mut self.dialog, s, cmd, "share_select")
+    }
+}
+
 fn handle_move_command<T: Send + Sync + 'static>(
     sel: &mut Modal<Dialog>,
     s: &mut Cursive,
@@ -410,3 +1062,24 @@ impl ViewWrapper for SelectArtistMenu {
 impl ViewWrapper for SelectArtistActionMenu {
     wrap_impl!(self.dialog: Modal<Dialog>);
 }
+
+impl ViewWrapper for RestorePlaylistMenu {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+impl ViewWrapper for MergePlaylistsMenu {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+impl ViewWrapper for ComparePlaylistsMenu {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+impl ViewWrapper for TrackInfoMenu {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+#[cfg(feature = "share_clipboard")]
+impl ViewWrapper for ShareMenu {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
@@ -0,0 +1,56 @@
+//! Picking the audio output device while ncspot runs.
+
+use std::sync::Arc;
+
+use cursive::traits::Resizable;
+use cursive::view::Margins;
+use cursive::views::{Dialog, ScrollView, SelectView};
+
+use crate::audio_devices;
+use crate::config::Config;
+use crate::queue::Queue;
+use crate::ui::modal::Modal;
+
+/// A dialog listing the output devices of the audio backend. Picking one switches to it.
+pub fn picker(queue: Arc<Queue>, cfg: Arc<Config>) -> Result<Modal<Dialog>, String> {
+    let backend = queue
+        .get_spotify()
+        .backend_name()
+        .ok_or("No audio backend available")?;
+    let devices = audio_devices::list(backend)?;
+    let current = cfg.output_device();
+    let label = |name: &str, selected: bool| format!("{} {name}", if selected { "●" } else { " " });
+
+    let mut select: SelectView<Option<String>> = SelectView::new();
+    let default = match cfg.values().backend_device {
+        Some(_) => "Configured device",
+        None => "Default device",
+    };
+    let state_device = cfg.state().output_device.clone();
+    select.add_item(label(default, state_device.is_none()), None);
+    let mut selection = 0;
+    for device in devices {
+        let selected = current.as_ref() == Some(&device.name) && state_device.is_some();
+        let name = match &device.description {
+            Some(description) => format!("{description} ({})", device.name),
+            None => device.name.clone(),
+        };
+        if selected {
+            selection = select.len();
+        }
+        select.add_item(label(&name, selected), Some(device.name));
+    }
+    let mut select = select.selected(selection);
+    select.set_on_submit(move |s, device: &Option<String>| {
+        s.pop_layer();
+        queue
+            .get_spotify()
+            .set_output_device(device.clone(), queue.get_current());
+    });
+
+    let dialog = Dialog::around(ScrollView::new(select).max_height(20))
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title(format!("Output device ({backend})"))
+        .dismiss_button("Cancel");
+    Ok(Modal::new(dialog))
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::Vec2;
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use cursive::view::View;
+use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+
+use crate::caches::CacheKind;
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::config::Config;
+use crate::downloads::{self, Download, Status};
+use crate::traits::ViewExt;
+use crate::utils::format_size;
+
+/// The downloads of this session and their progress.
+pub struct DownloadsView {
+    view: ScrollView<TextView>,
+    cfg: Arc<Config>,
+    /// The size of the audio cache, which is only measured when the view is opened, as that means
+    /// reading the whole cache directory.
+    cache_size: u64,
+}
+
+impl DownloadsView {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        Self {
+            view: ScrollView::new(TextView::new("")),
+            cfg,
+            cache_size: CacheKind::Audio.size(),
+        }
+    }
+
+    fn content(&self) -> StyledString {
+        let list = downloads::list();
+        let mut text = StyledString::styled("Downloads\n\n", Effect::Bold);
+        if list.is_empty() {
+            text.append(
+                "Nothing was downloaded in this session. Download a playlist or an album with the \
+                 `download` command or from its context menu.\n",
+            );
+        }
+        for download in list.iter() {
+            text.append(Self::row(download));
+        }
+
+        let quota = match downloads::quota(&self.cfg) {
+            Some(quota) => format!(" of {}", format_size(quota)),
+            None => String::new(),
+        };
+        text.append_styled(
+            format!(
+                "\nThe audio cache holds {}{quota}. Downloaded tracks play without streaming, \
+                 and while offline the queue skips the others. `downloads cancel` stops the \
+                 downloads.\n",
+                format_size(self.cache_size)
+            ),
+            Effect::Italic,
+        );
+        text
+    }
+
+    fn row(download: &Download) -> String {
+        let status = match &download.status {
+            Status::Queued => "Queued".to_string(),
+            Status::Downloading => "Downloading".to_string(),
+            Status::Finished => "Finished".to_string(),
+            Status::Cancelled => "Cancelled".to_string(),
+            Status::Stopped(reason) => reason.clone(),
+        };
+        let failed = match download.failed {
+            0 => String::new(),
+            failed => format!(", {failed} failed"),
+        };
+        format!(
+            "{:<40.40}  {:>5}/{:<5} {:>10}  {status}{failed}\n",
+            download.title,
+            download.done,
+            download.tracks.len(),
+            format_size(download.downloaded)
+        )
+    }
+}
+
+impl ViewWrapper for DownloadsView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        // The downloads progress in the background, so the text is updated on every redraw.
+        let content = self.content();
+        self.view.get_inner_mut().set_content(content);
+        self.view.layout(size);
+    }
+}
+
+impl ViewExt for DownloadsView {
+    fn title(&self) -> String {
+        "Downloads".to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::ShowDownloads => Ok(CommandResult::Consumed(None)),
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::Dialog;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::duplicates::{self, DuplicateKind};
+use crate::help::HelpContext;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::queue::Queue;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::layout::Layout;
+use crate::ui::listview::ListView;
+use crate::ui::modal::Modal;
+
+/// Where the duplicates were found, and will be removed from.
+enum Source {
+    Playlist(Box<Playlist>),
+    SavedTracks,
+}
+
+/// The duplicates of a playlist or the saved tracks, for reviewing them before they are removed.
+/// Deleting an entry keeps the track, running `duplicates` again removes all listed ones.
+pub struct DuplicatesView {
+    source: Arc<RwLock<Source>>,
+    tracks: Arc<RwLock<Vec<Playable>>>,
+    kinds: Arc<RwLock<Vec<DuplicateKind>>>,
+    /// Whether the tracks are still being loaded.
+    loading: Arc<AtomicBool>,
+    list: ListView<Playable>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl DuplicatesView {
+    /// The duplicates of `playlist`, whose tracks are loaded in the background if needed.
+    pub fn for_playlist(queue: Arc<Queue>, library: Arc<Library>, playlist: &Playlist) -> Self {
+        let view = Self::new(
+            queue.clone(),
+            library.clone(),
+            Source::Playlist(Box::new(playlist.clone())),
+            Vec::new(),
+        );
+        view.loading.store(true, Ordering::Relaxed);
+
+        let mut playlist = playlist.clone();
+        let source = view.source.clone();
+        let tracks = view.tracks.clone();
+        let kinds = view.kinds.clone();
+        let loading = view.loading.clone();
+        queue.get_spotify().api.spawn(move |_| {
+            playlist.load_tracks(&queue.get_spotify());
+            // The first occurrence in the actual order is kept, regardless of how the view is
+            // sorted.
+            let mut all = playlist.tracks.clone().unwrap_or_default();
+            all.sort_by_key(Playable::list_index);
+            Self::find(&all, &tracks, &kinds);
+            *source.write().unwrap() = Source::Playlist(Box::new(playlist));
+            loading.store(false, Ordering::Relaxed);
+            library.trigger_redraw();
+        });
+        view
+    }
+
+    pub fn for_saved_tracks(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let tracks = library
+            .tracks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|track| Playable::Track(track.clone()))
+            .collect();
+        Self::new(queue, library, Source::SavedTracks, tracks)
+    }
+
+    fn new(
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        source: Source,
+        tracks: Vec<Playable>,
+    ) -> Self {
+        let flagged = Arc::new(RwLock::new(Vec::new()));
+        let kinds = Arc::new(RwLock::new(Vec::new()));
+        Self::find(&tracks, &flagged, &kinds);
+        Self {
+            source: Arc::new(RwLock::new(source)),
+            tracks: flagged.clone(),
+            kinds,
+            loading: Arc::new(AtomicBool::new(false)),
+            list: ListView::new(flagged, queue.clone(), library.clone()),
+            queue,
+            library,
+        }
+    }
+
+    /// Find the duplicates in `tracks` and list them in `flagged` with their `kinds`.
+    fn find(
+        tracks: &[Playable],
+        flagged: &RwLock<Vec<Playable>>,
+        kinds: &RwLock<Vec<DuplicateKind>>,
+    ) {
+        let found = duplicates::find(tracks);
+        *kinds.write().unwrap() = found.iter().map(|duplicate| duplicate.kind).collect();
+        *flagged.write().unwrap() = found
+            .iter()
+            .map(|duplicate| tracks[duplicate.index].clone())
+            .collect();
+    }
+
+    fn source_name(&self) -> String {
+        match &*self.source.read().unwrap() {
+            Source::Playlist(playlist) => playlist.name.clone(),
+            Source::SavedTracks => "Liked Songs".to_string(),
+        }
+    }
+
+    /// Ask before removing all listed duplicates.
+    fn remove_dialog(&self) -> Modal<Dialog> {
+        let tracks = self.tracks.read().unwrap().clone();
+        let playlist = match &*self.source.read().unwrap() {
+            Source::Playlist(playlist) => Some(playlist.clone()),
+            Source::SavedTracks => None,
+        };
+        let queue = self.queue.clone();
+        let library = self.library.clone();
+        let dialog = Dialog::text(format!(
+            "Remove {} duplicates from {}?",
+            tracks.len(),
+            self.source_name()
+        ))
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title("Remove duplicates")
+        .dismiss_button("Cancel")
+        .button("Remove", move |s| {
+            s.pop_layer();
+            let removed = match playlist.clone() {
+                Some(mut playlist) => {
+                    let removed = playlist.delete_tracks(&tracks, &queue.get_spotify(), &library);
+                    // Replace the playlist below as well, as it still shows the removed tracks.
+                    let view = playlist.open(queue.clone(), library.clone());
+                    s.call_on_name("main", move |v: &mut Layout| {
+                        v.pop_view();
+                        if let Some(view) = view {
+                            v.pop_view();
+                            v.push_view(view);
+                        }
+                    });
+                    removed
+                }
+                None => {
+                    let saved: Vec<_> = tracks
+                        .iter()
+                        .filter_map(|playable| match playable {
+                            Playable::Track(track) => Some(track),
+                            Playable::Episode(_) => None,
+                        })
+                        .collect();
+                    let removed = library.unsave_tracks(&saved);
+                    s.call_on_name("main", |v: &mut Layout| v.pop_view());
+                    removed
+                }
+            };
+            if !removed {
+                s.add_layer(Dialog::info("Not all duplicates could be removed."));
+            }
+        });
+        Modal::new(dialog)
+    }
+}
+
+impl ViewWrapper for DuplicatesView {
+    wrap_impl!(self.list: ListView<Playable>);
+}
+
+impl ViewExt for DuplicatesView {
+    fn title(&self) -> String {
+        format!("Duplicates: {}", self.source_name())
+    }
+
+    fn title_sub(&self) -> String {
+        if self.loading.load(Ordering::Relaxed) {
+            return "Loading tracks...".to_string();
+        }
+        let kinds = self.kinds.read().unwrap();
+        let identical = kinds
+            .iter()
+            .filter(|&&kind| kind == DuplicateKind::Identical)
+            .count();
+        format!(
+            "{} identical, {} similar",
+            identical,
+            kinds.len() - identical
+        )
+    }
+
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.list.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            // The kinds are in the order of the tracks.
+            Command::Sort(_, _) => Ok(CommandResult::Ignored),
+            Command::Delete => {
+                let pos = self.list.get_selected_index();
+                let mut kinds = self.kinds.write().unwrap();
+                if pos < kinds.len() {
+                    kinds.remove(pos);
+                    self.list.remove(pos);
+                }
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Duplicates => {
+                if self.tracks.read().unwrap().is_empty() {
+                    return Err("There are no duplicates to remove.".to_string());
+                }
+                s.add_layer(self.remove_dialog());
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
@@ -1,25 +1,92 @@
 use std::collections::HashMap;
 
 use cursive::Cursive;
+use cursive::event::{Event, Key};
 use cursive::theme::Effect;
+use cursive::traits::{Nameable, Resizable};
 use cursive::utils::markup::StyledString;
-use cursive::view::ViewWrapper;
-use cursive::views::{ScrollView, TextView};
+use cursive::view::{Offset, Position, ViewWrapper};
+use cursive::views::{OnEventView, Panel, ScrollView, TextView};
 use ncspot::CONFIGURATION_FILE_NAME;
 
-use crate::command::{Command, MoveAmount, MoveMode};
+use crate::command::{Command, JumpMode, MoveAmount, MoveMode};
 use crate::commands::CommandResult;
 use crate::config::config_path;
+use crate::help::{self, COMMANDS, HelpContext};
 use crate::traits::ViewExt;
 use cursive::view::scroll::Scroller;
 
+/// The name of the popup with the keys of the current view.
+pub const KEYS_NAME: &str = "help_keys";
+
+/// The width of the column with the commands.
+const COMMAND_WIDTH: usize = 28;
+
+/// The width of the column with the keys.
+const KEYS_WIDTH: usize = 18;
+
+/// The help for the commands of `contexts` matching `query`, grouped by context. With `bound_only`
+/// commands without keys are left out.
+fn help_text(
+    bindings: &HashMap<String, Vec<Command>>,
+    contexts: &[HelpContext],
+    query: Option<&str>,
+    bound_only: bool,
+) -> StyledString {
+    let mut text = StyledString::new();
+    for context in contexts {
+        let mut rows = StyledString::new();
+        for command in COMMANDS.iter().filter(|c| c.context == *context) {
+            let keys = help::keys_of(bindings, command.name);
+            if query.is_some_and(|query| !help::matches(command, &keys, query))
+                || (bound_only && keys.is_empty())
+            {
+                continue;
+            }
+            let mut row = |usage: &str, keys: &str| {
+                rows.append(format!("{usage:<COMMAND_WIDTH$} "));
+                rows.append_styled(format!("{keys:<KEYS_WIDTH$} "), Effect::Bold);
+                rows.append(format!("{}\n", command.description));
+            };
+            if keys.is_empty() {
+                row(command.usage, "");
+            }
+            for (usage, keys) in &keys {
+                row(usage, &keys.join(", "));
+            }
+        }
+        if !rows.is_empty() {
+            text.append_styled(format!("{context}\n"), Effect::Underline);
+            text.append(rows);
+            text.append("\n");
+        }
+    }
+    if text.is_empty() {
+        text.append_styled("No matching commands\n", Effect::Italic);
+    }
+    text
+}
+
+/// All commands with their current keys, which can be searched with `filter` and `jump`.
 pub struct HelpView {
     view: ScrollView<TextView>,
+    bindings: HashMap<String, Vec<Command>>,
+    query: Option<String>,
 }
 
 impl HelpView {
     pub fn new(bindings: HashMap<String, Vec<Command>>) -> Self {
-        let mut text = StyledString::styled("Keybindings\n\n", Effect::Bold);
+        let mut view = Self {
+            view: ScrollView::new(TextView::empty()),
+            bindings,
+            query: None,
+        };
+        view.update();
+        view
+    }
+
+    fn update(&mut self) {
+        let mut text = StyledString::styled("Commands\n\n", Effect::Bold);
 
         let note = format!(
             "Custom bindings can be set in {} within the [keybindings] section.\n\n",
@@ -29,26 +96,74 @@ impl HelpView {
         );
         text.append(StyledString::styled(note, Effect::Italic));
 
-        let mut keys: Vec<&String> = bindings.keys().collect();
-        keys.sort();
-
-        for key in keys {
-            let commands = &bindings[key];
-            let binding = format!(
-                "{} -> {}\n",
-                key,
-                commands
-                    .iter()
-                    .map(|c| c.to_string())
-                    .collect::<Vec<_>>()
-                    .join("; ")
-            );
-            text.append(binding);
-        }
+        let contexts = [
+            HelpContext::Global,
+            HelpContext::Navigation,
+            HelpContext::Playback,
+            HelpContext::List,
+            HelpContext::Playlist,
+            HelpContext::Playlists,
+            HelpContext::Artist,
+            HelpContext::Queue,
+        ];
+        text.append(help_text(
+            &self.bindings,
+            &contexts,
+            self.query.as_deref(),
+            false,
+        ));
+        self.view.get_inner_mut().set_content(text);
+        self.view.scroll_to_top();
+    }
+
+    fn search(&mut self, query: Option<&str>) -> Result<CommandResult, String> {
+        self.query = query
+            .filter(|query| !query.trim().is_empty())
+            .map(str::to_string);
+        self.update();
+        Ok(CommandResult::Consumed(None))
+    }
 
-        Self {
-            view: ScrollView::new(TextView::new(text)),
+    /// Show the keys of the commands that work in a view with `contexts` in a popup. Running
+    /// `help keys` again while it is shown opens the full help.
+    pub fn show_keys(
+        s: &mut Cursive,
+        bindings: &HashMap<String, Vec<Command>>,
+        contexts: Vec<HelpContext>,
+    ) {
+        let mut all = contexts;
+        all.extend([
+            HelpContext::Navigation,
+            HelpContext::Playback,
+            HelpContext::Global,
+        ]);
+        let text = help_text(bindings, &all, None, true);
+        let popup = Panel::new(ScrollView::new(TextView::new(text)))
+            .title("Keys")
+            .max_height(20);
+        let popup = OnEventView::new(popup)
+            .on_event(Key::Esc, |s| {
+                close_keys(s);
+            })
+            .on_event(Event::Char('q'), |s| {
+                close_keys(s);
+            });
+        // An absolute offset is clamped to the screen, which places the popup at the bottom.
+        s.screen_mut().add_layer_at(
+            Position::new(Offset::Center, Offset::Absolute(usize::MAX)),
+            popup.with_name(KEYS_NAME),
+        );
+    }
+}
+
+/// Close the popup with the keys of the current view, returning whether it was shown.
+pub fn close_keys(s: &mut Cursive) -> bool {
+    match s.screen_mut().find_layer_from_name(KEYS_NAME) {
+        Some(position) => {
+            s.screen_mut().remove_layer(position);
+            true
         }
+        None => false,
     }
 }
 
@@ -61,9 +176,18 @@ impl ViewExt for HelpView {
         "Help".to_string()
     }
 
+    fn title_sub(&self) -> String {
+        self.query
+            .as_ref()
+            .map(|query| format!("matching \"{query}\""))
+            .unwrap_or_default()
+    }
+
     fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Help => Ok(CommandResult::Consumed(None)),
+            Command::Filter(query) => self.search(query.as_deref()),
+            Command::Jump(JumpMode::Query(query)) => self.search(Some(query)),
             Command::Move(mode, amount) => {
                 let scroller = self.view.get_scroller_mut();
                 let viewport = scroller.content_viewport();
@@ -5,22 +5,51 @@ use std::time::{Duration, SystemTime};
 use cursive::align::HAlign;
 use cursive::direction::Direction;
 use cursive::event::{AnyCb, Event, EventResult, Key, MouseButton, MouseEvent};
-use cursive::theme::{ColorStyle, ColorType, Theme};
+use cursive::theme::{ColorStyle, ColorType, Effect, Theme};
 use cursive::traits::View;
 use cursive::vec::Vec2;
 use cursive::view::{CannotFocus, IntoBoxedView, Selector};
 use cursive::views::EditView;
 use cursive::{Cursive, Printer};
+use log::warn;
 use unicode_width::UnicodeWidthStr;
 
 use crate::application::UserData;
-use crate::command::{self, Command, JumpMode};
+use crate::command::{self, Command, JumpListMode, JumpMode, PaneMode};
 use crate::commands::CommandResult;
 use crate::config::{self, Config};
 use crate::events;
 use crate::ext_traits::CursiveExt;
+use crate::help::HelpContext;
+use crate::marks::{JumpList, Location};
+use crate::search_filter::apply_completion;
+use crate::session::{ScreenSession, Session, ViewSession};
+use crate::theme;
+use crate::themes;
 use crate::traits::{IntoBoxedViewExt, ViewExt};
 
+/// A part of the layout that can be highlighted, for example by the onboarding tour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Area {
+    /// The title of the current screen.
+    Title,
+    /// The first row of the screen, where views like the search keep their input field.
+    Input,
+    StatusBar,
+    CommandLine,
+}
+
+/// The prefix of the command line that narrows the current list while typing.
+const FILTER_PREFIX: char = '|';
+
+/// Narrow the current list to the items matching `query`, or show all items again if it is empty.
+fn filter(s: &mut Cursive, query: &str) {
+    let query = (!query.is_empty()).then(|| query.to_string());
+    if let Some(data) = s.user_data::<UserData>().cloned() {
+        data.cmd.handle(s, Command::Filter(query));
+    }
+}
+
 pub struct Layout {
     screens: HashMap<String, Box<dyn ViewExt>>,
     stack: HashMap<String, Vec<Box<dyn ViewExt>>>,
@@ -31,8 +60,20 @@ pub struct Layout {
     result: Result<Option<String>, String>,
     result_time: Option<SystemTime>,
     last_size: Vec2,
+    highlight: Option<Area>,
+    /// Whether the left button was pressed on the status bar and is still held, so the status bar
+    /// gets the mouse events of the drag.
+    statusbar_drag: bool,
+    /// The screens shown in the left and the right pane, if the layout is split.
+    split: Option<[String; 2]>,
+    marks: HashMap<char, Location>,
+    jumps: JumpList,
+    /// Views closed by going back in the jump list, by screen, so going forward can open them
+    /// again.
+    detached: HashMap<String, Vec<Box<dyn ViewExt>>>,
+    /// The mark command waiting for the key with the name of the mark.
+    pending_mark: Option<fn(char) -> Command>,
     ev: events::EventManager,
-    theme: Theme,
     configuration: Arc<Config>,
 }
 
@@ -49,6 +90,13 @@ impl Layout {
         );
         let mut command_line_input = EditView::new().filler(" ").style(style);
 
+        // Filters are applied on every change instead of on submit.
+        command_line_input.set_on_edit(|s, content, _| {
+            if let Some(query) = content.strip_prefix(FILTER_PREFIX) {
+                filter(s, query);
+            }
+        });
+
         let event_manager = ev.clone();
         // 1. When a search was submitted on the commandline...
         command_line_input.set_on_submit(move |s, cmd| {
@@ -59,7 +107,10 @@ impl Layout {
             let mut command_characters = cmd.chars();
             command_characters.next();
             let cmd_without_prefix = command_characters.as_str();
-            if cmd.strip_prefix('/').is_some() {
+            if let Some(query) = cmd.strip_prefix(FILTER_PREFIX) {
+                // 4. If it is a filter, keep it and return to the list.
+                filter(s, query);
+            } else if cmd.strip_prefix('/').is_some() {
                 // 4. If it is a search command...
 
                 // 5. Send a jump command with the search query to the command manager.
@@ -99,8 +150,14 @@ impl Layout {
             result: Ok(None),
             result_time: None,
             last_size: Vec2::new(0, 0),
+            highlight: None,
+            statusbar_drag: false,
+            split: None,
+            marks: HashMap::new(),
+            jumps: JumpList::default(),
+            detached: HashMap::new(),
+            pending_mark: None,
             ev: ev.clone(),
-            theme,
             configuration,
         }
     }
@@ -145,6 +202,10 @@ impl Layout {
         }
 
         let s = id.into();
+        if self.focus.as_ref() != Some(&s) {
+            self.record_jump();
+        }
+        self.place(&s);
         self.focus = Some(s);
         self.cmdline_focus = false;
 
@@ -157,6 +218,97 @@ impl Layout {
         self.result_time = Some(SystemTime::now());
     }
 
+    /// Highlight `area`, or remove the highlight if it is `None`.
+    pub fn set_highlight(&mut self, area: Option<Area>) {
+        self.highlight = area;
+        self.ev.trigger();
+    }
+
+    /// Draw the title and the view on top of `screen` with the colors configured for the screen.
+    fn draw_pane(&self, printer: &Printer<'_, '_>, screen: &str, focused: bool) {
+        let Some(view) = self.top_view(screen) else {
+            return;
+        };
+        let screen_title = self
+            .screens
+            .get(screen)
+            .map(|screen| screen.title())
+            .unwrap_or_default();
+        let has_stack = self
+            .stack
+            .get(screen)
+            .is_some_and(|stack| !stack.is_empty());
+
+        let screen_theme = theme::for_view(printer.theme, screen);
+        let screen_printer = printer.theme(&screen_theme);
+
+        let draw_title = |printer: &Printer<'_, '_>| {
+            // back button + title
+            if has_stack {
+                printer.with_color(ColorStyle::title_secondary(), |printer| {
+                    printer.print((1, 0), &format!("< {screen_title}"));
+                });
+            }
+
+            // view title, which is dimmed in the pane without the focus
+            let title_style = if focused {
+                ColorStyle::title_primary()
+            } else {
+                ColorStyle::title_secondary()
+            };
+            printer.with_color(title_style, |printer| {
+                let offset = HAlign::Center.get_offset(view.title().width(), printer.size.x);
+                printer.print((offset, 0), &view.title());
+            });
+
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                let offset = HAlign::Right.get_offset(view.title_sub().width(), printer.size.x);
+                printer.print((offset, 0), &view.title_sub());
+            });
+        };
+        let title_printer = screen_printer.cropped((printer.size.x, 1));
+        if focused {
+            self.draw_area(Area::Title, &title_printer, draw_title);
+        } else {
+            draw_title(&title_printer);
+        }
+
+        // screen content
+        let printer = &screen_printer
+            .offset((0, 1))
+            .cropped((printer.size.x, printer.size.y.saturating_sub(1)))
+            .focused(focused);
+        view.draw(printer);
+        if focused && self.highlight == Some(Area::Input) {
+            // Draw the first row again on top of the regular content.
+            self.draw_area(
+                Area::Input,
+                &printer.cropped((printer.size.x, 1)),
+                |printer| view.draw(printer),
+            );
+        }
+    }
+
+    /// Draw the contents of `area` with `draw`, inverting its colors if it is highlighted.
+    /// `printer` must be cropped to the area.
+    fn draw_area(
+        &self,
+        area: Area,
+        printer: &Printer<'_, '_>,
+        draw: impl FnOnce(&Printer<'_, '_>),
+    ) {
+        if self.highlight == Some(area) {
+            printer.with_effect(Effect::Reverse, |printer| {
+                for y in 0..printer.size.y {
+                    printer.print_hline((0, y), printer.size.x, " ");
+                }
+                draw(printer);
+            });
+        } else {
+            draw(printer);
+        }
+    }
+
     pub fn clear_cmdline(&mut self) {
         self.cmdline.set_content("");
         self.cmdline_focus = false;
@@ -178,6 +330,10 @@ impl Layout {
             view.on_leave();
         }
 
+        self.record_jump();
+        if let Some(focus) = &self.focus {
+            self.detached.remove(focus);
+        }
         if let Some(stack) = self.get_focussed_stack_mut() {
             stack.push(view)
         }
@@ -188,9 +344,286 @@ impl Layout {
             view.on_leave();
         }
 
+        if let Some(focus) = &self.focus {
+            self.detached.remove(focus);
+        }
         self.get_focussed_stack_mut().map(|stack| stack.pop());
     }
 
+    /// Where the user is: the view on top of the focused screen and its selected item.
+    fn location(&self) -> Option<Location> {
+        let screen = self.focus.clone()?;
+        let view = self.get_top_view()?;
+        Some(Location {
+            depth: self.stack.get(&screen).map(Vec::len).unwrap_or_default(),
+            screen,
+            title: view.title(),
+            position: view.position(),
+        })
+    }
+
+    fn record_jump(&mut self) {
+        if let Some(location) = self.location() {
+            self.jumps.record(location);
+        }
+    }
+
+    /// Go to `location`, returning false if its view was closed since.
+    fn restore(&mut self, location: &Location) -> bool {
+        let (Some(screen), Some(stack)) = (
+            self.screens.get(&location.screen),
+            self.stack.get(&location.screen),
+        ) else {
+            return false;
+        };
+        let detached = self.detached.get(&location.screen);
+        let view = match location.depth {
+            0 => Some(screen),
+            depth => stack
+                .iter()
+                .chain(detached.into_iter().flatten())
+                .nth(depth - 1),
+        };
+        if view.map(|view| view.title()).as_ref() != Some(&location.title) {
+            return false;
+        }
+
+        if let Some(view) = self.get_top_view() {
+            view.on_leave();
+        }
+        let stack = self.stack.get_mut(&location.screen).unwrap();
+        let detached = self.detached.entry(location.screen.clone()).or_default();
+        if location.depth < stack.len() {
+            // The views on top are kept in case the jump list goes forward to them again.
+            detached.splice(0..0, stack.split_off(location.depth));
+        } else {
+            stack.extend(detached.drain(..location.depth - stack.len()));
+        }
+        self.place(&location.screen);
+        self.focus = Some(location.screen.clone());
+        self.cmdline_focus = false;
+        if let Some(position) = location.position
+            && let Some(view) = self.get_current_view_mut()
+        {
+            view.set_position(position);
+        }
+        self.ev.trigger();
+        true
+    }
+
+    /// Go back or forward in the jump list, skipping the locations whose views were closed.
+    fn jump(&mut self, mode: JumpListMode) -> Result<CommandResult, String> {
+        let Some(current) = self.location() else {
+            return Ok(CommandResult::Consumed(None));
+        };
+        loop {
+            let location = match mode {
+                JumpListMode::Back => self.jumps.back(current.clone()),
+                JumpListMode::Forward => self.jumps.forward(),
+            };
+            match location {
+                Some(location) if location == current || !self.restore(&location) => continue,
+                Some(_) => return Ok(CommandResult::Consumed(None)),
+                None => {
+                    return Err(match mode {
+                        JumpListMode::Back => "Already at the oldest jump".into(),
+                        JumpListMode::Forward => "Already at the newest jump".into(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Show `screen` in the focused pane, unless it is shown in the other one already.
+    fn place(&mut self, screen: &str) {
+        if let Some(panes) = &mut self.split
+            && !panes.iter().any(|pane| pane == screen)
+            && let Some(pane) = panes
+                .iter_mut()
+                .find(|pane| Some(&**pane) == self.focus.as_ref())
+        {
+            *pane = screen.to_string();
+        }
+    }
+
+    /// Show `screens` side by side. Without screens, the split is turned off, or on with the
+    /// configured screens and the focused one.
+    pub fn set_split(&mut self, screens: Option<[String; 2]>) -> Result<(), String> {
+        let screens = match screens {
+            Some(screens) => screens,
+            None if self.split.is_some() => {
+                self.split = None;
+                self.ev.trigger();
+                return Ok(());
+            }
+            None => {
+                let mut screens = self
+                    .configuration
+                    .values()
+                    .split
+                    .clone()
+                    .unwrap_or_else(|| config::DEFAULT_SPLIT.map(String::from));
+                if let Some(focus) = &self.focus
+                    && !screens.contains(focus)
+                {
+                    screens[0].clone_from(focus);
+                }
+                screens
+            }
+        };
+        if let Some(screen) = screens.iter().find(|screen| !self.has_screen(screen)) {
+            return Err(format!("Invalid screen name: {screen}"));
+        }
+        if screens[0] == screens[1] {
+            return Err("The panes have to show different screens".into());
+        }
+
+        if !self
+            .focus
+            .as_ref()
+            .is_some_and(|focus| screens.contains(focus))
+        {
+            self.focus_pane(screens[0].clone());
+        }
+        self.split = Some(screens);
+        self.ev.trigger();
+        Ok(())
+    }
+
+    /// Move the focus to the pane showing `screen`. Unlike switching screens, this isn't a jump.
+    fn focus_pane(&mut self, screen: String) {
+        if let Some(view) = self.get_top_view() {
+            view.on_leave();
+        }
+        self.focus = Some(screen);
+        self.cmdline_focus = false;
+        self.ev.trigger();
+    }
+
+    /// The screens, the views opened on top of them and their selected items, to save them when
+    /// ncspot is quit. Views that can't be opened again from a URL end the stack of their screen.
+    pub fn session(&self) -> Session {
+        let mut screens: Vec<ScreenSession> = self
+            .screens
+            .iter()
+            .map(|(name, screen)| ScreenSession {
+                name: name.clone(),
+                position: screen.position(),
+                views: self
+                    .stack
+                    .get(name)
+                    .into_iter()
+                    .flatten()
+                    .map_while(|view| {
+                        Some(ViewSession {
+                            url: view.url()?,
+                            position: view.position(),
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+        screens.sort_by(|a, b| a.name.cmp(&b.name));
+        Session {
+            focus: self.focus.clone(),
+            split: self.split.clone(),
+            library_tab: None,
+            screens,
+        }
+    }
+
+    /// Select the items of the screens again and show the views opened by `open`, without
+    /// recording jumps. Unless `all` is set, only the view on top of the screen that was focused
+    /// is restored.
+    pub fn restore_session(
+        &mut self,
+        session: &Session,
+        all: bool,
+        mut open: impl FnMut(&str) -> Option<Box<dyn ViewExt>>,
+    ) {
+        if all
+            && let Some(split) = session.split.clone()
+            && let Err(e) = self.set_split(Some(split))
+        {
+            warn!("Could not restore the split layout: {e}");
+        }
+        for screen in &session.screens {
+            if !all && session.focus.as_ref() != Some(&screen.name) {
+                continue;
+            }
+            let Some(view) = self.screens.get_mut(&screen.name) else {
+                continue;
+            };
+            if let Some(position) = screen.position {
+                view.set_position(position);
+            }
+            let views = if all {
+                &screen.views[..]
+            } else {
+                screen
+                    .views
+                    .last()
+                    .map(std::slice::from_ref)
+                    .unwrap_or_default()
+            };
+            for saved in views {
+                let Some(mut view) = open(&saved.url) else {
+                    break;
+                };
+                if let Some(position) = saved.position {
+                    view.set_position(position);
+                }
+                if let Some(stack) = self.stack.get_mut(&screen.name) {
+                    stack.push(view);
+                }
+            }
+        }
+        self.ev.trigger();
+    }
+
+    /// The screens that are shown with the column they start at and their width, from left to
+    /// right.
+    fn panes(&self, width: usize) -> Vec<(String, usize, usize)> {
+        match (&self.split, &self.focus) {
+            (Some([left, right]), _) => {
+                // The panes are separated by a line.
+                let left_width = width.saturating_sub(1) / 2;
+                vec![
+                    (left.clone(), 0, left_width),
+                    (
+                        right.clone(),
+                        left_width + 1,
+                        width.saturating_sub(left_width + 1),
+                    ),
+                ]
+            }
+            (None, Some(focus)) => vec![(focus.clone(), 0, width)],
+            (None, None) => Vec::new(),
+        }
+    }
+
+    #[allow(clippy::borrowed_box)]
+    fn top_view(&self, screen: &str) -> Option<&Box<dyn ViewExt>> {
+        self.stack
+            .get(screen)
+            .and_then(|stack| stack.last())
+            .or_else(|| self.screens.get(screen))
+    }
+
+    fn top_view_mut(&mut self, screen: &str) -> Option<&mut Box<dyn ViewExt>> {
+        if self
+            .stack
+            .get(screen)
+            .is_some_and(|stack| !stack.is_empty())
+        {
+            self.stack
+                .get_mut(screen)
+                .and_then(|stack| stack.last_mut())
+        } else {
+            self.screens.get_mut(screen)
+        }
+    }
+
     #[allow(clippy::borrowed_box)]
     fn get_current_screen(&self) -> Option<&Box<dyn ViewExt>> {
         self.focus
@@ -219,40 +652,43 @@ impl Layout {
 
     #[allow(clippy::borrowed_box)]
     fn get_top_view(&self) -> Option<&Box<dyn ViewExt>> {
-        let focussed_stack = self.get_focussed_stack();
-        if focussed_stack.map(|s| s.len()).unwrap_or_default() > 0 {
-            focussed_stack.unwrap().last()
-        } else if let Some(id) = &self.focus {
-            self.screens.get(id)
-        } else {
-            None
-        }
+        self.top_view(self.focus.as_ref()?)
     }
 
     fn get_current_view_mut(&mut self) -> Option<&mut Box<dyn ViewExt>> {
-        if let Some(focus) = &self.focus {
-            let last_view = self
-                .stack
-                .get_mut(focus)
-                .filter(|stack| !stack.is_empty())
-                .and_then(|stack| stack.last_mut());
-            if last_view.is_some() {
-                last_view
-            } else {
-                self.screens.get_mut(focus)
-            }
-        } else {
-            None
-        }
+        let focus = self.focus.clone()?;
+        self.top_view_mut(&focus)
+    }
+
+    /// Complete the theme name at the end of the `theme` command in the command line.
+    fn complete_cmdline(&mut self) {
+        let command_key = self
+            .configuration
+            .values()
+            .command_key
+            .unwrap_or(config::DEFAULT_COMMAND_KEY);
+        let content = self.cmdline.get_content();
+        let Some(command) = content.strip_prefix(command_key) else {
+            return;
+        };
+        let mut names = themes::names();
+        names.insert(0, themes::DEFAULT.to_string());
+        let candidates = themes::complete(command, &names);
+        let completed = apply_completion(&content, &candidates);
+        self.cmdline.set_content(completed);
     }
 
     /// Propagate the given event to the command line.
     fn command_line_handle_event(&mut self, event: Event) -> EventResult {
         let is_left_right_event = matches!(event, Event::Key(Key::Left) | Event::Key(Key::Right));
+        let filtering = self.cmdline.get_content().starts_with(FILTER_PREFIX);
         let result = self.cmdline.on_event(event);
 
         if self.cmdline.get_content().is_empty() {
             self.clear_cmdline();
+            if filtering {
+                return EventResult::with_cb(|s| filter(s, ""));
+            }
         }
 
         if is_left_right_event {
@@ -267,54 +703,42 @@ impl View for Layout {
     fn draw(&self, printer: &Printer<'_, '_>) {
         let result = self.get_result();
 
-        let cmdline_visible = !self.cmdline.get_content().is_empty();
+        // A highlighted command line is shown even if it is empty.
+        let cmdline_visible =
+            !self.cmdline.get_content().is_empty() || self.highlight == Some(Area::CommandLine);
         let mut cmdline_height = usize::from(cmdline_visible);
         if result.as_ref().map(Option::is_some).unwrap_or(true) {
             cmdline_height += 1;
         }
 
-        let screen_title = self
-            .get_current_screen()
-            .map(|screen| screen.title())
-            .unwrap_or_default();
-
-        if let Some(view) = self.get_top_view() {
-            // back button + title
-            if !self.is_current_stack_empty() {
-                printer.with_color(ColorStyle::title_secondary(), |printer| {
-                    printer.print((1, 0), &format!("< {screen_title}"));
-                });
-            }
-
-            // view title
-            printer.with_color(ColorStyle::title_primary(), |printer| {
-                let offset = HAlign::Center.get_offset(view.title().width(), printer.size.x);
-                printer.print((offset, 0), &view.title());
-            });
-
+        let content_height = printer.size.y.saturating_sub(3 + cmdline_height);
+        for (screen, x, width) in self.panes(printer.size.x) {
+            let focused = self.focus.as_ref() == Some(&screen);
+            let printer = printer.offset((x, 0)).cropped((width, content_height + 1));
+            self.draw_pane(&printer, &screen, focused);
+        }
+        if self.split.is_some() {
+            let x = printer.size.x.saturating_sub(1) / 2;
             printer.with_color(ColorStyle::secondary(), |printer| {
-                let offset = HAlign::Right.get_offset(view.title_sub().width(), printer.size.x);
-                printer.print((offset, 0), &view.title_sub());
+                printer.print_vline((x, 0), content_height + 1, "│");
             });
-
-            // screen content
-            let printer = &printer
-                .offset((0, 1))
-                .cropped((printer.size.x, printer.size.y - 3 - cmdline_height))
-                .focused(true);
-            view.draw(printer);
         }
 
-        self.statusbar
-            .draw(&printer.offset((0, printer.size.y - 2 - cmdline_height)));
+        self.draw_area(
+            Area::StatusBar,
+            &printer
+                .offset((0, printer.size.y - 2 - cmdline_height))
+                .cropped((printer.size.x, 2)),
+            |printer| self.statusbar.draw(printer),
+        );
 
         if let Ok(Some(r)) = result {
             printer.print_hline((0, printer.size.y - cmdline_height), printer.size.x, " ");
             printer.print((0, printer.size.y - cmdline_height), &r);
         } else if let Err(e) = result {
             let style = ColorStyle::new(
-                ColorType::Color(*self.theme.palette.custom("error").unwrap()),
-                ColorType::Color(*self.theme.palette.custom("error_bg").unwrap()),
+                ColorType::Color(*printer.theme.palette.custom("error").unwrap()),
+                ColorType::Color(*printer.theme.palette.custom("error_bg").unwrap()),
             );
 
             printer.with_color(style, |printer| {
@@ -325,7 +749,18 @@ impl View for Layout {
 
         if cmdline_visible {
             let printer = &printer.offset((0, printer.size.y - 1));
-            self.cmdline.draw(printer);
+            self.draw_area(Area::CommandLine, printer, |printer| {
+                if self.cmdline.get_content().is_empty() {
+                    let command_key = self
+                        .configuration
+                        .values()
+                        .command_key
+                        .unwrap_or(config::DEFAULT_COMMAND_KEY);
+                    printer.print((0, 0), &command_key.to_string());
+                } else {
+                    self.cmdline.draw(printer);
+                }
+            });
         }
     }
 
@@ -336,8 +771,10 @@ impl View for Layout {
 
         self.cmdline.layout(Vec2::new(size.x, 1));
 
-        if let Some(view) = self.get_current_view_mut() {
-            view.layout(Vec2::new(size.x, size.y - 3));
+        for (screen, _, width) in self.panes(size.x) {
+            if let Some(view) = self.top_view_mut(&screen) {
+                view.layout(Vec2::new(width, size.y.saturating_sub(3)));
+            }
         }
     }
 
@@ -346,9 +783,36 @@ impl View for Layout {
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
+        // The key after a mark command is the name of the mark.
+        if let Some(command) = self.pending_mark.take() {
+            match event {
+                Event::Char(name) => {
+                    return EventResult::with_cb(move |s| {
+                        if let Some(data) = s.user_data::<UserData>().cloned() {
+                            data.cmd.handle(s, command(name));
+                        }
+                    });
+                }
+                Event::Key(Key::Esc) => return EventResult::consumed(),
+                Event::Refresh | Event::WindowResize | Event::Mouse { .. } => {
+                    self.pending_mark = Some(command);
+                }
+                _ => {}
+            }
+        }
+
         match event {
             Event::Key(Key::Esc) if self.cmdline_focus => {
+                let filtering = self.cmdline.get_content().starts_with(FILTER_PREFIX);
                 self.clear_cmdline();
+                if filtering {
+                    EventResult::with_cb(|s| filter(s, ""))
+                } else {
+                    EventResult::consumed()
+                }
+            }
+            Event::Key(Key::Tab) if self.cmdline_focus => {
+                self.complete_cmdline();
                 EventResult::consumed()
             }
             _ if self.cmdline_focus => self.command_line_handle_event(event),
@@ -360,7 +824,8 @@ impl View for Layout {
                             .values()
                             .command_key
                             .unwrap_or(config::DEFAULT_COMMAND_KEY)
-                        || character == '/') =>
+                        || character == '/'
+                        || character == FILTER_PREFIX) =>
             {
                 let result = self
                     .get_current_view_mut()
@@ -380,6 +845,9 @@ impl View for Layout {
                     } else if character == '/' {
                         self.enable_jump();
                         EventResult::consumed()
+                    } else if character == FILTER_PREFIX {
+                        self.enable_cmdline(FILTER_PREFIX);
+                        EventResult::consumed()
                     } else {
                         EventResult::Ignored
                     }
@@ -392,11 +860,31 @@ impl View for Layout {
                 event: mouse_event,
                 ..
             } => {
+                // A drag that started on the status bar continues outside of it.
+                let dragged = self.statusbar_drag
+                    && matches!(mouse_event, MouseEvent::Hold(_) | MouseEvent::Release(_));
+
+                // The pane under the pointer, which gets the focus when it is clicked.
+                let pane = self
+                    .panes(self.last_size.x)
+                    .into_iter()
+                    .find(|(_, x, width)| position.x >= *x && position.x < x + width);
+                let focus_pane = |layout: &mut Self| {
+                    if let Some((screen, _, _)) = &pane
+                        && matches!(mouse_event, MouseEvent::Press(_))
+                        && layout.focus.as_ref() != Some(screen)
+                    {
+                        layout.focus_pane(screen.clone());
+                    }
+                };
+
                 // Handle mouse events in the command/jump area.
-                if position.y == 0 {
-                    if mouse_event == MouseEvent::Press(MouseButton::Left)
+                if position.y == 0 && !dragged {
+                    focus_pane(self);
+                    if let Some((_, x, _)) = &pane
+                        && mouse_event == MouseEvent::Press(MouseButton::Left)
                         && !self.is_current_stack_empty()
-                        && position.x
+                        && position.x - *x
                             < self
                                 .get_current_screen()
                                 .map(|screen| screen.title())
@@ -417,19 +905,25 @@ impl View for Layout {
                     cmdline_height += 1;
                 }
 
-                if position.y >= self.last_size.y.saturating_sub(2 + cmdline_height)
-                    && position.y < self.last_size.y - cmdline_height
+                if dragged
+                    || position.y >= self.last_size.y.saturating_sub(2 + cmdline_height)
+                        && position.y < self.last_size.y - cmdline_height
                 {
+                    self.statusbar_drag = match mouse_event {
+                        MouseEvent::Press(MouseButton::Left) => true,
+                        MouseEvent::Release(_) => false,
+                        _ => self.statusbar_drag,
+                    };
                     self.statusbar.on_event(
                         event.relativized(Vec2::new(0, self.last_size.y - 2 - cmdline_height)),
                     );
                     return EventResult::consumed();
                 }
 
-                if let Some(view) = self.get_current_view_mut() {
-                    view.on_event(event.relativized((0, 1)))
-                } else {
-                    EventResult::Ignored
+                focus_pane(self);
+                match pane.and_then(|(screen, x, _)| Some((self.top_view_mut(&screen)?, x))) {
+                    Some((view, x)) => view.on_event(event.relativized((x, 1))),
+                    None => EventResult::Ignored,
                 }
             }
             _ => {
@@ -443,8 +937,10 @@ impl View for Layout {
     }
 
     fn call_on_any(&mut self, s: &Selector, c: AnyCb<'_>) {
-        if let Some(view) = self.get_current_view_mut() {
-            view.call_on_any(s, c);
+        for (screen, _, _) in self.panes(self.last_size.x) {
+            if let Some(view) = self.top_view_mut(&screen) {
+                view.call_on_any(s, &mut *c);
+            }
         }
     }
 
@@ -462,6 +958,12 @@ impl View for Layout {
 }
 
 impl ViewExt for Layout {
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.get_top_view()
+            .map(|view| view.help_contexts())
+            .unwrap_or_default()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Focus(view) => {
@@ -473,6 +975,7 @@ impl ViewExt for Layout {
                     && let Some(stack) = self.stack.get_mut(search_view_name)
                 {
                     stack.clear();
+                    self.detached.remove(search_view_name);
                 }
 
                 if self.screens.keys().any(|k| k == view) {
@@ -487,6 +990,55 @@ impl ViewExt for Layout {
                 self.pop_view();
                 Ok(CommandResult::Consumed(None))
             }
+            Command::JumpList(mode) => self.jump(*mode),
+            Command::Split(screens) => {
+                self.set_split(screens.clone())?;
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Pane(mode) => {
+                let Some([left, right]) = self.split.clone() else {
+                    return Err("The layout isn't split".into());
+                };
+                let screen = match mode {
+                    Some(PaneMode::Left) => left,
+                    Some(PaneMode::Right) => right,
+                    None if self.focus.as_ref() == Some(&left) => right,
+                    None => left,
+                };
+                self.focus_pane(screen);
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::SetMark(None) => {
+                self.pending_mark = Some(|name| Command::SetMark(Some(name)));
+                Ok(CommandResult::Consumed(Some(
+                    "Set mark: press a key".into(),
+                )))
+            }
+            Command::GotoMark(None) => {
+                self.pending_mark = Some(|name| Command::GotoMark(Some(name)));
+                Ok(CommandResult::Consumed(Some(
+                    "Go to mark: press a key".into(),
+                )))
+            }
+            Command::SetMark(Some(name)) => {
+                if let Some(location) = self.location() {
+                    self.marks.insert(*name, location);
+                }
+                Ok(CommandResult::Consumed(Some(format!("Mark {name} set"))))
+            }
+            Command::GotoMark(Some(name)) => {
+                let Some(location) = self.marks.get(name).cloned() else {
+                    return Err(format!("Mark {name} is not set"));
+                };
+                let current = self.location();
+                if !self.restore(&location) {
+                    return Err(format!("The view of mark {name} was closed"));
+                }
+                if let Some(current) = current {
+                    self.jumps.record(current);
+                }
+                Ok(CommandResult::Consumed(None))
+            }
             _ => {
                 if let Some(view) = self.get_current_view_mut() {
                     view.on_command(s, cmd)
@@ -4,25 +4,34 @@ use cursive::Cursive;
 use cursive::view::ViewWrapper;
 use strum::IntoEnumIterator;
 
-use crate::command::Command;
+use crate::command::{Command, JumpMode};
 use crate::commands::CommandResult;
 use crate::config::LibraryTab;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::queue::Queue;
 use crate::traits::ViewExt;
 use crate::ui::browse::BrowseView;
+use crate::ui::duplicates::DuplicatesView;
+use crate::ui::layout::Layout;
+use crate::ui::library_search::LibrarySearchView;
 use crate::ui::listview::ListView;
 use crate::ui::playlists::PlaylistsView;
 use crate::ui::tabbedview::TabbedView;
 
 pub struct LibraryView {
     tabs: TabbedView,
+    /// The library tab of each tab, in the same order.
+    tab_ids: Vec<LibraryTab>,
     display_name: Option<String>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
 }
 
 impl LibraryView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
         let mut tabview = TabbedView::new();
+        let mut tab_ids = Vec::new();
         let selected_tabs = library
             .cfg
             .values()
@@ -31,18 +40,21 @@ impl LibraryView {
             .unwrap_or_else(|| Vec::from_iter(LibraryTab::iter()));
 
         for tab in selected_tabs {
-            match tab {
+            match &tab {
                 LibraryTab::Tracks => tabview.add_tab(
                     "Tracks",
-                    ListView::new(library.tracks.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.tracks.clone(), queue.clone(), library.clone())
+                        .with_order("tracks"),
                 ),
                 LibraryTab::Albums => tabview.add_tab(
                     "Albums",
-                    ListView::new(library.albums.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.albums.clone(), queue.clone(), library.clone())
+                        .with_order("albums"),
                 ),
                 LibraryTab::Artists => tabview.add_tab(
                     "Artists",
-                    ListView::new(library.artists.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.artists.clone(), queue.clone(), library.clone())
+                        .with_order("artists"),
                 ),
                 LibraryTab::Playlists => tabview.add_tab(
                     "Playlists",
@@ -50,16 +62,29 @@ impl LibraryView {
                 ),
                 LibraryTab::Podcasts => tabview.add_tab(
                     "Podcasts",
-                    ListView::new(library.shows.clone(), queue.clone(), library.clone()),
+                    ListView::new(library.shows.clone(), queue.clone(), library.clone())
+                        .with_order("podcasts"),
                 ),
                 LibraryTab::Browse => {
                     tabview.add_tab("Browse", BrowseView::new(queue.clone(), library.clone()))
                 }
+                LibraryTab::Local => {
+                    if library.cfg.values().local_directories.is_none() {
+                        continue;
+                    }
+                    tabview.add_tab(
+                        "Local",
+                        ListView::new(library.local_tracks.clone(), queue.clone(), library.clone())
+                            .with_order("local"),
+                    )
+                }
             }
+            tab_ids.push(tab);
         }
 
         Self {
             tabs: tabview,
+            tab_ids,
             display_name: {
                 let hide_username = library.cfg.values().hide_display_names.unwrap_or(false);
                 if hide_username {
@@ -68,6 +93,24 @@ impl LibraryView {
                     library.display_name.clone()
                 }
             },
+            queue,
+            library,
+        }
+    }
+
+    /// The library tab that is selected.
+    pub fn tab(&self) -> Option<LibraryTab> {
+        self.tab_ids.get(self.tabs.selected()).cloned()
+    }
+
+    /// Select `tab`, returning false if it isn't shown.
+    pub fn select_tab(&mut self, tab: &LibraryTab) -> bool {
+        match self.tab_ids.iter().position(|id| id == tab) {
+            Some(index) => {
+                self.tabs.set_selected(index);
+                true
+            }
+            None => false,
         }
     }
 }
@@ -85,7 +128,35 @@ impl ViewExt for LibraryView {
         }
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.tabs.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        // With an index, searching covers the whole library instead of the current tab.
+        if let Command::Jump(JumpMode::Query(query)) = cmd
+            && let Some(matches) = self.library.search_index(query)
+        {
+            let view =
+                LibrarySearchView::new(query, matches, self.queue.clone(), self.library.clone());
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Duplicates = cmd {
+            let view = DuplicatesView::for_saved_tracks(self.queue.clone(), self.library.clone());
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            return Ok(CommandResult::Consumed(None));
+        }
+
         self.tabs.on_command(s, cmd)
     }
 }
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::view::ViewWrapper;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::help::HelpContext;
+use crate::library::Library;
+use crate::library_index::IndexMatches;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::listview::ListView;
+use crate::ui::tabbedview::TabbedView;
+
+/// The saved items that match a search of the library index, found without the Web API.
+pub struct LibrarySearchView {
+    query: String,
+    tabs: TabbedView,
+}
+
+impl LibrarySearchView {
+    pub fn new(
+        query: &str,
+        matches: IndexMatches,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+    ) -> Self {
+        let tracks = pick(&library.tracks.read().unwrap(), &matches.tracks, |track| {
+            track.id.as_deref()
+        });
+        let albums = pick(&library.albums.read().unwrap(), &matches.albums, |album| {
+            album.id.as_deref()
+        });
+        let artists = pick(
+            &library.artists.read().unwrap(),
+            &matches.artists,
+            |artist| artist.id.as_deref(),
+        );
+        let playlists = pick(
+            &library.playlists.read().unwrap(),
+            &matches.playlists,
+            |playlist| Some(playlist.id.as_str()),
+        );
+
+        let mut tabs = TabbedView::new();
+        tabs.add_tab(
+            "Tracks",
+            ListView::new(tracks, queue.clone(), library.clone()),
+        );
+        tabs.add_tab(
+            "Albums",
+            ListView::new(albums, queue.clone(), library.clone()),
+        );
+        tabs.add_tab(
+            "Artists",
+            ListView::new(artists, queue.clone(), library.clone()),
+        );
+        tabs.add_tab("Playlists", ListView::new(playlists, queue, library));
+
+        Self {
+            query: query.to_string(),
+            tabs,
+        }
+    }
+}
+
+/// The `items` with the given `ids`, in the order of the ids.
+fn pick<T: Clone>(
+    items: &[T],
+    ids: &[String],
+    id: impl Fn(&T) -> Option<&str>,
+) -> Arc<RwLock<Vec<T>>> {
+    let by_id: HashMap<&str, &T> = items
+        .iter()
+        .filter_map(|item| Some((id(item)?, item)))
+        .collect();
+    let picked = ids
+        .iter()
+        .filter_map(|wanted| by_id.get(wanted.as_str()).map(|item| (*item).clone()))
+        .collect();
+    Arc::new(RwLock::new(picked))
+}
+
+impl ViewWrapper for LibrarySearchView {
+    wrap_impl!(self.tabs: TabbedView);
+}
+
+impl ViewExt for LibrarySearchView {
+    fn title(&self) -> String {
+        format!("Library search: {}", self.query)
+    }
+
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.tabs.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        self.tabs.on_command(s, cmd)
+    }
+}
@@ -1,7 +1,9 @@
 use cursive::view::scroll::Scroller;
 use log::info;
 use std::cmp::{Ordering, max, min};
+use std::collections::BTreeSet;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use cursive::align::HAlign;
 use cursive::event::{Callback, Event, EventResult, MouseButton, MouseEvent};
@@ -11,13 +13,21 @@ use cursive::view::scroll;
 use cursive::{Cursive, Printer, Rect, Vec2, XY};
 use unicode_width::UnicodeWidthStr;
 
-use crate::command::{Command, GotoMode, InsertSource, JumpMode, MoveAmount, MoveMode, TargetMode};
+use crate::columns::{self, ColumnSet};
+use crate::command::{
+    Command, GotoMode, InsertSource, JumpMode, MarkMode, MoveAmount, MoveMode, TargetMode,
+};
 use crate::commands::CommandResult;
+use crate::config::SortingOrder;
+use crate::downloads;
 use crate::ext_traits::CursiveExt;
+use crate::fuzzy;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
 use crate::model::episode::Episode;
+use crate::model::grouped_track::GroupedTrack;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::model::show::Show;
@@ -25,18 +35,32 @@ use crate::model::track::Track;
 use crate::queue::Queue;
 #[cfg(feature = "share_clipboard")]
 use crate::sharing::{read_share, write_share};
+use crate::sorting;
 use crate::spotify::UriType;
 use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
 use crate::ui::album::AlbumView;
 use crate::ui::artist::ArtistView;
+use crate::ui::confirm;
 use crate::ui::contextmenu::ContextMenu;
 use crate::ui::pagination::Pagination;
 
+/// Two clicks on the same item within this time are a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 pub enum MouseHandleResult {
     Handled(EventResult),
     Unhandled(Command),
 }
 
+/// A query narrowing a [ListView] to the items that match it, best matches first.
+struct Filter {
+    query: String,
+    /// The indexes of the matching items, in the order they are shown.
+    rows: Vec<usize>,
+    /// The length and the address of the content when it was filtered, like `sorted`.
+    content: (usize, usize),
+}
+
 pub struct ListView<I: ListItem> {
     content: Arc<RwLock<Vec<I>>>,
     last_content_len: usize,
@@ -50,6 +74,30 @@ pub struct ListView<I: ListItem> {
     library: Arc<Library>,
     pagination: Pagination<I>,
     title: String,
+    /// The id the order of the list is saved under, if it is kept between sessions.
+    order_id: Option<String>,
+    order: Option<SortingOrder>,
+    /// The length and the address of the content when it was last sorted. The content is sorted
+    /// in place, so it has to be sorted again when it is replaced or items are added.
+    sorted: (usize, usize),
+    filter: Option<Filter>,
+    /// The indexes of the marked items, which bulk actions apply to.
+    marked: BTreeSet<usize>,
+    /// Where the range that is being marked starts.
+    range_start: Option<usize>,
+    /// The length and the address of the content when items were marked, like `sorted`.
+    marked_content: (usize, usize),
+    /// The kind of view whose configured columns the tracks are shown in.
+    columns: Option<ColumnSet>,
+    /// The item that was clicked last and when, to detect double clicks.
+    last_click: Option<(usize, Instant)>,
+}
+
+impl<I: ListItem> Drop for ListView<I> {
+    /// Stop loading pages that nobody is going to look at anymore.
+    fn drop(&mut self) {
+        self.pagination.cancel();
+    }
 }
 
 impl<I: ListItem> Scroller for ListView<I> {
@@ -77,6 +125,15 @@ impl<I: ListItem + Clone> ListView<I> {
             library,
             pagination: Pagination::default(),
             title: "".to_string(),
+            order_id: None,
+            order: None,
+            sorted: (0, 0),
+            filter: None,
+            marked: BTreeSet::new(),
+            range_start: None,
+            marked_content: (0, 0),
+            columns: None,
+            last_click: None,
         };
         result.try_paginate();
         result
@@ -87,16 +144,228 @@ impl<I: ListItem + Clone> ListView<I> {
         self
     }
 
+    /// Show the tracks in the columns configured for `columns`, if there are any.
+    pub fn with_columns(mut self, columns: ColumnSet) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Keep the order of the list under `id`, and restore the order saved there.
+    pub fn with_order(mut self, id: &str) -> Self {
+        self.order = self.library.cfg.state().view_orders.get(id).cloned();
+        self.order_id = Some(id.to_string());
+        self.apply_order();
+        self
+    }
+
+    /// Sort the content if it changed since it was last sorted.
+    fn apply_order(&mut self) {
+        let Some(order) = &self.order else {
+            return;
+        };
+        let mut content = self.content.write().unwrap();
+        if self.sorted != (content.len(), content.as_ptr() as usize) {
+            sorting::sort(&mut content, &order.key, &order.direction);
+            self.sorted = (content.len(), content.as_ptr() as usize);
+            self.marked.clear();
+            self.range_start = None;
+            if let Some(filter) = &mut self.filter {
+                filter.content = (0, 0);
+            }
+        }
+    }
+
+    /// Narrow the list to the items matching `query` and select the best match, or show all
+    /// items again without a query.
+    pub fn set_filter(&mut self, query: Option<&str>) {
+        self.filter = query
+            .filter(|query| !query.trim().is_empty())
+            .map(|query| Filter {
+                query: query.to_string(),
+                rows: Vec::new(),
+                content: (0, 0),
+            });
+        self.refilter();
+        let first = self.item_at(0).unwrap_or(0);
+        match self.filter {
+            Some(_) => self.move_focus_to(first),
+            None => self.move_focus_to(self.selected),
+        }
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Match the content against the filter again if it changed since it was last matched.
+    fn refilter(&mut self) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        let content = self.content.read().unwrap();
+        if filter.content == (content.len(), content.as_ptr() as usize) {
+            return;
+        }
+
+        let mut matches: Vec<(i64, usize)> = content
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let fields = match item.sort_fields() {
+                    Some(fields) => vec![
+                        fields.title,
+                        fields.artists.join(" "),
+                        fields.album.unwrap_or_default(),
+                    ],
+                    None => vec![item.display_left(&self.library)],
+                };
+                Some((fuzzy::score(&filter.query, &fields)?, index))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        filter.rows = matches.into_iter().map(|(_, index)| index).collect();
+        filter.content = (content.len(), content.as_ptr() as usize);
+    }
+
+    /// Mark or unmark the selected item, or start or finish marking a range of items.
+    fn mark(&mut self, mode: &MarkMode) {
+        {
+            let content = self.content.read().unwrap();
+            self.marked_content = (content.len(), content.as_ptr() as usize);
+        }
+        match mode {
+            MarkMode::Toggle => {
+                if !self.marked.remove(&self.selected) {
+                    self.marked.insert(self.selected);
+                }
+            }
+            MarkMode::Range => match self.range_start {
+                Some(_) => {
+                    self.marked = self.marked_indexes().into_iter().collect();
+                    self.range_start = None;
+                }
+                None => self.range_start = Some(self.selected),
+            },
+            MarkMode::Clear => self.clear_marks(),
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.range_start = None;
+    }
+
+    /// Forget the marks if the content was replaced or items were added, as their indexes no
+    /// longer point to the same items.
+    fn check_marks(&mut self) {
+        let content = self.content.read().unwrap();
+        if self.marked_content != (content.len(), content.as_ptr() as usize) {
+            self.marked.clear();
+            self.range_start = None;
+        }
+    }
+
+    /// The indexes of the marked items, including the range that is being marked, in the order
+    /// they are shown.
+    pub fn marked_indexes(&self) -> Vec<usize> {
+        let range = self
+            .range_start
+            .and_then(|start| Some((self.row_of(start)?, self.row_of(self.selected)?)))
+            .map(|(a, b)| min(a, b)..=max(a, b));
+        (0..self.content_len(false))
+            .filter_map(|row| {
+                let index = self.item_at(row)?;
+                let in_range = range.as_ref().is_some_and(|range| range.contains(&row));
+                (in_range || self.marked.contains(&index)).then_some(index)
+            })
+            .collect()
+    }
+
+    /// The marked items, in the order they are shown.
+    fn marked_items(&self) -> Vec<I> {
+        let content = self.content.read().unwrap();
+        self.marked_indexes()
+            .into_iter()
+            .filter_map(|index| content.get(index).cloned())
+            .collect()
+    }
+
+    /// Apply a command to all marked items. Returns whether there were any.
+    fn run_bulk_command(&mut self, cmd: &Command) -> Option<CommandResult> {
+        let mut items = self.marked_items();
+        if items.is_empty() {
+            return None;
+        }
+        self.clear_marks();
+
+        let tracks: Vec<Track> = items.iter().filter_map(ListItem::track).collect();
+        let track_refs: Vec<&Track> = tracks.iter().collect();
+        match cmd {
+            Command::Queue => items.iter_mut().for_each(|item| item.queue(&self.queue)),
+            // Every item is played after the current one, so the last one goes first.
+            Command::PlayNext => items
+                .iter_mut()
+                .rev()
+                .for_each(|item| item.play_next(&self.queue)),
+            Command::Save => {
+                if !track_refs.is_empty() {
+                    self.library.save_tracks(&track_refs);
+                }
+                for item in items.iter_mut().filter(|item| item.track().is_none()) {
+                    item.save(&self.library);
+                }
+            }
+            Command::Delete => {
+                if !track_refs.is_empty() {
+                    self.library.unsave_tracks(&track_refs);
+                }
+                for item in items.iter_mut().filter(|item| item.track().is_none()) {
+                    item.unsave(&self.library);
+                }
+            }
+            Command::Add if !tracks.is_empty() => {
+                let dialog = ContextMenu::add_tracks_dialog(
+                    self.library.clone(),
+                    self.queue.get_spotify(),
+                    tracks,
+                );
+                return Some(CommandResult::Modal(Box::new(dialog)));
+            }
+            _ => {}
+        }
+        Some(CommandResult::Consumed(None))
+    }
+
+    /// The index of the item shown in `row`.
+    fn item_at(&self, row: usize) -> Option<usize> {
+        match &self.filter {
+            Some(filter) => filter.rows.get(row).copied(),
+            None => Some(row),
+        }
+    }
+
+    /// The row the item at `index` is shown in, unless it is filtered out.
+    fn row_of(&self, index: usize) -> Option<usize> {
+        match &self.filter {
+            Some(filter) => filter.rows.iter().position(|&i| i == index),
+            None => Some(index),
+        }
+    }
+
     pub fn get_pagination(&self) -> &Pagination<I> {
         &self.pagination
     }
 
-    /// Return the current amount of items in `content`
+    /// Return the current amount of rows, which is the amount of items in `content` unless the
+    /// list is filtered.
     ///
     /// If `include_paginator` is `true`, the pagination entry will be included
     /// in the count.
     pub fn content_len(&self, include_paginator: bool) -> usize {
-        let content_len = self.content.read().unwrap().len();
+        let content_len = match &self.filter {
+            Some(filter) => filter.rows.len(),
+            None => self.content.read().unwrap().len(),
+        };
 
         // add 1 more row for paginator if we can paginate
         if self.can_paginate() && include_paginator {
@@ -144,45 +413,73 @@ impl<I: ListItem + Clone> ListView<I> {
         content
             .iter()
             .enumerate()
-            .filter(|(_, i)| {
-                i.display_left(&self.library)
-                    .to_lowercase()
-                    .contains(&query[..].to_lowercase())
+            .filter(|(index, i)| {
+                self.row_of(*index).is_some()
+                    && i.display_left(&self.library)
+                        .to_lowercase()
+                        .contains(&query[..].to_lowercase())
             })
             .map(|(i, _)| i)
             .collect()
     }
 
+    /// Select the item at `target`.
     pub fn move_focus_to(&mut self, target: usize) {
-        let len = self.content_len(false).saturating_sub(1);
+        let len = self.content.read().unwrap().len().saturating_sub(1);
         self.selected = min(target, len);
-        self.scroller.scroll_to_y(self.selected);
+        if let Some(row) = self.row_of(self.selected) {
+            self.scroller.scroll_to_y(row);
+        }
     }
 
+    /// Select the item shown `delta` rows below the selected one.
     pub fn move_focus(&mut self, delta: i32) {
-        let new = self.selected as i32 + delta;
-        self.move_focus_to(max(new, 0) as usize);
+        let row = self.row_of(self.selected).unwrap_or_default() as i32 + delta;
+        self.move_focus_to_row(max(row, 0) as usize);
+    }
+
+    fn move_focus_to_row(&mut self, row: usize) {
+        let row = min(row, self.content_len(false).saturating_sub(1));
+        if let Some(index) = self.item_at(row) {
+            self.selected = index;
+        }
+        self.scroller.scroll_to_y(row);
     }
 
     /// Append the currently selected item and all the following ones to the queue after the
-    /// currently playing track and start playing them. Returns true if adding and playing the
-    /// tracks succeeded, false otherwhise.
+    /// currently playing track and start playing them. Only the rows that are shown are played.
+    /// Returns true if adding and playing the tracks succeeded, false otherwhise.
     fn attempt_play_all_tracks(&self) -> bool {
         let content = self.content.read().unwrap();
-        let any = &(*content) as &dyn std::any::Any;
-        let playables = any.downcast_ref::<Vec<Playable>>();
-        let tracks = any.downcast_ref::<Vec<Track>>().map(|t| {
-            t.iter()
-                .map(|t| Playable::Track(t.clone()))
-                .collect::<Vec<Playable>>()
-        });
-        if let Some(tracks) = playables.or(tracks.as_ref()) {
-            let index = self.queue.append_next(tracks);
-            self.queue.play(index + self.selected, true, false);
-            true
-        } else {
-            false
+        let mut tracks = Vec::new();
+        let mut selected = 0;
+        for row in 0..self.content_len(false) {
+            let Some(item) = self.item_at(row).and_then(|index| content.get(index)) else {
+                continue;
+            };
+            if self.item_at(row) == Some(self.selected) {
+                selected = tracks.len();
+            }
+            // Group headers aren't played, so they don't count.
+            let any = item as &dyn std::any::Any;
+            let playable = any
+                .downcast_ref::<Playable>()
+                .cloned()
+                .or_else(|| any.downcast_ref::<Track>().cloned().map(Playable::Track))
+                .or_else(|| {
+                    any.downcast_ref::<GroupedTrack>()
+                        .and_then(GroupedTrack::playable)
+                        .cloned()
+                });
+            tracks.extend(playable);
+        }
+
+        if tracks.is_empty() {
+            return false;
         }
+        let index = self.queue.append_next(&tracks);
+        self.queue.play(index + selected, true, false);
+        true
     }
 
     /// Appends the currently focused item after the currently playing item and starts playing it.
@@ -199,18 +496,23 @@ impl<I: ListItem + Clone> ListView<I> {
     ///
     /// Panics if `index` is out of bounds.
     pub fn remove(&mut self, index: usize) {
-        let mut c = self.content.write().unwrap();
-        c.remove(index);
-        if self.selected >= c.len() {
-            self.selected = self.selected.saturating_sub(1);
+        {
+            let mut c = self.content.write().unwrap();
+            c.remove(index);
+            if self.selected >= c.len() {
+                self.selected = self.selected.saturating_sub(1);
+            }
         }
+        self.refilter();
     }
 
-    /// Get the selected row from a mouse position and offset
+    /// Get the index of the item at a mouse position and offset
     fn get_selected_row(&self, position: XY<usize>, offset: XY<usize>) -> Option<usize> {
         let viewport = self.scroller.content_viewport().top_left();
         let selected_row = position.checked_sub(offset).map(|p| p.y + viewport.y);
-        selected_row.filter(|row| *row < self.content_len(false))
+        selected_row
+            .filter(|row| *row < self.content_len(false))
+            .and_then(|row| self.item_at(row))
     }
 
     fn run_play_command(&mut self) {
@@ -256,21 +558,22 @@ impl<I: ListItem + Clone> ListView<I> {
                 if drag_started {
                     log::debug!("grabbing scroller");
                 } else if let Some(clicked_row_index) = self.get_selected_row(position, offset) {
-                    let currently_selected_listitem = self
+                    // A single click selects the item, a double click plays or opens it.
+                    let double_click = self.last_click.is_some_and(|(index, time)| {
+                        index == clicked_row_index && time.elapsed() < DOUBLE_CLICK
+                    });
+                    self.last_click = (!double_click).then(|| (clicked_row_index, Instant::now()));
+                    self.move_focus_to(clicked_row_index);
+
+                    let clicked_is_individual = self
                         .content
                         .read()
                         .unwrap()
-                        .get(clicked_row_index)
-                        .map(ListItem::as_listitem);
-                    let currently_selected_is_individual = currently_selected_listitem
-                        .filter(|item| item.track().is_some())
-                        .is_some();
-                    if self.selected == clicked_row_index && currently_selected_is_individual {
+                        .get(self.selected)
+                        .is_some_and(|item| item.as_listitem().track().is_some());
+                    if double_click && clicked_is_individual {
                         return MouseHandleResult::Unhandled(Command::Play);
-                    } else {
-                        // The clicked position wasn't focused yet or the item is a collection
-                        // that can be opened.
-                        self.move_focus_to(clicked_row_index);
+                    } else if double_click {
                         let content = self.content.read().unwrap();
                         let clicked_list_item =
                             content.get(self.selected).map(ListItem::as_listitem);
@@ -336,23 +639,36 @@ impl<I: ListItem + Clone> ListView<I> {
 
 impl<I: ListItem + Clone> View for ListView<I> {
     fn draw(&self, printer: &Printer<'_, '_>) {
+        let marked: BTreeSet<usize> = self.marked_indexes().into_iter().collect();
         let content = self.content.read().unwrap();
 
-        scroll::draw_lines(self, printer, |_, printer, i| {
+        let rows = match &self.filter {
+            Some(filter) => filter.rows.len(),
+            None => content.len(),
+        };
+        let columns = self
+            .columns
+            .and_then(|columns| columns.columns(&self.library.cfg));
+
+        scroll::draw_lines(self, printer, |_, printer, row| {
             // draw paginator after content
-            if i == content.len() && self.can_paginate() {
+            if row == rows && self.can_paginate() {
                 let style = ColorStyle::secondary();
 
                 let max = self.pagination.max_content().unwrap();
-                let buf = format!("{} more items, scroll to load", max - i);
+                let buf = format!("{} more items, scroll to load", max - content.len());
                 printer.with_color(style, |printer| {
                     printer.print((0, 0), &buf);
                 });
-            } else if i < content.len() {
+            } else if let Some(i) = self.item_at(row).filter(|&i| i < content.len()) {
                 let item = &content[i];
                 let currently_playing =
                     item.is_playing(&self.queue) && self.queue.get_current_index() == Some(i);
-                let is_local = item.track().map(|t| t.is_local).unwrap_or_default();
+                // Local files that weren't found in the local directories can't be played.
+                let is_local = item
+                    .track()
+                    .map(|t| t.is_local && !t.is_playable())
+                    .unwrap_or_default();
                 let is_playable = item.track().map(|t| t.is_playable).unwrap_or_default();
 
                 let style = if self.selected == i {
@@ -366,9 +682,25 @@ impl<I: ListItem + Clone> View for ListView<I> {
                             ColorType::Palette(PaletteColor::Secondary),
                             ColorType::Palette(PaletteColor::Highlight),
                         )
-                    } else {
+                    } else if printer.focused {
                         ColorStyle::highlight()
+                    } else {
+                        // The selection of a list that doesn't have the focus.
+                        ColorStyle::new(
+                            ColorType::Color(
+                                *printer.theme.palette.custom("highlight_unfocused").unwrap(),
+                            ),
+                            ColorType::Color(
+                                *printer
+                                    .theme
+                                    .palette
+                                    .custom("highlight_unfocused_bg")
+                                    .unwrap(),
+                            ),
+                        )
                     }
+                } else if marked.contains(&i) {
+                    ColorStyle::highlight_inactive()
                 } else if currently_playing {
                     ColorStyle::new(
                         ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
@@ -380,9 +712,20 @@ impl<I: ListItem + Clone> View for ListView<I> {
                     ColorStyle::primary()
                 };
 
-                let left = item.display_left(&self.library);
-                let center = item.display_center(&self.library);
-                let right = item.display_right(&self.library);
+                let (left, center, right) = match (&columns, item.track()) {
+                    (Some(columns), Some(track)) => {
+                        // One cell is kept free like after the right part.
+                        let total = printer.size.x.saturating_sub(1);
+                        let playable = Playable::Track(track);
+                        let row = columns::row(&playable, columns, total, &self.library);
+                        (row, String::new(), String::new())
+                    }
+                    _ => (
+                        item.display_left(&self.library),
+                        item.display_center(&self.library),
+                        item.display_right(&self.library),
+                    ),
+                };
                 let draw_center = !center.is_empty();
 
                 // draw left string
@@ -394,8 +737,12 @@ impl<I: ListItem + Clone> View for ListView<I> {
                 // if line contains search query match, draw on top with
                 // highlight color
                 if self.search_indexes.contains(&i) {
-                    let fg = *printer.theme.palette.custom("search_match").unwrap();
-                    let matched_style = ColorStyle::new(fg, style.back);
+                    let palette = &printer.theme.palette;
+                    let fg = *palette.custom("search_match").unwrap();
+                    let bg = palette
+                        .custom("search_match_bg")
+                        .map_or(style.back, |bg| ColorType::Color(*bg));
+                    let matched_style = ColorStyle::new(fg, bg);
 
                     let matches: Vec<(usize, usize)> = left
                         .to_lowercase()
@@ -454,6 +801,9 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn layout(&mut self, size: Vec2) {
         self.last_size = size;
+        self.apply_order();
+        self.refilter();
+        self.check_marks();
 
         let relayout_scroller = self.content_len(false) != self.last_content_len;
         self.last_content_len = self.content_len(true);
@@ -490,7 +840,8 @@ impl<I: ListItem + Clone> View for ListView<I> {
 
     fn important_area(&self, view_size: Vec2) -> Rect {
         if self.content_len(false) > 0 {
-            Rect::from_point((view_size.x, self.selected))
+            let row = self.row_of(self.selected).unwrap_or_default();
+            Rect::from_point((view_size.x, row))
         } else {
             Rect::from_point((0, 0))
         }
@@ -502,12 +853,54 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
         self.title.clone()
     }
 
-    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        vec![HelpContext::List]
+    }
+
+    fn position(&self) -> Option<usize> {
+        Some(self.selected)
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.move_focus_to(position);
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Command::Queue | Command::PlayNext | Command::Save | Command::Delete | Command::Add =
+            cmd
+            && let Some(result) = self.run_bulk_command(cmd)
+        {
+            return Ok(result);
+        }
+
         match cmd {
+            Command::Mark(mode) => {
+                self.mark(mode);
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Play => {
                 self.run_play_command();
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::Sort(key, direction) => {
+                let order = SortingOrder {
+                    key: key.clone(),
+                    direction: direction.clone(),
+                };
+                if let Some(id) = &self.order_id {
+                    self.library.cfg.with_state_mut(|state| {
+                        state.view_orders.insert(id.clone(), order.clone());
+                    });
+                }
+                self.order = Some(order);
+                self.sorted = (0, 0);
+                self.apply_order();
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Filter(query) => {
+                self.set_filter(query.as_deref());
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::PlayNext => {
                 info!("played next");
                 let mut content = self.content.write().unwrap();
@@ -546,10 +939,10 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                 if let Some(track) = item
                     && let Some(track) = track.track()
                 {
-                    let dialog = ContextMenu::add_track_dialog(
+                    let dialog = ContextMenu::add_tracks_dialog(
                         self.library.clone(),
                         self.queue.get_spotify(),
-                        track,
+                        vec![track],
                     );
                     return Ok(CommandResult::Modal(Box::new(dialog)));
                 }
@@ -562,7 +955,13 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     content.get(self.selected).cloned()
                 };
 
-                if let Some(item) = item.as_mut() {
+                // Deleting a playlist is confirmed first.
+                if let Some(playlist) = item
+                    .as_ref()
+                    .and_then(|item| (item as &dyn std::any::Any).downcast_ref::<Playlist>())
+                {
+                    confirm::delete_playlist(s, self.library.clone(), playlist.clone());
+                } else if let Some(item) = item.as_mut() {
                     item.unsave(&self.library);
                 }
 
@@ -629,13 +1028,14 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                 }
             },
             Command::Move(mode, amount) => {
-                let last_idx = self.content.read().unwrap().len().saturating_sub(1);
+                let row = self.row_of(self.selected).unwrap_or_default();
+                let last_row = self.content_len(false).saturating_sub(1);
 
                 match mode {
                     MoveMode::Up => {
-                        if self.selected > 0 {
+                        if row > 0 {
                             match amount {
-                                MoveAmount::Extreme => self.move_focus_to(0),
+                                MoveAmount::Extreme => self.move_focus_to_row(0),
                                 MoveAmount::Float(scale) => {
                                     let amount = (self.last_size.y as f32) * scale;
                                     self.move_focus(-(amount as i32))
@@ -646,9 +1046,9 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                         return Ok(CommandResult::Consumed(None));
                     }
                     MoveMode::Down => {
-                        if self.selected < last_idx {
+                        if row < last_row {
                             match amount {
-                                MoveAmount::Extreme => self.move_focus_to(last_idx),
+                                MoveAmount::Extreme => self.move_focus_to_row(last_row),
                                 MoveAmount::Float(scale) => {
                                     let amount = (self.last_size.y as f32) * scale;
                                     self.move_focus(amount as i32)
@@ -801,6 +1201,40 @@ impl<I: ListItem + Clone> ViewExt for ListView<I> {
                     };
                 }
             }
+            Command::Download => {
+                let item = {
+                    let content = self.content.read().unwrap();
+                    content.get(self.selected).map(|item| item.as_listitem())
+                };
+                if let Some(item) = item {
+                    let title = item.display_left(&self.library);
+                    downloads::add_item(
+                        self.queue.get_spotify(),
+                        self.library.cfg.clone(),
+                        title.clone(),
+                        item,
+                    )?;
+                    return Ok(CommandResult::Consumed(Some(format!(
+                        "Downloading {title}"
+                    ))));
+                }
+            }
+            Command::Radio(mode) => {
+                let target: Option<Box<dyn ListItem>> = match mode {
+                    TargetMode::Current => self.queue.get_current().map(|t| t.as_listitem()),
+                    TargetMode::Selected => {
+                        let content = self.content.read().unwrap();
+                        content.get(self.selected).map(|t| t.as_listitem())
+                    }
+                };
+
+                if let Some(seed) = target.and_then(|t| t.radio_seed()) {
+                    let count = self.queue.start_radio(seed)?;
+                    return Ok(CommandResult::Consumed(Some(format!(
+                        "Started radio with {count} tracks"
+                    ))));
+                }
+            }
             _ => {}
         };
 
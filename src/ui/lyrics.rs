@@ -0,0 +1,212 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::theme::{ColorStyle, ColorType, Effect, Style};
+use cursive::utils::lines::simple::LinesIterator;
+use cursive::{CbSink, Cursive, Printer, Vec2, View};
+use unicode_width::UnicodeWidthStr;
+
+use crate::command::{Command, MoveAmount, MoveMode};
+use crate::commands::CommandResult;
+use crate::config::Config;
+use crate::lyrics::{self, Lyrics};
+use crate::model::playable::Playable;
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+
+enum State {
+    Loading,
+    Loaded(Lyrics),
+    Failed(String),
+}
+
+/// The lyrics of the playing track. Synced lyrics follow the playback with the current line in the
+/// middle of the view, other lyrics can be scrolled.
+pub struct LyricsView {
+    queue: Arc<Queue>,
+    cfg: Arc<Config>,
+    cb_sink: CbSink,
+    /// The lyrics of the track with the uri, which are fetched in the background.
+    state: Arc<RwLock<Option<(String, State)>>>,
+    /// The first row shown of lyrics that aren't synced.
+    scroll: usize,
+    last_size: Vec2,
+}
+
+impl LyricsView {
+    pub fn new(queue: Arc<Queue>, cfg: Arc<Config>, cb_sink: CbSink) -> Self {
+        Self {
+            queue,
+            cfg,
+            cb_sink,
+            state: Arc::new(RwLock::new(None)),
+            scroll: 0,
+            last_size: Vec2::zero(),
+        }
+    }
+
+    /// Fetch the lyrics of the playing track unless they were fetched already.
+    fn update(&mut self) {
+        let Some(Playable::Track(track)) = self.queue.get_current() else {
+            return;
+        };
+        if matches!(&*self.state.read().unwrap(), Some((uri, _)) if *uri == track.uri) {
+            return;
+        }
+        *self.state.write().unwrap() = Some((track.uri.clone(), State::Loading));
+        self.scroll = 0;
+
+        let cfg = self.cfg.clone();
+        let state = self.state.clone();
+        let cb_sink = self.cb_sink.clone();
+        self.queue.get_spotify().api.spawn_with_callback(
+            move |api| (lyrics::find(&cfg, api, &track), track.uri),
+            move |(result, uri)| {
+                let mut state = state.write().unwrap();
+                // The track may have changed while the lyrics were fetched.
+                if !matches!(&*state, Some((u, _)) if *u == uri) {
+                    return;
+                }
+                *state = Some((
+                    uri,
+                    match result {
+                        Ok(lyrics) => State::Loaded(lyrics),
+                        Err(e) => State::Failed(e),
+                    },
+                ));
+                cb_sink.send(Box::new(Cursive::noop)).ok();
+            },
+        );
+    }
+
+    /// The message shown instead of lyrics, if there are none to show.
+    fn message(&self) -> Option<String> {
+        let message = match self.queue.get_current() {
+            None => "Nothing is playing",
+            Some(Playable::Episode(_)) => "Podcast episodes have no lyrics",
+            Some(Playable::Track(_)) => match &*self.state.read().unwrap() {
+                None | Some((_, State::Loading)) => "Loading lyrics...",
+                Some((_, State::Failed(e))) => return Some(e.clone()),
+                Some((_, State::Loaded(lyrics))) if lyrics.instrumental => "♪ Instrumental ♪",
+                Some((_, State::Loaded(lyrics))) if lyrics.lines.is_empty() => "No lyrics found",
+                Some((_, State::Loaded(_))) => return None,
+            },
+        };
+        Some(message.to_string())
+    }
+}
+
+/// The rows `lyrics` take up at `width`, with their text, its width and the index of their line.
+/// Long lines are wrapped, so every line takes up one or more rows.
+fn rows(lyrics: &Lyrics, width: usize) -> Vec<(&str, usize, usize)> {
+    let mut rows = Vec::new();
+    for (index, line) in lyrics.lines.iter().enumerate() {
+        let wrapped: Vec<_> = LinesIterator::new(&line.text, width).collect();
+        if wrapped.is_empty() {
+            rows.push(("", 0, index));
+        }
+        for row in wrapped {
+            rows.push((&line.text[row.start..row.end], row.width, index));
+        }
+    }
+    rows
+}
+
+impl View for LyricsView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let center = |printer: &Printer, y: usize, text: &str, width: usize| {
+            let x = printer.size.x.saturating_sub(width) / 2;
+            printer.print((x, y), text);
+        };
+        if let Some(message) = self.message() {
+            printer.with_effect(Effect::Italic, |printer| {
+                center(printer, printer.size.y / 2, &message, message.width())
+            });
+            return;
+        }
+        let state = self.state.read().unwrap();
+        let Some((_, State::Loaded(lyrics))) = &*state else {
+            return;
+        };
+
+        let rows = rows(lyrics, printer.size.x);
+        let (top, current) = if lyrics.is_synced() {
+            let position = self.queue.get_spotify().get_current_progress().as_millis() as u32;
+            let current = lyrics.current_line(position);
+            let row = rows
+                .iter()
+                .position(|(_, _, index)| Some(*index) == current)
+                .unwrap_or(0);
+            (row as isize - (printer.size.y / 2) as isize, current)
+        } else {
+            (self.scroll as isize, None)
+        };
+
+        let playing = Style::from(ColorStyle::new(
+            ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+            ColorType::InheritParent,
+        ))
+        .combine(Effect::Bold);
+        for y in 0..printer.size.y {
+            let Some((text, width, index)) = usize::try_from(top + y as isize)
+                .ok()
+                .and_then(|row| rows.get(row))
+            else {
+                continue;
+            };
+            if Some(*index) == current {
+                printer.with_style(playing, |printer| center(printer, y, text, *width));
+            } else {
+                center(printer, y, text, *width);
+            }
+        }
+    }
+
+    fn layout(&mut self, size: Vec2) {
+        self.last_size = size;
+        self.update();
+        if let Some((_, State::Loaded(lyrics))) = &*self.state.read().unwrap() {
+            let max_scroll = rows(lyrics, size.x).len().saturating_sub(size.y);
+            self.scroll = self.scroll.min(max_scroll);
+        }
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
+
+impl ViewExt for LyricsView {
+    fn title(&self) -> String {
+        "Lyrics".to_string()
+    }
+
+    fn title_sub(&self) -> String {
+        match self.queue.get_current() {
+            Some(Playable::Track(track)) => {
+                format!("{} - {}", track.artists.join(", "), track.title)
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::ShowLyrics => Ok(CommandResult::Consumed(None)),
+            // Synced lyrics scroll by themselves.
+            Command::Move(mode, amount) => {
+                let rows = match amount {
+                    MoveAmount::Integer(amount) => amount.unsigned_abs() as usize,
+                    MoveAmount::Float(scale) => (self.last_size.y as f32 * scale) as usize,
+                    MoveAmount::Extreme => usize::MAX,
+                };
+                match mode {
+                    MoveMode::Up => self.scroll = self.scroll.saturating_sub(rows),
+                    MoveMode::Down => self.scroll = self.scroll.saturating_add(rows),
+                    _ => {}
+                }
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
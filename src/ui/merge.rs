@@ -0,0 +1,136 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::view::ViewWrapper;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::playlist_merge::{self, MergeMode};
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::contextmenu::ContextMenu;
+use crate::ui::listview::ListView;
+
+/// A preview of the tracks of several playlists merged into one, which can be written to a new or
+/// an existing playlist.
+pub struct MergeView {
+    merge: Arc<RwLock<Merge>>,
+    tracks: Arc<RwLock<Vec<Playable>>>,
+    list: ListView<Playable>,
+    library: Arc<Library>,
+}
+
+/// The merged playlists and how they are merged.
+struct Merge {
+    playlists: Vec<Playlist>,
+    mode: MergeMode,
+    /// Whether the tracks of the playlists are still being loaded.
+    loading: bool,
+}
+
+impl Merge {
+    fn merged(&self) -> Vec<Playable> {
+        let tracks: Vec<Vec<Playable>> = self
+            .playlists
+            .iter()
+            .map(|playlist| playlist.tracks.clone().unwrap_or_default())
+            .collect();
+        playlist_merge::merge(&tracks, self.mode)
+    }
+}
+
+impl MergeView {
+    /// The preview of `playlists` merged, whose tracks are loaded in the background.
+    pub fn new(queue: Arc<Queue>, library: Arc<Library>, playlists: Vec<Playlist>) -> Self {
+        let merge = Arc::new(RwLock::new(Merge {
+            playlists: playlists.clone(),
+            mode: MergeMode::default(),
+            loading: true,
+        }));
+        let tracks = Arc::new(RwLock::new(Vec::new()));
+
+        {
+            let merge = merge.clone();
+            let tracks = tracks.clone();
+            let library = library.clone();
+            let spotify = queue.get_spotify();
+            queue.get_spotify().api.spawn(move |_| {
+                let mut playlists = playlists;
+                for playlist in playlists.iter_mut() {
+                    playlist.load_tracks(&spotify);
+                }
+                let mut merge = merge.write().unwrap();
+                merge.playlists = playlists;
+                merge.loading = false;
+                *tracks.write().unwrap() = merge.merged();
+                library.trigger_redraw();
+            });
+        }
+
+        Self {
+            merge,
+            tracks: tracks.clone(),
+            list: ListView::new(tracks, queue, library.clone()),
+            library,
+        }
+    }
+
+    fn set_mode(&mut self, mode: MergeMode) {
+        let mut merge = self.merge.write().unwrap();
+        merge.mode = mode;
+        *self.tracks.write().unwrap() = merge.merged();
+    }
+}
+
+impl ViewWrapper for MergeView {
+    wrap_impl!(self.list: ListView<Playable>);
+}
+
+impl ViewExt for MergeView {
+    fn title(&self) -> String {
+        let merge = self.merge.read().unwrap();
+        let names: Vec<&str> = merge
+            .playlists
+            .iter()
+            .map(|playlist| playlist.name.as_str())
+            .collect();
+        format!("Merge: {}", names.join(" + "))
+    }
+
+    fn title_sub(&self) -> String {
+        let merge = self.merge.read().unwrap();
+        if merge.loading {
+            return "Loading tracks...".to_string();
+        }
+        format!(
+            "{} tracks, {}",
+            self.tracks.read().unwrap().len(),
+            merge.mode.label()
+        )
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Merge(mode) => {
+                let next = self.merge.read().unwrap().mode.next();
+                self.set_mode(mode.unwrap_or(next));
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::SaveQueue => {
+                if self.merge.read().unwrap().loading {
+                    return Err("The tracks are still loading.".to_string());
+                }
+                let tracks = self.tracks.read().unwrap().clone();
+                s.add_layer(ContextMenu::save_tracks_dialog(
+                    self.library.clone(),
+                    tracks,
+                ));
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::{Checkbox, Dialog, LinearLayout, NamedView, SliderView, TextView};
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::config::{Config, GainSettings};
+use crate::queue::Queue;
+use crate::traits::ViewExt;
+use crate::ui::modal::Modal;
+
+/// The gain sliders range from -GAIN_RANGE_DB to +GAIN_RANGE_DB in steps of 1 dB.
+const GAIN_RANGE_DB: i32 = 12;
+
+/// Width of the row labels, so the sliders line up.
+const LABEL_WIDTH: usize = 16;
+
+/// A small mixer dialog to adjust the gain staging of the audio pipeline.
+pub struct MixerView {
+    dialog: Modal<Dialog>,
+}
+
+impl MixerView {
+    pub fn new(queue: Arc<Queue>, cfg: Arc<Config>) -> NamedView<Self> {
+        let spotify = queue.get_spotify();
+        let initial_gain = cfg.gain_settings();
+        let mut layout = LinearLayout::vertical();
+
+        let volume_percent = Self::volume_percent(spotify.volume());
+        let volume_slider = {
+            let spotify = spotify.clone();
+            SliderView::horizontal(21)
+                .value(volume_percent / 5)
+                .on_change(move |s, step| {
+                    let percent = step * 5;
+                    spotify.set_volume((u16::MAX as f64 * percent as f64 / 100.0) as u16, true);
+                    s.call_on_name("mixer_volume", |v: &mut TextView| {
+                        v.set_content(format!("{percent}%"))
+                    });
+                })
+        };
+        layout.add_child(Self::row(
+            "Volume",
+            volume_slider,
+            "mixer_volume",
+            format!("{volume_percent}%"),
+        ));
+
+        let pregain_slider = {
+            let cfg = cfg.clone();
+            let applied = initial_gain.clone();
+            Self::gain_slider(initial_gain.volnorm_pregain).on_change(move |s, step| {
+                let db = Self::step_to_db(step);
+                cfg.with_state_mut(|state| state.mixer.volnorm_pregain = Some(db));
+                Self::update_status(s, &cfg, &applied);
+                s.call_on_name("mixer_pregain", |v: &mut TextView| {
+                    v.set_content(Self::format_db(db))
+                });
+            })
+        };
+        layout.add_child(Self::row(
+            "Norm. pregain",
+            pregain_slider,
+            "mixer_pregain",
+            Self::format_db(initial_gain.volnorm_pregain),
+        ));
+
+        let preamp_slider = {
+            let cfg = cfg.clone();
+            let queue = queue.clone();
+            let applied = initial_gain.clone();
+            Self::gain_slider(initial_gain.preamp).on_change(move |s, step| {
+                let db = Self::step_to_db(step);
+                cfg.with_state_mut(|state| state.mixer.preamp = Some(db));
+                // The preamp doesn't need a restart of the player.
+                queue
+                    .get_spotify()
+                    .update_gain(queue.get_current().as_ref());
+                Self::update_status(s, &cfg, &applied);
+                s.call_on_name("mixer_preamp", |v: &mut TextView| {
+                    v.set_content(Self::format_db(db))
+                });
+            })
+        };
+        layout.add_child(Self::row(
+            "Preamp",
+            preamp_slider,
+            "mixer_preamp",
+            Self::format_db(initial_gain.preamp),
+        ));
+
+        let volnorm_checkbox = {
+            let cfg = cfg.clone();
+            let applied = initial_gain.clone();
+            Checkbox::new()
+                .with_checked(initial_gain.volnorm)
+                .on_change(move |s, checked| {
+                    cfg.with_state_mut(|state| state.mixer.volnorm = Some(checked));
+                    Self::update_status(s, &cfg, &applied);
+                })
+        };
+        layout.add_child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Normalisation").fixed_width(LABEL_WIDTH))
+                .child(volnorm_checkbox),
+        );
+
+        let limiter_checkbox = {
+            let cfg = cfg.clone();
+            let applied = initial_gain.clone();
+            Checkbox::new()
+                .with_checked(initial_gain.volnorm_limiter)
+                .on_change(move |s, checked| {
+                    cfg.with_state_mut(|state| state.mixer.volnorm_limiter = Some(checked));
+                    Self::update_status(s, &cfg, &applied);
+                })
+        };
+        layout.add_child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Limiter").fixed_width(LABEL_WIDTH))
+                .child(limiter_checkbox),
+        );
+
+        layout.add_child(TextView::new(""));
+        layout
+            .add_child(TextView::new(Self::status(&initial_gain, false)).with_name("mixer_status"));
+
+        let dialog = Dialog::new()
+            .title("Mixer")
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(layout)
+            .button("Apply", move |s| {
+                s.pop_layer();
+                if cfg.gain_settings().needs_restart(&initial_gain) {
+                    // The gain stages are part of the player configuration, restart it to apply.
+                    queue.get_spotify().restart_player(queue.get_current());
+                }
+            })
+            .dismiss_button("Close");
+
+        Self {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("mixer")
+    }
+
+    fn row(label: &str, slider: SliderView, value_name: &str, value: String) -> LinearLayout {
+        LinearLayout::horizontal()
+            .child(TextView::new(label).fixed_width(LABEL_WIDTH))
+            .child(slider)
+            .child(TextView::new(format!(" {value}")).with_name(value_name))
+    }
+
+    fn gain_slider(db: f64) -> SliderView {
+        let step = (db.round() as i32).clamp(-GAIN_RANGE_DB, GAIN_RANGE_DB) + GAIN_RANGE_DB;
+        SliderView::horizontal((2 * GAIN_RANGE_DB + 1) as usize).value(step as usize)
+    }
+
+    fn step_to_db(step: usize) -> f64 {
+        (step as i32 - GAIN_RANGE_DB) as f64
+    }
+
+    fn format_db(db: f64) -> String {
+        format!("{db:+.0} dB")
+    }
+
+    fn volume_percent(volume: u16) -> usize {
+        (volume as f64 / u16::MAX as f64 * 100.0).round() as usize
+    }
+
+    fn update_status(s: &mut Cursive, cfg: &Config, applied: &GainSettings) {
+        let gain = cfg.gain_settings();
+        s.call_on_name("mixer_status", |v: &mut TextView| {
+            v.set_content(Self::status(&gain, gain.needs_restart(applied)));
+        });
+    }
+
+    /// Describe the limiter and whether the pregain is in effect.
+    fn status(gain: &GainSettings, pending: bool) -> String {
+        let limiter = match (gain.volnorm, gain.volnorm_limiter) {
+            (true, true) => "Limiter: active",
+            (true, false) => "Limiter: off",
+            (false, _) => "Limiter: inactive (normalisation off)",
+        };
+        let gain_stages = if gain.volnorm {
+            format!(
+                "Total gain: {}",
+                Self::format_db(gain.volnorm_pregain + gain.preamp)
+            )
+        } else {
+            format!(
+                "Total gain: {} (pregain requires normalisation)",
+                Self::format_db(gain.preamp)
+            )
+        };
+        let pending = if pending {
+            "\nPress Apply to restart the player with the new settings"
+        } else {
+            ""
+        };
+        format!("{limiter}\n{gain_stages}{pending}")
+    }
+}
+
+impl ViewWrapper for MixerView {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+impl ViewExt for MixerView {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Back => {
+                s.pop_layer();
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Consumed(None)),
+        }
+    }
+}
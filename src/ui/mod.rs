@@ -4,11 +4,21 @@ use ncspot::BIN_NAME;
 pub mod album;
 pub mod artist;
 pub mod browse;
+pub mod caches;
+pub mod compare;
+pub mod confirm;
 pub mod contextmenu;
+pub mod devices;
+pub mod downloads;
+pub mod duplicates;
 pub mod help;
 pub mod layout;
 pub mod library;
+pub mod library_search;
 pub mod listview;
+pub mod lyrics;
+pub mod merge;
+pub mod mixer;
 pub mod modal;
 pub mod pagination;
 pub mod playlist;
@@ -16,9 +26,17 @@ pub mod playlists;
 pub mod queue;
 pub mod search;
 pub mod search_results;
+pub mod setup;
 pub mod show;
+pub mod stats;
 pub mod statusbar;
 pub mod tabbedview;
+pub mod themes;
+pub mod tour;
+pub mod tune_recommendations;
+pub mod unavailable;
+pub mod visualizer;
+pub mod whichkey;
 
 #[cfg(feature = "cover")]
 pub mod cover;
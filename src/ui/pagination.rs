@@ -1,7 +1,14 @@
+use crate::application::ASYNC_RUNTIME;
 use crate::library::Library;
 use crate::traits::ListItem;
 use log::debug;
-use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// The amount of pages fetched ahead of the last page that was requested.
+pub const DEFAULT_READ_AHEAD: u32 = 2;
 
 pub struct ApiPage<I> {
     pub offset: u32,
@@ -9,18 +16,33 @@ pub struct ApiPage<I> {
     pub items: Vec<I>,
 }
 pub type FetchPageFn<I> = dyn Fn(u32) -> Option<ApiPage<I>> + Send + Sync;
+type PrefetchedPages<I> = HashMap<u32, JoinHandle<Option<ApiPage<I>>>>;
 pub struct ApiResult<I> {
     offset: Arc<RwLock<u32>>,
     limit: u32,
     pub total: u32,
     pub items: Arc<RwLock<Vec<I>>>,
     fetch_page: Arc<FetchPageFn<I>>,
+    /// The amount of pages to fetch in the background after each page.
+    read_ahead: u32,
+    /// Pages that are being fetched in the background, by offset. Every offset is fetched at most
+    /// once: it is only added here while it is ahead of the current offset.
+    prefetched: Arc<Mutex<PrefetchedPages<I>>>,
+    /// Cancelled when the result is no longer needed, which aborts the outstanding fetches.
+    cancel: CancellationToken,
 }
 
 impl<I: ListItem + Clone> ApiResult<I> {
-    pub fn new(limit: u32, fetch_page: Arc<FetchPageFn<I>>) -> Self {
+    /// Fetch the first page with `fetch_page`. The requests of `fetch_page` should be aborted once
+    /// `cancel` is cancelled.
+    pub fn new(
+        limit: u32,
+        read_ahead: u32,
+        fetch_page: Arc<FetchPageFn<I>>,
+        cancel: CancellationToken,
+    ) -> Self {
         let items = Arc::new(RwLock::new(Vec::new()));
-        if let Some(first_page) = fetch_page(0) {
+        let result = if let Some(first_page) = fetch_page(0) {
             debug!(
                 "fetched first page, items: {}, total: {}",
                 first_page.items.len(),
@@ -33,6 +55,9 @@ impl<I: ListItem + Clone> ApiResult<I> {
                 total: first_page.total,
                 items,
                 fetch_page: fetch_page.clone(),
+                read_ahead,
+                prefetched: Arc::new(Mutex::new(HashMap::new())),
+                cancel,
             }
         } else {
             Self {
@@ -41,8 +66,13 @@ impl<I: ListItem + Clone> ApiResult<I> {
                 total: 0,
                 items,
                 fetch_page: fetch_page.clone(),
+                read_ahead,
+                prefetched: Arc::new(Mutex::new(HashMap::new())),
+                cancel,
             }
-        }
+        };
+        result.prefetch(&mut result.prefetched.lock().unwrap());
+        result
     }
 
     fn offset(&self) -> u32 {
@@ -56,22 +86,39 @@ impl<I: ListItem + Clone> ApiResult<I> {
     pub fn apply_pagination(self, pagination: &Pagination<I>) {
         let total = self.total as usize;
         let fetched_items = self.items.read().unwrap().len();
+        let cancel = self.cancel.clone();
         pagination.set(
             fetched_items,
             total,
             Box::new(move |_| {
                 self.next();
             }),
-        )
+        );
+        *pagination.cancel.write().unwrap() = Some(cancel);
     }
 
     pub fn next(&self) -> Option<Vec<I>> {
+        // Holding the lock for the whole fetch makes concurrent callers wait for this page instead
+        // of requesting it again.
+        let mut prefetched = self.prefetched.lock().unwrap();
         let offset = self.offset() + self.limit;
+        if self.cancel.is_cancelled() {
+            debug!("not fetching page at offset {offset}, result was cancelled");
+            return None;
+        }
         debug!("fetching next page at offset {offset}");
         if !self.at_end() {
-            if let Some(next_page) = (self.fetch_page)(offset) {
+            let next_page = match prefetched.remove(&offset) {
+                Some(handle) => {
+                    debug!("waiting for prefetched page at offset {offset}");
+                    futures::executor::block_on(handle).ok().flatten()
+                }
+                None => (self.fetch_page)(offset),
+            };
+            if let Some(next_page) = next_page {
                 *self.offset.write().unwrap() = next_page.offset;
                 self.items.write().unwrap().extend(next_page.items.clone());
+                self.prefetch(&mut prefetched);
                 Some(next_page.items)
             } else {
                 None
@@ -81,6 +128,29 @@ impl<I: ListItem + Clone> ApiResult<I> {
             None
         }
     }
+
+    /// Start fetching the pages following the current offset on the async runtime, skipping the
+    /// ones that are already being fetched.
+    fn prefetch(&self, prefetched: &mut PrefetchedPages<I>) {
+        let Some(runtime) = ASYNC_RUNTIME.get() else {
+            return;
+        };
+        if self.cancel.is_cancelled() {
+            return;
+        }
+        let current = self.offset();
+        for page in 1..=self.read_ahead {
+            let offset = current + page * self.limit;
+            if offset >= self.total {
+                break;
+            }
+            prefetched.entry(offset).or_insert_with(|| {
+                debug!("prefetching page at offset {offset}");
+                let fetch_page = self.fetch_page.clone();
+                runtime.spawn_blocking(move || fetch_page(offset))
+            });
+        }
+    }
 }
 
 pub type Paginator<I> = Box<dyn Fn(Arc<RwLock<Vec<I>>>) + Send + Sync>;
@@ -92,12 +162,14 @@ pub type Paginator<I> = Box<dyn Fn(Arc<RwLock<Vec<I>>>) + Send + Sync>;
 /// `max_content`: The maximum amount of items
 /// `callback`: TODO: document
 /// `busy`: TODO: document
+/// `cancel`: Cancels the fetches of the current callback when it is replaced or cleared
 #[derive(Clone)]
 pub struct Pagination<I: ListItem> {
     loaded_content: Arc<RwLock<usize>>,
     max_content: Arc<RwLock<Option<usize>>>,
     callback: Arc<RwLock<Option<Paginator<I>>>>,
     busy: Arc<RwLock<bool>>,
+    cancel: Arc<RwLock<Option<CancellationToken>>>,
 }
 
 impl<I: ListItem> Default for Pagination<I> {
@@ -107,16 +179,29 @@ impl<I: ListItem> Default for Pagination<I> {
             max_content: Arc::new(RwLock::new(None)),
             callback: Arc::new(RwLock::new(None)),
             busy: Arc::new(RwLock::new(false)),
+            cancel: Arc::new(RwLock::new(None)),
+        }
+    }
+}
+
+impl<I: ListItem> Pagination<I> {
+    /// Abort the outstanding fetches of the current callback, e.g. when its view is closed.
+    pub fn cancel(&self) {
+        if let Some(cancel) = self.cancel.write().unwrap().take() {
+            debug!("cancelling pagination");
+            cancel.cancel();
         }
     }
 }
 
 impl<I: ListItem + Clone> Pagination<I> {
     pub fn clear(&mut self) {
+        self.cancel();
         *self.max_content.write().unwrap() = None;
         *self.callback.write().unwrap() = None;
     }
     pub fn set(&self, loaded_content: usize, max_content: usize, callback: Paginator<I>) {
+        self.cancel();
         *self.loaded_content.write().unwrap() = loaded_content;
         *self.max_content.write().unwrap() = Some(max_content);
         *self.callback.write().unwrap() = Some(callback);
@@ -139,7 +224,7 @@ impl<I: ListItem + Clone> Pagination<I> {
         let content = content.clone();
         if !self.is_busy() {
             *self.busy.write().unwrap() = true;
-            std::thread::spawn(move || {
+            let fetch = move || {
                 let cb = pagination.callback.read().unwrap();
                 if let Some(ref cb) = *cb {
                     debug!("calling paginator!");
@@ -148,7 +233,13 @@ impl<I: ListItem + Clone> Pagination<I> {
                     *pagination.busy.write().unwrap() = false;
                     library.trigger_redraw();
                 }
-            });
+            };
+            // Pages are fetched with blocking API calls, keep them off the async worker threads.
+            if let Some(runtime) = ASYNC_RUNTIME.get() {
+                runtime.spawn_blocking(fetch);
+            } else {
+                std::thread::spawn(fetch);
+            }
         }
     }
 }
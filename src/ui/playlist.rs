@@ -1,25 +1,41 @@
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
+use std::thread;
 
-use cursive::Cursive;
 use cursive::view::ViewWrapper;
+use cursive::{Cursive, Vec2, View};
 
-use crate::command::Command;
+use crate::columns::ColumnSet;
+use crate::command::{Command, GroupBy};
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
+use crate::model::grouped_track::GroupedTrack;
 use crate::model::playable::Playable;
 use crate::model::playlist::Playlist;
 use crate::queue::Queue;
+use crate::sorting;
 use crate::spotify::Spotify;
 
-use crate::traits::ViewExt;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::duplicates::DuplicatesView;
+use crate::ui::layout::Layout;
 use crate::ui::listview::ListView;
+use crate::ui::unavailable::UnavailableView;
 
 pub struct PlaylistView {
     playlist: Playlist,
-    list: ListView<Playable>,
+    entries: Arc<RwLock<Vec<GroupedTrack>>>,
+    list: ListView<GroupedTrack>,
     spotify: Spotify,
     library: Arc<Library>,
     queue: Arc<Queue>,
+    followed: Arc<RwLock<Option<bool>>>,
+    /// Only show the tracks added by this user, given by id or display name.
+    contributor: Option<String>,
+    group: Option<GroupBy>,
+    /// The names of the groups whose tracks are hidden.
+    collapsed: HashSet<String>,
 }
 
 impl PlaylistView {
@@ -31,31 +47,135 @@ impl PlaylistView {
             playlist.sort(&order.key, &order.direction);
         }
 
-        let tracks = if let Some(t) = playlist.tracks.as_ref() {
-            t.clone()
-        } else {
-            Vec::new()
-        };
+        let group = library
+            .cfg
+            .state()
+            .playlist_groups
+            .get(&playlist.id)
+            .copied();
 
         let spotify = queue.get_spotify();
-        let list = ListView::new(
-            Arc::new(RwLock::new(tracks)),
-            queue.clone(),
-            library.clone(),
-        );
+        let entries = Arc::new(RwLock::new(Vec::new()));
+        let list = ListView::new(entries.clone(), queue.clone(), library.clone())
+            .with_columns(ColumnSet::Playlist);
+
+        // Playlists owned by the user can't be followed, so only look up foreign ones.
+        let followed = Arc::new(RwLock::new(None));
+        if library.is_followed_playlist(&playlist) {
+            let followed = followed.clone();
+            let playlist = playlist.clone();
+            let library = library.clone();
+            thread::spawn(move || {
+                let is_followed = library.check_followed_playlist(&playlist);
+                *followed.write().unwrap() = Some(is_followed);
+                library.trigger_redraw();
+            });
+        }
 
-        Self {
+        let mut view = Self {
             playlist,
+            entries,
             list,
             spotify,
             library,
             queue,
+            followed,
+            contributor: None,
+            group,
+            collapsed: HashSet::new(),
+        };
+        view.update_list();
+        view
+    }
+
+    fn added_by_contributor(&self, playable: &Playable) -> bool {
+        let Some(contributor) = self.contributor.as_deref() else {
+            return true;
+        };
+        playable.added_by().is_some_and(|id| {
+            id.eq_ignore_ascii_case(contributor)
+                || self.library.user_name(id).eq_ignore_ascii_case(contributor)
+        })
+    }
+
+    /// Rebuild the list from the tracks of the playlist that pass the contributor filter, under
+    /// the headers of their groups if they are grouped.
+    fn update_list(&mut self) {
+        let tracks: Vec<Playable> = self
+            .playlist
+            .tracks
+            .iter()
+            .flatten()
+            .filter(|playable| self.added_by_contributor(playable))
+            .cloned()
+            .collect();
+
+        let track = |playable| GroupedTrack::Track {
+            playable: Box::new(playable),
+            in_group: self.group.is_some(),
+        };
+        let entries = match self.group {
+            None => tracks.into_iter().map(track).collect(),
+            Some(by) => {
+                let mut entries = Vec::new();
+                for (name, tracks) in sorting::group(&tracks, by) {
+                    let collapsed = self.collapsed.contains(&name);
+                    entries.push(GroupedTrack::Header {
+                        tracks: tracks.len(),
+                        duration: tracks.iter().map(Playable::duration).sum(),
+                        collapsed,
+                        name,
+                    });
+                    if !collapsed {
+                        entries.extend(tracks.into_iter().map(track));
+                    }
+                }
+                entries
+            }
+        };
+        *self.entries.write().unwrap() = entries;
+
+        let pos = self.list.get_selected_index();
+        self.list.move_focus_to(pos);
+    }
+
+    fn selected_entry(&self) -> Option<GroupedTrack> {
+        let entries = self.entries.read().unwrap();
+        entries.get(self.list.get_selected_index()).cloned()
+    }
+
+    /// Take over the tracks of the playlist if they were changed elsewhere, e.g. by undoing the
+    /// removal of some of them.
+    fn sync(&mut self) {
+        let updated = self
+            .library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .find(|p| {
+                p.id == self.playlist.id
+                    && p.snapshot_id != self.playlist.snapshot_id
+                    && p.tracks.is_some()
+            })
+            .cloned();
+        if let Some(mut playlist) = updated {
+            if let Some(order) = self.library.cfg.state().playlist_orders.get(&playlist.id) {
+                playlist.sort(&order.key, &order.direction);
+            }
+            self.playlist = playlist;
+            self.update_list();
         }
     }
 }
 
 impl ViewWrapper for PlaylistView {
-    wrap_impl!(self.list: ListView<Playable>);
+    wrap_impl!(self.list: ListView<GroupedTrack>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.sync();
+        self.list.layout(size);
+    }
 }
 
 impl ViewExt for PlaylistView {
@@ -67,31 +187,121 @@ impl ViewExt for PlaylistView {
         if let Some(tracks) = self.playlist.tracks.as_ref() {
             let duration_secs = tracks.iter().map(|p| p.duration() as u64 / 1000).sum();
             let duration = std::time::Duration::from_secs(duration_secs);
+            let followed = match *self.followed.read().unwrap() {
+                Some(true) => ", followed ✓",
+                Some(false) => ", not followed",
+                None => "",
+            };
             format!(
-                "{} tracks, {}",
+                "{} tracks, {}{}",
                 tracks.len(),
-                crate::utils::format_duration(&duration)
-            )
+                crate::utils::format_duration(&duration),
+                followed
+            ) + self
+                .contributor
+                .as_ref()
+                .map(|name| format!(", added by {name}"))
+                .unwrap_or_default()
+                .as_str()
         } else {
             "".to_string()
         }
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        vec![HelpContext::List, HelpContext::Playlist]
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
+    fn url(&self) -> Option<String> {
+        self.playlist.share_url()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        if let Some(GroupedTrack::Header { name, .. }) = self.selected_entry()
+            && let Command::Play | Command::Open(_) = cmd
+        {
+            if !self.collapsed.remove(&name) {
+                self.collapsed.insert(name);
+            }
+            self.update_list();
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Delete = cmd
+            && !self.list.marked_indexes().is_empty()
+        {
+            let marked: Vec<Playable> = {
+                let entries = self.entries.read().unwrap();
+                self.list
+                    .marked_indexes()
+                    .into_iter()
+                    .filter_map(|index| entries.get(index)?.playable().cloned())
+                    .collect()
+            };
+            self.list.clear_marks();
+            let deleted = self
+                .playlist
+                .delete_tracks(&marked, &self.spotify, &self.library);
+            self.update_list();
+            return if deleted {
+                Ok(CommandResult::Consumed(None))
+            } else {
+                Err("Could not delete all tracks.".to_string())
+            };
+        }
+
         if let Command::Delete = cmd {
-            let pos = self.list.get_selected_index();
+            // The list may be filtered or grouped, so find the track in the playlist.
+            let Some(selected) = self.selected_entry().and_then(|e| e.playable().cloned()) else {
+                return Err("Select a track to delete".to_string());
+            };
+            let index = self
+                .playlist
+                .tracks
+                .iter()
+                .flatten()
+                .position(|playable| playable.list_index() == selected.list_index())
+                .ok_or("No track selected")?;
 
             return if self
                 .playlist
-                .delete_track(pos, self.spotify.clone(), &self.library)
+                .delete_track(index, self.spotify.clone(), &self.library)
             {
-                self.list.remove(pos);
+                self.update_list();
                 Ok(CommandResult::Consumed(None))
             } else {
                 Err("Could not delete track.".to_string())
             };
         }
 
+        if let Command::Duplicates = cmd {
+            let view = DuplicatesView::for_playlist(
+                self.queue.clone(),
+                self.library.clone(),
+                &self.playlist,
+            );
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Unavailable = cmd {
+            let view = UnavailableView::for_playlist(
+                self.queue.clone(),
+                self.library.clone(),
+                &self.playlist,
+            );
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            return Ok(CommandResult::Consumed(None));
+        }
+
         if let Command::Sort(key, direction) = cmd {
             self.library.cfg.with_state_mut(|state| {
                 let order = crate::config::SortingOrder {
@@ -104,12 +314,29 @@ impl ViewExt for PlaylistView {
             });
 
             self.playlist.sort(key, direction);
-            let tracks = self.playlist.tracks.as_ref().unwrap_or(&Vec::new()).clone();
-            self.list = ListView::new(
-                Arc::new(RwLock::new(tracks)),
-                self.queue.clone(),
-                self.library.clone(),
-            );
+            self.update_list();
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Contributor(name) = cmd {
+            self.contributor = name.clone();
+            self.update_list();
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Group(by) = cmd {
+            self.library.cfg.with_state_mut(|state| match by {
+                Some(by) => {
+                    state.playlist_groups.insert(self.playlist.id.clone(), *by);
+                }
+                None => {
+                    state.playlist_groups.remove(&self.playlist.id);
+                }
+            });
+
+            self.group = *by;
+            self.collapsed.clear();
+            self.update_list();
             return Ok(CommandResult::Consumed(None));
         }
 
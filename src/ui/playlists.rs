@@ -1,55 +1,151 @@
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
 
-use cursive::Cursive;
-use cursive::view::{Margins, ViewWrapper};
-use cursive::views::Dialog;
+use cursive::view::ViewWrapper;
+use cursive::{Cursive, Vec2, View};
 
-use crate::command::Command;
+use crate::command::{Command, ShiftMode};
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::playlist::Playlist;
+use crate::model::playlist_folder::PlaylistEntry;
+use crate::playlist_folders::{self, PlaylistFolder, Row};
 use crate::queue::Queue;
 use crate::traits::ViewExt;
+use crate::ui::confirm;
+use crate::ui::contextmenu::ContextMenu;
+use crate::ui::layout::Layout;
 use crate::ui::listview::ListView;
-use crate::ui::modal::Modal;
+use crate::ui::unavailable::UnavailableView;
 
 pub struct PlaylistsView {
-    list: ListView<Playlist>,
+    list: ListView<PlaylistEntry>,
+    entries: Arc<RwLock<Vec<PlaylistEntry>>>,
+    /// What the entries are made of, in the same order.
+    rows: Vec<Row>,
+    /// A hash of the playlists and folders the entries were made from.
+    fingerprint: Option<u64>,
     library: Arc<Library>,
+    queue: Arc<Queue>,
 }
 
 impl PlaylistsView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
-        Self {
-            list: ListView::new(library.playlists.clone(), queue, library.clone()),
+        let entries = Arc::new(RwLock::new(Vec::new()));
+        let mut view = Self {
+            list: ListView::new(entries.clone(), queue.clone(), library.clone()),
+            entries,
+            rows: Vec::new(),
+            fingerprint: None,
             library,
-        }
+            queue,
+        };
+        view.refresh();
+        view
     }
 
-    pub fn delete_dialog(&mut self) -> Option<Modal<Dialog>> {
+    /// Rebuild the entries if the playlists of the library or the folders changed.
+    fn refresh(&mut self) {
         let playlists = self.library.playlists.read().unwrap();
-        let current = playlists.get(self.list.get_selected_index());
-
-        if let Some(playlist) = current {
-            let library = self.library.clone();
-            let id = playlist.id.clone();
-            let dialog = Dialog::text("Are you sure you want to delete this playlist?")
-                .padding(Margins::lrtb(1, 1, 1, 0))
-                .title("Delete playlist")
-                .dismiss_button("No")
-                .button("Yes", move |s: &mut Cursive| {
-                    library.delete_playlist(&id);
-                    s.pop_layer();
-                });
-            Some(Modal::new(dialog))
-        } else {
-            None
+        let folders = self.library.cfg.state().playlist_folders.clone();
+
+        let mut hasher = DefaultHasher::new();
+        folders.hash(&mut hasher);
+        for playlist in playlists.iter() {
+            playlist.id.hash(&mut hasher);
+            playlist.name.hash(&mut hasher);
+            playlist.owner_name.hash(&mut hasher);
+            playlist.snapshot_id.hash(&mut hasher);
+            playlist.num_tracks.hash(&mut hasher);
+        }
+        let fingerprint = Some(hasher.finish());
+        if fingerprint == self.fingerprint {
+            return;
+        }
+
+        let ids: Vec<&str> = playlists.iter().map(|p| p.id.as_str()).collect();
+        self.rows = playlist_folders::rows(&ids, &folders);
+        *self.entries.write().unwrap() = self
+            .rows
+            .iter()
+            .map(|row| match *row {
+                Row::Folder(index) => PlaylistEntry::Folder {
+                    name: folders[index].name.clone(),
+                    playlists: folders[index].playlists.len(),
+                    collapsed: folders[index].collapsed,
+                },
+                Row::Playlist { index, folder } => PlaylistEntry::Playlist {
+                    playlist: Box::new(playlists[index].clone()),
+                    in_folder: folder.is_some(),
+                },
+            })
+            .collect();
+        self.fingerprint = fingerprint;
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows.get(self.list.get_selected_index()).copied()
+    }
+
+    fn selected_playlist(&self) -> Option<Playlist> {
+        match self.selected_row()? {
+            Row::Playlist { index, .. } => {
+                self.library.playlists.read().unwrap().get(index).cloned()
+            }
+            Row::Folder(_) => None,
+        }
+    }
+
+    /// Change the folders with `change`, and rebuild the entries.
+    fn update_folders<T>(&mut self, change: impl FnOnce(&mut Vec<PlaylistFolder>) -> T) -> T {
+        let mut folders = self.library.cfg.state().playlist_folders.clone();
+        let result = change(&mut folders);
+        self.library
+            .cfg
+            .with_state_mut(|state| state.playlist_folders = folders.clone());
+        self.refresh();
+        result
+    }
+
+    /// Move the selected folder, or the selected playlist inside its folder, and keep it selected.
+    fn shift(&mut self, mode: &ShiftMode, amount: i32) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        let id = self.selected_playlist().map(|playlist| playlist.id);
+        let up = matches!(mode, ShiftMode::Up) != amount.is_negative();
+        let mut row = row;
+        for _ in 0..amount.unsigned_abs() {
+            if !self
+                .update_folders(|folders| playlist_folders::shift(folders, row, id.as_deref(), up))
+            {
+                break;
+            }
+            if let Row::Folder(index) = row {
+                row = Row::Folder(if up { index - 1 } else { index + 1 });
+            }
+        }
+
+        let position = self.rows.iter().position(|r| match (*r, row) {
+            (Row::Folder(a), Row::Folder(b)) => a == b,
+            (Row::Playlist { index: a, .. }, Row::Playlist { index: b, .. }) => a == b,
+            _ => false,
+        });
+        if let Some(position) = position {
+            self.list.move_focus_to(position);
         }
     }
 }
 
 impl ViewWrapper for PlaylistsView {
-    wrap_impl!(self.list: ListView<Playlist>);
+    wrap_impl!(self.list: ListView<PlaylistEntry>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.refresh();
+        self.list.layout(size);
+    }
 }
 
 impl ViewExt for PlaylistsView {
@@ -57,9 +153,81 @@ impl ViewExt for PlaylistsView {
         "Playlists".to_string()
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        vec![HelpContext::List, HelpContext::Playlists]
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        self.refresh();
+
+        if let Some(Row::Folder(index)) = self.selected_row() {
+            match cmd {
+                Command::Play | Command::Open(_) => {
+                    self.update_folders(|folders| {
+                        folders[index].collapsed = !folders[index].collapsed;
+                    });
+                    return Ok(CommandResult::Consumed(None));
+                }
+                // Removing a folder keeps its playlists.
+                Command::Delete => {
+                    self.update_folders(|folders| folders.remove(index));
+                    return Ok(CommandResult::Consumed(None));
+                }
+                _ => {}
+            }
+        }
+
+        match cmd {
+            Command::NewFolder(name) => {
+                self.update_folders(|folders| playlist_folders::create(folders, name))?;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::MoveToFolder(name) => {
+                let playlist = self
+                    .selected_playlist()
+                    .ok_or("Select a playlist to move")?;
+                self.update_folders(|folders| {
+                    playlist_folders::move_playlist(folders, &playlist.id, name.as_deref())
+                })?;
+                return Ok(CommandResult::Consumed(None));
+            }
+            Command::Shift(mode, amount) => {
+                self.shift(mode, amount.unwrap_or(1));
+                return Ok(CommandResult::Consumed(None));
+            }
+            // The order of the playlists comes from the library and the folders.
+            Command::Sort(_, _) => return Ok(CommandResult::Ignored),
+            _ => {}
+        }
+
+        if let Command::Unavailable = cmd {
+            let view = UnavailableView::for_playlists(self.queue.clone(), self.library.clone());
+            s.call_on_name("main", move |v: &mut Layout| v.push_view(Box::new(view)));
+            return Ok(CommandResult::Consumed(None));
+        }
+
         if let Command::Delete = cmd {
-            if let Some(dialog) = self.delete_dialog() {
+            if let Some(playlist) = self.selected_playlist() {
+                confirm::delete_playlist(s, self.library.clone(), playlist);
+            }
+            return Ok(CommandResult::Consumed(None));
+        }
+
+        if let Command::Merge(_) = cmd {
+            if let Some(playlist) = self.selected_playlist() {
+                let dialog = ContextMenu::merge_playlists_dialog(
+                    self.queue.clone(),
+                    self.library.clone(),
+                    &playlist,
+                );
                 s.add_layer(dialog);
             }
             return Ok(CommandResult::Consumed(None));
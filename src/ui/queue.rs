@@ -1,19 +1,19 @@
 use cursive::Cursive;
-use cursive::traits::{Nameable, Resizable};
-use cursive::view::{Margins, ViewWrapper};
-use cursive::views::{Dialog, EditView, ScrollView, SelectView};
+use cursive::view::ViewWrapper;
 
 use std::cmp::min;
 use std::sync::Arc;
 
+use crate::columns::ColumnSet;
 use crate::command::{Command, MoveMode, ShiftMode};
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::playable::Playable;
 use crate::queue::Queue;
 use crate::traits::ViewExt;
+use crate::ui::contextmenu::ContextMenu;
 use crate::ui::listview::ListView;
-use crate::ui::modal::Modal;
 
 use super::listview::MouseHandleResult;
 
@@ -25,7 +25,8 @@ pub struct QueueView {
 
 impl QueueView {
     pub fn new(queue: Arc<Queue>, library: Arc<Library>) -> Self {
-        let list = ListView::new(queue.queue.clone(), queue.clone(), library.clone());
+        let list = ListView::new(queue.queue.clone(), queue.clone(), library.clone())
+            .with_columns(ColumnSet::Queue);
 
         Self {
             list,
@@ -33,57 +34,6 @@ impl QueueView {
             queue,
         }
     }
-
-    fn save_dialog_cb(
-        s: &mut Cursive,
-        queue: Arc<Queue>,
-        library: Arc<Library>,
-        id: Option<String>,
-    ) {
-        let tracks = queue.queue.read().unwrap().clone();
-        match id {
-            Some(id) => {
-                library.overwrite_playlist(&id, &tracks);
-                s.pop_layer();
-            }
-            None => {
-                s.pop_layer();
-                let edit = EditView::new()
-                    .on_submit(move |s: &mut Cursive, name| {
-                        library.save_playlist(name, &tracks);
-                        s.pop_layer();
-                    })
-                    .with_name("name")
-                    .fixed_width(20);
-                let dialog = Dialog::new()
-                    .title("Enter name")
-                    .dismiss_button("Cancel")
-                    .padding(Margins::lrtb(1, 1, 1, 0))
-                    .content(edit);
-                s.add_layer(Modal::new(dialog));
-            }
-        }
-    }
-
-    fn save_dialog(queue: Arc<Queue>, library: Arc<Library>) -> Modal<Dialog> {
-        let mut list_select: SelectView<Option<String>> = SelectView::new().autojump();
-        list_select.add_item("[Create new]", None);
-
-        for list in library.playlists.read().unwrap().iter() {
-            list_select.add_item(list.name.clone(), Some(list.id.clone()));
-        }
-
-        list_select.set_on_submit(move |s, selected| {
-            Self::save_dialog_cb(s, queue.clone(), library.clone(), selected.clone())
-        });
-
-        let dialog = Dialog::new()
-            .title("Create new or overwrite existing playlist?")
-            .dismiss_button("Cancel")
-            .padding(Margins::lrtb(1, 1, 1, 0))
-            .content(ScrollView::new(list_select));
-        Modal::new(dialog)
-    }
 }
 
 impl ViewWrapper for QueueView {
@@ -134,18 +84,42 @@ impl ViewExt for QueueView {
         }
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        vec![HelpContext::List, HelpContext::Queue]
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Play => {
                 self.queue.play(self.list.get_selected_index(), true, false);
                 return Ok(CommandResult::Consumed(None));
             }
+            // Sorting would change the order of playback behind the back of the queue.
+            Command::Sort(_, _) => return Ok(CommandResult::Ignored),
             Command::PlayNext => {
                 return Ok(CommandResult::Ignored);
             }
             Command::Queue => {
                 return Ok(CommandResult::Ignored);
             }
+            Command::Delete if !self.list.marked_indexes().is_empty() => {
+                // Remove from the end, so the indexes of the remaining tracks stay valid.
+                let mut marked = self.list.marked_indexes();
+                marked.sort_unstable_by(|a, b| b.cmp(a));
+                for index in marked {
+                    self.queue.remove(index);
+                }
+                self.list.clear_marks();
+                return Ok(CommandResult::Consumed(None));
+            }
             Command::Delete => {
                 let selected = self.list.get_selected_index();
                 let len = self.queue.len();
@@ -156,6 +130,9 @@ impl ViewExt for QueueView {
                 }
                 return Ok(CommandResult::Consumed(None));
             }
+            Command::Shift(_, _) if self.list.is_filtered() => {
+                return Err("Clear the filter to reorder the queue".to_string());
+            }
             Command::Shift(mode, amount) => {
                 let amount = match amount {
                     Some(amount) => *amount,
@@ -190,7 +167,8 @@ impl ViewExt for QueueView {
                 }
             }
             Command::SaveQueue => {
-                let dialog = Self::save_dialog(self.queue.clone(), self.library.clone());
+                let tracks = self.queue.queue.read().unwrap().clone();
+                let dialog = ContextMenu::save_tracks_dialog(self.library.clone(), tracks);
                 s.add_layer(dialog);
                 return Ok(CommandResult::Consumed(None));
             }
@@ -20,6 +20,7 @@ use crate::model::playlist::Playlist;
 use crate::model::show::Show;
 use crate::model::track::Track;
 use crate::queue::Queue;
+use crate::search_filter::{apply_completion, complete_filter};
 use crate::spotify::{Spotify, UriType};
 use crate::traits::{ListItem, ViewExt};
 use crate::ui::layout::Layout;
@@ -58,6 +59,20 @@ impl SearchView {
         }
     }
 
+    /// Complete the filter keyword at the end of the search bar. Returns whether there was
+    /// something to complete.
+    fn complete(&mut self) -> bool {
+        let mut edit = self.edit.get_mut();
+        let content = edit.get_content();
+        let candidates = complete_filter(&content);
+        let completed = apply_completion(&content, &candidates);
+        if completed == *content {
+            return false;
+        }
+        edit.set_content(completed);
+        true
+    }
+
     pub fn clear(&mut self) {
         self.edit
             .call_on(&Selector::Name(EDIT_ID), |v: &mut EditView| {
@@ -73,6 +88,22 @@ impl View for SearchView {
             .cropped((printer.size.x, 1))
             .focused(self.edit_focused);
         self.edit.draw(printer);
+
+        // Hint the filter keywords that Tab would complete, on the right of the search bar.
+        let candidates = if self.edit_focused {
+            self.edit
+                .with_view(|edit| complete_filter(&edit.get_content()))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        if !candidates.is_empty() {
+            let hint = format!(" Tab: {} ", candidates.join(" "));
+            let width = hint.chars().count();
+            if width < printer.size.x / 2 {
+                printer.print((printer.size.x - width, 0), &hint);
+            }
+        }
     }
 
     fn layout(&mut self, size: Vec2) {
@@ -81,6 +112,9 @@ impl View for SearchView {
 
     fn on_event(&mut self, event: Event) -> EventResult {
         if event == Event::Key(Key::Tab) {
+            if self.edit_focused && self.complete() {
+                return EventResult::Consumed(None);
+            }
             self.edit_focused = !self.edit_focused;
             return EventResult::Consumed(None);
         } else if self.edit_focused && event == Event::Key(Key::Esc) {
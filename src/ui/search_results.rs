@@ -1,7 +1,9 @@
 use crate::application::ASYNC_RUNTIME;
+use crate::columns::ColumnSet;
 use crate::command::Command;
 use crate::commands::CommandResult;
 use crate::events::EventManager;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::album::Album;
 use crate::model::artist::Artist;
@@ -58,7 +60,8 @@ impl SearchResultsView {
         let results_shows = Arc::new(RwLock::new(Vec::new()));
         let results_episodes = Arc::new(RwLock::new(Vec::new()));
 
-        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone());
+        let list_tracks = ListView::new(results_tracks.clone(), queue.clone(), library.clone())
+            .with_columns(ColumnSet::Search);
         let pagination_tracks = list_tracks.get_pagination().clone();
         let list_albums = ListView::new(results_albums.clone(), queue.clone(), library.clone());
         let pagination_albums = list_albums.get_pagination().clone();
@@ -362,7 +365,7 @@ impl SearchResultsView {
         let ev = self.events.clone();
         let paginator = paginator.cloned();
 
-        std::thread::spawn(move || {
+        self.spotify.api.spawn(move |_| {
             let total_items = handler(&spotify, &results, &query, 0, false) as usize;
 
             // register paginator if the API has more than one page of results
@@ -563,6 +566,18 @@ impl ViewExt for SearchResultsView {
     fn title(&self) -> String {
         format!("Search: {}", self.search_term)
     }
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.tabs.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.tabs.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.tabs.set_position(position);
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.tabs.on_command(s, cmd)
     }
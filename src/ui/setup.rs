@@ -0,0 +1,222 @@
+//! The setup that is shown on the first start, to enter the credentials of the Spotify application
+//! ncspot logs in with.
+
+use std::thread;
+
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, LinearLayout, SelectView, TextView};
+use cursive::{Cursive, View};
+use librespot_playback::audio_backend;
+
+use crate::client_config::{ClientConfig, DEFAULT_PORT};
+use crate::config::Config;
+use crate::instance;
+use crate::ui::create_cursive;
+
+const DASHBOARD_URL: &str = "https://developer.spotify.com/dashboard";
+
+/// The width of the labels in front of the fields.
+const LABEL_WIDTH: usize = 16;
+
+/// Ask for the client configuration, test it and save it. Returns an error if the user quits.
+pub fn run(config: &Config) -> Result<(), String> {
+    let mut cursive = create_cursive().map_err(|e| e.to_string())?;
+    cursive.set_theme(config.build_theme());
+
+    let backend = config.values().backend.clone();
+    cursive.add_layer(dialog(backend));
+    cursive.run();
+
+    // The chosen audio backend is left as user data once the client configuration was saved.
+    let Some(backend) = cursive.take_user_data::<String>() else {
+        return Err("Setup cancelled".to_string());
+    };
+    drop(cursive);
+    if !backend.is_empty() {
+        config.set_backend(&backend).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn dialog(backend: Option<String>) -> impl View {
+    let introduction = format!(
+        "ncspot logs in with your own Spotify application. Create one at {DASHBOARD_URL}, add the \
+         redirect URI below to it and enter its Client ID and Client Secret here.\n"
+    );
+
+    let mut backends = SelectView::new().popup();
+    for (name, _) in audio_backend::BACKENDS {
+        backends.add_item_str(*name);
+    }
+    if let Some(index) = backend.and_then(|backend| {
+        audio_backend::BACKENDS
+            .iter()
+            .position(|(name, _)| *name == backend)
+    }) {
+        backends.set_selection(index);
+    }
+
+    let form = LinearLayout::vertical()
+        .child(TextView::new(introduction))
+        .child(field(
+            "Client ID",
+            "client_id",
+            EditView::new(),
+            validate_key,
+        ))
+        .child(field(
+            "Client Secret",
+            "client_secret",
+            EditView::new().secret(),
+            validate_key,
+        ))
+        .child(field(
+            "Port",
+            "port",
+            EditView::new().content(DEFAULT_PORT.to_string()),
+            validate_port,
+        ))
+        .child(TextView::new(redirect_uri_line(DEFAULT_PORT)).with_name("redirect_uri"))
+        .child(field(
+            "Device name",
+            "device_name",
+            EditView::new().content(instance::name()),
+            |_| Ok(()),
+        ))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Audio backend").fixed_width(LABEL_WIDTH))
+                .child(backends.with_name("backend")),
+        )
+        .child(TextView::new("\n").with_name("status"));
+
+    let mut dialog = Dialog::around(form)
+        .title("ncspot setup")
+        .button("Test connection", |s| test_connection(s, false))
+        .button("Save", |s| test_connection(s, true))
+        .button("Quit", |s| s.quit());
+    dialog.set_focus(cursive::views::DialogFocus::Content);
+    dialog.max_width(80)
+}
+
+/// A labelled field named `name`, with a line below that shows the error of `validate` while
+/// typing.
+fn field(
+    label: &str,
+    name: &str,
+    edit: EditView,
+    validate: fn(&str) -> Result<(), String>,
+) -> LinearLayout {
+    let error_name = format!("{name}_error");
+    let port_changed = name == "port";
+    let edit = edit.on_edit(move |s, content, _| {
+        let error = validate(content).err().unwrap_or_default();
+        s.call_on_name(&error_name, |view: &mut TextView| view.set_content(error));
+        if port_changed {
+            update_redirect_uri(s, content);
+        }
+    });
+    LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new(label).fixed_width(LABEL_WIDTH))
+                .child(edit.with_name(name).full_width()),
+        )
+        .child(TextView::new("").with_name(format!("{name}_error")))
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    ClientConfig::validate_client_key(key)
+}
+
+fn validate_port(port: &str) -> Result<(), String> {
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err("Enter a port between 1 and 65535".to_string()),
+    }
+}
+
+fn redirect_uri_line(port: u16) -> String {
+    let redirect_uri = ClientConfig::new().get_redirect_uri_for(port);
+    format!("{:<LABEL_WIDTH$}{redirect_uri}\n", "Redirect URI")
+}
+
+fn update_redirect_uri(s: &mut Cursive, port: &str) {
+    let text = port.parse().map(redirect_uri_line).unwrap_or_default();
+    s.call_on_name("redirect_uri", |view: &mut TextView| view.set_content(text));
+}
+
+/// Read the fields into a client configuration and the audio backend, or return the first error.
+fn read_fields(s: &mut Cursive) -> Result<(ClientConfig, String), String> {
+    let mut text = |name: &str| {
+        s.call_on_name(name, |view: &mut EditView| {
+            view.get_content().trim().to_string()
+        })
+        .unwrap_or_default()
+    };
+    let client_id = text("client_id");
+    let client_secret = text("client_secret");
+    let port = text("port");
+    let device_name = text("device_name");
+
+    validate_key(&client_id).map_err(|e| format!("Client ID: {e}"))?;
+    validate_key(&client_secret).map_err(|e| format!("Client Secret: {e}"))?;
+    validate_port(&port).map_err(|e| format!("Port: {e}"))?;
+
+    let backend = s
+        .call_on_name("backend", |view: &mut SelectView| view.selection())
+        .flatten()
+        .map(|backend| backend.to_string())
+        .unwrap_or_default();
+    let config = ClientConfig {
+        client_id,
+        client_secret,
+        port: port.parse().ok(),
+        device_name: Some(device_name).filter(|name| !name.is_empty()),
+        ..ClientConfig::new()
+    };
+    Ok((config, backend))
+}
+
+/// Check the credentials with Spotify in the background, and save them and close the setup
+/// afterwards if `save` is set.
+fn test_connection(s: &mut Cursive, save: bool) {
+    let (config, backend) = match read_fields(s) {
+        Ok(fields) => fields,
+        Err(e) => return set_status(s, &e),
+    };
+    set_status(s, "Checking the credentials with Spotify...");
+
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        let result = ClientConfig::verify_credentials(&config.client_id, &config.client_secret);
+        sink.send(Box::new(move |s: &mut Cursive| {
+            let checked = match result {
+                Ok(checked) => checked,
+                Err(e) => return set_status(s, &e),
+            };
+            if !save {
+                return set_status(
+                    s,
+                    if checked {
+                        "The credentials are valid."
+                    } else {
+                        "Could not reach Spotify to check the credentials."
+                    },
+                );
+            }
+            if let Err(e) = config.save() {
+                return set_status(s, &e);
+            }
+            s.set_user_data(backend);
+            s.quit();
+        }))
+        .ok();
+    });
+}
+
+fn set_status(s: &mut Cursive, status: &str) {
+    s.call_on_name("status", |view: &mut TextView| {
+        view.set_content(format!("\n{status}"))
+    });
+}
@@ -5,11 +5,12 @@ use cursive::view::ViewWrapper;
 
 use crate::command::Command;
 use crate::commands::CommandResult;
+use crate::help::HelpContext;
 use crate::library::Library;
 use crate::model::episode::Episode;
 use crate::model::show::Show;
 use crate::queue::Queue;
-use crate::traits::ViewExt;
+use crate::traits::{ListItem, ViewExt};
 use crate::ui::listview::ListView;
 
 pub struct ShowView {
@@ -43,6 +44,22 @@ impl ViewExt for ShowView {
         self.show.name.clone()
     }
 
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.list.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
+    fn url(&self) -> Option<String> {
+        self.show.share_url()
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         self.list.on_command(s, cmd)
     }
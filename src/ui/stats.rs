@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use cursive::Cursive;
+use cursive::theme::Effect;
+use cursive::utils::markup::StyledString;
+use cursive::view::ViewWrapper;
+use cursive::views::{ScrollView, TextView};
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::config::Config;
+use crate::data_usage::{self, DataUsage};
+use crate::play_history::{self, Play};
+use crate::traits::ViewExt;
+use crate::utils::{format_duration, format_size};
+
+/// How many artists and tracks are listed for every period.
+const TOP: usize = 5;
+/// How many days the listening time is shown for.
+const DAYS: u32 = 14;
+/// The width of the bar of the day with the longest listening time.
+const BAR_WIDTH: u64 = 40;
+
+/// Statistics about the usage of ncspot and the played tracks.
+pub struct StatsView {
+    view: ScrollView<TextView>,
+}
+
+/// The timestamp of the start of `date` in the local time zone.
+fn start_of(date: NaiveDate) -> i64 {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|date| date.timestamp())
+        .unwrap_or_default()
+}
+
+/// The most played artists and tracks of every period, and the listening time of the last days.
+fn play_history(cfg: &Config) -> StyledString {
+    let today = Local::now().date_naive();
+    let week = today - chrono::Days::new(today.weekday().num_days_from_monday().into());
+    let month = today.with_day(1).unwrap_or(today);
+    let year = today.with_ordinal(1).unwrap_or(today);
+    let days = today - chrono::Days::new((DAYS - 1).into());
+
+    let since = start_of(year.min(days));
+    let plays = play_history::load(cfg.storage().as_ref(), since);
+    let mut text = StyledString::styled("Play history\n", Effect::Bold);
+    if plays.is_empty() {
+        text.append("\nNo tracks were played this year.\n");
+        return text;
+    }
+
+    for (period, start) in [
+        ("this week", week),
+        ("this month", month),
+        ("this year", year),
+    ] {
+        let start = start_of(start);
+        let plays: Vec<Play> = plays
+            .iter()
+            .filter(|play| play.started >= start)
+            .cloned()
+            .collect();
+        let lists = [
+            ("artists", play_history::top_artists(&plays, TOP)),
+            ("tracks", play_history::top_tracks(&plays, TOP)),
+        ];
+        for (kind, top) in lists {
+            text.append_styled(format!("\nMost played {kind} {period}\n"), Effect::Italic);
+            if top.is_empty() {
+                text.append("  None yet\n");
+            }
+            for (name, count) in top {
+                text.append(format!("{count:>5}  {name}\n"));
+            }
+        }
+    }
+
+    text.append_styled("\nListening time per day\n", Effect::Italic);
+    let per_day = play_history::time_per_day(&plays, DAYS, today);
+    let longest = per_day
+        .iter()
+        .map(|(_, time)| time.as_secs())
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    for (day, time) in per_day {
+        let bar = "█".repeat((time.as_secs() * BAR_WIDTH / longest) as usize);
+        let time = format_duration(&Duration::from_secs(time.as_secs() / 60 * 60));
+        text.append(format!("{}  {bar} {time}\n", day.format("%a %d %b")));
+    }
+    text.append_styled(
+        "\nTracks count as played once half of them or four minutes were played. Export the \
+         whole history with `history export`.\n\n",
+        Effect::Italic,
+    );
+    text
+}
+
+impl StatsView {
+    pub fn new(cfg: Arc<Config>) -> Self {
+        let mut text = play_history(&cfg);
+        text.append_styled("Data usage\n\n", Effect::Bold);
+        let session = data_usage::session();
+        let total = data_usage::total();
+
+        let row = |label: &str, value: fn(&DataUsage) -> u64| {
+            format!(
+                "{label:<16}{:>14}{:>14}\n",
+                format_size(value(&session)),
+                format_size(value(&total))
+            )
+        };
+        text.append_styled(
+            format!("{:<16}{:>14}{:>14}\n", "", "This session", "All sessions"),
+            Effect::Italic,
+        );
+        text.append(row("Streamed audio", |usage| usage.streamed));
+        text.append(row("Web API", |usage| usage.api));
+        text.append(row("Total", |usage| usage.streamed + usage.api));
+        text.append_styled(
+            "\nStreamed audio is estimated from the bitrate and doesn't include tracks played from \
+             the audio cache.\n",
+            Effect::Italic,
+        );
+
+        Self {
+            view: ScrollView::new(TextView::new(text)),
+        }
+    }
+}
+
+impl ViewWrapper for StatsView {
+    wrap_impl!(self.view: ScrollView<TextView>);
+}
+
+impl ViewExt for StatsView {
+    fn title(&self) -> String {
+        "Stats".to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::ShowStats => Ok(CommandResult::Consumed(None)),
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 use cursive::Printer;
 use cursive::align::HAlign;
@@ -6,19 +6,91 @@ use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
 use cursive::theme::{ColorStyle, ColorType, PaletteColor};
 use cursive::traits::View;
 use cursive::vec::Vec2;
+use regex::Regex;
 use unicode_width::UnicodeWidthStr;
 
+use crate::chapters;
 use crate::library::Library;
 use crate::model::playable::Playable;
-use crate::queue::{Queue, RepeatSetting};
+use crate::queue::{AbLoop, Queue, RepeatSetting};
 use crate::spotify::{PlayerEvent, Spotify};
+use crate::spotify_api::OfflineMode;
 use crate::utils::ms_to_hms;
 
+/// The parts of the status bar unless others are configured, which look like this:
+/// ` ▶  Artist - Title              [R] 1:23 / 4:56 [80%]`
+const DEFAULT_LEFT: &str = " %state %track";
+const DEFAULT_RIGHT: &str = "%flags{%elapsed / %duration}{ [%volume%]}";
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%[a-z_]+").unwrap());
+
+/// What is being dragged with the mouse.
+#[derive(Clone, Copy)]
+enum Drag {
+    /// The progress bar, to seek to the position in milliseconds when the button is released.
+    Seek(u32),
+    /// The volume, which is set right away.
+    Volume,
+}
+
+/// A part of an expanded status bar template, which can be left out if it is a section.
+#[derive(Debug, PartialEq)]
+struct Part {
+    text: String,
+    section: bool,
+}
+
+/// Expand the placeholders of `template` with `value`. Sections in braces are left out when they
+/// have placeholders and all of them are empty.
+fn expand(template: &str, value: &dyn Fn(&str) -> String) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut section = false;
+    for (index, piece) in template.split(['{', '}']).enumerate() {
+        // Every other piece is inside braces.
+        if index > 0 {
+            section = !section;
+        }
+        let mut empty = true;
+        let mut placeholders = 0;
+        let text = PLACEHOLDER.replace_all(piece, |captures: &regex::Captures| {
+            let expanded = value(&captures[0]);
+            placeholders += 1;
+            empty &= expanded.is_empty();
+            expanded
+        });
+        let hidden = section && placeholders > 0 && empty;
+        if !hidden && !text.is_empty() {
+            parts.push(Part {
+                text: text.into_owned(),
+                section,
+            });
+        }
+    }
+    parts
+}
+
+/// Join the `left` and `right` parts, leaving out the last sections until both fit in `width` with
+/// some space between them. Sections on the right go first, as the track is on the left usually.
+fn fit(mut left: Vec<Part>, mut right: Vec<Part>, width: usize) -> (String, String) {
+    let text = |parts: &[Part]| parts.iter().map(|p| p.text.as_str()).collect::<String>();
+    while text(&left).width() + text(&right).width() + 1 > width {
+        if let Some(index) = right.iter().rposition(|p| p.section) {
+            right.remove(index);
+        } else if let Some(index) = left.iter().rposition(|p| p.section) {
+            left.remove(index);
+        } else {
+            break;
+        }
+    }
+    (text(&left), text(&right))
+}
+
 pub struct StatusBar {
     queue: Arc<Queue>,
     spotify: Spotify,
     library: Arc<Library>,
     last_size: Vec2,
+    drag: Option<Drag>,
 }
 
 impl StatusBar {
@@ -30,9 +102,27 @@ impl StatusBar {
             spotify,
             library,
             last_size: Vec2::new(0, 0),
+            drag: None,
         }
     }
 
+    /// The position in the current item at `x` on the progress bar.
+    fn seek_position(&self, x: usize) -> Option<u32> {
+        let playable = self.queue.get_current()?;
+        let fraction = x.min(self.last_size.x) as f32 / self.last_size.x as f32;
+        Some((playable.duration() as f32 * fraction) as u32)
+    }
+
+    /// Set the volume from `x` on the volume display, which goes from silent on its left to the
+    /// full volume on its right.
+    fn set_volume_at(&self, x: usize) {
+        let volume_len = self.volume_display().len();
+        let start = self.last_size.x.saturating_sub(volume_len);
+        let fraction = ((x + 1).saturating_sub(start) as f32 / volume_len as f32).min(1.0);
+        self.spotify
+            .set_volume((fraction * u16::MAX as f32) as u16, true);
+    }
+
     fn use_nerdfont(&self) -> bool {
         self.library.cfg.values().use_nerdfont.unwrap_or(false)
     }
@@ -64,11 +154,12 @@ impl StatusBar {
         }
     }
 
+    fn volume_percent(&self) -> u16 {
+        (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
+    }
+
     fn volume_display(&self) -> String {
-        format!(
-            " [{}%]",
-            (self.spotify.volume() as f64 / 65535_f64 * 100.0).round() as u16
-        )
+        format!(" [{}%]", self.volume_percent())
     }
 
     fn format_track(&self, t: &Playable) -> String {
@@ -119,10 +210,6 @@ impl View for StatusBar {
             );
         });
 
-        printer.with_color(style, |printer| {
-            printer.print((1, 1), self.playback_indicator());
-        });
-
         let updating = if !*self.library.is_done.read().unwrap() {
             if self.use_nerdfont() {
                 "\u{f04e6} "
@@ -157,7 +244,21 @@ impl View for StatusBar {
             ""
         };
 
-        let volume = self.volume_display();
+        let ab_loop = match self.queue.get_ab_loop() {
+            Some(AbLoop { end: None, .. }) => "[A-] ",
+            Some(_) => "[A-B] ",
+            None => "",
+        };
+
+        let autoplay = if self.queue.get_autoplay() {
+            if self.use_nerdfont() {
+                "\u{f06e4} "
+            } else {
+                "[A] "
+            }
+        } else {
+            ""
+        };
 
         printer.with_color(style_bar_bg, |printer| {
             printer.print((0, 0), &"┉".repeat(printer.size.x));
@@ -168,34 +269,112 @@ impl View for StatusBar {
 
         let formatted_elapsed = ms_to_hms(elapsed.as_millis().try_into().unwrap_or(0));
 
-        let playback_duration_status = match self.queue.get_current() {
-            Some(ref t) => format!("{} / {}", formatted_elapsed, t.duration_str()),
-            None => "".to_string(),
+        let seek_preview = match self.drag {
+            Some(Drag::Seek(position)) => Some(position),
+            _ => self.spotify.seek_preview(),
+        };
+        let markers = match self.queue.get_current() {
+            Some(t) => chapters::markers(self.library.cfg.storage().as_ref(), &t),
+            None => Vec::new(),
+        };
+        let current = self.queue.get_current();
+        let value = |placeholder: &str| -> String {
+            match placeholder {
+                "%state" => self.playback_indicator().to_string(),
+                "%track" => match &current {
+                    Some(t) => match chapters::current(&markers, elapsed_ms) {
+                        Some(chapter) => format!("{} · {}", self.format_track(t), chapter.title),
+                        None => self.format_track(t),
+                    },
+                    None => String::new(),
+                },
+                "%flags" => [updating, repeat, ab_loop, shuffle, autoplay].concat(),
+                "%updating" => updating.trim_end().to_string(),
+                "%repeat" => repeat.trim_end().to_string(),
+                "%abloop" => ab_loop.trim_end().to_string(),
+                "%shuffle" => shuffle.trim_end().to_string(),
+                "%autoplay" => autoplay.trim_end().to_string(),
+                "%elapsed" => match (&current, seek_preview) {
+                    (Some(_), Some(target)) => {
+                        format!("{formatted_elapsed} → {}", ms_to_hms(target))
+                    }
+                    (Some(_), None) => formatted_elapsed.clone(),
+                    (None, _) => String::new(),
+                },
+                "%volume" => self.volume_percent().to_string(),
+                "%position" => current
+                    .as_ref()
+                    .and_then(|_| self.queue.get_current_index())
+                    .map(|index| (index + 1).to_string())
+                    .unwrap_or_default(),
+                "%queue_length" => self.queue.len().to_string(),
+                // Everything else is a placeholder of the playing track.
+                _ => current
+                    .as_ref()
+                    .map(|t| Playable::format(t, placeholder, &self.library))
+                    .unwrap_or_default(),
+            }
         };
 
-        let right = updating.to_string()
-            + repeat
-            + shuffle
-            // + saved
-            + &playback_duration_status
-            + &volume;
+        let (left, right) = {
+            let values = self.library.cfg.values();
+            let layout = values.statusbar.clone().unwrap_or_default();
+            (
+                expand(layout.left.as_deref().unwrap_or(DEFAULT_LEFT), &value),
+                expand(layout.right.as_deref().unwrap_or(DEFAULT_RIGHT), &value),
+            )
+        };
+        let (left, right) = fit(left, right, printer.size.x);
         let offset = HAlign::Right.get_offset(right.width(), printer.size.x);
 
         printer.with_color(style, |printer| {
-            if let Some(ref t) = self.queue.get_current() {
-                printer.print((4, 1), &self.format_track(t));
-            }
+            printer.print((0, 1), &left);
             printer.print((offset, 1), &right);
         });
 
-        if let Some(t) = self.queue.get_current() {
+        // The offline banner replaces the progress bar.
+        if let Some(mode) = self.spotify.api.offline_mode() {
+            let style_offline = ColorStyle::new(
+                ColorType::Color(*printer.theme.palette.custom("error").unwrap()),
+                ColorType::Color(*printer.theme.palette.custom("error_bg").unwrap()),
+            );
+            let banner = match mode {
+                OfflineMode::Manual => "Offline, showing cached data",
+                OfflineMode::Detected { .. } => "Network unreachable, showing cached data",
+            };
+            printer.with_color(style_offline, |printer| {
+                printer.print_hline((0, 0), printer.size.x, " ");
+                let offset = HAlign::Center.get_offset(banner.width(), printer.size.x);
+                printer.print((offset, 0), banner);
+            });
+        } else if let Some(t) = self.queue.get_current() {
             printer.with_color(style_bar, |printer| {
                 let duration_width = elapsed_ms
                     .checked_mul(printer.size.x as u32)
                     .and_then(|v| v.checked_div(t.duration()))
                     .unwrap_or(0) as usize;
                 printer.print((0, 0), &"━".repeat(duration_width + 1));
+                // Where a pending seek leads to.
+                if let Some(target) = seek_preview {
+                    let target_x = (target as u64 * printer.size.x as u64)
+                        .checked_div(t.duration() as u64)
+                        .unwrap_or(0) as usize;
+                    printer.print((target_x.min(printer.size.x - 1), 0), "◆");
+                }
             });
+            for marker in &markers {
+                let x = (marker.position as u64 * printer.size.x as u64)
+                    .checked_div(t.duration() as u64)
+                    .unwrap_or(0) as usize;
+                let style = if marker.position <= elapsed_ms {
+                    style_bar
+                } else {
+                    style_bar_bg
+                };
+                printer.with_color(style, |printer| {
+                    printer.print((x.min(printer.size.x - 1), 0), "┃");
+                });
+            }
         }
     }
 
@@ -214,9 +393,28 @@ impl View for StatusBar {
             event,
         } = event
         {
-            let position = position - offset;
+            let position = position.saturating_sub(offset);
             let volume_len = self.volume_display().len();
 
+            match (event, self.drag) {
+                (MouseEvent::Hold(MouseButton::Left), Some(Drag::Seek(_))) => {
+                    self.drag = self.seek_position(position.x).map(Drag::Seek);
+                    return EventResult::Consumed(None);
+                }
+                (MouseEvent::Hold(MouseButton::Left), Some(Drag::Volume)) => {
+                    self.set_volume_at(position.x);
+                    return EventResult::Consumed(None);
+                }
+                (MouseEvent::Release(MouseButton::Left), Some(drag)) => {
+                    if let Drag::Seek(target) = drag {
+                        self.spotify.seek(target);
+                    }
+                    self.drag = None;
+                    return EventResult::Consumed(None);
+                }
+                _ => {}
+            }
+
             if position.y == 0 {
                 if event == MouseEvent::WheelUp {
                     self.spotify.seek_relative(-500);
@@ -226,12 +424,9 @@ impl View for StatusBar {
                     self.spotify.seek_relative(500);
                 }
 
-                if event == MouseEvent::Press(MouseButton::Left)
-                    && let Some(playable) = self.queue.get_current()
-                {
-                    let f: f32 = position.x as f32 / self.last_size.x as f32;
-                    let new = playable.duration() as f32 * f;
-                    self.spotify.seek(new as u32);
+                // The seek happens when the button is released, after dragging to the position.
+                if event == MouseEvent::Press(MouseButton::Left) {
+                    self.drag = self.seek_position(position.x).map(Drag::Seek);
                 }
             } else if self.last_size.x - position.x < volume_len {
                 if event == MouseEvent::WheelUp {
@@ -251,6 +446,11 @@ impl View for StatusBar {
 
                     self.spotify.set_volume(volume, true);
                 }
+
+                if event == MouseEvent::Press(MouseButton::Left) {
+                    self.drag = Some(Drag::Volume);
+                    self.set_volume_at(position.x);
+                }
             } else if event == MouseEvent::Press(MouseButton::Left) {
                 self.queue.toggleplayback();
             }
@@ -261,3 +461,31 @@ impl View for StatusBar {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let value = |placeholder: &str| match placeholder {
+            "%state" => "▶".to_string(),
+            "%volume" => "80".to_string(),
+            _ => String::new(),
+        };
+        let parts = expand("%state{ %track}{ [%volume%]}{ fixed}", &value);
+        let texts: Vec<&str> = parts.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, ["▶", " [80%]", " fixed"]);
+        assert!(!parts[0].section && parts[1].section);
+
+        let left = || expand("%state{ left}", &value);
+        let right = || expand("{right }{[%volume%]}", &value);
+        assert_eq!(
+            fit(left(), right(), 40),
+            ("▶ left".into(), "right [80%]".into())
+        );
+        assert_eq!(fit(left(), right(), 16), ("▶ left".into(), "right ".into()));
+        assert_eq!(fit(left(), right(), 8), ("▶ left".into(), "".into()));
+        assert_eq!(fit(left(), right(), 2), ("▶".into(), "".into()));
+    }
+}
@@ -4,7 +4,7 @@ use cursive::{
     Cursive, Printer, Vec2, View,
     align::HAlign,
     event::{Event, EventResult, MouseButton, MouseEvent},
-    theme::ColorStyle,
+    theme::{ColorStyle, ColorType},
     view::Nameable,
     views::NamedView,
 };
@@ -13,6 +13,7 @@ use unicode_width::UnicodeWidthStr;
 use crate::{
     command::{Command, MoveAmount, MoveMode},
     commands::CommandResult,
+    help::HelpContext,
     traits::{BoxedViewExt, IntoBoxedViewExt, ViewExt},
 };
 
@@ -49,6 +50,11 @@ impl TabbedView {
         self.tab_mut(self.selected)
     }
 
+    /// Return a reference to the selected tab, or None if there is no selected tab currently.
+    pub fn selected_tab(&self) -> Option<&NamedView<BoxedViewExt>> {
+        self.tabs.get(self.selected)
+    }
+
     /// Return the amount of tabs in this view.
     pub fn len(&self) -> usize {
         self.tabs.len()
@@ -59,6 +65,11 @@ impl TabbedView {
         self.len() == 0
     }
 
+    /// Return the index of the currently visible tab.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
     /// Set the tab at `index` as currently visible.
     pub fn set_selected(&mut self, index: usize) {
         self.selected = min(self.len().saturating_sub(1), index);
@@ -117,11 +128,16 @@ impl View for TabbedView {
 
         let tabwidth = self.tab_width();
         for (i, tab) in self.tabs.iter().enumerate() {
-            let style = if self.selected == i {
-                ColorStyle::highlight()
+            let palette = &printer.theme.palette;
+            let (front, back) = if self.selected == i {
+                ("tab_active", "tab_active_bg")
             } else {
-                ColorStyle::primary()
+                ("tab", "tab_bg")
             };
+            let style = ColorStyle::new(
+                ColorType::Color(*palette.custom(front).unwrap()),
+                ColorType::Color(*palette.custom(back).unwrap()),
+            );
 
             let mut width = tabwidth;
             if i == self.tabs.len() - 1 {
@@ -187,6 +203,22 @@ impl View for TabbedView {
 }
 
 impl ViewExt for TabbedView {
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.selected_tab()
+            .map(|tab| tab.help_contexts())
+            .unwrap_or_default()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.selected_tab().and_then(|tab| tab.position())
+    }
+
+    fn set_position(&mut self, position: usize) {
+        if let Some(tab) = self.selected_tab_mut() {
+            tab.set_position(position);
+        }
+    }
+
     fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
         match cmd {
             Command::Move(mode, amount) if matches!(mode, MoveMode::Left | MoveMode::Right) => {
@@ -0,0 +1,66 @@
+//! Picking a theme while ncspot runs.
+
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::theme::Theme;
+use cursive::traits::Resizable;
+use cursive::view::Margins;
+use cursive::views::{Dialog, OnEventView, ScrollView, SelectView};
+use log::warn;
+
+use crate::config::Config;
+use crate::theme;
+use crate::themes;
+use crate::ui::modal::Modal;
+
+/// A dialog listing the theme files. The selected theme is shown right away as a preview, and is
+/// kept when it is picked. Cancelling goes back to the theme from before.
+pub fn picker(cfg: Arc<Config>) -> Modal<OnEventView<Dialog>> {
+    let current = cfg.state().theme.clone();
+    let label = |name: &str, selected: bool| format!("{} {name}", if selected { "●" } else { " " });
+
+    let mut select: SelectView<Option<(String, Theme)>> = SelectView::new();
+    select.add_item(label("Configured theme", current.is_none()), None);
+    let mut selection = 0;
+    for name in themes::names() {
+        // Broken theme files are left out, switching to them by name shows the error.
+        let theme = match themes::load(&name) {
+            Ok(theme) => theme::load(&Some(theme)),
+            Err(e) => {
+                warn!("{e}");
+                continue;
+            }
+        };
+        let selected = current.as_ref() == Some(&name);
+        if selected {
+            selection = select.len();
+        }
+        select.add_item(label(&name, selected), Some((name, theme)));
+    }
+    let mut select = select.selected(selection);
+
+    let preview_cfg = cfg.clone();
+    select.set_on_select(move |s, item| match item {
+        Some((_, theme)) => s.set_theme(theme.clone()),
+        None => s.set_theme(preview_cfg.configured_theme()),
+    });
+    let submit_cfg = cfg.clone();
+    select.set_on_submit(move |s, item: &Option<(String, Theme)>| {
+        s.pop_layer();
+        let name = item.as_ref().map(|(name, _)| name.clone());
+        submit_cfg.with_state_mut(|state| state.theme.clone_from(&name));
+        s.set_theme(submit_cfg.build_theme());
+    });
+
+    let cancel = move |s: &mut Cursive| {
+        s.pop_layer();
+        s.set_theme(cfg.build_theme());
+    };
+    let dialog = Dialog::around(ScrollView::new(select).max_height(20))
+        .padding(Margins::lrtb(1, 1, 1, 0))
+        .title("Theme")
+        .button("Cancel", cancel.clone());
+    Modal::new(OnEventView::new(dialog).on_event(Key::Esc, cancel))
+}
@@ -0,0 +1,105 @@
+//! A short tour through the interface that is shown once after the first login.
+
+use std::sync::Arc;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use crate::config::{self, Config};
+use crate::ext_traits::CursiveExt;
+use crate::ui::layout::Area;
+use crate::ui::modal::Modal;
+
+struct Step {
+    title: &'static str,
+    text: String,
+    /// The screen to switch to, or `None` to stay on the current one.
+    screen: Option<&'static str>,
+    /// The part of the interface the step is about.
+    area: Area,
+}
+
+fn steps(command_key: char) -> Vec<Step> {
+    vec![
+        Step {
+            title: "Screens",
+            text: "ncspot is split into screens, the current one is shown at the top. Switch \
+                   between them with F1 (queue), F2 (search) and F3 (library)."
+                .into(),
+            screen: Some("library"),
+            area: Area::Title,
+        },
+        Step {
+            title: "Search",
+            text: "Type into the search field and press Enter to search Spotify. Move through the \
+                   results with the arrow keys and press Enter to play one."
+                .into(),
+            screen: Some("search"),
+            area: Area::Input,
+        },
+        Step {
+            title: "Queueing",
+            text: "Space adds the selected item to the queue and . plays it right after the \
+                   current track. The status bar shows what is playing."
+                .into(),
+            screen: Some("queue"),
+            area: Area::StatusBar,
+        },
+        Step {
+            title: "Command line",
+            text: format!(
+                "Press {command_key} to enter commands like \"search <query>\" or \"quit\". \
+                 Press ? for a list of all keybindings."
+            ),
+            screen: None,
+            area: Area::CommandLine,
+        },
+    ]
+}
+
+/// Start the tour, unless the user has already finished or skipped it.
+pub fn show_once(s: &mut Cursive, config: Arc<Config>) {
+    if !config.state().tour_completed {
+        show_step(s, config, 0);
+    }
+}
+
+fn show_step(s: &mut Cursive, config: Arc<Config>, index: usize) {
+    let command_key = config
+        .values()
+        .command_key
+        .unwrap_or(config::DEFAULT_COMMAND_KEY);
+    let mut steps = steps(command_key);
+    let count = steps.len();
+    if index >= count {
+        finish(s, &config);
+        return;
+    }
+    let step = steps.swap_remove(index);
+
+    s.on_layout(|_, mut layout| {
+        if let Some(screen) = step.screen {
+            layout.set_screen(screen);
+        }
+        layout.set_highlight(Some(step.area));
+    });
+
+    let next_config = config.clone();
+    let dialog = Dialog::text(step.text)
+        .title(format!("{} ({}/{count})", step.title, index + 1))
+        .button(if index + 1 < count { "Next" } else { "Done" }, move |s| {
+            s.pop_layer();
+            show_step(s, next_config.clone(), index + 1);
+        })
+        .button("Skip tour", move |s| {
+            s.pop_layer();
+            finish(s, &config);
+        });
+    s.add_layer(Modal::new(dialog));
+}
+
+fn finish(s: &mut Cursive, config: &Config) {
+    s.on_layout(|_, mut layout| layout.set_highlight(None));
+    config.with_state_mut(|state| state.tour_completed = true);
+    config.save_state();
+}
@@ -0,0 +1,264 @@
+use std::sync::{Arc, RwLock};
+
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::{Dialog, EditView, LinearLayout, NamedView, TextView};
+use rspotify::model::RecommendationsAttribute;
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::library::Library;
+use crate::queue::{Queue, RadioSeed};
+use crate::traits::{IntoBoxedViewExt, ListItem, ViewExt};
+use crate::ui::layout::Layout;
+use crate::ui::listview::ListView;
+use crate::ui::modal::Modal;
+
+/// Width of the field labels, so the inputs line up.
+const LABEL_WIDTH: usize = 24;
+
+/// The audio attributes that can be tuned in the dialog.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    MinTempo,
+    MaxTempo,
+    Energy,
+    Valence,
+    MinPopularity,
+}
+
+const FIELDS: [Field; 5] = [
+    Field::MinTempo,
+    Field::MaxTempo,
+    Field::Energy,
+    Field::Valence,
+    Field::MinPopularity,
+];
+
+impl Field {
+    fn label(self) -> &'static str {
+        match self {
+            Self::MinTempo => "Min. tempo (BPM)",
+            Self::MaxTempo => "Max. tempo (BPM)",
+            Self::Energy => "Energy (0-100)",
+            Self::Valence => "Mood (0-100)",
+            Self::MinPopularity => "Min. popularity (0-100)",
+        }
+    }
+
+    fn view_name(self) -> &'static str {
+        match self {
+            Self::MinTempo => "tune_min_tempo",
+            Self::MaxTempo => "tune_max_tempo",
+            Self::Energy => "tune_energy",
+            Self::Valence => "tune_valence",
+            Self::MinPopularity => "tune_min_popularity",
+        }
+    }
+
+    fn max(self) -> f32 {
+        match self {
+            Self::MinTempo | Self::MaxTempo => 300.0,
+            _ => 100.0,
+        }
+    }
+
+    /// Parse the user input for this field. Empty input leaves the attribute unset.
+    fn parse(self, input: &str) -> Result<Option<RecommendationsAttribute>, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let value: f32 = input
+            .parse()
+            .map_err(|_| format!("{}: \"{input}\" is not a number", self.label()))?;
+        if !(0.0..=self.max()).contains(&value) {
+            return Err(format!(
+                "{}: must be between 0 and {}",
+                self.label(),
+                self.max()
+            ));
+        }
+
+        Ok(Some(match self {
+            Self::MinTempo => RecommendationsAttribute::MinTempo(value),
+            Self::MaxTempo => RecommendationsAttribute::MaxTempo(value),
+            Self::Energy => RecommendationsAttribute::TargetEnergy(value / 100.0),
+            Self::Valence => RecommendationsAttribute::TargetValence(value / 100.0),
+            Self::MinPopularity => RecommendationsAttribute::MinPopularity(value.round() as i32),
+        }))
+    }
+}
+
+/// Turn the inputs of all fields into recommendation attributes.
+fn parse_attributes(inputs: &[(Field, String)]) -> Result<Vec<RecommendationsAttribute>, String> {
+    let mut attributes = Vec::new();
+    let (mut min_tempo, mut max_tempo) = (None, None);
+
+    for (field, input) in inputs {
+        if let Some(attribute) = field.parse(input)? {
+            match attribute {
+                RecommendationsAttribute::MinTempo(tempo) => min_tempo = Some(tempo),
+                RecommendationsAttribute::MaxTempo(tempo) => max_tempo = Some(tempo),
+                _ => (),
+            }
+            attributes.push(attribute);
+        }
+    }
+
+    if let (Some(min), Some(max)) = (min_tempo, max_tempo)
+        && min > max
+    {
+        return Err("The minimum tempo is above the maximum tempo".to_string());
+    }
+
+    Ok(attributes)
+}
+
+/// A dialog to request recommendations for an item, tuned by audio attributes like tempo and
+/// energy.
+pub struct TuneRecommendationsDialog {
+    dialog: Modal<Dialog>,
+}
+
+impl TuneRecommendationsDialog {
+    pub fn new(
+        item: &dyn ListItem,
+        seed: RadioSeed,
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+    ) -> NamedView<Self> {
+        let mut layout = LinearLayout::vertical();
+        for field in FIELDS {
+            layout.add_child(
+                LinearLayout::horizontal()
+                    .child(TextView::new(field.label()).fixed_width(LABEL_WIDTH))
+                    .child(EditView::new().with_name(field.view_name()).fixed_width(8)),
+            );
+        }
+        layout.add_child(TextView::new(""));
+        layout.add_child(
+            TextView::new("Leave a field empty to not restrict it").with_name("tune_status"),
+        );
+
+        let title = item.display_left(&library);
+        let radio_seed = seed.clone();
+        let radio_queue = queue.clone();
+        let dialog = Dialog::new()
+            .title(format!("Tune recommendations for {title}"))
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .content(layout)
+            .button("Start radio", move |s| {
+                let Some(seed) = Self::tuned_seed(s, &radio_seed) else {
+                    return;
+                };
+                match radio_queue.start_radio(seed) {
+                    Ok(_) => {
+                        s.pop_layer();
+                    }
+                    Err(e) => Self::set_status(s, &e),
+                }
+            })
+            .button("Show tracks", move |s| {
+                let Some(seed) = Self::tuned_seed(s, &seed) else {
+                    return;
+                };
+                let Some(tracks) = queue.fetch_recommendations(&seed) else {
+                    Self::set_status(s, "Could not fetch recommendations");
+                    return;
+                };
+                s.pop_layer();
+                let view = ListView::new(
+                    Arc::new(RwLock::new(tracks)),
+                    queue.clone(),
+                    library.clone(),
+                )
+                .with_title(&format!("Tuned recommendations for {title}"))
+                .into_boxed_view_ext();
+                s.call_on_name("main", move |v: &mut Layout| v.push_view(view));
+            })
+            .dismiss_button("Close");
+
+        Self {
+            dialog: Modal::new_ext(dialog),
+        }
+        .with_name("tunerecommendations")
+    }
+
+    /// Read the inputs and add them to `seed`, or show what's wrong with them.
+    fn tuned_seed(s: &mut Cursive, seed: &RadioSeed) -> Option<RadioSeed> {
+        let inputs: Vec<(Field, String)> = FIELDS
+            .iter()
+            .map(|&field| {
+                let input = s
+                    .call_on_name(field.view_name(), |v: &mut EditView| v.get_content())
+                    .map(|content| content.to_string())
+                    .unwrap_or_default();
+                (field, input)
+            })
+            .collect();
+
+        match parse_attributes(&inputs) {
+            Ok(attributes) => Some(RadioSeed {
+                attributes,
+                ..seed.clone()
+            }),
+            Err(e) => {
+                Self::set_status(s, &e);
+                None
+            }
+        }
+    }
+
+    fn set_status(s: &mut Cursive, status: &str) {
+        s.call_on_name("tune_status", |v: &mut TextView| v.set_content(status));
+    }
+}
+
+impl ViewWrapper for TuneRecommendationsDialog {
+    wrap_impl!(self.dialog: Modal<Dialog>);
+}
+
+impl ViewExt for TuneRecommendationsDialog {
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::Back => {
+                s.pop_layer();
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Consumed(None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(values: [&str; 5]) -> Vec<(Field, String)> {
+        FIELDS
+            .iter()
+            .zip(values)
+            .map(|(&field, value)| (field, value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        assert_eq!(parse_attributes(&inputs(["", "", "", "", ""])), Ok(vec![]));
+        assert_eq!(
+            parse_attributes(&inputs(["120", "130", "80", "", " 50 "])),
+            Ok(vec![
+                RecommendationsAttribute::MinTempo(120.0),
+                RecommendationsAttribute::MaxTempo(130.0),
+                RecommendationsAttribute::TargetEnergy(0.8),
+                RecommendationsAttribute::MinPopularity(50),
+            ])
+        );
+        assert!(parse_attributes(&inputs(["fast", "", "", "", ""])).is_err());
+        assert!(parse_attributes(&inputs(["", "", "120", "", ""])).is_err());
+        assert!(parse_attributes(&inputs(["140", "120", "", "", ""])).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use cursive::Cursive;
+use cursive::view::{Margins, ViewWrapper};
+use cursive::views::{Dialog, ScrollView, TextView};
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::help::HelpContext;
+use crate::library::Library;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+use crate::queue::Queue;
+use crate::relink;
+use crate::traits::{ListItem, ViewExt};
+use crate::ui::layout::Layout;
+use crate::ui::listview::ListView;
+use crate::ui::modal::Modal;
+
+/// An unavailable track of a playlist, and the version that can replace it if one was found.
+struct Entry {
+    playlist_id: String,
+    track: Playable,
+    replacement: Option<Track>,
+}
+
+/// The tracks of one or more playlists that can't be played in the user's market, with playable
+/// versions to replace them. Deleting an entry keeps the track, running `unavailable` again
+/// replaces all listed tracks that have a replacement.
+pub struct UnavailableView {
+    name: String,
+    /// Opened from the playlist itself, which has to be reopened after changing it.
+    from_playlist: bool,
+    playlists: Arc<RwLock<HashMap<String, Playlist>>>,
+    entries: Arc<RwLock<Vec<Entry>>>,
+    scanning: Arc<AtomicBool>,
+    tracks: Arc<RwLock<Vec<Playable>>>,
+    list: ListView<Playable>,
+    queue: Arc<Queue>,
+    library: Arc<Library>,
+}
+
+impl UnavailableView {
+    pub fn for_playlist(queue: Arc<Queue>, library: Arc<Library>, playlist: &Playlist) -> Self {
+        let name = playlist.name.clone();
+        Self::new(queue, library, vec![playlist.clone()], name, true)
+    }
+
+    /// Scan all playlists the user can change.
+    pub fn for_playlists(queue: Arc<Queue>, library: Arc<Library>) -> Self {
+        let playlists = library
+            .playlists
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|playlist| playlist.collaborative || !library.is_followed_playlist(playlist))
+            .cloned()
+            .collect();
+        Self::new(queue, library, playlists, "Playlists".to_string(), false)
+    }
+
+    fn new(
+        queue: Arc<Queue>,
+        library: Arc<Library>,
+        playlists: Vec<Playlist>,
+        name: String,
+        from_playlist: bool,
+    ) -> Self {
+        let tracks = Arc::new(RwLock::new(Vec::new()));
+        let view = Self {
+            name,
+            from_playlist,
+            playlists: Arc::new(RwLock::new(HashMap::new())),
+            entries: Arc::new(RwLock::new(Vec::new())),
+            scanning: Arc::new(AtomicBool::new(true)),
+            tracks: tracks.clone(),
+            list: ListView::new(tracks, queue.clone(), library.clone()),
+            queue,
+            library,
+        };
+        view.scan(playlists);
+        view
+    }
+
+    /// Look for unavailable tracks and their replacements in the background, as this takes a few
+    /// requests for every playlist and unavailable track.
+    fn scan(&self, mut playlists: Vec<Playlist>) {
+        let spotify = self.queue.get_spotify();
+        let library = self.library.clone();
+        let found = self.playlists.clone();
+        let entries = self.entries.clone();
+        let tracks = self.tracks.clone();
+        let scanning = self.scanning.clone();
+        thread::spawn(move || {
+            let mut scanned = Vec::new();
+            for playlist in playlists.iter_mut() {
+                playlist.load_tracks(&spotify);
+                let unavailable = playlist
+                    .tracks
+                    .iter()
+                    .flatten()
+                    .filter(|playable| relink::is_unavailable(playable));
+                for playable in unavailable {
+                    let replacement = match playable {
+                        Playable::Track(track) => relink::find_replacement(&spotify, track),
+                        Playable::Episode(_) => None,
+                    };
+                    scanned.push(Entry {
+                        playlist_id: playlist.id.clone(),
+                        track: playable.clone(),
+                        replacement,
+                    });
+                }
+            }
+
+            *tracks.write().unwrap() = scanned.iter().map(|entry| entry.track.clone()).collect();
+            *entries.write().unwrap() = scanned;
+            *found.write().unwrap() = playlists
+                .into_iter()
+                .map(|playlist| (playlist.id.clone(), playlist))
+                .collect();
+            scanning.store(false, Ordering::SeqCst);
+            library.trigger_redraw();
+        });
+    }
+
+    /// Ask before replacing the listed tracks that have a replacement.
+    fn replace_dialog(&self) -> Result<Modal<Dialog>, String> {
+        let entries = self.entries.read().unwrap();
+        let swaps: Vec<(String, Playable, Track)> = entries
+            .iter()
+            .filter_map(|entry| {
+                let replacement = entry.replacement.clone()?;
+                Some((entry.playlist_id.clone(), entry.track.clone(), replacement))
+            })
+            .collect();
+        if swaps.is_empty() {
+            return Err("No replacements were found.".to_string());
+        }
+
+        let text = swaps
+            .iter()
+            .map(|(_, track, replacement)| {
+                format!(
+                    "{track}\n  → {} ({})",
+                    replacement.title,
+                    replacement.album.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let playlists = self.playlists.clone();
+        let queue = self.queue.clone();
+        let library = self.library.clone();
+        let from_playlist = self.from_playlist;
+        let dialog = Dialog::around(ScrollView::new(TextView::new(text)))
+            .padding(Margins::lrtb(1, 1, 1, 0))
+            .title(format!("Replace {} unavailable tracks", swaps.len()))
+            .dismiss_button("Cancel")
+            .button("Replace", move |s| {
+                s.pop_layer();
+                let cb_sink = s.cb_sink().clone();
+                let (swaps, playlists) = (swaps.clone(), playlists.clone());
+                let (queue, library) = (queue.clone(), library.clone());
+                let replacing_library = library.clone();
+                queue.get_spotify().api.spawn_with_callback(
+                    move |api| {
+                        let mut playlists = playlists.write().unwrap();
+                        let failed = swaps
+                            .iter()
+                            .filter(|(playlist_id, track, replacement)| {
+                                !playlists.get_mut(playlist_id).is_some_and(|playlist| {
+                                    playlist.replace_track(
+                                        track,
+                                        Playable::Track(replacement.clone()),
+                                        api,
+                                        &replacing_library,
+                                    )
+                                })
+                            })
+                            .count();
+                        let playlist = playlists.values().next().cloned();
+                        (failed, playlist.filter(|_| from_playlist))
+                    },
+                    move |(failed, playlist)| {
+                        cb_sink
+                            .send(Box::new(move |s| {
+                                // The playlist below still shows the unavailable tracks, so it's
+                                // reopened.
+                                let reopened =
+                                    playlist.and_then(|playlist| playlist.open(queue, library));
+                                s.call_on_name("main", move |v: &mut Layout| {
+                                    v.pop_view();
+                                    if let Some(view) = reopened {
+                                        v.pop_view();
+                                        v.push_view(view);
+                                    }
+                                });
+                                if failed > 0 {
+                                    s.add_layer(Dialog::info(format!(
+                                        "{failed} tracks could not be replaced."
+                                    )));
+                                }
+                            }))
+                            .ok();
+                    },
+                );
+            });
+        Ok(Modal::new(dialog))
+    }
+}
+
+impl ViewWrapper for UnavailableView {
+    wrap_impl!(self.list: ListView<Playable>);
+}
+
+impl ViewExt for UnavailableView {
+    fn title(&self) -> String {
+        format!("Unavailable: {}", self.name)
+    }
+
+    fn title_sub(&self) -> String {
+        if self.scanning.load(Ordering::SeqCst) {
+            return "scanning...".to_string();
+        }
+        let entries = self.entries.read().unwrap();
+        let replaceable = entries
+            .iter()
+            .filter(|entry| entry.replacement.is_some())
+            .count();
+        format!(
+            "{} unavailable, {} replacements found",
+            entries.len(),
+            replaceable
+        )
+    }
+
+    fn help_contexts(&self) -> Vec<HelpContext> {
+        self.list.help_contexts()
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.list.position()
+    }
+
+    fn set_position(&mut self, position: usize) {
+        self.list.set_position(position);
+    }
+
+    fn on_command(&mut self, s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            // The entries are in the order of the tracks.
+            Command::Sort(_, _) => Ok(CommandResult::Ignored),
+            Command::Delete => {
+                let pos = self.list.get_selected_index();
+                let mut entries = self.entries.write().unwrap();
+                if pos < entries.len() {
+                    entries.remove(pos);
+                    self.list.remove(pos);
+                }
+                Ok(CommandResult::Consumed(None))
+            }
+            Command::Unavailable => {
+                if self.scanning.load(Ordering::SeqCst) {
+                    return Err("The playlists are still being scanned.".to_string());
+                }
+                s.add_layer(self.replace_dialog()?);
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => self.list.on_command(s, cmd),
+        }
+    }
+}
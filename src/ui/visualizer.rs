@@ -0,0 +1,158 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use cursive::theme::{ColorStyle, ColorType};
+use cursive::{CbSink, Cursive, Printer, Vec2, View};
+
+use crate::command::Command;
+use crate::commands::CommandResult;
+use crate::traits::ViewExt;
+use crate::visualizer::{self, AudioTap, VisualizerStyle};
+
+/// The time between two frames of the visualizer.
+const FRAME: Duration = Duration::from_millis(40);
+
+/// How much of its height a bar falls per frame, so it doesn't flicker.
+const FALL: f32 = 0.04;
+
+/// The characters of bars that fill an eighth to all of a cell.
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The spectrum or the waveform of the audio output. The samples are only kept while the view is
+/// open, and it redraws itself on every frame until it is closed.
+pub struct VisualizerView {
+    tap: AudioTap,
+    style: VisualizerStyle,
+    /// The levels of the bars of the last frame.
+    levels: Mutex<Vec<f32>>,
+    /// Keeps the thread that redraws the view running.
+    running: Arc<AtomicBool>,
+}
+
+impl VisualizerView {
+    pub fn new(tap: AudioTap, style: VisualizerStyle, cb_sink: CbSink) -> Self {
+        tap.set_enabled(true);
+        let running = Arc::new(AtomicBool::new(true));
+        let redraw = running.clone();
+        thread::spawn(move || {
+            while redraw.load(Ordering::Relaxed) {
+                if cb_sink.send(Box::new(Cursive::noop)).is_err() {
+                    break;
+                }
+                thread::sleep(FRAME);
+            }
+        });
+        Self {
+            tap,
+            style,
+            levels: Mutex::new(Vec::new()),
+            running,
+        }
+    }
+
+    fn draw_bars(&self, printer: &Printer<'_, '_>) {
+        // Bars are one cell wide with a gap between them.
+        let count = printer.size.x.div_ceil(2);
+        let spectrum = visualizer::spectrum(&self.tap.samples());
+        let bands = visualizer::bands(&spectrum, count);
+        let mut levels = self.levels.lock().unwrap();
+        levels.resize(count, 0.0);
+        for (level, band) in levels.iter_mut().zip(bands) {
+            *level = band.max(*level - FALL);
+        }
+
+        for (index, level) in levels.iter().enumerate() {
+            let eighths = (level * (printer.size.y * 8) as f32) as usize;
+            for y in 0..printer.size.y {
+                let filled = eighths.saturating_sub((printer.size.y - 1 - y) * 8).min(8);
+                if filled > 0 {
+                    printer.print((index * 2, y), &BLOCKS[filled - 1].to_string());
+                }
+            }
+        }
+    }
+
+    fn draw_wave(&self, printer: &Printer<'_, '_>) {
+        // Every cell holds a braille character of 2 by 4 dots.
+        let (width, height) = (printer.size.x * 2, printer.size.y * 4);
+        if width == 0 || height == 0 {
+            return;
+        }
+        let samples = self.tap.samples();
+        // Quiet passages are scaled up to stay visible.
+        let peak = samples.iter().fold(0.1f32, |peak, s| peak.max(s.abs()));
+        let row = |x: usize| {
+            let sample = samples[x * samples.len() / width] / peak;
+            (((1.0 - sample) / 2.0) * (height - 1) as f32).round() as usize
+        };
+
+        let mut cells = vec![0u8; printer.size.x * printer.size.y];
+        let mut previous = row(0);
+        for x in 0..width {
+            let current = row(x);
+            // Consecutive dots are connected, so steep parts don't fall apart.
+            for y in previous.min(current)..=previous.max(current) {
+                let bit = match (x % 2, y % 4) {
+                    (0, 3) => 0x40,
+                    (1, 3) => 0x80,
+                    (0, dy) => 1 << dy,
+                    (_, dy) => 1 << (dy + 3),
+                };
+                cells[y / 4 * printer.size.x + x / 2] |= bit;
+            }
+            previous = current;
+        }
+
+        for (index, bits) in cells.into_iter().enumerate() {
+            if bits != 0 {
+                let c = char::from_u32(0x2800 + bits as u32).unwrap_or(' ');
+                let position = (index % printer.size.x, index / printer.size.x);
+                printer.print(position, &c.to_string());
+            }
+        }
+    }
+}
+
+impl Drop for VisualizerView {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        self.tap.set_enabled(false);
+    }
+}
+
+impl View for VisualizerView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let style = ColorStyle::new(
+            ColorType::Color(*printer.theme.palette.custom("playing").unwrap()),
+            ColorType::InheritParent,
+        );
+        printer.with_color(style, |printer| match self.style {
+            VisualizerStyle::Bars => self.draw_bars(printer),
+            VisualizerStyle::Wave => self.draw_wave(printer),
+        });
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
+
+impl ViewExt for VisualizerView {
+    fn title(&self) -> String {
+        "Visualizer".to_string()
+    }
+
+    fn on_command(&mut self, _s: &mut Cursive, cmd: &Command) -> Result<CommandResult, String> {
+        match cmd {
+            Command::ShowVisualizer(style) => {
+                if let Some(style) = style {
+                    self.style = *style;
+                }
+                Ok(CommandResult::Consumed(None))
+            }
+            _ => Ok(CommandResult::Ignored),
+        }
+    }
+}
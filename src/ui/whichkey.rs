@@ -0,0 +1,168 @@
+//! The popup that is shown while a keybinding of several keys is typed, listing the keys that can
+//! follow.
+
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cursive::Cursive;
+use cursive::event::{Event, EventResult, Key};
+use cursive::theme::Effect;
+use cursive::traits::Nameable;
+use cursive::utils::markup::StyledString;
+use cursive::view::{Offset, Position, ViewWrapper};
+use cursive::views::{Panel, TextView};
+
+use crate::application::UserData;
+use crate::command::Command;
+
+const NAME: &str = "which_key";
+
+/// A keybinding of several keys.
+#[derive(Clone)]
+pub struct KeySequence {
+    pub keys: Vec<Event>,
+    /// The keys as they are written in the configuration.
+    pub names: Vec<String>,
+    pub commands: Vec<Command>,
+}
+
+pub struct WhichKeyView {
+    view: Panel<TextView>,
+    /// The sequences that start with the keys pressed so far.
+    sequences: Vec<KeySequence>,
+    pressed: usize,
+    /// How long to wait for the next key, or `None` to wait until another key is pressed.
+    timeout: Option<Duration>,
+    last_key: Instant,
+}
+
+impl WhichKeyView {
+    /// Show the completions of `sequences`, whose first key was just pressed.
+    pub fn start(s: &mut Cursive, sequences: Vec<KeySequence>, timeout: Option<Duration>) {
+        close(s);
+        let mut view = Self {
+            view: Panel::new(TextView::empty()),
+            sequences,
+            pressed: 1,
+            timeout,
+            last_key: Instant::now(),
+        };
+        view.update();
+        // An absolute offset is clamped to the screen, which places the popup at the bottom.
+        s.screen_mut().add_layer_at(
+            Position::new(Offset::Center, Offset::Absolute(usize::MAX)),
+            view.with_name(NAME),
+        );
+        schedule_timeout(s, timeout);
+    }
+
+    fn update(&mut self) {
+        let pressed = self.sequences[0].names[..self.pressed].join(" ");
+        self.view.set_title(pressed);
+
+        // The next keys and what they do, or how many bindings they lead to.
+        let mut next = BTreeMap::<&str, (usize, Option<&[Command]>)>::new();
+        for sequence in &self.sequences {
+            let entry = next
+                .entry(&sequence.names[self.pressed])
+                .or_insert((0, None));
+            entry.0 += 1;
+            if sequence.keys.len() == self.pressed + 1 {
+                entry.1 = Some(&sequence.commands);
+            }
+        }
+
+        let mut text = StyledString::new();
+        for (key, (count, commands)) in next {
+            text.append_styled(format!("{key:<10}"), Effect::Bold);
+            match commands {
+                Some(commands) => text.append(
+                    commands
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ),
+                None => text.append(format!("+{count} more")),
+            }
+            text.append("\n");
+        }
+        self.view.get_inner_mut().set_content(text);
+    }
+
+    fn press(&mut self, event: Event) -> EventResult {
+        self.sequences
+            .retain(|sequence| sequence.keys.get(self.pressed) == Some(&event));
+        self.pressed += 1;
+
+        if self.sequences.is_empty() {
+            return EventResult::with_cb(close);
+        }
+        if let Some(done) = self.sequences.iter().find(|s| s.keys.len() == self.pressed) {
+            let commands = done.commands.clone();
+            return EventResult::with_cb(move |s| {
+                close(s);
+                if let Some(data) = s.user_data::<UserData>().cloned() {
+                    for command in commands.clone() {
+                        data.cmd.handle(s, command);
+                    }
+                }
+            });
+        }
+
+        self.last_key = Instant::now();
+        self.update();
+        let timeout = self.timeout;
+        EventResult::with_cb(move |s| schedule_timeout(s, timeout))
+    }
+}
+
+impl ViewWrapper for WhichKeyView {
+    wrap_impl!(self.view: Panel<TextView>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Esc) => EventResult::with_cb(close),
+            Event::Char(_)
+            | Event::CtrlChar(_)
+            | Event::AltChar(_)
+            | Event::Key(_)
+            | Event::Shift(_)
+            | Event::Alt(_)
+            | Event::AltShift(_)
+            | Event::Ctrl(_)
+            | Event::CtrlShift(_)
+            | Event::CtrlAlt(_) => self.press(event),
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+/// Close the popup after `timeout` unless another key was pressed in the meantime.
+fn schedule_timeout(s: &mut Cursive, timeout: Option<Duration>) {
+    let Some(timeout) = timeout else {
+        return;
+    };
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        sink.send(Box::new(move |s: &mut Cursive| {
+            let expired = s
+                .call_on_name(NAME, |view: &mut WhichKeyView| {
+                    view.last_key.elapsed() >= timeout
+                })
+                .unwrap_or(false);
+            if expired {
+                close(s);
+            }
+        }))
+        .ok();
+    });
+}
+
+fn close(s: &mut Cursive) {
+    if let Some(position) = s.screen_mut().find_layer_from_name(NAME) {
+        s.screen_mut().remove_layer(position);
+    }
+}
@@ -0,0 +1,251 @@
+//! Undoing and redoing destructive actions: deleting playlists, removing tracks from playlists,
+//! removing tracks and albums from the library and clearing the queue. The changes are recorded
+//! where they are made, with everything needed to reverse them.
+
+use std::mem;
+use std::sync::Mutex;
+
+use crate::library::Library;
+use crate::model::album::Album;
+use crate::model::playable::Playable;
+use crate::model::playlist::Playlist;
+use crate::model::track::Track;
+use crate::queue::Queue;
+
+/// The amount of changes that can be undone.
+const MAX_CHANGES: usize = 50;
+
+#[derive(Clone)]
+pub enum Change {
+    DeletePlaylist(Box<Playlist>),
+    /// Tracks removed from a playlist, with the positions they had.
+    DeleteTracks {
+        playlist_id: String,
+        playlist_name: String,
+        tracks: Vec<(usize, Playable)>,
+    },
+    UnsaveTracks(Vec<Track>),
+    UnsaveAlbum(Box<Album>),
+    ClearQueue(Vec<Playable>),
+}
+
+impl Change {
+    fn describe(&self) -> String {
+        let tracks = |n: usize| match n {
+            1 => "1 track".to_string(),
+            n => format!("{n} tracks"),
+        };
+        match self {
+            Self::DeletePlaylist(playlist) => format!("deleting playlist \"{}\"", playlist.name),
+            Self::DeleteTracks {
+                playlist_name,
+                tracks: removed,
+                ..
+            } => format!(
+                "removing {} from \"{playlist_name}\"",
+                tracks(removed.len())
+            ),
+            Self::UnsaveTracks(removed) => {
+                format!("removing {} from your library", tracks(removed.len()))
+            }
+            Self::UnsaveAlbum(album) => format!("removing \"{}\" from your library", album.title),
+            Self::ClearQueue(_) => "clearing the queue".to_string(),
+        }
+    }
+
+    /// Reverse the change.
+    fn revert(&self, library: &Library, queue: &Queue) -> Result<(), String> {
+        match self {
+            Self::DeletePlaylist(playlist) => {
+                library.follow_playlist(*playlist.clone());
+                let restored = library
+                    .playlists
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .any(|p| p.id == playlist.id);
+                restored
+                    .then_some(())
+                    .ok_or_else(|| "Could not restore the playlist".to_string())
+            }
+            Self::DeleteTracks {
+                playlist_id,
+                tracks,
+                ..
+            } => restore_tracks(library, queue, playlist_id, tracks),
+            Self::UnsaveTracks(tracks) => {
+                let tracks: Vec<&Track> = tracks.iter().collect();
+                library
+                    .save_tracks(&tracks)
+                    .then_some(())
+                    .ok_or_else(|| "Could not save the tracks again".to_string())
+            }
+            Self::UnsaveAlbum(album) => {
+                library.save_album(album);
+                Ok(())
+            }
+            Self::ClearQueue(tracks) => {
+                for track in tracks {
+                    queue.append(track.clone());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Make the change again. It is recorded again by the code making it.
+    fn apply(&self, library: &Library, queue: &Queue) -> Result<(), String> {
+        match self {
+            Self::DeletePlaylist(playlist) => library.delete_playlist(&playlist.id),
+            Self::DeleteTracks {
+                playlist_id,
+                tracks,
+                ..
+            } => {
+                let mut playlist = library
+                    .playlists
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .find(|p| &p.id == playlist_id)
+                    .cloned()
+                    .ok_or("The playlist is no longer in your library")?;
+                playlist.load_tracks(&queue.get_spotify());
+                let playables: Vec<Playable> = tracks
+                    .iter()
+                    .map(|(position, playable)| {
+                        let mut playable = playable.clone();
+                        playable.set_list_index(*position);
+                        playable
+                    })
+                    .collect();
+                if !playlist.delete_tracks(&playables, &queue.get_spotify(), library) {
+                    return Err("Could not remove the tracks again".to_string());
+                }
+            }
+            Self::UnsaveTracks(tracks) => {
+                let tracks: Vec<&Track> = tracks.iter().collect();
+                library.unsave_tracks(&tracks);
+            }
+            Self::UnsaveAlbum(album) => library.unsave_album(album),
+            Self::ClearQueue(_) => clear_queue(queue),
+        }
+        Ok(())
+    }
+}
+
+struct History {
+    done: Vec<Change>,
+    undone: Vec<Change>,
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    done: Vec::new(),
+    undone: Vec::new(),
+});
+
+/// Remember `change` so it can be undone. Changes undone before can't be redone anymore.
+pub fn record(change: Change) {
+    let mut history = HISTORY.lock().unwrap();
+    history.done.push(change);
+    if history.done.len() > MAX_CHANGES {
+        history.done.remove(0);
+    }
+    history.undone.clear();
+}
+
+/// Undo the last change. Returns a message for the status bar.
+pub fn undo(library: &Library, queue: &Queue) -> Result<String, String> {
+    let change = HISTORY
+        .lock()
+        .unwrap()
+        .done
+        .pop()
+        .ok_or("Nothing to undo")?;
+    if let Err(e) = change.revert(library, queue) {
+        HISTORY.lock().unwrap().done.push(change);
+        return Err(e);
+    }
+    let message = format!("Undid {}", change.describe());
+    HISTORY.lock().unwrap().undone.push(change);
+    Ok(message)
+}
+
+/// Make the last undone change again. Returns a message for the status bar.
+pub fn redo(library: &Library, queue: &Queue) -> Result<String, String> {
+    let change = HISTORY
+        .lock()
+        .unwrap()
+        .undone
+        .pop()
+        .ok_or("Nothing to redo")?;
+    // Making the change records it again, which would forget the other undone changes.
+    let undone = mem::take(&mut HISTORY.lock().unwrap().undone);
+    let result = change.apply(library, queue);
+    let mut history = HISTORY.lock().unwrap();
+    history.undone = undone;
+    match result {
+        Ok(()) => Ok(format!("Redid {}", change.describe())),
+        Err(e) => {
+            history.undone.push(change);
+            Err(e)
+        }
+    }
+}
+
+/// Clear the queue, so that it can be restored with [undo].
+pub fn clear_queue(queue: &Queue) {
+    let tracks = queue.queue.read().unwrap().clone();
+    queue.clear();
+    if !tracks.is_empty() {
+        record(Change::ClearQueue(tracks));
+    }
+}
+
+/// Insert `tracks` into the playlist with `playlist_id` again, at the positions they had.
+fn restore_tracks(
+    library: &Library,
+    queue: &Queue,
+    playlist_id: &str,
+    tracks: &[(usize, Playable)],
+) -> Result<(), String> {
+    let spotify = queue.get_spotify();
+    let mut playlist = library
+        .playlists
+        .read()
+        .unwrap()
+        .iter()
+        .find(|p| p.id == playlist_id)
+        .cloned()
+        .ok_or("The playlist is no longer in your library")?;
+
+    let mut tracks = tracks.to_vec();
+    // Insert from the start, so every position is the one the track had before.
+    tracks.sort_by_key(|(position, _)| *position);
+    for (position, playable) in tracks {
+        let result = spotify
+            .api
+            .append_tracks(
+                &playlist.id,
+                std::slice::from_ref(&playable),
+                Some(position as u32),
+            )
+            .map_err(|_| "Could not restore the tracks")?;
+        playlist.snapshot_id = result.snapshot_id;
+
+        if let Some(list) = &mut playlist.tracks {
+            for other in list.iter_mut() {
+                if other.list_index() >= position {
+                    other.set_list_index(other.list_index() + 1);
+                }
+            }
+            let at = list.iter().filter(|p| p.list_index() < position).count();
+            let mut playable = playable;
+            playable.set_list_index(position);
+            list.insert(at, playable);
+        }
+        playlist.num_tracks += 1;
+    }
+    library.playlist_update(&playlist);
+    Ok(())
+}
@@ -24,6 +24,23 @@ pub fn format_duration(d: &std::time::Duration) -> String {
     s.trim_end().to_string()
 }
 
+/// Returns a human readable String of an amount of bytes
+///
+/// Example: `12.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
 /// Returns a human readable String of milliseconds in the HH:MM:SS format.
 pub fn ms_to_hms(duration: u32) -> String {
     let mut formated_time = String::new();
@@ -43,6 +60,33 @@ pub fn ms_to_hms(duration: u32) -> String {
     formated_time
 }
 
+/// Returns a popularity from 0 to 100 as a bar of five cells.
+///
+/// Example: `███░░` for 60
+pub fn popularity_bar(popularity: u32) -> String {
+    const WIDTH: usize = 5;
+    let filled = (popularity.min(100) as usize * WIDTH + 50) / 100;
+    format!("{}{}", "█".repeat(filled), "░".repeat(WIDTH - filled))
+}
+
+/// Returns the total size of the files in the directory `path` and its subdirectories, in bytes.
+pub fn dir_size(path: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
 pub fn cache_path_for_url(url: String) -> std::path::PathBuf {
     let mut path = crate::config::cache_path("covers");
     path.push(url.split('/').next_back().unwrap());
@@ -50,7 +94,10 @@ pub fn cache_path_for_url(url: String) -> std::path::PathBuf {
 }
 
 pub fn download(url: String, path: std::path::PathBuf) -> Result<(), std::io::Error> {
-    let mut resp = reqwest::blocking::get(url).map_err(std::io::Error::other)?;
+    let mut resp = crate::proxy::client_builder()
+        .build()
+        .and_then(|client| client.get(url).send())
+        .map_err(std::io::Error::other)?;
 
     std::fs::create_dir_all(path.parent().unwrap())?;
     let mut file = std::fs::File::create(path)?;
@@ -0,0 +1,218 @@
+//! The audio visualizer. The audio passes through a sink that keeps the latest samples while the
+//! visualizer is shown, and the view turns them into a spectrum or a waveform on every frame.
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use librespot_playback::audio_backend::{Sink, SinkResult};
+use librespot_playback::convert::Converter;
+use librespot_playback::decoder::AudioPacket;
+use librespot_playback::{NUM_CHANNELS, SAMPLE_RATE};
+use strum_macros::Display;
+
+/// The number of samples the spectrum is computed from, a power of two for the FFT.
+pub const WINDOW: usize = 2048;
+
+/// The range of frequencies shown in the spectrum, in Hz.
+const MIN_FREQUENCY: f32 = 40.0;
+const MAX_FREQUENCY: f32 = 16000.0;
+
+/// The level shown as an empty bar, in dB relative to a full scale sine.
+const FLOOR_DB: f32 = -70.0;
+
+#[derive(Display, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum VisualizerStyle {
+    /// The spectrum as vertical bars.
+    #[default]
+    Bars,
+    /// The waveform drawn with braille characters.
+    Wave,
+}
+
+impl VisualizerStyle {
+    pub const ALL: [Self; 2] = [Self::Bars, Self::Wave];
+}
+
+/// The latest samples of the audio output, shared between the player and the visualizer.
+#[derive(Clone)]
+pub struct AudioTap {
+    /// Samples are only kept while the visualizer is shown, to not cost anything otherwise.
+    enabled: Arc<AtomicBool>,
+    /// The latest samples, mixed down to mono.
+    samples: Arc<Mutex<VecDeque<f32>>>,
+}
+
+impl Default for AudioTap {
+    fn default() -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            samples: Arc::new(Mutex::new(VecDeque::with_capacity(WINDOW))),
+        }
+    }
+}
+
+impl AudioTap {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.samples.lock().unwrap().clear();
+        }
+    }
+
+    /// The latest [WINDOW] samples, padded with silence at the start.
+    pub fn samples(&self) -> Vec<f32> {
+        let samples = self.samples.lock().unwrap();
+        let mut latest = vec![0.0; WINDOW - samples.len()];
+        latest.extend(samples.iter());
+        latest
+    }
+
+    /// Wrap `sink` to keep the samples passing through it.
+    pub fn sink(&self, sink: Box<dyn Sink>) -> Box<dyn Sink> {
+        Box::new(TapSink {
+            sink,
+            tap: self.clone(),
+        })
+    }
+
+    fn push(&self, interleaved: &[f64]) {
+        let mut samples = self.samples.lock().unwrap();
+        for frame in interleaved.chunks(NUM_CHANNELS as usize) {
+            let mono = frame.iter().sum::<f64>() / frame.len() as f64;
+            if samples.len() == WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back(mono as f32);
+        }
+    }
+}
+
+struct TapSink {
+    sink: Box<dyn Sink>,
+    tap: AudioTap,
+}
+
+impl Sink for TapSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.sink.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.sink.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        if self.tap.enabled.load(Ordering::Relaxed)
+            && let AudioPacket::Samples(samples) = &packet
+        {
+            self.tap.push(samples);
+        }
+        self.sink.write(packet, converter)
+    }
+}
+
+/// The magnitudes of the frequencies in `samples`, whose length must be a power of two. The
+/// samples are weighted with a Hann window first, which keeps the peaks narrow. Bin `i` is the
+/// frequency `i * SAMPLE_RATE / samples.len()`.
+pub fn spectrum(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    debug_assert!(n.is_power_of_two());
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; n];
+
+    // An iterative radix-2 FFT, starting with the bit-reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+
+    // A full scale sine ends up with a magnitude of 1, the window halves the amplitude.
+    let scale = 4.0 / n as f32;
+    (0..n / 2)
+        .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt() * scale)
+        .collect()
+}
+
+/// Group `spectrum` into `count` bands spaced logarithmically like the pitch of the notes, with
+/// their level between 0 and 1.
+pub fn bands(spectrum: &[f32], count: usize) -> Vec<f32> {
+    let bin_width = SAMPLE_RATE as f32 / (spectrum.len() * 2) as f32;
+    let ratio = MAX_FREQUENCY / MIN_FREQUENCY;
+    (0..count)
+        .map(|band| {
+            let frequency = |band: usize| MIN_FREQUENCY * ratio.powf(band as f32 / count as f32);
+            let start = (frequency(band) / bin_width) as usize;
+            let end = ((frequency(band + 1) / bin_width) as usize).max(start + 1);
+            let peak = spectrum
+                .get(start..end.min(spectrum.len()))
+                .unwrap_or_default()
+                .iter()
+                .fold(0.0f32, |peak, magnitude| peak.max(*magnitude));
+            let db = 20.0 * peak.max(1e-9).log10();
+            ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spectrum() {
+        // A full scale sine in the middle of bin 100.
+        let frequency = 100.0 * SAMPLE_RATE as f32 / WINDOW as f32;
+        let samples: Vec<f32> = (0..WINDOW)
+            .map(|i| (2.0 * PI * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect();
+        let spectrum = spectrum(&samples);
+        let peak = (0..spectrum.len())
+            .max_by(|a, b| spectrum[*a].total_cmp(&spectrum[*b]))
+            .unwrap();
+        assert_eq!(peak, 100);
+        assert!((spectrum[100] - 1.0).abs() < 0.01);
+        assert!(spectrum[300] < 0.001);
+
+        let bands = bands(&spectrum, 10);
+        assert_eq!(bands.len(), 10);
+        // About 2153 Hz, in the seventh of ten bands between 40 Hz and 16 kHz.
+        let loudest = (0..bands.len()).max_by(|a, b| bands[*a].total_cmp(&bands[*b]));
+        assert_eq!(loudest, Some(6));
+        assert!(bands[6] > 0.99);
+        assert_eq!(bands[0], 0.0);
+    }
+}